@@ -69,8 +69,8 @@ async fn main() -> std::io::Result<()> {
             command.exec(client).await;
         }
         Commands::Server(command) => command.exec(client).await,
-        /*Commands::Account(command) => command.exec(client).await,
-        Commands::Domain(command) => command.exec(client).await,
+        Commands::Account(command) => command.exec(client).await,
+        /*Commands::Domain(command) => command.exec(client).await,
         Commands::List(command) => command.exec(client).await,
         Commands::Group(command) => command.exec(client).await,*/
         Commands::Dkim(command) => command.exec(client).await,
@@ -226,6 +226,21 @@ impl Client {
         url: &str,
         body: Option<B>,
     ) -> Option<R> {
+        self.http_request_result(method, url, body)
+            .await
+            .unwrap_or_else(|err| {
+                eprintln!("{err}");
+                std::process::exit(1);
+            })
+    }
+
+    // Same as try_http_request but errors are returned to the caller
+    pub async fn http_request_result<R: DeserializeOwned, B: Serialize>(
+        &self,
+        method: Method,
+        url: &str,
+        body: Option<B>,
+    ) -> Result<Option<R>, String> {
         let url = format!(
             "{}{}{}",
             self.url,
@@ -251,41 +266,50 @@ impl Client {
             );
 
         if let Some(body) = body {
-            request = request.body(serde_json::to_string(&body).unwrap_result("serialize body"));
+            request = request.body(
+                serde_json::to_string(&body)
+                    .map_err(|err| format!("Failed to serialize body: {err}"))?,
+            );
         }
 
-        let response = request.send().await.unwrap_result("send HTTP request");
+        let response = request
+            .send()
+            .await
+            .map_err(|err| format!("Failed to send HTTP request: {err}"))?;
 
         match response.status() {
             StatusCode::OK => (),
             StatusCode::NOT_FOUND => {
-                return None;
+                return Ok(None);
             }
             StatusCode::UNAUTHORIZED => {
-                eprintln!(
-                    "Authentication failed. Make sure the credentials are correct and that the account has administrator rights."
+                return Err(
+                    "Authentication failed. Make sure the credentials are correct and that the account has administrator rights.".to_string()
                 );
-                std::process::exit(1);
             }
             _ => {
-                eprintln!(
+                return Err(format!(
                     "Request failed: {}",
-                    response.text().await.unwrap_result("fetch text")
-                );
-                std::process::exit(1);
+                    response
+                        .text()
+                        .await
+                        .map_err(|err| format!("Failed to fetch text: {err}"))?
+                ));
             }
         }
 
-        let bytes = response.bytes().await.unwrap_result("fetch bytes");
-        match serde_json::from_slice::<Response<R>>(&bytes).unwrap_result(&format!(
-            "deserialize response {}",
-            String::from_utf8_lossy(bytes.as_ref())
-        )) {
-            Response::Data { data } => Some(data),
-            Response::Error(error) => {
-                eprintln!("Request failed: {error})");
-                std::process::exit(1);
-            }
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|err| format!("Failed to fetch bytes: {err}"))?;
+        match serde_json::from_slice::<Response<R>>(&bytes).map_err(|err| {
+            format!(
+                "Failed to deserialize response {}: {err}",
+                String::from_utf8_lossy(bytes.as_ref())
+            )
+        })? {
+            Response::Data { data } => Ok(Some(data)),
+            Response::Error(error) => Err(format!("Request failed: {error})")),
         }
     }
 }
@@ -8,12 +8,14 @@ use std::fmt::Display;
 
 use prettytable::{Attr, Cell, Row, Table};
 use pwhash::sha512_crypt;
+use rand::{Rng, distr::Alphanumeric};
 use reqwest::Method;
 use serde_json::Value;
 
 use super::{
     Principal, PrincipalField, PrincipalUpdate, PrincipalValue, Type,
     cli::{AccountCommands, Client},
+    read_file,
 };
 
 impl AccountCommands {
@@ -22,19 +24,28 @@ impl AccountCommands {
             AccountCommands::Create {
                 name,
                 password,
+                generate_password,
                 description,
                 quota,
                 is_admin,
                 addresses,
                 member_of,
             } => {
-                let principal = Principal {
-                    typ: if is_admin.unwrap_or_default() {
-                        Type::Superuser
-                    } else {
-                        Type::Individual
+                let password = match password {
+                    Some(password) => password,
+                    None if generate_password => {
+                        let password = random_password();
+                        println!("{password}");
+                        password
+                    }
+                    None => {
+                        eprintln!("No password specified.");
+                        std::process::exit(1);
                     }
-                    .into(),
+                };
+                let principal = Principal {
+                    typ: Type::Individual.into(),
+                    roles: vec![role_name(is_admin.unwrap_or_default()).to_string()],
                     quota,
                     name: name.clone().into(),
                     secrets: vec![sha512_crypt::hash(password).unwrap()],
@@ -48,6 +59,15 @@ impl AccountCommands {
                     .await;
                 eprintln!("Successfully created account {name:?} with id {account_id}.");
             }
+            AccountCommands::Import {
+                file,
+                generate_passwords,
+                dry_run,
+            } => {
+                client
+                    .import_accounts(&file, generate_passwords, dry_run)
+                    .await;
+            }
             AccountCommands::Update {
                 name,
                 new_name,
@@ -85,16 +105,8 @@ impl AccountCommands {
                 }
                 if let Some(is_admin) = is_admin {
                     changes.push(PrincipalUpdate::set(
-                        PrincipalField::Type,
-                        PrincipalValue::String(
-                            if is_admin {
-                                Type::Superuser
-                            } else {
-                                Type::Individual
-                            }
-                            .to_string()
-                            .to_ascii_lowercase(),
-                        ),
+                        PrincipalField::Roles,
+                        PrincipalValue::StringList(vec![role_name(is_admin).to_string()]),
                     ));
                 }
                 if let Some(addresses) = addresses {
@@ -229,7 +241,123 @@ impl AccountCommands {
     }
 }
 
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct AccountRecord {
+    name: String,
+    password: Option<String>,
+    description: Option<String>,
+    quota: Option<u32>,
+    emails: Option<String>,
+    member_of: Option<String>,
+    admin: Option<bool>,
+}
+
 impl Client {
+    pub async fn import_accounts(&self, file: &str, generate_passwords: bool, dry_run: bool) {
+        let contents = read_file(file);
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(contents.as_slice());
+        let mut success_count = 0;
+        let mut failed_count = 0;
+
+        for (row_num, record) in reader.deserialize::<AccountRecord>().enumerate() {
+            // Row numbers include the header
+            let row_num = row_num + 2;
+            let record = match record {
+                Ok(record) if !record.name.is_empty() => record,
+                Ok(_) => {
+                    eprintln!("Row {row_num}: Missing account name.");
+                    failed_count += 1;
+                    continue;
+                }
+                Err(err) => {
+                    eprintln!("Row {row_num}: {err}");
+                    failed_count += 1;
+                    continue;
+                }
+            };
+            let name = record.name.clone();
+            let (password, is_generated) = match record.password.clone().filter(|p| !p.is_empty()) {
+                Some(password) => (password, false),
+                None if generate_passwords => (random_password(), true),
+                None => {
+                    eprintln!("Row {row_num} ({name}): Missing password.");
+                    failed_count += 1;
+                    continue;
+                }
+            };
+
+            if dry_run {
+                match self
+                    .http_request_result::<Principal, String>(
+                        Method::GET,
+                        &format!("/api/principal/{name}"),
+                        None,
+                    )
+                    .await
+                {
+                    Ok(None) => {
+                        success_count += 1;
+                    }
+                    Ok(Some(_)) => {
+                        eprintln!("Row {row_num} ({name}): Account already exists.");
+                        failed_count += 1;
+                    }
+                    Err(err) => {
+                        eprintln!("Row {row_num} ({name}): {err}");
+                        failed_count += 1;
+                    }
+                }
+                continue;
+            }
+
+            let principal = Principal {
+                typ: Type::Individual.into(),
+                roles: vec![role_name(record.admin.unwrap_or_default()).to_string()],
+                quota: record.quota,
+                name: name.clone().into(),
+                secrets: vec![sha512_crypt::hash(&password).unwrap()],
+                emails: split_list(record.emails.as_deref()),
+                member_of: split_list(record.member_of.as_deref()),
+                description: record.description.filter(|d| !d.is_empty()),
+                ..Default::default()
+            };
+            match self
+                .http_request_result::<u32, _>(Method::POST, "/api/principal", Some(principal))
+                .await
+            {
+                Ok(Some(_)) => {
+                    if is_generated {
+                        println!("{name},{password}");
+                    }
+                    success_count += 1;
+                }
+                Ok(None) => {
+                    eprintln!("Row {row_num} ({name}): No data returned.");
+                    failed_count += 1;
+                }
+                Err(err) => {
+                    eprintln!("Row {row_num} ({name}): {err}");
+                    failed_count += 1;
+                }
+            }
+        }
+
+        if dry_run {
+            eprintln!(
+                "\nValidated {} row(s): {success_count} valid, {failed_count} with errors.",
+                success_count + failed_count
+            );
+        } else {
+            eprintln!("\nCreated {success_count} account(s), {failed_count} failed.");
+        }
+        if failed_count > 0 {
+            std::process::exit(1);
+        }
+    }
+
     pub async fn display_principal(&self, name: &str) {
         let principal = self
             .http_request::<Principal, String>(Method::GET, &format!("/api/principal/{name}"), None)
@@ -346,6 +474,30 @@ impl Client {
     }
 }
 
+fn random_password() -> String {
+    rand::rng()
+        .sample_iter(Alphanumeric)
+        .take(16)
+        .map(char::from)
+        .collect()
+}
+
+// Administrator rights are granted through roles, the superuser type is legacy
+fn role_name(is_admin: bool) -> &'static str {
+    if is_admin { "admin" } else { "user" }
+}
+
+// Multiple values are separated by semicolons
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .unwrap_or_default()
+        .split(';')
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+        .collect()
+}
+
 #[derive(Debug, serde::Deserialize)]
 struct ListResponse {
     pub total: usize,
@@ -33,9 +33,9 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     /// Manage user accounts
-    /* #[clap(subcommand)]
-        Account(AccountCommands),
-
+    #[clap(subcommand)]
+    Account(AccountCommands),
+    /*
         /// Manage domains
         #[clap(subcommand)]
         Domain(DomainCommands),
@@ -48,7 +48,6 @@ pub enum Commands {
         #[clap(subcommand)]
         Group(GroupCommands),
     */
-
     /// Manage DKIM signatures
     #[clap(subcommand)]
     Dkim(DkimCommands),
@@ -87,7 +86,11 @@ pub enum AccountCommands {
         /// Login Name
         name: String,
         /// Password
-        password: String,
+        #[clap(required_unless_present = "generate_password")]
+        password: Option<String>,
+        /// Generate a random password and print it
+        #[clap(short, long)]
+        generate_password: bool,
         /// Account description
         #[clap(short, long)]
         description: Option<String>,
@@ -174,6 +177,19 @@ pub enum AccountCommands {
         name: String,
     },
 
+    /// Create user accounts in batch from a CSV file
+    Import {
+        /// Path to the CSV file, use '-' to read from stdin. The first row is a header
+        /// with the columns name, password, description, quota, emails, member_of and admin
+        file: String,
+        /// Generate random passwords for rows without a password and print them
+        #[clap(short, long)]
+        generate_passwords: bool,
+        /// Validate the file without creating any accounts
+        #[clap(long)]
+        dry_run: bool,
+    },
+
     /// Display an existing user account
     Display {
         /// Account name to display
@@ -457,7 +473,7 @@ pub enum ServerCommands {
     /// Perform Healthcheck
     Healthcheck {
         /// Status `ready` (default) or `live` to check for
-        check: Option<String>
+        check: Option<String>,
     },
 }
 
@@ -474,6 +490,7 @@ pub enum MailboxFormat {
 #[derive(Subcommand)]
 pub enum QueueCommands {
     /// Shows messages queued for delivery
+    #[clap(visible_alias = "ls")]
     List {
         /// Filter by sender address
         #[clap(short, long)]
@@ -481,6 +498,17 @@ pub enum QueueCommands {
         /// Filter by recipient
         #[clap(short, long)]
         rcpt: Option<String>,
+        /// Filter by recipient domain
+        #[clap(short, long)]
+        domain: Option<String>,
+        /// Filter by recipient delivery status
+        #[clap(long)]
+        #[clap(value_enum)]
+        status: Option<QueueStatus>,
+        /// Filter messages queued longer than a certain time (e.g. 30m, 12h, 2d)
+        #[clap(long)]
+        #[arg(value_parser = parse_age)]
+        older_than: Option<u64>,
         /// Filter messages due for delivery before a certain datetime
         #[clap(short, long)]
         #[arg(value_parser = parse_datetime)]
@@ -492,12 +520,43 @@ pub enum QueueCommands {
         /// Number of items to show per page
         #[clap(short, long)]
         page_size: Option<usize>,
+        /// Print results as JSON
+        #[clap(short, long)]
+        json: bool,
     },
 
     /// Displays details about a queued message
+    #[clap(visible_alias = "show")]
     Status {
         #[clap(required = true)]
         ids: Vec<String>,
+        /// Print results as JSON
+        #[clap(short, long)]
+        json: bool,
+    },
+
+    /// Hold delivery until a certain datetime
+    Hold {
+        /// Apply to messages matching a sender address
+        #[clap(short, long)]
+        sender: Option<String>,
+        /// Apply to a specific domain
+        #[clap(short, long)]
+        domain: Option<String>,
+        /// Apply to messages due before a certain datetime
+        #[clap(short, long)]
+        #[arg(value_parser = parse_datetime)]
+        before: Option<DateTime>,
+        /// Apply to messages due after a certain datetime
+        #[clap(short, long)]
+        #[arg(value_parser = parse_datetime)]
+        after: Option<DateTime>,
+        /// Resume delivery at a specific time
+        #[clap(short, long, required = true)]
+        #[arg(value_parser = parse_datetime)]
+        until: DateTime,
+        // Hold one or multiple message ids
+        ids: Vec<String>,
     },
 
     /// Reschedule delivery
@@ -584,6 +643,33 @@ pub enum ReportFormat {
     Tls,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum QueueStatus {
+    /// Scheduled for delivery
+    Scheduled,
+    /// Delivered
+    Delivered,
+    /// Temporary failure
+    Tempfail,
+    /// Permanent failure
+    Permfail,
+}
+
+fn parse_age(arg: &str) -> Result<u64, &'static str> {
+    let arg = arg.trim();
+    let (value, multiplier) = match arg.char_indices().last() {
+        Some((pos, 's')) => (&arg[..pos], 1),
+        Some((pos, 'm')) => (&arg[..pos], 60),
+        Some((pos, 'h')) => (&arg[..pos], 3600),
+        Some((pos, 'd')) => (&arg[..pos], 86400),
+        _ => (arg, 1),
+    };
+    value
+        .parse::<u64>()
+        .map(|value| value * multiplier)
+        .map_err(|_| "Failed to parse age, expected a value such as 30m, 12h or 2d")
+}
+
 fn parse_datetime(arg: &str) -> Result<DateTime, &'static str> {
     if arg.contains('T') {
         DateTime::parse_rfc3339(arg).ok_or("Failed to parse RFC3339 datetime")
@@ -58,6 +58,9 @@ pub struct Principal {
     #[serde(rename = "members")]
     pub members: Vec<String>,
 
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roles: Vec<String>,
+
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
 }
@@ -99,6 +102,8 @@ pub enum PrincipalField {
     MemberOf,
     #[serde(rename = "members")]
     Members,
+    #[serde(rename = "roles")]
+    Roles,
 }
 
 #[derive(Clone, serde::Serialize, serde::Deserialize, Default)]
@@ -6,7 +6,7 @@
 
 use super::{
     List,
-    cli::{Client, QueueCommands},
+    cli::{Client, QueueCommands, QueueStatus},
 };
 use console::Term;
 use human_size::{Byte, SpecificSize};
@@ -14,6 +14,7 @@ use mail_parser::DateTime;
 use prettytable::{Attr, Cell, Row, Table, format::Alignment};
 use reqwest::Method;
 use serde::{Deserialize, Deserializer};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Deserialize, PartialEq, Eq)]
 pub struct Message {
@@ -64,94 +65,90 @@ impl QueueCommands {
             QueueCommands::List {
                 sender,
                 rcpt,
+                domain,
+                status,
+                older_than,
                 before,
                 after,
                 page_size,
+                json,
             } => {
                 let stdout = Term::buffered_stdout();
                 let ids = client.query_messages(&sender, &rcpt, &before, &after).await;
-                let ids_len = ids.len();
                 let page_size = page_size.map(|p| std::cmp::max(p, 1)).unwrap_or(20);
-                let pages_total = (ids_len as f64 / page_size as f64).ceil() as usize;
-                for (page_num, chunk) in ids.chunks(page_size).enumerate() {
-                    // Build table
-                    let mut table = Table::new();
-                    table.add_row(Row::new(
-                        ["ID", "Delivery Due", "Sender", "Recipients", "Size"]
-                            .iter()
-                            .map(|p| Cell::new(p).with_style(Attr::Bold))
-                            .collect(),
-                    ));
-                    for id in chunk {
-                        let message = client
-                            .http_request::<Message, String>(
-                                Method::GET,
-                                &format!("/api/queue/messages/{id}"),
-                                None,
-                            )
-                            .await;
-
-                        let mut rcpts = String::new();
-                        let mut deliver_at = i64::MAX;
-                        let mut deliver_pos = 0;
-
-                        for (pos, rcpt) in message.recipients.iter().enumerate() {
-                            if let Some(next_retry) = &rcpt.next_retry {
-                                let ts = next_retry.to_timestamp();
-                                if ts < deliver_at {
-                                    deliver_at = ts;
-                                    deliver_pos = pos;
-                                }
-                            }
-                            if !rcpts.is_empty() {
-                                rcpts.push('\n');
-                            }
-                            rcpts.push_str(&rcpt.address);
-                            rcpts.push_str(" (");
-                            rcpts.push_str(rcpt.status.status_short());
-                            rcpts.push(')');
-                        }
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_secs()) as i64;
+                let mut total = 0;
+                let mut results = Vec::new();
+                let mut rows = Vec::new();
+                let mut ids = ids.into_iter().peekable();
+
+                while let Some(id) = ids.next() {
+                    let value = client
+                        .http_request::<serde_json::Value, String>(
+                            Method::GET,
+                            &format!("/api/queue/messages/{id}"),
+                            None,
+                        )
+                        .await;
+                    let Ok(message) = serde_json::from_value::<Message>(value.clone()) else {
+                        continue;
+                    };
+                    if !message.matches(domain.as_deref(), status, older_than, now) {
+                        continue;
+                    }
+                    total += 1;
 
-                        let mut cells = Vec::new();
-                        cells.push(Cell::new(&format!("{id:X}")));
-                        cells.push(if deliver_at != i64::MAX {
-                            Cell::new(
-                                &message.recipients[deliver_pos]
-                                    .next_retry
-                                    .as_ref()
-                                    .unwrap()
-                                    .to_rfc822(),
-                            )
-                        } else {
-                            Cell::new("None")
-                        });
-                        cells.push(Cell::new(if !message.return_path.is_empty() {
-                            &message.return_path
-                        } else {
-                            "<>"
-                        }));
-                        cells.push(Cell::new(&rcpts));
-                        cells.push(Cell::new(
-                            &SpecificSize::new(message.size as u32, Byte)
-                                .unwrap()
-                                .to_string(),
-                        ));
-                        table.add_row(Row::new(cells));
+                    if json {
+                        results.push(value);
+                        continue;
                     }
 
-                    eprintln!();
-                    table.printstd();
-                    eprintln!();
-                    if page_num + 1 != pages_total {
-                        eprintln!("\n--- Press any key to continue or 'q' to exit ---");
-                        if let Ok('q' | 'Q') = stdout.read_char() {
-                            break;
+                    rows.push(message.table_row());
+                    if rows.len() == page_size {
+                        print_messages(std::mem::take(&mut rows));
+                        if ids.peek().is_some() {
+                            eprintln!("\n--- Press any key to continue or 'q' to exit ---");
+                            if let Ok('q' | 'Q') = stdout.read_char() {
+                                break;
+                            }
                         }
                     }
                 }
-                eprintln!("\n{ids_len} queued message(s) found.")
+
+                if json {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&results).unwrap_or_default()
+                    );
+                } else {
+                    if !rows.is_empty() {
+                        print_messages(rows);
+                    }
+                    eprintln!("\n{total} queued message(s) found.")
+                }
             }
-            QueueCommands::Status { ids } => {
+            QueueCommands::Status { ids, json: true } => {
+                let mut results = Vec::new();
+                for uid in parse_ids(&ids) {
+                    if let Some(message) = client
+                        .try_http_request::<serde_json::Value, String>(
+                            Method::GET,
+                            &format!("/api/queue/messages/{uid}"),
+                            None,
+                        )
+                        .await
+                    {
+                        results.push(message);
+                    }
+                }
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&results).unwrap_or_default()
+                );
+            }
+            QueueCommands::Status { ids, json: false } => {
                 for (uid, id) in parse_ids(&ids).into_iter().zip(ids) {
                     let message = client
                         .try_http_request::<Message, String>(
@@ -258,49 +255,9 @@ impl QueueCommands {
                 time,
                 ids,
             } => {
-                let (parsed_ids, ids) = if ids.is_empty() {
-                    if sender.is_some() || domain.is_some() || before.is_some() || after.is_some() {
-                        let parsed_ids = client
-                            .query_messages(&sender, &domain, &before, &after)
-                            .await;
-                        let ids = parsed_ids.iter().map(|id| format!("{id:X}")).collect();
-                        (parsed_ids, ids)
-                    } else {
-                        (vec![], vec![])
-                    }
-                } else {
-                    (parse_ids(&ids), ids)
-                };
-
-                if ids.is_empty() {
-                    eprintln!("No messages were found.");
-                    std::process::exit(1);
-                }
-
-                let mut success_count = 0;
-                let mut failed_list = vec![];
-
-                for id in parsed_ids {
-                    let mut query =
-                        form_urlencoded::Serializer::new(format!("/api/queue/messages/{id}"));
-
-                    if let Some(filter) = &domain {
-                        query.append_pair("filter", filter);
-                    }
-                    if let Some(at) = time {
-                        query.append_pair("at", &at.to_rfc3339());
-                    }
-
-                    if client
-                        .try_http_request::<bool, String>(Method::PATCH, &query.finish(), None)
-                        .await
-                        .unwrap_or(false)
-                    {
-                        success_count += 1;
-                    } else {
-                        failed_list.push(id.to_string());
-                    }
-                }
+                let (success_count, failed_list) = client
+                    .reschedule_messages(sender, domain, before, after, time, ids)
+                    .await;
 
                 eprint!("\nSuccessfully rescheduled {success_count} message(s).");
                 if !failed_list.is_empty() {
@@ -308,6 +265,27 @@ impl QueueCommands {
                 }
                 eprintln!();
             }
+            QueueCommands::Hold {
+                sender,
+                domain,
+                before,
+                after,
+                until,
+                ids,
+            } => {
+                let (success_count, failed_list) = client
+                    .reschedule_messages(sender, domain, before, after, Some(until), ids)
+                    .await;
+
+                eprint!(
+                    "\nHeld {success_count} message(s) until {}.",
+                    until.to_rfc822()
+                );
+                if !failed_list.is_empty() {
+                    eprint!(" Unable to hold id(s): {}.", failed_list.join(", "));
+                }
+                eprintln!();
+            }
             QueueCommands::Cancel {
                 sender,
                 rcpt,
@@ -369,6 +347,59 @@ impl QueueCommands {
 }
 
 impl Client {
+    async fn reschedule_messages(
+        &self,
+        sender: Option<String>,
+        domain: Option<String>,
+        before: Option<DateTime>,
+        after: Option<DateTime>,
+        time: Option<DateTime>,
+        ids: Vec<String>,
+    ) -> (usize, Vec<String>) {
+        let (parsed_ids, ids) = if ids.is_empty() {
+            if sender.is_some() || domain.is_some() || before.is_some() || after.is_some() {
+                let parsed_ids = self.query_messages(&sender, &domain, &before, &after).await;
+                let ids = parsed_ids.iter().map(|id| format!("{id:X}")).collect();
+                (parsed_ids, ids)
+            } else {
+                (vec![], vec![])
+            }
+        } else {
+            (parse_ids(&ids), ids)
+        };
+
+        if ids.is_empty() {
+            eprintln!("No messages were found.");
+            std::process::exit(1);
+        }
+
+        let mut success_count = 0;
+        let mut failed_list = vec![];
+
+        for id in parsed_ids {
+            let mut query = form_urlencoded::Serializer::new(format!("/api/queue/messages/{id}"));
+
+            if let Some(filter) = &domain {
+                query.append_pair("filter", filter);
+            }
+            if let Some(at) = time {
+                query.append_pair("at", &at.to_rfc3339());
+            }
+
+            if self
+                .try_http_request::<bool, String>(Method::PATCH, &query.finish(), None)
+                .await
+                .unwrap_or(false)
+            {
+                success_count += 1;
+            } else {
+                failed_list.push(id.to_string());
+            }
+        }
+
+        (success_count, failed_list)
+    }
+
     async fn query_messages(
         &self,
         from: &Option<String>,
@@ -397,6 +428,92 @@ impl Client {
     }
 }
 
+impl Message {
+    fn matches(
+        &self,
+        domain: Option<&str>,
+        status: Option<QueueStatus>,
+        older_than: Option<u64>,
+        now: i64,
+    ) -> bool {
+        domain.is_none_or(|domain| {
+            self.recipients.iter().any(|rcpt| {
+                rcpt.address
+                    .rsplit_once('@')
+                    .is_some_and(|(_, rcpt_domain)| rcpt_domain.eq_ignore_ascii_case(domain))
+            })
+        }) && status.is_none_or(|status| {
+            self.recipients.iter().any(|rcpt| {
+                matches!(
+                    (status, &rcpt.status),
+                    (QueueStatus::Scheduled, Status::Scheduled)
+                        | (QueueStatus::Delivered, Status::Completed(_))
+                        | (QueueStatus::Tempfail, Status::TemporaryFailure(_))
+                        | (QueueStatus::Permfail, Status::PermanentFailure(_))
+                )
+            })
+        }) && older_than
+            .is_none_or(|older_than| now - self.created.to_timestamp() >= older_than as i64)
+    }
+
+    fn table_row(&self) -> Row {
+        let mut rcpts = String::new();
+        let mut deliver_at: Option<&DateTime> = None;
+
+        for rcpt in &self.recipients {
+            if let Some(next_retry) = &rcpt.next_retry
+                && deliver_at.is_none_or(|dt| next_retry.to_timestamp() < dt.to_timestamp())
+            {
+                deliver_at = Some(next_retry);
+            }
+            if !rcpts.is_empty() {
+                rcpts.push('\n');
+            }
+            rcpts.push_str(&rcpt.address);
+            rcpts.push_str(" (");
+            rcpts.push_str(rcpt.status.status_short());
+            rcpts.push(')');
+        }
+
+        Row::new(vec![
+            Cell::new(&format!("{:X}", self.id)),
+            Cell::new(
+                &deliver_at
+                    .map(|dt| dt.to_rfc822())
+                    .unwrap_or_else(|| "None".to_string()),
+            ),
+            Cell::new(if !self.return_path.is_empty() {
+                &self.return_path
+            } else {
+                "<>"
+            }),
+            Cell::new(&rcpts),
+            Cell::new(
+                &SpecificSize::new(self.size as u32, Byte)
+                    .unwrap()
+                    .to_string(),
+            ),
+        ])
+    }
+}
+
+fn print_messages(rows: Vec<Row>) {
+    let mut table = Table::new();
+    table.add_row(Row::new(
+        ["ID", "Delivery Due", "Sender", "Recipients", "Size"]
+            .iter()
+            .map(|p| Cell::new(p).with_style(Attr::Bold))
+            .collect(),
+    ));
+    for row in rows {
+        table.add_row(row);
+    }
+
+    eprintln!();
+    table.printstd();
+    eprintln!();
+}
+
 fn deserialize_maybe_datetime<'de, D>(deserializer: D) -> Result<Option<DateTime>, D::Error>
 where
     D: Deserializer<'de>,
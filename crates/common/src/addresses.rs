@@ -6,13 +6,16 @@
 
 use directory::{Directory, backend::RcptType};
 use std::borrow::Cow;
+use store::dispatch::lookup::KeyValue;
+use trc::AddContext;
 use utils::config::{Config, utils::AsKey};
 
 use crate::{
-    Server,
+    KV_DISPOSABLE_ALIAS, Server,
     config::smtp::session::AddressMapping,
     expr::{
-        V_RECIPIENT, Variable, functions::ResolveVariable, if_block::IfBlock, tokenizer::TokenMap,
+        V_RECIPIENT, V_RECIPIENT_DOMAIN, Variable, functions::ResolveVariable, if_block::IfBlock,
+        tokenizer::TokenMap,
     },
 };
 
@@ -37,6 +40,8 @@ impl Server {
 
             if result.is_some() {
                 return Ok(result);
+            } else if let Some(account_id) = self.disposable_alias_to_id(address.as_ref()).await? {
+                return Ok(Some(account_id));
             } else if let Some(catch_all) = self
                 .core
                 .smtp
@@ -75,6 +80,12 @@ impl Server {
             let rcpt_type = directory.rcpt(address.as_ref()).await?;
             if rcpt_type != RcptType::Invalid {
                 return Ok(rcpt_type);
+            } else if self
+                .disposable_alias_to_id(address.as_ref())
+                .await?
+                .is_some()
+            {
+                return Ok(RcptType::Mailbox);
             } else if let Some(catch_all) = self
                 .core
                 .smtp
@@ -132,6 +143,55 @@ impl Server {
             )
             .await
     }
+
+    // Only active aliases are present in the store, disabled and expired ones are removed
+    pub async fn disposable_alias_to_id(&self, address: &str) -> trc::Result<Option<u32>> {
+        if !self.core.jmap.disposable_alias_enable || address.starts_with('@') {
+            return Ok(None);
+        }
+
+        self.in_memory_store()
+            .key_get::<u32>(KeyValue::<()>::build_key(
+                KV_DISPOSABLE_ALIAS,
+                address.to_lowercase().as_bytes(),
+            ))
+            .await
+            .caused_by(trc::location!())
+    }
+
+    // Splits an address into its base address and detail using the separators configured for its domain
+    pub async fn split_subaddress<'x>(
+        &self,
+        address: &'x str,
+        session_id: u64,
+    ) -> Option<(String, &'x str)> {
+        let (local_part, domain_part) = address.rsplit_once('@')?;
+        let separators = self
+            .eval_if::<String, _>(
+                &self.core.smtp.session.rcpt.subaddressing_separator,
+                &Recipient(address),
+                session_id,
+            )
+            .await?;
+        let (local_part, detail) = local_part.split_once(|ch| separators.contains(ch))?;
+
+        (!local_part.is_empty()).then(|| (format!("{}@{}", local_part, domain_part), detail))
+    }
+
+    pub async fn subaddress_detail<'x>(
+        &self,
+        address: &'x str,
+        session_id: u64,
+    ) -> Option<&'x str> {
+        match &self.core.smtp.session.rcpt.subaddressing {
+            AddressMapping::Enable => self
+                .split_subaddress(address, session_id)
+                .await
+                .map(|(_, detail)| detail)
+                .filter(|detail| !detail.is_empty()),
+            AddressMapping::Custom(_) | AddressMapping::Disable => None,
+        }
+    }
 }
 
 impl AddressMapping {
@@ -167,6 +227,8 @@ impl AddressMapping {
 
 struct Address<'x>(&'x str);
 
+struct Recipient<'x>(&'x str);
+
 impl ResolveVariable for Address<'_> {
     fn resolve_variable(&'_ self, _: u32) -> crate::expr::Variable<'_> {
         Variable::from(self.0)
@@ -177,6 +239,20 @@ impl ResolveVariable for Address<'_> {
     }
 }
 
+impl ResolveVariable for Recipient<'_> {
+    fn resolve_variable(&'_ self, variable: u32) -> crate::expr::Variable<'_> {
+        match variable {
+            V_RECIPIENT_DOMAIN => self.0.rsplit_once('@').map_or("", |(_, domain)| domain),
+            _ => self.0,
+        }
+        .into()
+    }
+
+    fn resolve_global(&self, _: &str) -> Variable<'_> {
+        Variable::Integer(0)
+    }
+}
+
 impl AddressMapping {
     pub async fn to_subaddress<'x, 'y: 'x>(
         &'x self,
@@ -186,10 +262,8 @@ impl AddressMapping {
     ) -> Cow<'x, str> {
         match self {
             AddressMapping::Enable => {
-                if let Some((local_part, domain_part)) = address.rsplit_once('@')
-                    && let Some((local_part, _)) = local_part.split_once('+')
-                {
-                    return format!("{}@{}", local_part, domain_part).into();
+                if let Some((address, _)) = core.split_subaddress(address, session_id).await {
+                    return address.into();
                 }
             }
             AddressMapping::Custom(if_block) => {
@@ -14,6 +14,7 @@ use ahash::AHashSet;
 use directory::{
     Permission, Principal, PrincipalData, QueryParams, Type,
     backend::internal::{
+        PrincipalField,
         lookup::DirectoryStore,
         manage::{ChangedPrincipals, ManageDirectory},
     },
@@ -63,6 +64,7 @@ impl Server {
         // Apply principal permissions
         let mut permissions = role_permissions.finalize();
         let mut tenant = None;
+        let mut tenant_message_quota = None;
 
         // SPDX-SnippetBegin
         // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
@@ -78,22 +80,22 @@ impl Server {
             permissions.intersection(&self.get_role_permissions(tenant_id).await?.enabled);
 
             // Obtain tenant quota
+            let tenant_principal = self
+                .store()
+                .query(QueryParams::id(tenant_id).with_return_member_of(false))
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| {
+                    trc::SecurityEvent::Unauthorized
+                        .into_err()
+                        .details("Tenant not found")
+                        .id(tenant_id)
+                        .caused_by(trc::location!())
+                })?;
+            tenant_message_quota = tenant_principal.message_quota();
             tenant = Some(TenantInfo {
                 id: tenant_id,
-                quota: self
-                    .store()
-                    .query(QueryParams::id(tenant_id).with_return_member_of(false))
-                    .await
-                    .caused_by(trc::location!())?
-                    .ok_or_else(|| {
-                        trc::SecurityEvent::Unauthorized
-                            .into_err()
-                            .details("Tenant not found")
-                            .id(tenant_id)
-                            .caused_by(trc::location!())
-                    })?
-                    .quota
-                    .unwrap_or_default(),
+                quota: tenant_principal.quota.unwrap_or_default(),
             });
         }
 
@@ -105,7 +107,7 @@ impl Server {
             .member_of_mut()
             .map(std::mem::take)
             .unwrap_or_default();
-        let mut emails = principal.emails;
+        let mut emails = std::mem::take(&mut principal.emails);
         for &group_id in &member_of {
             if let Some(group) = self
                 .store()
@@ -118,6 +120,30 @@ impl Server {
             }
         }
 
+        // Obtain the addresses of the principals this account can send as or on behalf of
+        let mut send_as = Vec::new();
+        let mut send_on_behalf = Vec::new();
+        for (field, addresses) in [
+            (PrincipalField::SendAs, &mut send_as),
+            (PrincipalField::SendOnBehalf, &mut send_on_behalf),
+        ] {
+            for &delegate_id in principal.delegates(field) {
+                if let Some(delegate) = self
+                    .store()
+                    .query(QueryParams::id(delegate_id).with_return_member_of(false))
+                    .await
+                    .caused_by(trc::location!())?
+                {
+                    addresses.extend(
+                        delegate
+                            .emails
+                            .into_iter()
+                            .filter(|email| !email.starts_with('@')),
+                    );
+                }
+            }
+        }
+
         // Build access token
         let mut access_token = AccessToken {
             primary_id,
@@ -127,7 +153,37 @@ impl Server {
             name: principal.name,
             description: principal.description,
             emails,
+            allowed_senders: principal
+                .data
+                .iter()
+                .find_map(|data| {
+                    if let PrincipalData::AllowedSenders(senders) = data {
+                        Some(senders.clone())
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or_default(),
+            send_as,
+            send_on_behalf,
             quota: principal.quota.unwrap_or_default(),
+            message_quota: self.message_quota(
+                principal.data.iter().find_map(|data| {
+                    if let PrincipalData::MessageQuota(quota) = data {
+                        Some(*quota)
+                    } else {
+                        None
+                    }
+                }),
+                tenant_message_quota,
+            ),
+            soft_quota: principal.data.iter().find_map(|data| {
+                if let PrincipalData::SoftQuota(quota) = data {
+                    Some(*quota)
+                } else {
+                    None
+                }
+            }),
             locale: principal.data.iter().find_map(|data| {
                 if let PrincipalData::Locale(v) = data {
                     Some(v.to_string())
@@ -135,6 +191,26 @@ impl Server {
                     None
                 }
             }),
+            legal_hold: principal.data.iter().find_map(|data| {
+                if let PrincipalData::LegalHold(since) = data {
+                    Some(*since)
+                } else {
+                    None
+                }
+            }),
+            spam_trap: principal
+                .data
+                .iter()
+                .any(|data| matches!(data, PrincipalData::SpamTrap(_))),
+            calendar_out_of_office: principal
+                .data
+                .iter()
+                .any(|data| matches!(data, PrincipalData::CalendarOutOfOffice(_))),
+            collect_contacts: principal
+                .data
+                .iter()
+                .any(|data| matches!(data, PrincipalData::CollectContacts(_))),
+            is_resource: matches!(principal.typ, Type::Resource | Type::Location),
             permissions,
             concurrent_imap_requests: self.core.imap.rate_concurrent.map(ConcurrencyLimiter::new),
             concurrent_http_requests: self
@@ -181,6 +257,11 @@ impl Server {
                     {
                         collections.insert(Collection::Email);
                     }
+                    if collection == Collection::AddressBook
+                        && (acl.contains(Acl::ReadItems) || acl.contains(Acl::Administer))
+                    {
+                        collections.insert(Collection::ContactCard);
+                    }
 
                     if !collections.is_empty() {
                         access_token
@@ -559,6 +640,15 @@ impl AccessToken {
                 }
                 jmap_proto::method::get::RequestArguments::Quota => Permission::JmapQuotaGet,
                 jmap_proto::method::get::RequestArguments::Blob(_) => Permission::JmapBlobGet,
+                jmap_proto::method::get::RequestArguments::AddressBook => {
+                    Permission::JmapAddressBookGet
+                }
+                jmap_proto::method::get::RequestArguments::ContactCard => {
+                    Permission::JmapContactCardGet
+                }
+                jmap_proto::method::get::RequestArguments::AutocryptKey => {
+                    Permission::JmapAutocryptKeyGet
+                }
             },
             RequestMethod::Set(m) => match &m.arguments {
                 jmap_proto::method::set::RequestArguments::Email => Permission::JmapEmailSet,
@@ -596,6 +686,12 @@ impl AccessToken {
                 jmap_proto::method::changes::RequestArguments::Quota => {
                     Permission::JmapQuotaChanges
                 }
+                jmap_proto::method::changes::RequestArguments::AddressBook => {
+                    Permission::JmapAddressBookChanges
+                }
+                jmap_proto::method::changes::RequestArguments::ContactCard => {
+                    Permission::JmapContactCardChanges
+                }
             },
             RequestMethod::Copy(m) => match m.arguments {
                 jmap_proto::method::copy::RequestArguments::Email => Permission::JmapEmailCopy,
@@ -622,6 +718,9 @@ impl AccessToken {
                 jmap_proto::method::query::RequestArguments::Quota => {
                     Permission::JmapQuotaQueryChanges
                 }
+                jmap_proto::method::query::RequestArguments::ContactCard => {
+                    Permission::JmapContactCardQueryChanges
+                }
             },
             RequestMethod::Query(m) => match m.arguments {
                 jmap_proto::method::query::RequestArguments::Email(_) => Permission::JmapEmailQuery,
@@ -638,6 +737,9 @@ impl AccessToken {
                     Permission::JmapPrincipalQuery
                 }
                 jmap_proto::method::query::RequestArguments::Quota => Permission::JmapQuotaQuery,
+                jmap_proto::method::query::RequestArguments::ContactCard => {
+                    Permission::JmapContactCardQuery
+                }
             },
             RequestMethod::SearchSnippet(_) => Permission::JmapSearchSnippet,
             RequestMethod::ValidateScript(_) => Permission::JmapSieveScriptValidate,
@@ -660,6 +762,7 @@ impl AccessToken {
         ResourceToken {
             account_id: self.primary_id,
             quota: self.quota,
+            message_quota: self.message_quota,
             tenant: self.tenant,
         }
     }
@@ -688,7 +791,14 @@ impl AccessToken {
             + (self.access_to.len() * (std::mem::size_of::<u32>() + std::mem::size_of::<u64>()))
             + self.name.len()
             + self.description.as_ref().map_or(0, |v| v.len())
-            + self.emails.iter().map(|v| v.len()).sum::<usize>()) as u64;
+            + self.emails.iter().map(|v| v.len()).sum::<usize>()
+            + self
+                .allowed_senders
+                .iter()
+                .chain(self.send_as.iter())
+                .chain(self.send_on_behalf.iter())
+                .map(|v| v.len())
+                .sum::<usize>()) as u64;
         self
     }
 }
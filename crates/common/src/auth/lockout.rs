@@ -0,0 +1,202 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{net::IpAddr, time::Duration};
+
+use store::{
+    Serialize,
+    dispatch::lookup::KeyValue,
+    write::{AlignedBytes, Archive, Archiver, now},
+};
+use trc::AddContext;
+use utils::config::{Config, Rate};
+
+use crate::{KV_ACCOUNT_LOCKOUT, KV_AUTH_FAILURES, Server};
+
+#[derive(Debug, Clone)]
+pub struct AccountLockout {
+    pub rate: Rate,
+    pub lockout: Duration,
+    pub max_lockout: Duration,
+}
+
+#[derive(
+    rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, Copy, Default, PartialEq, Eq,
+)]
+pub struct LockoutStatus {
+    pub failures: u64,
+    pub locked_until: u64,
+    pub strikes: u32,
+}
+
+impl AccountLockout {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        let rate = config
+            .property_or_default::<Option<Rate>>("server.auto-ban.account.rate", "false")
+            .unwrap_or_default()?;
+
+        Some(AccountLockout {
+            rate,
+            lockout: config
+                .property_or_default("server.auto-ban.account.lockout", "5m")
+                .unwrap_or(Duration::from_secs(5 * 60)),
+            max_lockout: config
+                .property_or_default("server.auto-ban.account.max-lockout", "1d")
+                .unwrap_or(Duration::from_secs(24 * 60 * 60)),
+        })
+    }
+
+    // Each consecutive lockout doubles the previous one, up to the configured maximum
+    pub fn lockout_duration(&self, strikes: u32) -> u64 {
+        let lockout = self.lockout.as_secs().max(1);
+        lockout
+            .saturating_mul(1u64 << strikes.saturating_sub(1).min(32))
+            .min(self.max_lockout.as_secs().max(lockout))
+    }
+}
+
+impl Server {
+    pub fn lockout_login(&self, login: Option<&str>, remote_ip: IpAddr) -> Option<String> {
+        login
+            .filter(|login| {
+                !login.is_empty()
+                    && self.core.network.security.account_lockout.is_some()
+                    && !self.is_ip_allowed(&remote_ip)
+            })
+            .map(|login| login.to_lowercase())
+    }
+
+    pub async fn assert_account_unlocked(&self, login: &str) -> trc::Result<()> {
+        let status = self.account_lockout(login).await?;
+        if status.locked_until > now() {
+            Err(trc::SecurityEvent::AccountLocked
+                .into_err()
+                .ctx(trc::Key::AccountName, login.to_string())
+                .ctx(trc::Key::Expires, status.locked_until)
+                .ctx(trc::Key::Total, status.strikes))
+        } else {
+            Ok(())
+        }
+    }
+
+    pub async fn record_auth_failure(&self, login: &str, remote_ip: IpAddr) -> trc::Result<()> {
+        let Some(config) = &self.core.network.security.account_lockout else {
+            return Ok(());
+        };
+        let store = self.in_memory_store();
+        let failures = store
+            .counter_incr(
+                KeyValue::with_prefix(KV_AUTH_FAILURES, login.as_bytes(), 1)
+                    .expires(config.rate.period.as_secs()),
+                true,
+            )
+            .await
+            .caused_by(trc::location!())?;
+        if (failures as u64) < config.rate.requests {
+            return Ok(());
+        }
+
+        // Lock the account, backing off exponentially on repeated lockouts
+        let strikes = self.account_lockout(login).await?.strikes.saturating_add(1);
+        let lockout = config.lockout_duration(strikes);
+        let locked_until = now() + lockout;
+        store
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_ACCOUNT_LOCKOUT,
+                    login.as_bytes(),
+                    Archiver::new(LockoutStatus {
+                        failures: 0,
+                        locked_until,
+                        strikes,
+                    })
+                    .untrusted()
+                    .serialize()
+                    .caused_by(trc::location!())?,
+                )
+                .expires(lockout + config.max_lockout.as_secs()),
+            )
+            .await
+            .caused_by(trc::location!())?;
+        store
+            .counter_delete(KeyValue::<()>::build_key(
+                KV_AUTH_FAILURES,
+                login.as_bytes(),
+            ))
+            .await
+            .caused_by(trc::location!())?;
+
+        Err(trc::SecurityEvent::AccountLocked
+            .into_err()
+            .ctx(trc::Key::AccountName, login.to_string())
+            .ctx(trc::Key::RemoteIp, remote_ip)
+            .ctx(trc::Key::Expires, locked_until)
+            .ctx(trc::Key::Total, strikes))
+    }
+
+    pub async fn clear_auth_failures(&self, login: &str) -> trc::Result<()> {
+        let store = self.in_memory_store();
+        let key = KeyValue::<()>::build_key(KV_AUTH_FAILURES, login.as_bytes());
+        if store
+            .counter_get(key.as_slice())
+            .await
+            .caused_by(trc::location!())?
+            > 0
+        {
+            store
+                .counter_delete(key)
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn account_lockout(&self, login: &str) -> trc::Result<LockoutStatus> {
+        let store = self.in_memory_store();
+        let mut status = match store
+            .key_get::<Archive<AlignedBytes>>(KeyValue::<()>::build_key(
+                KV_ACCOUNT_LOCKOUT,
+                login.as_bytes(),
+            ))
+            .await
+            .caused_by(trc::location!())?
+        {
+            Some(archive) => archive
+                .deserialize::<LockoutStatus>()
+                .caused_by(trc::location!())?,
+            None => LockoutStatus::default(),
+        };
+        status.failures = store
+            .counter_get(KeyValue::<()>::build_key(
+                KV_AUTH_FAILURES,
+                login.as_bytes(),
+            ))
+            .await
+            .caused_by(trc::location!())?
+            .max(0) as u64;
+
+        Ok(status)
+    }
+
+    pub async fn clear_account_lockout(&self, login: &str) -> trc::Result<()> {
+        let store = self.in_memory_store();
+        store
+            .key_delete(KeyValue::<()>::build_key(
+                KV_ACCOUNT_LOCKOUT,
+                login.as_bytes(),
+            ))
+            .await
+            .caused_by(trc::location!())?;
+        store
+            .counter_delete(KeyValue::<()>::build_key(
+                KV_AUTH_FAILURES,
+                login.as_bytes(),
+            ))
+            .await
+            .caused_by(trc::location!())
+    }
+}
@@ -0,0 +1,188 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::net::IpAddr;
+
+use directory::FALLBACK_ADMIN_ID;
+use store::{
+    Serialize,
+    dispatch::lookup::KeyValue,
+    write::{Archiver, BatchBuilder, TaskQueueClass, ValueClass, now},
+};
+use trc::AddContext;
+
+use crate::{KV_KNOWN_DEVICE, Server};
+
+use super::AccessToken;
+
+const DEVICE_ACCOUNT: u8 = 0;
+const DEVICE_IP: u8 = 1;
+const DEVICE_COUNTRY: u8 = 2;
+const DEVICE_CLIENT: u8 = 3;
+
+const MAX_CLIENT_LEN: usize = 255;
+
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct LoginAlert {
+    pub remote_ip: String,
+    pub country: Option<String>,
+    pub client: Option<String>,
+    pub new_location: bool,
+    pub new_client: bool,
+    pub timestamp: u64,
+}
+
+impl Server {
+    pub async fn track_login(
+        &self,
+        access_token: &AccessToken,
+        remote_ip: IpAddr,
+        client: Option<&str>,
+    ) -> trc::Result<()> {
+        let Some(config) = &self.core.network.login_alerts else {
+            return Ok(());
+        };
+        let account_id = access_token.primary_id;
+        let domain = access_token
+            .emails
+            .first()
+            .unwrap_or(&access_token.name)
+            .rsplit('@')
+            .next()
+            .unwrap_or_default();
+        if account_id == FALLBACK_ADMIN_ID || !config.is_enabled_for(domain) {
+            return Ok(());
+        }
+
+        let remote_ip_str = remote_ip.to_string();
+        let client = client.map(|client| {
+            client
+                .char_indices()
+                .take_while(|(pos, _)| *pos < MAX_CLIENT_LEN)
+                .map(|(_, ch)| ch)
+                .collect::<String>()
+        });
+
+        // Check the grace table of known devices for this account
+        let mut entries = vec![(DEVICE_ACCOUNT, ""), (DEVICE_IP, remote_ip_str.as_str())];
+        if let Some(client) = &client {
+            entries.push((DEVICE_CLIENT, client.as_str()));
+        }
+        let expiry = config.known_device_expiry.as_secs();
+        let mut is_known = [false; 4];
+        for (typ, value) in entries {
+            is_known[typ as usize] = self
+                .touch_known_device(account_id, typ, value, expiry)
+                .await?;
+        }
+
+        // The country is only needed when the address is new
+        let lookup_country = async || {
+            self.lookup_asn_country(remote_ip)
+                .await
+                .country
+                .map(|country| country.to_string())
+        };
+        let mut country = None;
+        if !is_known[DEVICE_IP as usize] {
+            country = lookup_country().await;
+            if let Some(country) = &country {
+                is_known[DEVICE_COUNTRY as usize] = self
+                    .touch_known_device(account_id, DEVICE_COUNTRY, country, expiry)
+                    .await?;
+            }
+        }
+
+        // Logins from a known IP or country, or from a new account, are not reported
+        let new_location = !is_known[DEVICE_IP as usize] && !is_known[DEVICE_COUNTRY as usize];
+        let new_client = client.is_some() && !is_known[DEVICE_CLIENT as usize];
+        if !is_known[DEVICE_ACCOUNT as usize] || !(new_location || new_client) {
+            return Ok(());
+        }
+        if is_known[DEVICE_IP as usize] {
+            // Resolved above only for new addresses
+            country = lookup_country().await;
+        }
+
+        // Queue the notification
+        let due = now();
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .update_document(0)
+            .set(
+                ValueClass::TaskQueue(TaskQueueClass::SendLoginAlert {
+                    due,
+                    is_payload: false,
+                }),
+                vec![],
+            )
+            .set(
+                ValueClass::TaskQueue(TaskQueueClass::SendLoginAlert {
+                    due,
+                    is_payload: true,
+                }),
+                Archiver::new(LoginAlert {
+                    remote_ip: remote_ip_str,
+                    country,
+                    client,
+                    new_location,
+                    new_client,
+                    timestamp: due,
+                })
+                .serialize()
+                .caused_by(trc::location!())?,
+            );
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+        self.notify_task_queue();
+
+        Ok(())
+    }
+
+    // Returns whether the device was known, entries are only rewritten when
+    // missing or past half of their expiry to avoid a write on every login
+    async fn touch_known_device(
+        &self,
+        account_id: u32,
+        typ: u8,
+        value: &str,
+        expiry: u64,
+    ) -> trc::Result<bool> {
+        let store = self.in_memory_store();
+        let key = device_key(account_id, typ, value);
+        let last_seen = store
+            .key_get::<i64>(KeyValue::<()>::build_key(KV_KNOWN_DEVICE, &key))
+            .await
+            .caused_by(trc::location!())?;
+        let now = now();
+        if last_seen.is_none_or(|last_seen| (last_seen as u64).saturating_add(expiry / 2) <= now) {
+            store
+                .key_set(
+                    KeyValue::with_prefix(
+                        KV_KNOWN_DEVICE,
+                        key,
+                        (now as i64).to_be_bytes().to_vec(),
+                    )
+                    .expires(expiry),
+                )
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(last_seen.is_some())
+    }
+}
+
+fn device_key(account_id: u32, typ: u8, value: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(value.len() + 5);
+    key.extend_from_slice(&account_id.to_be_bytes());
+    key.push(typ);
+    key.extend_from_slice(value.as_bytes());
+    key
+}
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use crate::{Server, listener::limiter::ConcurrencyLimiter};
+use crate::{Server, listener::limiter::ConcurrencyLimiter, telemetry::metrics::usage::UsageEvent};
 use directory::{
     Directory, FALLBACK_ADMIN_ID, Permission, Permissions, Principal, QueryParams, Type,
     backend::internal::lookup::DirectoryStore, core::secret::verify_secret_hash,
@@ -19,6 +19,8 @@ use utils::{
 };
 
 pub mod access_token;
+pub mod lockout;
+pub mod login_alert;
 pub mod oauth;
 pub mod rate_limit;
 pub mod roles;
@@ -33,7 +35,17 @@ pub struct AccessToken {
     pub description: Option<String>,
     pub locale: Option<String>,
     pub emails: Vec<String>,
+    pub allowed_senders: Vec<String>,
+    pub send_as: Vec<String>,
+    pub send_on_behalf: Vec<String>,
     pub quota: u64,
+    pub soft_quota: Option<u64>,
+    pub message_quota: u64,
+    pub legal_hold: Option<u64>,
+    pub spam_trap: bool,
+    pub calendar_out_of_office: bool,
+    pub collect_contacts: bool,
+    pub is_resource: bool,
     pub permissions: Permissions,
     pub tenant: Option<TenantInfo>,
     pub concurrent_http_requests: Option<ConcurrencyLimiter>,
@@ -53,6 +65,7 @@ pub struct TenantInfo {
 pub struct ResourceToken {
     pub account_id: u32,
     pub quota: u64,
+    pub message_quota: u64,
     pub tenant: Option<TenantInfo>,
 }
 
@@ -60,6 +73,7 @@ pub struct AuthRequest<'x> {
     credentials: Credentials<String>,
     session_id: u64,
     remote_ip: IpAddr,
+    client: Option<String>,
     return_member_of: bool,
     allow_api_access: bool,
     directory: Option<&'x Directory>,
@@ -71,7 +85,7 @@ impl Server {
         let directory = req.directory.unwrap_or(&self.core.storage.directory);
 
         // Validate credentials
-        match &req.credentials {
+        let result = match &req.credentials {
             Credentials::OAuthBearer { token } if !directory.has_bearer_token_support() => {
                 match self
                     .validate_access_token(GrantType::AccessToken.into(), token)
@@ -81,16 +95,78 @@ impl Server {
                     Err(err) => Err(err),
                 }
             }
-            _ => match self.authenticate_credentials(req, directory).await {
-                Ok(principal) => self.get_access_token(principal).await,
-                Err(err) => Err(err),
-            },
+            _ => {
+                // Refuse to authenticate locked out accounts
+                let lockout_login = self.lockout_login(req.credentials.login(), req.remote_ip);
+                if let Some(login) = &lockout_login {
+                    self.assert_account_unlocked(login).await?;
+                }
+
+                match self.authenticate_credentials(req, directory).await {
+                    Ok(principal) => {
+                        if let Some(login) = &lockout_login {
+                            self.clear_auth_failures(login).await?;
+                        }
+                        self.get_access_token(principal).await
+                    }
+                    Err(err) => {
+                        if let Some(login) = &lockout_login
+                            && (err.matches(trc::EventType::Auth(trc::AuthEvent::Failed))
+                                || err.matches(trc::EventType::Security(
+                                    trc::SecurityEvent::AuthenticationBan,
+                                )))
+                        {
+                            self.record_auth_failure(login, req.remote_ip).await?;
+                        }
+                        Err(err)
+                    }
+                }
+            }
         }
         .and_then(|token| {
             token
                 .assert_has_permission(Permission::Authenticate)
                 .map(|_| token)
-        })
+        });
+
+        if let Ok(access_token) = &result
+            && let Some(email) = access_token.emails.first()
+        {
+            self.record_usage(
+                email,
+                UsageEvent::Login {
+                    account_id: access_token.primary_id,
+                },
+            )
+            .await;
+        }
+
+        // Look for logins from new locations or clients, access tokens issued
+        // by this server were already tracked when the user signed in
+        if let Ok(access_token) = &result
+            && self.core.network.login_alerts.is_some()
+            && (!matches!(req.credentials, Credentials::OAuthBearer { .. })
+                || directory.has_bearer_token_support())
+        {
+            let server = self.clone();
+            let access_token = access_token.clone();
+            let remote_ip = req.remote_ip;
+            let client = req.client.clone();
+            let session_id = req.session_id;
+            tokio::spawn(async move {
+                if let Err(err) = server
+                    .track_login(&access_token, remote_ip, client.as_deref())
+                    .await
+                {
+                    trc::error!(
+                        err.span_id(session_id)
+                            .details("Failed to track login device")
+                    );
+                }
+            });
+        }
+
+        result
     }
 
     async fn authenticate_credentials(
@@ -258,6 +334,7 @@ impl<'x> AuthRequest<'x> {
             credentials,
             session_id,
             remote_ip,
+            client: None,
             return_member_of: true,
             directory: None,
             allow_api_access: false,
@@ -294,6 +371,11 @@ impl<'x> AuthRequest<'x> {
         self.allow_api_access = allow_api_access;
         self
     }
+
+    pub fn with_client(mut self, client: Option<impl Into<String>>) -> Self {
+        self.client = client.map(Into::into);
+        self
+    }
 }
 
 impl CacheItemWeight for AccessToken {
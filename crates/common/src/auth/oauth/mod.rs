@@ -26,6 +26,9 @@ pub enum GrantType {
     LiveMetrics,
     Troubleshoot,
     Rsvp,
+    Moderation,
+    Unsubscribe,
+    FileShare,
 }
 
 impl GrantType {
@@ -37,6 +40,9 @@ impl GrantType {
             GrantType::LiveMetrics => "live_metrics",
             GrantType::Troubleshoot => "troubleshoot",
             GrantType::Rsvp => "rsvp",
+            GrantType::Moderation => "moderation",
+            GrantType::Unsubscribe => "unsubscribe",
+            GrantType::FileShare => "file_share",
         }
     }
 
@@ -48,6 +54,9 @@ impl GrantType {
             GrantType::LiveMetrics => 3,
             GrantType::Troubleshoot => 4,
             GrantType::Rsvp => 5,
+            GrantType::Moderation => 6,
+            GrantType::Unsubscribe => 7,
+            GrantType::FileShare => 8,
         }
     }
 
@@ -59,6 +68,9 @@ impl GrantType {
             3 => Some(GrantType::LiveMetrics),
             4 => Some(GrantType::Troubleshoot),
             5 => Some(GrantType::Rsvp),
+            6 => Some(GrantType::Moderation),
+            7 => Some(GrantType::Unsubscribe),
+            8 => Some(GrantType::FileShare),
             _ => None,
         }
     }
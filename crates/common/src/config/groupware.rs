@@ -6,7 +6,12 @@
 
 use std::{str::FromStr, time::Duration};
 
-use utils::{config::Config, template::Template};
+use ahash::AHashMap;
+use calcard::common::timezone::Tz;
+use utils::{
+    config::{Config, Rate, cron::SimpleCron, utils::ParseValue},
+    template::Template,
+};
 
 #[derive(Debug, Clone, Default)]
 pub struct GroupwareConfig {
@@ -18,11 +23,14 @@ pub struct GroupwareConfig {
     pub max_locks_per_user: usize,
     pub max_results: usize,
     pub assisted_discovery: bool,
+    pub item_limits: DavItemLimits,
+    pub item_limits_by_domain: AHashMap<String, DavItemLimits>,
 
     // Calendar settings
     pub max_ical_size: usize,
     pub max_ical_instances: usize,
     pub max_ical_attendees_per_instance: usize,
+    pub tz_overrides: AHashMap<String, String>,
     pub default_calendar_name: Option<String>,
     pub default_calendar_display_name: Option<String>,
     pub alarms_enabled: bool,
@@ -39,14 +47,57 @@ pub struct GroupwareConfig {
     pub itip_http_rsvp_expiration: u64,
     pub itip_inbox_auto_expunge: Option<u64>,
     pub itip_template: Template<CalendarTemplateVariable>,
+    pub out_of_office: Option<OutOfOffice>,
+    pub resource_booking: ResourceBooking,
 
     // Addressbook settings
     pub max_vcard_size: usize,
     pub default_addressbook_name: Option<String>,
     pub default_addressbook_display_name: Option<String>,
+    pub collected_addressbook_name: Option<String>,
+    pub collected_addressbook_display_name: String,
+    pub collected_contacts_rate: Option<Rate>,
+    pub directory_addressbook: Option<DirectoryAddressBook>,
 
     // File storage settings
     pub max_file_size: usize,
+    pub file_share_url: Option<String>,
+    pub file_share_max_expiration: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DavItemLimits {
+    pub max_calendar_items: Option<usize>,
+    pub max_addressbook_items: Option<usize>,
+}
+
+#[derive(Debug, Clone)]
+pub struct OutOfOffice {
+    pub frequency: SimpleCron,
+    pub summaries: Vec<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DirectoryAddressBook {
+    pub frequency: SimpleCron,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ResourceBooking {
+    pub policy: ResourceBookingPolicy,
+    pub max_advance: Option<u64>,
+    pub max_duration: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ResourceBookingPolicy {
+    #[default]
+    Auto,
+    Accept,
+    Manual,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default, Hash)]
@@ -74,6 +125,23 @@ pub enum CalendarTemplateVariable {
 
 impl GroupwareConfig {
     pub fn parse(config: &mut Config) -> Self {
+        // Time zone names mapped to a zone in the bundled database
+        let mut tz_overrides = AHashMap::new();
+        let mut errors = vec![];
+        for (name, target) in config.iterate_prefix("calendar.timezone.override") {
+            if Tz::from_str(target).is_ok() {
+                tz_overrides.insert(name.to_string(), target.to_string());
+            } else {
+                errors.push((
+                    format!("calendar.timezone.override.{name}"),
+                    format!("Unknown time zone {target:?}"),
+                ));
+            }
+        }
+        for (key, error) in errors {
+            config.new_parse_error(key, error);
+        }
+
         GroupwareConfig {
             max_request_size: config
                 .property("dav.request.max-size")
@@ -91,6 +159,16 @@ impl GroupwareConfig {
                 .unwrap_or(3600),
             max_locks_per_user: config.property("dav.locks.max-per-user").unwrap_or(10),
             max_results: config.property("dav.response.max-results").unwrap_or(2000),
+            item_limits: DavItemLimits::parse(config, "dav.limits"),
+            item_limits_by_domain: config
+                .sub_keys("dav.limits.domain", ".name")
+                .into_iter()
+                .filter_map(|id| {
+                    let prefix = format!("dav.limits.domain.{id}");
+                    let domain = config.value_require((&prefix, "name"))?.to_lowercase();
+                    Some((domain, DavItemLimits::parse(config, &prefix)))
+                })
+                .collect(),
             default_calendar_name: config
                 .property_or_default::<Option<String>>("calendar.default.href-name", "default")
                 .unwrap_or_default(),
@@ -109,6 +187,19 @@ impl GroupwareConfig {
                     "Stalwart Address Book",
                 )
                 .unwrap_or_default(),
+            collected_addressbook_name: config
+                .property_or_default::<Option<String>>(
+                    "contacts.auto-collect.href-name",
+                    "collected",
+                )
+                .unwrap_or_default(),
+            collected_addressbook_display_name: config
+                .value("contacts.auto-collect.display-name")
+                .unwrap_or("Collected Addresses")
+                .to_string(),
+            collected_contacts_rate: config
+                .property_or_default::<Option<Rate>>("contacts.auto-collect.rate", "100/1d")
+                .unwrap_or_default(),
             max_ical_size: config.property("calendar.max-size").unwrap_or(512 * 1024),
             max_ical_instances: config
                 .property("calendar.max-recurrence-expansions")
@@ -116,10 +207,31 @@ impl GroupwareConfig {
             max_ical_attendees_per_instance: config
                 .property("calendar.max-attendees-per-instance")
                 .unwrap_or(20),
+            tz_overrides,
             max_vcard_size: config.property("contacts.max-size").unwrap_or(512 * 1024),
             max_file_size: config
                 .property("file-storage.max-size")
                 .unwrap_or(25 * 1024 * 1024),
+            file_share_url: if config.property("file-storage.share.enable").unwrap_or(true) {
+                if let Some(url) = config
+                    .value("file-storage.share.url")
+                    .map(|v| v.trim().trim_end_matches('/'))
+                    .filter(|v| !v.is_empty())
+                {
+                    Some(url.to_string())
+                } else {
+                    Some(format!(
+                        "https://{}/share",
+                        config.value("server.hostname").unwrap_or("localhost")
+                    ))
+                }
+            } else {
+                None
+            },
+            file_share_max_expiration: config
+                .property_or_default::<Duration>("file-storage.share.max-expiration", "30d")
+                .map(|d| d.as_secs())
+                .unwrap_or(30 * 24 * 60 * 60),
             alarms_enabled: config.property("calendar.alarms.enabled").unwrap_or(true),
             alarms_minimum_interval: config
                 .property_or_default::<Duration>("calendar.alarms.minimum-interval", "1h")
@@ -187,6 +299,107 @@ impl GroupwareConfig {
                 "/../../resources/html-templates/calendar-invite.html.min"
             )))
             .expect("Failed to parse calendar template"),
+            out_of_office: OutOfOffice::parse(config),
+            directory_addressbook: DirectoryAddressBook::parse(config),
+            resource_booking: ResourceBooking {
+                policy: config
+                    .property_or_default("calendar.scheduling.resource.policy", "auto")
+                    .unwrap_or_default(),
+                max_advance: config
+                    .property::<Duration>("calendar.scheduling.resource.max-advance")
+                    .map(|d| d.as_secs()),
+                max_duration: config
+                    .property::<Duration>("calendar.scheduling.resource.max-duration")
+                    .map(|d| d.as_secs()),
+            },
+        }
+    }
+}
+
+impl GroupwareConfig {
+    pub fn resolve_tz(&self, tz_id: &str) -> Option<Tz> {
+        Tz::from_str(
+            self.tz_overrides
+                .get(tz_id)
+                .map(|tz_id| tz_id.as_str())
+                .unwrap_or(tz_id),
+        )
+        .ok()
+    }
+}
+
+impl DavItemLimits {
+    fn parse(config: &mut Config, prefix: &str) -> Self {
+        DavItemLimits {
+            max_calendar_items: config.property((prefix, "max-items.calendar")),
+            max_addressbook_items: config.property((prefix, "max-items.addressbook")),
+        }
+    }
+}
+
+impl OutOfOffice {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("calendar.out-of-office.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        let mut summaries = config
+            .values("calendar.out-of-office.match")
+            .map(|(_, v)| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>();
+        if summaries.is_empty() {
+            summaries = vec!["out of office".to_string(), "ooo".to_string()];
+        }
+
+        Some(OutOfOffice {
+            frequency: config
+                .property_or_default::<SimpleCron>("calendar.out-of-office.frequency", "0 * *")
+                .unwrap_or_else(|| SimpleCron::parse_value("0 * *").unwrap()),
+            summaries,
+            message: config
+                .value("calendar.out-of-office.message")
+                .unwrap_or("I am out of the office and will reply when I return.")
+                .to_string(),
+        })
+    }
+}
+
+impl DirectoryAddressBook {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("contacts.directory.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        Some(DirectoryAddressBook {
+            frequency: config
+                .property_or_default::<SimpleCron>("contacts.directory.frequency", "0 * *")
+                .unwrap_or_else(|| SimpleCron::parse_value("0 * *").unwrap()),
+            name: config
+                .value("contacts.directory.href-name")
+                .unwrap_or("directory")
+                .to_string(),
+            display_name: config
+                .value("contacts.directory.display-name")
+                .unwrap_or("Directory")
+                .to_string(),
+        })
+    }
+}
+
+impl ParseValue for ResourceBookingPolicy {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "auto" => Ok(ResourceBookingPolicy::Auto),
+            "accept" | "always" => Ok(ResourceBookingPolicy::Accept),
+            "manual" => Ok(ResourceBookingPolicy::Manual),
+            _ => Err(format!("Invalid resource booking policy {value:?}.")),
         }
     }
 }
@@ -8,6 +8,19 @@ use std::time::Duration;
 
 use utils::config::{Config, Rate};
 
+use crate::expr::{
+    V_AUTHENTICATED_AS, V_LISTENER, V_PROTOCOL, V_REMOTE_IP, V_TLS, if_block::IfBlock,
+    tokenizer::TokenMap,
+};
+
+pub const IMAP_VARS: &[u32] = &[
+    V_AUTHENTICATED_AS,
+    V_LISTENER,
+    V_PROTOCOL,
+    V_REMOTE_IP,
+    V_TLS,
+];
+
 #[derive(Default, Clone)]
 pub struct ImapConfig {
     pub max_request_size: usize,
@@ -20,10 +33,15 @@ pub struct ImapConfig {
 
     pub rate_requests: Option<Rate>,
     pub rate_concurrent: Option<u64>,
+
+    pub bandwidth_session: Option<IfBlock>,
+    pub bandwidth_account: Option<IfBlock>,
 }
 
 impl ImapConfig {
     pub fn parse(config: &mut Config) -> Self {
+        let token_map = &TokenMap::default().with_variables(IMAP_VARS);
+
         ImapConfig {
             max_request_size: config
                 .property_or_default("imap.request.max-size", "52428800")
@@ -49,6 +67,16 @@ impl ImapConfig {
             allow_plain_auth: config
                 .property_or_default("imap.auth.allow-plain-text", "false")
                 .unwrap_or(false),
+            bandwidth_session: IfBlock::try_parse(
+                config,
+                "imap.rate-limit.bandwidth.session",
+                token_map,
+            ),
+            bandwidth_account: IfBlock::try_parse(
+                config,
+                "imap.rate-limit.bandwidth.account",
+                token_map,
+            ),
         }
     }
 }
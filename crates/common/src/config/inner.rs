@@ -71,6 +71,9 @@ impl Data {
             logos: Default::default(),
             smtp_connectors: TlsConnectors::default(),
             asn_geo_data: Default::default(),
+            imap_bandwidth: Default::default(),
+            connections: Default::default(),
+            sessions: Default::default(),
         }
     }
 }
@@ -192,6 +195,12 @@ impl Caches {
                 MB_5,
                 ((std::mem::size_of::<Ipv4Addr>() + 255) * 2) as u64,
             ),
+            ip_family: CacheWithTtl::from_config(
+                config,
+                "ip-family",
+                MB_1,
+                (std::mem::size_of::<bool>() + 255) as u64,
+            ),
         }
     }
 
@@ -234,6 +243,9 @@ impl Default for Data {
             logos: Default::default(),
             smtp_connectors: Default::default(),
             asn_geo_data: Default::default(),
+            imap_bandwidth: Default::default(),
+            connections: Default::default(),
+            sessions: Default::default(),
         }
     }
 }
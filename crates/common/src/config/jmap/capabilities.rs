@@ -113,7 +113,7 @@ impl JmapConfig {
         let mut capabilities: AHashSet<sieve::compiler::grammar::Capability> =
             AHashSet::from_iter(sieve::compiler::grammar::Capability::all().iter().cloned());
 
-        for (_, capability) in config.values("sieve.untrusted.disabled-capabilities") {
+        for (_, capability) in config.values("sieve.untrusted.disable-capabilities") {
             capabilities.remove(&sieve::compiler::grammar::Capability::parse(capability));
         }
 
@@ -176,5 +176,27 @@ impl JmapConfig {
             Capability::Quota,
             Capabilities::Empty(EmptyCapabilities::default()),
         );
+
+        // Add Contacts capabilities
+        self.capabilities.session.append(
+            Capability::Contacts,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+        self.capabilities.account.append(
+            Capability::Contacts,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+
+        // Add Autocrypt capabilities
+        if self.autocrypt_enable {
+            self.capabilities.session.append(
+                Capability::Autocrypt,
+                Capabilities::Empty(EmptyCapabilities::default()),
+            );
+            self.capabilities.account.append(
+                Capability::Autocrypt,
+                Capabilities::Empty(EmptyCapabilities::default()),
+            );
+        }
     }
 }
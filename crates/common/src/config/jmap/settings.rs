@@ -6,7 +6,7 @@
 
 use std::{str::FromStr, time::Duration};
 
-use jmap_proto::request::capability::BaseCapabilities;
+use jmap_proto::{request::capability::BaseCapabilities, types::keyword::OTHER};
 use nlp::language::Language;
 use utils::config::{Config, Rate, cron::SimpleCron, utils::ParseValue};
 
@@ -39,6 +39,9 @@ pub struct JmapConfig {
     pub mail_parse_max_items: usize,
     pub mail_max_size: usize,
     pub mail_autoexpunge_after: Option<u64>,
+    pub mail_max_keywords: usize,
+    pub mail_max_mailbox_keywords: usize,
+    pub mail_max_messages: u64,
 
     pub sieve_max_script_name: usize,
     pub sieve_max_scripts: usize,
@@ -64,17 +67,52 @@ pub struct JmapConfig {
 
     pub default_folders: Vec<DefaultFolder>,
     pub shared_folder: String,
+    pub saved_search_folder: String,
+    pub saved_search_max: usize,
+    pub scheduled_send_folder: Option<String>,
 
     pub http_headers: Vec<(hyper::header::HeaderName, hyper::header::HeaderValue)>,
     pub http_use_forwarded: bool,
+    pub http_image_proxy: Option<ImageProxy>,
 
     pub encrypt: bool,
     pub encrypt_append: bool,
+    pub encrypt_wkd: bool,
+    pub tnef_convert: bool,
+    pub tnef_keep_original: bool,
+
+    pub mdn_suppress_external: bool,
+    pub mdn_auto_send: bool,
+
+    pub forwarding_enable: bool,
+    pub forwarding_max_recipients: usize,
+    pub forwarding_max_hops: usize,
+
+    pub subaddress_folder: bool,
+    pub subaddress_folder_create: bool,
+    pub subaddress_folder_max: usize,
+
+    pub disposable_alias_enable: bool,
+    pub disposable_alias_max: usize,
+
+    pub identity_max_identities: usize,
+    pub identity_max_signature_size: usize,
+
+    pub autocrypt_enable: bool,
+    pub autocrypt_max_peers: usize,
+    pub autocrypt_add_header: bool,
 
     pub capabilities: BaseCapabilities,
     pub account_purge_frequency: SimpleCron,
 }
 
+#[derive(Clone, Debug)]
+pub struct ImageProxy {
+    pub timeout: Duration,
+    pub max_size: usize,
+    pub cache_ttl: u64,
+}
+
 #[derive(Clone, Debug)]
 pub struct DefaultFolder {
     pub name: String,
@@ -82,6 +120,8 @@ pub struct DefaultFolder {
     pub special_use: SpecialUse,
     pub subscribe: bool,
     pub create: bool,
+    pub localize: bool,
+    pub protect: bool,
 }
 
 #[derive(
@@ -145,6 +185,9 @@ impl JmapConfig {
                         .unwrap_or(true)
                         | [SpecialUse::Inbox, SpecialUse::Trash, SpecialUse::Junk]
                             .contains(&special_use);
+                    let protect = config
+                        .property_or_default(("email.folders", key.as_str(), "protect"), "true")
+                        .unwrap_or(true);
                     if let Some(name) = config
                         .value(("email.folders", key.as_str(), "name"))
                         .map(|name| name.trim())
@@ -162,6 +205,13 @@ impl JmapConfig {
                             special_use,
                             subscribe,
                             create,
+                            localize: config
+                                .property_or_default(
+                                    ("email.folders", key.as_str(), "localize"),
+                                    "false",
+                                )
+                                .unwrap_or(false),
+                            protect,
                         });
                     }
                 }
@@ -176,7 +226,9 @@ impl JmapConfig {
             (SpecialUse::Junk, "Junk Mail"),
             (SpecialUse::Drafts, "Drafts"),
             (SpecialUse::Sent, "Sent Items"),
+            (SpecialUse::Archive, "Archive"),
         ] {
+            // Folders without a configured name follow the locale of the account
             if !default_folders.iter().any(|f| f.special_use == special_use) {
                 default_folders.push(DefaultFolder {
                     name: name.to_string(),
@@ -184,6 +236,8 @@ impl JmapConfig {
                     special_use,
                     subscribe: true,
                     create: true,
+                    localize: special_use != SpecialUse::Inbox,
+                    protect: true,
                 });
             }
         }
@@ -284,6 +338,16 @@ impl JmapConfig {
                 .property_or_default::<Option<Duration>>("email.auto-expunge", "30d")
                 .map(|d| d.map(|d| d.as_secs()))
                 .unwrap_or_default(),
+            mail_max_keywords: config
+                .property_or_default::<usize>("email.keywords.max-per-account", "100")
+                .unwrap_or(100)
+                .min(128 - OTHER),
+            mail_max_messages: config
+                .property_or_default::<u64>("email.messages.max-per-account", "0")
+                .unwrap_or_default(),
+            mail_max_mailbox_keywords: config
+                .property_or_default::<usize>("email.keywords.max-per-mailbox", "50")
+                .unwrap_or(50),
             sieve_max_script_name: config
                 .property("sieve.untrusted.limits.name-length")
                 .unwrap_or(512),
@@ -318,7 +382,76 @@ impl JmapConfig {
             encrypt_append: config
                 .property_or_default("email.encryption.append", "false")
                 .unwrap_or(false),
+            encrypt_wkd: config
+                .property_or_default("email.encryption.wkd", "false")
+                .unwrap_or(false),
+            tnef_convert: config
+                .property_or_default("email.tnef.convert", "false")
+                .unwrap_or(false),
+            tnef_keep_original: config
+                .property_or_default("email.tnef.keep-original", "false")
+                .unwrap_or(false),
+            mdn_suppress_external: config
+                .property_or_default("email.mdn.suppress-external", "false")
+                .unwrap_or(false),
+            mdn_auto_send: config
+                .property_or_default("email.mdn.auto-send", "false")
+                .unwrap_or(false),
+            forwarding_enable: config
+                .property_or_default("email.forwarding.enable", "true")
+                .unwrap_or(true),
+            forwarding_max_recipients: config
+                .property_or_default::<usize>("email.forwarding.max-recipients", "5")
+                .unwrap_or(5),
+            forwarding_max_hops: config
+                .property_or_default::<usize>("email.forwarding.max-hops", "25")
+                .unwrap_or(25),
+            subaddress_folder: config
+                .property_or_default("email.sub-addressing.folder", "false")
+                .unwrap_or(false),
+            subaddress_folder_create: config
+                .property_or_default("email.sub-addressing.create-folder", "false")
+                .unwrap_or(false),
+            subaddress_folder_max: config
+                .property_or_default::<usize>("email.sub-addressing.max-folders", "50")
+                .unwrap_or(50),
+            disposable_alias_enable: config
+                .property_or_default("email.disposable-alias.enable", "false")
+                .unwrap_or(false),
+            disposable_alias_max: config
+                .property_or_default::<usize>("email.disposable-alias.max-aliases", "20")
+                .unwrap_or(20),
+            identity_max_identities: config
+                .property_or_default::<usize>("email.identity.max-identities", "100")
+                .unwrap_or(100),
+            identity_max_signature_size: config
+                .property_or_default::<usize>("email.identity.max-signature-size", "8192")
+                .unwrap_or(8192),
+            autocrypt_enable: config
+                .property_or_default("email.autocrypt.enable", "true")
+                .unwrap_or(true),
+            autocrypt_max_peers: config
+                .property_or_default::<usize>("email.autocrypt.max-peers", "500")
+                .unwrap_or(500),
+            autocrypt_add_header: config
+                .property_or_default("email.autocrypt.add-header", "false")
+                .unwrap_or(false),
             http_use_forwarded: config.property("http.use-x-forwarded").unwrap_or(false),
+            http_image_proxy: config
+                .property_or_default::<bool>("http.image-proxy.enable", "false")
+                .unwrap_or(false)
+                .then(|| ImageProxy {
+                    timeout: config
+                        .property_or_default("http.image-proxy.timeout", "10s")
+                        .unwrap_or_else(|| Duration::from_secs(10)),
+                    max_size: config
+                        .property_or_default("http.image-proxy.max-size", "5242880")
+                        .unwrap_or(5 * 1024 * 1024),
+                    cache_ttl: config
+                        .property_or_default::<Duration>("http.image-proxy.cache-ttl", "1d")
+                        .map(|d| d.as_secs())
+                        .unwrap_or(24 * 60 * 60),
+                }),
             http_headers,
             push_attempt_interval: config
                 .property_or_default("jmap.push.attempts.interval", "1m")
@@ -355,6 +488,26 @@ impl JmapConfig {
             }),
             default_folders,
             shared_folder,
+            saved_search_folder: config
+                .value("email.saved-searches.folder")
+                .map(|name| name.trim().trim_matches('/'))
+                .filter(|name| !name.is_empty())
+                .unwrap_or("Saved Searches")
+                .to_string(),
+            saved_search_max: config
+                .property_or_default::<usize>("email.saved-searches.max-per-account", "25")
+                .unwrap_or(25),
+            scheduled_send_folder: config
+                .property_or_default::<bool>("email.scheduled-send.enable", "true")
+                .unwrap_or(true)
+                .then(|| {
+                    config
+                        .value("email.scheduled-send.folder")
+                        .map(|name| name.trim().trim_matches('/'))
+                        .filter(|name| !name.is_empty())
+                        .unwrap_or("Scheduled")
+                        .to_string()
+                }),
         };
 
         // Add capabilities
@@ -106,6 +106,18 @@ impl Core {
                 }
             })
             .unwrap_or_default();
+        let mut queue = config
+            .value("storage.queue")
+            .filter(|id| config.value("storage.blob") != Some(*id))
+            .map(|id| id.to_string())
+            .and_then(|id| {
+                if let Some(store) = stores.blob_stores.get(&id) {
+                    store.clone().into()
+                } else {
+                    config.new_parse_error("storage.queue", format!("Blob store {id:?} not found"));
+                    None
+                }
+            });
         let mut lookup = config
             .value_require("storage.lookup")
             .map(|id| id.to_string())
@@ -180,6 +192,7 @@ impl Core {
         {
             data = Store::default();
             blob = BlobStore::default();
+            queue = None;
             lookup = InMemoryStore::default();
             fts = FtsStore::default();
             config.new_build_error(
@@ -208,6 +221,7 @@ impl Core {
             storage: Storage {
                 data,
                 blob,
+                queue,
                 fts,
                 lookup,
                 pubsub,
@@ -219,6 +233,7 @@ impl Core {
                 lookups: stores.in_memory_stores,
                 blobs: stores.blob_stores,
                 ftss: stores.fts_stores,
+                http_queries: stores.http_queries,
             },
         }
     }
@@ -6,10 +6,13 @@
 
 use std::time::Duration;
 
-use crate::expr::{if_block::IfBlock, tokenizer::TokenMap};
-use ahash::AHashSet;
+use crate::{
+    config::server::ServerProtocol,
+    expr::{if_block::IfBlock, tokenizer::TokenMap},
+};
+use ahash::{AHashMap, AHashSet};
 
-use utils::config::{Config, Rate};
+use utils::config::{Config, Rate, utils::ParseValue};
 
 use super::*;
 
@@ -24,6 +27,63 @@ pub struct Network {
     pub http_response_url: IfBlock,
     pub http_allowed_endpoint: IfBlock,
     pub asn_geo_lookup: AsnGeoLookupConfig,
+    pub connection_limits: ConnectionLimits,
+    pub login_alerts: Option<LoginAlerts>,
+    pub branding: AHashMap<String, Branding>,
+    pub quota_warnings: QuotaWarnings,
+}
+
+#[derive(Clone, Default)]
+pub struct ConnectionLimits {
+    pub imap: ProtocolConnectionLimits,
+    pub pop3: ProtocolConnectionLimits,
+    pub managesieve: ProtocolConnectionLimits,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct ProtocolConnectionLimits {
+    pub per_account: Option<u64>,
+    pub per_ip: Option<u64>,
+    pub action: ConnectionLimitAction,
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum ConnectionLimitAction {
+    #[default]
+    Reject,
+    Log,
+}
+
+#[derive(Clone)]
+pub struct LoginAlerts {
+    pub domains: AHashSet<String>,
+    pub deliver_to: LoginAlertDelivery,
+    pub known_device_expiry: Duration,
+    pub from_name: String,
+    pub from_email: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct QuotaWarnings {
+    pub soft_limit: u64,
+    pub thresholds: Vec<u64>,
+    pub period: Duration,
+    pub from_name: String,
+    pub from_email: Option<String>,
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Branding {
+    pub name: Option<String>,
+    pub locale: Option<String>,
+    pub footer: Option<String>,
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub enum LoginAlertDelivery {
+    #[default]
+    Inbox,
+    Recovery,
 }
 
 #[derive(Clone)]
@@ -102,6 +162,10 @@ impl Default for Network {
             ),
             http_allowed_endpoint: IfBlock::new::<()>("http.allowed-endpoint", [], "200"),
             asn_geo_lookup: AsnGeoLookupConfig::Disabled,
+            connection_limits: ConnectionLimits::default(),
+            login_alerts: None,
+            branding: AHashMap::new(),
+            quota_warnings: QuotaWarnings::default(),
             server_name: Default::default(),
             report_domain: Default::default(),
             roles: ClusterRoles {
@@ -157,6 +221,146 @@ impl ContactForm {
     }
 }
 
+impl LoginAlerts {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default::<bool>("authentication.login-alert.enable", "false")
+            .unwrap_or_default()
+        {
+            return None;
+        }
+
+        Some(LoginAlerts {
+            domains: config
+                .values("authentication.login-alert.domains")
+                .map(|(_, domain)| domain.trim().to_lowercase())
+                .collect(),
+            deliver_to: config
+                .property_or_default("authentication.login-alert.deliver-to", "inbox")
+                .unwrap_or_default(),
+            known_device_expiry: config
+                .property_or_default("authentication.login-alert.known-device-expiry", "90d")
+                .unwrap_or(Duration::from_secs(90 * 86400)),
+            from_name: config
+                .value("authentication.login-alert.from-name")
+                .unwrap_or("Security Notification")
+                .to_string(),
+            from_email: config
+                .value("authentication.login-alert.from-email")
+                .map(|email| email.trim().to_lowercase()),
+        })
+    }
+
+    pub fn is_enabled_for(&self, domain: &str) -> bool {
+        self.domains.is_empty() || self.domains.contains(domain)
+    }
+}
+
+impl QuotaWarnings {
+    pub fn parse(config: &mut Config) -> Self {
+        // Warning emails are sent when usage crosses one of the configured percentages
+        let thresholds = if config
+            .property_or_default::<bool>("quota.warning.enable", "false")
+            .unwrap_or_default()
+        {
+            let mut thresholds = config
+                .properties::<u64>("quota.warning.thresholds")
+                .into_iter()
+                .map(|(_, threshold)| threshold.clamp(1, 100))
+                .collect::<Vec<_>>();
+            if thresholds.is_empty() {
+                thresholds = vec![80, 90, 95];
+            }
+            thresholds.sort_unstable();
+            thresholds.dedup();
+            thresholds
+        } else {
+            vec![]
+        };
+
+        QuotaWarnings {
+            soft_limit: config
+                .property_or_default::<u64>("quota.soft-limit", "90")
+                .unwrap_or(90)
+                .clamp(1, 100),
+            thresholds,
+            period: config
+                .property_or_default("quota.warning.period", "7d")
+                .unwrap_or(Duration::from_secs(7 * 86400)),
+            from_name: config
+                .value("quota.warning.from-name")
+                .unwrap_or("Quota Notification")
+                .to_string(),
+            from_email: config
+                .value("quota.warning.from-email")
+                .map(|email| email.trim().to_lowercase()),
+        }
+    }
+
+    // Percentage of the hard limit in use, if the account has a quota
+    pub fn usage_percent(used: u64, quota: u64) -> Option<u64> {
+        (quota != 0).then(|| used.saturating_mul(100) / quota)
+    }
+
+    // Accounts with their own soft quota are not subject to the default percentage
+    pub fn soft_limit(&self, quota: u64, soft_quota: Option<u64>) -> u64 {
+        match soft_quota {
+            Some(soft_quota) if soft_quota != 0 => soft_quota.min(quota),
+            _ => (quota as u128 * self.soft_limit as u128 / 100) as u64,
+        }
+    }
+}
+
+impl Default for QuotaWarnings {
+    fn default() -> Self {
+        QuotaWarnings {
+            soft_limit: 90,
+            thresholds: vec![],
+            period: Duration::from_secs(7 * 86400),
+            from_name: "Quota Notification".to_string(),
+            from_email: None,
+        }
+    }
+}
+
+impl Branding {
+    pub fn parse(config: &mut Config) -> AHashMap<String, Self> {
+        let mut branding = AHashMap::new();
+        for domain in config.sub_keys_with_suffixes("branding", &[".name", ".locale", ".footer"]) {
+            let value = |config: &mut Config, key: &str| {
+                config
+                    .value(("branding", domain.as_str(), key))
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+            };
+            let name = value(config, "name");
+            let locale = value(config, "locale");
+            let footer = value(config, "footer");
+
+            branding.insert(
+                domain.to_lowercase(),
+                Branding {
+                    name,
+                    locale,
+                    footer,
+                },
+            );
+        }
+
+        branding
+    }
+}
+
+impl ParseValue for LoginAlertDelivery {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "inbox" => Ok(LoginAlertDelivery::Inbox),
+            "recovery" => Ok(LoginAlertDelivery::Recovery),
+            _ => Err(format!("Invalid login alert delivery {value:?}.")),
+        }
+    }
+}
+
 impl FieldOrDefault {
     pub fn parse(config: &mut Config, key: &str, default: &str) -> Self {
         FieldOrDefault {
@@ -205,6 +409,10 @@ impl Network {
             security: Security::parse(config),
             contact_form: ContactForm::parse(config),
             asn_geo_lookup: AsnGeoLookupConfig::parse(config).unwrap_or_default(),
+            connection_limits: ConnectionLimits::parse(config),
+            login_alerts: LoginAlerts::parse(config),
+            branding: Branding::parse(config),
+            quota_warnings: QuotaWarnings::parse(config),
             ..Default::default()
         };
         let token_map = &TokenMap::default().with_variables(HTTP_VARS);
@@ -250,6 +458,58 @@ impl Network {
 
         network
     }
+
+    // Domains without a branding block of their own use the "default" one, if any
+    pub fn branding(&self, domain: &str) -> Option<&Branding> {
+        self.branding
+            .get(domain)
+            .or_else(|| self.branding.get("default"))
+    }
+}
+
+impl ConnectionLimits {
+    pub fn parse(config: &mut Config) -> Self {
+        ConnectionLimits {
+            imap: ProtocolConnectionLimits::parse(config, "imap"),
+            pop3: ProtocolConnectionLimits::parse(config, "pop3"),
+            managesieve: ProtocolConnectionLimits::parse(config, "managesieve"),
+        }
+    }
+
+    pub fn get(&self, protocol: ServerProtocol) -> Option<&ProtocolConnectionLimits> {
+        match protocol {
+            ServerProtocol::Imap => Some(&self.imap),
+            ServerProtocol::Pop3 => Some(&self.pop3),
+            ServerProtocol::ManageSieve => Some(&self.managesieve),
+            _ => None,
+        }
+    }
+}
+
+impl ProtocolConnectionLimits {
+    fn parse(config: &mut Config, protocol: &str) -> Self {
+        ProtocolConnectionLimits {
+            per_account: config
+                .property(("server.connection-limit", protocol, "per-account"))
+                .filter(|limit| *limit > 0),
+            per_ip: config
+                .property(("server.connection-limit", protocol, "per-ip"))
+                .filter(|limit| *limit > 0),
+            action: config
+                .property_or_default(("server.connection-limit", protocol, "action"), "reject")
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl ParseValue for ConnectionLimitAction {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "reject" => Ok(ConnectionLimitAction::Reject),
+            "log" => Ok(ConnectionLimitAction::Log),
+            _ => Err(format!("Invalid connection limit action {value:?}.")),
+        }
+    }
 }
 
 impl AsnGeoLookupConfig {
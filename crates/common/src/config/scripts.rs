@@ -6,15 +6,16 @@
 
 use std::{sync::Arc, time::Duration};
 
-use ahash::AHashMap;
+use ahash::{AHashMap, AHashSet};
 use sieve::{Compiler, Runtime, Sieve, compiler::grammar::Capability};
 use store::Stores;
-use utils::config::Config;
+use utils::config::{Config, Rate};
 
 use crate::{
-    VERSION_PUBLIC,
+    USER_AGENT, VERSION_PUBLIC,
     scripts::{
         functions::{register_functions_trusted, register_functions_untrusted},
+        notify::PublicResolver,
         plugins::RegisterSievePlugins,
     },
 };
@@ -31,8 +32,41 @@ pub struct Scripting {
     pub sign: IfBlock,
     pub trusted_scripts: AHashMap<String, Arc<Sieve>>,
     pub untrusted_scripts: AHashMap<String, Arc<Sieve>>,
+    pub delivery_layers: SieveLayers,
+    pub notify: SieveNotify,
 }
 
+#[derive(Debug, Clone)]
+pub struct SieveNotify {
+    pub rate: Option<Rate>,
+    pub allowed_hosts: AHashSet<String>,
+    pub client: Option<reqwest::Client>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SieveLayers {
+    pub global: SieveLayer,
+    pub domains: AHashMap<String, SieveLayer>,
+    pub scripts: AHashMap<String, Arc<Sieve>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SieveLayer {
+    pub before: Option<String>,
+    pub after: Option<String>,
+    pub wrapper: Option<SieveLayerWrapper>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SieveLayerWrapper {
+    pub with_script: Arc<Sieve>,
+    pub without_script: Arc<Sieve>,
+}
+
+// Names used by the layer wrappers to include the layers and the user's active script
+pub const SIEVE_LAYER_PREFIX: &str = "layer:";
+pub const SIEVE_LAYER_ACTIVE_SCRIPT: &str = ":active";
+
 impl Scripting {
     pub async fn parse(config: &mut Config, stores: &Stores) -> Self {
         // Parse untrusted compiler
@@ -311,6 +345,12 @@ impl Scripting {
             }
         }
 
+        // Parse delivery layers
+        let delivery_layers = SieveLayers::parse(config, &untrusted_compiler);
+
+        // Parse notification settings
+        let notify = SieveNotify::parse(config);
+
         let token_map = TokenMap::default().with_variables(SMTP_RCPT_TO_VARS);
 
         Scripting {
@@ -345,6 +385,8 @@ impl Scripting {
             ),
             untrusted_scripts,
             trusted_scripts,
+            delivery_layers,
+            notify,
         }
     }
 }
@@ -372,6 +414,8 @@ impl Default for Scripting {
             ),
             untrusted_scripts: AHashMap::new(),
             trusted_scripts: AHashMap::new(),
+            delivery_layers: SieveLayers::default(),
+            notify: SieveNotify::default(),
         }
     }
 }
@@ -388,6 +432,237 @@ impl Clone for Scripting {
             sign: self.sign.clone(),
             trusted_scripts: self.trusted_scripts.clone(),
             untrusted_scripts: self.untrusted_scripts.clone(),
+            delivery_layers: self.delivery_layers.clone(),
+            notify: self.notify.clone(),
         }
     }
 }
+
+impl SieveNotify {
+    pub fn parse(config: &mut Config) -> Self {
+        let rate = config
+            .property_or_default::<Option<Rate>>("sieve.untrusted.notify.rate", "10/1h")
+            .unwrap_or_default();
+        let allowed_hosts = config
+            .values("sieve.untrusted.notify.allowed-hosts")
+            .map(|(_, host)| host.trim().to_lowercase())
+            .collect::<AHashSet<_>>();
+
+        // Webhooks are only enabled for explicitly allowed hosts
+        let client = if !allowed_hosts.is_empty() {
+            let timeout = config
+                .property_or_default("sieve.untrusted.notify.timeout", "10s")
+                .unwrap_or(Duration::from_secs(10));
+            let allow_invalid_certs = config
+                .property_or_default("sieve.untrusted.notify.allow-invalid-certs", "false")
+                .unwrap_or_default();
+            reqwest::Client::builder()
+                .user_agent(USER_AGENT)
+                .timeout(timeout)
+                .danger_accept_invalid_certs(allow_invalid_certs)
+                .redirect(reqwest::redirect::Policy::none())
+                .dns_resolver(Arc::new(PublicResolver))
+                .build()
+                .map_err(|err| {
+                    config.new_build_error(
+                        "sieve.untrusted.notify",
+                        format!("Failed to build HTTP client: {err}"),
+                    )
+                })
+                .ok()
+        } else {
+            None
+        };
+
+        SieveNotify {
+            rate,
+            allowed_hosts,
+            client,
+        }
+    }
+}
+
+impl Default for SieveNotify {
+    fn default() -> Self {
+        SieveNotify {
+            rate: Some(Rate {
+                requests: 10,
+                period: Duration::from_secs(3600),
+            }),
+            allowed_hosts: AHashSet::new(),
+            client: None,
+        }
+    }
+}
+
+impl SieveLayers {
+    // Layers reference scripts defined in sieve.trusted.scripts. As they run in the
+    // same instance as the user's script, they are compiled for the untrusted runtime.
+    pub fn parse(config: &mut Config, compiler: &Compiler) -> Self {
+        let mut layers = SieveLayers {
+            global: SieveLayer::parse(config, "sieve.trusted.layers"),
+            domains: AHashMap::new(),
+            scripts: AHashMap::new(),
+        };
+
+        for domain in
+            config.sub_keys_with_suffixes("sieve.trusted.layers.domain", &[".before", ".after"])
+        {
+            let layer = SieveLayer::parse(config, &format!("sieve.trusted.layers.domain.{domain}"));
+            if layer.before.is_some() || layer.after.is_some() {
+                layers.domains.insert(domain.to_lowercase(), layer);
+            }
+        }
+
+        // Compile the referenced scripts
+        for layer in std::iter::once(&mut layers.global).chain(layers.domains.values_mut()) {
+            for script in [&mut layer.before, &mut layer.after] {
+                let Some(id) = script.as_deref() else {
+                    continue;
+                };
+                if layers.scripts.contains_key(id) {
+                    continue;
+                }
+                let key = ("sieve.trusted.scripts", id, "contents");
+                let result = match config.value(key) {
+                    Some(contents) => compiler
+                        .compile(contents.as_bytes())
+                        .map_err(|err| format!("Failed to compile Sieve layer: {err}")),
+                    None => Err(format!(
+                        "Sieve script {id:?} does not exist in sieve.trusted.scripts"
+                    )),
+                };
+                match result {
+                    Ok(compiled) => {
+                        layers.scripts.insert(id.to_string(), compiled.into());
+                    }
+                    Err(err) => {
+                        config.new_build_error(key, err);
+                        *script = None;
+                    }
+                }
+            }
+        }
+
+        // Precompile the wrappers, each domain layer runs inside the global one
+        let compiler = compiler.clone().with_max_includes(5);
+        let global = layers.global.clone();
+        layers.global.wrapper = SieveLayerWrapper::compile(config, &compiler, &global, None);
+        for layer in layers.domains.values_mut() {
+            let wrapper = SieveLayerWrapper::compile(config, &compiler, &global, Some(layer));
+            layer.wrapper = wrapper;
+        }
+
+        layers
+    }
+
+    pub fn wrapper(&self, rcpt_domain: &str, has_script: bool) -> Option<&Arc<Sieve>> {
+        self.domains
+            .get(rcpt_domain)
+            .unwrap_or(&self.global)
+            .wrapper
+            .as_ref()
+            .map(|wrapper| {
+                if has_script {
+                    &wrapper.with_script
+                } else {
+                    &wrapper.without_script
+                }
+            })
+    }
+
+    pub fn applies_to(&self, rcpt_domain: &str) -> bool {
+        self.wrapper(rcpt_domain, false).is_some()
+    }
+}
+
+impl SieveLayerWrapper {
+    fn compile(
+        config: &mut Config,
+        compiler: &Compiler,
+        global: &SieveLayer,
+        domain: Option<&SieveLayer>,
+    ) -> Option<Self> {
+        let mut wrappers = [true, false].into_iter().map(|has_script| {
+            compiler
+                .compile(build_wrapper(global, domain, has_script)?.as_bytes())
+                .map(Arc::new)
+                .map_err(|err| {
+                    config.new_build_error(
+                        "sieve.trusted.layers",
+                        format!("Failed to compile Sieve layers: {err}"),
+                    );
+                })
+                .ok()
+        });
+
+        Some(SieveLayerWrapper {
+            with_script: wrappers.next()??,
+            without_script: wrappers.next()??,
+        })
+    }
+}
+
+impl SieveLayer {
+    fn parse(config: &mut Config, prefix: &str) -> Self {
+        let mut layer = SieveLayer::default();
+
+        for (key, value) in [("before", &mut layer.before), ("after", &mut layer.after)] {
+            *value = config.value((prefix, key)).map(|v| v.trim().to_lowercase());
+        }
+
+        layer
+    }
+}
+
+// Builds a script that runs the layers in order global -> domain -> user -> domain -> global.
+// Layers are included so they share the implicit keep, and a "stop" in any of them ends
+// processing of the remaining layers.
+fn build_wrapper(
+    global: &SieveLayer,
+    domain: Option<&SieveLayer>,
+    has_script: bool,
+) -> Option<String> {
+    let mut includes = Vec::with_capacity(5);
+    for script in [
+        global.before.as_deref(),
+        domain.and_then(|d| d.before.as_deref()),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        includes.push(("global", SIEVE_LAYER_PREFIX, script));
+    }
+    if includes.is_empty() && global.after.is_none() && domain.is_none_or(|d| d.after.is_none()) {
+        return None;
+    }
+    if has_script {
+        includes.push(("personal", "", SIEVE_LAYER_ACTIVE_SCRIPT));
+    }
+    for script in [
+        domain.and_then(|d| d.after.as_deref()),
+        global.after.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        includes.push(("global", SIEVE_LAYER_PREFIX, script));
+    }
+
+    let mut wrapper = String::from("require \"include\";\r\n");
+    for (location, prefix, script) in includes {
+        wrapper.push_str("include :");
+        wrapper.push_str(location);
+        wrapper.push_str(" \"");
+        wrapper.push_str(prefix);
+        for ch in script.chars() {
+            if matches!(ch, '"' | '\\') {
+                wrapper.push('\\');
+            }
+            wrapper.push(ch);
+        }
+        wrapper.push_str("\";\r\n");
+    }
+
+    Some(wrapper)
+}
@@ -20,7 +20,7 @@ use std::{
     time::Duration,
 };
 use throttle::parse_queue_rate_limiter_key;
-use utils::config::{Config, utils::ParseValue};
+use utils::config::{Config, Rate, utils::ParseValue};
 
 #[derive(
     Debug,
@@ -51,10 +51,17 @@ pub struct QueueConfig {
     // DSN
     pub dsn: Dsn,
 
+    // Held messages
+    pub moderation: Moderation,
+
     // Rate limits
     pub inbound_limiters: QueueRateLimiters,
     pub outbound_limiters: QueueRateLimiters,
     pub quota: QueueQuotas,
+    pub providers: Vec<ProviderProfile>,
+
+    // Domains that are never delivered to without verified TLS
+    pub require_tls_domains: Vec<String>,
 
     // Strategies
     pub queue_strategy: AHashMap<String, QueueStrategy>,
@@ -76,6 +83,22 @@ pub struct MxConfig {
     pub max_mx: usize,
     pub max_multi_homed: usize,
     pub ip_lookup_strategy: IpLookupStrategy,
+    pub proxy: Option<ProxyConfig>,
+}
+
+#[derive(Clone, Hash, PartialEq, Eq)]
+pub struct ProxyConfig {
+    pub protocol: ProxyProtocol,
+    pub address: String,
+    pub port: u16,
+    pub auth: Option<Credentials<String>>,
+    pub remote_dns: bool,
+}
+
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum ProxyProtocol {
+    Socks5,
+    Http,
 }
 
 #[derive(Clone)]
@@ -85,6 +108,14 @@ pub struct Dsn {
     pub sign: IfBlock,
 }
 
+#[derive(Clone)]
+pub struct Moderation {
+    pub address: IfBlock,
+    pub url: String,
+    pub expiry: u64,
+    pub release_attempts: u32,
+}
+
 #[derive(Clone, Debug)]
 pub struct VirtualQueue {
     pub threads: usize,
@@ -118,6 +149,7 @@ pub enum QueueExpiry {
 pub struct TlsStrategy {
     pub dane: RequireOptional,
     pub mta_sts: RequireOptional,
+    pub mta_sts_fallback: MtaStsFallback,
     pub tls: RequireOptional,
     pub allow_invalid_certs: bool,
 
@@ -130,6 +162,7 @@ pub struct ConnectionStrategy {
     pub source_ipv4: Vec<IpAndHost>,
     pub source_ipv6: Vec<IpAndHost>,
     pub ehlo_hostname: Option<String>,
+    pub happy_eyeballs: Option<Duration>,
 
     pub timeout_connect: Duration,
     pub timeout_greeting: Duration,
@@ -159,6 +192,18 @@ pub struct QueueQuotas {
     pub rcpt_domain: Vec<QueueQuota>,
 }
 
+#[derive(Clone)]
+pub struct ProviderProfile {
+    pub id: String,
+    pub mx: Vec<String>,
+    pub rate: Option<Rate>,
+    pub warmup_start: u64,
+    pub warmup: Vec<Rate>,
+    pub backoff_patterns: Vec<String>,
+    pub backoff_initial: u64,
+    pub backoff_max: u64,
+}
+
 #[derive(Clone)]
 pub struct QueueQuota {
     pub id: String,
@@ -176,6 +221,7 @@ pub struct RelayConfig {
     pub auth: Option<Credentials<String>>,
     pub tls_implicit: bool,
     pub tls_allow_invalid_certs: bool,
+    pub proxy: Option<ProxyConfig>,
 }
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -186,6 +232,15 @@ pub enum RequireOptional {
     Disable,
 }
 
+// Behavior when a required MTA-STS policy can't be fetched and none is cached
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MtaStsFallback {
+    #[default]
+    EnforceCached,
+    Dane,
+    Deliver,
+}
+
 impl Default for QueueConfig {
     fn default() -> Self {
         Self {
@@ -234,9 +289,17 @@ impl Default for QueueConfig {
                     "['rsa-' + config_get('report.domain'), 'ed25519-' + config_get('report.domain')]",
                 ),
             },
+            moderation: Moderation {
+                address: IfBlock::empty("queue.moderation.address"),
+                url: "https://localhost/queue/moderate".to_string(),
+                release_attempts: 10,
+                expiry: 7 * 24 * 60 * 60,
+            },
             inbound_limiters: QueueRateLimiters::default(),
             outbound_limiters: QueueRateLimiters::default(),
             quota: QueueQuotas::default(),
+            providers: Default::default(),
+            require_tls_domains: Default::default(),
             queue_strategy: Default::default(),
             virtual_queues: Default::default(),
             connection_strategy: Default::default(),
@@ -269,12 +332,38 @@ impl QueueConfig {
                 &sender_vars,
             ),
             (&mut queue.dsn.sign, "report.dsn.sign", &sender_vars),
+            (
+                &mut queue.moderation.address,
+                "queue.moderation.address",
+                &sender_vars,
+            ),
         ] {
             if let Some(if_block) = IfBlock::try_parse(config, key, token_map) {
                 *value = if_block;
             }
         }
 
+        // Parse moderation settings
+        queue.moderation.url = if let Some(url) = config
+            .value("queue.moderation.url")
+            .map(|v| v.trim().trim_end_matches('/'))
+            .filter(|v| !v.is_empty())
+        {
+            url.to_string()
+        } else {
+            format!(
+                "https://{}/queue/moderate",
+                config.value("server.hostname").unwrap_or("localhost")
+            )
+        };
+        queue.moderation.expiry = config
+            .property_or_default::<Duration>("queue.moderation.expiry", "7d")
+            .map(|d| d.as_secs())
+            .unwrap_or(7 * 24 * 60 * 60);
+        queue.moderation.release_attempts = config
+            .property_or_default::<u32>("queue.moderation.release-attempts", "10")
+            .unwrap_or(10);
+
         // Parse strategies
         queue.virtual_queues = parse_virtual_queues(config);
         queue.queue_strategy = parse_queue_strategies(config, &queue.virtual_queues);
@@ -286,8 +375,140 @@ impl QueueConfig {
         queue.inbound_limiters = parse_inbound_rate_limiters(config);
         queue.outbound_limiters = parse_outbound_rate_limiters(config);
         queue.quota = parse_queue_quota(config);
+        queue.providers = config
+            .sub_keys("queue.provider", ".mx")
+            .into_iter()
+            .filter_map(|id| parse_provider_profile(config, &id))
+            .collect();
+        queue.require_tls_domains = config
+            .values("queue.require-tls.domains")
+            .map(|(_, v)| v.trim().trim_end_matches('.').to_ascii_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect();
         queue
     }
+
+    pub fn provider_profile(&self, mx: &str) -> Option<&ProviderProfile> {
+        self.providers.iter().find(|profile| profile.matches_mx(mx))
+    }
+
+    pub fn is_tls_required_for(&self, domain: &str) -> bool {
+        if self.require_tls_domains.is_empty() {
+            return false;
+        }
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        self.require_tls_domains.iter().any(|pattern| {
+            if let Some(suffix) = pattern.strip_prefix('*') {
+                domain.ends_with(suffix)
+            } else {
+                domain == *pattern
+            }
+        })
+    }
+}
+
+impl ProviderProfile {
+    pub fn matches_mx(&self, mx: &str) -> bool {
+        let mx = mx.trim_end_matches('.').to_ascii_lowercase();
+        self.mx.iter().any(|pattern| {
+            if let Some(suffix) = pattern.strip_prefix('*') {
+                mx.ends_with(suffix)
+            } else {
+                mx == *pattern
+            }
+        })
+    }
+
+    // Returns the rate allowed by the warm-up schedule, or the
+    // steady-state rate once the schedule has been completed
+    pub fn current_rate(&self, now: u64) -> Option<&Rate> {
+        if !self.warmup.is_empty() {
+            let day = now.saturating_sub(self.warmup_start) / 86400;
+            if let Some(rate) = self.warmup.get(day as usize) {
+                return Some(rate);
+            }
+        }
+        self.rate.as_ref()
+    }
+
+    pub fn is_backoff_response(&self, response: &str) -> bool {
+        let response = response.to_ascii_lowercase();
+        self.backoff_patterns
+            .iter()
+            .any(|pattern| response.contains(pattern.as_str()))
+    }
+}
+
+fn parse_provider_profile(config: &mut Config, id: &str) -> Option<ProviderProfile> {
+    let mx = config
+        .values(("queue.provider", id, "mx"))
+        .map(|(_, v)| v.trim().trim_end_matches('.').to_ascii_lowercase())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>();
+    if mx.is_empty() {
+        config.new_parse_error(
+            ("queue.provider", id, "mx"),
+            "At least one MX host pattern must be specified.".to_string(),
+        );
+        return None;
+    }
+
+    let warmup = config
+        .properties::<Rate>(("queue.provider", id, "warmup.schedule"))
+        .into_iter()
+        .map(|(_, rate)| rate)
+        .collect::<Vec<_>>();
+    let warmup_start = if !warmup.is_empty() {
+        let start = config.value_require(("queue.provider", id, "warmup.start"))?;
+        match mail_parser::DateTime::parse_rfc3339(start) {
+            Some(start) => start.to_timestamp() as u64,
+            None => {
+                let err = format!("Invalid warm-up start date {start:?}.");
+                config.new_parse_error(("queue.provider", id, "warmup.start"), err);
+                return None;
+            }
+        }
+    } else {
+        0
+    };
+
+    let mut backoff_patterns = config
+        .values(("queue.provider", id, "backoff.patterns"))
+        .map(|(_, v)| v.trim().to_ascii_lowercase())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>();
+    if backoff_patterns.is_empty() {
+        backoff_patterns = [
+            "rate limit",
+            "rate-limit",
+            "unusual rate",
+            "too many",
+            "throttl",
+            "4.7.28",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    }
+
+    Some(ProviderProfile {
+        id: id.to_string(),
+        mx,
+        rate: config
+            .property::<Rate>(("queue.provider", id, "rate"))
+            .filter(|r| r.requests > 0),
+        warmup_start,
+        warmup,
+        backoff_patterns,
+        backoff_initial: config
+            .property_or_default::<Duration>(("queue.provider", id, "backoff.initial"), "5m")
+            .map(|d| d.as_secs())
+            .unwrap_or(300),
+        backoff_max: config
+            .property_or_default::<Duration>(("queue.provider", id, "backoff.max"), "4h")
+            .map(|d| d.as_secs())
+            .unwrap_or(4 * 3600),
+    })
 }
 
 fn parse_queue_strategies(
@@ -429,6 +650,7 @@ fn parse_route(config: &mut Config, id: &str) -> Option<RoutingStrategy> {
             tls_allow_invalid_certs: config
                 .property(("queue.route", id, "tls.allow-invalid-certs"))
                 .unwrap_or(false),
+            proxy: parse_proxy(config, id),
         })
         .into(),
         "local" => RoutingStrategy::Local.into(),
@@ -442,6 +664,7 @@ fn parse_route(config: &mut Config, id: &str) -> Option<RoutingStrategy> {
             ip_lookup_strategy: config
                 .property(("queue.route", id, "ip-lookup"))
                 .unwrap_or(IpLookupStrategy::Ipv4thenIpv6),
+            proxy: parse_proxy(config, id),
         })
         .into(),
         invalid => {
@@ -463,6 +686,7 @@ fn parse_tls_strategies(config: &mut Config) -> AHashMap<String, TlsStrategy> {
             ".starttls",
             ".timeout.tls",
             ".timeout.mta-sts",
+            ".mta-sts-fallback",
         ],
     ) {
         if let Some(strategy) = parse_tls(config, &key) {
@@ -480,6 +704,9 @@ fn parse_tls(config: &mut Config, id: &str) -> Option<TlsStrategy> {
         mta_sts: config
             .property::<RequireOptional>(("queue.tls", id, "mta-sts"))
             .unwrap_or(RequireOptional::Optional),
+        mta_sts_fallback: config
+            .property::<MtaStsFallback>(("queue.tls", id, "mta-sts-fallback"))
+            .unwrap_or_default(),
         tls: config
             .property::<RequireOptional>(("queue.tls", id, "starttls"))
             .unwrap_or(RequireOptional::Optional),
@@ -495,6 +722,43 @@ fn parse_tls(config: &mut Config, id: &str) -> Option<TlsStrategy> {
     })
 }
 
+fn parse_proxy(config: &mut Config, id: &str) -> Option<ProxyConfig> {
+    let protocol = match config.value(("queue.route", id, "proxy.type"))? {
+        "socks5" => ProxyProtocol::Socks5,
+        "http" => ProxyProtocol::Http,
+        invalid => {
+            let details = format!("Invalid proxy type {invalid:?}");
+            config.new_parse_error(("queue.route", id, "proxy.type"), details);
+            return None;
+        }
+    };
+
+    let default_port = if protocol == ProxyProtocol::Socks5 {
+        1080
+    } else {
+        8080
+    };
+
+    Some(ProxyConfig {
+        protocol,
+        address: config.property_require(("queue.route", id, "proxy.address"))?,
+        port: config
+            .property_or_default(("queue.route", id, "proxy.port"), &default_port.to_string())
+            .unwrap_or(default_port),
+        auth: if let (Some(username), Some(secret)) = (
+            config.value(("queue.route", id, "proxy.auth.username")),
+            config.value(("queue.route", id, "proxy.auth.secret")),
+        ) {
+            Credentials::new(username.to_string(), secret.to_string()).into()
+        } else {
+            None
+        },
+        remote_dns: config
+            .property(("queue.route", id, "proxy.remote-dns"))
+            .unwrap_or(false),
+    })
+}
+
 fn parse_connection_strategies(config: &mut Config) -> AHashMap<String, ConnectionStrategy> {
     let mut entries = AHashMap::new();
     for key in config.sub_keys_with_suffixes(
@@ -507,6 +771,7 @@ fn parse_connection_strategies(config: &mut Config) -> AHashMap<String, Connecti
             ".timeout.rcpt-to",
             ".timeout.data",
             ".ehlo-hostname",
+            ".happy-eyeballs",
         ],
     ) {
         if let Some(strategy) = parse_connection(config, &key) {
@@ -537,6 +802,12 @@ fn parse_connection(config: &mut Config, id: &str) -> Option<ConnectionStrategy>
         source_ipv4,
         source_ipv6,
         ehlo_hostname: config.property::<String>(("queue.connection", id, "ehlo-hostname")),
+        happy_eyeballs: config
+            .property_or_default::<Option<Duration>>(
+                ("queue.connection", id, "happy-eyeballs"),
+                "250ms",
+            )
+            .unwrap_or_default(),
         timeout_connect: config
             .property::<Duration>(("queue.connection", id, "timeout.connect"))
             .unwrap_or(Duration::from_secs(5 * 60)),
@@ -761,6 +1032,17 @@ impl ParseValue for RequireOptional {
     }
 }
 
+impl ParseValue for MtaStsFallback {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "enforce-cached" | "enforce" => Ok(MtaStsFallback::EnforceCached),
+            "dane" => Ok(MtaStsFallback::Dane),
+            "deliver" | "none" => Ok(MtaStsFallback::Deliver),
+            _ => Err(format!("Invalid MTA-STS fallback value {:?}.", value,)),
+        }
+    }
+}
+
 impl<'x> TryFrom<Variable<'x>> for RequireOptional {
     type Error = ();
 
@@ -846,6 +1128,18 @@ impl std::fmt::Debug for RelayConfig {
             .field("protocol", &self.protocol)
             .field("tls_implicit", &self.tls_implicit)
             .field("tls_allow_invalid_certs", &self.tls_allow_invalid_certs)
+            .field("proxy", &self.proxy)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for ProxyConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProxyConfig")
+            .field("protocol", &self.protocol)
+            .field("address", &self.address)
+            .field("port", &self.port)
+            .field("remote_dns", &self.remote_dns)
             .finish()
     }
 }
@@ -20,6 +20,7 @@ pub struct ReportConfig {
     pub dkim: Report,
     pub spf: Report,
     pub dmarc: Report,
+    pub dmarc_redaction: FailureRedaction,
     pub dmarc_aggregate: AggregateReport,
     pub tls: AggregateReport,
 }
@@ -29,6 +30,13 @@ pub struct ReportAnalysis {
     pub addresses: Vec<AddressMatch>,
     pub forward: bool,
     pub store: Option<Duration>,
+    pub suppress: Option<Duration>,
+}
+
+#[derive(Clone, Copy)]
+pub struct FailureRedaction {
+    pub body: bool,
+    pub recipients: bool,
 }
 
 #[derive(Clone)]
@@ -91,10 +99,24 @@ impl ReportConfig {
                 store: config
                     .property_or_default::<Option<Duration>>("report.analysis.store", "30d")
                     .unwrap_or_default(),
+                suppress: config
+                    .property_or_default::<Option<Duration>>(
+                        "report.analysis.suppress-complainants",
+                        "false",
+                    )
+                    .unwrap_or_default(),
             },
             dkim: Report::parse(config, "dkim", &rcpt_vars),
             spf: Report::parse(config, "spf", &sender_vars),
             dmarc: Report::parse(config, "dmarc", &rcpt_vars),
+            dmarc_redaction: FailureRedaction {
+                body: config
+                    .property_or_default("report.dmarc.redact.body", "true")
+                    .unwrap_or(true),
+                recipients: config
+                    .property_or_default("report.dmarc.redact.recipients", "true")
+                    .unwrap_or(true),
+            },
             dmarc_aggregate: AggregateReport::parse(
                 config,
                 "dmarc",
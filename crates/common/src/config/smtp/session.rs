@@ -7,18 +7,22 @@
 use std::{
     net::{SocketAddr, ToSocketAddrs},
     str::FromStr,
+    sync::Arc,
     time::Duration,
 };
 
 use ahash::AHashSet;
 use base64::{Engine, engine::general_purpose::STANDARD};
+use parking_lot::Mutex;
+
+use regex::Regex;
 
 use hyper::{
     HeaderMap,
     header::{AUTHORIZATION, CONTENT_TYPE, HeaderName, HeaderValue},
 };
 use smtp_proto::*;
-use utils::config::{Config, utils::ParseValue};
+use utils::config::{Config, Rate, ipmask::IpAddrMask, utils::ParseValue};
 
 use crate::{
     config::CONNECTION_VARS,
@@ -36,6 +40,7 @@ pub struct SessionConfig {
     pub transfer_limit: IfBlock,
 
     pub connect: Connect,
+    pub conformance: Conformance,
     pub ehlo: Ehlo,
     pub auth: Auth,
     pub mail: Mail,
@@ -46,6 +51,16 @@ pub struct SessionConfig {
 
     pub milters: Vec<Milter>,
     pub hooks: Vec<MTAHook>,
+    pub dlp: Vec<DlpRule>,
+    pub disclaimers: Vec<Disclaimer>,
+    pub attachment_offload: AttachmentOffload,
+    pub tags: Vec<MessageTag>,
+    pub spamd: Vec<Spamd>,
+    pub rspamd: Vec<Rspamd>,
+    pub bulk: Vec<BulkSender>,
+    pub trusted_relay: TrustedRelay,
+    pub null_sender: NullSender,
+    pub srs: Option<Srs>,
 }
 
 #[derive(Clone)]
@@ -53,6 +68,22 @@ pub struct Connect {
     pub hostname: IfBlock,
     pub script: IfBlock,
     pub greeting: IfBlock,
+    pub greeting_delay: IfBlock,
+}
+
+#[derive(Clone)]
+pub struct Conformance {
+    pub bare_lf: IfBlock,
+    pub pipelining: IfBlock,
+    pub early_talker: IfBlock,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConformanceAction {
+    #[default]
+    Disable,
+    Tag,
+    Reject,
 }
 
 #[derive(Clone)]
@@ -74,6 +105,8 @@ pub struct Extensions {
     pub future_release: IfBlock,
     pub deliver_by: IfBlock,
     pub mt_priority: IfBlock,
+    pub limits: IfBlock,
+    pub prdr: IfBlock,
 }
 
 #[derive(Clone)]
@@ -82,6 +115,7 @@ pub struct Auth {
     pub mechanisms: IfBlock,
     pub require: IfBlock,
     pub must_match_sender: IfBlock,
+    pub must_match_from: IfBlock,
     pub errors_max: IfBlock,
     pub errors_wait: IfBlock,
 }
@@ -106,10 +140,12 @@ pub struct Rcpt {
 
     // Limits
     pub max_recipients: IfBlock,
+    pub max_domains: IfBlock,
 
     // Catch-all and sub-addressing
     pub catch_all: AddressMapping,
     pub subaddressing: AddressMapping,
+    pub subaddressing_separator: IfBlock,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -124,6 +160,7 @@ pub enum AddressMapping {
 pub struct Data {
     pub script: IfBlock,
     pub spam_filter: IfBlock,
+    pub spam_profile: IfBlock,
 
     // Limits
     pub max_messages: IfBlock,
@@ -177,6 +214,197 @@ pub struct MTAHook {
     pub tempfail_on_error: bool,
     pub run_on_stage: AHashSet<Stage>,
     pub max_response_size: usize,
+    pub max_message_size: Option<usize>,
+    pub message_format: HookMessageFormat,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum HookMessageFormat {
+    #[default]
+    Contents,
+    Mime,
+    Raw,
+}
+
+#[derive(Clone)]
+pub struct Spamd {
+    pub enable: IfBlock,
+    pub id: String,
+    pub addrs: Vec<SocketAddr>,
+    pub hostname: String,
+    pub port: u16,
+    pub timeout_connect: Duration,
+    pub timeout_command: Duration,
+    pub user: Option<String>,
+    pub command: SpamdCommand,
+    pub tempfail_on_error: bool,
+    pub max_message_size: usize,
+    pub max_response_size: usize,
+    pub on_spam: SpamdAction,
+    pub reject_score: Option<f64>,
+}
+
+#[derive(Clone)]
+pub struct Rspamd {
+    pub enable: IfBlock,
+    pub id: String,
+    pub url: String,
+    pub learn_url: Option<String>,
+    pub timeout: Duration,
+    pub headers: HeaderMap,
+    pub tls_allow_invalid_certs: bool,
+    pub tempfail_on_error: bool,
+    pub max_message_size: usize,
+    pub max_response_size: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpamdCommand {
+    Check,
+    Symbols,
+    Report,
+    Headers,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpamdAction {
+    Accept,
+    Quarantine,
+    Discard,
+    Reject,
+}
+
+#[derive(Clone)]
+pub struct DlpRule {
+    pub enable: IfBlock,
+    pub id: String,
+    pub matcher: DlpMatcher,
+    pub threshold: usize,
+    pub action: DlpAction,
+}
+
+#[derive(Clone)]
+pub enum DlpMatcher {
+    CreditCard,
+    Iban,
+    Regex {
+        patterns: Vec<Regex>,
+        dictionary: Option<DlpDictionary>,
+    },
+}
+
+// Patterns stored one per line under a key of an in-memory store,
+// compiled again only when the stored value changes
+#[derive(Clone)]
+pub struct DlpDictionary {
+    pub store: String,
+    pub key: String,
+    pub compiled: Arc<Mutex<Option<CompiledDictionary>>>,
+}
+
+// Stored value and the patterns compiled from it
+pub type CompiledDictionary = (String, Arc<Vec<Regex>>);
+
+// Ordered by severity, the most severe action wins when several rules match
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DlpAction {
+    RequireTls,
+    Encrypt,
+    Quarantine,
+    Reject,
+}
+
+#[derive(Clone)]
+pub struct Disclaimer {
+    pub enable: IfBlock,
+    pub id: String,
+    pub text: String,
+    pub html: String,
+    pub on_signed: SignedMessagePolicy,
+}
+
+#[derive(Clone)]
+pub struct MessageTag {
+    pub enable: IfBlock,
+    pub id: String,
+    pub subject: Option<String>,
+    pub headers: Vec<(String, String)>,
+}
+
+#[derive(Clone)]
+pub struct BulkSender {
+    pub enable: IfBlock,
+    pub id: String,
+    pub domains: Vec<String>,
+    pub feedback_id: Option<String>,
+    pub unsubscribe: Option<BulkUnsubscribe>,
+}
+
+// Hosts on these networks may relay without authenticating and their
+// messages are handled like authenticated submissions
+#[derive(Clone, Default)]
+pub struct TrustedRelay {
+    pub networks: Vec<IpAddrMask>,
+    pub remove_headers: Vec<String>,
+}
+
+#[derive(Clone, Default)]
+pub struct NullSender {
+    pub rate: Option<Rate>,
+    pub single_recipient: bool,
+    pub batv: Option<Batv>,
+}
+
+// Bounce Address Tag Validation, return paths are signed as prvs=KDDDSSSSSS=user@domain
+// where K is the id of the signing key, which allows keys to be rotated
+#[derive(Clone)]
+pub struct Batv {
+    pub keys: Vec<(u8, Vec<u8>)>,
+    pub sign_key_id: u8,
+    pub expiry_days: u64,
+    pub require: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatvAddress {
+    Unsigned,
+    Valid(String),
+    Invalid,
+}
+
+// Sender Rewriting Scheme, forwarded return paths are rewritten as SRS0=HHHHHH=TT=domain=user@forwarder
+#[derive(Clone)]
+pub struct Srs {
+    pub key: Vec<u8>,
+    pub expiry_days: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SrsAddress {
+    Unsigned,
+    Valid(String),
+    Invalid,
+}
+
+#[derive(Clone)]
+pub struct BulkUnsubscribe {
+    pub url: String,
+    pub expiry: u64,
+}
+
+#[derive(Clone)]
+pub struct AttachmentOffload {
+    pub enable: IfBlock,
+    pub min_size: IfBlock,
+    pub expiry: IfBlock,
+    pub max_downloads: IfBlock,
+    pub folder: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignedMessagePolicy {
+    Skip,
+    Wrap,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -197,10 +425,19 @@ impl SessionConfig {
         let has_rcpt_vars = TokenMap::default().with_variables(SMTP_RCPT_TO_VARS);
         let mt_priority_vars = has_sender_vars.clone().with_constants::<MtPriority>();
         let mechanisms_vars = has_ehlo_hars.clone().with_constants::<Mechanism>();
+        let conformance_vars = has_conn_vars.clone().with_constants::<ConformanceAction>();
 
         let mut session = SessionConfig::default();
         session.rcpt.catch_all = AddressMapping::parse(config, "session.rcpt.catch-all");
         session.rcpt.subaddressing = AddressMapping::parse(config, "session.rcpt.sub-addressing");
+        if let Some(separator) = IfBlock::try_parse(
+            config,
+            "session.rcpt.sub-addressing-separator",
+            &TokenMap::default()
+                .with_variables_map([("rcpt", V_RECIPIENT), ("rcpt_domain", V_RECIPIENT_DOMAIN)]),
+        ) {
+            session.rcpt.subaddressing_separator = separator;
+        }
         session.milters = config
             .sub_keys("session.milter", ".hostname")
             .into_iter()
@@ -211,7 +448,45 @@ impl SessionConfig {
             .into_iter()
             .filter_map(|id| parse_hooks(config, &id, &has_rcpt_vars))
             .collect();
+        session.spamd = config
+            .sub_keys("session.spamd", ".hostname")
+            .into_iter()
+            .filter_map(|id| parse_spamd(config, &id, &has_rcpt_vars))
+            .collect();
+        session.rspamd = config
+            .sub_keys("session.rspamd", ".url")
+            .into_iter()
+            .filter_map(|id| parse_rspamd(config, &id, &has_rcpt_vars))
+            .collect();
+        session.dlp = config
+            .sub_keys("session.dlp", ".type")
+            .into_iter()
+            .filter_map(|id| parse_dlp_rule(config, &id, &has_rcpt_vars))
+            .collect();
+        session.disclaimers = config
+            .sub_keys("session.disclaimer", ".text")
+            .into_iter()
+            .filter_map(|id| parse_disclaimer(config, &id, &has_rcpt_vars))
+            .collect();
+        session.attachment_offload.folder = config
+            .value("session.attachment-offload.folder")
+            .unwrap_or("Attachments")
+            .trim_matches('/')
+            .to_string();
+        session.tags = config
+            .sub_keys("session.tag", "")
+            .into_iter()
+            .filter_map(|id| parse_message_tag(config, &id, &has_rcpt_vars))
+            .collect();
+        session.bulk = config
+            .sub_keys("session.bulk", ".domains")
+            .into_iter()
+            .filter_map(|id| parse_bulk_sender(config, &id, &has_rcpt_vars))
+            .collect();
         session.mta_sts_policy = Policy::try_parse(config);
+        session.trusted_relay = TrustedRelay::parse(config);
+        session.null_sender = NullSender::parse(config);
+        session.srs = Srs::parse(config);
 
         for (value, key, token_map) in [
             (&mut session.duration, "session.duration", &has_conn_vars),
@@ -236,6 +511,46 @@ impl SessionConfig {
                 "session.connect.greeting",
                 &has_conn_vars,
             ),
+            (
+                &mut session.connect.greeting_delay,
+                "session.connect.greeting-delay",
+                &has_conn_vars,
+            ),
+            (
+                &mut session.conformance.bare_lf,
+                "session.conformance.bare-lf",
+                &conformance_vars,
+            ),
+            (
+                &mut session.conformance.pipelining,
+                "session.conformance.pipelining",
+                &conformance_vars,
+            ),
+            (
+                &mut session.conformance.early_talker,
+                "session.conformance.early-talker",
+                &conformance_vars,
+            ),
+            (
+                &mut session.attachment_offload.enable,
+                "session.attachment-offload.enable",
+                &has_rcpt_vars,
+            ),
+            (
+                &mut session.attachment_offload.min_size,
+                "session.attachment-offload.min-size",
+                &has_rcpt_vars,
+            ),
+            (
+                &mut session.attachment_offload.expiry,
+                "session.attachment-offload.expiry",
+                &has_rcpt_vars,
+            ),
+            (
+                &mut session.attachment_offload.max_downloads,
+                "session.attachment-offload.max-downloads",
+                &has_rcpt_vars,
+            ),
             (
                 &mut session.extensions.pipelining,
                 "session.extensions.pipelining",
@@ -286,6 +601,16 @@ impl SessionConfig {
                 "session.extensions.mt-priority",
                 &mt_priority_vars,
             ),
+            (
+                &mut session.extensions.limits,
+                "session.extensions.limits",
+                &has_conn_vars,
+            ),
+            (
+                &mut session.extensions.prdr,
+                "session.extensions.prdr",
+                &has_sender_vars,
+            ),
             (
                 &mut session.ehlo.script,
                 "session.ehlo.script",
@@ -331,6 +656,11 @@ impl SessionConfig {
                 "session.auth.must-match-sender",
                 &has_sender_vars,
             ),
+            (
+                &mut session.auth.must_match_from,
+                "session.auth.must-match-from",
+                &has_rcpt_vars,
+            ),
             (
                 &mut session.mail.script,
                 "session.mail.script",
@@ -376,6 +706,11 @@ impl SessionConfig {
                 "session.rcpt.max-recipients",
                 &has_sender_vars,
             ),
+            (
+                &mut session.rcpt.max_domains,
+                "session.rcpt.max-domains",
+                &has_sender_vars,
+            ),
             (
                 &mut session.rcpt.rewrite,
                 "session.rcpt.rewrite",
@@ -406,6 +741,11 @@ impl SessionConfig {
                 "session.data.spam-filter",
                 &has_rcpt_vars,
             ),
+            (
+                &mut session.data.spam_profile,
+                "session.data.spam-profile",
+                &has_rcpt_vars,
+            ),
             (
                 &mut session.data.add_received,
                 "session.data.add-headers.received",
@@ -515,6 +855,622 @@ fn parse_milter(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<M
     })
 }
 
+fn parse_spamd(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<Spamd> {
+    let hostname = config
+        .value_require(("session.spamd", id, "hostname"))?
+        .to_string();
+    let port = config
+        .property_or_default(("session.spamd", id, "port"), "783")
+        .unwrap_or(783);
+    Some(Spamd {
+        enable: IfBlock::try_parse(config, ("session.spamd", id, "enable"), token_map)
+            .unwrap_or_else(|| {
+                IfBlock::new::<()>(format!("session.spamd.{id}.enable"), [], "false")
+            }),
+        id: id.to_string(),
+        addrs: format!("{}:{}", hostname, port)
+            .to_socket_addrs()
+            .map_err(|err| {
+                config.new_build_error(
+                    ("session.spamd", id, "hostname"),
+                    format!("Unable to resolve spamd hostname {hostname}: {err}"),
+                )
+            })
+            .ok()?
+            .collect(),
+        hostname,
+        port,
+        timeout_connect: config
+            .property_or_default(("session.spamd", id, "timeout.connect"), "10s")
+            .unwrap_or_else(|| Duration::from_secs(10)),
+        timeout_command: config
+            .property_or_default(("session.spamd", id, "timeout.command"), "30s")
+            .unwrap_or_else(|| Duration::from_secs(30)),
+        user: config
+            .value(("session.spamd", id, "user"))
+            .map(|user| user.to_string()),
+        command: config
+            .property_or_default(("session.spamd", id, "command"), "symbols")
+            .unwrap_or(SpamdCommand::Symbols),
+        tempfail_on_error: config
+            .property_or_default(("session.spamd", id, "options.tempfail-on-error"), "false")
+            .unwrap_or_default(),
+        max_message_size: config
+            .property_or_default(("session.spamd", id, "options.max-message-size"), "512000")
+            .unwrap_or(512000),
+        max_response_size: config
+            .property_or_default(
+                ("session.spamd", id, "options.max-response-size"),
+                "1048576",
+            )
+            .unwrap_or(1048576),
+        on_spam: config
+            .property_or_default(("session.spamd", id, "action.spam"), "accept")
+            .unwrap_or(SpamdAction::Accept),
+        reject_score: config.property(("session.spamd", id, "action.reject-score")),
+    })
+}
+
+fn parse_rspamd(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<Rspamd> {
+    let mut headers = HeaderMap::new();
+    if let Some(password) = config.value(("session.rspamd", id, "password")) {
+        headers.insert(
+            HeaderName::from_static("password"),
+            HeaderValue::from_str(password)
+                .map_err(|err| {
+                    config.new_parse_error(
+                        ("session.rspamd", id, "password"),
+                        format!("Invalid rspamd password: {err}"),
+                    )
+                })
+                .ok()?,
+        );
+    }
+
+    Some(Rspamd {
+        enable: IfBlock::try_parse(config, ("session.rspamd", id, "enable"), token_map)
+            .unwrap_or_else(|| {
+                IfBlock::new::<()>(format!("session.rspamd.{id}.enable"), [], "false")
+            }),
+        id: id.to_string(),
+        url: config
+            .value_require(("session.rspamd", id, "url"))?
+            .trim_end_matches('/')
+            .to_string(),
+        learn_url: config
+            .value(("session.rspamd", id, "learn.url"))
+            .map(|url| url.trim_end_matches('/').to_string()),
+        timeout: config
+            .property_or_default(("session.rspamd", id, "timeout"), "30s")
+            .unwrap_or_else(|| Duration::from_secs(30)),
+        tls_allow_invalid_certs: config
+            .property_or_default(("session.rspamd", id, "allow-invalid-certs"), "false")
+            .unwrap_or_default(),
+        tempfail_on_error: config
+            .property_or_default(("session.rspamd", id, "options.tempfail-on-error"), "false")
+            .unwrap_or_default(),
+        max_message_size: config
+            .property_or_default(
+                ("session.rspamd", id, "options.max-message-size"),
+                "20971520",
+            )
+            .unwrap_or(20971520),
+        max_response_size: config
+            .property_or_default(
+                ("session.rspamd", id, "options.max-response-size"),
+                "1048576",
+            )
+            .unwrap_or(1048576),
+        headers,
+    })
+}
+
+impl TrustedRelay {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut networks = Vec::new();
+        for network in config
+            .values("session.relay.trusted-networks")
+            .map(|(_, v)| IpAddrMask::parse_value(v))
+            .collect::<Vec<_>>()
+        {
+            match network {
+                Ok(network) => networks.push(network),
+                Err(err) => {
+                    config.new_parse_error("session.relay.trusted-networks", err);
+                }
+            }
+        }
+
+        let mut remove_headers = config
+            .values("session.relay.remove-headers")
+            .map(|(_, v)| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>();
+        if remove_headers.is_empty() {
+            remove_headers = ["User-Agent", "X-Mailer", "X-Originating-IP", "X-MimeOLE"]
+                .into_iter()
+                .map(String::from)
+                .collect();
+        }
+
+        TrustedRelay {
+            networks,
+            remove_headers,
+        }
+    }
+}
+
+impl NullSender {
+    pub fn parse(config: &mut Config) -> Self {
+        NullSender {
+            rate: config
+                .property_or_default::<Option<Rate>>("session.null-sender.rate", "false")
+                .unwrap_or_default(),
+            single_recipient: config
+                .property_or_default("session.null-sender.single-recipient", "false")
+                .unwrap_or(false),
+            batv: Batv::parse(config),
+        }
+    }
+}
+
+const BATV_PREFIX: &str = "prvs=";
+
+impl Batv {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        // A single key has id 0, multiple keys are configured by id
+        let mut keys = Vec::new();
+        if let Some(key) = config
+            .value("session.null-sender.batv.key")
+            .filter(|key| !key.is_empty())
+        {
+            keys.push((0, key.as_bytes().to_vec()));
+        } else {
+            let mut errors = Vec::new();
+            for (key_id, key) in config.iterate_prefix("session.null-sender.batv.key") {
+                match key_id.parse::<u8>() {
+                    Ok(key_id) if key_id <= 9 && !key.is_empty() => {
+                        keys.push((key_id, key.as_bytes().to_vec()));
+                    }
+                    _ => {
+                        errors.push(format!("session.null-sender.batv.key.{key_id}"));
+                    }
+                }
+            }
+            for key in errors {
+                config.new_parse_error(key, "Key ids must be a digit between 0 and 9");
+            }
+        }
+        keys.sort_unstable_by_key(|(key_id, _)| *key_id);
+        let (last_key_id, _) = keys.last()?;
+
+        let sign_key_id = config
+            .property::<u8>("session.null-sender.batv.key-id")
+            .unwrap_or(*last_key_id);
+        if !keys.iter().any(|(key_id, _)| *key_id == sign_key_id) {
+            config.new_parse_error(
+                "session.null-sender.batv.key-id",
+                format!("No key with id {sign_key_id} is configured"),
+            );
+            return None;
+        }
+
+        Some(Batv {
+            keys,
+            sign_key_id,
+            expiry_days: config
+                .property_or_default::<Duration>("session.null-sender.batv.expiry", "7d")
+                .map(|d| (d.as_secs() / 86400).clamp(1, 999))
+                .unwrap_or(7),
+            require: config
+                .property_or_default("session.null-sender.batv.require", "false")
+                .unwrap_or(false),
+        })
+    }
+
+    pub fn sign(&self, address: &str, now: u64) -> String {
+        let tag = format!("{}{:03}", self.sign_key_id, (now / 86400) % 1000);
+        format!(
+            "{BATV_PREFIX}{tag}{}={address}",
+            self.signature(self.sign_key_id, &tag, address)
+                .unwrap_or_default()
+        )
+    }
+
+    pub fn verify(&self, address: &str, now: u64) -> BatvAddress {
+        let Some((tag, address)) = address
+            .get(..BATV_PREFIX.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(BATV_PREFIX))
+            .and_then(|_| address[BATV_PREFIX.len()..].split_once('='))
+        else {
+            return BatvAddress::Unsigned;
+        };
+
+        if tag.len() == 10
+            && tag.is_ascii()
+            && let Ok(key_id) = tag[..1].parse::<u8>()
+            && let Ok(day) = tag[1..4].parse::<u64>()
+            && (((now / 86400) % 1000) + 1000 - day) % 1000 <= self.expiry_days
+            && let Some(signature) = self.signature(key_id, &tag[..4], address)
+            && tag[4..].eq_ignore_ascii_case(&signature)
+        {
+            BatvAddress::Valid(address.to_string())
+        } else {
+            BatvAddress::Invalid
+        }
+    }
+
+    fn signature(&self, key_id: u8, tag: &str, address: &str) -> Option<String> {
+        let (_, key) = self.keys.iter().find(|(id, _)| *id == key_id)?;
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, key);
+        let mut ctx = ring::hmac::Context::with_key(&key);
+        ctx.update(tag.as_bytes());
+        ctx.update(address.to_lowercase().as_bytes());
+        Some(
+            ctx.sign().as_ref()[..3]
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+        )
+    }
+}
+
+const SRS_PREFIX: &str = "SRS0=";
+const SRS1_PREFIX: &str = "SRS1=";
+const SRS_BASE32: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+impl Srs {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        let key = config
+            .value("session.srs.key")
+            .filter(|key| !key.is_empty())
+            .map(|key| key.as_bytes().to_vec())?;
+
+        Some(Srs {
+            key,
+            expiry_days: config
+                .property_or_default::<Duration>("session.srs.expiry", "21d")
+                .map(|d| (d.as_secs() / 86400).clamp(1, 1023))
+                .unwrap_or(21),
+        })
+    }
+
+    pub fn forward(&self, address: &str, forwarder_domain: &str, now: u64) -> String {
+        let Some((local, domain)) = address.rsplit_once('@') else {
+            return address.to_string();
+        };
+
+        // Addresses rewritten by another forwarder keep pointing to the first hop (SRS1)
+        if let Some(opaque) = strip_prefix_ignore_case(local, SRS_PREFIX) {
+            return self.forward_srs1(domain, &format!("={opaque}"), forwarder_domain);
+        } else if let Some((first_hop, opaque)) = strip_prefix_ignore_case(local, SRS1_PREFIX)
+            .and_then(|tag| tag.split_once('=')?.1.split_once('='))
+            .filter(|(first_hop, opaque)| !first_hop.is_empty() && opaque.starts_with('='))
+        {
+            return self.forward_srs1(first_hop, opaque, forwarder_domain);
+        }
+
+        let day = (now / 86400) % 1024;
+        let timestamp = String::from_iter([
+            SRS_BASE32[(day >> 5) as usize] as char,
+            SRS_BASE32[(day & 31) as usize] as char,
+        ]);
+
+        format!(
+            "{SRS_PREFIX}{}={timestamp}={domain}={local}@{forwarder_domain}",
+            self.signature(&timestamp, domain, local)
+        )
+    }
+
+    fn forward_srs1(&self, first_hop: &str, opaque: &str, forwarder_domain: &str) -> String {
+        format!(
+            "{SRS1_PREFIX}{}={first_hop}={opaque}@{forwarder_domain}",
+            self.signature(SRS1_PREFIX, first_hop, opaque)
+        )
+    }
+
+    pub fn reverse(&self, address: &str, now: u64) -> SrsAddress {
+        // SRS1 addresses are reversed to the address of the first hop
+        if let Some((hash, first_hop, opaque)) = address
+            .rsplit_once('@')
+            .and_then(|(local, _)| strip_prefix_ignore_case(local, SRS1_PREFIX))
+            .and_then(|tag| {
+                let (hash, tag) = tag.split_once('=')?;
+                let (first_hop, opaque) = tag.split_once('=')?;
+                Some((hash, first_hop, opaque))
+            })
+        {
+            return if !first_hop.is_empty()
+                && opaque.len() > 1
+                && opaque.starts_with('=')
+                && hash.eq_ignore_ascii_case(&self.signature(SRS1_PREFIX, first_hop, opaque))
+            {
+                SrsAddress::Valid(format!("SRS0{opaque}@{first_hop}"))
+            } else {
+                SrsAddress::Invalid
+            };
+        }
+
+        let Some((hash, timestamp, domain, local)) = address
+            .rsplit_once('@')
+            .and_then(|(local, _)| strip_prefix_ignore_case(local, SRS_PREFIX))
+            .and_then(|tag| {
+                let mut parts = tag.splitn(4, '=');
+                Some((parts.next()?, parts.next()?, parts.next()?, parts.next()?))
+            })
+        else {
+            return SrsAddress::Unsigned;
+        };
+
+        let timestamp_ = timestamp.to_ascii_uppercase();
+        if let [high, low] = timestamp_.as_bytes()
+            && let Some(high) = SRS_BASE32.iter().position(|ch| ch == high)
+            && let Some(low) = SRS_BASE32.iter().position(|ch| ch == low)
+            && ((now / 86400) % 1024 + 1024 - ((high << 5) | low) as u64) % 1024 <= self.expiry_days
+            && !local.is_empty()
+            && !domain.is_empty()
+            && hash.eq_ignore_ascii_case(&self.signature(&timestamp_, domain, local))
+        {
+            SrsAddress::Valid(format!("{local}@{domain}"))
+        } else {
+            SrsAddress::Invalid
+        }
+    }
+
+    fn signature(&self, timestamp: &str, domain: &str, local: &str) -> String {
+        let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, &self.key);
+        let mut ctx = ring::hmac::Context::with_key(&key);
+        ctx.update(timestamp.as_bytes());
+        ctx.update(domain.to_lowercase().as_bytes());
+        ctx.update(local.to_lowercase().as_bytes());
+        ctx.sign().as_ref()[..3]
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
+
+fn strip_prefix_ignore_case<'x>(value: &'x str, prefix: &str) -> Option<&'x str> {
+    value
+        .get(..prefix.len())
+        .filter(|value_prefix| value_prefix.eq_ignore_ascii_case(prefix))
+        .map(|_| &value[prefix.len()..])
+}
+
+fn parse_bulk_sender(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<BulkSender> {
+    let domains = config
+        .values(("session.bulk", id, "domains"))
+        .map(|(_, v)| v.trim().to_lowercase())
+        .filter(|v| !v.is_empty())
+        .collect::<Vec<_>>();
+    if domains.is_empty() {
+        config.new_build_error(
+            ("session.bulk", id, "domains"),
+            "No sender domains defined for bulk sender",
+        );
+        return None;
+    }
+
+    let feedback_id = config
+        .value(("session.bulk", id, "feedback-id"))
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string());
+    if feedback_id
+        .as_ref()
+        .is_some_and(|v| v.contains(['\r', '\n']) || !v.is_ascii())
+    {
+        config.new_parse_error(
+            ("session.bulk", id, "feedback-id"),
+            "Invalid Feedback-ID value",
+        );
+        return None;
+    }
+
+    let unsubscribe = if config
+        .property_or_default(("session.bulk", id, "unsubscribe.enable"), "true")
+        .unwrap_or(true)
+    {
+        BulkUnsubscribe {
+            url: if let Some(url) = config
+                .value(("session.bulk", id, "unsubscribe.url"))
+                .map(|v| v.trim().trim_end_matches('/'))
+                .filter(|v| !v.is_empty())
+            {
+                url.to_string()
+            } else {
+                format!(
+                    "https://{}/unsubscribe",
+                    config.value("server.hostname").unwrap_or("localhost")
+                )
+            },
+            expiry: config
+                .property_or_default::<Duration>(("session.bulk", id, "unsubscribe.expiry"), "90d")
+                .map(|d| d.as_secs())
+                .unwrap_or(90 * 24 * 60 * 60),
+        }
+        .into()
+    } else {
+        None
+    };
+
+    Some(BulkSender {
+        enable: IfBlock::try_parse(config, ("session.bulk", id, "enable"), token_map)
+            .unwrap_or_else(|| {
+                IfBlock::new::<()>(
+                    format!("session.bulk.{id}.enable"),
+                    [("!is_empty(authenticated_as)", "true")],
+                    "false",
+                )
+            }),
+        id: id.to_string(),
+        domains,
+        feedback_id,
+        unsubscribe,
+    })
+}
+
+fn parse_dlp_rule(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<DlpRule> {
+    let matcher = match config.value_require(("session.dlp", id, "type"))? {
+        "credit-card" => DlpMatcher::CreditCard,
+        "iban" => DlpMatcher::Iban,
+        "regex" => {
+            // Patterns can be listed inline or loaded from an in-memory store
+            let patterns = config
+                .values(("session.dlp", id, "patterns"))
+                .map(|(_, v)| v.to_string())
+                .collect::<Vec<_>>();
+            let dictionary = config
+                .value(("session.dlp", id, "lookup.store"))
+                .map(|store| store.to_string())
+                .map(|store| DlpDictionary {
+                    store,
+                    key: config
+                        .value(("session.dlp", id, "lookup.key"))
+                        .unwrap_or(id)
+                        .to_string(),
+                    compiled: Default::default(),
+                });
+
+            let mut regexes = Vec::with_capacity(patterns.len());
+            for pattern in patterns {
+                match Regex::new(&pattern) {
+                    Ok(regex) => regexes.push(regex),
+                    Err(err) => {
+                        config.new_parse_error(
+                            ("session.dlp", id, "patterns"),
+                            format!("Invalid regular expression {pattern:?}: {err}"),
+                        );
+                    }
+                }
+            }
+
+            if regexes.is_empty() && dictionary.is_none() {
+                config.new_build_error(
+                    ("session.dlp", id),
+                    "No regular expressions defined for DLP rule",
+                );
+                return None;
+            }
+
+            DlpMatcher::Regex {
+                patterns: regexes,
+                dictionary,
+            }
+        }
+        other => {
+            let err = format!("Invalid DLP rule type {other:?}");
+            config.new_parse_error(("session.dlp", id, "type"), err);
+            return None;
+        }
+    };
+
+    Some(DlpRule {
+        enable: IfBlock::try_parse(config, ("session.dlp", id, "enable"), token_map)
+            .unwrap_or_else(|| {
+                IfBlock::new::<()>(
+                    format!("session.dlp.{id}.enable"),
+                    [("!is_empty(authenticated_as)", "true")],
+                    "false",
+                )
+            }),
+        id: id.to_string(),
+        matcher,
+        threshold: config
+            .property_or_default(("session.dlp", id, "threshold"), "1")
+            .unwrap_or(1),
+        action: config
+            .property_or_default(("session.dlp", id, "action"), "reject")
+            .unwrap_or(DlpAction::Reject),
+    })
+}
+
+fn parse_disclaimer(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<Disclaimer> {
+    let text = config
+        .value_require(("session.disclaimer", id, "text"))?
+        .to_string();
+
+    // Derive the HTML version from the plain text one when not provided
+    let html = config
+        .value(("session.disclaimer", id, "html"))
+        .map(|html| html.to_string())
+        .unwrap_or_else(|| {
+            let mut html = String::with_capacity(text.len() + 16);
+            html.push_str("<p>");
+            for ch in text.chars() {
+                match ch {
+                    '<' => html.push_str("&lt;"),
+                    '>' => html.push_str("&gt;"),
+                    '&' => html.push_str("&amp;"),
+                    '"' => html.push_str("&quot;"),
+                    '\n' => html.push_str("<br>"),
+                    '\r' => {}
+                    _ => html.push(ch),
+                }
+            }
+            html.push_str("</p>");
+            html
+        });
+
+    Some(Disclaimer {
+        enable: IfBlock::try_parse(config, ("session.disclaimer", id, "enable"), token_map)
+            .unwrap_or_else(|| {
+                IfBlock::new::<()>(
+                    format!("session.disclaimer.{id}.enable"),
+                    [("!is_empty(authenticated_as)", "true")],
+                    "false",
+                )
+            }),
+        id: id.to_string(),
+        text,
+        html,
+        on_signed: config
+            .property_or_default(("session.disclaimer", id, "signed"), "skip")
+            .unwrap_or(SignedMessagePolicy::Skip),
+    })
+}
+
+fn parse_message_tag(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<MessageTag> {
+    let subject = config
+        .value(("session.tag", id, "subject"))
+        .filter(|subject| !subject.is_empty())
+        .map(|subject| subject.to_string());
+    let headers = config
+        .values(("session.tag", id, "headers"))
+        .map(|(_, v)| {
+            v.split_once(':')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .filter(|(k, v)| {
+                    !k.is_empty()
+                        && k.bytes().all(|ch| ch.is_ascii_graphic() && ch != b':')
+                        && !v.contains(['\r', '\n'])
+                })
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .ok_or_else(|| {
+                    format!("Invalid header found in property \"session.tag.{id}.headers\": {v}")
+                })
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map_err(|e| config.new_parse_error(("session.tag", id, "headers"), e))
+        .unwrap_or_default();
+
+    if subject.is_none() && headers.is_empty() {
+        config.new_build_error(
+            ("session.tag", id),
+            "No subject tag or headers defined for message tag",
+        );
+        return None;
+    }
+
+    Some(MessageTag {
+        enable: IfBlock::try_parse(config, ("session.tag", id, "enable"), token_map)
+            .unwrap_or_else(|| IfBlock::empty(format!("session.tag.{id}.enable"))),
+        id: id.to_string(),
+        subject,
+        headers,
+    })
+}
+
 fn parse_hooks(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<MTAHook> {
     let mut headers = HeaderMap::new();
 
@@ -585,6 +1541,10 @@ fn parse_hooks(config: &mut Config, id: &str, token_map: &TokenMap) -> Option<MT
                 "52428800",
             )
             .unwrap_or(52428800),
+        max_message_size: config.property(("session.hook", id, "options.max-message-size")),
+        message_format: config
+            .property_or_default(("session.hook", id, "options.message-format"), "contents")
+            .unwrap_or_default(),
         headers,
     })
 }
@@ -641,6 +1601,24 @@ impl Default for SessionConfig {
                     [],
                     "config_get('server.hostname') + ' Stalwart ESMTP at your service'",
                 ),
+                greeting_delay: IfBlock::new::<()>("session.connect.greeting-delay", [], "false"),
+            },
+            conformance: Conformance {
+                bare_lf: IfBlock::new::<ConformanceAction>(
+                    "session.conformance.bare-lf",
+                    [],
+                    "disable",
+                ),
+                pipelining: IfBlock::new::<ConformanceAction>(
+                    "session.conformance.pipelining",
+                    [],
+                    "disable",
+                ),
+                early_talker: IfBlock::new::<ConformanceAction>(
+                    "session.conformance.early-talker",
+                    [],
+                    "disable",
+                ),
             },
             ehlo: Ehlo {
                 script: IfBlock::empty("session.ehlo.script"),
@@ -680,6 +1658,7 @@ impl Default for SessionConfig {
                     "false",
                 ),
                 must_match_sender: IfBlock::new::<()>("session.auth.must-match-sender", [], "true"),
+                must_match_from: IfBlock::new::<()>("session.auth.must-match-from", [], "false"),
                 errors_max: IfBlock::new::<()>("session.auth.errors.total", [], "3"),
                 errors_wait: IfBlock::new::<()>("session.auth.errors.wait", [], "5s"),
             },
@@ -711,12 +1690,19 @@ impl Default for SessionConfig {
                 errors_max: IfBlock::new::<()>("session.rcpt.errors.total", [], "5"),
                 errors_wait: IfBlock::new::<()>("session.rcpt.errors.wait", [], "5s"),
                 max_recipients: IfBlock::new::<()>("session.rcpt.max-recipients", [], "100"),
+                max_domains: IfBlock::new::<()>("session.rcpt.max-domains", [], "0"),
                 catch_all: AddressMapping::Enable,
                 subaddressing: AddressMapping::Enable,
+                subaddressing_separator: IfBlock::new::<()>(
+                    "session.rcpt.sub-addressing-separator",
+                    [],
+                    "'+'",
+                ),
             },
             data: Data {
                 script: IfBlock::empty("session.data.script"),
                 spam_filter: IfBlock::new::<()>("session.data.spam-filter", [], "true"),
+                spam_profile: IfBlock::new::<()>("session.data.spam-profile", [], "false"),
                 max_messages: IfBlock::new::<()>("session.data.limits.messages", [], "10"),
                 max_message_size: IfBlock::new::<()>("session.data.limits.size", [], "104857600"),
                 max_received_headers: IfBlock::new::<()>(
@@ -791,10 +1777,32 @@ impl Default for SessionConfig {
                     [("!is_empty(authenticated_as)", "mixer")],
                     "false",
                 ),
+                limits: IfBlock::new::<()>("session.extensions.limits", [], "true"),
+                prdr: IfBlock::new::<()>("session.extensions.prdr", [], "false"),
             },
             mta_sts_policy: None,
             milters: Default::default(),
             hooks: Default::default(),
+            dlp: Default::default(),
+            disclaimers: Default::default(),
+            attachment_offload: AttachmentOffload {
+                enable: IfBlock::new::<()>("session.attachment-offload.enable", [], "false"),
+                min_size: IfBlock::new::<()>("session.attachment-offload.min-size", [], "10485760"),
+                expiry: IfBlock::new::<()>("session.attachment-offload.expiry", [], "7d"),
+                max_downloads: IfBlock::new::<()>(
+                    "session.attachment-offload.max-downloads",
+                    [],
+                    "0",
+                ),
+                folder: "Attachments".to_string(),
+            },
+            tags: Default::default(),
+            spamd: Default::default(),
+            rspamd: Default::default(),
+            bulk: Default::default(),
+            trusted_relay: Default::default(),
+            null_sender: Default::default(),
+            srs: None,
         }
     }
 }
@@ -802,6 +1810,85 @@ impl Default for SessionConfig {
 #[derive(Default)]
 pub struct Mechanism(u64);
 
+impl DlpAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DlpAction::RequireTls => "require-tls",
+            DlpAction::Encrypt => "encrypt",
+            DlpAction::Quarantine => "quarantine",
+            DlpAction::Reject => "reject",
+        }
+    }
+}
+
+impl ParseValue for DlpAction {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "reject" => Ok(DlpAction::Reject),
+            "quarantine" => Ok(DlpAction::Quarantine),
+            "require-tls" => Ok(DlpAction::RequireTls),
+            "encrypt" => Ok(DlpAction::Encrypt),
+            _ => Err(format!("Invalid DLP action {value:?}.")),
+        }
+    }
+}
+
+impl SpamdCommand {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpamdCommand::Check => "CHECK",
+            SpamdCommand::Symbols => "SYMBOLS",
+            SpamdCommand::Report => "REPORT",
+            SpamdCommand::Headers => "HEADERS",
+        }
+    }
+}
+
+impl ParseValue for SpamdCommand {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "check" => Ok(SpamdCommand::Check),
+            "symbols" => Ok(SpamdCommand::Symbols),
+            "report" => Ok(SpamdCommand::Report),
+            "headers" => Ok(SpamdCommand::Headers),
+            _ => Err(format!("Invalid spamd command {value:?}.")),
+        }
+    }
+}
+
+impl ParseValue for SpamdAction {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "accept" => Ok(SpamdAction::Accept),
+            "quarantine" => Ok(SpamdAction::Quarantine),
+            "discard" => Ok(SpamdAction::Discard),
+            "reject" => Ok(SpamdAction::Reject),
+            _ => Err(format!("Invalid spamd action {value:?}.")),
+        }
+    }
+}
+
+impl ParseValue for HookMessageFormat {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "contents" => Ok(HookMessageFormat::Contents),
+            "mime" => Ok(HookMessageFormat::Mime),
+            "raw" => Ok(HookMessageFormat::Raw),
+            _ => Err(format!("Invalid hook message format {value:?}.")),
+        }
+    }
+}
+
+impl ParseValue for SignedMessagePolicy {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "skip" => Ok(SignedMessagePolicy::Skip),
+            "wrap" => Ok(SignedMessagePolicy::Wrap),
+            _ => Err(format!("Invalid signed message policy {value:?}.")),
+        }
+    }
+}
+
 impl ParseValue for Mechanism {
     fn parse_value(value: &str) -> Result<Self, String> {
         Ok(Mechanism(match value.to_ascii_uppercase().as_str() {
@@ -930,6 +2017,56 @@ impl From<MtPriority> for Constant {
     }
 }
 
+impl<'x> TryFrom<Variable<'x>> for ConformanceAction {
+    type Error = ();
+
+    fn try_from(value: Variable<'x>) -> Result<Self, Self::Error> {
+        match value {
+            Variable::Integer(value) => match value {
+                0 => Ok(ConformanceAction::Disable),
+                1 => Ok(ConformanceAction::Tag),
+                2 => Ok(ConformanceAction::Reject),
+                _ => Err(()),
+            },
+            Variable::String(value) => {
+                ConformanceAction::parse_value(value.as_str()).map_err(|_| ())
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<ConformanceAction> for Constant {
+    fn from(value: ConformanceAction) -> Self {
+        Constant::Integer(match value {
+            ConformanceAction::Disable => 0,
+            ConformanceAction::Tag => 1,
+            ConformanceAction::Reject => 2,
+        })
+    }
+}
+
+impl ParseValue for ConformanceAction {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "disable" | "disabled" | "false" => Ok(ConformanceAction::Disable),
+            "tag" | "score" => Ok(ConformanceAction::Tag),
+            "reject" => Ok(ConformanceAction::Reject),
+            _ => Err(format!("Invalid conformance action {value:?}.")),
+        }
+    }
+}
+
+impl ConstantValue for ConformanceAction {
+    fn add_constants(token_map: &mut TokenMap) {
+        token_map
+            .add_constant("disable", ConformanceAction::Disable)
+            .add_constant("tag", ConformanceAction::Tag)
+            .add_constant("score", ConformanceAction::Tag)
+            .add_constant("reject", ConformanceAction::Reject);
+    }
+}
+
 impl ConstantValue for MtPriority {
     fn add_constants(token_map: &mut TokenMap) {
         token_map
@@ -938,3 +2075,157 @@ impl ConstantValue for MtPriority {
             .add_constant("nsep", MtPriority::Nsep);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use utils::config::Config;
+
+    use super::{Batv, BatvAddress, Srs, SrsAddress};
+
+    fn parse_batv(config: &str) -> Batv {
+        let mut config = Config::new(config).unwrap();
+        let batv = Batv::parse(&mut config).unwrap();
+        assert!(config.errors.is_empty(), "{:?}", config.errors);
+        batv
+    }
+
+    #[test]
+    fn batv_sign_verify() {
+        let now = 1_700_000_000;
+        let batv = parse_batv("[session.null-sender.batv]\nkey = \"secret\"\n");
+        let signed = batv.sign("john@example.org", now);
+        assert!(signed.starts_with("prvs=0"), "{signed}");
+        assert!(signed.ends_with("=john@example.org"), "{signed}");
+        assert_eq!(signed.len(), "prvs=0DDDSSSSSS=john@example.org".len());
+
+        // Tags are case insensitive and valid until they expire
+        let valid = BatvAddress::Valid("john@example.org".to_string());
+        assert_eq!(batv.verify(&signed, now), valid);
+        assert_eq!(batv.verify(&signed, now + 7 * 86400), valid);
+        assert_eq!(
+            batv.verify(&signed.to_uppercase(), now),
+            BatvAddress::Valid("JOHN@EXAMPLE.ORG".to_string())
+        );
+        assert_eq!(batv.verify(&signed, now + 8 * 86400), BatvAddress::Invalid);
+
+        // Tampered, unknown and missing tags
+        assert_eq!(
+            batv.verify(&signed.replace("john@", "jane@"), now),
+            BatvAddress::Invalid
+        );
+        assert_eq!(
+            batv.verify(&signed.replace("prvs=0", "prvs=5"), now),
+            BatvAddress::Invalid
+        );
+        assert_eq!(
+            batv.verify("prvs=john@example.org", now),
+            BatvAddress::Unsigned
+        );
+        assert_eq!(batv.verify("john@example.org", now), BatvAddress::Unsigned);
+        assert_eq!(
+            parse_batv("[session.null-sender.batv]\nkey = \"other\"\n").verify(&signed, now),
+            BatvAddress::Invalid
+        );
+    }
+
+    #[test]
+    fn batv_key_rotation() {
+        let now = 1_700_000_000;
+        let signed_old =
+            parse_batv("[session.null-sender.batv]\nkey = \"old\"\n").sign("john@example.org", now);
+
+        // The key with the highest id signs, all configured keys verify
+        let batv = parse_batv(concat!(
+            "[session.null-sender.batv.key]\n",
+            "\"0\" = \"old\"\n",
+            "\"1\" = \"new\"\n"
+        ));
+        let signed_new = batv.sign("john@example.org", now);
+        assert!(signed_new.starts_with("prvs=1"), "{signed_new}");
+        for signed in [&signed_old, &signed_new] {
+            assert_eq!(
+                batv.verify(signed, now),
+                BatvAddress::Valid("john@example.org".to_string()),
+                "{signed}"
+            );
+        }
+
+        // Retired keys no longer verify
+        let batv = parse_batv("[session.null-sender.batv.key]\n\"1\" = \"new\"\n");
+        assert_eq!(batv.verify(&signed_old, now), BatvAddress::Invalid);
+        assert_eq!(
+            batv.verify(&signed_new, now),
+            BatvAddress::Valid("john@example.org".to_string())
+        );
+
+        // The signing key can be selected explicitly
+        let batv = parse_batv(concat!(
+            "[session.null-sender.batv]\n",
+            "key-id = 0\n",
+            "[session.null-sender.batv.key]\n",
+            "\"0\" = \"old\"\n",
+            "\"1\" = \"new\"\n"
+        ));
+        assert_eq!(batv.sign("john@example.org", now), signed_old);
+
+        // Invalid key ids are reported
+        for config in [
+            "[session.null-sender.batv.key]\n\"12\" = \"new\"\n",
+            "[session.null-sender.batv]\nkey-id = 3\n[session.null-sender.batv.key]\n\"1\" = \"new\"\n",
+        ] {
+            let mut config = Config::new(config).unwrap();
+            Batv::parse(&mut config);
+            assert!(!config.errors.is_empty());
+        }
+    }
+
+    #[test]
+    fn srs_forward_reverse() {
+        let now = 1_700_000_000;
+        let mut config = Config::new("[session.srs]\nkey = \"secret\"\n").unwrap();
+        let srs = Srs::parse(&mut config).unwrap();
+
+        // SRS0 rewrites the original sender and is valid until it expires
+        let srs0 = srs.forward("john@example.org", "forwarder.org", now);
+        assert!(srs0.starts_with("SRS0="), "{srs0}");
+        assert!(srs0.ends_with("=example.org=john@forwarder.org"), "{srs0}");
+        let original = SrsAddress::Valid("john@example.org".to_string());
+        assert_eq!(srs.reverse(&srs0, now), original);
+        assert_eq!(srs.reverse(&srs0.to_lowercase(), now), original);
+        assert_eq!(srs.reverse(&srs0, now + 21 * 86400), original);
+        assert_eq!(srs.reverse(&srs0, now + 22 * 86400), SrsAddress::Invalid);
+        assert_eq!(
+            srs.reverse(&srs0.replace("=john@", "=jane@"), now),
+            SrsAddress::Invalid
+        );
+        assert_eq!(srs.reverse("john@example.org", now), SrsAddress::Unsigned);
+
+        // Addresses already rewritten by another forwarder are rewritten as SRS1
+        let foreign_srs0 = "SRS0=abcdef=AB=example.org=john@first.org";
+        let srs1 = srs.forward(foreign_srs0, "second.org", now);
+        assert!(srs1.starts_with("SRS1="), "{srs1}");
+        assert!(
+            srs1.ends_with("=first.org==abcdef=AB=example.org=john@second.org"),
+            "{srs1}"
+        );
+        assert_eq!(
+            srs.reverse(&srs1, now),
+            SrsAddress::Valid(foreign_srs0.to_string())
+        );
+        assert_eq!(
+            srs.reverse(&srs1.replace("=first.org=", "=other.org="), now),
+            SrsAddress::Invalid
+        );
+
+        // Further hops keep pointing to the first forwarder
+        let srs1_next = srs.forward(&srs1, "third.org", now);
+        assert!(
+            srs1_next.ends_with("=first.org==abcdef=AB=example.org=john@third.org"),
+            "{srs1_next}"
+        );
+        assert_eq!(
+            srs.reverse(&srs1_next, now),
+            SrsAddress::Valid(foreign_srs0.to_string())
+        );
+    }
+}
@@ -5,11 +5,12 @@
  */
 
 use std::{
+    fmt::Write,
     net::{IpAddr, SocketAddr},
     time::Duration,
 };
 
-use ahash::AHashSet;
+use ahash::{AHashMap, AHashSet};
 use mail_auth::common::resolver::ToReverseName;
 use nlp::bayes::BayesClassifier;
 use tokio::net::lookup_host;
@@ -25,13 +26,16 @@ use super::{Variable, functions::ResolveVariable, if_block::IfBlock, tokenizer::
 pub struct SpamFilterConfig {
     pub enabled: bool,
     pub card_is_ham: bool,
+    pub strip_trackers: bool,
     pub dnsbl: DnsBlConfig,
     pub rules: SpamFilterRules,
     pub lists: SpamFilterLists,
     pub pyzor: Option<PyzorConfig>,
     pub reputation: Option<ReputationConfig>,
     pub bayes: Option<BayesConfig>,
+    pub spam_trap: SpamTrapConfig,
     pub scores: SpamFilterScoreConfig,
+    pub profiles: AHashMap<String, SpamFilterScoreConfig>,
     pub expiry: SpamFilterExpiryConfig,
     pub headers: SpamFilterHeaderConfig,
 }
@@ -51,6 +55,12 @@ pub struct SpamFilterScoreConfig {
     pub spam_threshold: f64,
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct SpamTrapConfig {
+    pub reputation_penalty: f64,
+    pub block_for: Option<u64>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SpamFilterExpiryConfig {
     pub grey_list: Option<u64>,
@@ -183,13 +193,18 @@ impl SpamFilterConfig {
             card_is_ham: config
                 .property_or_default("spam-filter.card-is-ham", "true")
                 .unwrap_or(true),
+            strip_trackers: config
+                .property_or_default("spam-filter.html.strip-trackers", "false")
+                .unwrap_or(false),
             dnsbl: DnsBlConfig::parse(config),
             rules: SpamFilterRules::parse(config),
             lists: SpamFilterLists::parse(config),
             pyzor: PyzorConfig::parse(config).await,
             reputation: ReputationConfig::parse(config),
             bayes: BayesConfig::parse(config),
+            spam_trap: SpamTrapConfig::parse(config),
             scores: SpamFilterScoreConfig::parse(config),
+            profiles: SpamFilterScoreConfig::parse_profiles(config),
             expiry: SpamFilterExpiryConfig::parse(config),
             headers: SpamFilterHeaderConfig::parse(config),
         }
@@ -342,6 +357,18 @@ impl SpamFilterHeaderConfig {
 
         header
     }
+
+    pub fn write_status(&self, buf: &mut String, is_spam: bool, score: f64) {
+        if let Some(header_name) = &self.status {
+            let _ = write!(
+                buf,
+                "{}: {}, score={:.2}\r\n",
+                header_name,
+                if is_spam { "Yes" } else { "No" },
+                score
+            );
+        }
+    }
 }
 
 impl SpamFilterLists {
@@ -587,6 +614,55 @@ impl SpamFilterScoreConfig {
                 .unwrap_or(5.0),
         }
     }
+
+    pub fn parse_profiles(config: &mut Config) -> AHashMap<String, Self> {
+        let global = Self::parse(config);
+        let mut profiles = AHashMap::new();
+
+        for id in config.sub_keys("spam-filter.profile", "") {
+            let id_ = id.as_str();
+            profiles.insert(
+                id.clone(),
+                SpamFilterScoreConfig {
+                    reject_threshold: config
+                        .property(("spam-filter.profile", id_, "score.reject"))
+                        .unwrap_or(global.reject_threshold),
+                    discard_threshold: config
+                        .property(("spam-filter.profile", id_, "score.discard"))
+                        .unwrap_or(global.discard_threshold),
+                    spam_threshold: config
+                        .property(("spam-filter.profile", id_, "score.spam"))
+                        .unwrap_or(global.spam_threshold),
+                },
+            );
+        }
+
+        profiles
+    }
+
+    pub fn verdict(&self, score: f64) -> SpamFilterAction<bool> {
+        if self.reject_threshold > 0.0 && score >= self.reject_threshold {
+            SpamFilterAction::Reject
+        } else if self.discard_threshold > 0.0 && score >= self.discard_threshold {
+            SpamFilterAction::Discard
+        } else {
+            SpamFilterAction::Allow(score >= self.spam_threshold)
+        }
+    }
+}
+
+impl SpamTrapConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        SpamTrapConfig {
+            reputation_penalty: config
+                .property_or_default("spam-filter.spam-trap.reputation-penalty", "10.0")
+                .unwrap_or(10.0),
+            block_for: config
+                .property_or_default::<Option<Duration>>("spam-filter.spam-trap.block", "false")
+                .unwrap_or_default()
+                .map(|d| d.as_secs()),
+        }
+    }
 }
 
 impl SpamFilterExpiryConfig {
@@ -8,7 +8,9 @@ use std::sync::Arc;
 
 use ahash::AHashMap;
 use directory::Directory;
-use store::{BlobStore, FtsStore, InMemoryStore, PubSubStore, PurgeSchedule, Store};
+use store::{
+    BlobStore, FtsStore, InMemoryStore, PubSubStore, PurgeSchedule, Store, backend::http::HttpQuery,
+};
 
 use crate::manager::config::ConfigManager;
 
@@ -16,6 +18,7 @@ use crate::manager::config::ConfigManager;
 pub struct Storage {
     pub data: Store,
     pub blob: BlobStore,
+    pub queue: Option<BlobStore>,
     pub fts: FtsStore,
     pub lookup: InMemoryStore,
     pub pubsub: PubSubStore,
@@ -28,4 +31,5 @@ pub struct Storage {
     pub blobs: AHashMap<String, BlobStore>,
     pub lookups: AHashMap<String, InMemoryStore>,
     pub ftss: AHashMap<String, FtsStore>,
+    pub http_queries: AHashMap<String, Arc<HttpQuery>>,
 }
@@ -5,6 +5,7 @@
  */
 
 use super::parse_http_headers;
+use crate::telemetry::filter::{FilterCondition, TracerFilter};
 use ahash::{AHashMap, AHashSet};
 use base64::{Engine, engine::general_purpose::STANDARD};
 use hyper::{HeaderMap, header::CONTENT_TYPE};
@@ -20,8 +21,15 @@ use opentelemetry_sdk::{
 use opentelemetry_semantic_conventions::resource::SERVICE_VERSION;
 use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 use store::Stores;
-use trc::{EventType, Level, TelemetryEvent, ipc::subscriber::Interests};
-use utils::config::{Config, utils::ParseValue};
+use tokio_rustls::TlsConnector;
+use trc::{
+    EventType, Level, TelemetryEvent,
+    ipc::subscriber::{EventFilter, Interests},
+};
+use utils::{
+    config::{Config, utils::ParseValue},
+    rustls_client_config,
+};
 
 #[derive(Debug)]
 pub struct TelemetrySubscriber {
@@ -29,6 +37,7 @@ pub struct TelemetrySubscriber {
     pub interests: Interests,
     pub typ: TelemetrySubscriberType,
     pub lossy: bool,
+    pub filter: Option<Arc<dyn EventFilter>>,
 }
 
 #[allow(clippy::large_enum_variant)]
@@ -40,6 +49,7 @@ pub enum TelemetrySubscriberType {
     Webhook(WebhookTracer),
     #[cfg(unix)]
     JournalTracer(crate::telemetry::tracers::journald::Subscriber),
+    SyslogTracer(SyslogTracer),
     // SPDX-SnippetBegin
     // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
     // SPDX-License-Identifier: LicenseRef-SEL
@@ -56,6 +66,7 @@ pub struct OtelTracer {
     pub log_provider: SdkLogger,
     pub log_exporter_enable: bool,
     pub throttle: Duration,
+    pub sample_rate: f64,
 }
 
 pub struct OtelMetrics {
@@ -92,6 +103,37 @@ pub struct WebhookTracer {
     pub headers: HeaderMap,
 }
 
+#[derive(Debug)]
+pub struct SyslogTracer {
+    pub address: String,
+    pub transport: SyslogTransport,
+    pub timeout: Duration,
+    pub facility: u8,
+    pub hostname: String,
+    pub app_name: String,
+    pub sd_id: String,
+    pub severities: AHashMap<EventType, SyslogSeverity>,
+}
+
+#[derive(Clone)]
+pub enum SyslogTransport {
+    Udp,
+    Tcp,
+    Tls(TlsConnector),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyslogSeverity {
+    Emergency = 0,
+    Alert = 1,
+    Critical = 2,
+    Error = 3,
+    Warning = 4,
+    Notice = 5,
+    Informational = 6,
+    Debug = 7,
+}
+
 // SPDX-SnippetBegin
 // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
 // SPDX-License-Identifier: LicenseRef-SEL
@@ -270,6 +312,10 @@ impl Tracers {
                     let span_exporter_enable = config
                         .property_or_default(("tracer", id, "enable.span-exporter"), "true")
                         .unwrap_or(true);
+                    let sample_rate = config
+                        .property_or_default::<f64>(("tracer", id, "sample-rate"), "1.0")
+                        .unwrap_or(1.0)
+                        .clamp(0.0, 1.0);
 
                     match config
                         .value_require(("tracer", id, "transport"))
@@ -297,6 +343,7 @@ impl Tracers {
                                         throttle,
                                         span_exporter_enable,
                                         log_exporter_enable,
+                                        sample_rate,
                                         log_provider: SdkLoggerProvider::builder()
                                             .build()
                                             .logger("stalwart"),
@@ -366,6 +413,7 @@ impl Tracers {
                                             throttle,
                                             span_exporter_enable,
                                             log_exporter_enable,
+                                            sample_rate,
                                             log_provider: SdkLoggerProvider::builder()
                                                 .build()
                                                 .logger("stalwart"),
@@ -401,6 +449,86 @@ impl Tracers {
                         }
                     }
                 }
+                "syslog" => {
+                    let Some(address) = config
+                        .value_require(("tracer", id, "address"))
+                        .map(|s| s.to_string())
+                    else {
+                        continue;
+                    };
+                    let transport = match config.value(("tracer", id, "transport")).unwrap_or("udp")
+                    {
+                        "udp" => SyslogTransport::Udp,
+                        "tcp" => SyslogTransport::Tcp,
+                        "tls" => SyslogTransport::Tls(TlsConnector::from(Arc::new(
+                            rustls_client_config(
+                                config
+                                    .property_or_default(
+                                        ("tracer", id, "tls.allow-invalid-certs"),
+                                        "false",
+                                    )
+                                    .unwrap_or(false),
+                            ),
+                        ))),
+                        transport => {
+                            let err = format!("Invalid transport: {transport}");
+                            config.new_parse_error(("tracer", id, "transport"), err);
+                            continue;
+                        }
+                    };
+                    let facility = config
+                        .property_or_default::<SyslogFacility>(("tracer", id, "facility"), "mail")
+                        .unwrap_or(SyslogFacility(2))
+                        .0;
+                    let hostname = config
+                        .value(("tracer", id, "hostname"))
+                        .or_else(|| config.value("server.hostname"))
+                        .map(|s| s.to_string())
+                        .unwrap_or_else(|| {
+                            hostname::get()
+                                .map(|h| h.to_string_lossy().into_owned())
+                                .unwrap_or_else(|_| "localhost".to_string())
+                        });
+
+                    // Parse per-event severity overrides
+                    let mut severities = AHashMap::new();
+                    for event_name in config
+                        .prefix(("tracer", id, "severity"))
+                        .map(|s| s.to_string())
+                        .collect::<Vec<_>>()
+                    {
+                        if let Some(event_type) = config.try_parse_value::<EventType>(
+                            ("tracer", id, "severity", event_name.as_str()),
+                            &event_name,
+                        ) && let Some(severity) = config.property_require::<SyslogSeverity>((
+                            "tracer",
+                            id,
+                            "severity",
+                            event_name.as_str(),
+                        )) {
+                            severities.insert(event_type, severity);
+                        }
+                    }
+
+                    TelemetrySubscriberType::SyslogTracer(SyslogTracer {
+                        address,
+                        transport,
+                        timeout: config
+                            .property_or_default(("tracer", id, "timeout"), "10s")
+                            .unwrap_or_else(|| Duration::from_secs(10)),
+                        facility,
+                        hostname,
+                        app_name: config
+                            .value(("tracer", id, "app-name"))
+                            .unwrap_or("stalwart")
+                            .to_string(),
+                        sd_id: config
+                            .value(("tracer", id, "sd-id"))
+                            .unwrap_or("stalwart@32473")
+                            .to_string(),
+                        severities,
+                    })
+                }
                 "journal" => {
                     #[cfg(unix)]
                     {
@@ -454,6 +582,7 @@ impl Tracers {
                 lossy: config
                     .property_or_default(("tracer", id, "lossy"), "false")
                     .unwrap_or(false),
+                filter: parse_filter(config, ("tracer", id)),
                 typ,
             };
 
@@ -483,6 +612,9 @@ impl Tracers {
                 TelemetrySubscriberType::JournalTracer(_) => {
                     EventType::Telemetry(TelemetryEvent::JournalError).into()
                 }
+                TelemetrySubscriberType::SyslogTracer(_) => {
+                    EventType::Telemetry(TelemetryEvent::SyslogError).into()
+                }
                 // SPDX-SnippetBegin
                 // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
                 // SPDX-License-Identifier: LicenseRef-SEL
@@ -536,6 +668,7 @@ impl Tracers {
                         id: "history".to_string(),
                         interests: Default::default(),
                         lossy: false,
+                        filter: None,
                         typ: TelemetrySubscriberType::StoreTracer(StoreTracer {
                             store: store.clone(),
                         }),
@@ -584,6 +717,7 @@ impl Tracers {
                     buffered: true,
                 }),
                 lossy: false,
+                filter: None,
             });
         }
 
@@ -758,6 +892,7 @@ fn parse_webhook(
         lossy: config
             .property_or_default(("webhook", id, "lossy"), "false")
             .unwrap_or(false),
+        filter: parse_filter(config, ("webhook", id)),
         typ: TelemetrySubscriberType::Webhook(WebhookTracer {
             url: config.value_require(("webhook", id, "url"))?.to_string(),
             timeout: config
@@ -803,6 +938,46 @@ fn parse_webhook(
     }
 }
 
+fn parse_filter(config: &mut Config, prefix: (&str, &str)) -> Option<Arc<dyn EventFilter>> {
+    let mut filter = TracerFilter::default();
+
+    // Parse sampling rates
+    for event_name in config
+        .prefix((prefix.0, prefix.1, "sample"))
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+    {
+        let key = (prefix.0, prefix.1, "sample", event_name.as_str());
+        if let Some(event_or_many) = config.try_parse_value::<EventOrMany>(key, &event_name)
+            && let Some(rate) = config.property_require::<f64>(key)
+        {
+            let rate = rate.clamp(0.0, 1.0);
+            apply_events([event_or_many], true, |event_type| {
+                filter.sample_rates.insert(event_type, rate);
+            });
+        }
+    }
+
+    // Parse exclusion conditions
+    filter.exclude = config
+        .properties::<FilterCondition>((prefix.0, prefix.1, "filter.exclude"))
+        .into_iter()
+        .map(|(_, condition)| condition)
+        .collect();
+
+    if !filter.is_empty() {
+        Some(Arc::new(filter))
+    } else {
+        None
+    }
+}
+
+impl ParseValue for FilterCondition {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        FilterCondition::parse(value)
+    }
+}
+
 enum EventOrMany {
     Event(EventType),
     StartsWith(String),
@@ -874,6 +1049,59 @@ fn apply_events(
     }
 }
 
+struct SyslogFacility(u8);
+
+impl ParseValue for SyslogFacility {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        hashify::tiny_map!(value.as_bytes(),
+            "kern" => 0,
+            "user" => 1,
+            "mail" => 2,
+            "daemon" => 3,
+            "auth" => 4,
+            "syslog" => 5,
+            "lpr" => 6,
+            "news" => 7,
+            "uucp" => 8,
+            "cron" => 9,
+            "authpriv" => 10,
+            "ftp" => 11,
+            "local0" => 16,
+            "local1" => 17,
+            "local2" => 18,
+            "local3" => 19,
+            "local4" => 20,
+            "local5" => 21,
+            "local6" => 22,
+            "local7" => 23,
+        )
+        .or_else(|| value.parse::<u8>().ok().filter(|v| *v <= 23))
+        .map(SyslogFacility)
+        .ok_or_else(|| format!("Invalid syslog facility: {value}"))
+    }
+}
+
+impl ParseValue for SyslogSeverity {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        hashify::tiny_map!(value.as_bytes(),
+            "emerg" => SyslogSeverity::Emergency,
+            "emergency" => SyslogSeverity::Emergency,
+            "alert" => SyslogSeverity::Alert,
+            "crit" => SyslogSeverity::Critical,
+            "critical" => SyslogSeverity::Critical,
+            "err" => SyslogSeverity::Error,
+            "error" => SyslogSeverity::Error,
+            "warning" => SyslogSeverity::Warning,
+            "warn" => SyslogSeverity::Warning,
+            "notice" => SyslogSeverity::Notice,
+            "info" => SyslogSeverity::Informational,
+            "informational" => SyslogSeverity::Informational,
+            "debug" => SyslogSeverity::Debug,
+        )
+        .ok_or_else(|| format!("Invalid syslog severity: {value}"))
+    }
+}
+
 impl ParseValue for EventOrMany {
     fn parse_value(value: &str) -> Result<Self, String> {
         let value = value.trim();
@@ -896,3 +1124,13 @@ impl std::fmt::Debug for OtelMetrics {
             .finish()
     }
 }
+
+impl std::fmt::Debug for SyslogTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyslogTransport::Udp => f.write_str("Udp"),
+            SyslogTransport::Tcp => f.write_str("Tcp"),
+            SyslogTransport::Tls(_) => f.write_str("Tls"),
+        }
+    }
+}
@@ -10,8 +10,8 @@ use crate::{
     config::smtp::{
         auth::{ArcSealer, DkimSigner, LazySignature, ResolvedSignature, build_signature},
         queue::{
-            ConnectionStrategy, DEFAULT_QUEUE_NAME, MxConfig, QueueExpiry, QueueName,
-            QueueStrategy, RequireOptional, RoutingStrategy, TlsStrategy, VirtualQueue,
+            ConnectionStrategy, DEFAULT_QUEUE_NAME, MtaStsFallback, MxConfig, QueueExpiry,
+            QueueName, QueueStrategy, RequireOptional, RoutingStrategy, TlsStrategy, VirtualQueue,
         },
     },
     ipc::{BroadcastEvent, StateEvent},
@@ -27,6 +27,7 @@ use jmap_proto::types::{
 use mail_auth::IpLookupStrategy;
 use sieve::Sieve;
 use std::{
+    ops::Range,
     sync::{Arc, LazyLock},
     time::Duration,
 };
@@ -54,6 +55,15 @@ impl Server {
         &self.core.storage.blob
     }
 
+    #[inline(always)]
+    pub fn queue_blob_store(&self) -> &BlobStore {
+        self.core
+            .storage
+            .queue
+            .as_ref()
+            .unwrap_or(&self.core.storage.blob)
+    }
+
     #[inline(always)]
     pub fn fts_store(&self) -> &FtsStore {
         &self.core.storage.fts
@@ -69,6 +79,15 @@ impl Server {
         &self.core.storage.directory
     }
 
+    // Domain lookups are cached by each directory
+    pub fn invalidate_domain_cache(&self, domain: &str) {
+        for directory in std::iter::once(&self.core.storage.directory)
+            .chain(self.core.storage.directories.values())
+        {
+            directory.invalidate_domain(domain);
+        }
+    }
+
     pub fn get_directory(&self, name: &str) -> Option<&Arc<Directory>> {
         self.core.storage.directories.get(name)
     }
@@ -196,6 +215,7 @@ impl Server {
             max_mx: 5,
             max_multi_homed: 2,
             ip_lookup_strategy: IpLookupStrategy::Ipv4thenIpv6,
+            proxy: None,
         });
         self.core
             .smtp
@@ -278,6 +298,7 @@ impl Server {
         static DEFAULT_TLS: TlsStrategy = TlsStrategy {
             dane: RequireOptional::Optional,
             mta_sts: RequireOptional::Optional,
+            mta_sts_fallback: MtaStsFallback::EnforceCached,
             tls: RequireOptional::Optional,
             allow_invalid_certs: false,
             timeout_tls: Duration::from_secs(3 * 60),
@@ -307,6 +328,7 @@ impl Server {
             source_ipv4: Vec::new(),
             source_ipv6: Vec::new(),
             ehlo_hostname: None,
+            happy_eyeballs: Some(Duration::from_millis(250)),
             timeout_connect: Duration::from_secs(5 * 60),
             timeout_greeting: Duration::from_secs(5 * 60),
             timeout_ehlo: Duration::from_secs(5 * 60),
@@ -439,6 +461,7 @@ impl Server {
             ResourceToken {
                 account_id,
                 quota: access_token.quota,
+                message_quota: access_token.message_quota,
                 tenant: access_token.tenant,
             }
         } else {
@@ -456,6 +479,7 @@ impl Server {
                 .add_context(|err| err.caused_by(trc::location!()).account_id(account_id))?
             {
                 quotas.quota = principal.quota();
+                let mut tenant_message_quota = None;
 
                 // SPDX-SnippetBegin
                 // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
@@ -465,24 +489,26 @@ impl Server {
                 if self.core.is_enterprise_edition()
                     && let Some(tenant_id) = principal.tenant()
                 {
+                    let tenant = self
+                        .core
+                        .storage
+                        .directory
+                        .query(QueryParams::id(tenant_id).with_return_member_of(false))
+                        .await
+                        .add_context(|err| err.caused_by(trc::location!()).account_id(tenant_id))?;
+                    tenant_message_quota =
+                        tenant.as_ref().and_then(|tenant| tenant.message_quota());
                     quotas.tenant = TenantInfo {
                         id: tenant_id,
-                        quota: self
-                            .core
-                            .storage
-                            .directory
-                            .query(QueryParams::id(tenant_id).with_return_member_of(false))
-                            .await
-                            .add_context(|err| {
-                                err.caused_by(trc::location!()).account_id(tenant_id)
-                            })?
-                            .map(|tenant| tenant.quota())
-                            .unwrap_or_default(),
+                        quota: tenant.map(|tenant| tenant.quota()).unwrap_or_default(),
                     }
                     .into();
                 }
 
                 // SPDX-SnippetEnd
+
+                quotas.message_quota =
+                    self.message_quota(principal.message_quota(), tenant_message_quota);
             }
 
             quotas
@@ -613,6 +639,21 @@ impl Server {
         self.inner.ipc.task_tx.notify_one();
     }
 
+    pub async fn get_queued_blob(
+        &self,
+        hash: &[u8],
+        range: Range<usize>,
+    ) -> trc::Result<Option<Vec<u8>>> {
+        if let Some(queue) = &self.core.storage.queue
+            && let Some(blob) = queue.get_blob(hash, range.clone()).await?
+        {
+            return Ok(Some(blob));
+        }
+
+        // Messages queued before a spool was configured remain in the blob store
+        self.core.storage.blob.get_blob(hash, range).await
+    }
+
     pub async fn total_queued_messages(&self) -> trc::Result<u64> {
         let mut total = 0;
         self.store()
@@ -663,6 +704,10 @@ impl Server {
                         state_change.set_change(data_type);
                     }
                 }
+                // Quota usage follows changes to the account's messages
+                if state_change.types.contains(DataType::Email) {
+                    state_change.set_change(DataType::Quota);
+                }
                 if state_change.has_changes() {
                     self.broadcast_state_change(state_change).await;
                 }
@@ -175,6 +175,9 @@ impl Enterprise {
                     interval: config
                         .property_or_default::<SimpleCron>("metrics.history.interval", "0 * *")
                         .unwrap_or_else(|| SimpleCron::parse_value("0 * *").unwrap()),
+                    usage: config
+                        .property_or_default("metrics.history.usage", "false")
+                        .unwrap_or(false),
                 }
                 .into()
             } else {
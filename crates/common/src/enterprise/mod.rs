@@ -73,6 +73,7 @@ pub struct MetricStore {
     pub retention: Option<Duration>,
     pub store: Store,
     pub interval: SimpleCron,
+    pub usage: bool,
 }
 
 #[derive(Clone, Debug)]
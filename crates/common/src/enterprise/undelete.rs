@@ -31,6 +31,9 @@ pub struct DeletedBlob<H, T, C> {
     #[serde(rename = "expiresAt")]
     pub expires_at: T,
     pub collection: C,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mailbox: Option<String>,
 }
 
 impl Core {
@@ -40,24 +43,33 @@ impl Core {
         collection: u8,
         blob_hash: &BlobHash,
         blob_size: usize,
+        mailbox: Option<&str>,
     ) {
         if let Some(undelete) = self.enterprise.as_ref().and_then(|e| e.undelete.as_ref()) {
             let now = now();
+            let mailbox = mailbox.unwrap_or_default();
 
             batch.set(
                 BlobOp::Reserve {
                     hash: blob_hash.clone(),
                     until: now + undelete.retention.as_secs(),
                 },
-                KeySerializer::new(U64_LEN + U64_LEN)
+                KeySerializer::new(U64_LEN + U64_LEN + mailbox.len())
                     .write(blob_size as u32)
                     .write(now)
                     .write(collection)
+                    .write(mailbox.as_bytes())
                     .finalize(),
             );
         }
     }
 
+    pub fn is_undelete_enabled(&self) -> bool {
+        self.enterprise
+            .as_ref()
+            .is_some_and(|e| e.undelete.is_some())
+    }
+
     pub async fn list_deleted(
         &self,
         account_id: u32,
@@ -90,7 +102,7 @@ impl Core {
                 IterateParams::new(from_key, to_key).ascending(),
                 |key, value| {
                     let expires_at = key.deserialize_be_u64(key.len() - U64_LEN)?;
-                    if value.len() == U32_LEN + U64_LEN + 1 && expires_at > now {
+                    if value.len() > U32_LEN + U64_LEN && expires_at > now {
                         results.push(DeletedBlob {
                             hash: BlobHash::try_from_hash_slice(
                                 key.get(U32_LEN..U32_LEN + BLOB_HASH_LEN).ok_or_else(|| {
@@ -101,7 +113,11 @@ impl Core {
                             size: value.deserialize_be_u32(0)? as usize,
                             deleted_at: value.deserialize_be_u64(U32_LEN)?,
                             expires_at,
-                            collection: *value.last().unwrap(),
+                            collection: value[U32_LEN + U64_LEN],
+                            mailbox: value
+                                .get(U32_LEN + U64_LEN + 1..)
+                                .filter(|path| !path.is_empty())
+                                .map(|path| String::from_utf8_lossy(path).into_owned()),
                         });
                     }
                     Ok(true)
@@ -11,8 +11,9 @@ use directory::backend::RcptType;
 use mail_auth::IpLookupStrategy;
 use store::{Deserialize, Rows, Value, dispatch::lookup::KeyValue};
 use trc::AddContext;
+use utils::BlobHash;
 
-use crate::{Server, expr::StringCow};
+use crate::{KV_HTTP_QUERY, Server, expr::StringCow};
 
 use super::*;
 
@@ -104,6 +105,7 @@ impl Server {
             }
             F_DNS_QUERY => self.dns_query(params).await,
             F_SQL_QUERY => self.sql_query(params, session_id).await,
+            F_HTTP_QUERY => self.http_query(params).await,
             _ => Ok(Variable::default()),
         }
     }
@@ -170,6 +172,76 @@ impl Server {
         }
     }
 
+    async fn http_query<'x>(&self, mut arguments: FncParams<'x>) -> trc::Result<Variable<'x>> {
+        let url = arguments.next_as_string();
+        let method = arguments.next_as_string();
+        let body = arguments.next_as_string();
+
+        // Only URLs belonging to a configured endpoint can be queried
+        let Some((endpoint, url)) = reqwest::Url::parse(url.as_str()).ok().and_then(|url| {
+            self.core
+                .storage
+                .http_queries
+                .values()
+                .filter(|endpoint| endpoint.matches(&url))
+                .max_by_key(|endpoint| endpoint.url.path().len())
+                .map(|endpoint| (endpoint, url))
+        }) else {
+            return Err(trc::EventType::Eval(trc::EvalEvent::Error)
+                .into_err()
+                .ctx(trc::Key::Url, url.as_str().to_compact_string())
+                .details("No HTTP lookup endpoint configured for URL"));
+        };
+
+        // Responses are cached in the lookup store to avoid querying the endpoint on every call
+        let cache_key = BlobHash::generate(
+            format!(
+                "{}\n{}\n{}\n{}",
+                endpoint.id,
+                method.as_str(),
+                url.as_str(),
+                body.as_str()
+            )
+            .as_bytes(),
+        );
+        if let Some(result) = self
+            .in_memory_store()
+            .key_get::<String>(KeyValue::<()>::build_key(
+                KV_HTTP_QUERY,
+                cache_key.as_slice(),
+            ))
+            .await
+            .caused_by(trc::location!())?
+        {
+            return Ok(Variable::from(CompactString::from(result)));
+        }
+
+        // Failed queries are cached as empty results to avoid retrying a failing endpoint
+        let result = endpoint
+            .query(method.as_str(), &url, body.as_str())
+            .await
+            .caused_by(trc::location!());
+        let (cached, ttl) = match &result {
+            Ok(Some(value)) => (value.as_str(), endpoint.ttl_positive),
+            Ok(None) | Err(_) => ("", endpoint.ttl_negative),
+        };
+        if !ttl.is_zero() {
+            self.in_memory_store()
+                .key_set(
+                    KeyValue::with_prefix(
+                        KV_HTTP_QUERY,
+                        cache_key.as_slice(),
+                        cached.as_bytes().to_vec(),
+                    )
+                    .expires(ttl.as_secs()),
+                )
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        result.map(|value| Variable::from(CompactString::from(value.unwrap_or_default())))
+    }
+
     async fn dns_query<'x>(&self, mut arguments: FncParams<'x>) -> trc::Result<Variable<'x>> {
         let entry = arguments.next_as_string();
         let record_type = arguments.next_as_string();
@@ -78,6 +78,7 @@ pub(crate) const FUNCTIONS: &[(&str, fn(Vec<Variable>) -> Variable, u32)] = &[
     ("split_n", text::fn_split_n, 3),
     ("split_words", text::fn_split_words, 1),
     ("hash", text::fn_hash, 2),
+    ("url_encode", text::fn_url_encode, 1),
     ("if_then", misc::fn_if_then, 3),
 ];
 
@@ -90,6 +91,7 @@ pub const F_COUNTER_INCR: u32 = 5;
 pub const F_COUNTER_GET: u32 = 6;
 pub const F_SQL_QUERY: u32 = 7;
 pub const F_DNS_QUERY: u32 = 8;
+pub const F_HTTP_QUERY: u32 = 9;
 
 pub const ASYNC_FUNCTIONS: &[(&str, u32, u32)] = &[
     ("is_local_domain", F_IS_LOCAL_DOMAIN, 2),
@@ -101,4 +103,5 @@ pub const ASYNC_FUNCTIONS: &[(&str, u32, u32)] = &[
     ("counter_get", F_COUNTER_GET, 2),
     ("dns_query", F_DNS_QUERY, 2),
     ("sql_query", F_SQL_QUERY, 3),
+    ("http_query", F_HTTP_QUERY, 3),
 ];
@@ -4,6 +4,8 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::fmt::Write;
+
 use compact_str::{CompactString, ToCompactString, format_compact};
 use sha1::Sha1;
 use sha2::{Sha256, Sha512};
@@ -330,6 +332,20 @@ pub(crate) fn fn_rsplit_once(v: Vec<Variable>) -> Variable {
     }
 }
 
+pub(crate) fn fn_url_encode(mut v: Vec<Variable>) -> Variable {
+    v.remove(0).transform(|s| {
+        let mut result = CompactString::with_capacity(s.len());
+        for byte in s.as_str().bytes() {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                result.push(byte as char);
+            } else {
+                let _ = write!(result, "%{byte:02X}");
+            }
+        }
+        result.into()
+    })
+}
+
 pub(crate) fn fn_hash(v: Vec<Variable>) -> Variable {
     use sha1::Digest;
     let mut v = v.into_iter();
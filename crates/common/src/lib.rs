@@ -26,7 +26,13 @@ use config::{
 };
 use ipc::{BroadcastEvent, HousekeeperEvent, QueueEvent, ReportingEvent, StateEvent};
 use jmap_proto::types::value::AclGrant;
-use listener::{asn::AsnGeoLookupData, blocked::Security, tls::AcmeProviders};
+use listener::{
+    asn::AsnGeoLookupData,
+    blocked::Security,
+    limiter::{BandwidthLimiter, ConnectionTracker},
+    sessions::SessionRegistry,
+    tls::AcmeProviders,
+};
 use mail_auth::{MX, Txt};
 use manager::webadmin::{Resource, WebAdminManager};
 use nlp::bayes::{TokenHash, Weights};
@@ -122,6 +128,26 @@ pub const KV_LOCK_TASK: u8 = 23;
 pub const KV_LOCK_HOUSEKEEPER: u8 = 24;
 pub const KV_LOCK_DAV: u8 = 25;
 pub const KV_SIEVE_ID: u8 = 26;
+pub const KV_PROVIDER_BACKOFF: u8 = 27;
+pub const KV_SUPPRESSION: u8 = 28;
+pub const KV_IMPORT_CHECKPOINT: u8 = 29;
+pub const KV_KNOWN_DEVICE: u8 = 30;
+pub const KV_AUTH_FAILURES: u8 = 31;
+pub const KV_ACCOUNT_LOCKOUT: u8 = 32;
+pub const KV_IP_BAN: u8 = 33;
+pub const KV_SIEVE_NOTIFY: u8 = 34;
+pub const KV_OUT_OF_OFFICE: u8 = 35;
+pub const KV_FILE_SHARE: u8 = 36;
+pub const KV_IMAGE_PROXY: u8 = 37;
+pub const KV_CONTACT_COLLECT: u8 = 38;
+pub const KV_QUOTA_WARNING: u8 = 39;
+pub const KV_DISPOSABLE_ALIAS: u8 = 40;
+pub const KV_DISPOSABLE_ALIAS_RECEIVED: u8 = 41;
+pub const KV_MTA_STS_POLICY: u8 = 42;
+pub const KV_TLS_POSTURE: u8 = 43;
+pub const KV_HTTP_QUERY: u8 = 44;
+pub const KV_USAGE: u8 = 45;
+pub const KV_SELF_TEST: u8 = 46;
 
 pub const IDX_UID: u8 = 0;
 pub const IDX_EMAIL: u8 = 1;
@@ -157,6 +183,10 @@ pub struct Data {
     pub logos: Mutex<AHashMap<String, Option<Resource<Vec<u8>>>>>,
 
     pub smtp_connectors: TlsConnectors,
+
+    pub imap_bandwidth: Mutex<AHashMap<u32, Arc<BandwidthLimiter>>>,
+    pub connections: ConnectionTracker,
+    pub sessions: SessionRegistry,
 }
 
 pub struct Caches {
@@ -180,6 +210,8 @@ pub struct Caches {
     pub dns_tlsa: CacheWithTtl<String, Arc<Tlsa>>,
     pub dbs_mta_sts: CacheWithTtl<String, Arc<Policy>>,
     pub dns_rbl: CacheWithTtl<String, Option<Arc<IpResolver>>>,
+
+    pub ip_family: CacheWithTtl<String, bool>,
 }
 
 #[derive(Debug, Clone)]
@@ -208,6 +240,8 @@ pub struct MessagesCache {
     pub items: Vec<MessageCache>,
     pub index: AHashMap<u32, u32>,
     pub keywords: Vec<String>,
+    pub keyword_mask: u128,
+    pub mailbox_keyword_masks: AHashMap<u32, u128>,
     pub size: u64,
 }
 
@@ -491,6 +525,7 @@ impl Default for Caches {
             dns_ipv6: CacheWithTtl::new(1024, 10 * 1024 * 1024),
             dns_tlsa: CacheWithTtl::new(1024, 10 * 1024 * 1024),
             dbs_mta_sts: CacheWithTtl::new(1024, 10 * 1024 * 1024),
+            ip_family: CacheWithTtl::new(1024, 1024 * 1024),
         }
     }
 }
@@ -7,6 +7,7 @@
 use std::{fmt::Debug, net::IpAddr};
 
 use ahash::AHashSet;
+use store::dispatch::lookup::KeyValue;
 use utils::{
     config::{
         Config, ConfigKey, Rate,
@@ -17,8 +18,9 @@ use utils::{
 };
 
 use crate::{
-    KV_RATE_LIMIT_AUTH, KV_RATE_LIMIT_LOITER, KV_RATE_LIMIT_RCPT, KV_RATE_LIMIT_SCAN, Server,
-    ip_to_bytes, ipc::BroadcastEvent, manager::config::MatchType,
+    KV_IP_BAN, KV_RATE_LIMIT_AUTH, KV_RATE_LIMIT_LOITER, KV_RATE_LIMIT_RCPT, KV_RATE_LIMIT_SCAN,
+    Server, auth::lockout::AccountLockout, ip_to_bytes, ipc::BroadcastEvent,
+    manager::config::MatchType,
 };
 
 #[derive(Debug, Clone)]
@@ -36,6 +38,8 @@ pub struct Security {
     auth_fail_rate: Option<Rate>,
     rcpt_fail_rate: Option<Rate>,
     loiter_fail_rate: Option<Rate>,
+
+    pub account_lockout: Option<AccountLockout>,
 }
 
 pub const BLOCKED_IP_KEY: &str = "server.blocked-ip";
@@ -132,6 +136,7 @@ impl Security {
             scanner_fail_rate: config
                 .property_or_default::<Option<Rate>>("server.auto-ban.scan.rate", "30/1d")
                 .unwrap_or_default(),
+            account_lockout: AccountLockout::parse(config),
         }
     }
 }
@@ -250,6 +255,19 @@ impl Server {
         Ok(())
     }
 
+    // Temporary bans are kept in the in-memory store and expire on their own
+    pub async fn ban_ip_for(&self, ip: IpAddr, expires: u64) -> trc::Result<()> {
+        self.in_memory_store()
+            .key_set(KeyValue::with_prefix(KV_IP_BAN, ip_to_bytes(&ip), vec![]).expires(expires))
+            .await
+    }
+
+    pub async fn is_ip_banned(&self, ip: IpAddr) -> trc::Result<bool> {
+        self.in_memory_store()
+            .key_exists(KeyValue::<()>::build_key(KV_IP_BAN, ip_to_bytes(&ip)))
+            .await
+    }
+
     pub fn has_auth_fail2ban(&self) -> bool {
         self.core.network.security.auth_fail_rate.is_some()
     }
@@ -330,6 +348,7 @@ impl Default for Security {
             loiter_fail_rate: Default::default(),
             scanner_fail_rate: Default::default(),
             http_banned_paths: Default::default(),
+            account_lockout: Default::default(),
         }
     }
 }
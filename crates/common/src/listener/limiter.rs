@@ -4,9 +4,22 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::sync::{
-    Arc,
-    atomic::{AtomicU64, Ordering},
+use std::{
+    hash::Hash,
+    net::IpAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+
+use crate::{
+    Server,
+    config::{network::ConnectionLimitAction, server::ServerProtocol},
 };
 
 #[derive(Debug, Clone)]
@@ -18,6 +31,27 @@ pub struct ConcurrencyLimiter {
 #[derive(Default)]
 pub struct InFlight {
     concurrent: Arc<AtomicU64>,
+    linked: Option<Box<InFlight>>,
+}
+
+// Open connections by protocol and principal or remote address
+#[derive(Debug, Default)]
+pub struct ConnectionTracker {
+    accounts: Mutex<AHashMap<(ServerProtocol, u32), Arc<AtomicU64>>>,
+    ips: Mutex<AHashMap<(ServerProtocol, IpAddr), Arc<AtomicU64>>>,
+}
+
+// Token bucket allowing bursts of up to one second worth of bytes
+#[derive(Debug)]
+pub struct BandwidthLimiter {
+    bucket: Mutex<TokenBucket>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    rate: u64,
+    tokens: f64,
+    last_refill: Instant,
 }
 
 pub enum LimiterResult {
@@ -46,6 +80,7 @@ impl ConcurrencyLimiter {
             self.concurrent.fetch_add(1, Ordering::Relaxed);
             LimiterResult::Allowed(InFlight {
                 concurrent: self.concurrent.clone(),
+                linked: None,
             })
         } else {
             LimiterResult::Forbidden
@@ -65,6 +100,147 @@ impl InFlight {
     pub fn num_concurrent(&self) -> u64 {
         self.concurrent.load(Ordering::Relaxed)
     }
+
+    // Keeps another in-flight counter alive for as long as this one
+    pub fn link(mut self, other: Option<InFlight>) -> Self {
+        if let Some(other) = other {
+            self.linked = Some(Box::new(other));
+        }
+        self
+    }
+}
+
+impl ConnectionTracker {
+    pub fn account_connection(
+        &self,
+        protocol: ServerProtocol,
+        account_id: u32,
+        max_concurrent: u64,
+    ) -> LimiterResult {
+        track_connection(&self.accounts, (protocol, account_id), max_concurrent)
+    }
+
+    pub fn ip_connection(
+        &self,
+        protocol: ServerProtocol,
+        ip: IpAddr,
+        max_concurrent: u64,
+    ) -> LimiterResult {
+        track_connection(&self.ips, (protocol, ip), max_concurrent)
+    }
+
+    pub fn account_connections(&self) -> Vec<((ServerProtocol, u32), u64)> {
+        list_connections(&self.accounts)
+    }
+
+    pub fn ip_connections(&self) -> Vec<((ServerProtocol, IpAddr), u64)> {
+        list_connections(&self.ips)
+    }
+}
+
+impl Server {
+    // Tracks a new connection from a remote address, returns None when it must be dropped
+    pub fn limit_ip_connections(
+        &self,
+        protocol: ServerProtocol,
+        remote_ip: IpAddr,
+        in_flight: InFlight,
+    ) -> Option<InFlight> {
+        let Some(limits) = self.core.network.connection_limits.get(protocol) else {
+            return Some(in_flight);
+        };
+        let max_concurrent = limits
+            .per_ip
+            .filter(|_| !self.is_ip_allowed(&remote_ip))
+            .unwrap_or(u64::MAX);
+
+        match self
+            .inner
+            .data
+            .connections
+            .ip_connection(protocol, remote_ip, max_concurrent)
+        {
+            LimiterResult::Allowed(connection) => Some(in_flight.link(Some(connection))),
+            _ => {
+                trc::event!(
+                    Limit(trc::LimitEvent::IpConnections),
+                    RemoteIp = remote_ip,
+                    Type = protocol.as_str(),
+                    Limit = max_concurrent,
+                );
+
+                (limits.action == ConnectionLimitAction::Log).then_some(in_flight)
+            }
+        }
+    }
+
+    // Tracks a new authenticated connection for an account
+    pub fn limit_account_connections(
+        &self,
+        protocol: ServerProtocol,
+        account_id: u32,
+        in_flight: Option<InFlight>,
+        session_id: u64,
+    ) -> trc::Result<Option<InFlight>> {
+        let Some(limits) = self.core.network.connection_limits.get(protocol) else {
+            return Ok(in_flight);
+        };
+        let max_concurrent = limits.per_account.unwrap_or(u64::MAX);
+
+        match self
+            .inner
+            .data
+            .connections
+            .account_connection(protocol, account_id, max_concurrent)
+        {
+            LimiterResult::Allowed(connection) => Ok(Some(connection.link(in_flight))),
+            _ if limits.action == ConnectionLimitAction::Log => {
+                trc::event!(
+                    Limit(trc::LimitEvent::AccountConnections),
+                    SpanId = session_id,
+                    AccountId = account_id,
+                    Type = protocol.as_str(),
+                    Limit = max_concurrent,
+                );
+
+                Ok(in_flight)
+            }
+            _ => Err(trc::LimitEvent::AccountConnections
+                .into_err()
+                .account_id(account_id)
+                .ctx(trc::Key::Type, protocol.as_str())
+                .ctx(trc::Key::Limit, max_concurrent)),
+        }
+    }
+}
+
+fn track_connection<K: Hash + Eq>(
+    map: &Mutex<AHashMap<K, Arc<AtomicU64>>>,
+    key: K,
+    max_concurrent: u64,
+) -> LimiterResult {
+    let mut map = map.lock();
+    if map.len() >= 1024 {
+        map.retain(|_, concurrent| concurrent.load(Ordering::Relaxed) > 0);
+    }
+
+    ConcurrencyLimiter {
+        max_concurrent,
+        concurrent: map.entry(key).or_default().clone(),
+    }
+    .is_allowed()
+}
+
+fn list_connections<K: Hash + Eq + Copy>(
+    map: &Mutex<AHashMap<K, Arc<AtomicU64>>>,
+) -> Vec<(K, u64)> {
+    map.lock()
+        .iter()
+        .filter_map(|(key, concurrent)| {
+            let concurrent = concurrent.load(Ordering::Relaxed);
+            (concurrent > 0).then_some((*key, concurrent))
+        })
+        .collect()
 }
 
 impl From<LimiterResult> for Option<InFlight> {
@@ -76,3 +252,117 @@ impl From<LimiterResult> for Option<InFlight> {
         }
     }
 }
+
+impl BandwidthLimiter {
+    pub fn new(rate: u64) -> Self {
+        BandwidthLimiter {
+            bucket: Mutex::new(TokenBucket {
+                rate,
+                tokens: rate as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn rate(&self) -> u64 {
+        self.bucket.lock().rate
+    }
+
+    // Changes the rate without discarding the bytes already consumed
+    pub fn set_rate(&self, rate: u64) {
+        let mut bucket = self.bucket.lock();
+        if bucket.rate != rate {
+            bucket.refill(Instant::now());
+            bucket.rate = rate;
+            bucket.tokens = bucket.tokens.min(rate as f64);
+        }
+    }
+
+    // Consumes the tokens and returns how long to wait before sending
+    pub fn reserve(&self, bytes: usize) -> Option<Duration> {
+        self.reserve_at(bytes, Instant::now())
+    }
+
+    fn reserve_at(&self, bytes: usize, now: Instant) -> Option<Duration> {
+        let mut bucket = self.bucket.lock();
+        if bucket.rate == 0 {
+            return None;
+        }
+        bucket.refill(now);
+        bucket.tokens -= bytes as f64;
+
+        if bucket.tokens < 0.0 {
+            Some(Duration::from_secs_f64(-bucket.tokens / bucket.rate as f64))
+        } else {
+            None
+        }
+    }
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now: Instant) {
+        let rate = self.rate as f64;
+        self.tokens = (self.tokens
+            + now
+                .saturating_duration_since(self.last_refill)
+                .as_secs_f64()
+                * rate)
+            .min(rate);
+        self.last_refill = now;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bandwidth_limiter() {
+        // Bursts of up to one second worth of bytes are sent right away
+        let limiter = BandwidthLimiter::new(1000);
+        let start = limiter.bucket.lock().last_refill;
+        assert_eq!(limiter.reserve_at(600, start), None);
+        assert_eq!(limiter.reserve_at(400, start), None);
+        assert_eq!(
+            limiter.reserve_at(500, start),
+            Some(Duration::from_millis(500))
+        );
+
+        // Tokens are refilled at the configured rate
+        let now = start + Duration::from_millis(1500);
+        assert_eq!(limiter.reserve_at(1000, now), None);
+        assert_eq!(
+            limiter.reserve_at(250, now),
+            Some(Duration::from_millis(250))
+        );
+
+        // Idle time does not accumulate more than the burst size
+        let now = now + Duration::from_secs(60);
+        assert_eq!(limiter.reserve_at(1000, now), None);
+        assert_eq!(
+            limiter.reserve_at(100, now),
+            Some(Duration::from_millis(100))
+        );
+
+        // Changing the rate keeps the bytes already consumed
+        limiter.set_rate(2000);
+        assert_eq!(limiter.rate(), 2000);
+        let now = limiter.bucket.lock().last_refill;
+        assert_eq!(
+            limiter.reserve_at(100, now),
+            Some(Duration::from_millis(100))
+        );
+
+        // A zero rate is unlimited
+        let limiter = BandwidthLimiter::new(0);
+        assert_eq!(limiter.reserve(usize::MAX), None);
+        limiter.set_rate(1000);
+        assert!(limiter.reserve(2000).is_some());
+        limiter.set_rate(0);
+        assert_eq!(limiter.reserve(usize::MAX), None);
+
+        // Very large rates never wait
+        let limiter = BandwidthLimiter::new(u64::MAX);
+        assert_eq!(limiter.reserve(1024 * 1024 * 1024), None);
+    }
+}
@@ -30,6 +30,7 @@ pub mod asn;
 pub mod blocked;
 pub mod limiter;
 pub mod listen;
+pub mod sessions;
 pub mod stream;
 pub mod tls;
 
@@ -92,6 +93,7 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
     fn spawn<T: SessionStream>(
         &self,
         mut session: SessionData<T>,
+        server: Server,
         is_tls: bool,
         acme_core: Option<Server>,
         span_start: EventType,
@@ -131,17 +133,20 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
                             .send_with_metrics();
 
                             manager
-                                .handle(SessionData {
-                                    stream,
-                                    local_ip: session.local_ip,
-                                    local_port: session.local_port,
-                                    remote_ip: session.remote_ip,
-                                    remote_port: session.remote_port,
-                                    protocol: session.protocol,
-                                    session_id: session.session_id,
-                                    in_flight: session.in_flight,
-                                    instance: session.instance,
-                                })
+                                .handle_tracked(
+                                    &server,
+                                    SessionData {
+                                        stream,
+                                        local_ip: session.local_ip,
+                                        local_port: session.local_port,
+                                        remote_ip: session.remote_ip,
+                                        remote_port: session.remote_port,
+                                        protocol: session.protocol,
+                                        session_id: session.session_id,
+                                        in_flight: session.in_flight,
+                                        instance: session.instance,
+                                    },
+                                )
                                 .await;
                         }
                         Err(err) => {
@@ -176,7 +181,7 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
                         .send_with_metrics();
 
                         session.stream = stream;
-                        manager.handle(session).await;
+                        manager.handle_tracked(&server, session).await;
                     }
                     TcpAcceptorResult::Close => return,
                 }
@@ -198,7 +203,7 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
                 )
                 .send_with_metrics();
 
-                manager.handle(session).await;
+                manager.handle_tracked(&server, session).await;
             }
 
             // End span
@@ -218,6 +223,32 @@ pub trait SessionManager: Sync + Send + 'static + Clone {
         session: SessionData<T>,
     ) -> impl std::future::Future<Output = ()> + Send;
 
+    fn handle_tracked<T: SessionStream>(
+        self,
+        server: &Server,
+        session: SessionData<T>,
+    ) -> impl std::future::Future<Output = ()> + Send {
+        async move {
+            // Register the session so it can be listed and terminated
+            let session_id = session.session_id;
+            let (session, active) = server.track_session(session);
+
+            tokio::select! {
+                _ = self.handle(session) => {}
+                _ = active.disconnected() => {
+                    trc::event!(
+                        Network(trc::NetworkEvent::SessionTerminated),
+                        SpanId = session_id,
+                        RemoteIp = active.remote_ip,
+                        RemotePort = active.remote_port,
+                    );
+                }
+            }
+
+            server.untrack_session(session_id);
+        }
+    }
+
     fn shutdown(&self) -> impl std::future::Future<Output = ()> + Send;
 }
 
@@ -0,0 +1,211 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    borrow::Cow,
+    net::IpAddr,
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicU32, AtomicU64, Ordering},
+    },
+    task::{Context, Poll},
+};
+
+use ahash::AHashMap;
+use parking_lot::Mutex;
+use store::write::now;
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    sync::Notify,
+};
+
+use crate::{Server, config::server::ServerProtocol};
+
+use super::{SessionData, SessionStream};
+
+// Live protocol sessions by session id
+#[derive(Debug, Default)]
+pub struct SessionRegistry {
+    sessions: Mutex<AHashMap<u64, Arc<ActiveSession>>>,
+}
+
+#[derive(Debug)]
+pub struct ActiveSession {
+    pub session_id: u64,
+    pub protocol: ServerProtocol,
+    pub listener_id: String,
+    pub remote_ip: IpAddr,
+    pub remote_port: u16,
+    pub local_port: u16,
+    pub created_at: u64,
+    last_activity: AtomicU64,
+    account_id: AtomicU32,
+    bytes_received: AtomicU64,
+    bytes_sent: AtomicU64,
+    disconnect: Notify,
+}
+
+// Stream wrapper that accounts for traffic and activity of a session
+pub struct TrackedStream<T: SessionStream> {
+    stream: T,
+    session: Arc<ActiveSession>,
+}
+
+impl SessionRegistry {
+    pub fn list(&self) -> Vec<Arc<ActiveSession>> {
+        self.sessions.lock().values().cloned().collect()
+    }
+
+    pub fn get(&self, session_id: u64) -> Option<Arc<ActiveSession>> {
+        self.sessions.lock().get(&session_id).cloned()
+    }
+
+    pub fn set_account_id(&self, session_id: u64, account_id: u32) {
+        if let Some(session) = self.sessions.lock().get(&session_id) {
+            session.account_id.store(account_id, Ordering::Relaxed);
+        }
+    }
+
+    fn insert(&self, session: Arc<ActiveSession>) {
+        self.sessions.lock().insert(session.session_id, session);
+    }
+
+    fn remove(&self, session_id: u64) {
+        self.sessions.lock().remove(&session_id);
+    }
+}
+
+impl ActiveSession {
+    pub fn account_id(&self) -> Option<u32> {
+        let account_id = self.account_id.load(Ordering::Relaxed);
+        (account_id != u32::MAX).then_some(account_id)
+    }
+
+    pub fn last_activity(&self) -> u64 {
+        self.last_activity.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.load(Ordering::Relaxed)
+    }
+
+    pub fn disconnect(&self) {
+        self.disconnect.notify_one();
+    }
+
+    pub async fn disconnected(&self) {
+        self.disconnect.notified().await
+    }
+
+    fn touch(&self) {
+        self.last_activity.store(now(), Ordering::Relaxed);
+    }
+}
+
+impl Server {
+    pub fn track_session<T: SessionStream>(
+        &self,
+        session: SessionData<T>,
+    ) -> (SessionData<TrackedStream<T>>, Arc<ActiveSession>) {
+        let created_at = now();
+        let active = Arc::new(ActiveSession {
+            session_id: session.session_id,
+            protocol: session.protocol,
+            listener_id: session.instance.id.clone(),
+            remote_ip: session.remote_ip,
+            remote_port: session.remote_port,
+            local_port: session.local_port,
+            created_at,
+            last_activity: AtomicU64::new(created_at),
+            account_id: AtomicU32::new(u32::MAX),
+            bytes_received: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            disconnect: Notify::new(),
+        });
+        self.inner.data.sessions.insert(active.clone());
+
+        (
+            SessionData {
+                stream: TrackedStream {
+                    stream: session.stream,
+                    session: active.clone(),
+                },
+                local_ip: session.local_ip,
+                local_port: session.local_port,
+                remote_ip: session.remote_ip,
+                remote_port: session.remote_port,
+                protocol: session.protocol,
+                session_id: session.session_id,
+                in_flight: session.in_flight,
+                instance: session.instance,
+            },
+            active,
+        )
+    }
+
+    pub fn untrack_session(&self, session_id: u64) {
+        self.inner.data.sessions.remove(session_id);
+    }
+}
+
+impl<T: SessionStream> AsyncRead for TrackedStream<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.stream).poll_read(cx, buf);
+        let bytes = buf.filled().len().saturating_sub(filled);
+        if bytes > 0 {
+            self.session
+                .bytes_received
+                .fetch_add(bytes as u64, Ordering::Relaxed);
+            self.session.touch();
+        }
+        result
+    }
+}
+
+impl<T: SessionStream> AsyncWrite for TrackedStream<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let result = Pin::new(&mut self.stream).poll_write(cx, buf);
+        if let Poll::Ready(Ok(bytes)) = &result {
+            self.session
+                .bytes_sent
+                .fetch_add(*bytes as u64, Ordering::Relaxed);
+            self.session.touch();
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+impl<T: SessionStream> SessionStream for TrackedStream<T> {
+    fn is_tls(&self) -> bool {
+        self.stream.is_tls()
+    }
+
+    fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>) {
+        self.stream.tls_version_and_cipher()
+    }
+}
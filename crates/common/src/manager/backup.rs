@@ -388,6 +388,7 @@ impl Core {
     fn backup_blob(&self, dest: &Path) -> TaskHandle {
         let store = self.storage.data.clone();
         let blob_store = self.storage.blob.clone();
+        let queue_store = self.storage.queue.clone();
         let (handle, writer) = spawn_writer(dest.join("blob"));
         (
             tokio::spawn(async move {
@@ -456,11 +457,22 @@ impl Core {
                         .send(Op::DocumentId(u32::MAX))
                         .failed("Failed to send document id");
                     for hash in hashes {
-                        if let Some(value) = blob_store
+                        let mut value = blob_store
                             .get_blob(&hash, 0..usize::MAX)
                             .await
-                            .failed("Failed to get blob")
+                            .failed("Failed to get blob");
+
+                        // Queued messages may be spooled to a separate blob store
+                        if value.is_none()
+                            && let Some(queue_store) = &queue_store
                         {
+                            value = queue_store
+                                .get_blob(&hash, 0..usize::MAX)
+                                .await
+                                .failed("Failed to get blob");
+                        }
+
+                        if let Some(value) = value {
                             writer
                                 .send(Op::KeyValue((hash, value)))
                                 .failed("Failed to send key value");
@@ -10,6 +10,7 @@ use std::{
     sync::Arc,
 };
 
+use super::history::SETTINGS_HISTORY_PREFIX;
 use ahash::AHashMap;
 use arc_swap::ArcSwap;
 use store::{
@@ -156,7 +157,7 @@ impl ConfigManager {
                         trc::Error::corrupted_key(key, value.into(), trc::location!())
                     })?;
 
-                    if !patterns.is_local_key(key) {
+                    if !patterns.is_local_key(key) && !key.starts_with(SETTINGS_HISTORY_PREFIX) {
                         if strip_prefix && !prefix.is_empty() {
                             key = key.strip_prefix(prefix).unwrap_or(key);
                         }
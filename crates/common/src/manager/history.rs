@@ -0,0 +1,202 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::collections::{BTreeMap, btree_map::Entry};
+
+use serde::{Deserialize, Serialize};
+use store::{
+    IterateParams, ValueKey,
+    write::{BatchBuilder, ValueClass, now},
+};
+use trc::AddContext;
+use utils::config::ConfigKey;
+
+use super::config::ConfigManager;
+
+// Versions are stored next to the settings under a reserved prefix that is never listed
+pub const SETTINGS_HISTORY_PREFIX: &str = "@history.";
+const HISTORY_MAX_VERSIONS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SettingsVersion {
+    pub id: u64,
+    pub timestamp: u64,
+    pub author: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // Values of the modified keys before the change was applied
+    pub previous: BTreeMap<String, Option<String>>,
+}
+
+impl ConfigManager {
+    pub async fn snapshot_keys(
+        &self,
+        keys: impl IntoIterator<Item = String>,
+    ) -> trc::Result<BTreeMap<String, Option<String>>> {
+        let mut snapshot = BTreeMap::new();
+        for key in keys {
+            if let Entry::Vacant(entry) = snapshot.entry(key) {
+                let value = self.get(entry.key()).await?;
+                entry.insert(value);
+            }
+        }
+        Ok(snapshot)
+    }
+
+    pub async fn record_version(
+        &self,
+        author: &str,
+        description: Option<String>,
+        previous: BTreeMap<String, Option<String>>,
+    ) -> trc::Result<u64> {
+        loop {
+            let mut batch = BatchBuilder::new();
+            let id = add_version(
+                &mut batch,
+                self.versions().await?,
+                author,
+                description.clone(),
+                previous.clone(),
+            );
+
+            match self.cfg_store.write(batch.build_all()).await {
+                Ok(_) => return Ok(id),
+                // Another change was recorded concurrently with the same id, start over
+                Err(err)
+                    if err.matches(trc::EventType::Store(trc::StoreEvent::AssertValueFailed)) => {}
+                Err(err) => return Err(err.caused_by(trc::location!())),
+            }
+        }
+    }
+
+    // Returns all versions, newest first
+    pub async fn versions(&self) -> trc::Result<Vec<SettingsVersion>> {
+        let from_key = ValueKey::from(ValueClass::Config(
+            SETTINGS_HISTORY_PREFIX.as_bytes().to_vec(),
+        ));
+        let to_key = ValueKey::from(ValueClass::Config(
+            SETTINGS_HISTORY_PREFIX
+                .as_bytes()
+                .iter()
+                .copied()
+                .chain([u8::MAX, u8::MAX, u8::MAX, u8::MAX, u8::MAX])
+                .collect::<Vec<_>>(),
+        ));
+        let mut versions = Vec::new();
+
+        self.cfg_store
+            .iterate(
+                IterateParams::new(from_key, to_key).descending(),
+                |_, value| {
+                    if let Ok(version) = serde_json::from_slice::<SettingsVersion>(value) {
+                        versions.push(version);
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(versions)
+    }
+
+    // Reverts the changes made by a version and all the versions that followed it
+    pub async fn rollback(&self, id: u64, author: &str) -> trc::Result<Option<u64>> {
+        loop {
+            let versions = self.versions().await?;
+            let reverted = versions
+                .iter()
+                .take_while(|version| version.id >= id)
+                .collect::<Vec<_>>();
+            if reverted.last().is_none_or(|version| version.id != id) {
+                return Ok(None);
+            }
+
+            // Older versions hold the values the keys had before the reverted changes
+            let mut restore = BTreeMap::new();
+            for version in reverted {
+                restore.extend(version.previous.clone());
+            }
+
+            // Database keys are restored in the same batch that records the version,
+            // so a concurrent change either precedes the rollback or is retried after it
+            let previous = self.snapshot_keys(restore.keys().cloned()).await?;
+            let mut batch = BatchBuilder::new();
+            let mut restore_local = Vec::new();
+            for (key, value) in restore {
+                if self.cfg_local_patterns.is_local_key(&key) {
+                    restore_local.push((key, value));
+                } else if let Some(value) = value {
+                    batch.set(ValueClass::Config(key.into_bytes()), value);
+                } else {
+                    batch.clear(ValueClass::Config(key.into_bytes()));
+                }
+            }
+            let version_id = add_version(
+                &mut batch,
+                versions,
+                author,
+                Some(format!("Rollback of version {id}")),
+                previous,
+            );
+
+            match self.cfg_store.write(batch.build_all()).await {
+                Ok(_) => {}
+                Err(err)
+                    if err.matches(trc::EventType::Store(trc::StoreEvent::AssertValueFailed)) =>
+                {
+                    continue;
+                }
+                Err(err) => return Err(err.caused_by(trc::location!())),
+            }
+
+            for (key, value) in restore_local {
+                match value {
+                    Some(value) => self.set([ConfigKey { key, value }], true).await?,
+                    None => self.clear(key).await?,
+                }
+            }
+
+            return Ok(Some(version_id));
+        }
+    }
+}
+
+// Adds a version after the newest one, failing the batch if that id was taken meanwhile
+fn add_version(
+    batch: &mut BatchBuilder,
+    mut versions: Vec<SettingsVersion>,
+    author: &str,
+    description: Option<String>,
+    previous: BTreeMap<String, Option<String>>,
+) -> u64 {
+    let id = versions.first().map_or(1, |version| version.id + 1);
+    let version = SettingsVersion {
+        id,
+        timestamp: now(),
+        author: author.to_string(),
+        description,
+        previous,
+    };
+    let key = ValueClass::Config(version_key(id).into_bytes());
+    batch
+        .assert_value(key.clone(), ())
+        .set(key, serde_json::to_string(&version).unwrap_or_default());
+
+    // Discard the oldest versions
+    if versions.len() >= HISTORY_MAX_VERSIONS {
+        for version in versions.drain(HISTORY_MAX_VERSIONS - 1..) {
+            batch.clear(ValueClass::Config(version_key(version.id).into_bytes()));
+        }
+    }
+
+    id
+}
+
+fn version_key(id: u64) -> String {
+    format!("{SETTINGS_HISTORY_PREFIX}{id:020}")
+}
@@ -14,6 +14,7 @@ pub mod backup;
 pub mod boot;
 pub mod config;
 pub mod console;
+pub mod history;
 pub mod reload;
 pub mod restore;
 pub mod webadmin;
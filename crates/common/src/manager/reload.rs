@@ -77,6 +77,7 @@ impl Server {
             fts_stores: self.core.storage.ftss.clone(),
             in_memory_stores: self.core.storage.lookups.clone(),
             pubsub_stores: Default::default(),
+            http_queries: Default::default(),
             purge_schedules: Default::default(),
         };
         stores.parse_stores(&mut config).await;
@@ -13,6 +13,7 @@ use unicode_security::mixed_script::AugmentedScriptSet;
 use crate::IntoString;
 
 pub mod functions;
+pub mod notify;
 pub mod plugins;
 
 #[derive(Debug, serde::Serialize)]
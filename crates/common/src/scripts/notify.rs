@@ -0,0 +1,184 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::net::{IpAddr, SocketAddr};
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use store::dispatch::lookup::KeyValue;
+use utils::is_public_ip;
+
+use crate::{KV_SIEVE_NOTIFY, Server, config::scripts::SieveNotify};
+
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotifyWebhook<'x> {
+    pub account_id: u32,
+    pub from: &'x str,
+    pub envelope_from: &'x str,
+    pub envelope_to: &'x str,
+    pub subject: Option<&'x str>,
+    pub message: &'x str,
+    pub options: &'x [String],
+}
+
+impl Server {
+    // Notifications are limited per account to prevent scripts from flooding recipients
+    pub async fn check_sieve_notify_rate<T: AsRef<str>>(
+        &self,
+        account_id: u32,
+        session_id: u64,
+        recipients: &[T],
+    ) -> bool {
+        let Some(rate) = &self.core.sieve.notify.rate else {
+            return true;
+        };
+
+        match self
+            .in_memory_store()
+            .counter_incr(
+                KeyValue::with_prefix(KV_SIEVE_NOTIFY, account_id.to_be_bytes(), 1)
+                    .expires(rate.period.as_secs()),
+                true,
+            )
+            .await
+        {
+            Ok(count) if (count as u64) <= rate.requests => true,
+            Ok(_) => {
+                trc::event!(
+                    Sieve(trc::SieveEvent::NotifyRateLimited),
+                    AccountId = account_id,
+                    To = recipients
+                        .iter()
+                        .map(|r| trc::Value::String(r.as_ref().into()))
+                        .collect::<Vec<_>>(),
+                    Limit = rate.requests,
+                    SpanId = session_id,
+                );
+                false
+            }
+            Err(err) => {
+                trc::error!(
+                    err.account_id(account_id)
+                        .span_id(session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to check Sieve notification rate")
+                );
+                true
+            }
+        }
+    }
+
+    pub async fn send_sieve_notify_webhook(
+        &self,
+        url: &str,
+        payload: &NotifyWebhook<'_>,
+    ) -> trc::Result<()> {
+        let config = &self.core.sieve.notify;
+        let (Some(client), Some(url)) = (&config.client, config.webhook_url(url)) else {
+            return Err(trc::SieveEvent::NotifyFailed
+                .into_err()
+                .details("Webhook destination is not allowed"));
+        };
+        let body = serde_json::to_string(payload).map_err(|err| {
+            trc::SieveEvent::NotifyFailed
+                .into_err()
+                .reason(err)
+                .details("Failed to serialize payload")
+        })?;
+        let response = client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| {
+                trc::SieveEvent::NotifyFailed
+                    .into_err()
+                    .reason(err)
+                    .details("Failed to send request")
+            })?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(trc::SieveEvent::NotifyFailed
+                .into_err()
+                .ctx(trc::Key::Code, response.status().as_u16())
+                .details("Webhook returned an error status"))
+        }
+    }
+}
+
+impl SieveNotify {
+    // Only allowed hosts can be notified, literal addresses are checked here as they
+    // bypass the resolver while host names are checked when connecting
+    pub fn webhook_url(&self, url: &str) -> Option<reqwest::Url> {
+        let url = reqwest::Url::parse(url)
+            .ok()
+            .filter(|url| matches!(url.scheme(), "http" | "https"))?;
+        let host = url.host_str()?.to_lowercase();
+        if !self.allowed_hosts.contains(&host) {
+            return None;
+        }
+        match host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+        {
+            Ok(ip) if !is_public_ip(ip) => None,
+            _ => Some(url),
+        }
+    }
+}
+
+// Resolves webhook hosts when connecting, so a host can't pass validation
+// and then resolve to an internal address
+pub(crate) struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .collect::<Vec<SocketAddr>>();
+            if !addrs.is_empty() && addrs.iter().all(|addr| is_public_ip(addr.ip())) {
+                Ok(Box::new(addrs.into_iter()) as Addrs)
+            } else {
+                Err(format!("{} does not resolve to a public address", name.as_str()).into())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::scripts::SieveNotify;
+
+    #[test]
+    fn webhook_url() {
+        let config = SieveNotify {
+            allowed_hosts: ["hooks.example.com", "127.0.0.1", "[::1]", "8.8.8.8"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            ..Default::default()
+        };
+
+        for (url, expected) in [
+            ("https://hooks.example.com/alert", true),
+            ("http://HOOKS.example.com:8080/alert", true),
+            ("https://8.8.8.8/alert", true),
+            ("https://other.example.com/alert", false),
+            ("ftp://hooks.example.com/alert", false),
+            ("https://127.0.0.1/alert", false),
+            ("https://[::1]/alert", false),
+            ("https://hooks.example.com.evil.org/alert", false),
+            ("mailto:john@example.com", false),
+        ] {
+            assert_eq!(config.webhook_url(url).is_some(), expected, "{url}");
+        }
+    }
+}
@@ -6,4 +6,5 @@
 
 pub mod blob;
 pub mod index;
+pub mod quota;
 pub mod state;
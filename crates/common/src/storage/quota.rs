@@ -0,0 +1,97 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{KV_QUOTA_WARNING, Server, config::network::QuotaWarnings};
+use store::{
+    dispatch::lookup::KeyValue,
+    write::{BatchBuilder, TaskQueueClass, ValueClass, now},
+};
+use trc::AddContext;
+
+impl Server {
+    // Accounts above the soft limit can still write until the hard limit is reached
+    pub async fn is_over_soft_quota(
+        &self,
+        account_id: u32,
+        quota: u64,
+        soft_quota: Option<u64>,
+    ) -> trc::Result<bool> {
+        if quota != 0 {
+            let used_quota = self.get_used_quota(account_id).await? as u64;
+            Ok(used_quota
+                >= self
+                    .core
+                    .network
+                    .quota_warnings
+                    .soft_limit(quota, soft_quota))
+        } else {
+            Ok(false)
+        }
+    }
+
+    // Message limits set on the account take precedence over the tenant and server defaults
+    pub fn message_quota(&self, account_quota: Option<u64>, tenant_quota: Option<u64>) -> u64 {
+        account_quota
+            .or(tenant_quota)
+            .unwrap_or(self.core.jmap.mail_max_messages)
+    }
+
+    pub async fn track_quota_usage(&self, account_id: u32, quota: u64) -> trc::Result<()> {
+        let config = &self.core.network.quota_warnings;
+        if quota == 0 || config.thresholds.is_empty() {
+            return Ok(());
+        }
+
+        let used_quota = self.get_used_quota(account_id).await? as u64;
+        let Some(threshold) = QuotaWarnings::usage_percent(used_quota, quota).and_then(|usage| {
+            config
+                .thresholds
+                .iter()
+                .rev()
+                .find(|threshold| usage >= **threshold)
+                .copied()
+        }) else {
+            return Ok(());
+        };
+
+        // Each threshold is reported at most once per period
+        let mut key = Vec::with_capacity(std::mem::size_of::<u32>() + 1);
+        key.extend_from_slice(&account_id.to_be_bytes());
+        key.push(threshold as u8);
+        let store = self.in_memory_store();
+        if store
+            .key_exists(KeyValue::<()>::build_key(KV_QUOTA_WARNING, &key))
+            .await
+            .caused_by(trc::location!())?
+        {
+            return Ok(());
+        }
+        store
+            .key_set(
+                KeyValue::with_prefix(KV_QUOTA_WARNING, key, vec![])
+                    .expires(config.period.as_secs()),
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        // Queue the notification, the threshold is stored as the document id
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .update_document(threshold as u32)
+            .set(
+                ValueClass::TaskQueue(TaskQueueClass::SendQuotaWarning { due: now() }),
+                vec![],
+            );
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())?;
+        self.notify_task_queue();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::cmp::Ordering;
+
+use ahash::AHashMap;
+use trc::{Event, EventDetails, EventType, Key, Value, ipc::subscriber::EventFilter};
+
+#[derive(Debug, Default)]
+pub struct TracerFilter {
+    pub sample_rates: AHashMap<EventType, f64>,
+    pub exclude: Vec<FilterCondition>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterCondition {
+    pub key: Key,
+    pub op: FilterOperator,
+    pub value: String,
+    pub number: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterOperator {
+    Equal,
+    NotEqual,
+    GreaterThan,
+    GreaterEqualThan,
+    LowerThan,
+    LowerEqualThan,
+    Contains,
+    StartsWith,
+    EndsWith,
+}
+
+impl EventFilter for TracerFilter {
+    fn accept(&self, event: &Event<EventDetails>) -> bool {
+        if self
+            .exclude
+            .iter()
+            .any(|condition| condition.matches(event))
+        {
+            return false;
+        }
+
+        match self.sample_rates.get(&event.inner.typ) {
+            Some(rate) => store::rand::random::<f64>() < *rate,
+            None => true,
+        }
+    }
+}
+
+impl TracerFilter {
+    pub fn is_empty(&self) -> bool {
+        self.sample_rates.is_empty() && self.exclude.is_empty()
+    }
+}
+
+impl FilterCondition {
+    // Conditions have the form "<key> <operator> <value>", for example "remote-ip == 10.0.0.1"
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let mut parts = value.trim().splitn(3, char::is_whitespace);
+        let (Some(key), Some(op), Some(value)) = (parts.next(), parts.next(), parts.next()) else {
+            return Err(format!("Invalid filter condition {value:?}"));
+        };
+        let key = key
+            .parse::<Key>()
+            .map_err(|_| format!("Unknown event key {key:?}"))?;
+        let op = match op {
+            "==" | "eq" => FilterOperator::Equal,
+            "!=" | "ne" => FilterOperator::NotEqual,
+            ">" | "gt" => FilterOperator::GreaterThan,
+            ">=" | "ge" => FilterOperator::GreaterEqualThan,
+            "<" | "lt" => FilterOperator::LowerThan,
+            "<=" | "le" => FilterOperator::LowerEqualThan,
+            "contains" => FilterOperator::Contains,
+            "starts-with" => FilterOperator::StartsWith,
+            "ends-with" => FilterOperator::EndsWith,
+            _ => return Err(format!("Invalid filter operator {op:?}")),
+        };
+        let value = value.trim().trim_matches('"').to_string();
+
+        Ok(FilterCondition {
+            key,
+            op,
+            number: value.parse::<f64>().ok(),
+            value,
+        })
+    }
+
+    // Conditions only apply to events that include the key
+    pub fn matches(&self, event: &Event<EventDetails>) -> bool {
+        event
+            .keys
+            .iter()
+            .any(|(key, value)| *key == self.key && self.matches_value(value))
+    }
+
+    fn matches_value(&self, value: &Value) -> bool {
+        let ordering = match value {
+            Value::UInt(v) | Value::Timestamp(v) | Value::Duration(v) => self
+                .number
+                .and_then(|number| (*v as f64).partial_cmp(&number)),
+            Value::Int(v) => self
+                .number
+                .and_then(|number| (*v as f64).partial_cmp(&number)),
+            Value::Float(v) => self.number.and_then(|number| v.partial_cmp(&number)),
+            Value::Array(values) => {
+                return values.iter().any(|value| self.matches_value(value));
+            }
+            Value::None => return false,
+            _ => None,
+        };
+
+        if let Some(ordering) = ordering {
+            match self.op {
+                FilterOperator::Equal => ordering == Ordering::Equal,
+                FilterOperator::NotEqual => ordering != Ordering::Equal,
+                FilterOperator::GreaterThan => ordering == Ordering::Greater,
+                FilterOperator::GreaterEqualThan => ordering != Ordering::Less,
+                FilterOperator::LowerThan => ordering == Ordering::Less,
+                FilterOperator::LowerEqualThan => ordering != Ordering::Greater,
+                FilterOperator::Contains
+                | FilterOperator::StartsWith
+                | FilterOperator::EndsWith => self.matches_text(&value.to_string()),
+            }
+        } else {
+            self.matches_text(&value.to_string())
+        }
+    }
+
+    fn matches_text(&self, text: &str) -> bool {
+        match self.op {
+            FilterOperator::Equal => text == self.value,
+            FilterOperator::NotEqual => text != self.value,
+            FilterOperator::GreaterThan => text > self.value.as_str(),
+            FilterOperator::GreaterEqualThan => text >= self.value.as_str(),
+            FilterOperator::LowerThan => text < self.value.as_str(),
+            FilterOperator::LowerEqualThan => text <= self.value.as_str(),
+            FilterOperator::Contains => text.contains(self.value.as_str()),
+            FilterOperator::StartsWith => text.starts_with(self.value.as_str()),
+            FilterOperator::EndsWith => text.ends_with(self.value.as_str()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilterCondition, FilterOperator};
+    use trc::Key;
+
+    #[test]
+    fn parse_filter_conditions() {
+        for (condition, key, op, value, number) in [
+            (
+                "remote-ip == 10.0.0.1",
+                Key::RemoteIp,
+                FilterOperator::Equal,
+                "10.0.0.1",
+                None,
+            ),
+            (
+                "size gt 1024",
+                Key::Size,
+                FilterOperator::GreaterThan,
+                "1024",
+                Some(1024.0),
+            ),
+            (
+                "  domain ends-with \".example.org\"  ",
+                Key::Domain,
+                FilterOperator::EndsWith,
+                ".example.org",
+                None,
+            ),
+            (
+                "details contains two words",
+                Key::Details,
+                FilterOperator::Contains,
+                "two words",
+                None,
+            ),
+        ] {
+            assert_eq!(
+                FilterCondition::parse(condition),
+                Ok(FilterCondition {
+                    key,
+                    op,
+                    value: value.to_string(),
+                    number,
+                }),
+                "{condition}"
+            );
+        }
+
+        for condition in ["remote-ip", "remote-ip ==", "remoteIp == 1", "size ~ 1"] {
+            assert!(FilterCondition::parse(condition).is_err(), "{condition}");
+        }
+    }
+}
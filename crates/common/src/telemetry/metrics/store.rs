@@ -11,6 +11,7 @@
 use std::{future::Future, sync::Arc, time::Duration};
 
 use ahash::AHashMap;
+use directory::{Type, backend::internal::manage::ManageDirectory};
 use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use store::{
@@ -24,7 +25,10 @@ use store::{
 use trc::*;
 use utils::codec::leb128::Leb128Reader;
 
-use crate::Core;
+use crate::{
+    Core, Server,
+    telemetry::metrics::usage::{UsageMetric, UsageRecord},
+};
 
 pub trait MetricsStore: Sync + Send {
     fn write_metrics(
@@ -41,6 +45,19 @@ pub trait MetricsStore: Sync + Send {
     fn purge_metrics(&self, period: Duration) -> impl Future<Output = trc::Result<()>> + Send;
 }
 
+pub trait UsageStore: Sync + Send {
+    fn write_usage(
+        &self,
+        server: &Server,
+        timestamp: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+    fn query_usage(
+        &self,
+        from_timestamp: u64,
+        to_timestamp: u64,
+    ) -> impl Future<Output = trc::Result<Vec<UsageRecord>>> + Send;
+}
+
 #[derive(Default)]
 pub struct MetricsHistory {
     events: AHashMap<EventType, u32>,
@@ -80,6 +97,9 @@ pub type SharedMetricHistory = Arc<Mutex<MetricsHistory>>;
 const TYPE_COUNTER: u64 = 0x00;
 const TYPE_HISTOGRAM: u64 = 0x01;
 const TYPE_GAUGE: u64 = 0x02;
+// Usage snapshots store the domain or tenant id in place of the node id
+const TYPE_USAGE: u64 = 0x03;
+const USAGE_SNAPSHOT_LOCK_DURATION: u64 = 300;
 
 impl MetricsStore for Store {
     async fn write_metrics(
@@ -266,6 +286,7 @@ impl MetricsStore for Store {
                             value,
                         });
                     }
+                    TYPE_USAGE => (),
                     _ => return Err(trc::Error::corrupted_key(key, None, trc::location!())),
                 }
 
@@ -296,6 +317,173 @@ impl MetricsStore for Store {
     }
 }
 
+impl UsageStore for Store {
+    async fn write_usage(&self, server: &Server, timestamp: u64) -> trc::Result<()> {
+        let data = &server.core.storage.data;
+        if !server
+            .try_lock_usage_snapshot(USAGE_SNAPSHOT_LOCK_DURATION)
+            .await
+            .caused_by(trc::location!())?
+        {
+            return Ok(());
+        }
+        let last_snapshot = server
+            .last_usage_snapshot()
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default();
+
+        // Map domain names to their ids and tenants
+        let domains = data
+            .list_principals(None, None, &[Type::Domain], true, 0, 0)
+            .await
+            .caused_by(trc::location!())?
+            .items
+            .into_iter()
+            .map(|principal| {
+                let tenant = principal.tenant();
+                (principal.name, (principal.id, tenant))
+            })
+            .collect::<AHashMap<_, _>>();
+        let mut usage: AHashMap<u32, AHashMap<UsageMetric, u64>> = AHashMap::new();
+        let mut add_usage = |domain: &str, metric: UsageMetric, value: u64| {
+            if let Some((domain_id, tenant_id)) = domains.get(domain) {
+                for principal_id in [Some(*domain_id), *tenant_id].into_iter().flatten() {
+                    *usage
+                        .entry(principal_id)
+                        .or_default()
+                        .entry(metric)
+                        .or_default() += value;
+                }
+            }
+        };
+
+        // Storage and accounts are measured at snapshot time
+        for account in data
+            .list_principals(None, None, &[Type::Individual], true, 0, 0)
+            .await
+            .caused_by(trc::location!())?
+            .items
+        {
+            if let Some((_, domain)) = account.emails.first().and_then(|e| e.rsplit_once('@')) {
+                let used_quota = server
+                    .get_used_quota(account.id)
+                    .await
+                    .caused_by(trc::location!())?;
+                add_usage(domain, UsageMetric::Accounts, 1);
+                add_usage(domain, UsageMetric::BytesStored, used_quota.max(0) as u64);
+                if server
+                    .last_login(account.id)
+                    .await
+                    .caused_by(trc::location!())?
+                    .is_some_and(|last_login| last_login >= last_snapshot)
+                {
+                    add_usage(domain, UsageMetric::ActiveAccounts, 1);
+                }
+            }
+        }
+
+        // Activity is accumulated between snapshots
+        for domain in domains.keys() {
+            for metric in [UsageMetric::MessagesReceived, UsageMetric::MessagesSent] {
+                let value = server
+                    .take_usage_counter(metric, domain)
+                    .await
+                    .caused_by(trc::location!())?;
+                add_usage(domain, metric, value);
+            }
+        }
+        server
+            .set_last_usage_snapshot(timestamp)
+            .await
+            .caused_by(trc::location!())?;
+
+        let mut batch = BatchBuilder::new();
+        for (principal_id, metrics) in usage {
+            for (metric, value) in metrics {
+                if value > 0 {
+                    batch.set(
+                        ValueClass::Telemetry(TelemetryClass::Metric {
+                            timestamp,
+                            metric_id: (metric.id() << 2) | TYPE_USAGE,
+                            node_id: principal_id as u64,
+                        }),
+                        KeySerializer::new(U64_LEN).write_leb128(value).finalize(),
+                    );
+                }
+            }
+            if batch.is_large_batch() {
+                self.write(batch.build_all())
+                    .await
+                    .caused_by(trc::location!())?;
+                batch = BatchBuilder::new();
+            }
+        }
+
+        if !batch.is_empty() {
+            self.write(batch.build_all())
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+
+    async fn query_usage(
+        &self,
+        from_timestamp: u64,
+        to_timestamp: u64,
+    ) -> trc::Result<Vec<UsageRecord>> {
+        let mut records = Vec::new();
+        self.iterate(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Telemetry(TelemetryClass::Metric {
+                    timestamp: from_timestamp,
+                    metric_id: 0,
+                    node_id: 0,
+                })),
+                ValueKey::from(ValueClass::Telemetry(TelemetryClass::Metric {
+                    timestamp: to_timestamp,
+                    metric_id: 0,
+                    node_id: 0,
+                })),
+            ),
+            |key, value| {
+                let timestamp = key.deserialize_be_u64(0).caused_by(trc::location!())?;
+                let (metric_type, bytes_read) = key
+                    .get(U64_LEN..)
+                    .and_then(|bytes| bytes.read_leb128::<u64>())
+                    .ok_or_else(|| trc::Error::corrupted_key(key, None, trc::location!()))?;
+                if metric_type & 0x03 != TYPE_USAGE {
+                    return Ok(true);
+                }
+
+                let metric = UsageMetric::from_id(metric_type >> 2)
+                    .ok_or_else(|| trc::Error::corrupted_key(key, None, trc::location!()))?;
+                let (principal_id, _) = key
+                    .get(U64_LEN + bytes_read..)
+                    .and_then(|bytes| bytes.read_leb128::<u32>())
+                    .ok_or_else(|| trc::Error::corrupted_key(key, None, trc::location!()))?;
+                let (value, _) = value.read_leb128::<u64>().ok_or_else(|| {
+                    trc::Error::corrupted_key(key, value.into(), trc::location!())
+                })?;
+                records.push(UsageRecord {
+                    principal_id,
+                    timestamp,
+                    metric,
+                    value,
+                });
+
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())?;
+
+        Ok(records)
+    }
+}
+
 impl MetricsHistory {
     pub fn init() -> SharedMetricHistory {
         Arc::new(Mutex::new(Self::default()))
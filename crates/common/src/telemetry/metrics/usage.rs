@@ -0,0 +1,187 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{KV_USAGE, Server};
+use serde::{Deserialize, Serialize};
+use store::{dispatch::lookup::KeyValue, write::now};
+use trc::AddContext;
+
+// Activity is accumulated in the in-memory store so that every node in the
+// cluster contributes to the same counters between two usage snapshots.
+const USAGE_LAST_LOGIN: u8 = u8::MAX;
+const USAGE_LAST_SNAPSHOT: u8 = u8::MAX - 1;
+const USAGE_SNAPSHOT_LOCK: u8 = u8::MAX - 2;
+const LAST_LOGIN_EXPIRY: u64 = 31 * 86400;
+
+pub enum UsageEvent {
+    MessageReceived,
+    MessageSent,
+    Login { account_id: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UsageMetric {
+    MessagesReceived = 0,
+    MessagesSent = 1,
+    BytesStored = 2,
+    Accounts = 3,
+    ActiveAccounts = 4,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageRecord {
+    pub principal_id: u32,
+    pub timestamp: u64,
+    pub metric: UsageMetric,
+    pub value: u64,
+}
+
+impl UsageMetric {
+    pub fn id(&self) -> u64 {
+        *self as u64
+    }
+
+    pub fn from_id(id: u64) -> Option<Self> {
+        match id {
+            0 => Some(UsageMetric::MessagesReceived),
+            1 => Some(UsageMetric::MessagesSent),
+            2 => Some(UsageMetric::BytesStored),
+            3 => Some(UsageMetric::Accounts),
+            4 => Some(UsageMetric::ActiveAccounts),
+            _ => None,
+        }
+    }
+}
+
+impl Server {
+    pub async fn record_usage(&self, address: &str, event: UsageEvent) {
+        // SPDX-SnippetBegin
+        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+        // SPDX-License-Identifier: LicenseRef-SEL
+
+        #[cfg(feature = "enterprise")]
+        if self
+            .core
+            .enterprise
+            .as_ref()
+            .and_then(|e| e.metrics_store.as_ref())
+            .is_some_and(|store| store.usage)
+            && let Some((_, domain)) = address.rsplit_once('@')
+        {
+            let store = self.in_memory_store();
+            let result = match event {
+                UsageEvent::MessageReceived | UsageEvent::MessageSent => {
+                    let metric = if matches!(event, UsageEvent::MessageReceived) {
+                        UsageMetric::MessagesReceived
+                    } else {
+                        UsageMetric::MessagesSent
+                    };
+                    store
+                        .counter_incr(
+                            KeyValue::with_prefix(
+                                KV_USAGE,
+                                usage_counter_key(metric, &domain.to_lowercase()),
+                                1,
+                            ),
+                            false,
+                        )
+                        .await
+                        .map(|_| ())
+                }
+                UsageEvent::Login { account_id } => {
+                    store
+                        .key_set(
+                            KeyValue::with_prefix(
+                                KV_USAGE,
+                                last_login_key(account_id),
+                                (now() as i64).to_be_bytes().to_vec(),
+                            )
+                            .expires(LAST_LOGIN_EXPIRY),
+                        )
+                        .await
+                }
+            };
+
+            if let Err(err) = result {
+                trc::error!(err.caused_by(trc::location!()));
+            }
+        }
+
+        // SPDX-SnippetEnd
+
+        #[cfg(not(feature = "enterprise"))]
+        let _ = (address, event);
+    }
+
+    // Only one node in the cluster takes each snapshot
+    pub async fn try_lock_usage_snapshot(&self, duration: u64) -> trc::Result<bool> {
+        self.in_memory_store()
+            .try_lock(KV_USAGE, &[USAGE_SNAPSHOT_LOCK], duration)
+            .await
+    }
+
+    // Returns the counter value and subtracts it, events recorded in the
+    // meantime are kept for the next snapshot
+    pub async fn take_usage_counter(&self, metric: UsageMetric, domain: &str) -> trc::Result<u64> {
+        let store = self.in_memory_store();
+        let key = usage_counter_key(metric, domain);
+        let value = store
+            .counter_get(KeyValue::<()>::build_key(KV_USAGE, &key))
+            .await
+            .caused_by(trc::location!())?;
+        if value > 0 {
+            store
+                .counter_incr(KeyValue::with_prefix(KV_USAGE, key, -value), false)
+                .await
+                .caused_by(trc::location!())?;
+            Ok(value as u64)
+        } else {
+            Ok(0)
+        }
+    }
+
+    pub async fn last_login(&self, account_id: u32) -> trc::Result<Option<u64>> {
+        self.in_memory_store()
+            .key_get::<i64>(KeyValue::<()>::build_key(
+                KV_USAGE,
+                last_login_key(account_id),
+            ))
+            .await
+            .map(|value| value.map(|value| value as u64))
+    }
+
+    pub async fn last_usage_snapshot(&self) -> trc::Result<Option<u64>> {
+        self.in_memory_store()
+            .key_get::<i64>(KeyValue::<()>::build_key(KV_USAGE, [USAGE_LAST_SNAPSHOT]))
+            .await
+            .map(|value| value.map(|value| value as u64))
+    }
+
+    pub async fn set_last_usage_snapshot(&self, timestamp: u64) -> trc::Result<()> {
+        self.in_memory_store()
+            .key_set(KeyValue::with_prefix(
+                KV_USAGE,
+                [USAGE_LAST_SNAPSHOT],
+                (timestamp as i64).to_be_bytes().to_vec(),
+            ))
+            .await
+    }
+}
+
+fn usage_counter_key(metric: UsageMetric, domain: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(domain.len() + 1);
+    key.push(metric.id() as u8);
+    key.extend_from_slice(domain.as_bytes());
+    key
+}
+
+fn last_login_key(account_id: u32) -> Vec<u8> {
+    let mut key = Vec::with_capacity(std::mem::size_of::<u32>() + 1);
+    key.push(USAGE_LAST_LOGIN);
+    key.extend_from_slice(&account_id.to_be_bytes());
+    key
+}
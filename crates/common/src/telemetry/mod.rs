@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod filter;
 pub mod metrics;
 pub mod tracers;
 pub mod webhooks;
@@ -23,7 +24,8 @@ impl Telemetry {
             tracer.typ.spawn(
                 SubscriberBuilder::new(tracer.id)
                     .with_interests(tracer.interests)
-                    .with_lossy(tracer.lossy),
+                    .with_lossy(tracer.lossy)
+                    .with_filter(tracer.filter),
                 is_enterprise,
             );
         }
@@ -52,12 +54,18 @@ impl Telemetry {
         // Activate new tracers or update existing ones
         for tracer in self.tracers.subscribers {
             if active_subscribers.contains(&tracer.id) {
-                Collector::update_subscriber(tracer.id, tracer.interests, tracer.lossy);
+                Collector::update_subscriber(
+                    tracer.id,
+                    tracer.interests,
+                    tracer.lossy,
+                    tracer.filter,
+                );
             } else {
                 tracer.typ.spawn(
                     SubscriberBuilder::new(tracer.id)
                         .with_interests(tracer.interests)
-                        .with_lossy(tracer.lossy),
+                        .with_lossy(tracer.lossy)
+                        .with_filter(tracer.filter),
                     is_enterprise,
                 );
             }
@@ -108,6 +116,9 @@ impl TelemetrySubscriberType {
             TelemetrySubscriberType::JournalTracer(subscriber) => {
                 tracers::journald::spawn_journald_tracer(builder, subscriber)
             }
+            TelemetrySubscriberType::SyslogTracer(settings) => {
+                tracers::syslog::spawn_syslog_tracer(builder, settings)
+            }
             // SPDX-SnippetBegin
             // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
             // SPDX-License-Identifier: LicenseRef-SEL
@@ -10,7 +10,7 @@ use mail_parser::DateTime;
 use opentelemetry::{
     InstrumentationScope, Key, KeyValue, Value,
     logs::{AnyValue, Severity},
-    trace::{SpanContext, SpanKind, Status, TraceFlags, TraceState},
+    trace::{Link, SpanContext, SpanKind, Status, TraceFlags, TraceId, TraceState},
 };
 use opentelemetry_sdk::{
     Resource,
@@ -67,12 +67,15 @@ pub(crate) fn spawn_otel_tracer(builder: SubscriberBuilder, mut otel: OtelTracer
                                     events.push(event);
                                 }
                             } else if let Some(events) = active_spans.remove(&span_id) {
-                                pending_spans.push(build_span_data(
+                                let span = build_span_data(
                                     span,
                                     &event,
                                     events.iter().chain(std::iter::once(&event)),
                                     &instrumentation,
-                                ));
+                                );
+                                if is_sampled(span.span_context.trace_id(), otel.sample_rate) {
+                                    pending_spans.push(span);
+                                }
                             }
                         }
                     }
@@ -146,26 +149,53 @@ where
 {
     let span_id = start_span.span_id().unwrap();
 
+    // Spans that handled a message are stitched together using the queue id
+    let mut queue_ids = Vec::new();
     let mut events = SpanEvents::default();
-    events.events = span_events
-        .into_iter()
-        .map(|event| {
-            let event = event.as_ref();
+    let span_events = span_events.into_iter().collect::<Vec<_>>();
+    events.events = std::iter::once(start_span)
+        .chain(span_events.iter().map(|event| event.as_ref()))
+        .enumerate()
+        .filter_map(|(pos, event)| {
+            for (key, value) in &event.keys {
+                if let (trc::Key::QueueId, trc::Value::UInt(queue_id)) = (key, value)
+                    && !queue_ids.contains(queue_id)
+                {
+                    queue_ids.push(*queue_id);
+                }
+            }
 
-            opentelemetry::trace::Event::new(
-                event.inner.typ.name(),
-                UNIX_EPOCH + Duration::from_secs(event.inner.timestamp),
-                event.keys.iter().filter_map(build_key_value).collect(),
-                0,
-            )
+            (pos > 0).then(|| {
+                opentelemetry::trace::Event::new(
+                    event.inner.typ.name(),
+                    UNIX_EPOCH + Duration::from_secs(event.inner.timestamp),
+                    event.keys.iter().filter_map(build_key_value).collect(),
+                    0,
+                )
+            })
+        })
+        .collect();
+
+    let mut queue_ids = queue_ids.into_iter();
+    let trace_id = queue_ids.next().map_or(span_id as u128, message_trace_id);
+    let mut links = SpanLinks::default();
+    links.links = queue_ids
+        .map(|queue_id| {
+            Link::with_context(SpanContext::new(
+                message_trace_id(queue_id).into(),
+                queue_id.into(),
+                TraceFlags::SAMPLED,
+                true,
+                TraceState::default(),
+            ))
         })
         .collect();
 
     SpanData {
         span_context: SpanContext::new(
-            (span_id as u128).into(),
+            trace_id.into(),
             span_id.into(),
-            TraceFlags::default(),
+            TraceFlags::SAMPLED,
             false,
             TraceState::default(),
         ),
@@ -176,13 +206,38 @@ where
         end_time: UNIX_EPOCH + Duration::from_secs(end_span.inner.timestamp),
         attributes: start_span.keys.iter().filter_map(build_key_value).collect(),
         events,
-        links: SpanLinks::default(),
+        links,
         status: Status::default(),
-        span_kind: SpanKind::Server,
+        span_kind: if matches!(
+            start_span.inner.typ,
+            trc::EventType::Delivery(trc::DeliveryEvent::AttemptStart)
+        ) {
+            SpanKind::Client
+        } else {
+            SpanKind::Server
+        },
         instrumentation_scope: instrumentation.clone(),
     }
 }
 
+// Message traces use the upper half of the id to avoid clashing with span ids
+fn message_trace_id(queue_id: u64) -> u128 {
+    (1u128 << 64) | queue_id as u128
+}
+
+// Sampling is decided by trace, so all spans of a message are either kept or dropped
+fn is_sampled(trace_id: TraceId, sample_rate: f64) -> bool {
+    if sample_rate >= 1.0 {
+        true
+    } else if sample_rate <= 0.0 {
+        false
+    } else {
+        let hash =
+            (u128::from_be_bytes(trace_id.to_bytes()) as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        (hash as f64 / u64::MAX as f64) < sample_rate
+    }
+}
+
 impl OtelTracer {
     fn build_log_record(&self, event: &Event<EventDetails>) -> SdkLogRecord {
         use opentelemetry::logs::LogRecord;
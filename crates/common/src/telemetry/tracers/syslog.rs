@@ -0,0 +1,234 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{io::Write, net::SocketAddr};
+
+use crate::config::telemetry::{SyslogSeverity, SyslogTracer, SyslogTransport};
+
+use mail_parser::DateTime;
+use rustls_pki_types::ServerName;
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpStream, UdpSocket},
+};
+use tokio_rustls::client::TlsStream;
+use trc::{Event, EventDetails, Level, TelemetryEvent, ipc::subscriber::SubscriberBuilder};
+
+const MAX_MSGID_LEN: usize = 32;
+
+pub(crate) fn spawn_syslog_tracer(builder: SubscriberBuilder, settings: SyslogTracer) {
+    let (_, mut rx) = builder.register();
+    tokio::spawn(async move {
+        let mut connection: Option<SyslogConnection> = None;
+        let mut buf = Vec::with_capacity(512);
+
+        while let Some(events) = rx.recv().await {
+            for event in events {
+                buf.clear();
+                settings.write_message(&mut buf, &event);
+
+                if connection.is_none() {
+                    match tokio::time::timeout(settings.timeout, settings.connect()).await {
+                        Ok(Ok(conn)) => {
+                            connection = Some(conn);
+                        }
+                        Ok(Err(err)) => {
+                            trc::event!(
+                                Telemetry(TelemetryEvent::SyslogError),
+                                Details = "Failed to connect to syslog server",
+                                Hostname = settings.address.clone(),
+                                Reason = err.to_string()
+                            );
+                            break;
+                        }
+                        Err(_) => {
+                            trc::event!(
+                                Telemetry(TelemetryEvent::SyslogError),
+                                Details = "Timed out connecting to syslog server",
+                                Hostname = settings.address.clone(),
+                            );
+                            break;
+                        }
+                    }
+                }
+
+                if let Some(conn) = &mut connection
+                    && let Err(err) = conn.send(&buf).await
+                {
+                    trc::event!(
+                        Telemetry(TelemetryEvent::SyslogError),
+                        Details = "Failed to send event to syslog server",
+                        Hostname = settings.address.clone(),
+                        Reason = err.to_string()
+                    );
+
+                    // Reconnect on the next event
+                    connection = None;
+                }
+            }
+        }
+    });
+}
+
+enum SyslogConnection {
+    Udp(UdpSocket),
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl SyslogConnection {
+    async fn send(&mut self, message: &[u8]) -> std::io::Result<()> {
+        match self {
+            SyslogConnection::Udp(socket) => socket.send(message).await.map(|_| ()),
+            SyslogConnection::Tcp(stream) => write_framed(stream, message).await,
+            SyslogConnection::Tls(stream) => write_framed(stream.as_mut(), message).await,
+        }
+    }
+}
+
+// Octet-counting framing as described in RFC 6587
+async fn write_framed(
+    stream: &mut (impl AsyncWriteExt + Unpin),
+    message: &[u8],
+) -> std::io::Result<()> {
+    let mut frame = Vec::with_capacity(message.len() + 8);
+    let _ = write!(&mut frame, "{} ", message.len());
+    frame.extend_from_slice(message);
+    stream.write_all(&frame).await?;
+    stream.flush().await
+}
+
+impl SyslogTracer {
+    async fn connect(&self) -> std::io::Result<SyslogConnection> {
+        match &self.transport {
+            SyslogTransport::Udp => {
+                let addr = tokio::net::lookup_host(self.address.as_str())
+                    .await?
+                    .next()
+                    .ok_or_else(|| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::NotFound,
+                            "Failed to resolve syslog server address",
+                        )
+                    })?;
+                let socket = UdpSocket::bind(match addr {
+                    SocketAddr::V4(_) => "0.0.0.0:0",
+                    SocketAddr::V6(_) => "[::]:0",
+                })
+                .await?;
+                socket.connect(addr).await?;
+                Ok(SyslogConnection::Udp(socket))
+            }
+            SyslogTransport::Tcp => TcpStream::connect(self.address.as_str())
+                .await
+                .map(SyslogConnection::Tcp),
+            SyslogTransport::Tls(tls_connector) => {
+                let stream = TcpStream::connect(self.address.as_str()).await?;
+                let host = self
+                    .address
+                    .rsplit_once(':')
+                    .map_or(self.address.as_str(), |(host, _)| host)
+                    .trim_start_matches('[')
+                    .trim_end_matches(']');
+                let server_name = ServerName::try_from(host)
+                    .map_err(|_| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            "Invalid TLS server name",
+                        )
+                    })?
+                    .to_owned();
+                tls_connector
+                    .connect(server_name, stream)
+                    .await
+                    .map(|stream| SyslogConnection::Tls(Box::new(stream)))
+            }
+        }
+    }
+
+    pub fn severity(&self, event: &Event<EventDetails>) -> SyslogSeverity {
+        self.severities
+            .get(&event.inner.typ)
+            .copied()
+            .unwrap_or(match event.inner.level {
+                Level::Error => SyslogSeverity::Error,
+                Level::Warn => SyslogSeverity::Warning,
+                Level::Info => SyslogSeverity::Informational,
+                Level::Debug | Level::Trace | Level::Disable => SyslogSeverity::Debug,
+            })
+    }
+
+    // Formats an event as an RFC 5424 message, event keys are sent as structured data
+    pub fn write_message(&self, buf: &mut Vec<u8>, event: &Event<EventDetails>) {
+        let priority = (self.facility as u32) * 8 + self.severity(event) as u32;
+        let msg_id = event.inner.typ.name();
+        let _ = write!(
+            buf,
+            "<{priority}>1 {} {} {} {} {} ",
+            DateTime::from_timestamp(event.inner.timestamp as i64).to_rfc3339(),
+            header_field(&self.hostname, 255),
+            header_field(&self.app_name, 48),
+            std::process::id(),
+            header_field(msg_id, MAX_MSGID_LEN),
+        );
+
+        if !event.keys.is_empty() {
+            let _ = write!(buf, "[{}", self.sd_id);
+            for (key, value) in &event.keys {
+                let _ = write!(buf, " {}=\"", key.name());
+                write_param_value(buf, &value.to_string());
+                buf.push(b'"');
+            }
+            buf.push(b']');
+        } else {
+            buf.push(b'-');
+        }
+
+        let _ = write!(buf, " {}", event.inner.typ.description());
+    }
+}
+
+// Header fields are limited to printable US-ASCII
+fn header_field(value: &str, max_len: usize) -> String {
+    let value = value
+        .chars()
+        .filter(|ch| ch.is_ascii_graphic())
+        .take(max_len)
+        .collect::<String>();
+    if !value.is_empty() {
+        value
+    } else {
+        "-".to_string()
+    }
+}
+
+fn write_param_value(buf: &mut Vec<u8>, value: &str) {
+    for ch in value.bytes() {
+        if matches!(ch, b'"' | b'\\' | b']') {
+            buf.push(b'\\');
+        }
+        buf.push(ch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{header_field, write_param_value};
+
+    #[test]
+    fn syslog_escaping() {
+        let mut buf = Vec::new();
+        write_param_value(&mut buf, r#"a "quoted" [value] with \ slash"#);
+        assert_eq!(
+            std::str::from_utf8(&buf).unwrap(),
+            r#"a \"quoted\" [value\] with \\ slash"#
+        );
+
+        assert_eq!(header_field("mx .example.org", 255), "mx.example.org");
+        assert_eq!(header_field("", 255), "-");
+        assert_eq!(header_field("smtp.connection-start", 4), "smtp");
+    }
+}
@@ -24,6 +24,7 @@ use trc::AddContext;
 use crate::{
     DavError, DavMethod,
     common::{
+        limits::DavItemLimit,
         lock::{LockRequestHandler, ResourceState},
         uri::DavUriResource,
     },
@@ -310,6 +311,18 @@ impl CalendarCopyMoveRequestHandler for Server {
                     return Err(DavError::Code(StatusCode::FORBIDDEN));
                 }
 
+                // Validate item limit
+                if from_account_id != to_account_id || to_calendar_id != from_calendar_id {
+                    self.validate_item_limit(
+                        access_token,
+                        to_account_id,
+                        &to_resources,
+                        to_calendar_id,
+                        Collection::CalendarEvent,
+                    )
+                    .await?;
+                }
+
                 // Copy/move event
                 if is_move {
                     if from_account_id != to_account_id
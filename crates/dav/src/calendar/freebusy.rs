@@ -16,7 +16,7 @@ use calcard::{
         ICalendarValue,
     },
 };
-use common::{DavResourcePath, DavResources, PROD_ID, Server, auth::AccessToken};
+use common::{DavResourceMetadata, DavResources, PROD_ID, Server, auth::AccessToken};
 use dav_proto::{
     RequestHeaders,
     schema::{property::TimeRange, request::FreeBusyQuery},
@@ -43,13 +43,20 @@ pub(crate) trait CalendarFreebusyRequestHandler: Sync + Send {
         request: FreeBusyQuery,
     ) -> impl Future<Output = crate::Result<HttpResponse>> + Send;
 
-    fn build_freebusy_object(
+    fn calendar_availability(
         &self,
         access_token: &AccessToken,
+        account_id: u32,
+        request: FreeBusyQuery,
+    ) -> impl Future<Output = crate::Result<Option<ICalendar>>> + Send;
+
+    fn build_freebusy_object(
+        &self,
         request: FreeBusyQuery,
         resources: &DavResources,
         account_id: u32,
-        resource: DavResourcePath<'_>,
+        calendar_ids: &[u32],
+        default_tz: Tz,
     ) -> impl Future<Output = crate::Result<ICalendar>> + Send;
 }
 
@@ -81,38 +88,100 @@ impl CalendarFreebusyRequestHandler for Server {
             return Err(DavError::Code(StatusCode::METHOD_NOT_ALLOWED));
         }
 
-        self.build_freebusy_object(access_token, request, &resources, account_id, resource)
-            .await
-            .map(|ical| {
-                HttpResponse::new(StatusCode::OK)
-                    .with_content_type("text/calendar; charset=utf-8")
-                    .with_text_body(ical.to_string())
-            })
+        // Validate ACL
+        if !access_token.is_member(account_id)
+            && !resources
+                .shared_containers(
+                    access_token,
+                    [Acl::ReadItems, Acl::SchedulingReadFreeBusy],
+                    true,
+                )
+                .contains(resource.document_id())
+        {
+            return Err(DavError::Code(StatusCode::FORBIDDEN));
+        }
+
+        self.build_freebusy_object(
+            request,
+            &resources,
+            account_id,
+            &[resource.document_id()],
+            resource.resource.timezone().unwrap_or(Tz::UTC),
+        )
+        .await
+        .map(|ical| {
+            HttpResponse::new(StatusCode::OK)
+                .with_content_type("text/calendar; charset=utf-8")
+                .with_text_body(ical.to_string())
+        })
     }
 
-    async fn build_freebusy_object(
+    async fn calendar_availability(
         &self,
         access_token: &AccessToken,
-        request: FreeBusyQuery,
-        resources: &DavResources,
         account_id: u32,
-        resource: DavResourcePath<'_>,
-    ) -> crate::Result<ICalendar> {
-        // Obtain shared ids
+        request: FreeBusyQuery,
+    ) -> crate::Result<Option<ICalendar>> {
+        let resources = self
+            .fetch_dav_resources(access_token, account_id, SyncCollection::Calendar)
+            .await
+            .caused_by(trc::location!())?;
+
+        // Availability is calculated from all calendars the user is allowed to see
         let shared_ids = if !access_token.is_member(account_id) {
             resources
                 .shared_containers(
                     access_token,
                     [Acl::ReadItems, Acl::SchedulingReadFreeBusy],
-                    false,
+                    true,
                 )
                 .into()
         } else {
             None
         };
+        let mut has_calendars = false;
+        let mut calendar_ids = Vec::new();
+        for resource in &resources.resources {
+            if matches!(resource.data, DavResourceMetadata::Calendar { .. }) {
+                has_calendars = true;
+
+                // Busy periods are only disclosed for calendars shared with the user
+                if shared_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(resource.document_id))
+                {
+                    calendar_ids.push(resource.document_id);
+                }
+            }
+        }
+        if !has_calendars {
+            return Ok(None);
+        }
 
+        // Use the timezone of the default calendar for floating times
+        let default_tz = self
+            .core
+            .groupware
+            .default_calendar_name
+            .as_ref()
+            .and_then(|name| resources.by_path(name))
+            .and_then(|resource| resource.resource.timezone())
+            .unwrap_or(Tz::UTC);
+
+        self.build_freebusy_object(request, &resources, account_id, &calendar_ids, default_tz)
+            .await
+            .map(Some)
+    }
+
+    async fn build_freebusy_object(
+        &self,
+        request: FreeBusyQuery,
+        resources: &DavResources,
+        account_id: u32,
+        calendar_ids: &[u32],
+        default_tz: Tz,
+    ) -> crate::Result<ICalendar> {
         // Build FreeBusy component
-        let default_tz = resource.resource.timezone().unwrap_or(Tz::UTC);
         let mut entries = Vec::with_capacity(6);
         if let Some(range) = request.range {
             entries.push(ICalendarEntry {
@@ -137,16 +206,14 @@ impl CalendarFreebusyRequestHandler for Server {
                 ))],
             });
 
-            let document_ids = resources
-                .children(resource.document_id())
-                .filter(|resource| {
-                    shared_ids
-                        .as_ref()
-                        .is_none_or(|ids| ids.contains(resource.document_id()))
-                        && is_resource_in_time_range(resource.resource, &range)
-                })
+            let mut document_ids = calendar_ids
+                .iter()
+                .flat_map(|calendar_id| resources.children(*calendar_id))
+                .filter(|resource| is_resource_in_time_range(resource.resource, &range))
                 .map(|resource| resource.document_id())
                 .collect::<Vec<_>>();
+            document_ids.sort_unstable();
+            document_ids.dedup();
 
             let mut fb_entries: AHashMap<ICalendarFreeBusyType, Vec<(i64, i64)>> =
                 AHashMap::with_capacity(document_ids.len());
@@ -12,7 +12,6 @@ use crate::{
         uri::DavUriResource,
     },
 };
-use calcard::common::timezone::Tz;
 use common::{Server, auth::AccessToken};
 use dav_proto::{
     RequestHeaders, Return,
@@ -33,7 +32,6 @@ use jmap_proto::types::{
     acl::Acl,
     collection::{Collection, SyncCollection},
 };
-use std::str::FromStr;
 use store::write::BatchBuilder;
 use trc::AddContext;
 
@@ -303,7 +301,7 @@ impl CalendarPropPatchRequestHandler for Server {
                     }
                 }
                 (DavProperty::CalDav(CalDavProperty::TimezoneId), DavValue::String(tz_id)) => {
-                    if let Ok(tz) = Tz::from_str(&tz_id) {
+                    if let Some(tz) = self.core.groupware.resolve_tz(&tz_id) {
                         calendar.preferences_mut(account_id).time_zone = Timezone::IANA(tz.as_id());
                         items.insert_ok(property.property);
                     } else {
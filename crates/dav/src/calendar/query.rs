@@ -22,7 +22,7 @@ use calcard::{
         ICalendarValue, dates::CalendarEvent,
     },
 };
-use common::{DavResource, Server, auth::AccessToken};
+use common::{DavResource, Server, auth::AccessToken, config::groupware::GroupwareConfig};
 use dav_proto::{
     RequestHeaders,
     schema::{
@@ -210,9 +210,9 @@ fn extract_data_range(propfind: &PropFind, filter_range: Option<TimeRange>) -> O
     filter_range
 }
 
-pub fn try_parse_tz(tz: &Timezone) -> Option<Tz> {
+pub fn try_parse_tz(config: &GroupwareConfig, tz: &Timezone) -> Option<Tz> {
     match tz {
-        Timezone::Name(value) | Timezone::Id(value) => Tz::from_str(value).ok(),
+        Timezone::Name(value) | Timezone::Id(value) => config.resolve_tz(value),
         Timezone::None => None,
     }
 }
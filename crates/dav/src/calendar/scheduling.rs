@@ -350,27 +350,14 @@ impl CalendarSchedulingHandler for Server {
                 .await
                 .caused_by(trc::location!())?
             {
-                let resources = self
-                    .fetch_dav_resources(access_token, account_id, SyncCollection::Calendar)
-                    .await
-                    .caused_by(trc::location!())?;
-                if let Some(resource) = self
-                    .core
-                    .groupware
-                    .default_calendar_name
-                    .as_ref()
-                    .and_then(|name| resources.by_path(name))
+                if let Some(mut free_busy) = self
+                    .calendar_availability(
+                        access_token,
+                        account_id,
+                        FreeBusyQuery::new(from_date.timestamp(), to_date.timestamp()),
+                    )
+                    .await?
                 {
-                    let mut free_busy = self
-                        .build_freebusy_object(
-                            access_token,
-                            FreeBusyQuery::new(from_date.timestamp(), to_date.timestamp()),
-                            &resources,
-                            account_id,
-                            resource,
-                        )
-                        .await?;
-
                     // Add iTIP method
                     free_busy.components[0].entries.push(ICalendarEntry {
                         name: ICalendarProperty::Method,
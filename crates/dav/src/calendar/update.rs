@@ -9,6 +9,7 @@ use crate::{
     DavError, DavErrorCondition, DavMethod,
     common::{
         ETag, ExtractETag,
+        limits::DavItemLimit,
         lock::{LockRequestHandler, ResourceState},
         uri::DavUriResource,
     },
@@ -28,7 +29,11 @@ use dav_proto::{
 use directory::Permission;
 use groupware::{
     cache::GroupwareCache,
-    calendar::{CalendarEvent, CalendarEventData},
+    calendar::{
+        CalendarEvent, CalendarEventData,
+        booking::ResourceBooking,
+        normalize::{normalize_ical, repair_folding},
+    },
     scheduling::{ItipMessages, event_create::itip_create, event_update::itip_update},
 };
 use http_proto::HttpResponse;
@@ -91,7 +96,8 @@ impl CalendarUpdateRequestHandler for Server {
             )
         })?;
 
-        let ical = match Parser::new(ical_raw).entry() {
+        let ical_raw = repair_folding(ical_raw);
+        let mut ical = match Parser::new(ical_raw.as_ref()).entry() {
             Entry::ICalendar(ical) => ical,
             _ => {
                 return Err(DavError::Condition(
@@ -162,6 +168,14 @@ impl CalendarUpdateRequestHandler for Server {
                 Err(e) => return Err(e),
             }
 
+            // Normalize iCal
+            normalize_ical(
+                &mut ical,
+                event.inner.data.event.uids().next(),
+                &self.core.groupware.tz_overrides,
+            )
+            .map_err(invalid_calendar_data)?;
+
             if ical == event.inner.data.event {
                 // No changes, return existing event
                 return Ok(HttpResponse::new(StatusCode::NO_CONTENT));
@@ -214,19 +228,21 @@ impl CalendarUpdateRequestHandler for Server {
 
             // Scheduling
             let mut itip_messages = None;
+            let resource_owner = self.itip_resource_owner(access_token, account_id).await?;
+            let scheduling_emails = resource_owner
+                .as_ref()
+                .map_or(access_token.emails.as_slice(), |owner| {
+                    owner.emails.as_slice()
+                });
             if self.core.groupware.itip_enabled
-                && !access_token.emails.is_empty()
+                && !scheduling_emails.is_empty()
                 && access_token.has_permission(Permission::CalendarSchedulingSend)
                 && new_event.data.event_range_end() > now
             {
                 let result = if new_event.schedule_tag.is_some() {
-                    itip_update(
-                        &mut new_event.data.event,
-                        &old_ical,
-                        access_token.emails.as_slice(),
-                    )
+                    itip_update(&mut new_event.data.event, &old_ical, scheduling_emails)
                 } else {
-                    itip_create(&mut new_event.data.event, access_token.emails.as_slice())
+                    itip_create(&mut new_event.data.event, scheduling_emails)
                 };
 
                 match result {
@@ -322,6 +338,16 @@ impl CalendarUpdateRequestHandler for Server {
                 return Err(DavError::Code(StatusCode::FORBIDDEN));
             }
 
+            // Validate item limit
+            self.validate_item_limit(
+                access_token,
+                account_id,
+                &resources,
+                parent.document_id(),
+                Collection::CalendarEvent,
+            )
+            .await?;
+
             // Validate headers
             self.validate_headers(
                 access_token,
@@ -348,6 +374,8 @@ impl CalendarUpdateRequestHandler for Server {
             }
 
             // Validate ical object
+            normalize_ical(&mut ical, None, &self.core.groupware.tz_overrides)
+                .map_err(invalid_calendar_data)?;
             assert_is_unique_uid(
                 self,
                 &resources,
@@ -483,3 +511,13 @@ fn validate_ical(ical: &ICalendar) -> crate::Result<&str> {
         ))
     }
 }
+
+fn invalid_calendar_data(details: String) -> DavError {
+    DavError::Condition(
+        DavErrorCondition::new(
+            StatusCode::PRECONDITION_FAILED,
+            CalCondition::ValidCalendarData,
+        )
+        .with_details(details),
+    )
+}
@@ -23,6 +23,7 @@ use trc::AddContext;
 use crate::{
     DavError, DavMethod,
     common::{
+        limits::DavItemLimit,
         lock::{LockRequestHandler, ResourceState},
         uri::DavUriResource,
     },
@@ -293,6 +294,18 @@ impl CardCopyMoveRequestHandler for Server {
                     return Err(DavError::Code(StatusCode::FORBIDDEN));
                 }
 
+                // Validate item limit
+                if from_account_id != to_account_id || to_addressbook_id != from_addressbook_id {
+                    self.validate_item_limit(
+                        access_token,
+                        to_account_id,
+                        &to_resources,
+                        to_addressbook_id,
+                        Collection::ContactCard,
+                    )
+                    .await?;
+                }
+
                 // Copy/move card
                 if is_move {
                     if from_account_id != to_account_id
@@ -10,7 +10,11 @@ use dav_proto::{
     RequestHeaders, Return,
     schema::{property::Rfc1123DateTime, response::CardCondition},
 };
-use groupware::{cache::GroupwareCache, contact::ContactCard};
+use groupware::{
+    cache::GroupwareCache,
+    calendar::normalize::repair_folding,
+    contact::{ContactCard, normalize::normalize_vcard},
+};
 use http_proto::HttpResponse;
 use hyper::StatusCode;
 use jmap_proto::types::{
@@ -24,6 +28,7 @@ use crate::{
     DavError, DavErrorCondition, DavMethod,
     common::{
         ETag, ExtractETag,
+        limits::DavItemLimit,
         lock::{LockRequestHandler, ResourceState},
         uri::DavUriResource,
     },
@@ -83,7 +88,8 @@ impl CardUpdateRequestHandler for Server {
             )
         })?;
 
-        let vcard = match Parser::new(vcard_raw).strict().entry() {
+        let vcard_raw = repair_folding(vcard_raw);
+        let mut vcard = match Parser::new(vcard_raw.as_ref()).strict().entry() {
             Entry::VCard(vcard) => vcard,
             _ => {
                 return Err(DavError::Condition(
@@ -165,6 +171,9 @@ impl CardUpdateRequestHandler for Server {
                 .await?;
             }
 
+            // Normalize vCard
+            normalize_vcard(&mut vcard, card.inner.card.uid()).map_err(invalid_address_data)?;
+
             // Validate UID
             match (card.inner.card.uid(), vcard.uid()) {
                 (Some(old_uid), Some(new_uid)) if old_uid == new_uid => {}
@@ -209,6 +218,16 @@ impl CardUpdateRequestHandler for Server {
                 return Err(DavError::Code(StatusCode::FORBIDDEN));
             }
 
+            // Validate item limit
+            self.validate_item_limit(
+                access_token,
+                account_id,
+                &resources,
+                parent.document_id(),
+                Collection::ContactCard,
+            )
+            .await?;
+
             // Validate headers
             self.validate_headers(
                 access_token,
@@ -235,6 +254,7 @@ impl CardUpdateRequestHandler for Server {
             }
 
             // Validate UID
+            normalize_vcard(&mut vcard, None).map_err(invalid_address_data)?;
             assert_is_unique_uid(
                 self,
                 &resources,
@@ -274,3 +294,13 @@ impl CardUpdateRequestHandler for Server {
         }
     }
 }
+
+fn invalid_address_data(details: String) -> DavError {
+    DavError::Condition(
+        DavErrorCondition::new(
+            StatusCode::PRECONDITION_FAILED,
+            CardCondition::ValidAddressData,
+        )
+        .with_details(details),
+    )
+}
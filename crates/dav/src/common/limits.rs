@@ -0,0 +1,78 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{DavError, DavErrorCondition};
+use common::{DavResources, Server, auth::AccessToken, config::groupware::DavItemLimits};
+use dav_proto::schema::response::BaseCondition;
+use hyper::StatusCode;
+use jmap_proto::types::collection::Collection;
+use trc::AddContext;
+
+pub(crate) trait DavItemLimit: Sync + Send {
+    fn validate_item_limit(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        resources: &DavResources,
+        parent_id: u32,
+        collection: Collection,
+    ) -> impl Future<Output = crate::Result<()>> + Send;
+}
+
+impl DavItemLimit for Server {
+    async fn validate_item_limit(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        resources: &DavResources,
+        parent_id: u32,
+        collection: Collection,
+    ) -> crate::Result<()> {
+        let config = &self.core.groupware;
+        let max_items = |limits: &DavItemLimits| match collection {
+            Collection::Calendar | Collection::CalendarEvent => limits.max_calendar_items,
+            Collection::AddressBook | Collection::ContactCard => limits.max_addressbook_items,
+            _ => None,
+        };
+
+        // Domain limits take precedence over the server defaults
+        let mut limit = None;
+        if !config.item_limits_by_domain.is_empty() {
+            let owner_token;
+            let owner = if access_token.primary_id == account_id {
+                access_token
+            } else {
+                owner_token = self
+                    .get_access_token(account_id)
+                    .await
+                    .caused_by(trc::location!())?;
+                owner_token.as_ref()
+            };
+            limit = owner
+                .emails
+                .first()
+                .and_then(|email| email.rsplit_once('@'))
+                .and_then(|(_, domain)| config.item_limits_by_domain.get(domain))
+                .and_then(max_items);
+        }
+
+        if let Some(limit) = limit.or_else(|| max_items(&config.item_limits))
+            && resources.children(parent_id).count() >= limit
+        {
+            Err(DavError::Condition(
+                DavErrorCondition::new(
+                    StatusCode::INSUFFICIENT_STORAGE,
+                    BaseCondition::QuotaNotExceeded,
+                )
+                .with_details(format!(
+                    "The collection has reached the maximum of {limit} items."
+                )),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
@@ -34,6 +34,7 @@ use store::write::{AlignedBytes, Archive, BatchBuilder, Operation, ValueClass, V
 use uri::{OwnedUri, Urn};
 
 pub mod acl;
+pub mod limits;
 pub mod lock;
 pub mod propfind;
 pub mod uri;
@@ -455,7 +455,9 @@ impl PropFindRequestHandler for Server {
                         },
                         ArchivedResource::CalendarEvent(event),
                     ) => {
-                        let default_tz = if let Some(tz) = try_parse_tz(timezone) {
+                        let default_tz = if let Some(tz) =
+                            try_parse_tz(&self.core.groupware, timezone)
+                        {
                             tz
                         } else if let Some(calendar_id) = item.parent_id {
                             data.resources(self, access_token, account_id, SyncCollection::Calendar)
@@ -0,0 +1,159 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{str::FromStr, time::Duration};
+
+use base64::{Engine, engine::general_purpose};
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+use store::Store;
+use utils::{
+    cache::CacheWithTtl,
+    config::{Config, utils::AsKey},
+};
+
+use super::{HttpDirectory, HttpEndpoints, HttpMappings, HttpResponseCache};
+
+impl HttpDirectory {
+    pub fn from_config(config: &mut Config, prefix: impl AsKey, data_store: Store) -> Option<Self> {
+        let prefix = prefix.as_key();
+
+        // Parse endpoints
+        let mut endpoints = HttpEndpoints {
+            name: config
+                .value_require((&prefix, "endpoint.name"))?
+                .to_string(),
+            email: config
+                .value_require((&prefix, "endpoint.email"))?
+                .to_string(),
+            auth: config
+                .value((&prefix, "endpoint.auth"))
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string()),
+            headers: HeaderMap::new(),
+        };
+        for (header, value) in config
+            .values((&prefix, "headers"))
+            .map(|(_, v)| {
+                if let Some((k, v)) = v.split_once(':') {
+                    Ok((
+                        HeaderName::from_str(k.trim()).map_err(|err| {
+                            format!("Invalid header found in property \"{prefix}.headers\": {err}",)
+                        })?,
+                        HeaderValue::from_str(v.trim()).map_err(|err| {
+                            format!("Invalid header found in property \"{prefix}.headers\": {err}",)
+                        })?,
+                    ))
+                } else {
+                    Err(format!(
+                        "Invalid header found in property \"{prefix}.headers\": {v}",
+                    ))
+                }
+            })
+            .collect::<Result<Vec<(HeaderName, HeaderValue)>, String>>()
+            .map_err(|e| config.new_parse_error((&prefix, "headers"), e))
+            .unwrap_or_default()
+        {
+            endpoints.headers.insert(header, value);
+        }
+        let authorization = if let (Some(name), Some(secret)) = (
+            config.value((&prefix, "auth.username")),
+            config.value((&prefix, "auth.secret")),
+        ) {
+            Some(format!(
+                "Basic {}",
+                general_purpose::STANDARD.encode(format!("{name}:{secret}").as_bytes())
+            ))
+        } else {
+            config
+                .value((&prefix, "auth.token"))
+                .map(|token| format!("Bearer {token}"))
+        };
+        if let Some(authorization) = authorization {
+            match HeaderValue::from_str(&authorization) {
+                Ok(value) => {
+                    endpoints.headers.insert(AUTHORIZATION, value);
+                }
+                Err(err) => {
+                    config.new_parse_error(
+                        (&prefix, "auth"),
+                        format!("Invalid authentication credentials: {err}"),
+                    );
+                }
+            }
+        }
+
+        // Parse field mappings
+        let mut mappings = HttpMappings::default();
+        for (field_id, field, default) in [
+            ("fields.name", &mut mappings.field_name, "name"),
+            (
+                "fields.description",
+                &mut mappings.field_description,
+                "description",
+            ),
+            ("fields.secret", &mut mappings.field_secret, "secret"),
+            ("fields.email", &mut mappings.field_email, "email"),
+            ("fields.quota", &mut mappings.field_quota, "quota"),
+            ("fields.class", &mut mappings.field_type, "type"),
+            (
+                "fields.member-of",
+                &mut mappings.field_member_of,
+                "memberOf",
+            ),
+        ] {
+            *field = config
+                .value((&prefix, field_id))
+                .unwrap_or(default)
+                .to_string();
+        }
+
+        // Build client
+        let client = reqwest::Client::builder()
+            .timeout(
+                config
+                    .property_or_default::<Duration>((&prefix, "timeout"), "30s")
+                    .unwrap_or_else(|| Duration::from_secs(30)),
+            )
+            .danger_accept_invalid_certs(
+                config
+                    .property_or_default::<bool>((&prefix, "tls.allow-invalid-certs"), "false")
+                    .unwrap_or(false),
+            )
+            .build()
+            .map_err(|err| {
+                config.new_build_error(
+                    prefix.as_str(),
+                    format!("Failed to build HTTP client: {err}"),
+                )
+            })
+            .ok()?;
+
+        // Parse response cache
+        let cache = config
+            .property_or_default::<Option<u64>>((&prefix, "cache.response.size"), "1048576")
+            .unwrap_or_default()
+            .map(|cached_size| HttpResponseCache {
+                responses: CacheWithTtl::new(100, cached_size),
+                ttl_pos: config
+                    .property((&prefix, "cache.response.ttl.positive"))
+                    .unwrap_or(Duration::from_secs(300)),
+                ttl_neg: config
+                    .property((&prefix, "cache.response.ttl.negative"))
+                    .unwrap_or(Duration::from_secs(60)),
+            });
+
+        Some(HttpDirectory {
+            client,
+            endpoints,
+            mappings,
+            cache,
+            max_size: config
+                .property_or_default::<usize>((&prefix, "limits.size"), "1048576")
+                .unwrap_or(1048576),
+            data_store,
+        })
+    }
+}
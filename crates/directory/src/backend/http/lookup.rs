@@ -0,0 +1,501 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{HttpDirectory, HttpMappings};
+use crate::{
+    Principal, PrincipalData, QueryBy, QueryParams, ROLE_ADMIN, ROLE_USER, Type,
+    backend::{
+        RcptType,
+        internal::{
+            lookup::DirectoryStore,
+            manage::{self, ManageDirectory, UpdatePrincipal},
+        },
+    },
+};
+use compact_str::ToCompactString;
+use mail_send::Credentials;
+use reqwest::{
+    StatusCode,
+    header::{AUTHORIZATION, CONTENT_TYPE, HeaderValue},
+};
+use serde_json::Value;
+use std::fmt::Write;
+use trc::AddContext;
+use utils::HttpLimitResponse;
+
+type HttpResponse = serde_json::Map<String, Value>;
+
+struct ExternalPrincipal {
+    principal: Principal,
+    member_of: Vec<String>,
+}
+
+impl HttpDirectory {
+    pub async fn query(&self, by: QueryParams<'_>) -> trc::Result<Option<Principal>> {
+        let (external_principal, stored_principal) = match by.by {
+            QueryBy::Name(username) => (
+                self.fetch_principal(username)
+                    .await
+                    .caused_by(trc::location!())?,
+                None,
+            ),
+            QueryBy::Id(uid) => {
+                if let Some(principal) = self
+                    .data_store
+                    .query(QueryParams::id(uid).with_return_member_of(by.return_member_of))
+                    .await
+                    .caused_by(trc::location!())?
+                {
+                    (
+                        self.fetch_principal(principal.name())
+                            .await
+                            .caused_by(trc::location!())?,
+                        Some(principal),
+                    )
+                } else {
+                    return Ok(None);
+                }
+            }
+            QueryBy::Credentials(credentials) => match (credentials, &self.endpoints.auth) {
+                // Bearer tokens can only be validated by the remote service
+                (Credentials::OAuthBearer { token }, Some(endpoint)) => (
+                    self.authenticate(endpoint, None, token)
+                        .await
+                        .caused_by(trc::location!())?,
+                    None,
+                ),
+                (Credentials::OAuthBearer { .. }, None) => return Ok(None),
+                (
+                    Credentials::Plain { username, secret }
+                    | Credentials::XOauth2 { username, secret },
+                    Some(endpoint),
+                ) => (
+                    // Secrets are verified by the remote service
+                    self.authenticate(endpoint, Some(username.as_str()), secret)
+                        .await
+                        .caused_by(trc::location!())?,
+                    None,
+                ),
+                (
+                    Credentials::Plain { username, secret }
+                    | Credentials::XOauth2 { username, secret },
+                    None,
+                ) => match self
+                    .fetch_principal(username)
+                    .await
+                    .caused_by(trc::location!())?
+                {
+                    Some(external)
+                        if external
+                            .principal
+                            .verify_secret(secret, false)
+                            .await
+                            .caused_by(trc::location!())? =>
+                    {
+                        (Some(external), None)
+                    }
+                    _ => (None, None),
+                },
+            },
+        };
+
+        let Some(ExternalPrincipal {
+            principal: mut external_principal,
+            member_of,
+        }) = external_principal
+        else {
+            return Ok(None);
+        };
+
+        // Obtain members
+        if by.return_member_of && !member_of.is_empty() {
+            let mut data = Vec::with_capacity(member_of.len());
+            for name in member_of {
+                data.push(
+                    self.data_store
+                        .get_or_create_principal_id(&name, Type::Group)
+                        .await
+                        .caused_by(trc::location!())?,
+                );
+            }
+            external_principal.data.push(PrincipalData::MemberOf(data));
+        }
+
+        // Obtain account ID if not available
+        let mut principal = if let Some(stored_principal) = stored_principal {
+            stored_principal
+        } else {
+            let id = self
+                .data_store
+                .get_or_create_principal_id(external_principal.name(), Type::Individual)
+                .await
+                .caused_by(trc::location!())?;
+
+            self.data_store
+                .query(QueryParams::id(id).with_return_member_of(by.return_member_of))
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| manage::not_found(id).caused_by(trc::location!()))?
+        };
+
+        // Keep the internal store up to date with the remote service
+        let changes = principal.update_external(external_principal, true);
+        if !changes.is_empty() {
+            self.data_store
+                .update_principal(
+                    UpdatePrincipal::by_id(principal.id)
+                        .with_updates(changes)
+                        .create_domains(),
+                )
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(Some(principal))
+    }
+
+    pub async fn email_to_id(&self, address: &str) -> trc::Result<Option<u32>> {
+        if let Some(name) = self
+            .fetch(&self.endpoints.email, "{address}", address)
+            .await
+            .caused_by(trc::location!())?
+            .and_then(|mut response| take_string(&mut response, &self.mappings.field_name))
+        {
+            self.data_store
+                .get_or_create_principal_id(&name, Type::Individual)
+                .await
+                .caused_by(trc::location!())
+                .map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub async fn rcpt(&self, address: &str) -> trc::Result<RcptType> {
+        if self
+            .fetch(&self.endpoints.email, "{address}", address)
+            .await
+            .caused_by(trc::location!())?
+            .is_some()
+        {
+            Ok(RcptType::Mailbox)
+        } else {
+            self.data_store.rcpt(address).await.map(|result| {
+                if matches!(result, RcptType::List(_)) {
+                    result
+                } else {
+                    RcptType::Invalid
+                }
+            })
+        }
+    }
+
+    pub async fn vrfy(&self, address: &str) -> trc::Result<Vec<String>> {
+        self.data_store.vrfy(address).await
+    }
+
+    pub async fn expn(&self, address: &str) -> trc::Result<Vec<String>> {
+        self.data_store.expn(address).await
+    }
+
+    pub async fn is_local_domain(&self, domain: &str) -> trc::Result<bool> {
+        self.data_store.is_local_domain(domain).await
+    }
+
+    async fn fetch_principal(&self, username: &str) -> trc::Result<Option<ExternalPrincipal>> {
+        Ok(self
+            .fetch(&self.endpoints.name, "{name}", username)
+            .await?
+            .map(|response| self.mappings.response_to_principal(response, username)))
+    }
+
+    async fn fetch(
+        &self,
+        endpoint: &str,
+        placeholder: &str,
+        value: &str,
+    ) -> trc::Result<Option<HttpResponse>> {
+        let url = endpoint.replace(placeholder, &url_encode(value));
+
+        // Check cache
+        if let Some(cache) = &self.cache
+            && let Some(body) = cache.responses.get(&url)
+        {
+            return if !body.is_empty() {
+                parse_response(&url, body.as_bytes()).map(Some)
+            } else {
+                Ok(None)
+            };
+        }
+
+        let response = self
+            .client
+            .get(&url)
+            .headers(self.endpoints.headers.clone())
+            .send()
+            .await
+            .map_err(|err| {
+                trc::StoreEvent::HttpStoreError
+                    .into_err()
+                    .reason(err)
+                    .ctx(trc::Key::Url, url.to_compact_string())
+                    .details("HTTP request failed")
+            })?;
+        let body = match response.status() {
+            StatusCode::OK => {
+                String::from_utf8(self.read_body(response, &url).await?).map_err(|err| {
+                    trc::StoreEvent::HttpStoreError
+                        .into_err()
+                        .reason(err)
+                        .ctx(trc::Key::Url, url.to_compact_string())
+                        .details("Invalid UTF-8 response")
+                })?
+            }
+            StatusCode::NOT_FOUND => String::new(),
+            other => {
+                return Err(trc::StoreEvent::HttpStoreError
+                    .into_err()
+                    .code(other.as_u16())
+                    .ctx(trc::Key::Url, url.to_compact_string())
+                    .details("Unexpected status code"));
+            }
+        };
+        let result = if !body.is_empty() {
+            parse_response(&url, body.as_bytes()).map(Some)?
+        } else {
+            None
+        };
+
+        // Update cache
+        if let Some(cache) = &self.cache {
+            let ttl = if result.is_some() {
+                cache.ttl_pos
+            } else {
+                cache.ttl_neg
+            };
+            cache.responses.insert(url, body, ttl);
+        }
+
+        Ok(result)
+    }
+
+    async fn authenticate(
+        &self,
+        endpoint: &str,
+        username: Option<&str>,
+        secret: &str,
+    ) -> trc::Result<Option<ExternalPrincipal>> {
+        let mut headers = self.endpoints.headers.clone();
+        let body = if let Some(username) = username {
+            serde_json::json!({
+                "username": username,
+                "secret": secret,
+            })
+            .to_string()
+        } else if let Ok(value) = HeaderValue::from_str(&format!("Bearer {secret}")) {
+            headers.insert(AUTHORIZATION, value);
+            "{}".to_string()
+        } else {
+            return Ok(None);
+        };
+        let response = self
+            .client
+            .post(endpoint)
+            .headers(headers)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| {
+                trc::StoreEvent::HttpStoreError
+                    .into_err()
+                    .reason(err)
+                    .ctx(trc::Key::Url, endpoint.to_compact_string())
+                    .details("HTTP request failed")
+            })?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let body = self.read_body(response, endpoint).await?;
+
+                parse_response(endpoint, &body).map(|response| {
+                    Some(
+                        self.mappings
+                            .response_to_principal(response, username.unwrap_or_default()),
+                    )
+                    .filter(|external| !external.principal.name.is_empty())
+                })
+            }
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN | StatusCode::NOT_FOUND => Ok(None),
+            other => Err(trc::StoreEvent::HttpStoreError
+                .into_err()
+                .code(other.as_u16())
+                .ctx(trc::Key::Url, endpoint.to_compact_string())
+                .details("Unexpected status code")),
+        }
+    }
+
+    async fn read_body(&self, response: reqwest::Response, url: &str) -> trc::Result<Vec<u8>> {
+        response
+            .bytes_with_limit(self.max_size)
+            .await
+            .map_err(|err| {
+                trc::StoreEvent::HttpStoreError
+                    .into_err()
+                    .reason(err)
+                    .ctx(trc::Key::Url, url.to_compact_string())
+                    .details("Failed to read response")
+            })?
+            .ok_or_else(|| {
+                trc::StoreEvent::HttpStoreError
+                    .into_err()
+                    .ctx(trc::Key::Url, url.to_compact_string())
+                    .details("Response is too large")
+            })
+    }
+}
+
+impl HttpMappings {
+    fn response_to_principal(
+        &self,
+        mut response: HttpResponse,
+        username: &str,
+    ) -> ExternalPrincipal {
+        let mut principal = Principal::new(u32::MAX, Type::Individual);
+        let mut role = ROLE_USER;
+
+        principal.name =
+            take_string(&mut response, &self.field_name).unwrap_or_else(|| username.to_string());
+        principal.description = take_string(&mut response, &self.field_description);
+        principal
+            .secrets
+            .extend(take_strings(&mut response, &self.field_secret));
+        principal.emails.extend(
+            take_strings(&mut response, &self.field_email)
+                .into_iter()
+                .map(|email| email.to_lowercase()),
+        );
+        if let Some(quota) = response.remove(&self.field_quota).and_then(|v| v.as_u64()) {
+            principal.quota = quota.into();
+        }
+        if let Some(typ) = take_string(&mut response, &self.field_type) {
+            match typ.to_lowercase().as_str() {
+                "individual" | "person" | "user" => {
+                    principal.typ = Type::Individual;
+                }
+                "group" => principal.typ = Type::Group,
+                "admin" | "superuser" | "administrator" => {
+                    principal.typ = Type::Individual;
+                    role = ROLE_ADMIN;
+                }
+                _ => (),
+            }
+        }
+        principal.data.push(PrincipalData::Roles(vec![role]));
+
+        ExternalPrincipal {
+            principal,
+            member_of: take_strings(&mut response, &self.field_member_of),
+        }
+    }
+}
+
+fn parse_response(url: &str, body: &[u8]) -> trc::Result<HttpResponse> {
+    serde_json::from_slice::<HttpResponse>(body).map_err(|err| {
+        trc::StoreEvent::HttpStoreError
+            .into_err()
+            .reason(err)
+            .ctx(trc::Key::Url, url.to_compact_string())
+            .details("Failed to deserialize response")
+    })
+}
+
+fn take_string(response: &mut HttpResponse, field: &str) -> Option<String> {
+    match response.remove(field) {
+        Some(Value::String(value)) if !value.is_empty() => Some(value),
+        _ => None,
+    }
+}
+
+fn take_strings(response: &mut HttpResponse, field: &str) -> Vec<String> {
+    match response.remove(field) {
+        Some(Value::String(value)) if !value.is_empty() => vec![value],
+        Some(Value::Array(values)) => values
+            .into_iter()
+            .filter_map(|value| match value {
+                Value::String(value) if !value.is_empty() => Some(value),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn url_encode(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~' | b'@') {
+            result.push(byte as char);
+        } else {
+            let _ = write!(result, "%{byte:02X}");
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn http_response_mapping() {
+        let mappings = HttpMappings {
+            field_name: "name".into(),
+            field_description: "description".into(),
+            field_secret: "secret".into(),
+            field_email: "email".into(),
+            field_quota: "quota".into(),
+            field_type: "type".into(),
+            field_member_of: "memberOf".into(),
+        };
+        let response = serde_json::from_str::<HttpResponse>(
+            r#"{
+                "description": "John Doe",
+                "secret": "$2y$05$bvIG6Nmid91Mu9RcmmWZfO5HJIMCT8riNW0hEp8f6/FuA2/mHZFpe",
+                "email": ["John@example.org", "jdoe@example.org"],
+                "quota": 1024,
+                "type": "admin",
+                "memberOf": ["sales", "support"]
+            }"#,
+        )
+        .unwrap();
+
+        let external = mappings.response_to_principal(response, "john");
+        assert_eq!(external.principal.name, "john");
+        assert_eq!(external.principal.description.as_deref(), Some("John Doe"));
+        assert_eq!(external.principal.secrets.len(), 1);
+        assert_eq!(
+            external.principal.emails,
+            vec![
+                "john@example.org".to_string(),
+                "jdoe@example.org".to_string()
+            ]
+        );
+        assert_eq!(external.principal.quota, Some(1024));
+        assert_eq!(
+            external.principal.data,
+            vec![PrincipalData::Roles(vec![ROLE_ADMIN])]
+        );
+        assert_eq!(
+            external.member_of,
+            vec!["sales".to_string(), "support".to_string()]
+        );
+        assert_eq!(
+            url_encode("john doe+1@example.org"),
+            "john%20doe%2B1@example.org"
+        );
+    }
+}
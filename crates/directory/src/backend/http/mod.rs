@@ -0,0 +1,47 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use reqwest::header::HeaderMap;
+use store::Store;
+use utils::cache::CacheWithTtl;
+
+pub mod config;
+pub mod lookup;
+
+pub struct HttpDirectory {
+    client: reqwest::Client,
+    endpoints: HttpEndpoints,
+    mappings: HttpMappings,
+    cache: Option<HttpResponseCache>,
+    max_size: usize,
+    pub(crate) data_store: Store,
+}
+
+struct HttpEndpoints {
+    name: String,
+    email: String,
+    auth: Option<String>,
+    headers: HeaderMap,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct HttpMappings {
+    field_name: String,
+    field_description: String,
+    field_secret: String,
+    field_email: String,
+    field_quota: String,
+    field_type: String,
+    field_member_of: String,
+}
+
+struct HttpResponseCache {
+    responses: CacheWithTtl<String, String>,
+    ttl_pos: Duration,
+    ttl_neg: Duration,
+}
@@ -0,0 +1,464 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::fmt::Write;
+
+use smtp_proto::IntoString;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite};
+
+use super::{ImapClient, ImapError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImapListItem {
+    pub name: String,
+    pub delimiter: Option<char>,
+    pub attributes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImapMailboxStatus {
+    pub uid_validity: u32,
+    pub exists: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImapMessage {
+    pub uid: u32,
+    pub flags: Vec<String>,
+    pub internal_date: Option<String>,
+    pub contents: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Atom(Vec<u8>),
+    String(Vec<u8>),
+    ParenthesisOpen,
+    ParenthesisClose,
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> ImapClient<T> {
+    pub async fn login(&mut self, username: &str, secret: &str) -> Result<(), ImapError> {
+        let mut command = String::with_capacity(username.len() + secret.len() + 16);
+        command.push_str("LOGIN ");
+        write_quoted(&mut command, username);
+        command.push(' ');
+        write_quoted(&mut command, secret);
+
+        match self.command("L1", &command).await {
+            Err(ImapError::InvalidResponse(response)) if response.starts_with("L1 NO") => {
+                Err(ImapError::AuthenticationFailed)
+            }
+            result => result.map(|_| ()),
+        }
+    }
+
+    pub async fn list(&mut self) -> Result<Vec<ImapListItem>, ImapError> {
+        let response = self.command("L2", "LIST \"\" \"*\"").await?;
+        let mut items = Vec::new();
+
+        for line in tokenize_response(&response) {
+            let mut tokens = line.into_iter();
+            if !matches!(tokens.next(), Some(Token::Atom(star)) if star == b"*")
+                || !matches!(tokens.next(), Some(Token::Atom(cmd)) if cmd.eq_ignore_ascii_case(b"LIST"))
+                || tokens.next() != Some(Token::ParenthesisOpen)
+            {
+                continue;
+            }
+
+            let mut attributes = Vec::new();
+            for token in tokens.by_ref() {
+                match token {
+                    Token::Atom(attr) => attributes.push(attr.into_string()),
+                    Token::ParenthesisClose => break,
+                    _ => (),
+                }
+            }
+            let delimiter = match tokens.next() {
+                Some(Token::String(delimiter)) => delimiter.first().map(|ch| *ch as char),
+                _ => None,
+            };
+            if let Some(Token::Atom(name) | Token::String(name)) = tokens.next() {
+                items.push(ImapListItem {
+                    name: name.into_string(),
+                    delimiter,
+                    attributes,
+                });
+            }
+        }
+
+        Ok(items)
+    }
+
+    pub async fn examine(&mut self, mailbox: &str) -> Result<ImapMailboxStatus, ImapError> {
+        let mut command = String::with_capacity(mailbox.len() + 10);
+        command.push_str("EXAMINE ");
+        write_quoted(&mut command, mailbox);
+        let response = self.command("L3", &command).await?;
+        let mut status = ImapMailboxStatus::default();
+
+        for line in tokenize_response(&response) {
+            match line.as_slice() {
+                [Token::Atom(star), Token::Atom(value), Token::Atom(cmd), ..]
+                    if star == b"*" && cmd.eq_ignore_ascii_case(b"EXISTS") =>
+                {
+                    status.exists = parse_number(value).unwrap_or_default();
+                }
+                [
+                    Token::Atom(star),
+                    Token::Atom(ok),
+                    Token::Atom(code),
+                    Token::Atom(value),
+                    ..,
+                ] if star == b"*"
+                    && ok.eq_ignore_ascii_case(b"OK")
+                    && code.eq_ignore_ascii_case(b"[UIDVALIDITY") =>
+                {
+                    status.uid_validity =
+                        parse_number(value.strip_suffix(b"]").unwrap_or(value)).unwrap_or_default();
+                }
+                _ => (),
+            }
+        }
+
+        Ok(status)
+    }
+
+    pub async fn uid_search_from(&mut self, uid: u32) -> Result<Vec<u32>, ImapError> {
+        let response = self
+            .command("L4", &format!("UID SEARCH UID {}:*", uid.max(1)))
+            .await?;
+        let mut uids = Vec::new();
+
+        for line in tokenize_response(&response) {
+            let mut tokens = line.into_iter();
+            if matches!(tokens.next(), Some(Token::Atom(star)) if star == b"*")
+                && matches!(tokens.next(), Some(Token::Atom(cmd)) if cmd.eq_ignore_ascii_case(b"SEARCH"))
+            {
+                for token in tokens {
+                    if let Token::Atom(value) = token {
+                        // "n:*" always matches the highest UID, even if below n
+                        if let Some(value) = parse_number(&value).filter(|value| *value >= uid) {
+                            uids.push(value);
+                        }
+                    }
+                }
+            }
+        }
+        uids.sort_unstable();
+        uids.dedup();
+
+        Ok(uids)
+    }
+
+    pub async fn uid_fetch(&mut self, uids: &[u32]) -> Result<Vec<ImapMessage>, ImapError> {
+        let mut command = String::with_capacity(uids.len() * 6 + 64);
+        command.push_str("UID FETCH ");
+        for (pos, uid) in uids.iter().enumerate() {
+            if pos > 0 {
+                command.push(',');
+            }
+            let _ = write!(command, "{uid}");
+        }
+        command.push_str(" (UID FLAGS INTERNALDATE BODY.PEEK[])");
+        let response = self.command("L5", &command).await?;
+        let mut messages = Vec::with_capacity(uids.len());
+
+        for line in tokenize_response(&response) {
+            let mut tokens = line.into_iter();
+            if !matches!(tokens.next(), Some(Token::Atom(star)) if star == b"*")
+                || !matches!(tokens.next(), Some(Token::Atom(_)))
+                || !matches!(tokens.next(), Some(Token::Atom(cmd)) if cmd.eq_ignore_ascii_case(b"FETCH"))
+                || tokens.next() != Some(Token::ParenthesisOpen)
+            {
+                continue;
+            }
+
+            let mut message = ImapMessage::default();
+            while let Some(Token::Atom(item)) = tokens.next() {
+                if item.eq_ignore_ascii_case(b"UID") {
+                    if let Some(Token::Atom(value)) = tokens.next() {
+                        message.uid = parse_number(&value).unwrap_or_default();
+                    }
+                } else if item.eq_ignore_ascii_case(b"FLAGS") {
+                    if tokens.next() == Some(Token::ParenthesisOpen) {
+                        for token in tokens.by_ref() {
+                            match token {
+                                Token::Atom(flag) => message.flags.push(flag.into_string()),
+                                Token::ParenthesisClose => break,
+                                _ => (),
+                            }
+                        }
+                    }
+                } else if item.eq_ignore_ascii_case(b"INTERNALDATE") {
+                    if let Some(Token::String(value)) = tokens.next() {
+                        message.internal_date = Some(value.into_string());
+                    }
+                } else if item.eq_ignore_ascii_case(b"BODY[]") {
+                    if let Some(Token::String(value)) = tokens.next() {
+                        message.contents = value;
+                    }
+                } else {
+                    // Skip unrequested items such as MODSEQ
+                    match tokens.next() {
+                        Some(Token::ParenthesisOpen) => {
+                            let mut depth = 1;
+                            for token in tokens.by_ref() {
+                                match token {
+                                    Token::ParenthesisOpen => depth += 1,
+                                    Token::ParenthesisClose => {
+                                        depth -= 1;
+                                        if depth == 0 {
+                                            break;
+                                        }
+                                    }
+                                    _ => (),
+                                }
+                            }
+                        }
+                        Some(_) => (),
+                        None => break,
+                    }
+                }
+            }
+
+            if message.uid != 0 {
+                messages.push(message);
+            }
+        }
+
+        Ok(messages)
+    }
+
+    async fn command(&mut self, tag: &str, command: &str) -> Result<Vec<u8>, ImapError> {
+        tokio::time::timeout(self.timeout, async {
+            self.write(format!("{tag} {command}\r\n").as_bytes()).await
+        })
+        .await
+        .map_err(|_| ImapError::Timeout)??;
+
+        let mut response = Vec::with_capacity(1024);
+        let mut buf = vec![0u8; 8192];
+        loop {
+            let br = tokio::time::timeout(self.timeout, self.stream.read(&mut buf))
+                .await
+                .map_err(|_| ImapError::Timeout)??;
+            if br == 0 {
+                return Err(ImapError::Disconnected);
+            }
+            response.extend_from_slice(&buf[..br]);
+
+            if let Some(tagged_pos) = find_tagged_response(&response, tag.as_bytes()) {
+                let tagged = response.split_off(tagged_pos);
+                return if tagged
+                    .get(tag.len() + 1..tag.len() + 3)
+                    .is_some_and(|status| status.eq_ignore_ascii_case(b"OK"))
+                {
+                    Ok(response)
+                } else {
+                    Err(ImapError::InvalidResponse(tagged.into_string()))
+                };
+            }
+        }
+    }
+}
+
+fn write_quoted(buf: &mut String, value: &str) {
+    buf.push('"');
+    for ch in value.chars() {
+        if matches!(ch, '"' | '\\') {
+            buf.push('\\');
+        }
+        buf.push(ch);
+    }
+    buf.push('"');
+}
+
+fn parse_number(value: &[u8]) -> Option<u32> {
+    std::str::from_utf8(value).ok()?.parse().ok()
+}
+
+// Returns the position of the tagged response line once it has been fully received
+pub fn find_tagged_response(data: &[u8], tag: &[u8]) -> Option<usize> {
+    let mut pos = 0;
+
+    while pos < data.len() {
+        let line_start = pos;
+        let is_tagged = data[pos..].starts_with(tag) && data.get(pos + tag.len()) == Some(&b' ');
+
+        // Find the end of the line, skipping literals
+        loop {
+            let line_end = pos + data[pos..].iter().position(|ch| *ch == b'\n')?;
+            match literal_size(&data[line_start..line_end]) {
+                Some(size) if !is_tagged => {
+                    pos = line_end + 1 + size;
+                    if pos > data.len() {
+                        return None;
+                    }
+                }
+                _ => {
+                    if is_tagged {
+                        return Some(line_start);
+                    }
+                    pos = line_end + 1;
+                    break;
+                }
+            }
+        }
+    }
+
+    None
+}
+
+// Parses the size of a literal at the end of a line such as "{123}\r"
+fn literal_size(line: &[u8]) -> Option<usize> {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    let line = line.strip_suffix(b"}")?;
+    let start = line.iter().rposition(|ch| *ch == b'{')?;
+    let size = &line[start + 1..];
+    let size = size.strip_suffix(b"+").unwrap_or(size);
+    if !size.is_empty() && size.iter().all(|ch| ch.is_ascii_digit()) {
+        std::str::from_utf8(size).ok()?.parse().ok()
+    } else {
+        None
+    }
+}
+
+pub fn tokenize_response(data: &[u8]) -> Vec<Vec<Token>> {
+    let mut lines = Vec::new();
+    let mut line = Vec::new();
+    let mut pos = 0;
+
+    while pos < data.len() {
+        match data[pos] {
+            b'\n' => {
+                if !line.is_empty() {
+                    lines.push(std::mem::take(&mut line));
+                }
+                pos += 1;
+            }
+            b' ' | b'\r' => {
+                pos += 1;
+            }
+            b'(' => {
+                line.push(Token::ParenthesisOpen);
+                pos += 1;
+            }
+            b')' => {
+                line.push(Token::ParenthesisClose);
+                pos += 1;
+            }
+            b'"' => {
+                let mut value = Vec::new();
+                pos += 1;
+                while let Some(&ch) = data.get(pos) {
+                    pos += 1;
+                    match ch {
+                        b'\\' => {
+                            if let Some(&ch) = data.get(pos) {
+                                value.push(ch);
+                                pos += 1;
+                            }
+                        }
+                        b'"' => break,
+                        _ => value.push(ch),
+                    }
+                }
+                line.push(Token::String(value));
+            }
+            b'{' => {
+                let line_end = data[pos..]
+                    .iter()
+                    .position(|ch| *ch == b'\n')
+                    .map_or(data.len(), |end| pos + end);
+                if let Some(size) = literal_size(&data[pos..line_end]) {
+                    let start = (line_end + 1).min(data.len());
+                    let end = (start + size).min(data.len());
+                    line.push(Token::String(data[start..end].to_vec()));
+                    pos = end;
+                } else {
+                    line.push(Token::Atom(vec![b'{']));
+                    pos += 1;
+                }
+            }
+            _ => {
+                let start = pos;
+                while let Some(&ch) = data.get(pos) {
+                    if matches!(ch, b' ' | b'\r' | b'\n' | b'(' | b')' | b'"') {
+                        break;
+                    }
+                    pos += 1;
+                }
+                line.push(Token::Atom(data[start..pos].to_vec()));
+            }
+        }
+    }
+
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Token, find_tagged_response, tokenize_response};
+
+    #[test]
+    fn tokenize_fetch() {
+        let response = concat!(
+            "* 1 FETCH (UID 7 FLAGS (\\Seen $Forwarded) INTERNALDATE ",
+            "\"17-Jul-1996 02:44:25 -0700\" BODY[] {12}\r\n",
+            "Hello\r\nWorld)\r\n",
+            "* LIST (\\HasNoChildren \\Sent) \"/\" {4}\r\nSent\r\n",
+            "L5 OK done\r\n"
+        )
+        .as_bytes();
+
+        assert_eq!(
+            find_tagged_response(response, b"L5"),
+            Some(response.len() - 12)
+        );
+        assert_eq!(find_tagged_response(&response[..40], b"L5"), None);
+        assert_eq!(find_tagged_response(&response[..100], b"L5"), None);
+
+        let lines = tokenize_response(&response[..response.len() - 12]);
+        assert_eq!(
+            lines,
+            vec![
+                vec![
+                    Token::Atom(b"*".to_vec()),
+                    Token::Atom(b"1".to_vec()),
+                    Token::Atom(b"FETCH".to_vec()),
+                    Token::ParenthesisOpen,
+                    Token::Atom(b"UID".to_vec()),
+                    Token::Atom(b"7".to_vec()),
+                    Token::Atom(b"FLAGS".to_vec()),
+                    Token::ParenthesisOpen,
+                    Token::Atom(b"\\Seen".to_vec()),
+                    Token::Atom(b"$Forwarded".to_vec()),
+                    Token::ParenthesisClose,
+                    Token::Atom(b"INTERNALDATE".to_vec()),
+                    Token::String(b"17-Jul-1996 02:44:25 -0700".to_vec()),
+                    Token::Atom(b"BODY[]".to_vec()),
+                    Token::String(b"Hello\r\nWorld".to_vec()),
+                    Token::ParenthesisClose,
+                ],
+                vec![
+                    Token::Atom(b"*".to_vec()),
+                    Token::Atom(b"LIST".to_vec()),
+                    Token::ParenthesisOpen,
+                    Token::Atom(b"\\HasNoChildren".to_vec()),
+                    Token::Atom(b"\\Sent".to_vec()),
+                    Token::ParenthesisClose,
+                    Token::String(b"/".to_vec()),
+                    Token::String(b"Sent".to_vec()),
+                ],
+            ]
+        );
+    }
+}
@@ -6,6 +6,7 @@
 
 pub mod client;
 pub mod config;
+pub mod fetch;
 pub mod lookup;
 pub mod pool;
 pub mod tls;
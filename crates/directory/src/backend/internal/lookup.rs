@@ -91,11 +91,16 @@ impl DirectoryStore for Store {
     }
 
     async fn is_local_domain(&self, domain: &str) -> trc::Result<bool> {
-        self.get_value::<PrincipalInfo>(ValueKey::from(ValueClass::Directory(
-            DirectoryClass::NameToId(domain.as_bytes().to_vec()),
-        )))
-        .await
-        .map(|p| p.is_some_and(|p| p.typ == Type::Domain))
+        match self
+            .get_value::<PrincipalInfo>(ValueKey::from(ValueClass::Directory(
+                DirectoryClass::NameToId(domain.as_bytes().to_vec()),
+            )))
+            .await?
+        {
+            // Mail is only accepted for domains that completed onboarding
+            Some(pinfo) => Ok(pinfo.typ == Type::Domain && !pinfo.inactive),
+            None => Ok(false),
+        }
     }
 
     async fn rcpt(&self, address: &str) -> trc::Result<RcptType> {
@@ -9,9 +9,13 @@ use super::{
     SpecialSecrets, lookup::DirectoryStore,
 };
 use crate::{
-    FALLBACK_ADMIN_ID, MemberOf, Permission, PermissionGrant, Permissions, Principal,
+    DomainStatus, FALLBACK_ADMIN_ID, MemberOf, Permission, PermissionGrant, Permissions, Principal,
     PrincipalData, PrincipalQuota, QueryBy, QueryParams, ROLE_ADMIN, ROLE_TENANT_ADMIN, ROLE_USER,
-    Type, backend::RcptType, core::principal::build_search_index,
+    Type,
+    backend::RcptType,
+    core::principal::{
+        AVATAR_CONTENT_TYPES, MAX_AVATAR_LEN, OUT_OF_OFFICE_INDEX, build_search_index,
+    },
 };
 use ahash::{AHashMap, AHashSet};
 use compact_str::CompactString;
@@ -23,11 +27,11 @@ use store::{
     roaring::RoaringBitmap,
     write::{
         AlignedBytes, Archive, Archiver, BatchBuilder, DirectoryClass, ValueClass,
-        key::DeserializeBigEndian,
+        key::DeserializeBigEndian, now,
     },
 };
 use trc::AddContext;
-use utils::sanitize_email;
+use utils::{normalize_domain, sanitize_email};
 
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
 pub struct PrincipalList<T> {
@@ -69,6 +73,7 @@ pub trait ManageDirectory: Sized {
     async fn get_principal_name(&self, principal_id: u32) -> trc::Result<Option<String>>;
     async fn get_member_of(&self, principal_id: u32) -> trc::Result<Vec<MemberOf>>;
     async fn get_members(&self, principal_id: u32) -> trc::Result<Vec<u32>>;
+    async fn get_out_of_office_principals(&self) -> trc::Result<RoaringBitmap>;
     async fn create_principal(
         &self,
         principal: PrincipalSet,
@@ -259,8 +264,11 @@ impl ManageDirectory for Store {
         mut tenant_id: Option<u32>,
         allowed_permissions: Option<&Permissions>,
     ) -> trc::Result<CreatedPrincipal> {
-        // Make sure the principal has a name
-        let name = principal_set.name().to_lowercase();
+        // Make sure the principal has a name, domains are stored in their ASCII form
+        let mut name = principal_set.name().to_lowercase();
+        if principal_set.typ() == Type::Domain {
+            name = normalize_domain(&name).into_owned();
+        }
         if name.is_empty() {
             return Err(err_missing(PrincipalField::Name));
         }
@@ -382,19 +390,101 @@ impl ManageDirectory for Store {
             .take_str_array(PrincipalField::Secrets)
             .unwrap_or_default();
         if let Some(picture) = principal_set.take_str(PrincipalField::Picture) {
+            let picture = validate_picture(picture)?;
             principal_create.data.push(PrincipalData::Picture(picture));
         }
         if let Some(picture) = principal_set.take_str(PrincipalField::Locale) {
             principal_create.data.push(PrincipalData::Locale(picture));
         }
+        if let Some(phone) = principal_set.take_str(PrincipalField::Phone) {
+            principal_create.data.push(PrincipalData::Phone(phone));
+        }
+        if let Some(email) = principal_set.take_str(PrincipalField::RecoveryEmail) {
+            let email = validate_recovery_email(email)?;
+            principal_create
+                .data
+                .push(PrincipalData::RecoveryEmail(email));
+        }
         if let Some(urls) = principal_set.take_str_array(PrincipalField::Urls) {
             principal_create.data.push(PrincipalData::Urls(urls));
         }
+        if let Some(soft_quota) = principal_set
+            .take_int(PrincipalField::SoftQuota)
+            .filter(|quota| *quota > 0)
+        {
+            principal_create
+                .data
+                .push(PrincipalData::SoftQuota(soft_quota));
+        }
+        if let Some(message_quota) = principal_set
+            .take_int(PrincipalField::MessageQuota)
+            .filter(|quota| *quota > 0)
+        {
+            principal_create
+                .data
+                .push(PrincipalData::MessageQuota(message_quota));
+        }
+        if principal_set
+            .take_int(PrincipalField::LegalHold)
+            .is_some_and(|hold| hold > 0)
+        {
+            principal_create.data.push(PrincipalData::LegalHold(now()));
+        }
+        if principal_set
+            .take_int(PrincipalField::SpamTrap)
+            .is_some_and(|trap| trap > 0)
+        {
+            principal_create.data.push(PrincipalData::SpamTrap(now()));
+        }
+        if principal_set
+            .take_int(PrincipalField::CalendarOutOfOffice)
+            .is_some_and(|enabled| enabled > 0)
+        {
+            principal_create
+                .data
+                .push(PrincipalData::CalendarOutOfOffice(now()));
+        }
+        if principal_set
+            .take_int(PrincipalField::CollectContacts)
+            .is_some_and(|enabled| enabled > 0)
+        {
+            principal_create
+                .data
+                .push(PrincipalData::CollectContacts(now()));
+        }
+        if let Some(status) = principal_set.take_str(PrincipalField::DomainStatus)
+            && principal_create.typ == Type::Domain
+        {
+            principal_create.set_domain_status(parse_domain_status(&status)?);
+        }
         if let Some(urls) = principal_set.take_str_array(PrincipalField::ExternalMembers) {
             principal_create
                 .data
                 .push(PrincipalData::ExternalMembers(urls));
         }
+        if let Some(senders) = principal_set.take_str_array(PrincipalField::AllowedSenders) {
+            let mut allowed_senders = Vec::with_capacity(senders.len());
+            for sender in senders {
+                allowed_senders.push(validate_allowed_sender(self, &sender, tenant_id).await?);
+            }
+            principal_create
+                .data
+                .push(PrincipalData::AllowedSenders(allowed_senders));
+        }
+        for field in [PrincipalField::SendAs, PrincipalField::SendOnBehalf] {
+            if let Some(names) = principal_set.take_str_array(field) {
+                let mut delegates = Vec::with_capacity(names.len());
+                for name in names {
+                    let delegate_id = get_delegate_id(self, &name, tenant_id).await?;
+                    if !delegates.contains(&delegate_id) {
+                        delegates.push(delegate_id);
+                    }
+                }
+                if !delegates.is_empty() {
+                    principal_create.set_delegates(field, delegates);
+                }
+            }
+        }
         if let Some(quotas) = principal_set.take_int_array(PrincipalField::Quota) {
             let mut principal_quotas = Vec::new();
 
@@ -549,7 +639,8 @@ impl ManageDirectory for Store {
         }
         principal_create.id = principal_id;
         let mut batch = BatchBuilder::new();
-        let pinfo_name = PrincipalInfo::new(principal_id, principal_create.typ, tenant_id);
+        let pinfo_name = PrincipalInfo::new(principal_id, principal_create.typ, tenant_id)
+            .with_domain_status(principal_create.domain_status());
         let pinfo_email = PrincipalInfo::new(principal_id, principal_create.typ, None);
 
         // Validate object size
@@ -933,8 +1024,9 @@ impl ManageDirectory for Store {
 
         // Prepare changes
         let mut batch = BatchBuilder::new();
-        let mut pinfo_name =
-            PrincipalInfo::new(principal_id, principal_type, principal.tenant()).serialize();
+        let mut pinfo_name = PrincipalInfo::new(principal_id, principal_type, principal.tenant())
+            .with_domain_status(principal.domain_status())
+            .serialize();
         let pinfo_email = PrincipalInfo::new(principal_id, principal_type, None).serialize();
         let update_principal = !changes.is_empty()
             && !changes.iter().all(|c| {
@@ -992,13 +1084,18 @@ impl ManageDirectory for Store {
             Type::Role => &[Type::Role][..],
         };
         let mut valid_domains = AHashSet::new();
+        let mut reset_domain_status = false;
+        let mut domain_status_set = false;
 
         // Process changes
         for change in changes {
             match (change.action, change.field, change.value) {
                 (PrincipalAction::Set, PrincipalField::Name, PrincipalValue::String(new_name)) => {
                     // Make sure new name is not taken
-                    let new_name = new_name.to_lowercase();
+                    let mut new_name = new_name.to_lowercase();
+                    if principal_type == Type::Domain {
+                        new_name = normalize_domain(&new_name).into_owned();
+                    }
                     if principal.name() != new_name {
                         if tenant_id.is_some()
                             && !matches!(principal_type, Type::Tenant | Type::Domain)
@@ -1044,6 +1141,7 @@ impl ManageDirectory for Store {
                             pinfo_name.clone(),
                         );
                         principal.name = new_name;
+                        reset_domain_status |= principal_type == Type::Domain;
 
                         // Name changed, update changed principals
                         changed_principals.add_change(principal_id, principal_type, change.field);
@@ -1089,8 +1187,10 @@ impl ManageDirectory for Store {
                         changed_principals.add_change(principal_id, principal_type, change.field);
 
                         principal.tenant = tenant_info.id.into();
+                        reset_domain_status |= principal_type == Type::Domain;
                         pinfo_name =
                             PrincipalInfo::new(principal_id, principal_type, tenant_info.id.into())
+                                .with_domain_status(principal.domain_status())
                                 .serialize();
                     } else if let Some(tenant_id) = principal.tenant() {
                         // Update quota
@@ -1102,8 +1202,9 @@ impl ManageDirectory for Store {
                         changed_principals.add_change(principal_id, principal_type, change.field);
 
                         principal.tenant = None;
-                        pinfo_name =
-                            PrincipalInfo::new(principal_id, principal_type, None).serialize();
+                        pinfo_name = PrincipalInfo::new(principal_id, principal_type, None)
+                            .with_domain_status(principal.domain_status())
+                            .serialize();
                     } else {
                         continue;
                     }
@@ -1188,7 +1289,8 @@ impl ManageDirectory for Store {
                         .data
                         .retain(|v| !matches!(v, PrincipalData::Picture(_)));
                     if !value.is_empty() {
-                        principal.data.push(PrincipalData::Picture(value));
+                        let picture = validate_picture(value)?;
+                        principal.data.push(PrincipalData::Picture(picture));
                     }
                 }
                 (PrincipalAction::Set, PrincipalField::Locale, PrincipalValue::String(value)) => {
@@ -1199,6 +1301,108 @@ impl ManageDirectory for Store {
                         principal.data.push(PrincipalData::Locale(value));
                     }
                 }
+                (PrincipalAction::Set, PrincipalField::Phone, PrincipalValue::String(value)) => {
+                    principal
+                        .data
+                        .retain(|v| !matches!(v, PrincipalData::Phone(_)));
+                    if !value.is_empty() {
+                        principal.data.push(PrincipalData::Phone(value));
+                    }
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::RecoveryEmail,
+                    PrincipalValue::String(value),
+                ) => {
+                    principal
+                        .data
+                        .retain(|v| !matches!(v, PrincipalData::RecoveryEmail(_)));
+                    if !value.is_empty() {
+                        let email = validate_recovery_email(value)?;
+                        principal.data.push(PrincipalData::RecoveryEmail(email));
+                    }
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::LegalHold,
+                    PrincipalValue::Integer(hold),
+                ) if matches!(principal_type, Type::Individual | Type::Group) => {
+                    // Keep the original hold date when the hold is renewed
+                    if hold == 0 {
+                        principal
+                            .data
+                            .retain(|v| !matches!(v, PrincipalData::LegalHold(_)));
+                    } else if !principal
+                        .data
+                        .iter()
+                        .any(|v| matches!(v, PrincipalData::LegalHold(_)))
+                    {
+                        principal.data.push(PrincipalData::LegalHold(now()));
+                    }
+                    changed_principals.add_change(principal_id, principal_type, change.field);
+                }
+                (PrincipalAction::Set, PrincipalField::SpamTrap, PrincipalValue::Integer(trap))
+                    if matches!(principal_type, Type::Individual | Type::Group) =>
+                {
+                    if trap == 0 {
+                        principal
+                            .data
+                            .retain(|v| !matches!(v, PrincipalData::SpamTrap(_)));
+                    } else if !principal
+                        .data
+                        .iter()
+                        .any(|v| matches!(v, PrincipalData::SpamTrap(_)))
+                    {
+                        principal.data.push(PrincipalData::SpamTrap(now()));
+                    }
+                    changed_principals.add_change(principal_id, principal_type, change.field);
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::CalendarOutOfOffice,
+                    PrincipalValue::Integer(enabled),
+                ) if matches!(principal_type, Type::Individual) => {
+                    if enabled == 0 {
+                        principal
+                            .data
+                            .retain(|v| !matches!(v, PrincipalData::CalendarOutOfOffice(_)));
+                    } else if !principal
+                        .data
+                        .iter()
+                        .any(|v| matches!(v, PrincipalData::CalendarOutOfOffice(_)))
+                    {
+                        principal
+                            .data
+                            .push(PrincipalData::CalendarOutOfOffice(now()));
+                    }
+                    changed_principals.add_change(principal_id, principal_type, change.field);
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::CollectContacts,
+                    PrincipalValue::Integer(enabled),
+                ) if matches!(principal_type, Type::Individual) => {
+                    if enabled == 0 {
+                        principal
+                            .data
+                            .retain(|v| !matches!(v, PrincipalData::CollectContacts(_)));
+                    } else if !principal
+                        .data
+                        .iter()
+                        .any(|v| matches!(v, PrincipalData::CollectContacts(_)))
+                    {
+                        principal.data.push(PrincipalData::CollectContacts(now()));
+                    }
+                    changed_principals.add_change(principal_id, principal_type, change.field);
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::DomainStatus,
+                    PrincipalValue::String(status),
+                ) if matches!(principal_type, Type::Domain) => {
+                    principal.set_domain_status(parse_domain_status(&status)?);
+                    domain_status_set = true;
+                }
                 (PrincipalAction::Set, PrincipalField::Quota, PrincipalValue::Integer(quota))
                     if matches!(
                         principal_type,
@@ -1219,6 +1423,38 @@ impl ManageDirectory for Store {
                     changed_principals.add_change(principal_id, principal_type, change.field);
                     principal.quota = None;
                 }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::SoftQuota,
+                    PrincipalValue::Integer(soft_quota),
+                ) if matches!(principal_type, Type::Individual | Type::Group) => {
+                    principal
+                        .data
+                        .retain(|v| !matches!(v, PrincipalData::SoftQuota(_)));
+                    if soft_quota != 0 {
+                        principal.data.push(PrincipalData::SoftQuota(soft_quota));
+                    }
+                    changed_principals.add_change(principal_id, principal_type, change.field);
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::MessageQuota,
+                    PrincipalValue::Integer(message_quota),
+                ) if matches!(
+                    principal_type,
+                    Type::Individual | Type::Group | Type::Tenant
+                ) =>
+                {
+                    principal
+                        .data
+                        .retain(|v| !matches!(v, PrincipalData::MessageQuota(_)));
+                    if message_quota != 0 {
+                        principal
+                            .data
+                            .push(PrincipalData::MessageQuota(message_quota));
+                    }
+                    changed_principals.add_change(principal_id, principal_type, change.field);
+                }
                 (
                     PrincipalAction::Set,
                     PrincipalField::Quota,
@@ -1470,19 +1706,16 @@ impl ManageDirectory for Store {
                     PrincipalField::MemberOf | PrincipalField::Lists | PrincipalField::Roles,
                     PrincipalValue::String(member),
                 ) => {
-                    if let Some(member_info) =
-                        self.get_principal_info(&member)
-                            .await
-                            .caused_by(trc::location!())?
-                            .or_else(|| {
-                                change.field.map_internal_role_name(&member).map(|id| {
-                                    PrincipalInfo {
-                                        id,
-                                        typ: Type::Role,
-                                        tenant: None,
-                                    }
-                                })
-                            })
+                    if let Some(member_info) = self
+                        .get_principal_info(&member)
+                        .await
+                        .caused_by(trc::location!())?
+                        .or_else(|| {
+                            change
+                                .field
+                                .map_internal_role_name(&member)
+                                .map(|id| PrincipalInfo::new(id, Type::Role, None))
+                        })
                     {
                         for (pos, member) in member_of.iter().enumerate() {
                             if member.principal_id == member_info.id {
@@ -1788,15 +2021,24 @@ impl ManageDirectory for Store {
                 }
                 (
                     PrincipalAction::Set,
-                    PrincipalField::ExternalMembers,
+                    PrincipalField::ExternalMembers | PrincipalField::AllowedSenders,
                     PrincipalValue::StringList(items),
                 ) => {
-                    principal
-                        .data
-                        .retain(|v| !matches!(v, PrincipalData::ExternalMembers(_)));
+                    principal.data.retain(|v| {
+                        !matches!(
+                            (v, change.field),
+                            (
+                                PrincipalData::ExternalMembers(_),
+                                PrincipalField::ExternalMembers
+                            ) | (
+                                PrincipalData::AllowedSenders(_),
+                                PrincipalField::AllowedSenders
+                            )
+                        )
+                    });
                     if !items.is_empty() {
-                        principal.data.push(PrincipalData::ExternalMembers(
-                            items
+                        if matches!(change.field, PrincipalField::ExternalMembers) {
+                            let items = items
                                 .into_iter()
                                 .map(|item| {
                                     sanitize_email(&item).ok_or_else(|| {
@@ -1811,8 +2053,63 @@ impl ManageDirectory for Store {
                                         )
                                     })
                                 })
-                                .collect::<trc::Result<_>>()?,
-                        ));
+                                .collect::<trc::Result<_>>()?;
+                            principal.data.push(PrincipalData::ExternalMembers(items));
+                        } else {
+                            let mut allowed_senders = Vec::with_capacity(items.len());
+                            for item in items {
+                                allowed_senders
+                                    .push(validate_allowed_sender(self, &item, tenant_id).await?);
+                            }
+                            principal
+                                .data
+                                .push(PrincipalData::AllowedSenders(allowed_senders));
+                        }
+                    }
+                    changed_principals.add_change(principal_id, principal_type, change.field);
+                }
+                (
+                    PrincipalAction::Set,
+                    PrincipalField::SendAs | PrincipalField::SendOnBehalf,
+                    PrincipalValue::StringList(names),
+                ) => {
+                    let mut delegates = Vec::with_capacity(names.len());
+                    for name in names {
+                        let delegate_id = get_delegate_id(self, &name, tenant_id).await?;
+                        if !delegates.contains(&delegate_id) {
+                            delegates.push(delegate_id);
+                        }
+                    }
+                    principal.set_delegates(change.field, delegates);
+                    changed_principals.add_change(principal_id, principal_type, change.field);
+                }
+                (
+                    PrincipalAction::AddItem,
+                    PrincipalField::SendAs | PrincipalField::SendOnBehalf,
+                    PrincipalValue::String(name),
+                ) => {
+                    let delegate_id = get_delegate_id(self, &name, tenant_id).await?;
+                    let mut delegates = principal.delegates(change.field).to_vec();
+                    if !delegates.contains(&delegate_id) {
+                        delegates.push(delegate_id);
+                        principal.set_delegates(change.field, delegates);
+                        changed_principals.add_change(principal_id, principal_type, change.field);
+                    }
+                }
+                (
+                    PrincipalAction::RemoveItem,
+                    PrincipalField::SendAs | PrincipalField::SendOnBehalf,
+                    PrincipalValue::String(name),
+                ) => {
+                    if let Some(delegate) = self
+                        .get_principal_info(&name)
+                        .await
+                        .caused_by(trc::location!())?
+                    {
+                        let mut delegates = principal.delegates(change.field).to_vec();
+                        delegates.retain(|id| *id != delegate.id);
+                        principal.set_delegates(change.field, delegates);
+                        changed_principals.add_change(principal_id, principal_type, change.field);
                     }
                 }
                 (PrincipalAction::Set, PrincipalField::Urls, PrincipalValue::StringList(items)) => {
@@ -1826,10 +2123,12 @@ impl ManageDirectory for Store {
                 }
                 (
                     PrincipalAction::AddItem,
-                    PrincipalField::Urls | PrincipalField::ExternalMembers,
+                    PrincipalField::Urls
+                    | PrincipalField::ExternalMembers
+                    | PrincipalField::AllowedSenders,
                     PrincipalValue::String(mut item),
                 ) => {
-                    if matches!(change.field, PrincipalField::ExternalMembers) {
+                    if change.field == PrincipalField::ExternalMembers {
                         item = sanitize_email(&item).ok_or_else(|| {
                             error(
                                 "Invalid email address",
@@ -1837,6 +2136,8 @@ impl ManageDirectory for Store {
                                     .into(),
                             )
                         })?
+                    } else if change.field == PrincipalField::AllowedSenders {
+                        item = validate_allowed_sender(self, &item, tenant_id).await?;
                     }
 
                     let mut found = false;
@@ -1852,6 +2153,10 @@ impl ManageDirectory for Store {
                             (
                                 PrincipalData::ExternalMembers(emails),
                                 PrincipalField::ExternalMembers,
+                            )
+                            | (
+                                PrincipalData::AllowedSenders(emails),
+                                PrincipalField::AllowedSenders,
                             ) => {
                                 if !emails.contains(&item) {
                                     emails.push(item.clone());
@@ -1871,13 +2176,19 @@ impl ManageDirectory for Store {
                             PrincipalField::ExternalMembers => principal
                                 .data
                                 .push(PrincipalData::ExternalMembers(vec![item])),
+                            PrincipalField::AllowedSenders => principal
+                                .data
+                                .push(PrincipalData::AllowedSenders(vec![item])),
                             _ => {}
                         }
                     }
+                    changed_principals.add_change(principal_id, principal_type, change.field);
                 }
                 (
                     PrincipalAction::RemoveItem,
-                    PrincipalField::Urls | PrincipalField::ExternalMembers,
+                    PrincipalField::Urls
+                    | PrincipalField::ExternalMembers
+                    | PrincipalField::AllowedSenders,
                     PrincipalValue::String(item),
                 ) => {
                     for data in &mut principal.data {
@@ -1889,6 +2200,10 @@ impl ManageDirectory for Store {
                             (
                                 PrincipalData::ExternalMembers(emails),
                                 PrincipalField::ExternalMembers,
+                            )
+                            | (
+                                PrincipalData::AllowedSenders(emails),
+                                PrincipalField::AllowedSenders,
                             ) => {
                                 emails.retain(|v| *v != item);
                                 break;
@@ -1896,6 +2211,7 @@ impl ManageDirectory for Store {
                             _ => {}
                         }
                     }
+                    changed_principals.add_change(principal_id, principal_type, change.field);
                 }
 
                 (_, field, value) => {
@@ -1907,6 +2223,23 @@ impl ManageDirectory for Store {
             }
         }
 
+        // Tenant domains that were renamed or moved have to be verified again
+        if reset_domain_status && !domain_status_set && principal.tenant().is_some() {
+            principal.set_domain_status(DomainStatus::PendingVerification);
+        }
+
+        // Keep the onboarding state of domains in sync with the name mapping
+        if principal_type == Type::Domain && (reset_domain_status || domain_status_set) {
+            batch.set(
+                ValueClass::Directory(DirectoryClass::NameToId(
+                    principal.name().as_bytes().to_vec(),
+                )),
+                PrincipalInfo::new(principal_id, principal_type, principal.tenant())
+                    .with_domain_status(principal.domain_status())
+                    .serialize(),
+            );
+        }
+
         // Validate object size
         if principal.object_size() > 100_000 {
             return Err(error(
@@ -2146,6 +2479,34 @@ impl ManageDirectory for Store {
         Ok(results)
     }
 
+    async fn get_out_of_office_principals(&self) -> trc::Result<RoaringBitmap> {
+        let word = OUT_OF_OFFICE_INDEX.as_bytes();
+        let from_key = ValueKey::from(ValueClass::Directory(DirectoryClass::Index {
+            word: word.to_vec(),
+            principal_id: 0,
+        }));
+        let to_key = ValueKey::from(ValueClass::Directory(DirectoryClass::Index {
+            word: word.to_vec(),
+            principal_id: u32::MAX,
+        }));
+        let mut results = RoaringBitmap::new();
+        self.iterate(
+            IterateParams::new(from_key, to_key).no_values(),
+            |key, _| {
+                let id_pos = key.len() - U32_LEN;
+                if key.get(1..id_pos).is_some_and(|v| v == word) {
+                    results.insert(key.deserialize_be_u32(id_pos)?);
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            },
+        )
+        .await
+        .caused_by(trc::location!())?;
+        Ok(results)
+    }
+
     async fn map_principal(
         &self,
         principal: Principal,
@@ -2239,11 +2600,92 @@ impl ManageDirectory for Store {
                         result.set(PrincipalField::Locale, compact_string);
                     }
                 }
+                PrincipalData::Phone(phone) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::Phone) {
+                        result.set(PrincipalField::Phone, phone);
+                    }
+                }
+                PrincipalData::DomainStatus(status) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::DomainStatus) {
+                        result.set(PrincipalField::DomainStatus, status.as_str());
+                    }
+                }
+                PrincipalData::VerificationToken(token) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::VerificationToken) {
+                        result.set(PrincipalField::VerificationToken, token);
+                    }
+                }
+                PrincipalData::RecoveryEmail(email) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::RecoveryEmail) {
+                        result.set(PrincipalField::RecoveryEmail, email);
+                    }
+                }
+                PrincipalData::SoftQuota(soft_quota) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::SoftQuota) {
+                        result.set(PrincipalField::SoftQuota, soft_quota);
+                    }
+                }
+                PrincipalData::MessageQuota(message_quota) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::MessageQuota) {
+                        result.set(PrincipalField::MessageQuota, message_quota);
+                    }
+                }
+                PrincipalData::LegalHold(since) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::LegalHold) {
+                        result.set(PrincipalField::LegalHold, since);
+                    }
+                }
+                PrincipalData::SpamTrap(since) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::SpamTrap) {
+                        result.set(PrincipalField::SpamTrap, since);
+                    }
+                }
+                PrincipalData::CalendarOutOfOffice(since) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::CalendarOutOfOffice) {
+                        result.set(PrincipalField::CalendarOutOfOffice, since);
+                    }
+                }
+                PrincipalData::CollectContacts(since) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::CollectContacts) {
+                        result.set(PrincipalField::CollectContacts, since);
+                    }
+                }
                 PrincipalData::ExternalMembers(compact_strings) => {
                     if fields.is_empty() || fields.contains(&PrincipalField::ExternalMembers) {
                         result.set(PrincipalField::ExternalMembers, compact_strings);
                     }
                 }
+                PrincipalData::SendAs(items)
+                    if fields.is_empty() || fields.contains(&PrincipalField::SendAs) =>
+                {
+                    for principal_id in items {
+                        if let Some(name) = self
+                            .get_principal_name(principal_id)
+                            .await
+                            .caused_by(trc::location!())?
+                        {
+                            result.append_str(PrincipalField::SendAs, name);
+                        }
+                    }
+                }
+                PrincipalData::SendOnBehalf(items)
+                    if fields.is_empty() || fields.contains(&PrincipalField::SendOnBehalf) =>
+                {
+                    for principal_id in items {
+                        if let Some(name) = self
+                            .get_principal_name(principal_id)
+                            .await
+                            .caused_by(trc::location!())?
+                        {
+                            result.append_str(PrincipalField::SendOnBehalf, name);
+                        }
+                    }
+                }
+                PrincipalData::AllowedSenders(compact_strings) => {
+                    if fields.is_empty() || fields.contains(&PrincipalField::AllowedSenders) {
+                        result.set(PrincipalField::AllowedSenders, compact_strings);
+                    }
+                }
                 PrincipalData::Urls(compact_strings) => {
                     if fields.is_empty() || fields.contains(&PrincipalField::Urls) {
                         result.set(PrincipalField::Urls, compact_strings);
@@ -2485,6 +2927,88 @@ impl<'x> UpdatePrincipal<'x> {
     }
 }
 
+fn validate_recovery_email(email: String) -> trc::Result<String> {
+    sanitize_email(&email).ok_or_else(|| {
+        error(
+            "Invalid email address",
+            format!("Invalid value {email:?} for recoveryEmail").into(),
+        )
+    })
+}
+
+fn parse_domain_status(status: &str) -> trc::Result<DomainStatus> {
+    DomainStatus::parse(status).ok_or_else(|| {
+        error(
+            "Invalid domain status",
+            format!("Invalid value {status:?} for domainStatus").into(),
+        )
+    })
+}
+
+// Pictures are either links or avatars uploaded as a base64 data URI
+fn validate_picture(picture: String) -> trc::Result<String> {
+    let Some(data_uri) = picture.strip_prefix("data:") else {
+        return Ok(picture);
+    };
+
+    if picture.len() <= MAX_AVATAR_LEN
+        && data_uri
+            .split_once(";base64,")
+            .is_some_and(|(content_type, _)| AVATAR_CONTENT_TYPES.contains(&content_type))
+    {
+        Ok(picture)
+    } else {
+        Err(error(
+            "Invalid picture",
+            Some("Avatars must be PNG, JPEG, GIF or WebP images encoded as a base64 data URI"),
+        ))
+    }
+}
+
+// Only individual and group accounts can be delegated
+async fn get_delegate_id(store: &Store, name: &str, tenant_id: Option<u32>) -> trc::Result<u32> {
+    store
+        .get_principal_info(name)
+        .await
+        .caused_by(trc::location!())?
+        .filter(|v| {
+            matches!(v.typ, Type::Individual | Type::Group) && v.has_tenant_access(tenant_id)
+        })
+        .map(|v| v.id)
+        .ok_or_else(|| not_found(name.to_string()))
+}
+
+// Allowed senders must belong to a domain managed by the principal's tenant
+async fn validate_allowed_sender(
+    store: &Store,
+    sender: &str,
+    tenant_id: Option<u32>,
+) -> trc::Result<String> {
+    let sender = sanitize_email(sender).ok_or_else(|| {
+        error(
+            "Invalid email address",
+            format!(
+                "Invalid value {:?} for {}",
+                sender,
+                PrincipalField::AllowedSenders.as_str()
+            )
+            .into(),
+        )
+    })?;
+    let domain = sender
+        .rsplit_once('@')
+        .map(|(_, domain)| domain)
+        .unwrap_or_default();
+    store
+        .get_principal_info(domain)
+        .await
+        .caused_by(trc::location!())?
+        .filter(|v| v.typ == Type::Domain && v.has_tenant_access(tenant_id))
+        .ok_or_else(|| not_found(domain.to_string()))?;
+
+    Ok(sender)
+}
+
 fn validate_member_of(
     field: PrincipalField,
     typ: Type,
@@ -2539,6 +3063,15 @@ impl ChangedPrincipals {
                 Type::Individual | Type::Group,
                 PrincipalField::Name
                     | PrincipalField::Quota
+                    | PrincipalField::SoftQuota
+                    | PrincipalField::MessageQuota
+                    | PrincipalField::LegalHold
+                    | PrincipalField::SpamTrap
+                    | PrincipalField::CalendarOutOfOffice
+                    | PrincipalField::CollectContacts
+                    | PrincipalField::AllowedSenders
+                    | PrincipalField::SendAs
+                    | PrincipalField::SendOnBehalf
                     | PrincipalField::Secrets
                     | PrincipalField::Emails
                     | PrincipalField::MemberOf
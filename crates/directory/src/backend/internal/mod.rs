@@ -7,7 +7,7 @@
 pub mod lookup;
 pub mod manage;
 
-use crate::Type;
+use crate::{DomainStatus, Type};
 use ahash::AHashMap;
 
 use std::fmt::Display;
@@ -18,8 +18,14 @@ pub struct PrincipalInfo {
     pub id: u32,
     pub typ: Type,
     pub tenant: Option<u32>,
+    // Domains that have not completed onboarding, stored so that
+    // local domain lookups do not need to fetch the principal
+    pub inactive: bool,
 }
 
+// Flag stored in the type byte of inactive domains
+const INACTIVE_DOMAIN: u8 = 0x80;
+
 impl PrincipalInfo {
     // SPDX-SnippetBegin
     // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
@@ -41,16 +47,21 @@ impl PrincipalInfo {
 
 impl SerializeInfallible for PrincipalInfo {
     fn serialize(&self) -> Vec<u8> {
+        let typ = if self.inactive {
+            self.typ as u8 | INACTIVE_DOMAIN
+        } else {
+            self.typ as u8
+        };
         if let Some(tenant) = self.tenant {
             KeySerializer::new((U32_LEN * 2) + 1)
                 .write_leb128(self.id)
-                .write(self.typ as u8)
+                .write(typ)
                 .write_leb128(tenant)
                 .finalize()
         } else {
             KeySerializer::new(U32_LEN + 1)
                 .write_leb128(self.id)
-                .write(self.typ as u8)
+                .write(typ)
                 .finalize()
         }
     }
@@ -59,18 +70,21 @@ impl SerializeInfallible for PrincipalInfo {
 impl Deserialize for PrincipalInfo {
     fn deserialize(bytes_: &[u8]) -> trc::Result<Self> {
         let mut bytes = bytes_.iter();
+        let id = bytes.next_leb128().ok_or_else(|| {
+            trc::StoreEvent::DataCorruption
+                .caused_by(trc::location!())
+                .ctx(trc::Key::Value, bytes_)
+        })?;
+        let typ = *bytes.next().ok_or_else(|| {
+            trc::StoreEvent::DataCorruption
+                .caused_by(trc::location!())
+                .ctx(trc::Key::Value, bytes_)
+        })?;
         Ok(PrincipalInfo {
-            id: bytes.next_leb128().ok_or_else(|| {
-                trc::StoreEvent::DataCorruption
-                    .caused_by(trc::location!())
-                    .ctx(trc::Key::Value, bytes_)
-            })?,
-            typ: Type::from_u8(*bytes.next().ok_or_else(|| {
-                trc::StoreEvent::DataCorruption
-                    .caused_by(trc::location!())
-                    .ctx(trc::Key::Value, bytes_)
-            })?),
+            id,
+            typ: Type::from_u8(typ & !INACTIVE_DOMAIN),
             tenant: bytes.next_leb128(),
+            inactive: typ & INACTIVE_DOMAIN != 0,
         })
     }
 }
@@ -81,8 +95,14 @@ impl PrincipalInfo {
             id: principal_id,
             typ,
             tenant,
+            inactive: false,
         }
     }
+
+    pub fn with_domain_status(mut self, status: DomainStatus) -> Self {
+        self.inactive = self.typ == Type::Domain && !status.is_active();
+        self
+    }
 }
 
 #[derive(
@@ -108,6 +128,19 @@ pub enum PrincipalField {
     Urls,
     ExternalMembers,
     Locale,
+    LegalHold,
+    RecoveryEmail,
+    SpamTrap,
+    CalendarOutOfOffice,
+    CollectContacts,
+    Phone,
+    DomainStatus,
+    VerificationToken,
+    AllowedSenders,
+    SendAs,
+    SendOnBehalf,
+    SoftQuota,
+    MessageQuota,
 }
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
@@ -196,6 +229,19 @@ impl PrincipalField {
             PrincipalField::Urls => 15,
             PrincipalField::ExternalMembers => 16,
             PrincipalField::Locale => 17,
+            PrincipalField::LegalHold => 18,
+            PrincipalField::RecoveryEmail => 19,
+            PrincipalField::SpamTrap => 20,
+            PrincipalField::CalendarOutOfOffice => 21,
+            PrincipalField::CollectContacts => 22,
+            PrincipalField::Phone => 23,
+            PrincipalField::DomainStatus => 24,
+            PrincipalField::VerificationToken => 25,
+            PrincipalField::AllowedSenders => 26,
+            PrincipalField::SendAs => 27,
+            PrincipalField::SendOnBehalf => 28,
+            PrincipalField::SoftQuota => 29,
+            PrincipalField::MessageQuota => 30,
         }
     }
 
@@ -219,6 +265,19 @@ impl PrincipalField {
             15 => Some(PrincipalField::Urls),
             16 => Some(PrincipalField::ExternalMembers),
             17 => Some(PrincipalField::Locale),
+            18 => Some(PrincipalField::LegalHold),
+            19 => Some(PrincipalField::RecoveryEmail),
+            20 => Some(PrincipalField::SpamTrap),
+            21 => Some(PrincipalField::CalendarOutOfOffice),
+            22 => Some(PrincipalField::CollectContacts),
+            23 => Some(PrincipalField::Phone),
+            24 => Some(PrincipalField::DomainStatus),
+            25 => Some(PrincipalField::VerificationToken),
+            26 => Some(PrincipalField::AllowedSenders),
+            27 => Some(PrincipalField::SendAs),
+            28 => Some(PrincipalField::SendOnBehalf),
+            29 => Some(PrincipalField::SoftQuota),
+            30 => Some(PrincipalField::MessageQuota),
             _ => None,
         }
     }
@@ -243,6 +302,19 @@ impl PrincipalField {
             PrincipalField::Urls => "urls",
             PrincipalField::ExternalMembers => "externalMembers",
             PrincipalField::Locale => "locale",
+            PrincipalField::LegalHold => "legalHold",
+            PrincipalField::RecoveryEmail => "recoveryEmail",
+            PrincipalField::SpamTrap => "spamTrap",
+            PrincipalField::CalendarOutOfOffice => "calendarOutOfOffice",
+            PrincipalField::CollectContacts => "collectContacts",
+            PrincipalField::Phone => "phone",
+            PrincipalField::DomainStatus => "domainStatus",
+            PrincipalField::VerificationToken => "verificationToken",
+            PrincipalField::AllowedSenders => "allowedSenders",
+            PrincipalField::SendAs => "sendAs",
+            PrincipalField::SendOnBehalf => "sendOnBehalf",
+            PrincipalField::SoftQuota => "softQuota",
+            PrincipalField::MessageQuota => "messageQuota",
         }
     }
 
@@ -266,6 +338,19 @@ impl PrincipalField {
             "urls" => Some(PrincipalField::Urls),
             "externalMembers" => Some(PrincipalField::ExternalMembers),
             "locale" => Some(PrincipalField::Locale),
+            "legalHold" => Some(PrincipalField::LegalHold),
+            "recoveryEmail" => Some(PrincipalField::RecoveryEmail),
+            "spamTrap" => Some(PrincipalField::SpamTrap),
+            "calendarOutOfOffice" => Some(PrincipalField::CalendarOutOfOffice),
+            "collectContacts" => Some(PrincipalField::CollectContacts),
+            "phone" => Some(PrincipalField::Phone),
+            "domainStatus" => Some(PrincipalField::DomainStatus),
+            "verificationToken" => Some(PrincipalField::VerificationToken),
+            "allowedSenders" => Some(PrincipalField::AllowedSenders),
+            "sendAs" => Some(PrincipalField::SendAs),
+            "sendOnBehalf" => Some(PrincipalField::SendOnBehalf),
+            "softQuota" => Some(PrincipalField::SoftQuota),
+            "messageQuota" => Some(PrincipalField::MessageQuota),
             _ => None,
         }
     }
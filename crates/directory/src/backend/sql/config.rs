@@ -57,6 +57,14 @@ impl SqlDirectory {
             ("emails", &mut mappings.query_emails),
             ("recipients", &mut mappings.query_recipients),
             ("secrets", &mut mappings.query_secrets),
+            ("insert", &mut mappings.query_insert),
+            ("update", &mut mappings.query_update),
+            ("rename", &mut mappings.query_rename),
+            ("delete", &mut mappings.query_delete),
+            ("insert-email", &mut mappings.query_insert_email),
+            ("delete-emails", &mut mappings.query_delete_emails),
+            ("insert-member", &mut mappings.query_insert_member),
+            ("delete-members", &mut mappings.query_delete_members),
         ] {
             *query = config
                 .value(("store", store_id.as_str(), "query", query_id))
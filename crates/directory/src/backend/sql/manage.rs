@@ -0,0 +1,346 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::SqlDirectory;
+use crate::{
+    MemberOf, Principal, QueryParams, ROLE_ADMIN, ROLE_TENANT_ADMIN, ROLE_USER, Type,
+    backend::internal::{
+        PrincipalField, PrincipalUpdate, PrincipalValue, SpecialSecrets,
+        lookup::DirectoryStore,
+        manage::{self, ChangedPrincipals, ManageDirectory, UpdatePrincipal},
+    },
+};
+use store::Value;
+use trc::AddContext;
+
+// Snapshot of a principal taken before an update, used to revert the internal
+// directory when the SQL directory rejects the change
+pub struct SqlPrincipalState {
+    principal: Principal,
+    member_of: Vec<MemberOf>,
+    members: Vec<u32>,
+}
+
+impl SqlDirectory {
+    pub fn supports_writes(&self) -> bool {
+        !self.mappings.query_insert.is_empty()
+    }
+
+    // Only accounts and groups are provisioned into the SQL schema, other
+    // principals such as domains or roles are kept in the internal directory
+    pub fn is_managed_type(&self, typ: Type) -> bool {
+        self.supports_writes() && matches!(typ, Type::Individual | Type::Group)
+    }
+
+    pub async fn store_principal(
+        &self,
+        principal_id: u32,
+        previous_name: Option<&str>,
+    ) -> trc::Result<()> {
+        let principal = self
+            .data_store
+            .query(QueryParams::id(principal_id).with_return_member_of(true))
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| manage::not_found(principal_id).caused_by(trc::location!()))?;
+        if !self.is_managed_type(principal.typ()) {
+            return Ok(());
+        }
+
+        let name = principal.name();
+        let typ = if principal.typ() == Type::Group {
+            "group"
+        } else if principal.roles().contains(&ROLE_ADMIN) {
+            "admin"
+        } else {
+            "individual"
+        };
+        let secret = principal
+            .secrets
+            .iter()
+            .find(|secret| secret.is_password())
+            .map(|secret| Value::from(secret.as_str()))
+            .unwrap_or(Value::Null);
+        let description = principal
+            .description()
+            .map(Value::from)
+            .unwrap_or(Value::Null);
+        let quota = Value::from(principal.quota());
+
+        match previous_name {
+            None => {
+                self.execute(
+                    &self.mappings.query_insert,
+                    vec![name.into(), typ.into(), secret, description, quota],
+                )
+                .await?;
+            }
+            Some(previous_name) => {
+                if previous_name != name {
+                    // Memberships and addresses are reinserted below under the new name
+                    self.execute(
+                        &self.mappings.query_delete_emails,
+                        vec![previous_name.into()],
+                    )
+                    .await?;
+                    self.execute(
+                        &self.mappings.query_delete_members,
+                        vec![previous_name.into()],
+                    )
+                    .await?;
+                    self.execute(
+                        &self.mappings.query_rename,
+                        vec![name.into(), previous_name.into()],
+                    )
+                    .await?;
+                }
+                self.execute(
+                    &self.mappings.query_update,
+                    vec![typ.into(), secret, description, quota, name.into()],
+                )
+                .await?;
+            }
+        }
+
+        // Replace email addresses
+        if !self.mappings.query_insert_email.is_empty() {
+            self.execute(&self.mappings.query_delete_emails, vec![name.into()])
+                .await?;
+            for email in &principal.emails {
+                self.execute(
+                    &self.mappings.query_insert_email,
+                    vec![name.into(), email.into()],
+                )
+                .await?;
+            }
+        }
+
+        // Replace group memberships
+        if !self.mappings.query_insert_member.is_empty() {
+            self.execute(&self.mappings.query_delete_members, vec![name.into()])
+                .await?;
+            for member_of in principal.member_of() {
+                if let Some(group) = self
+                    .data_store
+                    .get_principal_name(*member_of)
+                    .await
+                    .caused_by(trc::location!())?
+                {
+                    self.execute(
+                        &self.mappings.query_insert_member,
+                        vec![name.into(), group.into()],
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Stores a principal along with any other principals affected by the same change
+    pub async fn store_principals(
+        &self,
+        principal_id: u32,
+        previous_name: &str,
+        changed_principals: &ChangedPrincipals,
+    ) -> trc::Result<()> {
+        self.store_principal(principal_id, Some(previous_name))
+            .await?;
+        for (changed_id, changed) in changed_principals.iter() {
+            if *changed_id != principal_id
+                && self.is_managed_type(changed.typ)
+                && let Some(name) = self
+                    .data_store
+                    .get_principal_name(*changed_id)
+                    .await
+                    .caused_by(trc::location!())?
+            {
+                self.store_principal(*changed_id, Some(&name)).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn principal_state(&self, principal_id: u32) -> trc::Result<SqlPrincipalState> {
+        Ok(SqlPrincipalState {
+            principal: self
+                .data_store
+                .query(QueryParams::id(principal_id).with_return_member_of(false))
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| manage::not_found(principal_id).caused_by(trc::location!()))?,
+            member_of: self
+                .data_store
+                .get_member_of(principal_id)
+                .await
+                .caused_by(trc::location!())?,
+            members: self
+                .data_store
+                .get_members(principal_id)
+                .await
+                .caused_by(trc::location!())?,
+        })
+    }
+
+    // Restores the fields mirrored into the SQL directory to their previous values
+    // and writes them back to both directories
+    pub async fn revert_principal(
+        &self,
+        previous: SqlPrincipalState,
+    ) -> trc::Result<ChangedPrincipals> {
+        let principal_id = previous.principal.id;
+        let current = self.principal_state(principal_id).await?;
+        let mut changes = Vec::new();
+
+        if previous.principal.name != current.principal.name {
+            changes.push(PrincipalUpdate::set(
+                PrincipalField::Name,
+                PrincipalValue::String(previous.principal.name.clone()),
+            ));
+        }
+        if previous.principal.description != current.principal.description {
+            changes.push(PrincipalUpdate::set(
+                PrincipalField::Description,
+                PrincipalValue::String(previous.principal.description.unwrap_or_default()),
+            ));
+        }
+        if previous.principal.quota != current.principal.quota {
+            changes.push(PrincipalUpdate::set(
+                PrincipalField::Quota,
+                previous
+                    .principal
+                    .quota
+                    .map(PrincipalValue::Integer)
+                    .unwrap_or_else(|| PrincipalValue::String(String::new())),
+            ));
+        }
+        if previous.principal.secrets != current.principal.secrets {
+            changes.push(PrincipalUpdate::set(
+                PrincipalField::Secrets,
+                PrincipalValue::StringList(previous.principal.secrets),
+            ));
+        }
+        if previous.principal.emails != current.principal.emails {
+            changes.push(PrincipalUpdate::set(
+                PrincipalField::Emails,
+                PrincipalValue::StringList(previous.principal.emails),
+            ));
+        }
+
+        // Memberships are restored item by item, a set would also replace lists and roles
+        for member in &previous.member_of {
+            if !current
+                .member_of
+                .iter()
+                .any(|m| m.principal_id == member.principal_id)
+                && let Some((field, name)) = self.member_of_name(member).await?
+            {
+                changes.push(PrincipalUpdate::add_item(
+                    field,
+                    PrincipalValue::String(name),
+                ));
+            }
+        }
+        for member in &current.member_of {
+            if !previous
+                .member_of
+                .iter()
+                .any(|m| m.principal_id == member.principal_id)
+                && let Some((field, name)) = self.member_of_name(member).await?
+            {
+                changes.push(PrincipalUpdate::remove_item(
+                    field,
+                    PrincipalValue::String(name),
+                ));
+            }
+        }
+        for (members, other, add) in [
+            (&previous.members, &current.members, true),
+            (&current.members, &previous.members, false),
+        ] {
+            for member_id in members {
+                if !other.contains(member_id)
+                    && let Some(name) = self
+                        .data_store
+                        .get_principal_name(*member_id)
+                        .await
+                        .caused_by(trc::location!())?
+                {
+                    let value = PrincipalValue::String(name);
+                    changes.push(if add {
+                        PrincipalUpdate::add_item(PrincipalField::Members, value)
+                    } else {
+                        PrincipalUpdate::remove_item(PrincipalField::Members, value)
+                    });
+                }
+            }
+        }
+
+        let changed_principals = if !changes.is_empty() {
+            self.data_store
+                .update_principal(UpdatePrincipal::by_id(principal_id).with_updates(changes))
+                .await
+                .caused_by(trc::location!())?
+        } else {
+            ChangedPrincipals::default()
+        };
+
+        // Rows may have been partially written under either name
+        self.store_principals(principal_id, current.principal.name(), &changed_principals)
+            .await?;
+
+        Ok(changed_principals)
+    }
+
+    async fn member_of_name(
+        &self,
+        member: &MemberOf,
+    ) -> trc::Result<Option<(PrincipalField, String)>> {
+        let field = match member.typ {
+            Type::Role => PrincipalField::Roles,
+            Type::List => PrincipalField::Lists,
+            _ => PrincipalField::MemberOf,
+        };
+        let name = match member.principal_id {
+            ROLE_ADMIN => "admin".to_string(),
+            ROLE_TENANT_ADMIN => "tenant-admin".to_string(),
+            ROLE_USER => "user".to_string(),
+            principal_id => match self
+                .data_store
+                .get_principal_name(principal_id)
+                .await
+                .caused_by(trc::location!())?
+            {
+                Some(name) => name,
+                None => return Ok(None),
+            },
+        };
+
+        Ok(Some((field, name)))
+    }
+
+    pub async fn remove_principal(&self, name: &str) -> trc::Result<()> {
+        self.execute(&self.mappings.query_delete_emails, vec![name.into()])
+            .await?;
+        self.execute(&self.mappings.query_delete_members, vec![name.into()])
+            .await?;
+        self.execute(&self.mappings.query_delete, vec![name.into()])
+            .await
+    }
+
+    async fn execute(&self, query: &str, params: Vec<Value<'_>>) -> trc::Result<()> {
+        if !query.is_empty() {
+            self.sql_store
+                .sql_query::<usize>(query, params)
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+}
@@ -8,6 +8,7 @@ use store::Store;
 
 pub mod config;
 pub mod lookup;
+pub mod manage;
 
 pub struct SqlDirectory {
     sql_store: Store,
@@ -22,6 +23,14 @@ pub(crate) struct SqlMappings {
     query_emails: String,
     query_recipients: String,
     query_secrets: String,
+    query_insert: String,
+    query_update: String,
+    query_rename: String,
+    query_delete: String,
+    query_insert_email: String,
+    query_delete_emails: String,
+    query_insert_member: String,
+    query_delete_members: String,
     column_description: String,
     column_secret: String,
     column_email: String,
@@ -64,4 +64,8 @@ impl CachedDirectory {
             if exists { self.ttl_pos } else { self.ttl_neg },
         );
     }
+
+    pub fn remove_domain(&self, domain: &str) {
+        self.cached_domains.remove(domain);
+    }
 }
@@ -17,8 +17,8 @@ use ahash::AHashMap;
 use crate::{
     Directories, Directory, DirectoryInner,
     backend::{
-        imap::ImapDirectory, ldap::LdapDirectory, memory::MemoryDirectory, oidc::OpenIdDirectory,
-        smtp::SmtpDirectory, sql::SqlDirectory,
+        http::HttpDirectory, imap::ImapDirectory, ldap::LdapDirectory, memory::MemoryDirectory,
+        oidc::OpenIdDirectory, smtp::SmtpDirectory, sql::SqlDirectory,
     },
 };
 
@@ -82,6 +82,8 @@ impl Directories {
                     .map(DirectoryInner::Memory),
                 "oidc" => OpenIdDirectory::from_config(config, prefix, data_store.clone())
                     .map(DirectoryInner::OpenId),
+                "http" => HttpDirectory::from_config(config, prefix, data_store.clone())
+                    .map(DirectoryInner::Http),
                 unknown => {
                     let err = format!("Unknown directory type: {unknown:?}");
                     config.new_parse_error(("directory", id, "type"), err);
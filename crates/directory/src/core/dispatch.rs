@@ -5,6 +5,7 @@
  */
 
 use trc::AddContext;
+use utils::{normalize_address, normalize_domain};
 
 use crate::{
     Directory, DirectoryInner, Principal, QueryParams,
@@ -21,11 +22,14 @@ impl Directory {
             DirectoryInner::Smtp(store) => store.query(by.by).await,
             DirectoryInner::Memory(store) => store.query(by).await,
             DirectoryInner::OpenId(store) => store.query(by).await,
+            DirectoryInner::Http(store) => store.query(by).await,
         }
         .caused_by(trc::location!())
     }
 
     pub async fn email_to_id(&self, address: &str) -> trc::Result<Option<u32>> {
+        let address = normalize_address(address);
+        let address = address.as_ref();
         match &self.store {
             DirectoryInner::Internal(store) => store.email_to_id(address).await,
             DirectoryInner::Ldap(store) => store.email_to_id(address).await,
@@ -34,11 +38,16 @@ impl Directory {
             DirectoryInner::Smtp(store) => store.email_to_id(address).await,
             DirectoryInner::Memory(store) => store.email_to_id(address).await,
             DirectoryInner::OpenId(store) => store.email_to_id(address).await,
+            DirectoryInner::Http(store) => store.email_to_id(address).await,
         }
         .caused_by(trc::location!())
     }
 
     pub async fn is_local_domain(&self, domain: &str) -> trc::Result<bool> {
+        // IDNs are looked up by their ASCII form
+        let domain = normalize_domain(domain);
+        let domain = domain.as_ref();
+
         // Check cache
         if let Some(cache) = &self.cache
             && let Some(result) = cache.get_domain(domain)
@@ -54,6 +63,7 @@ impl Directory {
             DirectoryInner::Smtp(store) => store.is_local_domain(domain).await,
             DirectoryInner::Memory(store) => store.is_local_domain(domain).await,
             DirectoryInner::OpenId(store) => store.is_local_domain(domain).await,
+            DirectoryInner::Http(store) => store.is_local_domain(domain).await,
         }
         .caused_by(trc::location!())?;
 
@@ -65,7 +75,16 @@ impl Directory {
         Ok(result)
     }
 
+    pub fn invalidate_domain(&self, domain: &str) {
+        if let Some(cache) = &self.cache {
+            cache.remove_domain(normalize_domain(domain).as_ref());
+        }
+    }
+
     pub async fn rcpt(&self, email: &str) -> trc::Result<RcptType> {
+        let email = normalize_address(email);
+        let email = email.as_ref();
+
         // Check cache
         if let Some(cache) = &self.cache
             && let Some(result) = cache.get_rcpt(email)
@@ -81,6 +100,7 @@ impl Directory {
             DirectoryInner::Smtp(store) => store.rcpt(email).await,
             DirectoryInner::Memory(store) => store.rcpt(email).await,
             DirectoryInner::OpenId(store) => store.rcpt(email).await,
+            DirectoryInner::Http(store) => store.rcpt(email).await,
         }
         .caused_by(trc::location!())?;
 
@@ -101,11 +121,14 @@ impl Directory {
             DirectoryInner::Smtp(store) => store.vrfy(address).await,
             DirectoryInner::Memory(store) => store.vrfy(address).await,
             DirectoryInner::OpenId(store) => store.vrfy(address).await,
+            DirectoryInner::Http(store) => store.vrfy(address).await,
         }
         .caused_by(trc::location!())
     }
 
     pub async fn expn(&self, address: &str) -> trc::Result<Vec<String>> {
+        let address = normalize_address(address);
+        let address = address.as_ref();
         match &self.store {
             DirectoryInner::Internal(store) => store.expn(address).await,
             DirectoryInner::Ldap(store) => store.expn(address).await,
@@ -114,6 +137,7 @@ impl Directory {
             DirectoryInner::Smtp(store) => store.expn(address).await,
             DirectoryInner::Memory(store) => store.expn(address).await,
             DirectoryInner::OpenId(store) => store.expn(address).await,
+            DirectoryInner::Http(store) => store.expn(address).await,
         }
         .caused_by(trc::location!())
     }
@@ -125,7 +149,8 @@ impl Directory {
             | DirectoryInner::Sql(_)
             | DirectoryInner::Imap(_)
             | DirectoryInner::Smtp(_)
-            | DirectoryInner::Memory(_) => false,
+            | DirectoryInner::Memory(_)
+            | DirectoryInner::Http(_) => false,
             DirectoryInner::OpenId(_) => true,
         }
     }
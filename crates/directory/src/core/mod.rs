@@ -251,6 +251,27 @@ impl Permission {
             Permission::CalendarSchedulingReceive => {
                 "Receive calendar scheduling requests via e-mail"
             }
+            Permission::MessageRedact => "Redact messages across all accounts",
+            Permission::MessageImport => "Import messages from remote IMAP servers",
+            Permission::ConnectionsView => "View open connections by account and IP address",
+            Permission::SessionsList => "List live protocol sessions",
+            Permission::SessionsTerminate => "Terminate live protocol sessions",
+            Permission::LockoutView => "View authentication lockouts of accounts",
+            Permission::LockoutClear => "Clear authentication lockouts of accounts",
+            Permission::JmapAddressBookGet => "Retrieve address books via JMAP",
+            Permission::JmapAddressBookChanges => "Track changes to address books via JMAP",
+            Permission::JmapContactCardGet => "Retrieve contact cards via JMAP",
+            Permission::JmapContactCardChanges => "Track changes to contact cards via JMAP",
+            Permission::JmapContactCardQuery => "Perform contact card queries via JMAP",
+            Permission::JmapContactCardQueryChanges => "Track contact card query changes via JMAP",
+            Permission::ManageAvatar => "Manage the account avatar",
+            Permission::ManageSavedSearches => "Manage saved searches",
+            Permission::EmailRecall => "Recall sent messages from local recipients",
+            Permission::ManageReadReceipts => "Manage read receipt preferences",
+            Permission::ManageForwarding => "Forward incoming messages to other addresses",
+            Permission::ManageDisposableAliases => "Create and manage disposable email aliases",
+            Permission::JmapAutocryptKeyGet => "Retrieve Autocrypt keys via JMAP",
+            Permission::MaildirImport => "Import messages from Maildir folders on the server",
         }
     }
 }
@@ -5,8 +5,8 @@
  */
 
 use crate::{
-    ArchivedPrincipal, FALLBACK_ADMIN_ID, Permission, PermissionGrant, Principal, PrincipalData,
-    ROLE_ADMIN, Type,
+    ArchivedPrincipal, ArchivedPrincipalData, DomainStatus, FALLBACK_ADMIN_ID, Permission,
+    PermissionGrant, Principal, PrincipalData, ROLE_ADMIN, Type,
     backend::internal::{PrincipalField, PrincipalSet, PrincipalUpdate, PrincipalValue},
 };
 use ahash::AHashSet;
@@ -20,6 +20,7 @@ use std::{collections::hash_map::Entry, fmt, str::FromStr};
 use store::{
     U32_LEN, U64_LEN,
     backend::MAX_TOKEN_LENGTH,
+    rand::{Rng, distr::Alphanumeric, rng},
     write::{BatchBuilder, DirectoryClass},
 };
 
@@ -54,6 +55,16 @@ impl Principal {
         self.quota.unwrap_or_default()
     }
 
+    pub fn message_quota(&self) -> Option<u64> {
+        self.data.iter().find_map(|d| {
+            if let PrincipalData::MessageQuota(quota) = d {
+                Some(*quota)
+            } else {
+                None
+            }
+        })
+    }
+
     pub fn principal_quota(&self, typ: &Type) -> Option<u64> {
         self.data
             .iter()
@@ -152,6 +163,39 @@ impl Principal {
             .unwrap_or_default()
     }
 
+    // Principals this account can send as or on behalf of
+    pub fn delegates(&self, field: PrincipalField) -> &[u32] {
+        self.data
+            .iter()
+            .find_map(|item| match (item, field) {
+                (PrincipalData::SendAs(items), PrincipalField::SendAs)
+                | (PrincipalData::SendOnBehalf(items), PrincipalField::SendOnBehalf) => {
+                    Some(items.as_slice())
+                }
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn set_delegates(&mut self, field: PrincipalField, delegates: Vec<u32>) {
+        self.data.retain(|item| {
+            !matches!(
+                (item, field),
+                (PrincipalData::SendAs(_), PrincipalField::SendAs)
+                    | (PrincipalData::SendOnBehalf(_), PrincipalField::SendOnBehalf)
+            )
+        });
+        if !delegates.is_empty() {
+            match field {
+                PrincipalField::SendAs => self.data.push(PrincipalData::SendAs(delegates)),
+                PrincipalField::SendOnBehalf => {
+                    self.data.push(PrincipalData::SendOnBehalf(delegates))
+                }
+                _ => {}
+            }
+        }
+    }
+
     pub fn roles_mut(&mut self) -> Option<&mut Vec<u32>> {
         self.data.iter_mut().find_map(|item| {
             if let PrincipalData::Roles(items) = item {
@@ -185,6 +229,73 @@ impl Principal {
         })
     }
 
+    pub fn recovery_email(&self) -> Option<&str> {
+        self.data.iter().find_map(|item| {
+            if let PrincipalData::RecoveryEmail(email) = item {
+                Some(email.as_str())
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn phone(&self) -> Option<&str> {
+        self.data.iter().find_map(|item| {
+            if let PrincipalData::Phone(phone) = item {
+                Some(phone.as_str())
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn domain_status(&self) -> DomainStatus {
+        self.data
+            .iter()
+            .find_map(|item| {
+                if let PrincipalData::DomainStatus(status) = item {
+                    Some(*status)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn verification_token(&self) -> Option<&str> {
+        self.data.iter().find_map(|item| {
+            if let PrincipalData::VerificationToken(token) = item {
+                Some(token.as_str())
+            } else {
+                None
+            }
+        })
+    }
+
+    // Pending domains are assigned a token to be published in DNS, it is discarded on activation
+    pub fn set_domain_status(&mut self, status: DomainStatus) {
+        self.data
+            .retain(|item| !matches!(item, PrincipalData::DomainStatus(_)));
+        match status {
+            DomainStatus::PendingVerification | DomainStatus::Verified => {
+                if self.verification_token().is_none() {
+                    self.data.push(PrincipalData::VerificationToken(
+                        rng()
+                            .sample_iter(Alphanumeric)
+                            .take(32)
+                            .map(|ch| char::from(ch.to_ascii_lowercase()))
+                            .collect(),
+                    ));
+                }
+                self.data.push(PrincipalData::DomainStatus(status));
+            }
+            DomainStatus::Active => {
+                self.data
+                    .retain(|item| !matches!(item, PrincipalData::VerificationToken(_)));
+            }
+        }
+    }
+
     pub fn picture_mut(&mut self) -> Option<&mut String> {
         self.data.iter_mut().find_map(|item| {
             if let PrincipalData::Picture(picture) = item {
@@ -341,13 +452,28 @@ impl Principal {
                 .map(|d| match d {
                     PrincipalData::MemberOf(items)
                     | PrincipalData::Roles(items)
-                    | PrincipalData::Lists(items) => items.len() * U32_LEN,
+                    | PrincipalData::Lists(items)
+                    | PrincipalData::SendAs(items)
+                    | PrincipalData::SendOnBehalf(items) => items.len() * U32_LEN,
                     PrincipalData::Permissions(items) => items.len() * U32_LEN,
-                    PrincipalData::ExternalMembers(items) | PrincipalData::Urls(items) => {
+                    PrincipalData::ExternalMembers(items)
+                    | PrincipalData::Urls(items)
+                    | PrincipalData::AllowedSenders(items) => {
                         items.iter().map(|s| s.len()).sum::<usize>()
                     }
                     PrincipalData::PrincipalQuota(items) => items.len() * U32_LEN,
-                    PrincipalData::Picture(value) | PrincipalData::Locale(value) => value.len(),
+                    PrincipalData::Picture(value)
+                    | PrincipalData::Locale(value)
+                    | PrincipalData::RecoveryEmail(value)
+                    | PrincipalData::Phone(value)
+                    | PrincipalData::VerificationToken(value) => value.len(),
+                    PrincipalData::LegalHold(_)
+                    | PrincipalData::SpamTrap(_)
+                    | PrincipalData::CalendarOutOfOffice(_)
+                    | PrincipalData::CollectContacts(_)
+                    | PrincipalData::SoftQuota(_)
+                    | PrincipalData::MessageQuota(_) => U64_LEN,
+                    PrincipalData::DomainStatus(_) => 1,
                 })
                 .sum::<usize>()
     }
@@ -861,6 +987,10 @@ impl From<Vec<u32>> for PrincipalValue {
     }
 }
 
+// Principals that opted into calendar based out of office responses are indexed under a
+// reserved word that the tokenizer never produces
+pub const OUT_OF_OFFICE_INDEX: &str = "\0out-of-office";
+
 pub(crate) fn build_search_index(
     batch: &mut BatchBuilder,
     principal_id: u32,
@@ -878,6 +1008,13 @@ pub(crate) fn build_search_index(
         {
             current_words.extend(WordTokenizer::new(word, MAX_TOKEN_LENGTH).map(|t| t.word));
         }
+        if current
+            .data
+            .iter()
+            .any(|data| matches!(data, ArchivedPrincipalData::CalendarOutOfOffice(_)))
+        {
+            current_words.insert(OUT_OF_OFFICE_INDEX.into());
+        }
     }
 
     if let Some(new) = new {
@@ -888,6 +1025,13 @@ pub(crate) fn build_search_index(
         {
             new_words.extend(WordTokenizer::new(word, MAX_TOKEN_LENGTH).map(|t| t.word));
         }
+        if new
+            .data
+            .iter()
+            .any(|data| matches!(data, PrincipalData::CalendarOutOfOffice(_)))
+        {
+            new_words.insert(OUT_OF_OFFICE_INDEX.into());
+        }
     }
 
     for word in new_words.difference(&current_words) {
@@ -987,6 +1131,29 @@ impl FromStr for Type {
     }
 }
 
+impl DomainStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DomainStatus::PendingVerification => "pending-verification",
+            DomainStatus::Verified => "verified",
+            DomainStatus::Active => "active",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending-verification" => Some(DomainStatus::PendingVerification),
+            "verified" => Some(DomainStatus::Verified),
+            "active" => Some(DomainStatus::Active),
+            _ => None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self, DomainStatus::Active)
+    }
+}
+
 impl serde::Serialize for PrincipalSet {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -1011,6 +1178,8 @@ impl serde::Serialize for PrincipalSet {
 }
 
 const MAX_STRING_LEN: usize = 512;
+pub const MAX_AVATAR_LEN: usize = 64 * 1024;
+pub const AVATAR_CONTENT_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
 
 impl<'de> serde::Deserialize<'de> for PrincipalValue {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -1150,7 +1319,11 @@ impl<'de> serde::Deserialize<'de> for PrincipalSet {
                         PrincipalField::Description
                         | PrincipalField::Tenant
                         | PrincipalField::Picture
-                        | PrincipalField::Locale => {
+                        | PrincipalField::Locale
+                        | PrincipalField::RecoveryEmail
+                        | PrincipalField::Phone
+                        | PrincipalField::DomainStatus
+                        | PrincipalField::VerificationToken => {
                             if let Some(v) = map.next_value::<Option<String>>()? {
                                 if v.len() <= MAX_STRING_LEN {
                                     PrincipalValue::String(v)
@@ -1167,7 +1340,13 @@ impl<'de> serde::Deserialize<'de> for PrincipalSet {
                             })?;
                             continue;
                         }
-                        PrincipalField::Quota => map.next_value::<PrincipalValue>()?,
+                        PrincipalField::Quota
+                        | PrincipalField::SoftQuota
+                        | PrincipalField::MessageQuota
+                        | PrincipalField::LegalHold
+                        | PrincipalField::SpamTrap
+                        | PrincipalField::CalendarOutOfOffice
+                        | PrincipalField::CollectContacts => map.next_value::<PrincipalValue>()?,
                         PrincipalField::Secrets
                         | PrincipalField::Emails
                         | PrincipalField::MemberOf
@@ -1177,7 +1356,10 @@ impl<'de> serde::Deserialize<'de> for PrincipalSet {
                         | PrincipalField::EnabledPermissions
                         | PrincipalField::DisabledPermissions
                         | PrincipalField::Urls
-                        | PrincipalField::ExternalMembers => {
+                        | PrincipalField::ExternalMembers
+                        | PrincipalField::AllowedSenders
+                        | PrincipalField::SendAs
+                        | PrincipalField::SendOnBehalf => {
                             match map.next_value::<StringOrMany>()? {
                                 StringOrMany::One(v) => PrincipalValue::StringList(vec![v]),
                                 StringOrMany::Many(v) => {
@@ -1465,6 +1647,18 @@ impl Permission {
                 | Permission::CalendarAlarms
                 | Permission::CalendarSchedulingSend
                 | Permission::CalendarSchedulingReceive
+                | Permission::JmapAddressBookGet
+                | Permission::JmapAddressBookChanges
+                | Permission::JmapContactCardGet
+                | Permission::JmapContactCardChanges
+                | Permission::JmapContactCardQuery
+                | Permission::JmapContactCardQueryChanges
+                | Permission::ManageAvatar
+                | Permission::ManageSavedSearches
+                | Permission::ManageReadReceipts
+                | Permission::ManageForwarding
+                | Permission::ManageDisposableAliases
+                | Permission::JmapAutocryptKeyGet
         )
     }
 
@@ -59,6 +59,30 @@ pub enum PrincipalData {
     Urls(Vec<String>),
     PrincipalQuota(Vec<PrincipalQuota>),
     Locale(String),
+    LegalHold(u64),
+    RecoveryEmail(String),
+    SpamTrap(u64),
+    CalendarOutOfOffice(u64),
+    CollectContacts(u64),
+    Phone(String),
+    DomainStatus(DomainStatus),
+    VerificationToken(String),
+    AllowedSenders(Vec<String>),
+    SendAs(Vec<u32>),
+    SendOnBehalf(Vec<u32>),
+    SoftQuota(u64),
+    MessageQuota(u64),
+}
+
+// Domains without a status were created before onboarding existed and are active
+#[derive(
+    rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Default, Clone, Copy, PartialEq, Eq,
+)]
+pub enum DomainStatus {
+    PendingVerification,
+    Verified,
+    #[default]
+    Active,
 }
 
 #[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq)]
@@ -379,6 +403,27 @@ pub enum Permission {
     CalendarAlarms,
     CalendarSchedulingSend,
     CalendarSchedulingReceive,
+    MessageRedact,
+    MessageImport,
+    ConnectionsView,
+    SessionsList,
+    SessionsTerminate,
+    LockoutView,
+    LockoutClear,
+    JmapAddressBookGet,
+    JmapAddressBookChanges,
+    JmapContactCardGet,
+    JmapContactCardChanges,
+    JmapContactCardQuery,
+    JmapContactCardQueryChanges,
+    ManageAvatar,
+    ManageSavedSearches,
+    EmailRecall,
+    ManageReadReceipts,
+    ManageForwarding,
+    ManageDisposableAliases,
+    JmapAutocryptKeyGet,
+    MaildirImport,
     // WARNING: add new ids at the end (TODO: use static ids)
 }
 
@@ -394,6 +439,7 @@ pub enum DirectoryInner {
     Ldap(LdapDirectory),
     Sql(SqlDirectory),
     OpenId(backend::oidc::OpenIdDirectory),
+    Http(backend::http::HttpDirectory),
     Imap(ImapDirectory),
     Smtp(SmtpDirectory),
     Memory(MemoryDirectory),
@@ -7,7 +7,7 @@
 use crate::message::metadata::{ArchivedMessageData, MessageData};
 use common::{
     MessageCache, MessageStoreCache, MessageUidCache, MessagesCache, Server, auth::AccessToken,
-    sharing::EffectiveAcl,
+    config::jmap::settings::JmapConfig, sharing::EffectiveAcl,
 };
 use jmap_proto::types::{
     acl::Acl,
@@ -30,6 +30,10 @@ pub(crate) async fn update_email_cache(
         size: 0,
         change_id: 0,
         keywords: store_cache.emails.keywords.clone(),
+        keyword_mask: 0,
+        mailbox_keyword_masks: AHashMap::with_capacity(
+            store_cache.emails.mailbox_keyword_masks.len(),
+        ),
     };
 
     for (document_id, is_update) in changed_ids {
@@ -73,6 +77,8 @@ pub(crate) async fn full_email_cache_build(
         items: Vec::with_capacity(16),
         index: AHashMap::with_capacity(16),
         keywords: Vec::new(),
+        keyword_mask: 0,
+        mailbox_keyword_masks: AHashMap::new(),
         size: 0,
         change_id: 0,
     };
@@ -96,6 +102,7 @@ pub(crate) async fn full_email_cache_build(
 
     cache.items.shrink_to_fit();
     cache.index.shrink_to_fit();
+    cache.mailbox_keyword_masks.shrink_to_fit();
 
     Ok(cache)
 }
@@ -175,6 +182,23 @@ pub trait MessageCacheAccess {
     fn expand_keywords(&self, message: &MessageCache) -> impl Iterator<Item = Keyword>;
 
     fn has_keyword(&self, message: &MessageCache, keyword: &Keyword) -> bool;
+
+    fn custom_keywords(&self, mailbox_id: Option<u32>) -> AHashMap<&str, usize>;
+
+    fn can_create_keywords(&self, mailbox_id: u32, config: &JmapConfig) -> bool;
+
+    fn permanent_keywords(
+        &self,
+        mailbox_id: u32,
+        config: &JmapConfig,
+    ) -> impl Iterator<Item = &str>;
+
+    fn exceeds_keyword_limits<'x>(
+        &self,
+        keywords: &'x [Keyword],
+        mailbox_ids: &[u32],
+        config: &JmapConfig,
+    ) -> Option<&'x Keyword>;
 }
 
 impl MessageCacheAccess for MessageStoreCache {
@@ -280,10 +304,129 @@ impl MessageCacheAccess for MessageStoreCache {
     fn has_keyword(&self, message: &MessageCache, keyword: &Keyword) -> bool {
         keyword_to_id(self, keyword).is_some_and(|id| message.keywords & (1 << id) != 0)
     }
+
+    // Returns the custom keywords in use and the number of messages tagged with each.
+    // Keywords no longer set on any message are not included.
+    fn custom_keywords(&self, mailbox_id: Option<u32>) -> AHashMap<&str, usize> {
+        let mut keywords = AHashMap::new();
+        for message in self.emails.items.iter().filter(|m| {
+            mailbox_id
+                .is_none_or(|mailbox_id| m.mailboxes.iter().any(|m| m.mailbox_id == mailbox_id))
+        }) {
+            for id in KeywordsIter(message.keywords >> OTHER) {
+                if let Some(keyword) = self.emails.keywords.get(id) {
+                    *keywords.entry(keyword.as_str()).or_insert(0) += 1;
+                }
+            }
+        }
+        keywords
+    }
+
+    fn can_create_keywords(&self, mailbox_id: u32, config: &JmapConfig) -> bool {
+        custom_keywords_mask(self, None).count_ones() < config.mail_max_keywords as u32
+            && custom_keywords_mask(self, Some(mailbox_id)).count_ones()
+                < config.mail_max_mailbox_keywords as u32
+    }
+
+    // Custom keywords that can be set on messages in the mailbox without exceeding its limit
+    fn permanent_keywords(
+        &self,
+        mailbox_id: u32,
+        config: &JmapConfig,
+    ) -> impl Iterator<Item = &str> {
+        let mailbox_mask = custom_keywords_mask(self, Some(mailbox_id));
+        let mask = if mailbox_mask.count_ones() < config.mail_max_mailbox_keywords as u32 {
+            custom_keywords_mask(self, None)
+        } else {
+            mailbox_mask
+        };
+        KeywordsIter(mask).filter_map(|id| self.emails.keywords.get(id).map(|k| k.as_str()))
+    }
+
+    fn exceeds_keyword_limits<'x>(
+        &self,
+        keywords: &'x [Keyword],
+        mailbox_ids: &[u32],
+        config: &JmapConfig,
+    ) -> Option<&'x Keyword> {
+        if keywords.iter().all(|keyword| keyword.id().is_ok()) {
+            return None;
+        }
+
+        let mut account_mask = custom_keywords_mask(self, None);
+        let mut mailbox_masks = mailbox_ids
+            .iter()
+            .map(|mailbox_id| custom_keywords_mask(self, Some(*mailbox_id)))
+            .collect::<Vec<_>>();
+        let mut new_keywords = Vec::new();
+
+        for keyword in keywords {
+            let Err(name) = keyword.id() else {
+                continue;
+            };
+
+            // Keywords not yet in the account table are assigned the next free bit
+            let bit = if let Some(idx) = self.emails.keywords.iter().position(|k| k == name) {
+                1u128 << idx
+            } else if let Some(idx) = new_keywords.iter().position(|k| k == &name) {
+                1u128 << (self.emails.keywords.len() + idx)
+            } else {
+                new_keywords.push(name);
+                1u128
+                    .checked_shl((self.emails.keywords.len() + new_keywords.len() - 1) as u32)
+                    .unwrap_or_default()
+            };
+            if bit == 0 {
+                // The account keyword table is full
+                return Some(keyword);
+            }
+
+            account_mask |= bit;
+            if account_mask.count_ones() > config.mail_max_keywords as u32 {
+                return Some(keyword);
+            }
+            for mailbox_mask in &mut mailbox_masks {
+                *mailbox_mask |= bit;
+                if mailbox_mask.count_ones() > config.mail_max_mailbox_keywords as u32 {
+                    return Some(keyword);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+fn custom_keywords_mask(cache: &MessageStoreCache, mailbox_id: Option<u32>) -> u128 {
+    match mailbox_id {
+        Some(mailbox_id) => cache
+            .emails
+            .mailbox_keyword_masks
+            .get(&mailbox_id)
+            .copied()
+            .unwrap_or_default(),
+        None => cache.emails.keyword_mask,
+    }
 }
 
 fn email_insert(cache: &mut MessagesCache, item: MessageCache) {
     let id = item.document_id;
+
+    // Keep the custom keywords in use per account and mailbox up to date
+    let keywords = item.keywords >> OTHER;
+    if keywords != 0 {
+        cache.keyword_mask |= keywords;
+        for mailbox in &item.mailboxes {
+            *cache
+                .mailbox_keyword_masks
+                .entry(mailbox.mailbox_id)
+                .or_insert_with(|| {
+                    cache.size += (std::mem::size_of::<u32>() + std::mem::size_of::<u128>()) as u64;
+                    0
+                }) |= keywords;
+        }
+    }
+
     if let Some(idx) = cache.index.get(&id) {
         cache.items[*idx as usize] = item;
     } else {
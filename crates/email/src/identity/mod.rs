@@ -4,6 +4,10 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use common::{Server, auth::AccessToken};
+use std::future::Future;
+use trc::AddContext;
+
 pub mod index;
 
 #[derive(
@@ -23,3 +27,36 @@ pub struct EmailAddress {
     pub name: Option<String>,
     pub email: String,
 }
+
+pub trait IdentityAuthorization: Sync + Send {
+    fn is_identity_authorized(
+        &self,
+        access_token: &AccessToken,
+        email: &str,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl IdentityAuthorization for Server {
+    async fn is_identity_authorized(
+        &self,
+        access_token: &AccessToken,
+        email: &str,
+    ) -> trc::Result<bool> {
+        let email = email.to_lowercase();
+        if access_token
+            .emails
+            .iter()
+            .chain(access_token.allowed_senders.iter())
+            .chain(access_token.send_as.iter())
+            .chain(access_token.send_on_behalf.iter())
+            .any(|e| e == &email)
+        {
+            return Ok(true);
+        }
+
+        self.disposable_alias_to_id(&email)
+            .await
+            .caused_by(trc::location!())
+            .map(|account_id| account_id == Some(access_token.primary_id()))
+    }
+}
@@ -38,22 +38,12 @@ impl MailboxDestroy for Server {
         access_token: &AccessToken,
         remove_emails: bool,
     ) -> trc::Result<Result<Option<u64>, SetError>> {
-        // Internal folders cannot be deleted
-        #[cfg(feature = "test_mode")]
-        if [INBOX_ID, TRASH_ID].contains(&document_id)
+        // System folders cannot be deleted
+        if is_protected_folder(&self.core.jmap, document_id)
             && !access_token.has_permission(Permission::DeleteSystemFolders)
         {
             return Ok(Err(SetError::forbidden().with_description(
-                "You are not allowed to delete Inbox, Junk or Trash folders.",
-            )));
-        }
-
-        #[cfg(not(feature = "test_mode"))]
-        if [INBOX_ID, TRASH_ID, JUNK_ID].contains(&document_id)
-            && !access_token.has_permission(Permission::DeleteSystemFolders)
-        {
-            return Ok(Err(SetError::forbidden().with_description(
-                "You are not allowed to delete Inbox, Junk or Trash folders.",
+                "You are not allowed to delete system folders.",
             )));
         }
 
@@ -6,7 +6,13 @@
 
 use super::*;
 use crate::cache::MessageCacheFetch;
-use common::{Server, config::jmap::settings::SpecialUse, storage::index::ObjectIndexBuilder};
+use common::{
+    Server,
+    config::jmap::settings::SpecialUse,
+    i18n::{self, Locale},
+    storage::index::ObjectIndexBuilder,
+};
+use directory::{PrincipalData, backend::internal::manage::ManageDirectory};
 use jmap_proto::types::collection::Collection;
 use std::future::Future;
 use store::write::BatchBuilder;
@@ -38,8 +44,15 @@ impl MailboxFnc for Server {
             .with_collection(Collection::Mailbox);
 
         // Create mailboxes
+        let locale = account_locale(self, account_id).await?;
         let mut last_document_id = ARCHIVE_ID;
-        for folder in &self.core.jmap.default_folders {
+        for folder in self
+            .core
+            .jmap
+            .default_folders
+            .iter()
+            .filter(|folder| folder.create)
+        {
             let document_id = match folder.special_use {
                 SpecialUse::Inbox => INBOX_ID,
                 SpecialUse::Trash => TRASH_ID,
@@ -54,7 +67,15 @@ impl MailboxFnc for Server {
                 SpecialUse::Shared => unreachable!(),
             };
 
-            let mut object = Mailbox::new(folder.name.clone()).with_role(folder.special_use);
+            let name = match folder.special_use {
+                SpecialUse::Trash if folder.localize => locale.mailbox_trash,
+                SpecialUse::Junk if folder.localize => locale.mailbox_junk,
+                SpecialUse::Drafts if folder.localize => locale.mailbox_drafts,
+                SpecialUse::Sent if folder.localize => locale.mailbox_sent,
+                SpecialUse::Archive if folder.localize => locale.mailbox_archive,
+                _ => folder.name.as_str(),
+            };
+            let mut object = Mailbox::new(name).with_role(folder.special_use);
             if folder.subscribe {
                 object.add_subscriber(account_id);
             }
@@ -152,3 +173,41 @@ impl MailboxFnc for Server {
         Ok(Some(next_parent_id - 1))
     }
 }
+
+// Accounts without a locale of their own inherit the one set on their domain
+async fn account_locale(server: &Server, account_id: u32) -> trc::Result<&'static Locale> {
+    let access_token = server
+        .get_access_token(account_id)
+        .await
+        .caused_by(trc::location!())?;
+    if let Some(locale) = &access_token.locale {
+        return Ok(i18n::locale_or_default(locale));
+    }
+
+    if let Some((_, domain)) = access_token
+        .emails
+        .first()
+        .unwrap_or(&access_token.name)
+        .rsplit_once('@')
+        && let Some(domain_id) = server
+            .store()
+            .get_principal_id(domain)
+            .await
+            .caused_by(trc::location!())?
+        && let Some(locale) = server
+            .store()
+            .get_principal(domain_id)
+            .await
+            .caused_by(trc::location!())?
+            .and_then(|domain| {
+                domain.data.into_iter().find_map(|data| match data {
+                    PrincipalData::Locale(locale) => Some(locale),
+                    _ => None,
+                })
+            })
+    {
+        return Ok(i18n::locale_or_default(&locale));
+    }
+
+    Ok(i18n::locale_or_default("en"))
+}
@@ -4,12 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::config::jmap::settings::SpecialUse;
+use common::config::jmap::settings::{JmapConfig, SpecialUse};
 use jmap_proto::types::value::AclGrant;
 
 pub mod destroy;
 pub mod index;
 pub mod manage;
+pub mod search;
 
 pub const INBOX_ID: u32 = 0;
 pub const TRASH_ID: u32 = 1;
@@ -19,6 +20,28 @@ pub const SENT_ID: u32 = 4;
 pub const ARCHIVE_ID: u32 = 5;
 pub const TOMBSTONE_ID: u32 = u32::MAX - 1;
 
+// Virtual mailboxes (saved searches and scheduled submissions) have the high bit set
+pub const VIRTUAL_MAILBOX_ID: u32 = 1 << 31;
+
+// Provisioned special-use folders cannot be deleted or renamed unless configured otherwise,
+// the Inbox is always protected
+pub fn is_protected_folder(config: &JmapConfig, document_id: u32) -> bool {
+    let special_use = match document_id {
+        INBOX_ID => return true,
+        TRASH_ID => SpecialUse::Trash,
+        JUNK_ID => SpecialUse::Junk,
+        DRAFTS_ID => SpecialUse::Drafts,
+        SENT_ID => SpecialUse::Sent,
+        ARCHIVE_ID => SpecialUse::Archive,
+        _ => return false,
+    };
+
+    config
+        .default_folders
+        .iter()
+        .any(|folder| folder.special_use == special_use && folder.protect)
+}
+
 #[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq)]
 #[rkyv(derive(Debug))]
 pub struct Mailbox {
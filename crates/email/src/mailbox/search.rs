@@ -0,0 +1,357 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{INBOX_ID, VIRTUAL_MAILBOX_ID};
+use crate::message::query::EmailQueryFilter;
+use common::{MessageStoreCache, Server};
+use jmap_proto::{
+    method::query::{Filter, parse_filter},
+    parser::{Ignore, Token, json::Parser},
+    types::{
+        collection::{Collection, SyncCollection},
+        property::Property,
+    },
+};
+use std::future::Future;
+use store::{
+    Serialize,
+    roaring::RoaringBitmap,
+    write::{Archiver, BatchBuilder},
+};
+use trc::AddContext;
+
+#[derive(
+    rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq, Default,
+)]
+#[rkyv(derive(Debug))]
+pub struct SavedSearches {
+    pub items: Vec<SavedSearch>,
+    pub next_id: u32,
+}
+
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq)]
+#[rkyv(derive(Debug))]
+pub struct SavedSearch {
+    pub id: u32,
+    pub name: String,
+    pub filter: String,
+    pub uid_validity: u32,
+}
+
+// UIDs assigned to the messages of a virtual mailbox. Messages keep their UID
+// while they remain in the results and new ones are assigned a higher UID.
+#[derive(
+    rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq, Default,
+)]
+#[rkyv(derive(Debug))]
+pub struct VirtualMailboxUids {
+    pub uid_validity: u32,
+    pub uid_next: u32,
+    pub items: Vec<VirtualMailboxUid>,
+}
+
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq)]
+#[rkyv(derive(Debug))]
+pub struct VirtualMailboxUid {
+    pub document_id: u32,
+    pub uid: u32,
+}
+
+impl SavedSearch {
+    pub fn new(id: u32, name: impl Into<String>, filter: impl Into<String>) -> Self {
+        SavedSearch {
+            id,
+            name: name.into(),
+            filter: filter.into(),
+            uid_validity: rand::random::<u32>(),
+        }
+    }
+
+    pub fn set_filter(&mut self, filter: String) {
+        // Clients must discard cached UIDs when the results change meaning
+        if self.filter != filter {
+            self.filter = filter;
+            self.uid_validity = rand::random::<u32>();
+        }
+    }
+}
+
+pub trait SavedSearchManager: Sync + Send {
+    fn get_saved_searches(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<SavedSearches>> + Send;
+
+    fn set_saved_searches(
+        &self,
+        account_id: u32,
+        searches: SavedSearches,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn expand_saved_searches(
+        &self,
+        account_id: u32,
+        filter: Vec<Filter>,
+    ) -> impl Future<Output = trc::Result<Vec<Filter>>> + Send;
+
+    fn saved_search_results(
+        &self,
+        account_id: u32,
+        cached_messages: &MessageStoreCache,
+        search: &SavedSearch,
+    ) -> impl Future<Output = trc::Result<RoaringBitmap>> + Send;
+
+    fn get_virtual_uids(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        uid_validity: u32,
+    ) -> impl Future<Output = trc::Result<VirtualMailboxUids>> + Send;
+
+    fn assign_virtual_uids(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        uid_validity: u32,
+        document_ids: &RoaringBitmap,
+    ) -> impl Future<Output = trc::Result<VirtualMailboxUids>> + Send;
+}
+
+impl SavedSearchManager for Server {
+    async fn get_saved_searches(&self, account_id: u32) -> trc::Result<SavedSearches> {
+        self.get_archive_by_property(
+            account_id,
+            Collection::Principal,
+            0,
+            Property::SavedSearches,
+        )
+        .await
+        .caused_by(trc::location!())?
+        .map(|archive| archive.deserialize::<SavedSearches>())
+        .transpose()
+        .map(Option::unwrap_or_default)
+    }
+
+    async fn set_saved_searches(
+        &self,
+        account_id: u32,
+        searches: SavedSearches,
+    ) -> trc::Result<()> {
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(account_id);
+
+        // Remove the UIDs assigned to deleted searches
+        for search in self.get_saved_searches(account_id).await?.items {
+            if !searches.items.iter().any(|item| item.id == search.id) {
+                batch
+                    .with_collection(Collection::Mailbox)
+                    .update_document(VIRTUAL_MAILBOX_ID | search.id)
+                    .clear(Property::EmailIds);
+            }
+        }
+
+        batch
+            .with_collection(Collection::Principal)
+            .update_document(0);
+        if !searches.items.is_empty() || searches.next_id > 0 {
+            batch.set(
+                Property::SavedSearches,
+                Archiver::new(searches)
+                    .serialize()
+                    .caused_by(trc::location!())?,
+            );
+        } else {
+            batch.clear(Property::SavedSearches);
+        }
+
+        // Bump the mailbox state so IMAP and JMAP clients pick up the new definitions
+        batch
+            .with_collection(Collection::Mailbox)
+            .log_container_property_change(SyncCollection::Email, INBOX_ID);
+
+        self.commit_batch(batch)
+            .await
+            .caused_by(trc::location!())
+            .map(|_| ())
+    }
+
+    async fn expand_saved_searches(
+        &self,
+        account_id: u32,
+        filter: Vec<Filter>,
+    ) -> trc::Result<Vec<Filter>> {
+        if !filter
+            .iter()
+            .any(|cond| matches!(cond, Filter::InSavedSearch(_)))
+        {
+            return Ok(filter);
+        }
+
+        let searches = self.get_saved_searches(account_id).await?;
+        let mut expanded = Vec::with_capacity(filter.len());
+        for cond in filter {
+            if let Filter::InSavedSearch(id) = cond {
+                let search = searches
+                    .items
+                    .iter()
+                    .find(|search| search.id == id.document_id())
+                    .ok_or_else(|| {
+                        trc::JmapEvent::InvalidArguments
+                            .into_err()
+                            .details(format!("Saved search {id} does not exist."))
+                    })?;
+                expanded.extend(parse_saved_filter(&search.filter)?);
+            } else {
+                expanded.push(cond);
+            }
+        }
+
+        Ok(expanded)
+    }
+
+    async fn saved_search_results(
+        &self,
+        account_id: u32,
+        cached_messages: &MessageStoreCache,
+        search: &SavedSearch,
+    ) -> trc::Result<RoaringBitmap> {
+        let filters = self
+            .email_query_filter(
+                account_id,
+                cached_messages,
+                parse_saved_filter(&search.filter)?,
+            )
+            .await?;
+
+        self.core
+            .storage
+            .data
+            .filter(account_id, Collection::Email, filters)
+            .await
+            .caused_by(trc::location!())
+            .map(|result_set| result_set.results)
+    }
+
+    async fn get_virtual_uids(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        uid_validity: u32,
+    ) -> trc::Result<VirtualMailboxUids> {
+        self.get_archive_by_property(
+            account_id,
+            Collection::Mailbox,
+            mailbox_id,
+            Property::EmailIds,
+        )
+        .await
+        .caused_by(trc::location!())?
+        .map(|archive| archive.deserialize::<VirtualMailboxUids>())
+        .transpose()
+        .map(|uids| {
+            // A new UID validity invalidates previously assigned UIDs
+            uids.filter(|uids| uids.uid_validity == uid_validity)
+                .unwrap_or(VirtualMailboxUids {
+                    uid_validity,
+                    uid_next: 1,
+                    items: vec![],
+                })
+        })
+    }
+
+    async fn assign_virtual_uids(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        uid_validity: u32,
+        document_ids: &RoaringBitmap,
+    ) -> trc::Result<VirtualMailboxUids> {
+        loop {
+            let archive = self
+                .get_archive_by_property(
+                    account_id,
+                    Collection::Mailbox,
+                    mailbox_id,
+                    Property::EmailIds,
+                )
+                .await
+                .caused_by(trc::location!())?;
+            let mut uids = archive
+                .as_ref()
+                .map(|archive| archive.deserialize::<VirtualMailboxUids>())
+                .transpose()?
+                .filter(|uids| uids.uid_validity == uid_validity)
+                .unwrap_or(VirtualMailboxUids {
+                    uid_validity,
+                    uid_next: 1,
+                    items: vec![],
+                });
+
+            // Drop messages no longer in the results and assign UIDs to new ones
+            let num_items = uids.items.len();
+            uids.items
+                .retain(|item| document_ids.contains(item.document_id));
+            let mut has_changes = uids.items.len() != num_items;
+            let assigned_ids =
+                RoaringBitmap::from_iter(uids.items.iter().map(|item| item.document_id));
+            for document_id in document_ids {
+                if !assigned_ids.contains(document_id) {
+                    uids.items.push(VirtualMailboxUid {
+                        document_id,
+                        uid: uids.uid_next,
+                    });
+                    uids.uid_next += 1;
+                    has_changes = true;
+                }
+            }
+            if !has_changes {
+                return Ok(uids);
+            }
+
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Mailbox)
+                .update_document(mailbox_id);
+            if let Some(archive) = &archive {
+                batch.assert_value(Property::EmailIds, archive);
+            } else {
+                batch.assert_value(Property::EmailIds, ());
+            }
+            batch.set(
+                Property::EmailIds,
+                Archiver::new(uids.clone())
+                    .serialize()
+                    .caused_by(trc::location!())?,
+            );
+            match self.commit_batch(batch).await {
+                Ok(_) => return Ok(uids),
+                // Another session assigned UIDs concurrently, start over
+                Err(err)
+                    if err.matches(trc::EventType::Store(trc::StoreEvent::AssertValueFailed)) => {}
+                Err(err) => return Err(err.caused_by(trc::location!())),
+            }
+        }
+    }
+}
+
+// Saved searches cannot reference other saved searches, which keeps expansion non-recursive
+pub fn parse_saved_filter(filter: &str) -> trc::Result<Vec<Filter>> {
+    let mut parser = Parser::new(filter.as_bytes());
+    parser.next_token::<Ignore>()?.assert(Token::DictStart)?;
+    let filter = parse_filter(&mut parser)?;
+
+    if filter
+        .iter()
+        .any(|cond| matches!(cond, Filter::InSavedSearch(_)))
+    {
+        Err(trc::JmapEvent::UnsupportedFilter
+            .into_err()
+            .details("Saved searches cannot reference other saved searches."))
+    } else {
+        Ok(filter)
+    }
+}
@@ -0,0 +1,289 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{KV_DISPOSABLE_ALIAS, KV_DISPOSABLE_ALIAS_RECEIVED, Server};
+use directory::backend::RcptType;
+use jmap_proto::types::{collection::Collection, property::Property};
+use std::future::Future;
+use store::{
+    Serialize,
+    dispatch::lookup::KeyValue,
+    rand::{Rng, distr::Alphanumeric, rng},
+    write::{Archiver, BatchBuilder, now},
+};
+use trc::AddContext;
+
+const ALIAS_LEN: usize = 8;
+
+#[derive(
+    rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq, Default,
+)]
+#[rkyv(derive(Debug))]
+pub struct DisposableAliases {
+    pub items: Vec<DisposableAlias>,
+    pub next_id: u32,
+}
+
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq)]
+#[rkyv(derive(Debug))]
+pub struct DisposableAlias {
+    pub id: u32,
+    pub address: String,
+    pub description: String,
+    pub enabled: bool,
+    pub created: u64,
+    pub expires: Option<u64>,
+}
+
+impl DisposableAlias {
+    pub fn is_active(&self, now: u64) -> bool {
+        self.enabled && self.expires.is_none_or(|expires| expires > now)
+    }
+}
+
+pub trait DisposableAliasManager: Sync + Send {
+    fn get_disposable_aliases(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<DisposableAliases>> + Send;
+
+    fn update_disposable_aliases<T: Send>(
+        &self,
+        account_id: u32,
+        update: impl Fn(&mut DisposableAliases) -> trc::Result<T> + Send + Sync,
+    ) -> impl Future<Output = trc::Result<T>> + Send;
+
+    fn remove_disposable_aliases(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn generate_disposable_alias(
+        &self,
+        domain: &str,
+    ) -> impl Future<Output = trc::Result<Option<String>>> + Send;
+
+    fn disposable_alias_received(
+        &self,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<i64>> + Send;
+
+    fn disposable_alias_delivered(
+        &self,
+        account_id: u32,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl DisposableAliasManager for Server {
+    async fn get_disposable_aliases(&self, account_id: u32) -> trc::Result<DisposableAliases> {
+        self.get_archive_by_property(
+            account_id,
+            Collection::Principal,
+            0,
+            Property::DisposableAliases,
+        )
+        .await
+        .caused_by(trc::location!())?
+        .map(|archive| archive.deserialize::<DisposableAliases>())
+        .transpose()
+        .map(Option::unwrap_or_default)
+    }
+
+    async fn update_disposable_aliases<T: Send>(
+        &self,
+        account_id: u32,
+        update: impl Fn(&mut DisposableAliases) -> trc::Result<T> + Send + Sync,
+    ) -> trc::Result<T> {
+        loop {
+            let archive = self
+                .get_archive_by_property(
+                    account_id,
+                    Collection::Principal,
+                    0,
+                    Property::DisposableAliases,
+                )
+                .await
+                .caused_by(trc::location!())?;
+            let current = archive
+                .as_ref()
+                .map(|archive| archive.deserialize::<DisposableAliases>())
+                .transpose()
+                .caused_by(trc::location!())?
+                .unwrap_or_default();
+            let mut aliases = current.clone();
+            let result = update(&mut aliases)?;
+            if aliases == current {
+                return Ok(result);
+            }
+
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Principal)
+                .update_document(0);
+            if let Some(archive) = &archive {
+                batch.assert_value(Property::DisposableAliases, archive);
+            } else {
+                batch.assert_value(Property::DisposableAliases, ());
+            }
+            if !aliases.items.is_empty() || aliases.next_id > 0 {
+                batch.set(
+                    Property::DisposableAliases,
+                    Archiver::new(aliases.clone())
+                        .serialize()
+                        .caused_by(trc::location!())?,
+                );
+            } else {
+                batch.clear(Property::DisposableAliases);
+            }
+            match self.store().write(batch.build_all()).await {
+                Ok(_) => {}
+                // The aliases were modified concurrently, start over
+                Err(err) if err.is_assertion_failure() => continue,
+                Err(err) => return Err(err.caused_by(trc::location!())),
+            }
+
+            // Update the alias lookup table, only active aliases are resolved on delivery
+            let store = self.in_memory_store();
+            let now = now();
+            for alias in &current.items {
+                if !aliases
+                    .items
+                    .iter()
+                    .any(|item| item.address == alias.address && item.is_active(now))
+                {
+                    store
+                        .key_delete(KeyValue::<()>::build_key(
+                            KV_DISPOSABLE_ALIAS,
+                            alias.address.as_bytes(),
+                        ))
+                        .await
+                        .caused_by(trc::location!())?;
+                }
+                if !aliases
+                    .items
+                    .iter()
+                    .any(|item| item.address == alias.address)
+                {
+                    store
+                        .counter_delete(KeyValue::<()>::build_key(
+                            KV_DISPOSABLE_ALIAS_RECEIVED,
+                            alias.address.as_bytes(),
+                        ))
+                        .await
+                        .caused_by(trc::location!())?;
+                }
+            }
+            for alias in &aliases.items {
+                if alias.is_active(now) {
+                    store
+                        .key_set(
+                            KeyValue::with_prefix(
+                                KV_DISPOSABLE_ALIAS,
+                                alias.address.as_bytes(),
+                                account_id.to_be_bytes().to_vec(),
+                            )
+                            .expires_opt(alias.expires.map(|expires| expires - now)),
+                        )
+                        .await
+                        .caused_by(trc::location!())?;
+                }
+            }
+
+            return Ok(result);
+        }
+    }
+
+    async fn remove_disposable_aliases(&self, account_id: u32) -> trc::Result<()> {
+        // Lookup entries are not removed when the account data is deleted
+        let store = self.in_memory_store();
+        for alias in self
+            .get_disposable_aliases(account_id)
+            .await
+            .caused_by(trc::location!())?
+            .items
+        {
+            store
+                .key_delete(KeyValue::<()>::build_key(
+                    KV_DISPOSABLE_ALIAS,
+                    alias.address.as_bytes(),
+                ))
+                .await
+                .caused_by(trc::location!())?;
+            store
+                .counter_delete(KeyValue::<()>::build_key(
+                    KV_DISPOSABLE_ALIAS_RECEIVED,
+                    alias.address.as_bytes(),
+                ))
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+
+    async fn generate_disposable_alias(&self, domain: &str) -> trc::Result<Option<String>> {
+        // Retry a few times in the unlikely event of a collision
+        for _ in 0..5 {
+            let local_part = rng()
+                .sample_iter(Alphanumeric)
+                .take(ALIAS_LEN)
+                .map(|ch| char::from(ch.to_ascii_lowercase()))
+                .collect::<String>();
+            let address = format!("{local_part}@{domain}");
+            // Catch-all addresses are not taken into account
+            if self
+                .core
+                .storage
+                .directory
+                .rcpt(&address)
+                .await
+                .caused_by(trc::location!())?
+                == RcptType::Invalid
+                && self
+                    .disposable_alias_to_id(&address)
+                    .await
+                    .caused_by(trc::location!())?
+                    .is_none()
+            {
+                return Ok(Some(address));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn disposable_alias_received(&self, address: &str) -> trc::Result<i64> {
+        self.in_memory_store()
+            .counter_get(KeyValue::<()>::build_key(
+                KV_DISPOSABLE_ALIAS_RECEIVED,
+                address.as_bytes(),
+            ))
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn disposable_alias_delivered(&self, account_id: u32, address: &str) -> trc::Result<()> {
+        let address = address.to_lowercase();
+        if self
+            .disposable_alias_to_id(&address)
+            .await
+            .caused_by(trc::location!())?
+            == Some(account_id)
+        {
+            self.in_memory_store()
+                .counter_incr(
+                    KeyValue::with_prefix(KV_DISPOSABLE_ALIAS_RECEIVED, address.as_bytes(), 1),
+                    false,
+                )
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,485 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::crypto::EncryptionParams;
+use common::Server;
+use jmap_proto::types::{collection::Collection, property::Property};
+use mail_builder::encoders::base64::base64_encode_mime;
+use mail_parser::{HeaderName, Message, decoders::base64::base64_decode};
+use std::future::Future;
+use store::{
+    Serialize, ValueKey,
+    write::{Archiver, BatchBuilder, ValueClass},
+    xxhash_rust::xxh3::xxh3_64,
+};
+use trc::AddContext;
+use utils::sanitize_email;
+
+const MAX_KEY_DATA_LEN: usize = 32 * 1024;
+
+#[derive(
+    rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq, Default,
+)]
+#[rkyv(derive(Debug))]
+pub struct AutocryptPeers {
+    pub items: Vec<AutocryptPeer>,
+    pub next_id: u32,
+}
+
+// The key data of each peer is stored separately, see `AutocryptPeer::key_document_id`
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq)]
+#[rkyv(derive(Debug))]
+pub struct AutocryptPeer {
+    pub id: u32,
+    pub address: String,
+    pub key_hash: u64,
+    pub prefer_encrypt: bool,
+    pub is_gossip: bool,
+    pub last_seen: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AutocryptHeader {
+    pub address: String,
+    pub key_data: String,
+    pub prefer_encrypt: bool,
+}
+
+pub trait AutocryptManager: Sync + Send {
+    fn get_autocrypt_peers(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<AutocryptPeers>> + Send;
+
+    fn get_autocrypt_key(
+        &self,
+        account_id: u32,
+        peer: &AutocryptPeer,
+    ) -> impl Future<Output = trc::Result<Option<String>>> + Send;
+
+    fn autocrypt_ingest(
+        &self,
+        account_id: u32,
+        message: &Message<'_>,
+        received_at: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn autocrypt_header(
+        &self,
+        account_id: u32,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<Option<String>>> + Send;
+
+    fn get_encryption_key(
+        &self,
+        account_id: Option<u32>,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<Option<Vec<u8>>>> + Send;
+}
+
+impl AutocryptManager for Server {
+    async fn get_autocrypt_peers(&self, account_id: u32) -> trc::Result<AutocryptPeers> {
+        self.get_archive_by_property(account_id, Collection::Principal, 0, Property::Autocrypt)
+            .await
+            .caused_by(trc::location!())?
+            .map(|archive| archive.deserialize::<AutocryptPeers>())
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    async fn get_autocrypt_key(
+        &self,
+        account_id: u32,
+        peer: &AutocryptPeer,
+    ) -> trc::Result<Option<String>> {
+        self.store()
+            .get_value::<String>(ValueKey::<ValueClass>::property(
+                account_id,
+                Collection::Principal,
+                peer.key_document_id(),
+                Property::KeyData,
+            ))
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn autocrypt_ingest(
+        &self,
+        account_id: u32,
+        message: &Message<'_>,
+        received_at: u64,
+    ) -> trc::Result<()> {
+        // Autocrypt headers are only accepted from messages with a single sender
+        let Some(from) = message
+            .from()
+            .filter(|from| from.iter().count() == 1)
+            .and_then(|from| from.first())
+            .and_then(|from| from.address())
+            .and_then(sanitize_email)
+        else {
+            return Ok(());
+        };
+
+        // Messages containing more than one Autocrypt header are ignored
+        let mut headers = Vec::new();
+        let mut gossip = Vec::new();
+        let mut num_headers = 0;
+        for header in &message.root_part().headers {
+            if let HeaderName::Other(name) = &header.name {
+                let is_gossip = if name.eq_ignore_ascii_case("Autocrypt") {
+                    num_headers += 1;
+                    false
+                } else if name.eq_ignore_ascii_case("Autocrypt-Gossip") {
+                    true
+                } else {
+                    continue;
+                };
+                if let Some(header) = message
+                    .raw_message
+                    .get(header.offset_start as usize..header.offset_end as usize)
+                    .and_then(|value| std::str::from_utf8(value).ok())
+                    .and_then(AutocryptHeader::parse)
+                {
+                    if is_gossip {
+                        gossip.push(header);
+                    } else {
+                        headers.push(header);
+                    }
+                }
+            }
+        }
+        if num_headers > 1 {
+            headers.clear();
+        }
+
+        // Gossip keys are only accepted for the recipients of the message
+        let recipients = message
+            .to()
+            .into_iter()
+            .chain(message.cc())
+            .flat_map(|addr| addr.iter())
+            .filter_map(|addr| addr.address().and_then(sanitize_email))
+            .collect::<Vec<_>>();
+        let updates = headers
+            .into_iter()
+            .filter(|header| header.address == from)
+            .map(|header| (header, false))
+            .chain(
+                gossip
+                    .into_iter()
+                    .filter(|header| header.address != from && recipients.contains(&header.address))
+                    .map(|header| (header, true)),
+            )
+            .collect::<Vec<_>>();
+        if updates.is_empty() {
+            return Ok(());
+        }
+
+        loop {
+            let archive = self
+                .get_archive_by_property(account_id, Collection::Principal, 0, Property::Autocrypt)
+                .await
+                .caused_by(trc::location!())?;
+            let mut peers = archive
+                .as_ref()
+                .map(|archive| archive.deserialize::<AutocryptPeers>())
+                .transpose()
+                .caused_by(trc::location!())?
+                .unwrap_or_default();
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Principal);
+            let mut has_changes = false;
+            for (header, is_gossip) in &updates {
+                let key_hash = xxh3_64(header.key_data.as_bytes());
+                let peer = if let Some(peer) = peers
+                    .items
+                    .iter_mut()
+                    .find(|peer| peer.address == header.address)
+                {
+                    // Keys sent directly by a peer are never replaced by gossip
+                    if peer.last_seen > received_at
+                        || (*is_gossip && !peer.is_gossip)
+                        || (peer.key_hash == key_hash
+                            && peer.prefer_encrypt == header.prefer_encrypt
+                            && peer.is_gossip == *is_gossip)
+                    {
+                        continue;
+                    }
+                    peer.key_hash = key_hash;
+                    peer.prefer_encrypt = header.prefer_encrypt;
+                    peer.is_gossip = *is_gossip;
+                    peer.last_seen = received_at;
+                    peer.clone()
+                } else {
+                    // Evict the least recently seen peer once the limit is reached
+                    if peers.items.len() >= self.core.jmap.autocrypt_max_peers
+                        && let Some(pos) = peers
+                            .items
+                            .iter()
+                            .enumerate()
+                            .min_by_key(|(_, peer)| peer.last_seen)
+                            .map(|(pos, _)| pos)
+                    {
+                        let evicted = peers.items.swap_remove(pos);
+                        batch
+                            .update_document(evicted.key_document_id())
+                            .clear(Property::KeyData);
+                    }
+                    let peer = AutocryptPeer {
+                        id: peers.next_id,
+                        address: header.address.clone(),
+                        key_hash,
+                        prefer_encrypt: header.prefer_encrypt,
+                        is_gossip: *is_gossip,
+                        last_seen: received_at,
+                    };
+                    peers.items.push(peer.clone());
+                    peers.next_id += 1;
+                    peer
+                };
+                batch
+                    .update_document(peer.key_document_id())
+                    .set(Property::KeyData, header.key_data.as_bytes().to_vec());
+                has_changes = true;
+            }
+            if !has_changes {
+                return Ok(());
+            }
+
+            batch.update_document(0);
+            if let Some(archive) = &archive {
+                batch.assert_value(Property::Autocrypt, archive);
+            } else {
+                batch.assert_value(Property::Autocrypt, ());
+            }
+            batch.set(
+                Property::Autocrypt,
+                Archiver::new(peers)
+                    .serialize()
+                    .caused_by(trc::location!())?,
+            );
+            match self.store().write(batch.build_all()).await {
+                Ok(_) => return Ok(()),
+                // The peers were modified concurrently, start over
+                Err(err) if err.is_assertion_failure() => continue,
+                Err(err) => return Err(err.caused_by(trc::location!())),
+            }
+        }
+    }
+
+    async fn autocrypt_header(
+        &self,
+        account_id: u32,
+        address: &str,
+    ) -> trc::Result<Option<String>> {
+        // The account's OpenPGP encryption at rest key is advertised to peers
+        let Some(params_) = self
+            .get_archive_by_property(account_id, Collection::Principal, 0, Property::Parameters)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(None);
+        };
+        let Some(key_data) = params_
+            .deserialize::<EncryptionParams>()
+            .caused_by(trc::location!())?
+            .openpgp_public_key()
+        else {
+            return Ok(None);
+        };
+
+        let mut header = format!("Autocrypt: addr={address}; keydata=");
+        let mut encoded = Vec::with_capacity(key_data.len() * 4 / 3 + 4);
+        if base64_encode_mime(&key_data, &mut encoded, false).is_err() {
+            return Ok(None);
+        }
+        for line in String::from_utf8_lossy(&encoded).split("\r\n") {
+            if !line.is_empty() {
+                header.push_str("\r\n ");
+                header.push_str(line);
+            }
+        }
+        header.push_str("\r\n");
+
+        Ok(Some(header))
+    }
+
+    async fn get_encryption_key(
+        &self,
+        account_id: Option<u32>,
+        address: &str,
+    ) -> trc::Result<Option<Vec<u8>>> {
+        // Keys of local accounts are preferred over the ones learned from peers
+        if let Some(rcpt_id) = self
+            .core
+            .storage
+            .directory
+            .email_to_id(address)
+            .await
+            .caused_by(trc::location!())?
+            && let Some(key) = self
+                .get_archive_by_property(rcpt_id, Collection::Principal, 0, Property::Parameters)
+                .await
+                .caused_by(trc::location!())?
+                .map(|params| params.deserialize::<EncryptionParams>())
+                .transpose()
+                .caused_by(trc::location!())?
+                .and_then(|params| params.openpgp_public_key())
+        {
+            return Ok(Some(key));
+        }
+
+        let Some(account_id) = account_id else {
+            return Ok(None);
+        };
+        let Some(peer) = self
+            .get_autocrypt_peers(account_id)
+            .await?
+            .items
+            .into_iter()
+            .find(|peer| peer.address == address)
+        else {
+            return Ok(None);
+        };
+
+        self.get_autocrypt_key(account_id, &peer)
+            .await
+            .map(|key| key.and_then(|key| base64_decode(key.as_bytes())))
+    }
+}
+
+impl AutocryptPeers {
+    // Stable across restarts, used as the JMAP state
+    pub fn state(&self) -> u64 {
+        let mut buf = Vec::with_capacity(self.items.len() * 64);
+        for peer in &self.items {
+            buf.extend_from_slice(&peer.id.to_be_bytes());
+            buf.extend_from_slice(peer.address.as_bytes());
+            buf.extend_from_slice(&peer.key_hash.to_be_bytes());
+            buf.extend_from_slice(&peer.last_seen.to_be_bytes());
+            buf.push(peer.prefer_encrypt as u8);
+            buf.push(peer.is_gossip as u8);
+        }
+        xxh3_64(&buf)
+    }
+}
+
+impl AutocryptPeer {
+    // Document 0 holds the list of peers, the key of each peer is stored after it
+    pub fn key_document_id(&self) -> u32 {
+        self.id + 1
+    }
+}
+
+impl AutocryptHeader {
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut address = None;
+        let mut key_data = None;
+        let mut prefer_encrypt = false;
+
+        for attribute in value.split(';') {
+            let attribute = attribute.trim();
+            if attribute.is_empty() {
+                continue;
+            }
+            let (name, value) = attribute.split_once('=')?;
+            match name.trim().to_ascii_lowercase().as_str() {
+                "addr" => {
+                    address = Some(sanitize_email(value)?);
+                }
+                "prefer-encrypt" => {
+                    prefer_encrypt = value.trim().eq_ignore_ascii_case("mutual");
+                }
+                "keydata" => {
+                    let value = value
+                        .chars()
+                        .filter(|ch| !ch.is_ascii_whitespace())
+                        .collect::<String>();
+                    if value.is_empty()
+                        || value.len() > MAX_KEY_DATA_LEN
+                        || base64_decode(value.as_bytes()).is_none()
+                    {
+                        return None;
+                    }
+                    key_data = Some(value);
+                }
+                // Unknown non-critical attributes are ignored
+                name if name.starts_with('_') => (),
+                _ => return None,
+            }
+        }
+
+        Some(AutocryptHeader {
+            address: address?,
+            key_data: key_data?,
+            prefer_encrypt,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_autocrypt_header() {
+        assert_eq!(
+            AutocryptHeader::parse(
+                "addr=Bill@Remote.org; prefer-encrypt=mutual; _extra=1; keydata=\r\n a2V5\r\n ZGF0YQ=="
+            ),
+            Some(AutocryptHeader {
+                address: "bill@remote.org".into(),
+                key_data: "a2V5ZGF0YQ==".into(),
+                prefer_encrypt: true,
+            })
+        );
+        assert_eq!(
+            AutocryptHeader::parse("addr=bill@remote.org; keydata=a2V5ZGF0YQ=="),
+            Some(AutocryptHeader {
+                address: "bill@remote.org".into(),
+                key_data: "a2V5ZGF0YQ==".into(),
+                prefer_encrypt: false,
+            })
+        );
+
+        for value in [
+            // Missing attributes
+            "addr=bill@remote.org",
+            "keydata=a2V5ZGF0YQ==",
+            // Unknown critical attribute
+            "addr=bill@remote.org; critical=1; keydata=a2V5ZGF0YQ==",
+            // Invalid address and key data
+            "addr=bill; keydata=a2V5ZGF0YQ==",
+            "addr=bill@remote.org; keydata=",
+            "addr=bill@remote.org; keydata=!!!",
+        ] {
+            assert_eq!(AutocryptHeader::parse(value), None, "{value}");
+        }
+    }
+
+    #[test]
+    fn peers_state() {
+        let peer = AutocryptPeer {
+            id: 0,
+            address: "bill@remote.org".into(),
+            key_hash: xxh3_64(b"a2V5ZGF0YQ=="),
+            prefer_encrypt: true,
+            is_gossip: false,
+            last_seen: 1000,
+        };
+        let mut peers = AutocryptPeers {
+            items: vec![peer],
+            next_id: 1,
+        };
+        let state = peers.state();
+        assert_eq!(state, peers.clone().state());
+        peers.items[0].key_hash = xxh3_64(b"bmV3a2V5");
+        assert_ne!(state, peers.state());
+        assert_eq!(peers.items[0].key_document_id(), 1);
+    }
+}
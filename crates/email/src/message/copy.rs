@@ -77,10 +77,14 @@ impl EmailCopy for Server {
         };
 
         // Check quota
-        match self
+        let has_quota = match self
             .has_available_quota(resource_token, metadata.size as u64)
             .await
         {
+            Ok(_) => self.has_available_message_quota(resource_token).await,
+            err => err,
+        };
+        match has_quota {
             Ok(_) => (),
             Err(err) => {
                 if err.matches(trc::EventType::Limit(trc::LimitEvent::Quota))
@@ -222,6 +226,14 @@ impl EmailCopy for Server {
             .caused_by(trc::location!())?
             .last_change_id(account_id)?;
 
+        // Warn the account owner as usage approaches the quota
+        if let Err(err) = self
+            .track_quota_usage(account_id, resource_token.quota)
+            .await
+        {
+            trc::error!(err.account_id(account_id).caused_by(trc::location!()));
+        }
+
         // Request FTS index
         self.notify_task_queue();
 
@@ -12,7 +12,7 @@ use mail_builder::{encoders::base64::base64_encode_mime, mime::make_boundary};
 use mail_parser::{Message, MimeHeaders, PartType, decoders::base64::base64_decode};
 use openpgp::{
     parse::Parse,
-    serialize::stream,
+    serialize::{SerializeInto, stream},
     types::{KeyFlags, SymmetricAlgorithm},
 };
 use rand::{RngCore, SeedableRng, rngs::StdRng};
@@ -457,6 +457,20 @@ impl EncryptMessage for Message<'_> {
     }
 }
 
+impl EncryptionParams {
+    // Returns the first OpenPGP certificate in binary form, without any secret key material
+    pub fn openpgp_public_key(&self) -> Option<Vec<u8>> {
+        if self.method == EncryptionMethod::PGP {
+            self.certs
+                .first()
+                .and_then(|cert| openpgp::Cert::from_bytes(cert).ok())
+                .and_then(|cert| cert.to_vec().ok())
+        } else {
+            None
+        }
+    }
+}
+
 impl ArchivedAlgorithm {
     fn key_size(&self) -> usize {
         match self {
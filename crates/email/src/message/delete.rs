@@ -6,7 +6,9 @@
 
 use super::metadata::MessageData;
 use crate::{cache::MessageCacheFetch, mailbox::*, message::metadata::MessageMetadata};
-use common::{KV_LOCK_PURGE_ACCOUNT, Server, storage::index::ObjectIndexBuilder};
+use common::{
+    KV_LOCK_PURGE_ACCOUNT, MessageStoreCache, Server, storage::index::ObjectIndexBuilder,
+};
 use groupware::calendar::storage::ItipAutoExpunge;
 use jmap_proto::types::collection::VanishedCollection;
 use jmap_proto::types::{collection::Collection, property::Property};
@@ -45,6 +47,12 @@ pub trait EmailDeletion: Sync + Send {
         &self,
         account_id: u32,
     ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn emails_purge_tombstoned_ids(
+        &self,
+        account_id: u32,
+        document_ids: Option<RoaringBitmap>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
 }
 
 impl EmailDeletion for Server {
@@ -55,6 +63,10 @@ impl EmailDeletion for Server {
         document_ids: RoaringBitmap,
     ) -> trc::Result<RoaringBitmap> {
         // Tombstone message and untag it from the mailboxes
+        let cache = self
+            .get_cached_messages(account_id)
+            .await
+            .caused_by(trc::location!())?;
         let mut deleted_ids = RoaringBitmap::new();
         batch
             .with_account_id(account_id)
@@ -74,12 +86,20 @@ impl EmailDeletion for Server {
                         (mailbox.mailbox_id.to_native(), mailbox.uid.to_native()),
                     );
                 }
+                let tombstone_mailbox = metadata
+                    .inner
+                    .mailboxes
+                    .first()
+                    .map(|mailbox| mailbox.mailbox_id.to_native());
                 batch
                     .update_document(document_id)
                     .custom(ObjectIndexBuilder::<_, ()>::new().with_current(metadata))
                     .caused_by(trc::location!())?
-                    .tag(Property::MailboxIds, TagValue::Id(TOMBSTONE_ID))
-                    .commit_point();
+                    .tag(Property::MailboxIds, TagValue::Id(TOMBSTONE_ID));
+                if let Some(mailbox_id) = tombstone_mailbox {
+                    tombstone_mailbox_path(self, batch, &cache, mailbox_id);
+                }
+                batch.commit_point();
 
                 deleted_ids.insert(document_id);
 
@@ -260,8 +280,16 @@ impl EmailDeletion for Server {
     }
 
     async fn emails_purge_tombstoned(&self, account_id: u32) -> trc::Result<()> {
-        // Obtain tombstoned messages
-        let tombstoned_ids = self
+        self.emails_purge_tombstoned_ids(account_id, None).await
+    }
+
+    async fn emails_purge_tombstoned_ids(
+        &self,
+        account_id: u32,
+        document_ids: Option<RoaringBitmap>,
+    ) -> trc::Result<()> {
+        // Obtain tombstoned messages, optionally limited to the given ids
+        let mut tombstoned_ids = self
             .core
             .storage
             .data
@@ -276,11 +304,28 @@ impl EmailDeletion for Server {
             })
             .await?
             .unwrap_or_default();
+        if let Some(document_ids) = document_ids {
+            tombstoned_ids &= document_ids;
+        }
 
         if tombstoned_ids.is_empty() {
             return Ok(());
         }
 
+        // Tombstones are kept while the account is under legal hold
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        if access_token.legal_hold.is_some() {
+            trc::event!(
+                Purge(trc::PurgeEvent::LegalHold),
+                AccountId = account_id,
+                Total = tombstoned_ids.len(),
+            );
+            return Ok(());
+        }
+
         trc::event!(
             Purge(trc::PurgeEvent::TombstoneCleanup),
             AccountId = account_id,
@@ -295,12 +340,7 @@ impl EmailDeletion for Server {
             .await?;
 
         // Obtain tenant id
-        let tenant_id = self
-            .get_access_token(account_id)
-            .await
-            .caused_by(trc::location!())?
-            .tenant
-            .map(|t| t.id);
+        let tenant_id = access_token.tenant.map(|t| t.id);
 
         // Delete messages
         let mut batch = BatchBuilder::new();
@@ -336,12 +376,30 @@ impl EmailDeletion for Server {
 
                 // Hold blob for undeletion
                 #[cfg(feature = "enterprise")]
-                self.core.hold_undelete(
-                    &mut batch,
-                    Collection::Email.into(),
-                    &BlobHash::from(&metadata.blob_hash),
-                    u32::from(metadata.size) as usize,
-                );
+                {
+                    let mailbox = self
+                        .core
+                        .storage
+                        .data
+                        .get_value::<String>(ValueKey {
+                            account_id,
+                            collection: Collection::Email.into(),
+                            document_id,
+                            class: ValueClass::Property(Property::MailboxIds.into()),
+                        })
+                        .await
+                        .caused_by(trc::location!())?;
+                    if mailbox.is_some() {
+                        batch.clear(Property::MailboxIds);
+                    }
+                    self.core.hold_undelete(
+                        &mut batch,
+                        Collection::Email.into(),
+                        &BlobHash::from(&metadata.blob_hash),
+                        u32::from(metadata.size) as usize,
+                        mailbox.as_deref(),
+                    );
+                }
 
                 // SPDX-SnippetEnd
 
@@ -368,3 +426,32 @@ impl EmailDeletion for Server {
         Ok(())
     }
 }
+
+/// Remembers the folder a message was deleted from so that the undelete
+/// API can restore it in place.
+pub fn tombstone_mailbox_path(
+    server: &Server,
+    batch: &mut BatchBuilder,
+    cache: &MessageStoreCache,
+    mailbox_id: u32,
+) {
+    // SPDX-SnippetBegin
+    // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+    // SPDX-License-Identifier: LicenseRef-SEL
+
+    #[cfg(feature = "enterprise")]
+    {
+        use crate::cache::mailbox::MailboxCacheAccess;
+
+        if server.core.is_undelete_enabled()
+            && let Some(mailbox) = cache.mailbox_by_id(&mailbox_id)
+        {
+            batch.set(Property::MailboxIds, mailbox.path.as_bytes().to_vec());
+        }
+    }
+
+    // SPDX-SnippetEnd
+
+    #[cfg(not(feature = "enterprise"))]
+    let _ = (server, batch, cache, mailbox_id);
+}
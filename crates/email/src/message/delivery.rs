@@ -4,18 +4,27 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::Server;
+use common::{Server, config::jmap::settings::SpecialUse, telemetry::metrics::usage::UsageEvent};
 
 use directory::Permission;
 use jmap_proto::types::{state::StateChange, type_state::DataType};
-use mail_parser::MessageParser;
+use mail_parser::{HeaderName, MessageParser};
 use std::{borrow::Cow, future::Future};
 use store::ahash::AHashMap;
+use trc::AddContext;
 use utils::BlobHash;
 
-use crate::{mailbox::INBOX_ID, sieve::ingest::SieveScriptIngest};
+use crate::{
+    cache::{MessageCacheFetch, mailbox::MailboxCacheAccess},
+    mailbox::{INBOX_ID, manage::MailboxFnc},
+    sieve::ingest::SieveScriptIngest,
+};
 
-use super::ingest::{EmailIngest, IngestEmail, IngestSource};
+use super::{
+    alias::DisposableAliasManager,
+    forward::{EmailForwarding, ForwardingSettings},
+    ingest::{EmailIngest, IngestEmail, IngestSource, IngestedEmail},
+};
 
 #[derive(Debug)]
 pub struct IngestMessage {
@@ -61,10 +70,7 @@ impl MailDelivery for Server {
     async fn deliver_message(&self, message: IngestMessage) -> LocalDeliveryResult {
         // Read message
         let raw_message = match self
-            .core
-            .storage
-            .blob
-            .get_blob(message.message_blob.as_slice(), 0..usize::MAX)
+            .get_queued_blob(message.message_blob.as_slice(), 0..usize::MAX)
             .await
         {
             Ok(Some(raw_message)) => raw_message,
@@ -148,57 +154,118 @@ impl MailDelivery for Server {
                     .assert_has_permission(Permission::EmailReceive)
                     .map(|_| token)
             }) {
-                Ok(access_token) => {
-                    // Check if there is an active sieve script
-                    match self.sieve_script_get_active(uid).await {
-                        Ok(None) => {
-                            // Ingest message
-                            self.email_ingest(IngestEmail {
-                                raw_message: &raw_message,
-                                message: MessageParser::new().parse(&raw_message),
-                                access_token: &access_token,
-                                mailbox_ids: vec![INBOX_ID],
-                                keywords: vec![],
-                                received_at: None,
-                                source: IngestSource::Smtp {
-                                    deliver_to: &rcpt,
-                                    is_sender_authenticated: message.sender_authenticated,
-                                },
-                                spam_classify: access_token
-                                    .has_permission(Permission::SpamFilterClassify),
-                                spam_train: self.email_bayes_can_train(&access_token),
-                                session_id: message.session_id,
+                Ok(access_token) => match self.get_active_forwarding(&access_token).await {
+                    Ok(forwarding) => {
+                        let keep_copy = forwarding
+                            .as_ref()
+                            .is_none_or(|forwarding| forwarding.keep_copy);
+                        let status = if !keep_copy {
+                            Ok(IngestedEmail {
+                                change_id: u64::MAX,
+                                is_spam: has_spam_verdict(self, &raw_message),
+                                ..Default::default()
                             })
-                            .await
-                        }
-                        Ok(Some(active_script)) => {
-                            self.sieve_script_ingest(
-                                &access_token,
-                                &raw_message,
+                        } else {
+                            // Check if there is an active sieve script
+                            match self.sieve_script_get_active(uid).await {
+                                Ok(None)
+                                    if !self.core.sieve.delivery_layers.applies_to(
+                                        &rcpt
+                                            .rsplit_once('@')
+                                            .map(|(_, domain)| domain.to_lowercase())
+                                            .unwrap_or_default(),
+                                    ) =>
+                                {
+                                    match subaddress_mailbox(self, uid, &rcpt, message.session_id)
+                                        .await
+                                    {
+                                        Ok(mailbox_id) => {
+                                            // Ingest message
+                                            self.email_ingest(IngestEmail {
+                                                raw_message: &raw_message,
+                                                message: MessageParser::new().parse(&raw_message),
+                                                access_token: &access_token,
+                                                mailbox_ids: vec![mailbox_id],
+                                                keywords: vec![],
+                                                received_at: None,
+                                                source: IngestSource::Smtp {
+                                                    deliver_to: &rcpt,
+                                                    is_sender_authenticated: message
+                                                        .sender_authenticated,
+                                                },
+                                                spam_classify: access_token
+                                                    .has_permission(Permission::SpamFilterClassify),
+                                                spam_train: self
+                                                    .email_bayes_can_train(&access_token),
+                                                session_id: message.session_id,
+                                            })
+                                            .await
+                                        }
+                                        Err(err) => Err(err),
+                                    }
+                                }
+                                Ok(active_script) => {
+                                    self.sieve_script_ingest(
+                                        &access_token,
+                                        &raw_message,
+                                        &message.sender_address,
+                                        message.sender_authenticated,
+                                        &rcpt,
+                                        message.session_id,
+                                        active_script,
+                                        &mut result.autogenerated,
+                                    )
+                                    .await
+                                }
+                                Err(err) => Err(err),
+                            }
+                        };
+
+                        // Forward message once the spam verdict is known
+                        if let Some(forwarding) = forwarding
+                            && status
+                                .as_ref()
+                                .is_ok_and(|ingested_message| !ingested_message.is_spam)
+                        {
+                            forward_message(
+                                self,
                                 &message.sender_address,
-                                message.sender_authenticated,
                                 &rcpt,
+                                forwarding,
+                                &raw_message,
                                 message.session_id,
-                                active_script,
                                 &mut result.autogenerated,
-                            )
-                            .await
+                            );
                         }
-                        Err(err) => Err(err),
+
+                        status
                     }
-                }
+                    Err(err) => Err(err),
+                },
 
                 Err(err) => Err(err),
             };
 
             let status = match status {
                 Ok(ingested_message) => {
+                    self.record_usage(&rcpt, UsageEvent::MessageReceived).await;
+
+                    // Keep track of the messages received by disposable aliases
+                    if let Err(err) = self.disposable_alias_delivered(uid, &rcpt).await {
+                        trc::error!(
+                            err.ctx(trc::Key::To, rcpt.to_string())
+                                .span_id(message.session_id)
+                                .caused_by(trc::location!())
+                        );
+                    }
+
                     // Notify state change
                     if ingested_message.change_id != u64::MAX {
                         self.broadcast_state_change(
                             StateChange::new(uid, ingested_message.change_id)
                                 .with_change(DataType::EmailDelivery)
                                 .with_change(DataType::Email)
+                                .with_change(DataType::Quota)
                                 .with_change(DataType::Mailbox)
                                 .with_change(DataType::Thread),
                         )
@@ -264,3 +331,141 @@ impl MailDelivery for Server {
         result
     }
 }
+
+// Messages sent to a subaddress are filed into the folder matching its detail, if any
+async fn subaddress_mailbox(
+    server: &Server,
+    account_id: u32,
+    rcpt: &str,
+    session_id: u64,
+) -> trc::Result<u32> {
+    if !server.core.jmap.subaddress_folder {
+        return Ok(INBOX_ID);
+    }
+    let Some(folder) = server.subaddress_detail(rcpt, session_id).await else {
+        return Ok(INBOX_ID);
+    };
+
+    // Messages are only filed into user folders, never into Junk, Trash or other system folders
+    let cache = server
+        .get_cached_messages(account_id)
+        .await
+        .caused_by(trc::location!())?;
+    let mailbox_id = if let Some(mailbox) = cache.mailbox_by_path(folder) {
+        Some(mailbox.document_id).filter(|_| mailbox.role == SpecialUse::None)
+    } else if server.core.jmap.subaddress_folder_create
+        && !folder.contains('/')
+        && cache.mailboxes.items.len() < server.core.jmap.subaddress_folder_max
+    {
+        // Only top-level folders are created, up to a maximum number of folders per account
+        server
+            .mailbox_create_path(account_id, folder)
+            .await
+            .caused_by(trc::location!())?
+    } else {
+        None
+    };
+
+    Ok(mailbox_id.unwrap_or(INBOX_ID))
+}
+
+// Messages that are not kept are forwarded according to the verdict of the SMTP spam filter
+pub(crate) fn has_spam_verdict(server: &Server, raw_message: &[u8]) -> bool {
+    server
+        .core
+        .spam
+        .headers
+        .status
+        .as_ref()
+        .is_some_and(|name| {
+            MessageParser::new()
+                .parse_headers(raw_message)
+                .is_some_and(|message| {
+                    let headers = message.root_part().headers.iter();
+
+                    // Tests inject their verdict below the one added by the spam filter
+                    #[cfg(feature = "test_mode")]
+                    let mut headers = headers.rev();
+                    #[cfg(not(feature = "test_mode"))]
+                    let mut headers = headers;
+
+                    headers
+                        .find(|h| h.name.as_str().eq_ignore_ascii_case(name.as_str()))
+                        .and_then(|v| v.value.as_text())
+                        .is_some_and(|v| v.contains("Yes"))
+                })
+        })
+}
+
+fn forward_message(
+    server: &Server,
+    sender_address: &str,
+    rcpt: &str,
+    forwarding: ForwardingSettings,
+    raw_message: &[u8],
+    session_id: u64,
+    autogenerated: &mut Vec<AutogeneratedMessage>,
+) {
+    // Messages are never forwarded back to the recipient
+    let recipients = forwarding
+        .addresses
+        .into_iter()
+        .filter(|address| address != rcpt)
+        .collect::<Vec<_>>();
+    if recipients.is_empty() {
+        return;
+    }
+
+    // Messages already forwarded by this recipient or with too many hops are dropped
+    let (num_hops, is_loop) = MessageParser::new()
+        .parse_headers(raw_message)
+        .map(|message| {
+            let headers = &message.root_part().headers;
+            (
+                headers
+                    .iter()
+                    .filter(|h| h.name == HeaderName::Received)
+                    .count(),
+                headers.iter().any(|h| {
+                    h.name.as_str().eq_ignore_ascii_case("X-Loop")
+                        && h.value
+                            .as_text()
+                            .is_some_and(|v| v.trim().eq_ignore_ascii_case(rcpt))
+                }),
+            )
+        })
+        .unwrap_or_default();
+    if is_loop || num_hops >= server.core.jmap.forwarding_max_hops {
+        trc::event!(
+            MessageIngest(trc::MessageIngestEvent::ForwardLoop),
+            SpanId = session_id,
+            Details = rcpt.to_string(),
+            Total = num_hops,
+        );
+        return;
+    }
+
+    let sender_address = server.forwarding_return_path(sender_address, rcpt);
+    trc::event!(
+        MessageIngest(trc::MessageIngestEvent::Forwarded),
+        SpanId = session_id,
+        From = sender_address.clone(),
+        Details = rcpt.to_string(),
+        To = recipients
+            .iter()
+            .map(|r| trc::Value::String(r.as_str().into()))
+            .collect::<Vec<_>>(),
+        Size = raw_message.len(),
+    );
+
+    let mut message = Vec::with_capacity(raw_message.len() + rcpt.len() + 10);
+    message.extend_from_slice(b"X-Loop: ");
+    message.extend_from_slice(rcpt.as_bytes());
+    message.extend_from_slice(b"\r\n");
+    message.extend_from_slice(raw_message);
+    autogenerated.push(AutogeneratedMessage {
+        sender_address,
+        recipients,
+        message,
+    });
+}
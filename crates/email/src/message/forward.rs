@@ -0,0 +1,121 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::Permission;
+use jmap_proto::types::{collection::Collection, property::Property};
+use std::future::Future;
+use store::{
+    Serialize,
+    write::{Archiver, BatchBuilder, now},
+};
+use trc::AddContext;
+
+#[derive(
+    rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq, Default,
+)]
+#[rkyv(derive(Debug))]
+pub struct ForwardingSettings {
+    pub addresses: Vec<String>,
+    pub keep_copy: bool,
+    pub expires: Option<u64>,
+}
+
+impl ForwardingSettings {
+    pub fn is_active(&self, now: u64) -> bool {
+        !self.addresses.is_empty() && self.expires.is_none_or(|expires| expires > now)
+    }
+}
+
+pub trait EmailForwarding: Sync + Send {
+    fn get_forwarding(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<ForwardingSettings>> + Send;
+
+    fn set_forwarding(
+        &self,
+        account_id: u32,
+        settings: ForwardingSettings,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn get_active_forwarding(
+        &self,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<Option<ForwardingSettings>>> + Send;
+
+    fn forwarding_return_path(&self, return_path: &str, forwarder: &str) -> String;
+}
+
+impl EmailForwarding for Server {
+    async fn get_forwarding(&self, account_id: u32) -> trc::Result<ForwardingSettings> {
+        self.get_archive_by_property(account_id, Collection::Principal, 0, Property::Forwarding)
+            .await
+            .caused_by(trc::location!())?
+            .map(|archive| archive.deserialize::<ForwardingSettings>())
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    async fn set_forwarding(
+        &self,
+        account_id: u32,
+        settings: ForwardingSettings,
+    ) -> trc::Result<()> {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Principal)
+            .update_document(0);
+        if !settings.addresses.is_empty() {
+            batch.set(
+                Property::Forwarding,
+                Archiver::new(settings)
+                    .serialize()
+                    .caused_by(trc::location!())?,
+            );
+        } else {
+            batch.clear(Property::Forwarding);
+        }
+
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())
+            .map(|_| ())
+    }
+
+    async fn get_active_forwarding(
+        &self,
+        access_token: &AccessToken,
+    ) -> trc::Result<Option<ForwardingSettings>> {
+        // Revoking the permission stops any forwarding already configured
+        if !self.core.jmap.forwarding_enable
+            || !access_token.has_permission(Permission::ManageForwarding)
+        {
+            return Ok(None);
+        }
+
+        self.get_forwarding(access_token.primary_id())
+            .await
+            .map(|settings| Some(settings).filter(|settings| settings.is_active(now())))
+    }
+
+    fn forwarding_return_path(&self, return_path: &str, forwarder: &str) -> String {
+        // Rewriting the return path keeps forwarded messages passing SPF checks
+        if let Some(srs) = &self.core.smtp.session.srs
+            && !return_path.is_empty()
+        {
+            srs.forward(
+                return_path,
+                forwarder.rsplit_once('@').map_or("", |(_, domain)| domain),
+                now(),
+            )
+        } else {
+            return_path.to_string()
+        }
+    }
+}
@@ -5,8 +5,11 @@
  */
 
 use super::{
+    autocrypt::AutocryptManager,
     crypto::{EncryptMessage, EncryptMessageError},
     index::{MAX_SORT_FIELD_LENGTH, TrimTextValue},
+    mdn::{EmailMdn, mdn_request_address},
+    tnef::convert_tnef_attachments,
 };
 use crate::{
     cache::{MessageCacheFetch, email::MessageCacheAccess},
@@ -17,7 +20,11 @@ use crate::{
         metadata::MessageData,
     },
 };
-use common::{IDX_EMAIL, Server, auth::AccessToken, storage::index::ObjectIndexBuilder};
+use common::{
+    IDX_EMAIL, Server,
+    auth::{AccessToken, ResourceToken},
+    storage::index::ObjectIndexBuilder,
+};
 use directory::Permission;
 use groupware::{
     calendar::itip::{ItipIngest, ItipIngestError},
@@ -36,7 +43,9 @@ use mail_parser::{
     parsers::fields::thread::thread_name,
 };
 use spam_filter::{
-    SpamFilterInput, analysis::init::SpamFilterInit, modules::bayes::BayesClassifier,
+    SpamFilterInput,
+    analysis::init::SpamFilterInit,
+    modules::{bayes::BayesClassifier, html::strip_message_trackers},
 };
 use std::future::Future;
 use std::{
@@ -62,6 +71,7 @@ pub struct IngestedEmail {
     pub blob_id: BlobId,
     pub size: usize,
     pub imap_uids: Vec<u32>,
+    pub is_spam: bool,
 }
 
 pub struct IngestEmail<'x> {
@@ -108,6 +118,10 @@ pub trait EmailIngest: Sync + Send {
         mailbox_id: u32,
     ) -> impl Future<Output = trc::Result<u32>> + Send;
     fn email_bayes_can_train(&self, access_token: &AccessToken) -> bool;
+    fn has_available_message_quota(
+        &self,
+        resource_token: &ResourceToken,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
 }
 
 pub enum ThreadResult {
@@ -128,6 +142,9 @@ impl EmailIngest for Server {
         self.has_available_quota(&resource_token, raw_message_len)
             .await
             .caused_by(trc::location!())?;
+        self.has_available_message_quota(&resource_token)
+            .await
+            .caused_by(trc::location!())?;
 
         // Parse message
         let mut raw_message = Cow::from(params.raw_message);
@@ -159,6 +176,26 @@ impl EmailIngest for Server {
                     });
                 }
 
+                // Suppress read receipt requests from external senders
+                if self.core.jmap.mdn_suppress_external
+                    && !params.keywords.contains(&Keyword::MdnSent)
+                    && let Some(address) = mdn_request_address(&message)
+                    && (!is_sender_authenticated
+                        || !self
+                            .is_local_mdn_request(&address)
+                            .await
+                            .caused_by(trc::location!())?)
+                {
+                    params.keywords.push(Keyword::MdnSent);
+
+                    trc::event!(
+                        MessageIngest(trc::MessageIngestEvent::MdnSuppressed),
+                        SpanId = params.session_id,
+                        AccountId = account_id,
+                        To = address,
+                    );
+                }
+
                 // Spam classification and training
                 if params.spam_classify
                     && self.core.spam.enabled
@@ -333,10 +370,8 @@ impl EmailIngest for Server {
                                         )
                                         .await
                                     {
-                                        Ok(message) => {
-                                            if let Some(message) = message {
-                                                itip_messages.push(message);
-                                            }
+                                        Ok(messages) => {
+                                            itip_messages.extend(messages);
                                             trc::event!(
                                                 Calendar(trc::CalendarEvent::ItipMessageReceived),
                                                 SpanId = params.session_id,
@@ -384,6 +419,21 @@ impl EmailIngest for Server {
                         }
                     }
                 }
+
+                // Store the Autocrypt keys of peers that passed DMARC or authenticated
+                if self.core.jmap.autocrypt_enable
+                    && !is_spam
+                    && is_sender_authenticated
+                    && let Err(err) = self
+                        .autocrypt_ingest(
+                            account_id,
+                            &message,
+                            params.received_at.unwrap_or_else(now),
+                        )
+                        .await
+                {
+                    trc::error!(err.span_id(params.session_id).caused_by(trc::location!()));
+                }
             }
             IngestSource::Jmap | IngestSource::Imap
                 if params.spam_train && self.core.spam.enabled =>
@@ -478,6 +528,7 @@ impl EmailIngest for Server {
                         blob_id: BlobId::default(),
                         imap_uids: Vec::new(),
                         size: 0,
+                        is_spam,
                     });
                 }
             }
@@ -532,6 +583,47 @@ impl EmailIngest for Server {
             root_part.headers = extra_headers_parsed;
         }
 
+        // Unpack TNEF (winmail.dat) attachments
+        if self.core.jmap.tnef_convert
+            && params.source.is_smtp()
+            && !message.is_encrypted()
+            && let Some(new_raw_message) =
+                convert_tnef_attachments(&message, self.core.jmap.tnef_keep_original)
+        {
+            raw_message = Cow::from(new_raw_message);
+            raw_message_len = raw_message.len() as u64;
+            message = MessageParser::default()
+                .parse(raw_message.as_ref())
+                .ok_or_else(|| {
+                    trc::EventType::MessageIngest(trc::MessageIngestEvent::Error)
+                        .ctx(trc::Key::Code, 550)
+                        .ctx(
+                            trc::Key::Reason,
+                            "Failed to parse rewritten e-mail message.",
+                        )
+                })?;
+        }
+
+        // Strip tracking pixels
+        if self.core.spam.strip_trackers
+            && params.source.is_smtp()
+            && !message.is_encrypted()
+            && let Some(new_raw_message) = strip_message_trackers(&message)
+        {
+            raw_message = Cow::from(new_raw_message);
+            raw_message_len = raw_message.len() as u64;
+            message = MessageParser::default()
+                .parse(raw_message.as_ref())
+                .ok_or_else(|| {
+                    trc::EventType::MessageIngest(trc::MessageIngestEvent::Error)
+                        .ctx(trc::Key::Code, 550)
+                        .ctx(
+                            trc::Key::Reason,
+                            "Failed to parse rewritten e-mail message.",
+                        )
+                })?;
+        }
+
         // Encrypt message
         let do_encrypt = match params.source {
             IngestSource::Jmap | IngestSource::Imap => {
@@ -593,6 +685,20 @@ impl EmailIngest for Server {
             }
         }
 
+        // Drop custom keywords exceeding the account or mailbox limits
+        if params.keywords.iter().any(|keyword| keyword.id().is_err()) {
+            let cache = self
+                .get_cached_messages(account_id)
+                .await
+                .caused_by(trc::location!())?;
+            while let Some(keyword) = cache
+                .exceeds_keyword_limits(&params.keywords, &params.mailbox_ids, &self.core.jmap)
+                .cloned()
+            {
+                params.keywords.retain(|k| k != &keyword);
+            }
+        }
+
         // Store blob
         let blob_id = self
             .put_blob(account_id, raw_message.as_ref(), false)
@@ -684,6 +790,14 @@ impl EmailIngest for Server {
             .last_change_id(account_id)?;
         let id = Id::from_parts(thread_id, document_id);
 
+        // Warn the account owner as usage approaches the quota
+        if let Err(err) = self
+            .track_quota_usage(account_id, resource_token.quota)
+            .await
+        {
+            trc::error!(err.account_id(account_id).caused_by(trc::location!()));
+        }
+
         // Request FTS index
         self.notify_task_queue();
 
@@ -723,6 +837,7 @@ impl EmailIngest for Server {
             },
             size: raw_message_len as usize,
             imap_uids,
+            is_spam,
         })
     }
 
@@ -966,6 +1081,29 @@ impl EmailIngest for Server {
             bayes.account_classify && access_token.has_permission(Permission::SpamFilterTrain)
         })
     }
+
+    async fn has_available_message_quota(&self, resource_token: &ResourceToken) -> trc::Result<()> {
+        let max_messages = resource_token.message_quota;
+        if max_messages != 0 {
+            let total_messages = self
+                .get_cached_messages(resource_token.account_id)
+                .await
+                .caused_by(trc::location!())?
+                .emails
+                .items
+                .len() as u64;
+
+            if total_messages >= max_messages {
+                return Err(trc::LimitEvent::Quota
+                    .into_err()
+                    .ctx(trc::Key::Limit, max_messages)
+                    .ctx(trc::Key::Total, total_messages)
+                    .details("Message count quota exceeded"));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl IngestSource<'_> {
@@ -0,0 +1,170 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::metadata::{ArchivedMessageData, MessageData};
+use common::{Server, storage::index::ObjectIndexBuilder};
+use jmap_proto::types::{collection::Collection, keyword::Keyword, property::Property};
+use mail_parser::{HeaderName, Message, parsers::MessageStream};
+use std::future::Future;
+use store::{
+    Serialize,
+    write::{Archiver, BatchBuilder, TaskQueueClass, ValueClass, now},
+};
+use trc::AddContext;
+
+#[derive(
+    rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, PartialEq, Eq, Default,
+)]
+#[rkyv(derive(Debug))]
+pub struct MdnSettings {
+    pub auto_send: bool,
+}
+
+pub trait EmailMdn: Sync + Send {
+    fn get_mdn_settings(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<MdnSettings>> + Send;
+
+    fn set_mdn_settings(
+        &self,
+        account_id: u32,
+        settings: MdnSettings,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn is_local_mdn_request(&self, address: &str)
+    -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn set_mdn_sent(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl EmailMdn for Server {
+    async fn get_mdn_settings(&self, account_id: u32) -> trc::Result<MdnSettings> {
+        self.get_archive_by_property(account_id, Collection::Principal, 0, Property::MdnSettings)
+            .await
+            .caused_by(trc::location!())?
+            .map(|archive| archive.deserialize::<MdnSettings>())
+            .transpose()
+            .map(Option::unwrap_or_default)
+    }
+
+    async fn set_mdn_settings(&self, account_id: u32, settings: MdnSettings) -> trc::Result<()> {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Principal)
+            .update_document(0);
+        if settings != MdnSettings::default() {
+            batch.set(
+                Property::MdnSettings,
+                Archiver::new(settings)
+                    .serialize()
+                    .caused_by(trc::location!())?,
+            );
+        } else {
+            batch.clear(Property::MdnSettings);
+        }
+
+        self.store()
+            .write(batch.build_all())
+            .await
+            .caused_by(trc::location!())
+            .map(|_| ())
+    }
+
+    async fn is_local_mdn_request(&self, address: &str) -> trc::Result<bool> {
+        self.core
+            .storage
+            .directory
+            .is_local_domain(address.rsplit_once('@').map_or("", |(_, domain)| domain))
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn set_mdn_sent(&self, account_id: u32, document_id: u32) -> trc::Result<bool> {
+        let Some(data_) = self
+            .get_archive(account_id, Collection::Email, document_id)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(true);
+        };
+        let data = data_
+            .to_unarchived::<MessageData>()
+            .caused_by(trc::location!())?;
+        let mut new_data = data
+            .deserialize::<MessageData>()
+            .caused_by(trc::location!())?;
+        if !new_data.add_keyword(Keyword::MdnSent) {
+            return Ok(true);
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Email)
+            .update_document(document_id)
+            .custom(
+                ObjectIndexBuilder::new()
+                    .with_current(data)
+                    .with_changes(new_data),
+            )
+            .caused_by(trc::location!())?;
+
+        match self.commit_batch(batch).await {
+            Ok(_) => Ok(true),
+            // The message was modified concurrently, retry later
+            Err(err) if err.is_assertion_failure() => Ok(false),
+            Err(err) => Err(err.caused_by(trc::location!())),
+        }
+    }
+}
+
+// Returns the address a read receipt was requested to be sent to, if any
+pub fn mdn_request_address(message: &Message<'_>) -> Option<String> {
+    let header = message.root_part().headers.iter().find(|header| {
+        matches!(&header.name, HeaderName::Other(name)
+            if name.eq_ignore_ascii_case("Disposition-Notification-To"))
+    })?;
+
+    MessageStream::new(
+        message
+            .raw_message
+            .get(header.offset_start as usize..header.offset_end as usize)?,
+    )
+    .parse_address()
+    .as_address()?
+    .first()?
+    .address()
+    .map(|address| address.trim().to_lowercase())
+    .filter(|address| address.contains('@'))
+}
+
+// Reading a message for the first time queues a task that sends the receipt, if one was requested.
+// The batch has to point to the message being updated.
+pub fn queue_mdn_on_read(
+    batch: &mut BatchBuilder,
+    current: &ArchivedMessageData,
+    changes: &MessageData,
+) -> bool {
+    if changes
+        .added_keywords(current)
+        .any(|keyword| keyword == &Keyword::Seen)
+        && !changes.has_keyword(&Keyword::MdnSent)
+    {
+        batch.set(
+            ValueClass::TaskQueue(TaskQueueClass::SendMdn { due: now() }),
+            vec![],
+        );
+        true
+    } else {
+        false
+    }
+}
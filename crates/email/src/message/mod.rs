@@ -4,11 +4,18 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod alias;
+pub mod autocrypt;
 pub mod bayes;
 pub mod copy;
 pub mod crypto;
 pub mod delete;
 pub mod delivery;
+pub mod forward;
 pub mod index;
 pub mod ingest;
+pub mod mdn;
 pub mod metadata;
+pub mod query;
+pub mod snooze;
+pub mod tnef;
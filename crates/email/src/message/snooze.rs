@@ -0,0 +1,161 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{ingest::EmailIngest, metadata::MessageData};
+use crate::mailbox::{INBOX_ID, UidMailbox};
+use common::{Server, storage::index::ObjectIndexBuilder};
+use jmap_proto::types::{
+    collection::{Collection, SyncCollection, VanishedCollection},
+    property::Property,
+};
+use std::future::Future;
+use store::{
+    ValueKey,
+    write::{BatchBuilder, TaskQueueClass, ValueClass},
+};
+use trc::AddContext;
+
+pub trait EmailSnooze: Sync + Send {
+    fn get_snoozed_until(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> impl Future<Output = trc::Result<Option<u64>>> + Send;
+
+    fn unsnooze_email(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        due: u64,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl EmailSnooze for Server {
+    async fn get_snoozed_until(
+        &self,
+        account_id: u32,
+        document_id: u32,
+    ) -> trc::Result<Option<u64>> {
+        self.store()
+            .get_value::<u64>(ValueKey::<ValueClass>::property(
+                account_id,
+                Collection::Email,
+                document_id,
+                Property::SnoozedUntil,
+            ))
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn unsnooze_email(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        due: u64,
+    ) -> trc::Result<bool> {
+        // Tasks left behind by a cancelled or rescheduled snooze are discarded
+        if self.get_snoozed_until(account_id, document_id).await? != Some(due) {
+            return Ok(true);
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Email)
+            .update_document(document_id)
+            .clear(Property::SnoozedUntil);
+
+        let Some(data_) = self
+            .get_archive(account_id, Collection::Email, document_id)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            // The message was deleted while snoozed
+            self.store()
+                .write(batch.build_all())
+                .await
+                .caused_by(trc::location!())?;
+            return Ok(true);
+        };
+        let data = data_
+            .to_unarchived::<MessageData>()
+            .caused_by(trc::location!())?;
+        let mut new_data = data
+            .deserialize::<MessageData>()
+            .caused_by(trc::location!())?;
+
+        // Move the message back to the Inbox
+        let inbox = match new_data
+            .mailboxes
+            .iter()
+            .find(|mailbox| mailbox.mailbox_id == INBOX_ID)
+        {
+            Some(inbox) => *inbox,
+            None => UidMailbox::new(
+                INBOX_ID,
+                self.assign_imap_uid(account_id, INBOX_ID)
+                    .await
+                    .caused_by(trc::location!())?,
+            ),
+        };
+        let removed_mailboxes = new_data
+            .mailboxes
+            .iter()
+            .filter(|mailbox| mailbox.mailbox_id != INBOX_ID)
+            .map(|mailbox| (mailbox.mailbox_id, mailbox.uid))
+            .collect::<Vec<_>>();
+        new_data.set_mailboxes(vec![inbox]);
+
+        batch
+            .custom(
+                ObjectIndexBuilder::new()
+                    .with_current(data)
+                    .with_changes(new_data),
+            )
+            .caused_by(trc::location!())?
+            .log_container_property_change(SyncCollection::Email, INBOX_ID);
+        for (mailbox_id, uid) in removed_mailboxes {
+            batch
+                .log_container_property_change(SyncCollection::Email, mailbox_id)
+                .log_vanished_item(VanishedCollection::Email, (mailbox_id, uid));
+        }
+
+        match self.commit_batch(batch).await {
+            Ok(_) => {
+                trc::event!(
+                    TaskQueue(trc::TaskQueueEvent::MessageUnsnoozed),
+                    AccountId = account_id,
+                    DocumentId = document_id,
+                );
+                Ok(true)
+            }
+            // The message was modified concurrently, retry later
+            Err(err) if err.is_assertion_failure() => Ok(false),
+            Err(err) => Err(err.caused_by(trc::location!())),
+        }
+    }
+}
+
+// Snoozing is tracked per message along with a task that fires once the snooze expires.
+// The batch has to point to the message being updated.
+pub fn set_snoozed_until(batch: &mut BatchBuilder, current: Option<u64>, until: Option<u64>) {
+    if let Some(current) = current {
+        batch.clear(ValueClass::TaskQueue(TaskQueueClass::Unsnooze {
+            due: current,
+        }));
+    }
+
+    if let Some(until) = until {
+        batch
+            .set(Property::SnoozedUntil, until.to_be_bytes().to_vec())
+            .set(
+                ValueClass::TaskQueue(TaskQueueClass::Unsnooze { due: until }),
+                vec![],
+            );
+    } else {
+        batch.clear(Property::SnoozedUntil);
+    }
+}
@@ -0,0 +1,391 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use mail_builder::mime::{BodyPart, MimePart};
+use mail_parser::{Message, MimeHeaders, PartType};
+use utils::splice_bytes;
+
+const TNEF_SIGNATURE: u32 = 0x223E9F78;
+const LVL_ATTACHMENT: u8 = 0x02;
+
+const ATT_ATTACH_REND_DATA: u32 = 0x0006_9002;
+const ATT_ATTACH_TITLE: u32 = 0x0001_8010;
+const ATT_ATTACH_DATA: u32 = 0x0006_800F;
+const ATT_ATTACHMENT: u32 = 0x0006_9005;
+
+const PR_ATTACH_LONG_FILENAME: u16 = 0x3707;
+const PR_ATTACH_MIME_TAG: u16 = 0x370E;
+
+const PT_SHORT: u16 = 0x0002;
+const PT_LONG: u16 = 0x0003;
+const PT_FLOAT: u16 = 0x0004;
+const PT_DOUBLE: u16 = 0x0005;
+const PT_CURRENCY: u16 = 0x0006;
+const PT_APPTIME: u16 = 0x0007;
+const PT_ERROR: u16 = 0x000A;
+const PT_BOOLEAN: u16 = 0x000B;
+const PT_OBJECT: u16 = 0x000D;
+const PT_I8: u16 = 0x0014;
+const PT_STRING8: u16 = 0x001E;
+const PT_UNICODE: u16 = 0x001F;
+const PT_SYSTIME: u16 = 0x0040;
+const PT_CLSID: u16 = 0x0048;
+const PT_BINARY: u16 = 0x0102;
+const MV_FLAG: u16 = 0x1000;
+
+const MAX_ATTACHMENTS: usize = 256;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TnefAttachment {
+    pub name: Option<String>,
+    pub content_type: Option<String>,
+    pub contents: Vec<u8>,
+}
+
+// Replaces winmail.dat parts with the attachments they contain, returns None if
+// the message has no TNEF parts that could be decoded
+pub fn convert_tnef_attachments(message: &Message<'_>, keep_original: bool) -> Option<Vec<u8>> {
+    let raw_message = message.raw_message();
+    let mut edits: Vec<(usize, usize, Vec<u8>)> = Vec::new();
+
+    for (part_id, part) in message.parts.iter().enumerate() {
+        // Single part messages can't be replaced without rewriting the root headers
+        if part_id == 0 || !matches!(part.body, PartType::Binary(_) | PartType::InlineBinary(_)) {
+            continue;
+        }
+        let is_tnef = part.content_type().is_some_and(|ct| {
+            ct.ctype().eq_ignore_ascii_case("application")
+                && ct.subtype().is_some_and(|st| {
+                    st.eq_ignore_ascii_case("ms-tnef") || st.eq_ignore_ascii_case("vnd.ms-tnef")
+                })
+        }) || part
+            .attachment_name()
+            .is_some_and(|name| name.eq_ignore_ascii_case("winmail.dat"));
+        if !is_tnef {
+            continue;
+        }
+        let Some(attachments) = parse_tnef(part.contents()).filter(|a| !a.is_empty()) else {
+            continue;
+        };
+
+        let mut parts = attachments
+            .into_iter()
+            .map(|attachment| {
+                MimePart::new(
+                    attachment
+                        .content_type
+                        .unwrap_or_else(|| "application/octet-stream".to_string()),
+                    BodyPart::Binary(attachment.contents.into()),
+                )
+                .attachment(attachment.name.unwrap_or_else(|| "attachment".to_string()))
+            })
+            .collect::<Vec<_>>();
+        if keep_original {
+            parts.push(
+                MimePart::new(
+                    "application/ms-tnef",
+                    BodyPart::Binary(part.contents().into()),
+                )
+                .attachment(part.attachment_name().unwrap_or("winmail.dat").to_string()),
+            );
+        }
+
+        let mut bytes = Vec::with_capacity(part.contents().len() * 4 / 3 + 256);
+        MimePart::new("multipart/mixed", BodyPart::Multipart(parts))
+            .write_part(&mut bytes)
+            .ok()?;
+        edits.push((part.offset_header as usize, part.offset_end as usize, bytes));
+    }
+
+    if edits.is_empty() {
+        return None;
+    }
+
+    splice_bytes(raw_message, edits)
+}
+
+pub fn parse_tnef(bytes: &[u8]) -> Option<Vec<TnefAttachment>> {
+    let mut reader = Reader::new(bytes);
+    if reader.u32()? != TNEF_SIGNATURE {
+        return None;
+    }
+    reader.u16()?;
+
+    let mut attachments: Vec<TnefAttachment> = Vec::new();
+    while !reader.is_empty() {
+        let level = reader.u8()?;
+        let id = reader.u32()?;
+        let len = reader.u32()? as usize;
+        let data = reader.bytes(len)?;
+        reader.u16()?;
+
+        if level != LVL_ATTACHMENT {
+            continue;
+        }
+
+        match id {
+            ATT_ATTACH_REND_DATA => {
+                if attachments.len() == MAX_ATTACHMENTS {
+                    break;
+                }
+                attachments.push(TnefAttachment::default());
+            }
+            ATT_ATTACH_TITLE => {
+                if let Some(attachment) = attachments.last_mut()
+                    && attachment.name.is_none()
+                {
+                    attachment.name = decode_string8(data);
+                }
+            }
+            ATT_ATTACH_DATA => {
+                if let Some(attachment) = attachments.last_mut() {
+                    attachment.contents = data.to_vec();
+                }
+            }
+            ATT_ATTACHMENT => {
+                if let Some(attachment) = attachments.last_mut() {
+                    parse_attachment_props(data, attachment);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    attachments.retain(|attachment| !attachment.contents.is_empty());
+    Some(attachments)
+}
+
+// Extracts the long file name and MIME type from the MAPI properties of an attachment,
+// stops at the first property it can't decode
+fn parse_attachment_props(bytes: &[u8], attachment: &mut TnefAttachment) -> Option<()> {
+    let mut reader = Reader::new(bytes);
+    let count = reader.u32()?;
+
+    for _ in 0..count {
+        let prop_type = reader.u16()?;
+        let prop_id = reader.u16()?;
+
+        // Named properties are preceded by their GUID and name
+        if prop_id >= 0x8000 {
+            reader.bytes(16)?;
+            if reader.u32()? == 0 {
+                reader.u32()?;
+            } else {
+                let len = reader.u32()? as usize;
+                reader.bytes(padded(len))?;
+            }
+        }
+
+        let is_multi = prop_type & MV_FLAG != 0;
+        let base_type = prop_type & !MV_FLAG;
+        let fixed_size = match base_type {
+            PT_SHORT | PT_LONG | PT_FLOAT | PT_ERROR | PT_BOOLEAN => Some(4usize),
+            PT_DOUBLE | PT_CURRENCY | PT_APPTIME | PT_I8 | PT_SYSTIME => Some(8),
+            PT_CLSID => Some(16),
+            PT_STRING8 | PT_UNICODE | PT_BINARY | PT_OBJECT => None,
+            _ => return None,
+        };
+
+        if let Some(size) = fixed_size {
+            let values = if is_multi { reader.u32()? as usize } else { 1 };
+            reader.bytes(size.checked_mul(values)?)?;
+        } else {
+            let values = reader.u32()? as usize;
+            for _ in 0..values {
+                let len = reader.u32()? as usize;
+                let data = reader.bytes(padded(len))?.get(..len)?;
+                let value = match base_type {
+                    PT_STRING8 => decode_string8(data),
+                    PT_UNICODE => decode_unicode(data),
+                    _ => None,
+                };
+                match prop_id {
+                    PR_ATTACH_LONG_FILENAME if value.is_some() => attachment.name = value,
+                    PR_ATTACH_MIME_TAG if value.is_some() => attachment.content_type = value,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Some(())
+}
+
+fn decode_string8(bytes: &[u8]) -> Option<String> {
+    let bytes = bytes.split(|&ch| ch == 0).next().unwrap_or_default();
+    let value = String::from_utf8(bytes.to_vec())
+        .unwrap_or_else(|_| bytes.iter().map(|&ch| char::from(ch)).collect());
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn decode_unicode(bytes: &[u8]) -> Option<String> {
+    let value = char::decode_utf16(
+        bytes
+            .chunks_exact(2)
+            .map(|ch| u16::from_le_bytes([ch[0], ch[1]]))
+            .take_while(|&ch| ch != 0),
+    )
+    .map(|ch| ch.unwrap_or(char::REPLACEMENT_CHARACTER))
+    .collect::<String>();
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(4) * 4
+}
+
+struct Reader<'x> {
+    bytes: &'x [u8],
+    pos: usize,
+}
+
+impl<'x> Reader<'x> {
+    fn new(bytes: &'x [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'x [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.bytes(1).map(|b| b[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.bytes(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.bytes(4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mail_parser::MessageParser;
+
+    use super::*;
+
+    fn attribute(tnef: &mut Vec<u8>, level: u8, id: u32, data: &[u8]) {
+        tnef.push(level);
+        tnef.extend_from_slice(&id.to_le_bytes());
+        tnef.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        tnef.extend_from_slice(data);
+        let checksum = data.iter().fold(0u16, |acc, &b| acc.wrapping_add(b as u16));
+        tnef.extend_from_slice(&checksum.to_le_bytes());
+    }
+
+    fn string_prop(props: &mut Vec<u8>, prop_id: u16, value: &str) {
+        let mut value = value
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect::<Vec<_>>();
+        value.extend_from_slice(&[0, 0]);
+        props.extend_from_slice(&PT_UNICODE.to_le_bytes());
+        props.extend_from_slice(&prop_id.to_le_bytes());
+        props.extend_from_slice(&1u32.to_le_bytes());
+        props.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        props.extend_from_slice(&value);
+        props.resize(props.len() + padded(value.len()) - value.len(), 0);
+    }
+
+    fn build_tnef() -> Vec<u8> {
+        let mut tnef = Vec::new();
+        tnef.extend_from_slice(&TNEF_SIGNATURE.to_le_bytes());
+        tnef.extend_from_slice(&0x1234u16.to_le_bytes());
+        attribute(&mut tnef, 0x01, 0x0008_9006, &[0x01, 0x00, 0x01, 0x00]);
+
+        // First attachment with a long file name and MIME type
+        attribute(&mut tnef, LVL_ATTACHMENT, ATT_ATTACH_REND_DATA, &[0; 14]);
+        attribute(
+            &mut tnef,
+            LVL_ATTACHMENT,
+            ATT_ATTACH_TITLE,
+            b"REPORT~1.PDF\0",
+        );
+        attribute(&mut tnef, LVL_ATTACHMENT, ATT_ATTACH_DATA, b"%PDF-1.4 test");
+        let mut props = Vec::new();
+        props.extend_from_slice(&3u32.to_le_bytes());
+        props.extend_from_slice(&PT_LONG.to_le_bytes());
+        props.extend_from_slice(&0x0E20u16.to_le_bytes());
+        props.extend_from_slice(&13u32.to_le_bytes());
+        string_prop(&mut props, PR_ATTACH_LONG_FILENAME, "Quarterly report.pdf");
+        string_prop(&mut props, PR_ATTACH_MIME_TAG, "application/pdf");
+        attribute(&mut tnef, LVL_ATTACHMENT, ATT_ATTACHMENT, &props);
+
+        // Second attachment with only a short name
+        attribute(&mut tnef, LVL_ATTACHMENT, ATT_ATTACH_REND_DATA, &[0; 14]);
+        attribute(&mut tnef, LVL_ATTACHMENT, ATT_ATTACH_TITLE, b"NOTES.TXT\0");
+        attribute(&mut tnef, LVL_ATTACHMENT, ATT_ATTACH_DATA, b"hello");
+
+        tnef
+    }
+
+    #[test]
+    fn parse_tnef_attachments() {
+        assert_eq!(
+            parse_tnef(&build_tnef()).unwrap(),
+            vec![
+                TnefAttachment {
+                    name: Some("Quarterly report.pdf".to_string()),
+                    content_type: Some("application/pdf".to_string()),
+                    contents: b"%PDF-1.4 test".to_vec(),
+                },
+                TnefAttachment {
+                    name: Some("NOTES.TXT".to_string()),
+                    content_type: None,
+                    contents: b"hello".to_vec(),
+                }
+            ]
+        );
+        assert_eq!(parse_tnef(b"not a tnef stream"), None);
+        assert_eq!(parse_tnef(&build_tnef()[..40]), None);
+    }
+
+    #[test]
+    fn convert_tnef_message() {
+        let mut tnef_b64 = Vec::new();
+        mail_builder::encoders::base64::base64_encode_mime(&build_tnef(), &mut tnef_b64, false)
+            .unwrap();
+        let raw_message = [
+            b"From: john@example.org\r\nTo: jane@example.org\r\nSubject: Report\r\n".as_slice(),
+            b"MIME-Version: 1.0\r\nContent-Type: multipart/mixed; boundary=\"b1\"\r\n\r\n",
+            b"--b1\r\nContent-Type: text/plain\r\n\r\nSee attached.\r\n",
+            b"--b1\r\nContent-Type: application/ms-tnef; name=\"winmail.dat\"\r\n",
+            b"Content-Transfer-Encoding: base64\r\n\r\n",
+            &tnef_b64,
+            b"\r\n--b1--\r\n",
+        ]
+        .concat();
+
+        for keep_original in [false, true] {
+            let message = MessageParser::new().parse(&raw_message).unwrap();
+            let converted = convert_tnef_attachments(&message, keep_original).unwrap();
+            let message = MessageParser::new().parse(&converted).unwrap();
+            let names = message
+                .attachments()
+                .filter_map(|part| part.attachment_name())
+                .collect::<Vec<_>>();
+            let mut expected = vec!["Quarterly report.pdf", "NOTES.TXT"];
+            if keep_original {
+                expected.push("winmail.dat");
+            }
+            assert_eq!(names, expected);
+            assert_eq!(message.body_text(0).unwrap().trim(), "See attached.");
+            assert_eq!(message.attachment(0).unwrap().contents(), b"%PDF-1.4 test");
+        }
+    }
+}
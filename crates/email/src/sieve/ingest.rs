@@ -9,12 +9,18 @@ use crate::{
     cache::{MessageCacheFetch, mailbox::MailboxCacheAccess},
     mailbox::{INBOX_ID, TRASH_ID, manage::MailboxFnc},
     message::{
-        delivery::AutogeneratedMessage,
+        delivery::{AutogeneratedMessage, has_spam_verdict},
         ingest::{EmailIngest, IngestEmail, IngestSource, IngestedEmail},
     },
 };
 use common::{
-    Server, auth::AccessToken, config::jmap::settings::SpecialUse, scripts::plugins::PluginContext,
+    Server,
+    auth::AccessToken,
+    config::{
+        jmap::settings::SpecialUse,
+        scripts::{SIEVE_LAYER_ACTIVE_SCRIPT, SIEVE_LAYER_PREFIX},
+    },
+    scripts::{notify::NotifyWebhook, plugins::PluginContext},
 };
 use directory::{Permission, QueryParams};
 use jmap_proto::types::{collection::Collection, id::Id, keyword::Keyword, property::Property};
@@ -49,7 +55,7 @@ pub trait SieveScriptIngest: Sync + Send {
         envelope_from_authenticated: bool,
         envelope_to: &str,
         session_id: u64,
-        active_script: ActiveScript,
+        active_script: Option<ActiveScript>,
         autogenerated: &mut Vec<AutogeneratedMessage>,
     ) -> impl Future<Output = trc::Result<IngestedEmail>> + Send;
 
@@ -81,7 +87,7 @@ impl SieveScriptIngest for Server {
         envelope_from_authenticated: bool,
         envelope_to: &str,
         session_id: u64,
-        active_script: ActiveScript,
+        active_script: Option<ActiveScript>,
         autogenerated: &mut Vec<AutogeneratedMessage>,
     ) -> trc::Result<IngestedEmail> {
         // Parse message
@@ -126,10 +132,29 @@ impl SieveScriptIngest for Server {
         instance.set_envelope(Envelope::From, envelope_from);
         instance.set_envelope(Envelope::To, envelope_to);
 
-        let mut input = Input::script(
-            active_script.script_name.to_string(),
-            active_script.script.clone(),
-        );
+        // Run the user's script between the administrator defined layers
+        let rcpt_domain = envelope_to
+            .rsplit_once('@')
+            .map(|(_, domain)| domain.to_lowercase())
+            .unwrap_or_default();
+        let wrapper = self
+            .core
+            .sieve
+            .delivery_layers
+            .wrapper(&rcpt_domain, active_script.is_some());
+        let is_layered = wrapper.is_some();
+        let mut input = match (wrapper, &active_script) {
+            (Some(wrapper), _) => Input::script("layers".to_string(), wrapper.clone()),
+            (None, Some(active_script)) => Input::script(
+                active_script.script_name.to_string(),
+                active_script.script.clone(),
+            ),
+            (None, None) => {
+                return Err(trc::EventType::Sieve(SieveEvent::UnexpectedError)
+                    .into_err()
+                    .details("No Sieve script to run"));
+            }
+        };
 
         let mut do_discard = false;
         let mut do_deliver = false;
@@ -147,6 +172,7 @@ impl SieveScriptIngest for Server {
             blob_id: Default::default(),
             size: raw_message.len(),
             imap_uids: Vec::new(),
+            is_spam: has_spam_verdict(self, raw_message),
         };
         let mut checked_ids: AHashMap<SeenIdHash, bool> = AHashMap::new();
 
@@ -155,7 +181,12 @@ impl SieveScriptIngest for Server {
                 Ok(event) => match event {
                     Event::IncludeScript { name, .. } => match &name {
                         sieve::Script::Personal(name_) => {
-                            if let Ok(Some(script)) =
+                            if let Some(active_script) = active_script
+                                .as_ref()
+                                .filter(|_| is_layered && name_ == SIEVE_LAYER_ACTIVE_SCRIPT)
+                            {
+                                input = Input::script(name, active_script.script.clone());
+                            } else if let Ok(Some(script)) =
                                 self.sieve_script_get_by_name(account_id, name_).await
                             {
                                 input = Input::script(name, script);
@@ -164,8 +195,16 @@ impl SieveScriptIngest for Server {
                             }
                         }
                         sieve::Script::Global(name_) => {
-                            if let Some(script) =
-                                self.get_untrusted_sieve_script(&name_.to_lowercase(), session_id)
+                            if let Some(script) = name_
+                                .strip_prefix(SIEVE_LAYER_PREFIX)
+                                .filter(|_| is_layered)
+                                .and_then(|name| self.core.sieve.delivery_layers.scripts.get(name))
+                                .or_else(|| {
+                                    self.get_untrusted_sieve_script(
+                                        &name_.to_lowercase(),
+                                        session_id,
+                                    )
+                                })
                             {
                                 input = Input::script(name, script.clone());
                             } else {
@@ -246,7 +285,10 @@ impl SieveScriptIngest for Server {
                     Event::DuplicateId { id, expiry, last } => {
                         let id_hash = SeenIdHash::new(
                             account_id,
-                            active_script.version.hash().unwrap_or_default(),
+                            active_script
+                                .as_ref()
+                                .and_then(|s| s.version.hash())
+                                .unwrap_or_default(),
                             &id,
                         );
                         if let Some(result) = checked_ids.get(&id_hash) {
@@ -388,6 +430,15 @@ impl SieveScriptIngest for Server {
                                 }
                             };
 
+                            // Notifications generated by the runtime count towards the notify rate
+                            if is_notification(&message.raw_message)
+                                && !self
+                                    .check_sieve_notify_rate(account_id, session_id, &recipients)
+                                    .await
+                            {
+                                continue;
+                            }
+
                             if message.raw_message.len() <= self.core.jmap.mail_max_size {
                                 trc::event!(
                                     Sieve(SieveEvent::SendMessage),
@@ -429,9 +480,68 @@ impl SieveScriptIngest for Server {
                             continue;
                         }
                     }
-                    Event::ListContains { .. }
-                    | Event::Notify { .. }
-                    | Event::SetEnvelope { .. } => {
+                    Event::Notify {
+                        method,
+                        from,
+                        options,
+                        message,
+                        ..
+                    } => {
+                        input = true.into();
+
+                        // Mailto notifications are delivered as messages, other methods are
+                        // webhooks which can only target the allowed hosts
+                        let notify = &self.core.sieve.notify;
+                        if notify.client.is_none() || notify.webhook_url(&method).is_none() {
+                            trc::event!(
+                                Sieve(SieveEvent::NotSupported),
+                                Url = method,
+                                SpanId = session_id
+                            );
+                            continue;
+                        }
+
+                        if !self
+                            .check_sieve_notify_rate(account_id, session_id, &[method.as_str()])
+                            .await
+                        {
+                            continue;
+                        }
+
+                        let subject = instance.message().subject().map(|s| s.to_string());
+                        match self
+                            .send_sieve_notify_webhook(
+                                &method,
+                                &NotifyWebhook {
+                                    account_id,
+                                    from: from.as_deref().unwrap_or(mail_from.as_str()),
+                                    envelope_from,
+                                    envelope_to,
+                                    subject: subject.as_deref(),
+                                    message: &message,
+                                    options: &options,
+                                },
+                            )
+                            .await
+                        {
+                            Ok(_) => {
+                                trc::event!(
+                                    Sieve(SieveEvent::NotifySent),
+                                    AccountId = account_id,
+                                    Url = method,
+                                    SpanId = session_id
+                                );
+                            }
+                            Err(err) => {
+                                trc::error!(
+                                    err.account_id(account_id)
+                                        .span_id(session_id)
+                                        .ctx(trc::Key::Url, method)
+                                );
+                            }
+                        }
+                    }
+                    Event::ListContains { .. } | Event::SetEnvelope { .. } => {
                         // Not allowed
                         input = false.into();
                     }
@@ -484,6 +594,13 @@ impl SieveScriptIngest for Server {
             messages[0].file_into.push(INBOX_ID);
         }
 
+        // A reject in any layer takes precedence over actions taken by other layers
+        if is_layered && reject_reason.is_some() {
+            for message in &mut messages {
+                message.file_into.clear();
+            }
+        }
+
         // Deliver messages
         let mut last_temp_error = None;
         let mut has_delivered = false;
@@ -507,6 +624,11 @@ impl SieveScriptIngest for Server {
                     continue;
                 };
 
+                // Messages filed by the script are not classified, keep the filter's verdict
+                let spam_classify = access_token.has_permission(Permission::SpamFilterClassify)
+                    && !sieve_message.did_file_into;
+                let is_classified = spam_classify && sieve_message.file_into == [INBOX_ID];
+
                 // Deliver message
                 match self
                     .email_ingest(IngestEmail {
@@ -520,8 +642,7 @@ impl SieveScriptIngest for Server {
                             deliver_to: envelope_to,
                             is_sender_authenticated: envelope_from_authenticated,
                         },
-                        spam_classify: access_token.has_permission(Permission::SpamFilterClassify)
-                            && !sieve_message.did_file_into,
+                        spam_classify,
                         spam_train: can_spam_train,
                         session_id,
                     })
@@ -529,7 +650,14 @@ impl SieveScriptIngest for Server {
                 {
                     Ok(ingested_message_) => {
                         has_delivered = true;
-                        ingested_message = ingested_message_;
+                        ingested_message = IngestedEmail {
+                            is_spam: if is_classified {
+                                ingested_message_.is_spam
+                            } else {
+                                ingested_message.is_spam
+                            },
+                            ..ingested_message_
+                        };
                     }
                     Err(err) => {
                         last_temp_error = err.into();
@@ -725,6 +853,22 @@ impl SieveScriptIngest for Server {
     }
 }
 
+fn is_notification(raw_message: &[u8]) -> bool {
+    let headers = raw_message
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .map_or(raw_message, |pos| &raw_message[..pos]);
+
+    headers.split(|&ch| ch == b'\n').any(|line| {
+        line.get(..15)
+            .is_some_and(|name| name.eq_ignore_ascii_case(b"Auto-Submitted:"))
+            && line[15..]
+                .trim_ascii_start()
+                .get(..13)
+                .is_some_and(|value| value.eq_ignore_ascii_case(b"auto-notified"))
+    })
+}
+
 pub struct CompiledScript {
     pub script: Sieve,
     pub name: String,
@@ -16,6 +16,7 @@ pub mod activate;
 pub mod delete;
 pub mod index;
 pub mod ingest;
+pub mod vacation;
 
 #[derive(Debug, Clone)]
 pub struct ActiveScript {
@@ -0,0 +1,160 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::borrow::Cow;
+
+use common::Server;
+use jmap_proto::types::{collection::Collection, date::UTCDate, property::Property};
+use mail_builder::MessageBuilder;
+use mail_parser::decoders::html::html_to_text;
+use store::{Serialize, query::Filter, write::Archiver};
+use trc::AddContext;
+
+use super::SieveScript;
+
+pub trait SieveVacation: Sync + Send {
+    fn sieve_vacation_script_id(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Option<u32>>> + Send;
+
+    fn sieve_vacation_build(&self, obj: &mut SieveScript) -> trc::Result<Vec<u8>>;
+}
+
+impl SieveVacation for Server {
+    async fn sieve_vacation_script_id(&self, account_id: u32) -> trc::Result<Option<u32>> {
+        self.store()
+            .filter(
+                account_id,
+                Collection::SieveScript,
+                vec![Filter::eq(Property::Name, "vacation".as_bytes().to_vec())],
+            )
+            .await
+            .caused_by(trc::location!())
+            .map(|r| r.results.min())
+    }
+
+    fn sieve_vacation_build(&self, obj: &mut SieveScript) -> trc::Result<Vec<u8>> {
+        // Build Sieve script
+        let mut script = Vec::with_capacity(1024);
+        script.extend_from_slice(b"require [\"vacation\", \"relational\", \"date\"];\r\n\r\n");
+        let mut num_blocks = 0;
+
+        // Add start date
+        if let Some(value) = obj.vacation_response.as_ref().and_then(|v| v.from_date) {
+            script.extend_from_slice(b"if currentdate :value \"ge\" \"iso8601\" \"");
+            script.extend_from_slice(UTCDate::from(value).to_string().as_bytes());
+            script.extend_from_slice(b"\" {\r\n");
+            num_blocks += 1;
+        }
+
+        // Add end date
+        if let Some(value) = obj.vacation_response.as_ref().and_then(|v| v.to_date) {
+            script.extend_from_slice(b"if currentdate :value \"le\" \"iso8601\" \"");
+            script.extend_from_slice(UTCDate::from(value).to_string().as_bytes());
+            script.extend_from_slice(b"\" {\r\n");
+            num_blocks += 1;
+        }
+
+        script.extend_from_slice(b"vacation :mime ");
+        if let Some(value) = obj
+            .vacation_response
+            .as_ref()
+            .and_then(|v| v.subject.as_ref())
+        {
+            script.extend_from_slice(b":subject \"");
+            for &ch in value.as_bytes().iter() {
+                match ch {
+                    b'\\' | b'\"' => {
+                        script.push(b'\\');
+                    }
+                    b'\r' | b'\n' => {
+                        continue;
+                    }
+                    _ => (),
+                }
+                script.push(ch);
+            }
+            script.extend_from_slice(b"\" ");
+        }
+
+        let mut text_body = if let Some(value) = obj
+            .vacation_response
+            .as_ref()
+            .and_then(|v| v.text_body.as_ref())
+        {
+            Cow::from(value.as_str()).into()
+        } else {
+            None
+        };
+        let html_body = if let Some(value) = obj
+            .vacation_response
+            .as_ref()
+            .and_then(|v| v.html_body.as_ref())
+        {
+            Cow::from(value.as_str()).into()
+        } else {
+            None
+        };
+        match (&html_body, &text_body) {
+            (Some(html_body), None) => {
+                text_body = Cow::from(html_to_text(html_body.as_ref())).into();
+            }
+            (None, None) => {
+                text_body = Cow::from("I am away.").into();
+            }
+            _ => (),
+        }
+
+        let mut builder = MessageBuilder::new();
+        let mut body_len = 0;
+        if let Some(html_body) = html_body {
+            body_len = html_body.len();
+            builder = builder.html_body(html_body);
+        }
+        if let Some(text_body) = text_body {
+            body_len += text_body.len();
+            builder = builder.text_body(text_body);
+        }
+        let mut message_body = Vec::with_capacity(body_len + 128);
+        builder.write_body(&mut message_body).ok();
+
+        script.push(b'\"');
+        for ch in message_body {
+            if [b'\\', b'\"'].contains(&ch) {
+                script.push(b'\\');
+            }
+            script.push(ch);
+        }
+        script.extend_from_slice(b"\";\r\n");
+
+        // Close blocks
+        for _ in 0..num_blocks {
+            script.extend_from_slice(b"}\r\n");
+        }
+
+        match self.core.sieve.untrusted_compiler.compile(&script) {
+            Ok(compiled_script) => {
+                // Update blob length
+                obj.size = script.len() as u32;
+
+                // Serialize script
+                script.extend(
+                    Archiver::new(compiled_script)
+                        .untrusted()
+                        .serialize()
+                        .caused_by(trc::location!())?,
+                );
+
+                Ok(script)
+            }
+            Err(err) => Err(trc::StoreEvent::UnexpectedError
+                .caused_by(trc::location!())
+                .reason(err)
+                .details("Vacation Sieve Script failed to compile.")),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{ArchivedUndoStatus, EmailSubmission};
+use common::Server;
+use jmap_proto::types::{collection::Collection, property::Property};
+use std::future::Future;
+use store::{
+    SerializeInfallible,
+    query::Filter,
+    write::{AlignedBytes, Archive, now},
+};
+use trc::AddContext;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduledSubmission {
+    pub document_id: u32,
+    pub email_id: u32,
+    pub queue_id: u64,
+    pub send_at: u64,
+}
+
+pub trait ScheduledSubmissions: Sync + Send {
+    fn scheduled_submissions(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<ScheduledSubmission>>> + Send;
+}
+
+impl ScheduledSubmissions for Server {
+    // Submissions with a future release time are held in the queue until they are due
+    async fn scheduled_submissions(
+        &self,
+        account_id: u32,
+    ) -> trc::Result<Vec<ScheduledSubmission>> {
+        let document_ids = self
+            .core
+            .storage
+            .data
+            .filter(
+                account_id,
+                Collection::EmailSubmission,
+                vec![Filter::gt(Property::SendAt, now().serialize())],
+            )
+            .await
+            .caused_by(trc::location!())?
+            .results;
+
+        let mut submissions = Vec::with_capacity(document_ids.len() as usize);
+        if !document_ids.is_empty() {
+            self.get_archives(
+                account_id,
+                Collection::EmailSubmission,
+                &document_ids,
+                |document_id, archive: Archive<AlignedBytes>| {
+                    let submission = archive
+                        .unarchive::<EmailSubmission>()
+                        .caused_by(trc::location!())?;
+                    if let Some(queue_id) = submission.queue_id.as_ref()
+                        && !matches!(submission.undo_status, ArchivedUndoStatus::Canceled)
+                    {
+                        submissions.push(ScheduledSubmission {
+                            document_id,
+                            email_id: submission.email_id.to_native(),
+                            queue_id: queue_id.to_native(),
+                            send_at: submission.send_at.to_native(),
+                        });
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+        }
+
+        Ok(submissions)
+    }
+}
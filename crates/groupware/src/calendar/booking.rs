@@ -0,0 +1,290 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{
+    calendar::{ArchivedCalendarEvent, CalendarEvent, itip::ItipIngestError},
+    scheduling::{ItipMessage, event_update::itip_update},
+};
+use calcard::{
+    common::timezone::Tz,
+    icalendar::{
+        ArchivedICalendarComponent, ArchivedICalendarComponentType, ArchivedICalendarParameter,
+        ArchivedICalendarParticipationStatus, ArchivedICalendarProperty, ArchivedICalendarStatus,
+        ICalendar, ICalendarParameter, ICalendarParticipationStatus, ICalendarProperty,
+        ICalendarTransparency, dates::TimeOrDelta,
+    },
+};
+use common::{Server, auth::AccessToken, config::groupware::ResourceBookingPolicy};
+use dav_proto::schema::property::TimeRange;
+use jmap_proto::types::collection::Collection;
+use std::sync::Arc;
+use store::write::now;
+use trc::AddContext;
+
+pub trait ResourceBooking: Sync + Send {
+    fn itip_resource_booking(
+        &self,
+        access_token: &AccessToken,
+        document_id: Option<u32>,
+        ical: &mut ICalendar,
+    ) -> impl Future<Output = Result<Vec<ItipMessage<ICalendar>>, ItipIngestError>> + Send;
+
+    fn is_resource_available(
+        &self,
+        access_token: &AccessToken,
+        document_id: Option<u32>,
+        instances: &[(i64, i64)],
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn itip_resource_owner(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<Option<Arc<AccessToken>>>> + Send;
+}
+
+impl ResourceBooking for Server {
+    async fn itip_resource_booking(
+        &self,
+        access_token: &AccessToken,
+        document_id: Option<u32>,
+        ical: &mut ICalendar,
+    ) -> Result<Vec<ItipMessage<ICalendar>>, ItipIngestError> {
+        // Requests are left for the resource delegates to approve
+        let config = &self.core.groupware.resource_booking;
+        if config.policy == ResourceBookingPolicy::Manual {
+            return Ok(vec![]);
+        }
+
+        let instances = ical
+            .expand_dates(Tz::Floating, self.core.groupware.max_ical_instances)
+            .events
+            .into_iter()
+            .map(|event| {
+                let start = event.start.timestamp();
+                let end = match event.end {
+                    TimeOrDelta::Time(time) => time.timestamp(),
+                    TimeOrDelta::Delta(delta) => start + delta.num_seconds(),
+                };
+                (start, end)
+            })
+            .collect::<Vec<_>>();
+
+        // Apply booking limits
+        let now = now() as i64;
+        let is_within_limits = instances.iter().all(|(start, end)| {
+            config
+                .max_advance
+                .is_none_or(|max_advance| *start <= now + max_advance as i64)
+                && config
+                    .max_duration
+                    .is_none_or(|max_duration| end - start <= max_duration as i64)
+        });
+
+        let part_stat = if is_within_limits
+            && (config.policy == ResourceBookingPolicy::Accept
+                || self
+                    .is_resource_available(access_token, document_id, &instances)
+                    .await?)
+        {
+            ICalendarParticipationStatus::Accepted
+        } else {
+            ICalendarParticipationStatus::Declined
+        };
+
+        // Reply on behalf of the resource
+        let old_ical = ical.clone();
+        if set_part_stat(ical, access_token.emails.as_slice(), part_stat) {
+            itip_update(ical, &old_ical, access_token.emails.as_slice()).map_err(Into::into)
+        } else {
+            Ok(vec![])
+        }
+    }
+
+    async fn is_resource_available(
+        &self,
+        access_token: &AccessToken,
+        document_id: Option<u32>,
+        instances: &[(i64, i64)],
+    ) -> trc::Result<bool> {
+        let (Some(range_start), Some(range_end)) = (
+            instances.iter().map(|(start, _)| *start).min(),
+            instances.iter().map(|(_, end)| *end).max(),
+        ) else {
+            return Ok(true);
+        };
+        let account_id = access_token.primary_id;
+        let Some(document_ids) = self
+            .get_document_ids(account_id, Collection::CalendarEvent)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(true);
+        };
+
+        let mut is_available = true;
+        self.get_archives(
+            account_id,
+            Collection::CalendarEvent,
+            &document_ids,
+            |event_id, archive| {
+                if document_id == Some(event_id) {
+                    return Ok(true);
+                }
+
+                let event = archive
+                    .unarchive::<CalendarEvent>()
+                    .caused_by(trc::location!())?;
+                if event.data.event_range_start() >= range_end
+                    || event.data.event_range_end() <= range_start
+                {
+                    return Ok(true);
+                }
+
+                // Double bookings are not allowed
+                is_available = !has_conflict(
+                    event,
+                    access_token.emails.as_slice(),
+                    instances,
+                    range_start,
+                    range_end,
+                );
+                Ok(is_available)
+            },
+        )
+        .await
+        .caused_by(trc::location!())?;
+
+        Ok(is_available)
+    }
+
+    async fn itip_resource_owner(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+    ) -> trc::Result<Option<Arc<AccessToken>>> {
+        // Delegates with write access to a resource calendar approve or decline
+        // pending bookings on behalf of the resource
+        if account_id != access_token.primary_id {
+            let owner = self
+                .get_access_token(account_id)
+                .await
+                .caused_by(trc::location!())?;
+            if owner.is_resource {
+                return Ok(Some(owner));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+fn has_conflict(
+    event: &ArchivedCalendarEvent,
+    emails: &[String],
+    instances: &[(i64, i64)],
+    range_start: i64,
+    range_end: i64,
+) -> bool {
+    event
+        .data
+        .expand(
+            Tz::Floating,
+            TimeRange {
+                start: range_start,
+                end: range_end,
+            },
+        )
+        .unwrap_or_default()
+        .into_iter()
+        .any(|instance| {
+            event
+                .data
+                .event
+                .components
+                .get(instance.comp_id as usize)
+                .is_some_and(|comp| is_busy(comp, emails))
+                && instances
+                    .iter()
+                    .any(|(start, end)| instance.start < *end && *start < instance.end)
+        })
+}
+
+fn is_busy(comp: &ArchivedICalendarComponent, emails: &[String]) -> bool {
+    matches!(comp.component_type, ArchivedICalendarComponentType::VEvent)
+        && comp
+            .transparency()
+            .is_none_or(|t| t == &ICalendarTransparency::Opaque)
+        && !matches!(comp.status(), Some(ArchivedICalendarStatus::Cancelled))
+        && !comp.entries.iter().any(|entry| {
+            matches!(entry.name, ArchivedICalendarProperty::Attendee)
+                && entry
+                    .values
+                    .first()
+                    .and_then(|v| v.as_text())
+                    .is_some_and(|v| is_local_address(v, emails))
+                && entry.params.iter().any(|param| {
+                    matches!(
+                        param,
+                        ArchivedICalendarParameter::Partstat(
+                            ArchivedICalendarParticipationStatus::Declined
+                        )
+                    )
+                })
+        })
+}
+
+fn set_part_stat(
+    ical: &mut ICalendar,
+    emails: &[String],
+    part_stat: ICalendarParticipationStatus,
+) -> bool {
+    let mut did_change = false;
+
+    for component in &mut ical.components {
+        if component.component_type.is_scheduling_object() {
+            for entry in &mut component.entries {
+                if entry.name == ICalendarProperty::Attendee
+                    && entry
+                        .values
+                        .first()
+                        .and_then(|v| v.as_text())
+                        .is_some_and(|v| is_local_address(v, emails))
+                {
+                    if let Some(current) = entry.params.iter_mut().find_map(|param| {
+                        if let ICalendarParameter::Partstat(current) = param {
+                            Some(current)
+                        } else {
+                            None
+                        }
+                    }) {
+                        if current != &part_stat {
+                            *current = part_stat.clone();
+                            did_change = true;
+                        }
+                    } else {
+                        entry
+                            .params
+                            .push(ICalendarParameter::Partstat(part_stat.clone()));
+                        did_change = true;
+                    }
+                }
+            }
+        }
+    }
+
+    did_change
+}
+
+fn is_local_address(uri: &str, emails: &[String]) -> bool {
+    let address = uri
+        .strip_prefix("mailto:")
+        .or_else(|| uri.strip_prefix("MAILTO:"))
+        .unwrap_or(uri);
+    emails
+        .iter()
+        .any(|email| email.eq_ignore_ascii_case(address))
+}
@@ -7,7 +7,11 @@
 use crate::{
     RFC_3986,
     cache::GroupwareCache,
-    calendar::{CalendarEvent, CalendarEventData, CalendarScheduling},
+    calendar::{
+        CalendarEvent, CalendarEventData, CalendarScheduling,
+        booking::ResourceBooking,
+        normalize::{normalize_ical, repair_folding},
+    },
     scheduling::{
         ItipError, ItipMessage,
         inbound::{
@@ -54,7 +58,7 @@ pub trait ItipIngest: Sync + Send {
         sender: &str,
         recipient: &str,
         itip_message: &str,
-    ) -> impl Future<Output = Result<Option<ItipMessage<ICalendar>>, ItipIngestError>> + Send;
+    ) -> impl Future<Output = Result<Vec<ItipMessage<ICalendar>>, ItipIngestError>> + Send;
 
     fn http_rsvp_url(
         &self,
@@ -78,13 +82,14 @@ impl ItipIngest for Server {
         sender: &str,
         recipient: &str,
         itip_message: &str,
-    ) -> Result<Option<ItipMessage<ICalendar>>, ItipIngestError> {
+    ) -> Result<Vec<ItipMessage<ICalendar>>, ItipIngestError> {
         // Parse and validate the iTIP message
-        let mut itip = ICalendar::parse(itip_message)
+        let mut itip = ICalendar::parse(repair_folding(itip_message).as_ref())
             .map_err(|_| ItipIngestError::Message(ItipError::ICalendarParseError))
-            .and_then(|ical| {
+            .and_then(|mut ical| {
                 if ical.components.len() > 1
                     && ical.components[0].component_type == ICalendarComponentType::VCalendar
+                    && normalize_ical(&mut ical, None, &self.core.groupware.tz_overrides).is_ok()
                 {
                     Ok(ical)
                 } else {
@@ -178,6 +183,18 @@ impl ItipIngest for Server {
                         // Merge changes
                         itip_merge_changes(&mut event.data.event, changes);
 
+                        // Resources confirm that rescheduled events do not overlap other bookings
+                        let itip_replies = if access_token.is_resource && is_organizer_update {
+                            self.itip_resource_booking(
+                                access_token,
+                                document_id.into(),
+                                &mut event.data.event,
+                            )
+                            .await?
+                        } else {
+                            vec![]
+                        };
+
                         // Calculate the new ical size
                         event.size = event.data.event.to_string().len() as u32;
                         if event.size > self.core.groupware.max_ical_size as u32 {
@@ -245,10 +262,10 @@ impl ItipIngest for Server {
                             .caused_by(trc::location!())?;
                         self.commit_batch(batch).await.caused_by(trc::location!())?;
 
-                        Ok(None)
+                        Ok(itip_replies)
                     }
-                    MergeResult::Message(itip_message) => Ok(Some(itip_message)),
-                    MergeResult::None => Ok(None),
+                    MergeResult::Message(itip_message) => Ok(vec![itip_message]),
+                    MergeResult::None => Ok(vec![]),
                 }
             } else {
                 Err(ItipIngestError::Message(ItipError::EventNotFound))
@@ -256,6 +273,7 @@ impl ItipIngest for Server {
         } else {
             // Verify that auto-adding invitations is allowed
             if !self.core.groupware.itip_auto_add
+                && !access_token.is_resource
                 && self
                     .store()
                     .filter(
@@ -277,6 +295,14 @@ impl ItipIngest for Server {
             let mut ical = itip.clone();
             itip_import_message(&mut ical)?;
 
+            // Resources accept or decline invitations based on their availability
+            let itip_replies = if access_token.is_resource {
+                self.itip_resource_booking(access_token, None, &mut ical)
+                    .await?
+            } else {
+                vec![]
+            };
+
             // Validate quota
             if self
                 .has_available_quota(resource_token, itip_message.len() as u64)
@@ -348,7 +374,7 @@ impl ItipIngest for Server {
                 .caused_by(trc::location!())?;
             self.commit_batch(batch).await.caused_by(trc::location!())?;
 
-            Ok(None)
+            Ok(itip_replies)
         }
     }
 
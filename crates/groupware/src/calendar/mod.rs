@@ -5,11 +5,15 @@
  */
 
 pub mod alarm;
+pub mod booking;
 pub mod dates;
 pub mod expand;
 pub mod index;
 pub mod itip;
+pub mod normalize;
+pub mod out_of_office;
 pub mod storage;
+pub mod tzdata;
 
 use calcard::icalendar::ICalendar;
 use common::DavName;
@@ -0,0 +1,197 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{borrow::Cow, str::FromStr};
+
+use ahash::{AHashMap, AHashSet};
+use calcard::{
+    common::timezone::Tz,
+    icalendar::{ICalendar, ICalendarComponentType, ICalendarParameter, ICalendarProperty},
+};
+use store::rand;
+
+// Restores continuation lines that were folded without the leading whitespace
+// required by RFC 5545 and RFC 6350
+pub fn repair_folding(raw: &str) -> Cow<'_, str> {
+    if !raw.lines().skip(1).any(is_broken_continuation) {
+        return Cow::Borrowed(raw);
+    }
+
+    let mut result = String::with_capacity(raw.len() + 16);
+    for (pos, line) in raw.lines().enumerate() {
+        if pos > 0 {
+            result.push_str(if is_broken_continuation(line) {
+                "\r\n "
+            } else {
+                "\r\n"
+            });
+        }
+        result.push_str(line.trim_end_matches('\r'));
+    }
+    if raw.ends_with('\n') {
+        result.push_str("\r\n");
+    }
+
+    Cow::Owned(result)
+}
+
+fn is_broken_continuation(line: &str) -> bool {
+    let line = line.trim_end_matches('\r');
+    if line.is_empty() || line.starts_with([' ', '\t']) {
+        return false;
+    }
+
+    // Content lines start with an optionally grouped property name
+    let name = line
+        .split_once([':', ';'])
+        .map(|(name, _)| name.rsplit_once('.').map_or(name, |(_, name)| name))
+        .unwrap_or_default();
+    name.is_empty()
+        || !name
+            .bytes()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == b'-')
+}
+
+// Fixes common vendor quirks in an iCalendar object and returns
+// a description of the problem when the object can't be repaired
+pub fn normalize_ical(
+    ical: &mut ICalendar,
+    existing_uid: Option<&str>,
+    tz_overrides: &AHashMap<String, String>,
+) -> Result<(), String> {
+    let mut timezones = AHashSet::new();
+    let mut uids = AHashSet::new();
+    let mut missing_uid = false;
+
+    for comp in &ical.components {
+        match comp.component_type {
+            ICalendarComponentType::VTimezone => {
+                if let Some(tz_id) = comp
+                    .property(&ICalendarProperty::Tzid)
+                    .and_then(|e| e.values.first())
+                    .and_then(|v| v.as_text())
+                {
+                    timezones.insert(tz_id.to_string());
+                } else {
+                    return Err("VTIMEZONE component is missing the TZID property".to_string());
+                }
+            }
+            ICalendarComponentType::VEvent
+            | ICalendarComponentType::VTodo
+            | ICalendarComponentType::VJournal
+            | ICalendarComponentType::VFreebusy
+            | ICalendarComponentType::VAvailability => {
+                if let Some(uid) = comp.uid() {
+                    uids.insert(uid.to_string());
+                } else {
+                    missing_uid = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Assign a UID to components that lack one, as long as it is not ambiguous
+    if missing_uid {
+        let uid = match uids.len() {
+            0 => existing_uid
+                .map(|uid| uid.to_string())
+                .unwrap_or_else(generate_uid),
+            1 => uids.iter().next().unwrap().clone(),
+            _ => {
+                return Err(
+                    "Components without a UID cannot be matched to a single event".to_string(),
+                );
+            }
+        };
+        for comp in &mut ical.components {
+            if matches!(
+                comp.component_type,
+                ICalendarComponentType::VEvent
+                    | ICalendarComponentType::VTodo
+                    | ICalendarComponentType::VJournal
+                    | ICalendarComponentType::VFreebusy
+                    | ICalendarComponentType::VAvailability
+            ) && comp.uid().is_none()
+            {
+                comp.add_uid(&uid);
+            }
+        }
+    }
+
+    let has_method = ical
+        .components
+        .first()
+        .and_then(|comp| comp.property(&ICalendarProperty::Method))
+        .is_some();
+    for comp in &mut ical.components {
+        if comp.component_type == ICalendarComponentType::VTimezone {
+            continue;
+        }
+
+        // Map references to overridden time zones and drop those that are neither
+        // defined nor known, the affected times are then interpreted as floating
+        for entry in &mut comp.entries {
+            entry.params.retain_mut(|param| match param {
+                ICalendarParameter::Tzid(tz_id) if !timezones.contains(tz_id.as_str()) => {
+                    if let Some(target) = tz_overrides.get(tz_id.as_str()) {
+                        *tz_id = target.clone();
+                        true
+                    } else {
+                        Tz::from_str(tz_id).is_ok()
+                    }
+                }
+                _ => true,
+            });
+        }
+
+        if comp.component_type == ICalendarComponentType::VEvent {
+            let mut dt_start = 0;
+            let mut dt_end = false;
+            let mut duration = false;
+            for entry in &comp.entries {
+                match entry.name {
+                    ICalendarProperty::Dtstart => dt_start += 1,
+                    ICalendarProperty::Dtend => dt_end = true,
+                    ICalendarProperty::Duration => duration = true,
+                    _ => {}
+                }
+            }
+
+            match dt_start {
+                0 if !has_method => {
+                    return Err("VEVENT component is missing the DTSTART property".to_string());
+                }
+                0 | 1 => {}
+                _ => {
+                    return Err(
+                        "VEVENT component contains more than one DTSTART property".to_string()
+                    );
+                }
+            }
+
+            // DTEND takes precedence when both are present
+            if dt_end && duration {
+                comp.entries
+                    .retain(|entry| entry.name != ICalendarProperty::Duration);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub(crate) fn generate_uid() -> String {
+    let id = rand::random::<u128>();
+    format!(
+        "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+        (id >> 96) as u32,
+        (id >> 80) as u16,
+        (id >> 64) as u16,
+        (id >> 48) as u16,
+        id as u64 & 0xffff_ffff_ffff
+    )
+}
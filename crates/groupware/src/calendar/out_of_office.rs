@@ -0,0 +1,80 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use calcard::{common::timezone::Tz, icalendar::ArchivedICalendarProperty};
+use dav_proto::schema::property::TimeRange;
+
+use super::ArchivedCalendarEventData;
+
+const DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutOfOfficePeriod {
+    pub start: i64,
+    pub end: i64,
+    pub summary: String,
+}
+
+// Tracks a vacation response enabled from the calendar so it can be reverted once the period ends
+#[derive(rkyv::Archive, rkyv::Deserialize, rkyv::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfOfficeState {
+    pub until: u64,
+    pub previous_script_id: Option<u32>,
+    pub previous_from_date: Option<u64>,
+    pub previous_to_date: Option<u64>,
+    // Set when the user manages the vacation response during the period
+    pub released: bool,
+}
+
+impl ArchivedCalendarEventData {
+    // Returns the all-day instance running at the given time whose summary contains any of the
+    // lowercase patterns, preferring the one that ends last.
+    pub fn out_of_office_at(
+        &self,
+        time: i64,
+        default_tz: Tz,
+        summaries: &[String],
+    ) -> Option<OutOfOfficePeriod> {
+        if self.event_range_start() > time || self.event_range_end() < time {
+            return None;
+        }
+
+        self.expand(
+            default_tz,
+            TimeRange {
+                start: time,
+                end: time,
+            },
+        )?
+        .into_iter()
+        .filter(|instance| {
+            instance.end > instance.start && (instance.end - instance.start) % DAY == 0
+        })
+        .filter_map(|instance| {
+            let summary = self
+                .event
+                .components
+                .get(instance.comp_id as usize)?
+                .entries
+                .iter()
+                .find(|entry| matches!(entry.name, ArchivedICalendarProperty::Summary))?
+                .values
+                .first()?
+                .as_text()?;
+            let summary_lcase = summary.to_lowercase();
+
+            summaries
+                .iter()
+                .any(|pattern| summary_lcase.contains(pattern.as_str()))
+                .then(|| OutOfOfficePeriod {
+                    start: instance.start,
+                    end: instance.end,
+                    summary: summary.to_string(),
+                })
+        })
+        .max_by_key(|period| period.end)
+    }
+}
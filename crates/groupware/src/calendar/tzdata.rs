@@ -0,0 +1,176 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::str::FromStr;
+
+use calcard::common::timezone::Tz;
+
+#[derive(Debug, Default)]
+pub struct TzDataImport {
+    pub overrides: Vec<(String, String)>,
+    pub unsupported: Vec<String>,
+}
+
+// Extracts the zones missing from the bundled database out of a tzdata source
+// file (such as "backward" or "tzdata.zi"). Links are mapped to their target and
+// new zones without daylight saving rules are mapped to their fixed offset.
+// When a name appears more than once, the last record replaces the earlier ones.
+pub fn import_tzdata(tzdata: &str) -> TzDataImport {
+    let mut import = TzDataImport::default();
+    let mut zone: Option<(&str, Option<(&str, &str)>)> = None;
+
+    for line in tzdata.lines() {
+        let line = line.split_once('#').map_or(line, |(line, _)| line);
+        let is_continuation = line.starts_with([' ', '\t']);
+        let mut fields = line.split_ascii_whitespace();
+        let Some(first) = fields.next() else {
+            continue;
+        };
+
+        match first {
+            "Zone" | "Z" if !is_continuation => {
+                if let Some(zone) = zone.take() {
+                    add_zone(&mut import, zone);
+                }
+                if let Some(name) = fields.next() {
+                    let rule = fields.next().zip(fields.next());
+                    zone = Some((name, rule.filter(|_| fields.nth(1).is_none())));
+                }
+            }
+            "Link" | "L" if !is_continuation => {
+                if let Some(zone) = zone.take() {
+                    add_zone(&mut import, zone);
+                }
+                if let (Some(target), Some(name)) = (fields.next(), fields.next())
+                    && Tz::from_str(name).is_err()
+                {
+                    // Links may point to a zone imported earlier from the same file
+                    let target = if Tz::from_str(target).is_ok() {
+                        Some(target.to_string())
+                    } else {
+                        import.target(target).map(|target| target.to_string())
+                    };
+                    import.merge(name, target);
+                }
+            }
+            "Rule" | "R" if !is_continuation => {
+                if let Some(zone) = zone.take() {
+                    add_zone(&mut import, zone);
+                }
+            }
+            std_offset => {
+                // Continuation lines only carry an UNTIL field when they are not the last one
+                if let Some((_, rule)) = &mut zone {
+                    *rule = fields
+                        .next()
+                        .map(|rules| (std_offset, rules))
+                        .filter(|_| fields.nth(1).is_none());
+                }
+            }
+        }
+    }
+
+    if let Some(zone) = zone.take() {
+        add_zone(&mut import, zone);
+    }
+
+    import
+}
+
+fn add_zone(import: &mut TzDataImport, (name, rule): (&str, Option<(&str, &str)>)) {
+    if Tz::from_str(name).is_ok() {
+        return;
+    }
+
+    // Zones observing daylight saving time can't be represented with the bundled rules
+    let target = rule
+        .filter(|(_, rules)| *rules == "-")
+        .and_then(|(std_offset, _)| fixed_offset_zone(std_offset))
+        .filter(|target| Tz::from_str(target).is_ok());
+    import.merge(name, target);
+}
+
+impl TzDataImport {
+    fn target(&self, name: &str) -> Option<&str> {
+        self.overrides
+            .iter()
+            .find(|(override_name, _)| override_name == name)
+            .map(|(_, target)| target.as_str())
+    }
+
+    // Updates the record of a zone that was already imported instead of adding a duplicate
+    fn merge(&mut self, name: &str, target: Option<String>) {
+        self.overrides
+            .retain(|(override_name, _)| override_name != name);
+        self.unsupported
+            .retain(|unsupported_name| unsupported_name != name);
+
+        if let Some(target) = target {
+            self.overrides.push((name.to_string(), target));
+        } else {
+            self.unsupported.push(name.to_string());
+        }
+    }
+}
+
+fn fixed_offset_zone(std_offset: &str) -> Option<String> {
+    let (negative, offset) = match std_offset.strip_prefix('-') {
+        Some(offset) => (true, offset),
+        None => (false, std_offset),
+    };
+    let mut parts = offset.split(':');
+    let hours = parts.next()?.parse::<u32>().ok()?;
+    if parts.any(|part| part.parse::<u32>() != Ok(0)) {
+        return None;
+    }
+
+    // POSIX style names have their sign inverted
+    Some(match (hours, negative) {
+        (0, _) => "Etc/UTC".to_string(),
+        (hours, true) => format!("Etc/GMT+{hours}"),
+        (hours, false) => format!("Etc/GMT-{hours}"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::import_tzdata;
+
+    #[test]
+    fn import_merges_zone_records() {
+        let import = import_tzdata(concat!(
+            "# Zone NAME STDOFF RULES FORMAT [UNTIL]\n",
+            "Zone Test/Fixed 5:00 - +05\n",
+            "Zone Test/Changed 1:00 EU CE%sT 2030\n",
+            "\t\t2:00 - +02\n",
+            "Zone Test/Dst 1:00 EU CE%sT\n",
+            "Link Test/Fixed Test/Alias\n",
+            "Link Test/Missing Test/Broken\n",
+            "Link Europe/Paris Test/Relinked\n",
+            "# Records appearing again replace the earlier ones\n",
+            "Zone Test/Fixed -3:00 - -03\n",
+            "Zone Test/Relinked 1:00 EU CE%sT\n",
+            "Zone Test/Broken 0:00 - UTC\n",
+            "Zone Europe/Paris 1:00 EU CE%sT\n",
+        ));
+
+        let mut overrides = import.overrides;
+        overrides.sort_unstable();
+        assert_eq!(
+            overrides,
+            [
+                ("Test/Alias", "Etc/GMT-5"),
+                ("Test/Broken", "Etc/UTC"),
+                ("Test/Changed", "Etc/GMT-2"),
+                ("Test/Fixed", "Etc/GMT+3"),
+            ]
+            .map(|(name, target)| (name.to_string(), target.to_string()))
+        );
+        let mut unsupported = import.unsupported;
+        unsupported.sort_unstable();
+        assert_eq!(unsupported, ["Test/Dst", "Test/Relinked"]);
+    }
+}
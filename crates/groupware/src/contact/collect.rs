@@ -0,0 +1,162 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{AddressBook, ContactCard};
+use crate::{cache::GroupwareCache, calendar::normalize::generate_uid};
+use ahash::AHashSet;
+use calcard::{Entry, Parser};
+use common::{DavName, IDX_EMAIL, KV_CONTACT_COLLECT, Server, auth::AccessToken};
+use jmap_proto::types::collection::{Collection, SyncCollection};
+use store::{query::Filter, write::BatchBuilder};
+use trc::AddContext;
+
+pub trait ContactCollect: Sync + Send {
+    fn collect_contacts(
+        &self,
+        access_token: &AccessToken,
+        recipients: Vec<(String, Option<String>)>,
+    ) -> impl Future<Output = trc::Result<usize>> + Send;
+}
+
+impl ContactCollect for Server {
+    // Adds the recipients that are not yet in any of the user's address books
+    // to the collected addresses book, returns the number of contacts created
+    async fn collect_contacts(
+        &self,
+        access_token: &AccessToken,
+        recipients: Vec<(String, Option<String>)>,
+    ) -> trc::Result<usize> {
+        let Some(book_name) = &self.core.groupware.collected_addressbook_name else {
+            return Ok(0);
+        };
+        let account_id = access_token.primary_id;
+        let mut seen = AHashSet::new();
+        let mut batch = BatchBuilder::new();
+        let mut parent_id = None;
+        let mut added = 0;
+
+        for (email, name) in recipients {
+            if !seen.insert(email.clone())
+                || access_token.emails.iter().any(|e| e == &email)
+                || !self
+                    .store()
+                    .filter(
+                        account_id,
+                        Collection::ContactCard,
+                        vec![Filter::eq(IDX_EMAIL, email.as_bytes().to_vec())],
+                    )
+                    .await
+                    .caused_by(trc::location!())?
+                    .results
+                    .is_empty()
+            {
+                continue;
+            }
+
+            if let Some(rate) = &self.core.groupware.collected_contacts_rate
+                && self
+                    .core
+                    .storage
+                    .lookup
+                    .is_rate_allowed(KV_CONTACT_COLLECT, &account_id.to_be_bytes(), rate, false)
+                    .await
+                    .caused_by(trc::location!())?
+                    .is_some()
+            {
+                break;
+            }
+
+            let uid = generate_uid();
+            let vcard_raw = format!(
+                "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:urn:uuid:{uid}\r\nFN:{}\r\nEMAIL:{email}\r\nEND:VCARD\r\n",
+                escape_text(name.as_deref().filter(|n| !n.is_empty()).unwrap_or(&email))
+            );
+            let Entry::VCard(card) = Parser::new(&vcard_raw).entry() else {
+                continue;
+            };
+            self.has_available_quota(
+                &self.get_resource_token(access_token, account_id).await?,
+                vcard_raw.len() as u64,
+            )
+            .await?;
+
+            // Create the address book on first use
+            let parent_id = match parent_id {
+                Some(parent_id) => parent_id,
+                None => {
+                    let resources = self
+                        .fetch_dav_resources(access_token, account_id, SyncCollection::AddressBook)
+                        .await
+                        .caused_by(trc::location!())?;
+                    let document_id = match resources
+                        .by_path(book_name)
+                        .filter(|resource| resource.is_container())
+                    {
+                        Some(resource) => resource.document_id(),
+                        None => {
+                            let document_id = self
+                                .store()
+                                .assign_document_ids(account_id, Collection::AddressBook, 1)
+                                .await
+                                .caused_by(trc::location!())?;
+                            AddressBook {
+                                name: book_name.clone(),
+                                display_name: self
+                                    .core
+                                    .groupware
+                                    .collected_addressbook_display_name
+                                    .clone()
+                                    .into(),
+                                ..Default::default()
+                            }
+                            .insert(access_token, account_id, document_id, &mut batch)
+                            .caused_by(trc::location!())?;
+                            document_id
+                        }
+                    };
+                    *parent_id.insert(document_id)
+                }
+            };
+
+            let document_id = self
+                .store()
+                .assign_document_ids(account_id, Collection::ContactCard, 1)
+                .await
+                .caused_by(trc::location!())?;
+            ContactCard {
+                names: vec![DavName::new(format!("{uid}.vcf"), parent_id)],
+                card,
+                size: vcard_raw.len() as u32,
+                ..Default::default()
+            }
+            .insert(access_token, account_id, document_id, &mut batch)
+            .caused_by(trc::location!())?;
+            added += 1;
+        }
+
+        if !batch.is_empty() {
+            self.commit_batch(batch).await.caused_by(trc::location!())?;
+        }
+
+        Ok(added)
+    }
+}
+
+pub(crate) fn escape_text(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' | ',' | ';' => {
+                result.push('\\');
+                result.push(ch);
+            }
+            '\n' => result.push_str("\\n"),
+            '\r' => {}
+            _ => result.push(ch),
+        }
+    }
+    result
+}
@@ -0,0 +1,46 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::collect::escape_text;
+use directory::Principal;
+
+// Builds the vCard published in the directory address book for a principal
+pub fn principal_vcard(principal: &Principal) -> String {
+    let mut vcard = format!(
+        "BEGIN:VCARD\r\nVERSION:4.0\r\nKIND:individual\r\nUID:urn:x-principal:{}\r\nFN:{}\r\n",
+        principal.id(),
+        escape_text(
+            principal
+                .description()
+                .filter(|name| !name.trim().is_empty())
+                .unwrap_or(principal.name())
+        )
+    );
+    for (pos, email) in principal.emails.iter().enumerate() {
+        if pos == 0 {
+            vcard.push_str("EMAIL;PREF=1:");
+        } else {
+            vcard.push_str("EMAIL:");
+        }
+        vcard.push_str(email);
+        vcard.push_str("\r\n");
+    }
+    if let Some(phone) = principal.phone() {
+        vcard.push_str("TEL;VALUE=TEXT:");
+        vcard.push_str(&escape_text(phone));
+        vcard.push_str("\r\n");
+    }
+    if let Some(picture) = principal
+        .picture()
+        .filter(|picture| picture.starts_with("https://") || picture.starts_with("data:"))
+    {
+        vcard.push_str("PHOTO:");
+        vcard.push_str(picture);
+        vcard.push_str("\r\n");
+    }
+    vcard.push_str("END:VCARD\r\n");
+    vcard
+}
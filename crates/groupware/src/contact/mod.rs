@@ -4,7 +4,10 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod collect;
+pub mod directory;
 pub mod index;
+pub mod normalize;
 pub mod storage;
 
 use calcard::vcard::VCard;
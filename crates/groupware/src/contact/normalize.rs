@@ -0,0 +1,98 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::calendar::normalize::generate_uid;
+use calcard::vcard::{VCard, VCardEntry, VCardProperty, VCardValue};
+
+// Fixes common vendor quirks in a vCard and returns a description
+// of the problem when the card can't be repaired
+pub fn normalize_vcard(vcard: &mut VCard, existing_uid: Option<&str>) -> Result<(), String> {
+    let mut has_uid = false;
+    let mut has_fn = false;
+
+    for entry in &vcard.entries {
+        match entry.name {
+            VCardProperty::Uid => {
+                if has_uid {
+                    return Err("vCard contains more than one UID property".to_string());
+                }
+                has_uid = true;
+            }
+            VCardProperty::Fn => {
+                has_fn = has_fn
+                    || entry
+                        .values
+                        .first()
+                        .and_then(|v| v.as_text())
+                        .is_some_and(|v| !v.trim().is_empty());
+            }
+            _ => {}
+        }
+    }
+
+    // FN is mandatory, derive it from the structured name or other identifying properties
+    if !has_fn {
+        let name = vcard
+            .properties(&VCardProperty::N)
+            .next()
+            .map(|entry| {
+                // N = family;given;additional;prefix;suffix
+                let parts = entry
+                    .values
+                    .iter()
+                    .map(|v| v.as_text().unwrap_or_default().trim())
+                    .collect::<Vec<_>>();
+                [3, 1, 2, 0, 4]
+                    .into_iter()
+                    .filter_map(|idx| parts.get(idx).copied().filter(|v| !v.is_empty()))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .filter(|name| !name.is_empty())
+            .or_else(|| {
+                [VCardProperty::Org, VCardProperty::Email]
+                    .iter()
+                    .find_map(|prop| {
+                        vcard
+                            .properties(prop)
+                            .next()
+                            .and_then(|entry| entry.values.first())
+                            .and_then(|v| v.as_text())
+                            .map(|v| v.trim())
+                            .filter(|v| !v.is_empty())
+                            .map(|v| v.to_string())
+                    })
+            })
+            .ok_or_else(|| {
+                "vCard is missing the FN property and it could not be derived from N, ORG or EMAIL"
+                    .to_string()
+            })?;
+
+        vcard
+            .entries
+            .retain(|entry| entry.name != VCardProperty::Fn);
+        vcard.entries.push(VCardEntry {
+            group: None,
+            name: VCardProperty::Fn,
+            params: vec![],
+            values: vec![VCardValue::Text(name)],
+        });
+    }
+
+    if !has_uid {
+        let uid = existing_uid
+            .map(|uid| uid.to_string())
+            .unwrap_or_else(|| format!("urn:uuid:{}", generate_uid()));
+        vcard.entries.push(VCardEntry {
+            group: None,
+            name: VCardProperty::Uid,
+            params: vec![],
+            values: vec![VCardValue::Text(uid)],
+        });
+    }
+
+    Ok(())
+}
@@ -5,6 +5,7 @@
  */
 
 pub mod index;
+pub mod share;
 pub mod storage;
 
 use dav_proto::schema::request::DeadProperty;
@@ -0,0 +1,211 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{FileNode, FileProperties};
+use crate::{RFC_3986, cache::GroupwareCache};
+use common::{
+    Server,
+    auth::{AccessToken, oauth::GrantType},
+    storage::index::ObjectIndexBuilder,
+};
+use jmap_proto::types::collection::{Collection, SyncCollection};
+use store::write::{BatchBuilder, now};
+use trc::AddContext;
+
+pub struct FileShareLink {
+    pub account_id: u32,
+    pub document_id: u32,
+    pub created: i64,
+    pub max_downloads: Option<u32>,
+    pub issued_at: u64,
+    pub expires_in: u64,
+}
+
+pub trait FileShare: Sync + Send {
+    fn file_share_url(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        created: i64,
+        expires_in: u64,
+        max_downloads: Option<u32>,
+    ) -> impl Future<Output = trc::Result<Option<String>>> + Send;
+
+    fn file_share_decode(
+        &self,
+        token: &str,
+    ) -> impl Future<Output = trc::Result<FileShareLink>> + Send;
+
+    fn file_share_store(
+        &self,
+        access_token: &AccessToken,
+        folder: &str,
+        name: &str,
+        media_type: Option<&str>,
+        contents: &[u8],
+    ) -> impl Future<Output = trc::Result<(u32, i64)>> + Send;
+}
+
+impl FileShare for Server {
+    async fn file_share_url(
+        &self,
+        account_id: u32,
+        document_id: u32,
+        created: i64,
+        expires_in: u64,
+        max_downloads: Option<u32>,
+    ) -> trc::Result<Option<String>> {
+        let Some(base_url) = &self.core.groupware.file_share_url else {
+            return Ok(None);
+        };
+
+        // The creation time is part of the token so links stop working if the
+        // document id is reused by a different file
+        let mut client_id = format!("{document_id};{created}");
+        if let Some(max_downloads) = max_downloads {
+            client_id.push_str(&format!(";{max_downloads}"));
+        }
+        let token = self
+            .encode_access_token(
+                GrantType::FileShare,
+                account_id,
+                &client_id,
+                expires_in.clamp(1, self.core.groupware.file_share_max_expiration),
+            )
+            .await?;
+
+        Ok(Some(format!(
+            "{base_url}?t={}",
+            percent_encoding::percent_encode(token.as_bytes(), RFC_3986)
+        )))
+    }
+
+    async fn file_share_decode(&self, token: &str) -> trc::Result<FileShareLink> {
+        let token = self
+            .validate_access_token(GrantType::FileShare.into(), token)
+            .await?;
+        let mut parts = token.client_id.split(';');
+
+        parts
+            .next()
+            .and_then(|document_id| document_id.parse().ok())
+            .zip(parts.next().and_then(|created| created.parse().ok()))
+            .and_then(|(document_id, created)| {
+                let max_downloads = match parts.next() {
+                    Some(max_downloads) => Some(max_downloads.parse().ok()?),
+                    None => None,
+                };
+                Some(FileShareLink {
+                    account_id: token.account_id,
+                    document_id,
+                    created,
+                    max_downloads,
+                    issued_at: token.issued_at,
+                    expires_in: token.expires_in,
+                })
+            })
+            .ok_or_else(|| {
+                trc::AuthEvent::Error
+                    .into_err()
+                    .details("Invalid file share token")
+            })
+    }
+
+    async fn file_share_store(
+        &self,
+        access_token: &AccessToken,
+        folder: &str,
+        name: &str,
+        media_type: Option<&str>,
+        contents: &[u8],
+    ) -> trc::Result<(u32, i64)> {
+        let account_id = access_token.primary_id;
+        let resource_token = self.get_resource_token(access_token, account_id).await?;
+        self.has_available_quota(&resource_token, contents.len() as u64)
+            .await?;
+
+        let files = self
+            .fetch_dav_resources(access_token, account_id, SyncCollection::FileNode)
+            .await
+            .caused_by(trc::location!())?;
+        let now = now() as i64;
+        let mut batch = BatchBuilder::new();
+
+        // Create the folder on first use
+        let parent_id = match files.by_path(folder) {
+            Some(resource) if resource.is_container() => resource.document_id() + 1,
+            Some(_) => {
+                return Err(trc::StoreEvent::UnexpectedError
+                    .into_err()
+                    .details("Attachment folder name is taken by a file"));
+            }
+            None => {
+                let document_id = self
+                    .store()
+                    .assign_document_ids(account_id, Collection::FileNode, 1)
+                    .await
+                    .caused_by(trc::location!())?;
+                FileNode {
+                    parent_id: 0,
+                    name: folder.to_string(),
+                    ..Default::default()
+                }
+                .insert(access_token, account_id, document_id, &mut batch)
+                .caused_by(trc::location!())?;
+                document_id + 1
+            }
+        };
+
+        // Avoid clashing with existing names
+        let mut file_name = name.replace('/', "_");
+        let mut suffix = 1;
+        while files.by_path(&format!("{folder}/{file_name}")).is_some() {
+            suffix += 1;
+            file_name = match name.rsplit_once('.') {
+                Some((stem, ext)) if !stem.is_empty() => format!("{stem} ({suffix}).{ext}"),
+                _ => format!("{name} ({suffix})"),
+            }
+            .replace('/', "_");
+        }
+
+        let blob_hash = self
+            .put_blob(account_id, contents, false)
+            .await
+            .caused_by(trc::location!())?
+            .hash;
+        let document_id = self
+            .store()
+            .assign_document_ids(account_id, Collection::FileNode, 1)
+            .await
+            .caused_by(trc::location!())?;
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::FileNode)
+            .create_document(document_id)
+            .custom(
+                ObjectIndexBuilder::<(), _>::new()
+                    .with_changes(FileNode {
+                        parent_id,
+                        name: file_name,
+                        file: Some(FileProperties {
+                            blob_hash,
+                            size: contents.len() as u32,
+                            media_type: media_type.map(|v| v.to_string()),
+                            executable: false,
+                        }),
+                        created: now,
+                        modified: now,
+                        ..Default::default()
+                    })
+                    .with_tenant_id(access_token),
+            )
+            .caused_by(trc::location!())?
+            .commit_point();
+        self.commit_batch(batch).await.caused_by(trc::location!())?;
+
+        Ok((document_id, now))
+    }
+}
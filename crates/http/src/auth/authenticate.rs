@@ -90,7 +90,12 @@ impl Authenticator for Server {
                         session.session_id,
                         session.remote_ip,
                     )
-                    .with_api_access(allow_api_access),
+                    .with_api_access(allow_api_access)
+                    .with_client(
+                        req.headers()
+                            .get(header::USER_AGENT)
+                            .and_then(|value| value.to_str().ok()),
+                    ),
                 )
                 .await?;
 
@@ -189,7 +189,7 @@ impl FormHandler for Server {
                 .write(batch.build_all())
                 .await
                 .caused_by(trc::location!())?;
-            self.blob_store()
+            self.queue_blob_store()
                 .put_blob(message_blob.as_slice(), message.as_ref())
                 .await
                 .caused_by(trc::location!())?;
@@ -8,7 +8,12 @@ pub mod auth;
 pub mod autoconfig;
 pub mod form;
 pub mod management;
+pub mod moderation;
+pub mod proxy;
 pub mod request;
+pub mod share;
+pub mod unsubscribe;
+pub mod wkd;
 
 use std::sync::Arc;
 
@@ -0,0 +1,244 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::principal::PrincipalManager;
+use common::{Server, auth::AccessToken};
+use directory::{
+    Permission, QueryParams,
+    backend::internal::{
+        PrincipalField, PrincipalUpdate, PrincipalValue,
+        manage::{self, ManageDirectory, UpdatePrincipal, not_found},
+    },
+    core::principal::{AVATAR_CONTENT_TYPES, MAX_AVATAR_LEN},
+};
+use http_proto::{request::decode_path_element, *};
+use hyper::{Method, StatusCode, header};
+use mail_builder::encoders::base64::base64_encode;
+use mail_parser::decoders::base64::base64_decode;
+use serde_json::json;
+use std::future::Future;
+use trc::AddContext;
+use utils::BlobHash;
+
+pub trait AvatarManagement: Sync + Send {
+    fn handle_manage_avatar(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_account_avatar_update(
+        &self,
+        req: &HttpRequest,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn update_avatar(
+        &self,
+        req: &HttpRequest,
+        principal_id: u32,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl AvatarManagement for Server {
+    async fn handle_manage_avatar(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let name = decode_path_element(path.get(1).copied().unwrap_or_default());
+        let principal_id = if name.contains('@') {
+            self.core.storage.directory.email_to_id(&name).await?
+        } else {
+            self.store()
+                .get_principal_id(&name)
+                .await
+                .caused_by(trc::location!())?
+        }
+        .ok_or_else(|| not_found(name.to_string()))?;
+
+        match *req.method() {
+            Method::GET | Method::HEAD => {
+                // Avatars are visible to all users of the same tenant
+                let principal = self
+                    .core
+                    .storage
+                    .directory
+                    .query(QueryParams::id(principal_id).with_return_member_of(false))
+                    .await?
+                    .filter(|principal| {
+                        access_token
+                            .tenant
+                            .is_none_or(|tenant| principal.tenant == Some(tenant.id))
+                    })
+                    .ok_or_else(|| not_found(name.to_string()))?;
+                // Pictures that are links are not served
+                let Some((content_type, contents)) = principal.picture().and_then(|picture| {
+                    let (content_type, data) = picture
+                        .strip_prefix("data:")?
+                        .split_once(";base64,")
+                        .filter(|(content_type, _)| AVATAR_CONTENT_TYPES.contains(content_type))?;
+                    Some((content_type, base64_decode(data.as_bytes())?))
+                }) else {
+                    return Ok(HttpResponse::new(StatusCode::NOT_FOUND));
+                };
+
+                let etag = format!("\"{}\"", BlobHash::generate(&contents).to_hex());
+                let response = if req
+                    .headers()
+                    .get(header::IF_NONE_MATCH)
+                    .and_then(|value| value.to_str().ok())
+                    .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag))
+                {
+                    HttpResponse::new(StatusCode::NOT_MODIFIED)
+                } else if req.method() == Method::HEAD {
+                    HttpResponse::new(StatusCode::OK)
+                        .with_content_type(content_type)
+                        .with_content_length(contents.len())
+                } else {
+                    HttpResponse::new(StatusCode::OK)
+                        .with_content_type(content_type)
+                        .with_binary_body(contents)
+                };
+
+                Ok(response
+                    .with_etag(etag)
+                    .with_cache_control("private, no-cache")
+                    .with_header("X-Content-Type-Options", "nosniff"))
+            }
+            Method::PUT | Method::DELETE => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::IndividualUpdate)?;
+
+                self.update_avatar(req, principal_id, body, access_token)
+                    .await
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+
+    async fn handle_account_avatar_update(
+        &self,
+        req: &HttpRequest,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        if access_token.primary_id() == u32::MAX {
+            return Err(manage::error(
+                "Fallback administrator accounts do not support avatars",
+                None::<u32>,
+            ));
+        }
+
+        self.update_avatar(req, access_token.primary_id(), body, access_token)
+            .await
+    }
+
+    // Images are uploaded as the raw request body and stored as a data URI picture
+    async fn update_avatar(
+        &self,
+        req: &HttpRequest,
+        principal_id: u32,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        self.assert_supported_directory(false)?;
+
+        let avatar = if req.method() == Method::DELETE {
+            String::new()
+        } else {
+            let body = body
+                .filter(|body| !body.is_empty())
+                .ok_or_else(|| manage::error("Missing avatar image", None::<u32>))?;
+
+            // The content type is obtained from the image itself rather than the request
+            let content_type = image_content_type(&body).ok_or_else(|| {
+                manage::error(
+                    "Unsupported avatar image",
+                    Some("Avatars must be PNG, JPEG, GIF or WebP images"),
+                )
+            })?;
+            if req
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split(';').next())
+                .is_some_and(|value| !value.trim().eq_ignore_ascii_case(content_type))
+            {
+                return Err(manage::error(
+                    "Unsupported avatar image",
+                    Some("The content type does not match the image"),
+                ));
+            }
+
+            let avatar = format!(
+                "data:{content_type};base64,{}",
+                String::from_utf8(base64_encode(&body).unwrap_or_default()).unwrap_or_default()
+            );
+            if avatar.len() > MAX_AVATAR_LEN {
+                return Err(manage::error(
+                    "Avatar too large",
+                    Some(format!(
+                        "Avatars can't be larger than {} bytes",
+                        MAX_AVATAR_LEN / 4 * 3
+                    )),
+                ));
+            }
+            avatar
+        };
+
+        let changed_principals = self
+            .core
+            .storage
+            .data
+            .update_principal(
+                UpdatePrincipal::by_id(principal_id)
+                    .with_updates(vec![PrincipalUpdate::set(
+                        PrincipalField::Picture,
+                        PrincipalValue::String(avatar),
+                    )])
+                    .with_tenant(access_token.tenant.map(|t| t.id)),
+            )
+            .await?;
+        self.invalidate_principal_caches(changed_principals).await;
+
+        Ok(JsonResponse::new(json!({
+            "data": (),
+        }))
+        .into_http_response())
+    }
+}
+
+fn image_content_type(contents: &[u8]) -> Option<&'static str> {
+    match contents {
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("image/png"),
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("image/gif"),
+        [
+            b'R',
+            b'I',
+            b'F',
+            b'F',
+            _,
+            _,
+            _,
+            _,
+            b'W',
+            b'E',
+            b'B',
+            b'P',
+            ..,
+        ] => Some("image/webp"),
+        _ => None,
+    }
+}
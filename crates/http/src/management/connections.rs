@@ -0,0 +1,104 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::Permission;
+use http_proto::*;
+use serde::Serialize;
+use serde_json::json;
+use std::{future::Future, net::IpAddr};
+use utils::url_params::UrlParams;
+
+#[derive(Serialize)]
+struct AccountConnections {
+    account: String,
+    #[serde(rename = "accountId")]
+    account_id: u32,
+    protocol: &'static str,
+    connections: u64,
+}
+
+#[derive(Serialize)]
+struct IpConnections {
+    ip: IpAddr,
+    protocol: &'static str,
+    connections: u64,
+}
+
+pub trait ConnectionManagement: Sync + Send {
+    fn handle_view_connections(
+        &self,
+        req: &HttpRequest,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ConnectionManagement for Server {
+    async fn handle_view_connections(
+        &self,
+        req: &HttpRequest,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        // Validate the access token
+        access_token.assert_has_permission(Permission::ConnectionsView)?;
+
+        let params = UrlParams::new(req.uri().query());
+        let protocol = params.get("protocol");
+        let limit: usize = params.parse("limit").unwrap_or(100);
+        let tenant_id = access_token.tenant.map(|tenant| tenant.id);
+
+        // Open connections by principal
+        let mut accounts = Vec::new();
+        let mut account_connections = self.inner.data.connections.account_connections();
+        account_connections.sort_unstable_by_key(|b| std::cmp::Reverse(b.1));
+        for ((account_protocol, account_id), connections) in account_connections {
+            if accounts.len() >= limit {
+                break;
+            } else if protocol.is_some_and(|protocol| protocol != account_protocol.as_str()) {
+                continue;
+            }
+
+            let Ok(account_token) = self.get_access_token(account_id).await else {
+                continue;
+            };
+            if tenant_id.is_none() || account_token.tenant.map(|tenant| tenant.id) == tenant_id {
+                accounts.push(AccountConnections {
+                    account: account_token.name.clone(),
+                    account_id,
+                    protocol: account_protocol.as_str(),
+                    connections,
+                });
+            }
+        }
+
+        // Tenants can only see connections from their own accounts
+        let mut ips = Vec::new();
+        if tenant_id.is_none() {
+            let mut ip_connections = self.inner.data.connections.ip_connections();
+            ip_connections.sort_unstable_by_key(|b| std::cmp::Reverse(b.1));
+            ips = ip_connections
+                .into_iter()
+                .filter(|((ip_protocol, _), _)| {
+                    protocol.is_none_or(|protocol| protocol == ip_protocol.as_str())
+                })
+                .take(limit)
+                .map(|((ip_protocol, ip), connections)| IpConnections {
+                    ip,
+                    protocol: ip_protocol.as_str(),
+                    connections,
+                })
+                .collect();
+        }
+
+        Ok(JsonResponse::new(json!({
+            "data": {
+                "accounts": accounts,
+                "ips": ips,
+            },
+        }))
+        .into_http_response())
+    }
+}
@@ -0,0 +1,225 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::backend::internal::manage::{self, not_found};
+use email::message::alias::{DisposableAlias, DisposableAliasManager};
+use http_proto::*;
+use hyper::Method;
+use jmap_proto::types::id::Id;
+use serde::Deserialize;
+use serde_json::json;
+use std::future::Future;
+use store::write::now;
+
+#[derive(Debug, Deserialize)]
+struct DisposableAliasRequest {
+    #[serde(default)]
+    domain: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    enabled: Option<bool>,
+    #[serde(default)]
+    expires: Option<u64>,
+}
+
+pub trait DisposableAliasManagement: Sync + Send {
+    fn handle_disposable_aliases(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl DisposableAliasManagement for Server {
+    async fn handle_disposable_aliases(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        if !self.core.jmap.disposable_alias_enable {
+            return Err(manage::unsupported(
+                "Disposable aliases have been disabled by the system administrator",
+            ));
+        }
+
+        let account_id = access_token.primary_id();
+        let alias_id = path
+            .get(2)
+            .map(|id| {
+                Id::from_bytes(id.as_bytes())
+                    .map(|id| id.document_id())
+                    .ok_or_else(|| not_found(id.to_string()))
+            })
+            .transpose()?;
+
+        match (alias_id, req.method()) {
+            (None, &Method::GET) => {
+                let aliases = self.get_disposable_aliases(account_id).await?;
+                let now = now();
+                let mut results = Vec::with_capacity(aliases.items.len());
+                for alias in &aliases.items {
+                    results.push(json!({
+                        "id": Id::from(alias.id).to_string(),
+                        "address": alias.address,
+                        "description": alias.description,
+                        "enabled": alias.enabled,
+                        "active": alias.is_active(now),
+                        "created": alias.created,
+                        "expires": alias.expires,
+                        "received": self.disposable_alias_received(&alias.address).await?,
+                    }));
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": results,
+                }))
+                .into_http_response())
+            }
+            (None, &Method::POST) => {
+                let request = parse_request(body.as_deref())?;
+
+                // Aliases are created on one of the account's domains
+                let domain = if let Some(domain) = request.domain {
+                    let domain = domain.trim().to_lowercase();
+                    if !access_token.emails.iter().any(|email| {
+                        email
+                            .rsplit_once('@')
+                            .is_some_and(|(_, email_domain)| email_domain == domain)
+                    }) {
+                        return Err(manage::error(
+                            "Invalid domain",
+                            Some("Aliases can only be created on the account's own domains"),
+                        ));
+                    }
+                    domain
+                } else {
+                    access_token
+                        .emails
+                        .first()
+                        .and_then(|email| email.rsplit_once('@'))
+                        .map(|(_, domain)| domain.to_string())
+                        .ok_or_else(|| {
+                            manage::error("Account has no email addresses", None::<u32>)
+                        })?
+                };
+
+                let address = self
+                    .generate_disposable_alias(&domain)
+                    .await?
+                    .ok_or_else(|| {
+                        manage::error("Failed to generate a unique alias", None::<u32>)
+                    })?;
+                let created = now();
+                let max_aliases = self.core.jmap.disposable_alias_max;
+                let id = self
+                    .update_disposable_aliases(account_id, |aliases| {
+                        if aliases.items.len() >= max_aliases {
+                            return Err(manage::error(
+                                "Disposable alias limit reached",
+                                Some(format!(
+                                    "Up to {max_aliases} disposable aliases are allowed"
+                                )),
+                            ));
+                        }
+
+                        let id = aliases.next_id;
+                        aliases.next_id += 1;
+                        aliases.items.push(DisposableAlias {
+                            id,
+                            address: address.clone(),
+                            description: request.description.clone().unwrap_or_default(),
+                            enabled: request.enabled.unwrap_or(true),
+                            created,
+                            expires: request.expires,
+                        });
+                        Ok(id)
+                    })
+                    .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "id": Id::from(id).to_string(),
+                        "address": address,
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some(alias_id), &Method::PUT) => {
+                let request = parse_request(body.as_deref())?;
+                self.update_disposable_aliases(account_id, |aliases| {
+                    let alias = aliases
+                        .items
+                        .iter_mut()
+                        .find(|alias| alias.id == alias_id)
+                        .ok_or_else(|| not_found(Id::from(alias_id).to_string()))?;
+                    if let Some(description) = &request.description {
+                        alias.description = description.clone();
+                    }
+                    if let Some(enabled) = request.enabled {
+                        alias.enabled = enabled;
+                    }
+                    if request.expires.is_some() {
+                        alias.expires = request.expires;
+                    }
+                    Ok(())
+                })
+                .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            (Some(alias_id), &Method::DELETE) => {
+                self.update_disposable_aliases(account_id, |aliases| {
+                    let num_aliases = aliases.items.len();
+                    aliases.items.retain(|alias| alias.id != alias_id);
+                    if aliases.items.len() != num_aliases {
+                        Ok(())
+                    } else {
+                        Err(not_found(Id::from(alias_id).to_string()))
+                    }
+                })
+                .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+fn parse_request(body: Option<&[u8]>) -> trc::Result<DisposableAliasRequest> {
+    let request = serde_json::from_slice::<DisposableAliasRequest>(body.unwrap_or_default())
+        .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
+    if request
+        .description
+        .as_ref()
+        .is_some_and(|description| description.len() > 255)
+    {
+        return Err(manage::error(
+            "Invalid description",
+            Some("Descriptions cannot be longer than 255 characters"),
+        ));
+    }
+    if request.expires.is_some_and(|expires| expires <= now()) {
+        return Err(manage::error(
+            "Invalid expiration date",
+            Some("The expiration date must be in the future"),
+        ));
+    }
+
+    Ok(request)
+}
@@ -6,11 +6,15 @@
 
 use common::{Server, auth::AccessToken};
 use directory::{
-    Permission,
-    backend::internal::manage::{self},
+    DomainStatus, Permission, Type,
+    backend::internal::{
+        PrincipalField, PrincipalUpdate, PrincipalValue,
+        manage::{self, ManageDirectory, UpdatePrincipal, not_found},
+    },
 };
 
 use hyper::Method;
+use mail_auth::hickory_resolver::{Name, proto::rr::RecordType};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sha1::Digest;
@@ -19,7 +23,11 @@ use x509_parser::parse_x509_certificate;
 
 use crate::management::dkim::{Algorithm, obtain_dkim_public_key};
 use http_proto::{request::decode_path_element, *};
-use std::future::Future;
+use std::{future::Future, str::FromStr};
+use store::ahash::AHashMap;
+use trc::AddContext;
+
+const VERIFICATION_PREFIX: &str = "_stalwart-verification";
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DnsRecord {
@@ -29,6 +37,37 @@ pub struct DnsRecord {
     content: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct DnsRecordStatus {
+    #[serde(flatten)]
+    record: DnsRecord,
+    status: DnsRecordCheck,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    found: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DnsRecordCheck {
+    Ok,
+    Missing,
+    Mismatch,
+    Error,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainOnboarding {
+    domain: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    record: Option<DnsRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    verified_by: Option<&'static str>,
+}
+
 pub trait DnsManagement: Sync + Send {
     fn handle_manage_dns(
         &self,
@@ -41,6 +80,19 @@ pub trait DnsManagement: Sync + Send {
         &self,
         domain_name: &str,
     ) -> impl Future<Output = trc::Result<Vec<DnsRecord>>> + Send;
+
+    fn verify_dns_records(
+        &self,
+        domain_name: &str,
+    ) -> impl Future<Output = trc::Result<Vec<DnsRecordStatus>>> + Send;
+
+    fn handle_domain_onboarding(
+        &self,
+        req: &HttpRequest,
+        domain_name: &str,
+        action: Option<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<DomainOnboarding>> + Send;
 }
 
 impl DnsManagement for Server {
@@ -66,6 +118,38 @@ impl DnsManagement for Server {
                 }))
                 .into_http_response())
             }
+            ("verify", Some(domain), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::DomainGet)?;
+
+                // Compare the expected records with the published ones
+                let domain = decode_path_element(domain);
+                Ok(JsonResponse::new(json!({
+                    "data": self.verify_dns_records(domain.as_ref()).await?,
+                }))
+                .into_http_response())
+            }
+            ("onboarding", Some(domain), &Method::GET | &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(if req.method() == Method::GET {
+                    Permission::DomainGet
+                } else {
+                    Permission::DomainUpdate
+                })?;
+
+                let domain = decode_path_element(domain).to_lowercase();
+                Ok(JsonResponse::new(json!({
+                    "data": self
+                        .handle_domain_onboarding(
+                            req,
+                            &domain,
+                            path.get(3).copied(),
+                            access_token,
+                        )
+                        .await?,
+                }))
+                .into_http_response())
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
@@ -75,6 +159,23 @@ impl DnsManagement for Server {
         let server_name = &self.core.network.server_name;
         let mut records = Vec::new();
 
+        // Add the ownership record of domains pending onboarding
+        if let Some(pinfo) = self
+            .store()
+            .get_principal_info(domain_name)
+            .await
+            .caused_by(trc::location!())?
+            .filter(|pinfo| pinfo.typ == Type::Domain)
+            && let Some(principal) = self
+                .store()
+                .get_principal(pinfo.id)
+                .await
+                .caused_by(trc::location!())?
+            && let Some(token) = principal.verification_token()
+        {
+            records.push(verification_record(domain_name, token));
+        }
+
         // Obtain DKIM keys
         let mut keys = Config::default();
         let mut signature_ids = Vec::new();
@@ -184,7 +285,7 @@ impl DnsManagement for Server {
                 }
                 ("http", _) if is_tls => {
                     has_https = true;
-                    for service in ["jmap", "caldavs", "carddavs"] {
+                    for service in ["jmap", "caldavs", "carddavs", "autodiscover"] {
                         records.push(DnsRecord {
                             typ: "SRV".to_string(),
                             name: format!("_{service}._tcp.{domain_name}.",),
@@ -287,4 +388,298 @@ impl DnsManagement for Server {
 
         Ok(records)
     }
+
+    async fn verify_dns_records(&self, domain_name: &str) -> trc::Result<Vec<DnsRecordStatus>> {
+        let server_name = &self.core.network.server_name;
+        let mut lookups: AHashMap<(String, RecordType), Result<Vec<String>, String>> =
+            AHashMap::new();
+        let mut results = Vec::new();
+
+        for record in self.build_dns_records(domain_name).await? {
+            let Ok(record_type) = RecordType::from_str(&record.typ) else {
+                continue;
+            };
+            let key = (record.name.to_lowercase(), record_type);
+            if !lookups.contains_key(&key) {
+                let found = lookup_dns_record(self, &key.0, record_type).await;
+                lookups.insert(key.clone(), found);
+            }
+
+            let (status, found, error) = match &lookups[&key] {
+                Ok(found) => {
+                    // Only TXT records of the same kind are compared
+                    let found = if record_type == RecordType::TXT {
+                        let tag = txt_tag(&record.content);
+                        found
+                            .iter()
+                            .filter(|value| txt_tag(value).eq_ignore_ascii_case(tag))
+                            .cloned()
+                            .collect::<Vec<_>>()
+                    } else {
+                        found.clone()
+                    };
+
+                    let status = if found
+                        .iter()
+                        .any(|value| record_matches(record_type, &record.content, value))
+                    {
+                        DnsRecordCheck::Ok
+                    } else if record_type == RecordType::CNAME
+                        && same_addresses(self, &record.name, server_name).await
+                    {
+                        // Address records pointing to this server are also accepted
+                        DnsRecordCheck::Ok
+                    } else if found.is_empty() {
+                        DnsRecordCheck::Missing
+                    } else {
+                        DnsRecordCheck::Mismatch
+                    };
+
+                    (status, found, None)
+                }
+                Err(err) => (DnsRecordCheck::Error, vec![], Some(err.clone())),
+            };
+
+            results.push(DnsRecordStatus {
+                record,
+                status,
+                found,
+                error,
+            });
+        }
+
+        Ok(results)
+    }
+
+    async fn handle_domain_onboarding(
+        &self,
+        req: &HttpRequest,
+        domain_name: &str,
+        action: Option<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<DomainOnboarding> {
+        let tenant_id = access_token.tenant.map(|t| t.id);
+        let pinfo = self
+            .store()
+            .get_principal_info(domain_name)
+            .await
+            .caused_by(trc::location!())?
+            .filter(|pinfo| pinfo.typ == Type::Domain && pinfo.has_tenant_access(tenant_id))
+            .ok_or_else(|| not_found(domain_name.to_string()))?;
+        let principal = self
+            .store()
+            .get_principal(pinfo.id)
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| not_found(domain_name.to_string()))?;
+        let status = principal.domain_status();
+        let mut verified_by = None;
+
+        let new_status = match (req.method(), action) {
+            (&Method::GET, None) => None,
+            (&Method::POST, Some("verify")) => {
+                if status == DomainStatus::PendingVerification {
+                    verified_by = verify_domain_ownership(
+                        self,
+                        domain_name,
+                        principal.verification_token().unwrap_or_default(),
+                    )
+                    .await;
+                    verified_by.map(|_| DomainStatus::Verified)
+                } else {
+                    None
+                }
+            }
+            (&Method::POST, Some("activate")) => match status {
+                DomainStatus::Verified => Some(DomainStatus::Active),
+                DomainStatus::PendingVerification => {
+                    return Err(manage::error(
+                        "Domain not verified",
+                        "The domain ownership has to be verified before activation".into(),
+                    ));
+                }
+                DomainStatus::Active => None,
+            },
+            _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+        };
+
+        if let Some(new_status) = new_status {
+            let changed_principals = self
+                .core
+                .storage
+                .data
+                .update_principal(
+                    UpdatePrincipal::by_id(pinfo.id)
+                        .with_updates(vec![PrincipalUpdate::set(
+                            PrincipalField::DomainStatus,
+                            PrincipalValue::String(new_status.as_str().to_string()),
+                        )])
+                        .with_tenant(tenant_id),
+                )
+                .await?;
+            self.invalidate_principal_caches(changed_principals).await;
+            self.invalidate_domain_cache(domain_name);
+        }
+
+        let status = new_status.unwrap_or(status);
+        Ok(DomainOnboarding {
+            domain: domain_name.to_string(),
+            status: status.as_str(),
+            record: principal
+                .verification_token()
+                .filter(|_| status != DomainStatus::Active)
+                .map(|token| verification_record(domain_name, token)),
+            verified_by,
+        })
+    }
+}
+
+// Ownership is proven by publishing the verification token or by pointing the MX at this server
+async fn verify_domain_ownership(
+    server: &Server,
+    domain_name: &str,
+    token: &str,
+) -> Option<&'static str> {
+    let record = verification_record(domain_name, token);
+    if lookup_dns_record(server, &record.name, RecordType::TXT)
+        .await
+        .is_ok_and(|found| found.iter().any(|value| value.trim() == record.content))
+    {
+        return Some("txt");
+    }
+
+    let server_name = normalize_name(&server.core.network.server_name);
+    if lookup_dns_record(server, &format!("{domain_name}."), RecordType::MX)
+        .await
+        .is_ok_and(|found| {
+            found.iter().any(|value| {
+                normalize_name(value.rsplit(' ').next().unwrap_or_default()) == server_name
+            })
+        })
+    {
+        return Some("mx");
+    }
+
+    None
+}
+
+fn verification_record(domain_name: &str, token: &str) -> DnsRecord {
+    DnsRecord {
+        typ: "TXT".to_string(),
+        name: format!("{VERIFICATION_PREFIX}.{domain_name}."),
+        content: format!("stalwart-verification={token}"),
+    }
+}
+
+async fn lookup_dns_record(
+    server: &Server,
+    name: &str,
+    record_type: RecordType,
+) -> Result<Vec<String>, String> {
+    let name = Name::from_str_relaxed(name).map_err(|err| err.to_string())?;
+    let lookup = match server
+        .core
+        .smtp
+        .resolvers
+        .dnssec
+        .resolver
+        .lookup(name, record_type)
+        .await
+        .map_err(mail_auth::Error::from)
+    {
+        Ok(lookup) => lookup,
+        Err(mail_auth::Error::DnsRecordNotFound(_)) => return Ok(vec![]),
+        Err(err) => return Err(err.to_string()),
+    };
+
+    Ok(lookup
+        .record_iter()
+        .filter(|record| record.record_type() == record_type)
+        .filter_map(|record| {
+            let data = record.data();
+            match record_type {
+                RecordType::TXT => data.as_txt().map(|txt| {
+                    txt.txt_data()
+                        .iter()
+                        .map(|part| String::from_utf8_lossy(part))
+                        .collect::<String>()
+                }),
+                RecordType::MX => data
+                    .as_mx()
+                    .map(|mx| format!("{} {}", mx.preference(), mx.exchange())),
+                RecordType::CNAME => data.as_cname().map(|cname| cname.0.to_string()),
+                RecordType::SRV => data.as_srv().map(|srv| {
+                    format!(
+                        "{} {} {} {}",
+                        srv.priority(),
+                        srv.weight(),
+                        srv.port(),
+                        srv.target()
+                    )
+                }),
+                RecordType::TLSA => data.as_tlsa().map(|tlsa| {
+                    format!(
+                        "{} {} {} {}",
+                        u8::from(tlsa.cert_usage()),
+                        u8::from(tlsa.selector()),
+                        u8::from(tlsa.matching()),
+                        tlsa.cert_data()
+                            .iter()
+                            .map(|byte| format!("{byte:02x}"))
+                            .collect::<String>()
+                    )
+                }),
+                _ => Some(data.to_string()),
+            }
+        })
+        .collect())
+}
+
+async fn same_addresses(server: &Server, name: &str, server_name: &str) -> bool {
+    let resolver = &server.core.smtp.resolvers.dnssec.resolver;
+    match (
+        resolver.lookup_ip(name).await,
+        resolver.lookup_ip(format!("{server_name}.")).await,
+    ) {
+        (Ok(addrs), Ok(expected)) => {
+            let expected = expected.iter().collect::<Vec<_>>();
+            let mut addrs = addrs.iter().peekable();
+            addrs.peek().is_some() && addrs.all(|addr| expected.contains(&addr))
+        }
+        _ => false,
+    }
+}
+
+// Returns the version tag of a TXT record, such as "v=spf1"
+fn txt_tag(value: &str) -> &str {
+    value.trim().split([';', ' ']).next().unwrap_or_default()
+}
+
+fn record_matches(record_type: RecordType, expected: &str, found: &str) -> bool {
+    match record_type {
+        RecordType::TXT => normalize_txt(expected) == normalize_txt(found),
+        RecordType::MX => {
+            // Any preference is accepted as long as the exchange matches
+            normalize_name(expected.rsplit(' ').next().unwrap_or_default())
+                == normalize_name(found.rsplit(' ').next().unwrap_or_default())
+        }
+        _ => normalize_name(expected) == normalize_name(found),
+    }
+}
+
+fn normalize_txt(value: &str) -> String {
+    value
+        .split(';')
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn normalize_name(value: &str) -> String {
+    value
+        .split_whitespace()
+        .map(|part| part.trim_end_matches('.').to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
 }
@@ -9,6 +9,7 @@
  */
 
 use std::{
+    collections::hash_map::Entry,
     fmt::Write,
     time::{Duration, Instant},
 };
@@ -17,11 +18,14 @@ use common::{
     Server,
     auth::{AccessToken, oauth::GrantType},
     telemetry::{
-        metrics::store::{Metric, MetricsStore},
+        metrics::store::{Metric, MetricsStore, UsageStore},
         tracers::store::{TracingQuery, TracingStore},
     },
 };
-use directory::{Permission, backend::internal::manage};
+use directory::{
+    Permission,
+    backend::internal::manage::{self, ManageDirectory},
+};
 use http_body_util::{StreamBody, combinators::BoxBody};
 use http_proto::*;
 use hyper::{
@@ -34,7 +38,10 @@ use std::future::Future;
 use store::ahash::{AHashMap, AHashSet};
 use trc::{
     Collector, DeliveryEvent, EventType, Key, MetricType, QueueEvent, Value,
-    ipc::{bitset::Bitset, subscriber::SubscriberBuilder},
+    ipc::{
+        bitset::Bitset,
+        subscriber::{Interests, SubscriberBuilder},
+    },
     serializers::json::JsonEventSerializer,
 };
 use utils::{snowflake::SnowflakeIdGenerator, url_params::UrlParams};
@@ -180,17 +187,34 @@ impl TelemetryApi for Server {
 
                 let mut key_filters = AHashMap::new();
                 let mut filter = None;
+                let mut interests: Option<Interests> = None;
 
                 for (key, value) in params.into_inner() {
                     if key == "filter" {
                         filter = value.into_owned().into();
+                    } else if key == "events" {
+                        // Comma separated list of event names, a trailing '*' matches a prefix
+                        let interests = interests.get_or_insert_with(Default::default);
+                        for pattern in value.split(',').map(|v| v.trim()).filter(|v| !v.is_empty())
+                        {
+                            for event_type in EventType::variants() {
+                                let name = event_type.name();
+                                if pattern == "*"
+                                    || pattern
+                                        .strip_suffix('*')
+                                        .map_or(name == pattern, |prefix| name.starts_with(prefix))
+                                {
+                                    interests.set(event_type);
+                                }
+                            }
+                        }
                     } else if let Some(key) = Key::try_parse(key.to_ascii_lowercase().as_str()) {
                         key_filters.insert(key, value.into_owned());
                     }
                 }
 
                 let (_, mut rx) = SubscriberBuilder::new("live-tracer".to_string())
-                    .with_interests(Box::new(Bitset::all()))
+                    .with_interests(interests.unwrap_or_else(|| Box::new(Bitset::all())))
                     .with_lossy(false)
                     .register();
                 let throttle = Duration::from_secs(1);
@@ -240,10 +264,10 @@ impl TelemetryApi for Server {
                                                         Value::Bool(false) => needle == "false",
                                                         Value::Ipv4(haystack) => haystack.to_string().contains(needle),
                                                         Value::Ipv6(haystack) => haystack.to_string().contains(needle),
+                                                        Value::UInt(haystack) => needle.parse::<u64>().is_ok_and(|needle| needle == *haystack),
+                                                        Value::Int(haystack) => needle.parse::<i64>().is_ok_and(|needle| needle == *haystack),
                                                         Value::Event(_) |
                                                         Value::Array(_) |
-                                                        Value::UInt(_) |
-                                                        Value::Int(_) |
                                                         Value::Float(_) |
                                                         Value::Duration(_) |
                                                         Value::Bytes(_) |
@@ -430,6 +454,74 @@ impl TelemetryApi for Server {
                 }))
                 .into_http_response())
             }
+            ("usage", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MetricsList)?;
+
+                let before = params
+                    .parse::<Timestamp>("before")
+                    .map(|t| t.into_inner())
+                    .unwrap_or(u64::MAX);
+                let after = params
+                    .parse::<Timestamp>("after")
+                    .map(|t| t.into_inner())
+                    .unwrap_or(0);
+                let filter = params.get("domain").map(|domain| domain.to_lowercase());
+                let results = self
+                    .core
+                    .enterprise
+                    .as_ref()
+                    .and_then(|e| e.metrics_store.as_ref())
+                    .filter(|store| store.usage)
+                    .ok_or_else(|| {
+                        manage::error(
+                            "Usage tracking is disabled",
+                            "You need to enable metrics.history.usage to use this feature.".into(),
+                        )
+                    })?
+                    .store
+                    .query_usage(after, before)
+                    .await?;
+
+                // Tenant administrators can only see their own domains
+                let tenant_id = access_token.tenant.map(|t| t.id);
+                let mut principals = AHashMap::new();
+                let mut records = Vec::with_capacity(results.len());
+                for record in results {
+                    let principal = match principals.entry(record.principal_id) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => entry.insert(
+                            self.store()
+                                .get_principal(record.principal_id)
+                                .await?
+                                .filter(|principal| {
+                                    tenant_id.is_none_or(|tenant_id| {
+                                        principal.id == tenant_id
+                                            || principal.tenant() == Some(tenant_id)
+                                    })
+                                }),
+                        ),
+                    };
+
+                    if let Some(principal) = principal
+                        .as_ref()
+                        .filter(|p| filter.as_ref().is_none_or(|f| p.name() == f))
+                    {
+                        records.push(json!({
+                            "name": principal.name(),
+                            "type": principal.typ.to_jmap(),
+                            "metric": record.metric,
+                            "timestamp": DateTime::from_timestamp(record.timestamp as i64).to_rfc3339(),
+                            "value": record.value,
+                        }));
+                    }
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": records,
+                }))
+                .into_http_response())
+            }
             ("metrics", Some("live"), &Method::GET) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::MetricsLive)?;
@@ -14,7 +14,7 @@ use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
 use common::{Server, enterprise::undelete::DeletedBlob};
 use directory::backend::internal::manage::ManageDirectory;
 use email::{
-    mailbox::INBOX_ID,
+    mailbox::{INBOX_ID, manage::MailboxFnc},
     message::ingest::{EmailIngest, IngestEmail, IngestSource},
 };
 use hyper::Method;
@@ -37,6 +37,9 @@ pub struct UndeleteRequest<H, C, T> {
     #[serde(rename = "cancelDeletion")]
     #[serde(default)]
     pub cancel_deletion: Option<T>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mailbox: Option<String>,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, PartialEq, Eq, Debug)]
@@ -76,9 +79,16 @@ impl UndeleteApi for Server {
                     .get_principal_id(account_name.as_ref())
                     .await?
                     .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
-                let mut deleted = self.core.list_deleted(account_id).await?;
-
                 let params = UrlParams::new(req.uri().query());
+                let filter = DeletedFilter::new(&params);
+                let mut deleted = self
+                    .core
+                    .list_deleted(account_id)
+                    .await?
+                    .into_iter()
+                    .filter(|blob| filter.matches(blob))
+                    .collect::<Vec<_>>();
+
                 let limit = params.parse::<usize>("limit").unwrap_or_default();
                 let mut offset = params
                     .parse::<usize>("page")
@@ -101,6 +111,7 @@ impl UndeleteApi for Server {
                             expires_at: DateTime::from_timestamp(blob.expires_at as i64)
                                 .to_rfc3339(),
                             collection: Collection::from(blob.collection).to_string(),
+                            mailbox: blob.mailbox,
                         });
                         if results.len() == limit {
                             break;
@@ -159,21 +170,29 @@ impl UndeleteApi for Server {
                                     } else {
                                         None
                                     },
+                                    mailbox: request.mailbox,
                                 }
                                 .into()
                             })
                             .collect::<Option<Vec<_>>>()
                             .ok_or_else(|| trc::ResourceEvent::BadParameters.into_err())?,
                         Ok(None) => {
+                            // Restore everything deleted from a folder or, without filters,
+                            // the whole account as of the requested point in time
+                            let params = UrlParams::new(req.uri().query());
+                            let filter = DeletedFilter::new(&params);
                             let deleted = self.core.list_deleted(account_id).await?;
                             let mut results = Vec::with_capacity(deleted.len());
                             for blob in deleted {
-                                results.push(UndeleteRequest {
-                                    hash: blob.hash,
-                                    collection: Collection::from(blob.collection),
-                                    time: blob.deleted_at,
-                                    cancel_deletion: blob.expires_at.into(),
-                                });
+                                if filter.matches(&blob) {
+                                    results.push(UndeleteRequest {
+                                        hash: blob.hash,
+                                        collection: Collection::from(blob.collection),
+                                        time: blob.deleted_at,
+                                        cancel_deletion: blob.expires_at.into(),
+                                        mailbox: blob.mailbox,
+                                    });
+                                }
                             }
                             results
                         }
@@ -198,12 +217,22 @@ impl UndeleteApi for Server {
                                 .await?
                             {
                                 Some(bytes) => {
+                                    // Restore into the original folder, recreating it if needed
+                                    let mailbox_id = if let Some(path) = &request.mailbox {
+                                        self.mailbox_create_path(account_id, path)
+                                            .await
+                                            .caused_by(trc::location!())?
+                                            .unwrap_or(INBOX_ID)
+                                    } else {
+                                        INBOX_ID
+                                    };
+
                                     match self
                                         .email_ingest(IngestEmail {
                                             raw_message: &bytes,
                                             message: MessageParser::new().parse(&bytes),
                                             access_token: access_token.as_ref(),
-                                            mailbox_ids: vec![INBOX_ID],
+                                            mailbox_ids: vec![mailbox_id],
                                             keywords: vec![],
                                             received_at: request.time.into(),
                                             source: IngestSource::Restore,
@@ -272,3 +301,33 @@ impl UndeleteApi for Server {
         }
     }
 }
+
+struct DeletedFilter<'x> {
+    mailbox: Option<&'x str>,
+    since: u64,
+}
+
+impl<'x> DeletedFilter<'x> {
+    fn new(params: &'x UrlParams<'_>) -> Self {
+        DeletedFilter {
+            mailbox: params.get("mailbox").filter(|mailbox| !mailbox.is_empty()),
+            since: params
+                .get("since")
+                .and_then(DateTime::parse_rfc3339)
+                .map(|since| since.to_timestamp() as u64)
+                .unwrap_or_default(),
+        }
+    }
+
+    fn matches<H, C>(&self, blob: &DeletedBlob<H, u64, C>) -> bool {
+        blob.deleted_at >= self.since
+            && self.mailbox.is_none_or(|filter| {
+                blob.mailbox.as_deref().is_some_and(|mailbox| {
+                    mailbox == filter
+                        || mailbox
+                            .strip_prefix(filter)
+                            .is_some_and(|child| child.starts_with('/'))
+                })
+            })
+    }
+}
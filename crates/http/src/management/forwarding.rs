@@ -0,0 +1,128 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::backend::internal::manage;
+use email::message::forward::{EmailForwarding, ForwardingSettings};
+use http_proto::*;
+use hyper::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::future::Future;
+use store::write::now;
+use utils::sanitize_email;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ForwardingRequest {
+    #[serde(default)]
+    addresses: Vec<String>,
+    #[serde(default)]
+    keep_copy: bool,
+    #[serde(default)]
+    expires: Option<u64>,
+}
+
+pub trait ForwardingManagement: Sync + Send {
+    fn handle_forwarding(
+        &self,
+        req: &HttpRequest,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ForwardingManagement for Server {
+    async fn handle_forwarding(
+        &self,
+        req: &HttpRequest,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let account_id = access_token.primary_id();
+
+        match *req.method() {
+            Method::GET => {
+                let forwarding = self.get_forwarding(account_id).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": ForwardingRequest {
+                        addresses: forwarding.addresses,
+                        keep_copy: forwarding.keep_copy,
+                        expires: forwarding.expires,
+                    },
+                }))
+                .into_http_response())
+            }
+            Method::PUT => {
+                let request = serde_json::from_slice::<ForwardingRequest>(
+                    body.as_deref().unwrap_or_default(),
+                )
+                .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
+                let settings = validate_request(self, access_token, request)?;
+                self.set_forwarding(account_id, settings).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            Method::DELETE => {
+                self.set_forwarding(account_id, ForwardingSettings::default())
+                    .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+fn validate_request(
+    server: &Server,
+    access_token: &AccessToken,
+    request: ForwardingRequest,
+) -> trc::Result<ForwardingSettings> {
+    let mut addresses = Vec::with_capacity(request.addresses.len());
+    for address in request.addresses {
+        let address = sanitize_email(&address)
+            .ok_or_else(|| manage::error("Invalid forwarding address", address.into()))?;
+        if access_token.emails.contains(&address) {
+            return Err(manage::error(
+                "Messages cannot be forwarded to the account's own addresses",
+                address.into(),
+            ));
+        }
+        if !addresses.contains(&address) {
+            addresses.push(address);
+        }
+    }
+    if addresses.len() > server.core.jmap.forwarding_max_recipients {
+        return Err(manage::error(
+            "Too many forwarding addresses",
+            format!(
+                "A maximum of {} addresses is allowed",
+                server.core.jmap.forwarding_max_recipients
+            )
+            .into(),
+        ));
+    }
+    if request.expires.is_some_and(|expires| expires <= now()) {
+        return Err(manage::error(
+            "Invalid expiration date",
+            Some("The expiration date must be in the future"),
+        ));
+    }
+
+    Ok(ForwardingSettings {
+        addresses,
+        keep_copy: request.keep_copy,
+        expires: request.expires,
+    })
+}
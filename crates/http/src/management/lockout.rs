@@ -0,0 +1,89 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::{Permission, backend::internal::manage::ManageDirectory};
+use http_proto::{request::decode_path_element, *};
+use hyper::Method;
+use serde_json::json;
+use std::future::Future;
+use store::write::now;
+
+pub trait LockoutManagement: Sync + Send {
+    fn handle_manage_lockout(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl LockoutManagement for Server {
+    async fn handle_manage_lockout(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let login = path
+            .get(1)
+            .map(|login| decode_path_element(login).to_lowercase())
+            .filter(|login| !login.is_empty())
+            .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+        match *req.method() {
+            Method::GET => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::LockoutView)?;
+            }
+            Method::DELETE => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::LockoutClear)?;
+            }
+            _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+
+        // Tenants can only manage lockouts of their own accounts
+        if let Some(tenant) = access_token.tenant
+            && self
+                .store()
+                .get_principal_info(&login)
+                .await?
+                .is_none_or(|principal| principal.tenant != Some(tenant.id))
+        {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+
+        if req.method() == Method::GET {
+            let status = self.account_lockout(&login).await?;
+            let seconds_left = status.locked_until.saturating_sub(now());
+
+            Ok(JsonResponse::new(json!({
+                "data": {
+                    "account": login,
+                    "failures": status.failures,
+                    "locked": seconds_left > 0,
+                    "lockedUntil": (status.locked_until > 0).then_some(status.locked_until),
+                    "secondsLeft": seconds_left,
+                    "strikes": status.strikes,
+                },
+            }))
+            .into_http_response())
+        } else {
+            self.clear_account_lockout(&login).await?;
+
+            trc::event!(
+                Security(trc::SecurityEvent::AccountUnlocked),
+                AccountName = login,
+            );
+
+            Ok(JsonResponse::new(json!({
+                "data": (),
+            }))
+            .into_http_response())
+        }
+    }
+}
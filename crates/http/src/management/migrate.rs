@@ -0,0 +1,1056 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use common::{
+    KV_IMPORT_CHECKPOINT, Server, config::jmap::settings::SpecialUse,
+    storage::index::ObjectIndexBuilder,
+};
+use directory::backend::{
+    imap::{ImapClient, ImapError, fetch::ImapListItem},
+    internal::manage::ManageDirectory,
+};
+use email::{
+    cache::{MessageCacheFetch, mailbox::MailboxCacheAccess},
+    mailbox::{INBOX_ID, Mailbox, manage::MailboxFnc},
+    message::ingest::{EmailIngest, IngestEmail, IngestSource},
+};
+use http_proto::*;
+use imap_proto::{parser::parse_datetime, utf7::utf7_decode};
+use jmap_proto::types::{collection::Collection, keyword::Keyword, property::Property};
+use mail_parser::MessageParser;
+use mail_send::smtp::tls::build_tls_connector;
+use serde_json::json;
+use store::{
+    ValueKey,
+    dispatch::lookup::KeyValue,
+    write::{BatchBuilder, ValueClass},
+};
+use tokio::{net::TcpStream, sync::Semaphore};
+use tokio_rustls::client::TlsStream;
+use trc::AddContext;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ImapImportRequest {
+    pub host: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default = "default_tls")]
+    pub tls: bool,
+    #[serde(default)]
+    #[serde(rename = "allowInvalidCerts")]
+    pub allow_invalid_certs: bool,
+    #[serde(default)]
+    pub accounts: Vec<ImapImportAccount>,
+    #[serde(default)]
+    pub csv: Option<String>,
+    #[serde(default)]
+    #[serde(rename = "maxConcurrent")]
+    pub max_concurrent: Option<usize>,
+    #[serde(default)]
+    #[serde(rename = "batchSize")]
+    pub batch_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ImapImportAccount {
+    pub account: String,
+    pub username: String,
+    pub secret: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ImportStats {
+    pub mailboxes: usize,
+    pub messages: usize,
+    pub failed: usize,
+}
+
+pub trait ImapImportApi: Sync + Send {
+    fn handle_imap_import(
+        &self,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn imap_import_account(
+        &self,
+        request: &ImapImportRequest,
+        account_id: u32,
+        account: &ImapImportAccount,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<ImportStats>> + Send;
+}
+
+const DEFAULT_MAX_CONCURRENT: usize = 4;
+const DEFAULT_BATCH_SIZE: usize = 50;
+const IMPORT_TIMEOUT: Duration = Duration::from_secs(60);
+
+impl ImapImportApi for Server {
+    async fn handle_imap_import(
+        &self,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+    ) -> trc::Result<HttpResponse> {
+        let mut request =
+            serde_json::from_slice::<ImapImportRequest>(body.as_deref().unwrap_or_default())
+                .map_err(|err| {
+                    trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+                })?;
+        if request.host.is_empty() {
+            return Err(trc::ResourceEvent::BadParameters
+                .into_err()
+                .details("Missing remote host"));
+        }
+
+        // Accounts can also be mapped using "account,username,secret" lines
+        if let Some(csv) = request.csv.take() {
+            for line in csv.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let mut parts = line.splitn(3, ',');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some(account), Some(username), Some(secret))
+                        if !account.trim().is_empty() && !username.trim().is_empty() =>
+                    {
+                        request.accounts.push(ImapImportAccount {
+                            account: account.trim().to_string(),
+                            username: username.trim().to_string(),
+                            secret: secret.to_string(),
+                        });
+                    }
+                    _ => {
+                        return Err(trc::ResourceEvent::BadParameters
+                            .into_err()
+                            .details("Invalid CSV line")
+                            .ctx(trc::Key::Value, line.to_string()));
+                    }
+                }
+            }
+        }
+        if request.accounts.is_empty() {
+            return Err(trc::ResourceEvent::BadParameters
+                .into_err()
+                .details("No accounts to import"));
+        }
+
+        // Resolve all local accounts before starting the import
+        let mut accounts = Vec::with_capacity(request.accounts.len());
+        for account in std::mem::take(&mut request.accounts) {
+            let account_id = self
+                .store()
+                .get_principal_id(&account.account)
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| {
+                    trc::ManageEvent::NotFound
+                        .into_err()
+                        .details(account.account.clone())
+                })?;
+            accounts.push((account_id, account));
+        }
+
+        let total = accounts.len();
+        let server = self.clone();
+        let session_id = session.session_id;
+        tokio::spawn(async move {
+            let request = Arc::new(request);
+            let semaphore = Arc::new(Semaphore::new(
+                request
+                    .max_concurrent
+                    .unwrap_or(DEFAULT_MAX_CONCURRENT)
+                    .max(1),
+            ));
+
+            for (account_id, account) in accounts {
+                let Ok(permit) = semaphore.clone().acquire_owned().await else {
+                    break;
+                };
+                let server = server.clone();
+                let request = request.clone();
+
+                tokio::spawn(async move {
+                    let _permit = permit;
+
+                    trc::event!(
+                        MessageIngest(trc::MessageIngestEvent::ImportStart),
+                        SpanId = session_id,
+                        AccountId = account_id,
+                        AccountName = account.account.clone(),
+                        Hostname = request.host.clone(),
+                    );
+
+                    match server
+                        .imap_import_account(&request, account_id, &account, session_id)
+                        .await
+                    {
+                        Ok(stats) => {
+                            trc::event!(
+                                MessageIngest(trc::MessageIngestEvent::ImportComplete),
+                                SpanId = session_id,
+                                AccountId = account_id,
+                                AccountName = account.account,
+                                Hostname = request.host.clone(),
+                                Total = stats.messages,
+                                Details = vec![
+                                    trc::Value::from(stats.mailboxes),
+                                    trc::Value::from(stats.failed)
+                                ],
+                            );
+                        }
+                        Err(err) => {
+                            trc::event!(
+                                MessageIngest(trc::MessageIngestEvent::ImportError),
+                                SpanId = session_id,
+                                AccountId = account_id,
+                                AccountName = account.account,
+                                Hostname = request.host.clone(),
+                                CausedBy = err,
+                            );
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(JsonResponse::new(json!({
+            "data": total,
+        }))
+        .into_http_response())
+    }
+
+    async fn imap_import_account(
+        &self,
+        request: &ImapImportRequest,
+        account_id: u32,
+        account: &ImapImportAccount,
+        session_id: u64,
+    ) -> trc::Result<ImportStats> {
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let batch_size = request.batch_size.unwrap_or(DEFAULT_BATCH_SIZE).max(1);
+        let port = request.port.unwrap_or(if request.tls { 993 } else { 143 });
+        let mut client = ImapClient::<TlsStream<TcpStream>>::connect(
+            (request.host.as_str(), port),
+            IMPORT_TIMEOUT,
+            &build_tls_connector(request.allow_invalid_certs),
+            &request.host,
+            request.tls,
+        )
+        .await
+        .map_err(into_error)?;
+        client
+            .login(&account.username, &account.secret)
+            .await
+            .map_err(into_error)?;
+
+        let mut stats = ImportStats::default();
+        for mailbox in client.list().await.map_err(into_error)? {
+            if mailbox.attributes.iter().any(|attr| {
+                attr.eq_ignore_ascii_case("\\Noselect")
+                    || attr.eq_ignore_ascii_case("\\NonExistent")
+            }) {
+                continue;
+            }
+
+            // Obtain the last imported UID for this mailbox
+            let checkpoint_key = format!(
+                "{account_id}\0{}\0{}\0{}",
+                request.host, account.username, mailbox.name
+            );
+            let checkpoint = self
+                .in_memory_store()
+                .key_get::<String>(KeyValue::<()>::build_key(
+                    KV_IMPORT_CHECKPOINT,
+                    &checkpoint_key,
+                ))
+                .await
+                .caused_by(trc::location!())?
+                .and_then(|value| {
+                    let (uid_validity, uid) = value.split_once(':')?;
+                    Some((uid_validity.parse::<u32>().ok()?, uid.parse::<u32>().ok()?))
+                });
+
+            let status = client.examine(&mailbox.name).await.map_err(into_error)?;
+            let next_uid = match checkpoint {
+                Some((uid_validity, uid)) if uid_validity == status.uid_validity => uid + 1,
+                _ => 1,
+            };
+            let uids = if status.exists > 0 {
+                client.uid_search_from(next_uid).await.map_err(into_error)?
+            } else {
+                vec![]
+            };
+            if uids.is_empty() {
+                continue;
+            }
+
+            let Some(mailbox_id) = self
+                .import_mailbox_id(
+                    account_id,
+                    &imap_mailbox_path(&mailbox),
+                    imap_mailbox_role(&mailbox),
+                )
+                .await
+                .caused_by(trc::location!())?
+            else {
+                continue;
+            };
+            stats.mailboxes += 1;
+
+            for uids in uids.chunks(batch_size) {
+                let mut last_uid = 0;
+                for message in client.uid_fetch(uids).await.map_err(into_error)? {
+                    last_uid = last_uid.max(message.uid);
+                    if message.contents.is_empty() {
+                        stats.failed += 1;
+                        continue;
+                    }
+
+                    let result = self
+                        .email_ingest(IngestEmail {
+                            raw_message: &message.contents,
+                            message: MessageParser::new().parse(&message.contents),
+                            access_token: &access_token,
+                            mailbox_ids: vec![mailbox_id],
+                            keywords: message
+                                .flags
+                                .iter()
+                                .map(Keyword::from)
+                                .filter(|keyword| !matches!(keyword, Keyword::Recent))
+                                .collect(),
+                            received_at: message
+                                .internal_date
+                                .as_deref()
+                                .and_then(|date| parse_datetime(date.as_bytes()).ok())
+                                .map(|date| date as u64),
+                            source: IngestSource::Imap,
+                            spam_classify: false,
+                            spam_train: false,
+                            session_id,
+                        })
+                        .await;
+                    match result {
+                        Ok(_) => {
+                            stats.messages += 1;
+                        }
+                        Err(err) if err.matches(trc::EventType::Limit(trc::LimitEvent::Quota)) => {
+                            return Err(err);
+                        }
+                        Err(err) => {
+                            stats.failed += 1;
+                            trc::error!(
+                                err.span_id(session_id)
+                                    .account_id(account_id)
+                                    .ctx(trc::Key::Uid, message.uid)
+                                    .details("Failed to import message")
+                            );
+                        }
+                    }
+                }
+
+                // Store checkpoint after each batch, allowing the import to be resumed
+                let last_uid = last_uid.max(uids.last().copied().unwrap_or_default());
+                self.in_memory_store()
+                    .key_set(KeyValue::with_prefix(
+                        KV_IMPORT_CHECKPOINT,
+                        &checkpoint_key,
+                        format!("{}:{last_uid}", status.uid_validity).into_bytes(),
+                    ))
+                    .await
+                    .caused_by(trc::location!())?;
+            }
+        }
+
+        let _ = client.logout().await;
+
+        Ok(stats)
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct MaildirImportRequest {
+    pub account: String,
+    pub path: String,
+    #[serde(default)]
+    pub layout: MaildirLayout,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MaildirLayout {
+    // Maildir++ with dot separated folders (Dovecot's default layout)
+    #[default]
+    Maildir,
+    // Folders stored as nested directories (Dovecot's LAYOUT=fs)
+    Fs,
+    // Dovecot's multi-dbox, not supported by the importer
+    Mdbox,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DovecotUidList {
+    pub uid_validity: u32,
+    pub uids: HashMap<String, u32>,
+}
+
+struct MaildirFolder {
+    path: String,
+    dir: PathBuf,
+}
+
+struct MaildirMessage {
+    path: PathBuf,
+    base: String,
+    flags: String,
+}
+
+pub trait MaildirImportApi: Sync + Send {
+    fn handle_maildir_import(
+        &self,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn maildir_import_account(
+        &self,
+        request: &MaildirImportRequest,
+        account_id: u32,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<ImportStats>> + Send;
+}
+
+impl MaildirImportApi for Server {
+    async fn handle_maildir_import(
+        &self,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+    ) -> trc::Result<HttpResponse> {
+        let request =
+            serde_json::from_slice::<MaildirImportRequest>(body.as_deref().unwrap_or_default())
+                .map_err(|err| {
+                    trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+                })?;
+        let account_id = self
+            .store()
+            .get_principal_id(&request.account)
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| {
+                trc::ManageEvent::NotFound
+                    .into_err()
+                    .details(request.account.clone())
+            })?;
+        if !tokio::fs::metadata(&request.path)
+            .await
+            .is_ok_and(|metadata| metadata.is_dir())
+        {
+            return Err(trc::ResourceEvent::NotFound
+                .into_err()
+                .details("Maildir not found")
+                .ctx(trc::Key::Path, request.path));
+        }
+
+        // Messages in an mdbox are stored in multi-message files that can only
+        // be located through Dovecot's binary map index, which is not parsed here
+        if request.layout == MaildirLayout::Mdbox || is_mdbox(Path::new(&request.path)).await {
+            return Err(trc::ResourceEvent::BadParameters
+                .into_err()
+                .details("The mdbox layout is not supported, convert it to Maildir first")
+                .ctx(trc::Key::Path, request.path));
+        }
+
+        let server = self.clone();
+        let session_id = session.session_id;
+        tokio::spawn(async move {
+            trc::event!(
+                MessageIngest(trc::MessageIngestEvent::ImportStart),
+                SpanId = session_id,
+                AccountId = account_id,
+                AccountName = request.account.clone(),
+                Path = request.path.clone(),
+            );
+
+            match server
+                .maildir_import_account(&request, account_id, session_id)
+                .await
+            {
+                Ok(stats) => {
+                    trc::event!(
+                        MessageIngest(trc::MessageIngestEvent::ImportComplete),
+                        SpanId = session_id,
+                        AccountId = account_id,
+                        AccountName = request.account,
+                        Path = request.path,
+                        Total = stats.messages,
+                        Details = vec![
+                            trc::Value::from(stats.mailboxes),
+                            trc::Value::from(stats.failed)
+                        ],
+                    );
+                }
+                Err(err) => {
+                    trc::event!(
+                        MessageIngest(trc::MessageIngestEvent::ImportError),
+                        SpanId = session_id,
+                        AccountId = account_id,
+                        AccountName = request.account,
+                        Path = request.path,
+                        CausedBy = err,
+                    );
+                }
+            }
+        });
+
+        Ok(JsonResponse::new(json!({
+            "data": 1,
+        }))
+        .into_http_response())
+    }
+
+    async fn maildir_import_account(
+        &self,
+        request: &MaildirImportRequest,
+        account_id: u32,
+        session_id: u64,
+    ) -> trc::Result<ImportStats> {
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let root = PathBuf::from(&request.path);
+        let mut stats = ImportStats::default();
+
+        for folder in maildir_folders(&root, request.layout)
+            .await
+            .map_err(|err| io_error(err, &root))?
+        {
+            let messages = maildir_messages(&folder.dir)
+                .await
+                .map_err(|err| io_error(err, &folder.dir))?;
+            if messages.is_empty() {
+                continue;
+            }
+            let uid_list = tokio::fs::read_to_string(folder.dir.join("dovecot-uidlist"))
+                .await
+                .ok()
+                .and_then(|contents| DovecotUidList::parse(&contents));
+            let keywords = tokio::fs::read_to_string(folder.dir.join("dovecot-keywords"))
+                .await
+                .map(|contents| parse_dovecot_keywords(&contents))
+                .unwrap_or_default();
+
+            let Some(mailbox_id) = self
+                .import_mailbox_id(account_id, &folder.path, maildir_mailbox_role(&folder.path))
+                .await
+                .caused_by(trc::location!())?
+            else {
+                continue;
+            };
+            stats.mailboxes += 1;
+
+            // Preserve UIDVALIDITY and UIDs when the local mailbox has not assigned any UIDs yet,
+            // or when resuming a previous import of the same mailbox
+            let mut last_uid = self
+                .store()
+                .get_counter(ValueKey {
+                    account_id,
+                    collection: Collection::Mailbox.into(),
+                    document_id: mailbox_id,
+                    class: ValueClass::Property(Property::EmailIds.into()),
+                })
+                .await
+                .caused_by(trc::location!())? as u32;
+            let uid_list = if let Some(uid_list) = uid_list.filter(|list| list.uid_validity != 0) {
+                let uid_validity = self
+                    .get_cached_messages(account_id)
+                    .await
+                    .caused_by(trc::location!())?
+                    .mailbox_by_id(&mailbox_id)
+                    .map(|mailbox| mailbox.uid_validity);
+                if uid_validity == Some(uid_list.uid_validity) {
+                    Some(uid_list)
+                } else if last_uid == 0 {
+                    self.import_uid_validity(account_id, mailbox_id, uid_list.uid_validity)
+                        .await
+                        .caused_by(trc::location!())?;
+                    Some(uid_list)
+                } else {
+                    None
+                }
+            } else {
+                None
+            };
+
+            // Import messages in UID order, messages missing from the UID list go last
+            let mut messages = messages
+                .into_iter()
+                .map(|message| {
+                    (
+                        uid_list
+                            .as_ref()
+                            .and_then(|list| list.uids.get(&message.base).copied()),
+                        message,
+                    )
+                })
+                .collect::<Vec<_>>();
+            messages.sort_by_key(|(uid, _)| uid.unwrap_or(u32::MAX));
+
+            for (uid, message) in messages {
+                if let Some(uid) = uid {
+                    if uid <= last_uid {
+                        // Already imported
+                        continue;
+                    } else if uid > last_uid + 1 {
+                        let mut batch = BatchBuilder::new();
+                        batch
+                            .with_account_id(account_id)
+                            .with_collection(Collection::Mailbox)
+                            .update_document(mailbox_id)
+                            .add(Property::EmailIds, (uid - last_uid - 1) as i64);
+                        self.store()
+                            .write(batch.build_all())
+                            .await
+                            .caused_by(trc::location!())?;
+                        last_uid = uid - 1;
+                    }
+                }
+
+                let Ok(raw_message) = tokio::fs::read(&message.path).await else {
+                    stats.failed += 1;
+                    continue;
+                };
+                let received_at = tokio::fs::metadata(&message.path)
+                    .await
+                    .ok()
+                    .and_then(|metadata| metadata.modified().ok())
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map(|modified| modified.as_secs());
+
+                match self
+                    .email_ingest(IngestEmail {
+                        raw_message: &raw_message,
+                        message: MessageParser::new().parse(&raw_message),
+                        access_token: &access_token,
+                        mailbox_ids: vec![mailbox_id],
+                        keywords: maildir_keywords(&message.flags, &keywords),
+                        received_at,
+                        source: IngestSource::Imap,
+                        spam_classify: false,
+                        spam_train: false,
+                        session_id,
+                    })
+                    .await
+                {
+                    Ok(ingested) => {
+                        stats.messages += 1;
+                        last_uid = ingested
+                            .imap_uids
+                            .first()
+                            .copied()
+                            .unwrap_or_default()
+                            .max(last_uid);
+                    }
+                    Err(err) if err.matches(trc::EventType::Limit(trc::LimitEvent::Quota)) => {
+                        return Err(err);
+                    }
+                    Err(err) => {
+                        stats.failed += 1;
+                        trc::error!(
+                            err.span_id(session_id)
+                                .account_id(account_id)
+                                .ctx(trc::Key::Path, message.path.to_string_lossy().into_owned())
+                                .details("Failed to import message")
+                        );
+                    }
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+trait ImportMailbox: Sync + Send {
+    fn import_mailbox_id(
+        &self,
+        account_id: u32,
+        path: &str,
+        role: Option<SpecialUse>,
+    ) -> impl Future<Output = trc::Result<Option<u32>>> + Send;
+
+    fn import_uid_validity(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        uid_validity: u32,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl ImportMailbox for Server {
+    async fn import_mailbox_id(
+        &self,
+        account_id: u32,
+        path: &str,
+        role: Option<SpecialUse>,
+    ) -> trc::Result<Option<u32>> {
+        if path.eq_ignore_ascii_case("INBOX") {
+            return Ok(Some(INBOX_ID));
+        }
+
+        // Map special-use folders to their local counterparts
+        if let Some(role) = role
+            && let Some(mailbox) = self
+                .get_cached_messages(account_id)
+                .await
+                .caused_by(trc::location!())?
+                .mailbox_by_role(&role)
+        {
+            return Ok(Some(mailbox.document_id));
+        }
+
+        self.mailbox_create_path(account_id, path)
+            .await
+            .caused_by(trc::location!())
+    }
+
+    async fn import_uid_validity(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        uid_validity: u32,
+    ) -> trc::Result<()> {
+        let Some(mailbox_) = self
+            .get_archive(account_id, Collection::Mailbox, mailbox_id)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(());
+        };
+        let mailbox = mailbox_
+            .to_unarchived::<Mailbox>()
+            .caused_by(trc::location!())?;
+        let mut new_mailbox = mailbox.deserialize().caused_by(trc::location!())?;
+        new_mailbox.uid_validity = uid_validity;
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Mailbox)
+            .update_document(mailbox_id)
+            .custom(
+                ObjectIndexBuilder::new()
+                    .with_current(mailbox)
+                    .with_changes(new_mailbox),
+            )
+            .caused_by(trc::location!())?;
+        self.commit_batch(batch)
+            .await
+            .caused_by(trc::location!())
+            .map(|_| ())
+    }
+}
+
+impl DovecotUidList {
+    pub fn parse(contents: &str) -> Option<Self> {
+        let mut lines = contents.lines();
+        let mut header = lines.next()?.split_ascii_whitespace();
+        let version = header.next()?;
+
+        // Version 1 headers are "1 <uidvalidity> <nextuid>", later versions
+        // use prefixed fields such as "3 V<uidvalidity> N<nextuid> G<guid>"
+        let uid_validity = if version == "1" {
+            header.next()?.parse().ok()?
+        } else {
+            header
+                .find_map(|field| field.strip_prefix('V'))?
+                .parse()
+                .ok()?
+        };
+
+        let mut uids = HashMap::new();
+        for line in lines {
+            let Some((uid, rest)) = line.split_once(' ') else {
+                continue;
+            };
+            let Ok(uid) = uid.parse::<u32>() else {
+                continue;
+            };
+            let file_name = if version == "1" {
+                Some(rest)
+            } else {
+                rest.split_once(':').map(|(_, file_name)| file_name)
+            };
+            if let Some(file_name) = file_name.map(|name| name.trim()).filter(|n| !n.is_empty()) {
+                uids.insert(file_name.to_string(), uid);
+            }
+        }
+
+        Some(DovecotUidList { uid_validity, uids })
+    }
+}
+
+pub fn parse_dovecot_keywords(contents: &str) -> Vec<String> {
+    let mut keywords = Vec::new();
+    for (idx, keyword) in contents.lines().filter_map(|line| {
+        let (idx, keyword) = line.split_once(' ')?;
+        Some((idx.parse::<usize>().ok()?, keyword.trim()))
+    }) {
+        if idx < 26 && !keyword.is_empty() {
+            if keywords.len() <= idx {
+                keywords.resize(idx + 1, String::new());
+            }
+            keywords[idx] = keyword.to_string();
+        }
+    }
+    keywords
+}
+
+pub fn maildir_keywords(flags: &str, keywords: &[String]) -> Vec<Keyword> {
+    flags
+        .chars()
+        .filter_map(|flag| match flag {
+            'S' => Some(Keyword::Seen),
+            'R' => Some(Keyword::Answered),
+            'F' => Some(Keyword::Flagged),
+            'T' => Some(Keyword::Deleted),
+            'D' => Some(Keyword::Draft),
+            'P' => Some(Keyword::Forwarded),
+            'a'..='z' => keywords
+                .get((flag as u8 - b'a') as usize)
+                .filter(|keyword| !keyword.is_empty())
+                .map(Keyword::from),
+            _ => None,
+        })
+        .collect()
+}
+
+async fn maildir_folders(
+    root: &Path,
+    layout: MaildirLayout,
+) -> std::io::Result<Vec<MaildirFolder>> {
+    let mut folders = Vec::new();
+    if is_maildir(root).await {
+        folders.push(MaildirFolder {
+            path: "INBOX".to_string(),
+            dir: root.to_path_buf(),
+        });
+    }
+
+    match layout {
+        MaildirLayout::Maildir => {
+            let mut entries = tokio::fs::read_dir(root).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let file_name = entry.file_name();
+                let Some(name) = file_name
+                    .to_str()
+                    .and_then(|name| name.strip_prefix('.'))
+                    .filter(|name| !name.is_empty() && *name != ".")
+                else {
+                    continue;
+                };
+                if is_maildir(&entry.path()).await {
+                    folders.push(MaildirFolder {
+                        path: decode_folder_path(name.split('.')),
+                        dir: entry.path(),
+                    });
+                }
+            }
+        }
+        MaildirLayout::Fs => {
+            let mut pending = vec![(root.to_path_buf(), Vec::new())];
+            while let Some((dir, parents)) = pending.pop() {
+                let mut entries = tokio::fs::read_dir(&dir).await?;
+                while let Some(entry) = entries.next_entry().await? {
+                    let file_name = entry.file_name();
+                    let Some(name) = file_name.to_str().filter(|name| {
+                        !matches!(*name, "cur" | "new" | "tmp") && !name.starts_with('.')
+                    }) else {
+                        continue;
+                    };
+                    if !entry.file_type().await?.is_dir() {
+                        continue;
+                    }
+                    let mut path = parents.clone();
+                    path.push(name.to_string());
+                    if is_maildir(&entry.path()).await {
+                        folders.push(MaildirFolder {
+                            path: decode_folder_path(path.iter().map(|name| name.as_str())),
+                            dir: entry.path(),
+                        });
+                    }
+                    pending.push((entry.path(), path));
+                }
+            }
+        }
+        MaildirLayout::Mdbox => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "mdbox layout is not supported",
+            ));
+        }
+    }
+
+    Ok(folders)
+}
+
+async fn maildir_messages(dir: &Path) -> std::io::Result<Vec<MaildirMessage>> {
+    let mut messages = Vec::new();
+    for sub_dir in ["new", "cur"] {
+        let Ok(mut entries) = tokio::fs::read_dir(dir.join(sub_dir)).await else {
+            continue;
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            if !entry.file_type().await?.is_file() {
+                continue;
+            }
+            let Some(file_name) = entry.file_name().to_str().map(|name| name.to_string()) else {
+                continue;
+            };
+            let (base, flags) = file_name
+                .split_once(":2,")
+                .map(|(base, flags)| (base.to_string(), flags.to_string()))
+                .unwrap_or_else(|| (file_name.clone(), String::new()));
+            messages.push(MaildirMessage {
+                path: entry.path(),
+                base,
+                flags,
+            });
+        }
+    }
+    Ok(messages)
+}
+
+async fn is_maildir(dir: &Path) -> bool {
+    tokio::fs::metadata(dir.join("cur"))
+        .await
+        .is_ok_and(|metadata| metadata.is_dir())
+}
+
+async fn is_mdbox(dir: &Path) -> bool {
+    tokio::fs::metadata(dir.join("storage").join("dovecot.map.index"))
+        .await
+        .is_ok_and(|metadata| metadata.is_file())
+}
+
+fn decode_folder_path<'x>(names: impl Iterator<Item = &'x str>) -> String {
+    names
+        .map(|name| {
+            utf7_decode(name)
+                .unwrap_or_else(|| name.to_string())
+                .replace('/', "_")
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn maildir_mailbox_role(path: &str) -> Option<SpecialUse> {
+    match path.to_ascii_lowercase().as_str() {
+        "sent" | "sent items" | "sent messages" => Some(SpecialUse::Sent),
+        "trash" | "deleted items" | "deleted messages" => Some(SpecialUse::Trash),
+        "junk" | "spam" => Some(SpecialUse::Junk),
+        "drafts" => Some(SpecialUse::Drafts),
+        "archive" => Some(SpecialUse::Archive),
+        _ => None,
+    }
+}
+
+fn imap_mailbox_role(mailbox: &ImapListItem) -> Option<SpecialUse> {
+    mailbox
+        .attributes
+        .iter()
+        .find_map(|attr| match attr.to_ascii_lowercase().as_str() {
+            "\\sent" => Some(SpecialUse::Sent),
+            "\\trash" => Some(SpecialUse::Trash),
+            "\\junk" => Some(SpecialUse::Junk),
+            "\\drafts" => Some(SpecialUse::Drafts),
+            "\\archive" => Some(SpecialUse::Archive),
+            _ => None,
+        })
+}
+
+fn imap_mailbox_path(mailbox: &ImapListItem) -> String {
+    match mailbox.delimiter {
+        Some(delimiter) if delimiter != '/' => decode_folder_path(mailbox.name.split(delimiter)),
+        _ => utf7_decode(&mailbox.name).unwrap_or_else(|| mailbox.name.clone()),
+    }
+}
+
+fn io_error(err: std::io::Error, path: &Path) -> trc::Error {
+    trc::ResourceEvent::Error
+        .reason(err)
+        .ctx(trc::Key::Path, path.to_string_lossy().into_owned())
+        .caused_by(trc::location!())
+}
+
+fn into_error(err: ImapError) -> trc::Error {
+    match err {
+        ImapError::AuthenticationFailed => trc::AuthEvent::Failed
+            .into_err()
+            .details("Remote IMAP authentication failed"),
+        err => trc::ImapEvent::Error.into_err().reason(err),
+    }
+}
+
+fn default_tls() -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use jmap_proto::types::keyword::Keyword;
+
+    use super::{DovecotUidList, maildir_keywords, parse_dovecot_keywords};
+
+    #[test]
+    fn parse_dovecot_metadata() {
+        let uid_list = DovecotUidList::parse(concat!(
+            "3 V1275660208 N25 G3085f01b7f11094c501100008c4a11c1\n",
+            "1 :1276528487.M364837P9451.kurkku,S=1355,W=1394\n",
+            "2 W2042 :1276528488.M12740P9451.kurkku,S=1981\n",
+            "24 :1276533073.M242911P3632.kurkku\n",
+        ))
+        .unwrap();
+        assert_eq!(uid_list.uid_validity, 1275660208);
+        assert_eq!(uid_list.uids.len(), 3);
+        assert_eq!(
+            uid_list
+                .uids
+                .get("1276528487.M364837P9451.kurkku,S=1355,W=1394"),
+            Some(&1)
+        );
+        assert_eq!(
+            uid_list.uids.get("1276528488.M12740P9451.kurkku,S=1981"),
+            Some(&2)
+        );
+
+        let uid_list = DovecotUidList::parse("1 1021324110 3\n1 123.host\n2 124.host\n").unwrap();
+        assert_eq!(uid_list.uid_validity, 1021324110);
+        assert_eq!(uid_list.uids.get("124.host"), Some(&2));
+
+        let keywords = parse_dovecot_keywords("0 $Forwarded\n2 Important\n");
+        assert_eq!(
+            maildir_keywords("SRabc", &keywords),
+            vec![
+                Keyword::Seen,
+                Keyword::Answered,
+                Keyword::Forwarded,
+                Keyword::Other("Important".to_string())
+            ]
+        );
+    }
+}
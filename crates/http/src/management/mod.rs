@@ -4,17 +4,30 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod avatar;
+pub mod connections;
 pub mod crypto;
+pub mod disposable_alias;
 pub mod dkim;
 pub mod dns;
+pub mod forwarding;
+pub mod lockout;
 pub mod log;
+pub mod migrate;
 pub mod principal;
 pub mod queue;
+pub mod read_receipt;
+pub mod recall;
+pub mod redact;
 pub mod reload;
 pub mod report;
+pub mod saved_search;
+pub mod selftest;
+pub mod sessions;
 pub mod settings;
 pub mod spam;
 pub mod stores;
+pub mod timezone;
 pub mod troubleshoot;
 
 // SPDX-SnippetBegin
@@ -27,29 +40,39 @@ pub mod enterprise;
 use enterprise::telemetry::TelemetryApi;
 // SPDX-SnippetEnd
 
-use crate::auth::oauth::auth::OAuthApiHandler;
+use crate::{auth::oauth::auth::OAuthApiHandler, share::FileShareHandler};
+use avatar::AvatarManagement;
 use common::{Server, auth::AccessToken};
+use connections::ConnectionManagement;
 use crypto::CryptoHandler;
 use directory::{Permission, backend::internal::manage};
+use disposable_alias::DisposableAliasManagement;
 use dkim::DkimManagement;
 use dns::DnsManagement;
+use forwarding::ForwardingManagement;
 use http_proto::{request::fetch_body, *};
 use hyper::{Method, StatusCode, header};
 use jmap::api::{ToJmapHttpResponse, ToRequestError};
 use jmap_proto::error::request::RequestError;
+use lockout::LockoutManagement;
 use log::LogManagement;
 use mail_parser::DateTime;
 use principal::PrincipalManager;
 use queue::QueueManagement;
+use read_receipt::ReadReceiptManagement;
+use recall::RecallApi;
 use reload::ManageReload;
 use report::ManageReports;
+use saved_search::SavedSearchManagement;
 use serde::Serialize;
+use sessions::SessionManagement;
 use settings::ManageSettings;
 use spam::ManageSpamHandler;
 use std::future::Future;
 use std::{str::FromStr, sync::Arc};
 use store::write::now;
 use stores::ManageStore;
+use timezone::ManageTimezones;
 use troubleshoot::TroubleshootApi;
 
 #[derive(Serialize)]
@@ -107,6 +130,10 @@ impl ManagementApi for Server {
                 self.handle_manage_principal(req, path, body, &access_token)
                     .await
             }
+            "avatar" => {
+                self.handle_manage_avatar(req, path, body, &access_token)
+                    .await
+            }
             "dns" => self.handle_manage_dns(req, path, &access_token).await,
             "store" => {
                 self.handle_manage_store(req, path, body, session, &access_token)
@@ -118,9 +145,15 @@ impl ManagementApi for Server {
                     .await
             }
             "update" => self.handle_manage_update(req, path, &access_token).await,
+            "timezone" => self.handle_manage_timezones(req, body, &access_token).await,
             "logs" if req.method() == Method::GET => {
                 self.handle_view_logs(req, &access_token).await
             }
+            "connections" if req.method() == Method::GET => {
+                self.handle_view_connections(req, &access_token).await
+            }
+            "sessions" => self.handle_manage_sessions(req, path, &access_token).await,
+            "lockout" => self.handle_manage_lockout(req, path, &access_token).await,
             "spam-filter" => {
                 self.handle_manage_spam(req, path, body, session, &access_token)
                     .await
@@ -162,13 +195,60 @@ impl ManagementApi for Server {
 
                     self.handle_account_auth_post(req, access_token, body).await
                 }
+                ("avatar", &Method::PUT | &Method::DELETE) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageAvatar)?;
+
+                    self.handle_account_avatar_update(req, body, &access_token)
+                        .await
+                }
+                ("saved-searches", _) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageSavedSearches)?;
+
+                    self.handle_saved_searches(req, path, body, &access_token)
+                        .await
+                }
+                ("aliases", _) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageDisposableAliases)?;
+
+                    self.handle_disposable_aliases(req, path, body, &access_token)
+                        .await
+                }
+                ("forwarding", &Method::GET | &Method::PUT | &Method::DELETE) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageForwarding)?;
+
+                    self.handle_forwarding(req, body, &access_token).await
+                }
+                ("read-receipts", &Method::GET | &Method::PUT) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageReadReceipts)?;
+
+                    self.handle_read_receipt_settings(req, body, &access_token)
+                        .await
+                }
+                ("recall", &Method::POST) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::EmailRecall)?;
+
+                    self.handle_recall_request(body, session, &access_token)
+                        .await
+                }
+                ("share", &Method::POST) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::DavFileGet)?;
+
+                    self.handle_file_share_create(access_token, body).await
+                }
                 _ => Err(trc::ResourceEvent::NotFound.into_err()),
             },
             "troubleshoot" => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::Troubleshoot)?;
 
-                self.handle_troubleshoot_api_request(req, path, &access_token, body)
+                self.handle_troubleshoot_api_request(req, path, &access_token, body, session)
                     .await
             }
             // SPDX-SnippetBegin
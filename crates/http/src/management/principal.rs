@@ -6,19 +6,25 @@
 
 use common::{KV_BAYES_MODEL_USER, Server, auth::AccessToken};
 use directory::{
-    DirectoryInner, Permission, QueryBy, QueryParams, Type,
-    backend::internal::{
-        PrincipalAction, PrincipalField, PrincipalSet, PrincipalUpdate, PrincipalValue,
-        SpecialSecrets,
-        lookup::DirectoryStore,
-        manage::{
-            self, ChangedPrincipals, ManageDirectory, PrincipalList, UpdatePrincipal, not_found,
+    DirectoryInner, DomainStatus, Permission, QueryBy, QueryParams, Type,
+    backend::{
+        internal::{
+            PrincipalAction, PrincipalField, PrincipalSet, PrincipalUpdate, PrincipalValue,
+            SpecialSecrets,
+            lookup::DirectoryStore,
+            manage::{
+                self, ChangedPrincipals, ManageDirectory, PrincipalList, UpdatePrincipal, not_found,
+            },
         },
+        sql::{SqlDirectory, manage::SqlPrincipalState},
     },
 };
+use email::message::alias::DisposableAliasManager;
 use http_proto::{request::decode_path_element, *};
 use hyper::{Method, header};
+use jmap::identity::get::IdentityGet;
 use serde_json::json;
+use services::housekeeper::out_of_office::OutOfOfficeSync;
 use std::future::Future;
 use std::sync::Arc;
 use trc::AddContext;
@@ -65,6 +71,15 @@ pub trait PrincipalManager: Sync + Send {
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
 
     fn assert_supported_directory(&self, override_: bool) -> trc::Result<()>;
+
+    fn writable_sql_directory(&self, typ: Type) -> Option<&SqlDirectory>;
+
+    fn revert_sql_principal(
+        &self,
+        sql: &SqlDirectory,
+        previous: SqlPrincipalState,
+        changed_principals: ChangedPrincipals,
+    ) -> impl Future<Output = ()> + Send;
 }
 
 impl PrincipalManager for Server {
@@ -78,7 +93,7 @@ impl PrincipalManager for Server {
         match (path.get(1).copied(), req.method()) {
             (None | Some("deploy"), &Method::POST) => {
                 // Parse principal
-                let principal =
+                let mut principal =
                     serde_json::from_slice::<PrincipalSet>(body.as_deref().unwrap_or_default())
                         .map_err(|err| {
                             trc::EventType::Resource(trc::ResourceEvent::BadParameters)
@@ -112,6 +127,14 @@ impl PrincipalManager for Server {
 
                 // SPDX-SnippetEnd
 
+                // Domains added by tenants only receive mail once their ownership is verified
+                if principal.typ() == Type::Domain && access_token.tenant.is_some() {
+                    principal.set(
+                        PrincipalField::DomainStatus,
+                        DomainStatus::PendingVerification.as_str(),
+                    );
+                }
+
                 // Make sure the current directory supports updates
                 if matches!(principal.typ(), Type::Individual) {
                     self.assert_supported_directory(path.get(1).copied() == Some("deploy"))?;
@@ -160,6 +183,8 @@ impl PrincipalManager for Server {
                 };
 
                 // Create principal
+                let typ = principal.typ();
+                let name = principal.name().to_lowercase();
                 let result = self
                     .core
                     .storage
@@ -167,6 +192,25 @@ impl PrincipalManager for Server {
                     .create_principal(principal, tenant_id, Some(&access_token.permissions))
                     .await?;
 
+                // Provision the principal into the SQL directory, if writable
+                if let Some(sql) = self.writable_sql_directory(typ)
+                    && let Err(err) = sql.store_principal(result.id, None).await
+                {
+                    if let Err(err) = sql.remove_principal(&name).await {
+                        trc::error!(err.details("Failed to roll back SQL principal creation"));
+                    }
+                    if let Err(err) = self
+                        .core
+                        .storage
+                        .data
+                        .delete_principal(QueryBy::Id(result.id))
+                        .await
+                    {
+                        trc::error!(err.details("Failed to roll back principal creation"));
+                    }
+                    return Err(err);
+                }
+
                 // Set report domain
                 if let Some(report_domain) = report_domain
                     && let Err(err) = self
@@ -383,6 +427,29 @@ impl PrincipalManager for Server {
                             .as_ref()
                             .is_some_and(|c| c.account_classify);
                         for principal in principals.items {
+                            // Remove disposable aliases
+                            if matches!(typ, Type::Individual | Type::Group)
+                                && let Err(err) =
+                                    server.remove_disposable_aliases(principal.id()).await
+                            {
+                                trc::error!(err.details("Failed to delete disposable aliases"));
+                            }
+
+                            // Remove the SQL rows first, the principal is kept if they cannot be deleted
+                            let sql = server.writable_sql_directory(typ);
+                            if let Some(sql) = sql
+                                && let Err(err) = sql.remove_principal(principal.name()).await
+                            {
+                                trc::error!(err.details("Failed to delete SQL principal"));
+                                if let Err(err) = sql
+                                    .store_principal(principal.id(), Some(principal.name()))
+                                    .await
+                                {
+                                    trc::error!(err.details("Failed to restore SQL principal"));
+                                }
+                                continue;
+                            }
+
                             // Delete account
                             match server
                                 .store()
@@ -395,6 +462,12 @@ impl PrincipalManager for Server {
                                 }
                                 Err(err) => {
                                     trc::error!(err.details("Failed to delete principal"));
+                                    if let Some(sql) = sql
+                                        && let Err(err) =
+                                            sql.store_principal(principal.id(), None).await
+                                    {
+                                        trc::error!(err.details("Failed to restore SQL principal"));
+                                    }
                                     continue;
                                 }
                             }
@@ -509,11 +582,53 @@ impl PrincipalManager for Server {
                             }
                         })?;
 
+                        // Accounts under legal hold cannot be deleted
+                        if matches!(typ, Type::Individual | Type::Group)
+                            && self
+                                .get_access_token(account_id)
+                                .await
+                                .caused_by(trc::location!())?
+                                .legal_hold
+                                .is_some()
+                        {
+                            return Err(manage::error(
+                                "Account is under legal hold",
+                                "Lift the legal hold before deleting this account".into(),
+                            ));
+                        }
+
+                        // Remove disposable aliases
+                        if matches!(typ, Type::Individual | Type::Group) {
+                            self.remove_disposable_aliases(account_id).await?;
+                        }
+
+                        // Remove the SQL rows first so that a failure leaves both directories intact
+                        let sql = self.writable_sql_directory(typ);
+                        if let Some(sql) = sql
+                            && let Err(err) = sql.remove_principal(name.as_ref()).await
+                        {
+                            if let Err(err) =
+                                sql.store_principal(account_id, Some(name.as_ref())).await
+                            {
+                                trc::error!(err.details("Failed to restore SQL principal"));
+                            }
+                            return Err(err);
+                        }
+
                         // Delete account
-                        let changed_principals = self
-                            .store()
-                            .delete_principal(QueryBy::Id(account_id))
-                            .await?;
+                        let changed_principals =
+                            match self.store().delete_principal(QueryBy::Id(account_id)).await {
+                                Ok(changed_principals) => changed_principals,
+                                Err(err) => {
+                                    if let Some(sql) = sql
+                                        && let Err(err) =
+                                            sql.store_principal(account_id, None).await
+                                    {
+                                        trc::error!(err.details("Failed to restore SQL principal"));
+                                    }
+                                    return Err(err);
+                                }
+                            };
 
                         if matches!(typ, Type::Individual | Type::Group) {
                             // Remove FTS index
@@ -572,14 +687,33 @@ impl PrincipalManager for Server {
                                 .from_json_error(err)
                         })?;
 
+                        // Renamed or moved domains may no longer be active
+                        let mut invalidate_domains = Vec::new();
+                        if typ == Type::Domain {
+                            invalidate_domains.push(name.to_string());
+                            invalidate_domains.extend(changes.iter().filter_map(|change| {
+                                match (change.field, &change.value) {
+                                    (PrincipalField::Name, PrincipalValue::String(name)) => {
+                                        Some(name.to_lowercase())
+                                    }
+                                    _ => None,
+                                }
+                            }));
+                        }
+
                         // Validate changes
                         let mut invalidate_logo_cache = false;
+                        let mut legal_hold = None;
+                        let mut sync_out_of_office = false;
+                        let mut sync_identities = false;
                         for change in &changes {
                             match change.field {
                                 PrincipalField::Secrets
                                 | PrincipalField::Name
                                 | PrincipalField::Emails
                                 | PrincipalField::Quota
+                                | PrincipalField::SoftQuota
+                                | PrincipalField::MessageQuota
                                 | PrincipalField::UsedQuota
                                 | PrincipalField::Description
                                 | PrincipalField::Type
@@ -588,11 +722,54 @@ impl PrincipalManager for Server {
                                 | PrincipalField::Lists
                                 | PrincipalField::Urls
                                 | PrincipalField::ExternalMembers
-                                | PrincipalField::Locale => (),
+                                | PrincipalField::AllowedSenders
+                                | PrincipalField::Locale
+                                | PrincipalField::RecoveryEmail
+                                | PrincipalField::SpamTrap
+                                | PrincipalField::CollectContacts
+                                | PrincipalField::Phone
+                                | PrincipalField::VerificationToken => (),
+                                PrincipalField::CalendarOutOfOffice => {
+                                    sync_out_of_office = true;
+                                }
+                                PrincipalField::SendAs | PrincipalField::SendOnBehalf => {
+                                    sync_identities = true;
+                                }
+                                PrincipalField::DomainStatus => {
+                                    // Tenants activate domains through the onboarding API
+                                    if access_token.tenant.is_some() {
+                                        trc::bail!(
+                                            trc::SecurityEvent::Unauthorized
+                                                .into_err()
+                                                .details(permission_needed.name())
+                                                .ctx(
+                                                    trc::Key::Reason,
+                                                    "Tenants cannot change the domain status"
+                                                )
+                                        );
+                                    }
+                                }
                                 PrincipalField::Picture => {
                                     invalidate_logo_cache |=
                                         matches!(typ, Type::Domain | Type::Tenant);
                                 }
+                                PrincipalField::LegalHold => {
+                                    // Legal holds are managed by system administrators only
+                                    if access_token.tenant.is_some() {
+                                        trc::bail!(
+                                            trc::SecurityEvent::Unauthorized
+                                                .into_err()
+                                                .details(permission_needed.name())
+                                                .ctx(
+                                                    trc::Key::Reason,
+                                                    "Tenants cannot change legal holds"
+                                                )
+                                        );
+                                    }
+                                    if let PrincipalValue::Integer(hold) = &change.value {
+                                        legal_hold = Some(*hold > 0);
+                                    }
+                                }
                                 PrincipalField::Tenant => {
                                     // Tenants are not allowed to change their tenantId
                                     if access_token.tenant.is_some() {
@@ -660,6 +837,14 @@ impl PrincipalManager for Server {
                             }
                         }
 
+                        // Snapshot the principal so the update can be reverted if the SQL write fails
+                        let sql = self.writable_sql_directory(typ);
+                        let previous = if let Some(sql) = sql {
+                            Some(sql.principal_state(account_id).await?)
+                        } else {
+                            None
+                        };
+
                         // Update principal
                         let changed_principals = self
                             .core
@@ -673,14 +858,64 @@ impl PrincipalManager for Server {
                             )
                             .await?;
 
+                        // Keep the SQL directory in sync, including any members affected by the change
+                        if let (Some(sql), Some(previous)) = (sql, previous)
+                            && let Err(err) = sql
+                                .store_principals(account_id, name.as_ref(), &changed_principals)
+                                .await
+                        {
+                            self.revert_sql_principal(sql, previous, changed_principals)
+                                .await;
+                            return Err(err);
+                        }
+
                         // Increment revision
                         self.invalidate_principal_caches(changed_principals).await;
+                        for domain in &invalidate_domains {
+                            self.invalidate_domain_cache(domain);
+                        }
 
                         // Invalidate logo cache if needed
                         if invalidate_logo_cache {
                             self.inner.data.logos.lock().clear();
                         }
 
+                        // Enable or revert the automatic vacation response right away
+                        if sync_out_of_office
+                            && let Some(config) = &self.core.groupware.out_of_office
+                            && let Err(err) =
+                                self.sync_account_out_of_office(account_id, config).await
+                        {
+                            trc::error!(
+                                err.account_id(account_id)
+                                    .details("Failed to synchronize out of office status")
+                            );
+                        }
+
+                        // Create or remove the identities of delegated addresses
+                        if sync_identities
+                            && let Err(err) = self.identity_sync_delegated(account_id).await
+                        {
+                            trc::error!(
+                                err.account_id(account_id)
+                                    .details("Failed to synchronize delegated identities")
+                            );
+                        }
+
+                        // Audit legal hold changes
+                        if let Some(legal_hold) = legal_hold {
+                            trc::event!(
+                                Security(if legal_hold {
+                                    trc::SecurityEvent::LegalHoldSet
+                                } else {
+                                    trc::SecurityEvent::LegalHoldReleased
+                                }),
+                                AccountId = account_id,
+                                AccountName = name.to_string(),
+                                Details = access_token.name.clone(),
+                            );
+                        }
+
                         Ok(JsonResponse::new(json!({
                             "data": (),
                         }))
@@ -847,6 +1082,14 @@ impl PrincipalManager for Server {
             });
         }
 
+        // Snapshot the principal so the update can be reverted if the SQL write fails
+        let sql = self.writable_sql_directory(Type::Individual);
+        let previous = if let Some(sql) = sql {
+            Some(sql.principal_state(access_token.primary_id()).await?)
+        } else {
+            None
+        };
+
         // Update password
         let changed_principals = self
             .core
@@ -858,6 +1101,15 @@ impl PrincipalManager for Server {
                     .with_tenant(access_token.tenant.map(|t| t.id)),
             )
             .await?;
+        if let (Some(sql), Some(previous)) = (sql, previous)
+            && let Err(err) = sql
+                .store_principal(access_token.primary_id(), Some(access_token.name.as_str()))
+                .await
+        {
+            self.revert_sql_principal(sql, previous, changed_principals)
+                .await;
+            return Err(err);
+        }
 
         // Increment revision
         self.invalidate_principal_caches(changed_principals).await;
@@ -871,12 +1123,14 @@ impl PrincipalManager for Server {
     fn assert_supported_directory(&self, override_: bool) -> trc::Result<()> {
         let class = match &self.core.storage.directory.store {
             DirectoryInner::Internal(_) => return Ok(()),
+            DirectoryInner::Sql(sql) if sql.supports_writes() => return Ok(()),
             DirectoryInner::Ldap(_) => "LDAP",
             DirectoryInner::Sql(_) => "SQL",
             DirectoryInner::Imap(_) => "IMAP",
             DirectoryInner::Smtp(_) => "SMTP",
             DirectoryInner::Memory(_) => "In-Memory",
             DirectoryInner::OpenId(_) => "OpenID",
+            DirectoryInner::Http(_) => "HTTP",
         };
 
         if !override_ {
@@ -892,4 +1146,28 @@ impl PrincipalManager for Server {
             Ok(())
         }
     }
+
+    fn writable_sql_directory(&self, typ: Type) -> Option<&SqlDirectory> {
+        match &self.core.storage.directory.store {
+            DirectoryInner::Sql(sql) if sql.is_managed_type(typ) => Some(sql),
+            _ => None,
+        }
+    }
+
+    async fn revert_sql_principal(
+        &self,
+        sql: &SqlDirectory,
+        previous: SqlPrincipalState,
+        changed_principals: ChangedPrincipals,
+    ) {
+        match sql.revert_principal(previous).await {
+            Ok(reverted_principals) => {
+                self.invalidate_principal_caches(reverted_principals).await;
+            }
+            Err(err) => {
+                trc::error!(err.details("Failed to revert principal update"));
+            }
+        }
+        self.invalidate_principal_caches(changed_principals).await;
+    }
 }
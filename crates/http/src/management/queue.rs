@@ -24,14 +24,17 @@ use mail_parser::DateTime;
 use serde::{Deserializer, Serializer};
 use serde_json::json;
 use smtp::{
+    outbound::posture::TlsPostureHistory,
     queue::{
-        self, ArchivedMessage, ArchivedStatus, ErrorDetails, QueueId, Status, spool::SmtpSpool,
+        self, ArchivedError, ArchivedErrorDetails, ArchivedMessage, ArchivedStatus, ErrorDetails,
+        QueueId, Status, spool::SmtpSpool,
     },
     reporting::{dmarc::DmarcReporting, tls::TlsReporting},
 };
 use std::{future::Future, sync::atomic::Ordering};
 use store::{
     Deserialize, IterateParams, ValueKey,
+    ahash::AHashMap,
     write::{
         AlignedBytes, Archive, QueueClass, ReportEvent, ValueClass, key::DeserializeBigEndian, now,
     },
@@ -542,6 +545,32 @@ impl QueueManagement for Server {
                     Err(trc::ResourceEvent::NotFound.into_err())
                 }
             }
+            ("deferred", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueList)?;
+
+                Ok(JsonResponse::new(json!({
+                        "data": fetch_deferred_stats(self, &params, &tenant_domains).await?,
+                }))
+                .into_http_response())
+            }
+            ("tls", Some(domain), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let domain = domain.to_lowercase();
+                if tenant_domains
+                    .as_ref()
+                    .is_some_and(|domains| !domains.contains(&domain))
+                {
+                    return Err(trc::ResourceEvent::NotFound.into_err());
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": self.tls_posture_history(&domain).await?,
+                }))
+                .into_http_response())
+            }
             ("status", None, &Method::GET) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::MessageQueueGet)?;
@@ -741,6 +770,200 @@ async fn fetch_queued_messages(
         .map(|_| result)
 }
 
+#[derive(Debug, Default, serde::Serialize)]
+struct DeferredStats {
+    total: usize,
+    retries: Vec<RetryBucket>,
+    reasons: Vec<DeferralReason>,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct RetryBucket {
+    label: &'static str,
+    count: usize,
+}
+
+#[derive(Debug, serde::Serialize)]
+struct DeferralReason {
+    reason: String,
+    count: usize,
+    domains: Vec<String>,
+}
+
+// Upper bounds, in seconds from now, of the next retry histogram buckets
+const RETRY_BUCKETS: [(&str, u64); 6] = [
+    ("due", 0),
+    ("5m", 300),
+    ("15m", 900),
+    ("1h", 3600),
+    ("6h", 21600),
+    ("24h", 86400),
+];
+const MAX_REASON_DOMAINS: usize = 5;
+
+async fn fetch_deferred_stats(
+    server: &Server,
+    params: &UrlParams<'_>,
+    tenant_domains: &Option<Vec<String>>,
+) -> trc::Result<DeferredStats> {
+    let queue = params.get("queue").and_then(QueueName::new);
+    let limit = params.parse::<usize>("limit").unwrap_or(10);
+    let now = now();
+
+    let mut stats = DeferredStats {
+        retries: RETRY_BUCKETS
+            .iter()
+            .map(|(label, _)| *label)
+            .chain(["later"])
+            .map(|label| RetryBucket { label, count: 0 })
+            .collect(),
+        ..Default::default()
+    };
+    let mut reasons: AHashMap<String, (usize, AHashMap<String, usize>)> = AHashMap::new();
+
+    let from_key = ValueKey::from(ValueClass::Queue(QueueClass::Message(0)));
+    let to_key = ValueKey::from(ValueClass::Queue(QueueClass::Message(u64::MAX)));
+    server
+        .core
+        .storage
+        .data
+        .iterate(
+            IterateParams::new(from_key, to_key).ascending(),
+            |key, value| {
+                let message_ = <Archive<AlignedBytes> as Deserialize>::deserialize(value)
+                    .add_context(|ctx| ctx.ctx(trc::Key::Key, key))?;
+                let message = message_
+                    .unarchive::<queue::Message>()
+                    .add_context(|ctx| ctx.ctx(trc::Key::Key, key))?;
+                for rcpt in message.recipients.iter() {
+                    let ArchivedStatus::TemporaryFailure(err) = &rcpt.status else {
+                        continue;
+                    };
+                    if queue.as_ref().is_some_and(|queue| &rcpt.queue != queue) {
+                        continue;
+                    }
+
+                    // Tenants only see recipients in their own domains, even on shared messages
+                    let domain = rcpt.domain_part();
+                    if tenant_domains
+                        .as_ref()
+                        .is_some_and(|domains| !domains.iter().any(|dd| dd == domain))
+                    {
+                        continue;
+                    }
+                    stats.total += 1;
+
+                    // Bucket by next retry time
+                    let due_in = u64::from(rcpt.retry.due).saturating_sub(now);
+                    let bucket = RETRY_BUCKETS
+                        .iter()
+                        .position(|(_, max)| due_in <= *max)
+                        .unwrap_or(RETRY_BUCKETS.len());
+                    stats.retries[bucket].count += 1;
+
+                    // Group by normalized reason
+                    let (count, domains) = reasons.entry(deferral_reason(err)).or_default();
+                    *count += 1;
+                    *domains.entry(domain.to_string()).or_default() += 1;
+                }
+
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())?;
+
+    let mut reasons = reasons
+        .into_iter()
+        .map(|(reason, (count, domains))| {
+            let mut domains = domains.into_iter().collect::<Vec<_>>();
+            domains.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            DeferralReason {
+                reason,
+                count,
+                domains: domains
+                    .into_iter()
+                    .take(MAX_REASON_DOMAINS)
+                    .map(|(domain, _)| domain)
+                    .collect(),
+            }
+        })
+        .collect::<Vec<_>>();
+    reasons.sort_unstable_by(|a, b| b.count.cmp(&a.count).then_with(|| a.reason.cmp(&b.reason)));
+    if limit > 0 {
+        reasons.truncate(limit);
+    }
+    stats.reasons = reasons;
+
+    Ok(stats)
+}
+
+// Remote responses often embed queue ids, addresses or timestamps, these are
+// masked so that equivalent deferrals are grouped together
+fn deferral_reason(err: &ArchivedErrorDetails) -> String {
+    let (class, details) = match &err.details {
+        ArchivedError::UnexpectedResponse(response) => {
+            let response = &response.response;
+            let code = u16::from(response.code);
+            let class = if response.esc.iter().any(|v| *v != 0) {
+                format!(
+                    "{code} {}.{}.{}",
+                    response.esc[0], response.esc[1], response.esc[2]
+                )
+            } else {
+                code.to_string()
+            };
+            (class, response.message.as_str())
+        }
+        ArchivedError::DnsError(details) => ("DNS error".to_string(), details.as_str()),
+        ArchivedError::ConnectionError(details) => {
+            ("Connection error".to_string(), details.as_str())
+        }
+        ArchivedError::TlsError(details) => ("TLS error".to_string(), details.as_str()),
+        ArchivedError::DaneError(details) => ("DANE error".to_string(), details.as_str()),
+        ArchivedError::MtaStsError(details) => ("MTA-STS error".to_string(), details.as_str()),
+        ArchivedError::RateLimited => return "Rate limited".to_string(),
+        ArchivedError::ConcurrencyLimited => return "Concurrency limited".to_string(),
+        ArchivedError::Io(details) => ("Queue error".to_string(), details.as_str()),
+    };
+
+    normalize_reason(class, details)
+}
+
+fn normalize_reason(class: String, details: &str) -> String {
+    let mut reason = class;
+    reason.push_str(": ");
+    let mut last_ch = ' ';
+    for word in details.split_ascii_whitespace() {
+        if reason.len() >= 128 {
+            break;
+        }
+        if last_ch != ' ' {
+            reason.push(' ');
+            last_ch = ' ';
+        }
+        let word = if word.contains('@') {
+            "<address>"
+        } else {
+            word
+        };
+        for ch in word.chars() {
+            let ch = if ch.is_ascii_digit() {
+                '#'
+            } else {
+                ch.to_ascii_lowercase()
+            };
+            // Collapse runs of digits
+            if ch != '#' || last_ch != '#' {
+                reason.push(ch);
+            }
+            last_ch = ch;
+        }
+    }
+
+    reason.trim_end_matches([' ', ':']).to_string()
+}
+
 struct QueuedReports {
     ids: Vec<QueueClass>,
     total: usize,
@@ -948,3 +1171,35 @@ impl IsTenantDomain for ArchivedMessage {
             .is_none_or(|domains| self.has_domain(domains))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::normalize_reason;
+
+    #[test]
+    fn deferral_reasons() {
+        for (class, details, expected) in [
+            (
+                "451 4.7.1",
+                "Greylisted, try again in 300 seconds",
+                "451 4.7.1: greylisted, try again in # seconds",
+            ),
+            (
+                "452 4.2.2",
+                "Mailbox of <John.Doe@Example.org> is FULL (queue id 9F3A21B0)",
+                "452 4.2.2: mailbox of <address> is full (queue id #f#a#b#)",
+            ),
+            (
+                "Connection error",
+                "Connection to 192.168.1.20:25   timed out",
+                "Connection error: connection to #.#.#.#:# timed out",
+            ),
+            ("DNS error", "", "DNS error"),
+        ] {
+            assert_eq!(normalize_reason(class.to_string(), details), expected);
+        }
+
+        // Long responses are truncated to group equivalent deferrals
+        assert!(normalize_reason("421".to_string(), &"word ".repeat(100)).len() <= 140);
+    }
+}
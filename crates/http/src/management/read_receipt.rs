@@ -0,0 +1,71 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use email::message::mdn::{EmailMdn, MdnSettings};
+use http_proto::*;
+use hyper::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::future::Future;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadReceiptSettings {
+    auto_send: bool,
+}
+
+pub trait ReadReceiptManagement: Sync + Send {
+    fn handle_read_receipt_settings(
+        &self,
+        req: &HttpRequest,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ReadReceiptManagement for Server {
+    async fn handle_read_receipt_settings(
+        &self,
+        req: &HttpRequest,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let account_id = access_token.primary_id();
+
+        match *req.method() {
+            Method::GET => {
+                let settings = self.get_mdn_settings(account_id).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": ReadReceiptSettings {
+                        auto_send: settings.auto_send,
+                    },
+                }))
+                .into_http_response())
+            }
+            Method::PUT => {
+                let request = serde_json::from_slice::<ReadReceiptSettings>(
+                    body.as_deref().unwrap_or_default(),
+                )
+                .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
+                self.set_mdn_settings(
+                    account_id,
+                    MdnSettings {
+                        auto_send: request.auto_send,
+                    },
+                )
+                .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
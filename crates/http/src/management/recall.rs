@@ -0,0 +1,369 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken, config::jmap::settings::SpecialUse};
+use directory::backend::internal::manage::{self, not_found};
+use email::{
+    cache::{MessageCacheFetch, email::MessageCacheAccess, mailbox::MailboxCacheAccess},
+    mailbox::INBOX_ID,
+    message::{
+        delete::EmailDeletion,
+        index::MAX_ID_LENGTH,
+        ingest::{EmailIngest, IngestEmail, IngestSource},
+        metadata::{MessageData, MessageMetadata},
+    },
+};
+use http_proto::*;
+use jmap_proto::types::{collection::Collection, keyword::Keyword, property::Property};
+use mail_builder::MessageBuilder;
+use mail_parser::{HeaderName, Message, MessageParser};
+use serde_json::json;
+use std::{fmt::Write, future::Future};
+use store::write::BatchBuilder;
+use trc::AddContext;
+
+use super::redact::RedactApi;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RecallRequest {
+    #[serde(rename = "messageId")]
+    pub message_id: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RecallStatus {
+    Recalled,
+    AlreadyRead,
+    NotFound,
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RecallResult {
+    pub recipient: String,
+    pub status: RecallStatus,
+}
+
+pub trait RecallApi: Sync + Send {
+    fn handle_recall_request(
+        &self,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl RecallApi for Server {
+    async fn handle_recall_request(
+        &self,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let request = serde_json::from_slice::<RecallRequest>(body.as_deref().unwrap_or_default())
+            .map_err(|err| {
+                trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+            })?;
+        let message_id = request
+            .message_id
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>');
+        if message_id.is_empty() || message_id.len() >= MAX_ID_LENGTH {
+            return Err(trc::ResourceEvent::BadParameters
+                .into_err()
+                .details("Invalid Message-ID"));
+        }
+
+        // Recipients are obtained from the sender's copy in the Sent folder, which includes any Bcc
+        let sender_id = access_token.primary_id();
+        let cache = self
+            .get_cached_messages(sender_id)
+            .await
+            .caused_by(trc::location!())?;
+        let sent_mailbox_id = cache
+            .mailbox_by_role(&SpecialUse::Sent)
+            .map(|mailbox| mailbox.document_id)
+            .ok_or_else(|| not_found(message_id.to_string()))?;
+        let sent_id = self
+            .find_message_id(sender_id, message_id)
+            .await
+            .caused_by(trc::location!())?
+            .into_iter()
+            .find(|document_id| {
+                cache.email_by_id(document_id).is_some_and(|email| {
+                    email
+                        .mailboxes
+                        .iter()
+                        .any(|mailbox| mailbox.mailbox_id == sent_mailbox_id)
+                })
+            })
+            .ok_or_else(|| not_found(message_id.to_string()))?;
+        let Some(metadata_) = self
+            .get_archive_by_property(
+                sender_id,
+                Collection::Email,
+                sent_id,
+                &Property::BodyStructure,
+            )
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Err(not_found(message_id.to_string()));
+        };
+        let metadata = metadata_
+            .unarchive::<MessageMetadata>()
+            .caused_by(trc::location!())?;
+        let mut subject = String::new();
+        let mut recipients = Vec::new();
+        if let Some(message) = MessageParser::new().parse_headers(metadata.raw_headers.as_slice()) {
+            if !is_sent_by(&message, access_token) {
+                return Err(manage::error(
+                    "Only messages sent from your own addresses can be recalled",
+                    None::<String>,
+                ));
+            }
+
+            subject = message.subject().unwrap_or_default().to_string();
+            for header in message.headers() {
+                if matches!(
+                    header.name,
+                    HeaderName::To | HeaderName::Cc | HeaderName::Bcc
+                ) && let Some(addrs) = header.value().as_address()
+                {
+                    for addr in addrs.iter() {
+                        if let Some(address) = addr.address().map(|a| a.trim().to_lowercase())
+                            && !address.is_empty()
+                            && !recipients.contains(&address)
+                        {
+                            recipients.push(address);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Only messages that never left the server can be recalled
+        for recipient in &recipients {
+            let domain = recipient.rsplit_once('@').map_or("", |(_, domain)| domain);
+            if !self
+                .core
+                .storage
+                .directory
+                .is_local_domain(domain)
+                .await
+                .caused_by(trc::location!())?
+            {
+                return Err(manage::error(
+                    "Messages sent to external recipients cannot be recalled",
+                    recipient.to_string().into(),
+                ));
+            }
+        }
+
+        let mut results = Vec::with_capacity(recipients.len());
+        let mut recalled_accounts = Vec::new();
+        for recipient in recipients {
+            let account_id = self
+                .email_to_id(&self.core.storage.directory, &recipient, session.session_id)
+                .await
+                .caused_by(trc::location!())?;
+            let status = match account_id {
+                Some(account_id) if account_id == sender_id => continue,
+                Some(account_id) if recalled_accounts.contains(&account_id) => {
+                    RecallStatus::Recalled
+                }
+                Some(account_id) => {
+                    let status = recall_message(self, access_token, account_id, message_id)
+                        .await
+                        .caused_by(trc::location!())?;
+                    if status == RecallStatus::Recalled {
+                        recalled_accounts.push(account_id);
+
+                        trc::event!(
+                            MessageIngest(trc::MessageIngestEvent::Recalled),
+                            SpanId = session.session_id,
+                            AccountId = account_id,
+                            MessageId = message_id.to_string(),
+                            From = access_token.name.clone(),
+                            To = recipient.clone(),
+                        );
+                    }
+                    status
+                }
+                None => RecallStatus::NotFound,
+            };
+
+            results.push(RecallResult { recipient, status });
+        }
+
+        // Notify the sender
+        ingest_recall_report(
+            self,
+            access_token,
+            message_id,
+            &subject,
+            &results,
+            session.session_id,
+        )
+        .await
+        .caused_by(trc::location!())?;
+
+        Ok(JsonResponse::new(json!({
+            "data": results,
+        }))
+        .into_http_response())
+    }
+}
+
+// Only copies sent by the caller are recalled, messages that were read by the recipient are left untouched
+async fn recall_message(
+    server: &Server,
+    access_token: &AccessToken,
+    account_id: u32,
+    message_id: &str,
+) -> trc::Result<RecallStatus> {
+    let mut document_ids = server
+        .find_message_id(account_id, message_id)
+        .await
+        .caused_by(trc::location!())?;
+    for document_id in document_ids.clone() {
+        let is_sent_by = server
+            .get_archive_by_property(
+                account_id,
+                Collection::Email,
+                document_id,
+                &Property::BodyStructure,
+            )
+            .await
+            .caused_by(trc::location!())?
+            .map(|metadata_| {
+                metadata_
+                    .unarchive::<MessageMetadata>()
+                    .caused_by(trc::location!())
+                    .map(|metadata| {
+                        MessageParser::new()
+                            .parse_headers(metadata.raw_headers.as_slice())
+                            .is_some_and(|message| is_sent_by(&message, access_token))
+                    })
+            })
+            .transpose()?
+            .unwrap_or(false);
+        if !is_sent_by {
+            document_ids.remove(document_id);
+        }
+    }
+    if document_ids.is_empty() {
+        return Ok(RecallStatus::NotFound);
+    }
+
+    for document_id in &document_ids {
+        if let Some(data_) = server
+            .get_archive(account_id, Collection::Email, document_id)
+            .await
+            .caused_by(trc::location!())?
+            && data_
+                .deserialize::<MessageData>()
+                .caused_by(trc::location!())?
+                .keywords
+                .contains(&Keyword::Seen)
+        {
+            return Ok(RecallStatus::AlreadyRead);
+        }
+    }
+
+    let mut batch = BatchBuilder::new();
+    server
+        .emails_tombstone(account_id, &mut batch, document_ids)
+        .await
+        .caused_by(trc::location!())?;
+    server
+        .commit_batch(batch)
+        .await
+        .caused_by(trc::location!())?;
+
+    Ok(RecallStatus::Recalled)
+}
+
+// The From address and, when present, the Return-Path must belong to the caller
+fn is_sent_by(message: &Message<'_>, access_token: &AccessToken) -> bool {
+    let is_own_address = |address: &str| {
+        access_token
+            .emails
+            .iter()
+            .any(|email| email.eq_ignore_ascii_case(address.trim()))
+    };
+
+    message
+        .from()
+        .and_then(|from| from.first())
+        .and_then(|from| from.address())
+        .is_some_and(is_own_address)
+        && message
+            .header(HeaderName::ReturnPath)
+            .and_then(|return_path| {
+                return_path
+                    .as_address()
+                    .and_then(|addr| addr.first())
+                    .and_then(|addr| addr.address())
+                    .or_else(|| return_path.as_text())
+            })
+            .is_none_or(|address| {
+                is_own_address(address.trim().trim_start_matches('<').trim_end_matches('>'))
+            })
+}
+
+async fn ingest_recall_report(
+    server: &Server,
+    access_token: &AccessToken,
+    message_id: &str,
+    subject: &str,
+    results: &[RecallResult],
+    session_id: u64,
+) -> trc::Result<()> {
+    let mut text = format!("Recall report for message <{message_id}>:\r\n\r\n");
+    for result in results {
+        let _ = write!(
+            text,
+            "{}: {}\r\n",
+            result.recipient,
+            match result.status {
+                RecallStatus::Recalled => "the message was recalled",
+                RecallStatus::AlreadyRead => "recall failed, the message was already read",
+                RecallStatus::NotFound => "recall failed, the message was not found",
+            }
+        );
+    }
+
+    let from_addr = format!("MAILER-DAEMON@{}", server.core.network.server_name);
+    let raw_message = MessageBuilder::new()
+        .from(("Mail Delivery System", from_addr.as_str()))
+        .to(access_token
+            .emails
+            .first()
+            .map_or(access_token.name.as_str(), |email| email.as_str()))
+        .in_reply_to(message_id)
+        .subject(format!("Recall report: {subject}"))
+        .text_body(text)
+        .write_to_vec()
+        .unwrap_or_default();
+
+    server
+        .email_ingest(IngestEmail {
+            raw_message: &raw_message,
+            message: MessageParser::new().parse(&raw_message),
+            access_token,
+            mailbox_ids: vec![INBOX_ID],
+            keywords: vec![],
+            received_at: None,
+            source: IngestSource::Restore,
+            spam_classify: false,
+            spam_train: false,
+            session_id,
+        })
+        .await
+        .map(|_| ())
+}
@@ -0,0 +1,390 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::backend::internal::manage::ManageDirectory;
+use email::{
+    cache::{MessageCacheFetch, email::MessageCacheAccess},
+    message::{
+        delete::EmailDeletion,
+        index::MAX_ID_LENGTH,
+        ingest::{EmailIngest, IngestEmail, IngestSource},
+        metadata::{MessageData, MessageMetadata},
+    },
+};
+use http_proto::*;
+use jmap_proto::types::{collection::Collection, property::Property};
+use mail_parser::{HeaderName, MessageParser};
+use serde_json::json;
+use std::future::Future;
+use store::{
+    IndexKey, IterateParams, U32_LEN, ValueKey,
+    roaring::RoaringBitmap,
+    write::{AlignedBytes, Archive, BatchBuilder, ValueClass, key::DeserializeBigEndian},
+};
+use trc::AddContext;
+
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct RedactRequest {
+    #[serde(rename = "messageId")]
+    pub message_id: String,
+    #[serde(default)]
+    pub action: RedactAction,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RedactAction {
+    #[default]
+    Delete,
+    Replace,
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RedactResult {
+    pub account: String,
+    pub total: u64,
+}
+
+pub trait RedactApi: Sync + Send {
+    fn handle_redact_request(
+        &self,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn find_message_id(
+        &self,
+        account_id: u32,
+        message_id: &str,
+    ) -> impl Future<Output = trc::Result<RoaringBitmap>> + Send;
+
+    fn ingest_redaction_stub(
+        &self,
+        access_token: &AccessToken,
+        document_id: u32,
+        reason: Option<&str>,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+// Headers copied from the original message into the redaction stub, the
+// Message-ID is not kept so the stub is not matched by later redactions
+const STUB_HEADERS: &[HeaderName<'static>] = &[
+    HeaderName::From,
+    HeaderName::Sender,
+    HeaderName::To,
+    HeaderName::Cc,
+    HeaderName::Date,
+    HeaderName::InReplyTo,
+    HeaderName::References,
+];
+
+impl RedactApi for Server {
+    async fn handle_redact_request(
+        &self,
+        body: Option<Vec<u8>>,
+        session: &HttpSessionData,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let request = serde_json::from_slice::<RedactRequest>(body.as_deref().unwrap_or_default())
+            .map_err(|err| {
+                trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+            })?;
+        let message_id = request
+            .message_id
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>');
+        if message_id.is_empty() || message_id.len() >= MAX_ID_LENGTH {
+            return Err(trc::ResourceEvent::BadParameters
+                .into_err()
+                .details("Invalid Message-ID"));
+        }
+        let reason = request
+            .reason
+            .as_deref()
+            .filter(|reason| !reason.is_empty());
+
+        let mut results = Vec::new();
+        for account_id in self
+            .get_document_ids(u32::MAX, Collection::Principal)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default()
+        {
+            let document_ids = self
+                .find_message_id(account_id, message_id)
+                .await
+                .caused_by(trc::location!())?;
+            if document_ids.is_empty() {
+                continue;
+            }
+
+            // Add redaction stubs before removing the original messages
+            if request.action == RedactAction::Replace {
+                let account_token = self
+                    .get_access_token(account_id)
+                    .await
+                    .caused_by(trc::location!())?;
+                for document_id in &document_ids {
+                    self.ingest_redaction_stub(
+                        &account_token,
+                        document_id,
+                        reason,
+                        session.session_id,
+                    )
+                    .await
+                    .caused_by(trc::location!())?;
+                }
+            }
+
+            // SPDX-SnippetBegin
+            // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+            // SPDX-License-Identifier: LicenseRef-SEL
+
+            // Obtain blob hashes to prevent the messages from being undeleted
+            #[cfg(feature = "enterprise")]
+            let mut blob_hashes = Vec::new();
+            #[cfg(feature = "enterprise")]
+            if self.core.is_undelete_enabled() {
+                for document_id in &document_ids {
+                    if let Some(metadata_) = self
+                        .store()
+                        .get_value::<Archive<AlignedBytes>>(ValueKey {
+                            account_id,
+                            collection: Collection::Email.into(),
+                            document_id,
+                            class: ValueClass::Property(Property::BodyStructure.into()),
+                        })
+                        .await
+                        .caused_by(trc::location!())?
+                    {
+                        blob_hashes.push(utils::BlobHash::from(
+                            &metadata_
+                                .unarchive::<MessageMetadata>()
+                                .caused_by(trc::location!())?
+                                .blob_hash,
+                        ));
+                    }
+                }
+            }
+
+            // SPDX-SnippetEnd
+
+            // Tombstone and purge the messages, accounts under legal hold
+            // keep the tombstones until the hold is lifted
+            let total = document_ids.len();
+            let mut batch = BatchBuilder::new();
+            self.emails_tombstone(account_id, &mut batch, document_ids.clone())
+                .await
+                .caused_by(trc::location!())?;
+            self.commit_batch(batch).await.caused_by(trc::location!())?;
+            self.emails_purge_tombstoned_ids(account_id, Some(document_ids))
+                .await
+                .caused_by(trc::location!())?;
+
+            // SPDX-SnippetBegin
+            // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+            // SPDX-License-Identifier: LicenseRef-SEL
+
+            #[cfg(feature = "enterprise")]
+            if !blob_hashes.is_empty() {
+                let mut batch = BatchBuilder::new();
+                batch.with_account_id(account_id);
+                for blob in self
+                    .core
+                    .list_deleted(account_id)
+                    .await
+                    .caused_by(trc::location!())?
+                {
+                    if blob_hashes.contains(&blob.hash) {
+                        batch.clear(ValueClass::Blob(store::write::BlobOp::Reserve {
+                            hash: blob.hash,
+                            until: blob.expires_at,
+                        }));
+                    }
+                }
+                if !batch.is_empty() {
+                    self.store()
+                        .write(batch.build_all())
+                        .await
+                        .caused_by(trc::location!())?;
+                }
+            }
+
+            // SPDX-SnippetEnd
+
+            let account = self
+                .store()
+                .get_principal_name(account_id)
+                .await
+                .caused_by(trc::location!())?
+                .unwrap_or_else(|| account_id.to_string());
+
+            trc::event!(
+                Security(trc::SecurityEvent::MessageRedacted),
+                SpanId = session.session_id,
+                AccountId = account_id,
+                AccountName = account.clone(),
+                MessageId = message_id.to_string(),
+                Type = match request.action {
+                    RedactAction::Delete => "delete",
+                    RedactAction::Replace => "replace",
+                },
+                Total = total,
+                Reason = reason.map(|reason| reason.to_string()),
+                Details = access_token.name.clone(),
+            );
+
+            results.push(RedactResult { account, total });
+        }
+
+        Ok(JsonResponse::new(json!({
+            "data": results,
+        }))
+        .into_http_response())
+    }
+
+    async fn find_message_id(
+        &self,
+        account_id: u32,
+        message_id: &str,
+    ) -> trc::Result<RoaringBitmap> {
+        // Message-IDs are indexed with a trailing zero byte
+        let mut key = Vec::with_capacity(message_id.len() + 1);
+        key.extend_from_slice(message_id.as_bytes());
+        key.push(0);
+
+        let mut document_ids = RoaringBitmap::new();
+        self.store()
+            .iterate(
+                IterateParams::new(
+                    IndexKey {
+                        account_id,
+                        collection: Collection::Email.into(),
+                        document_id: 0,
+                        field: Property::References.into(),
+                        key: key.clone(),
+                    },
+                    IndexKey {
+                        account_id,
+                        collection: Collection::Email.into(),
+                        document_id: u32::MAX,
+                        field: Property::References.into(),
+                        key,
+                    },
+                )
+                .no_values()
+                .ascending(),
+                |key, _| {
+                    document_ids.insert(key.deserialize_be_u32(key.len() - U32_LEN)?);
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        // Skip messages that are already tombstoned
+        if !document_ids.is_empty() {
+            let cache = self
+                .get_cached_messages(account_id)
+                .await
+                .caused_by(trc::location!())?;
+            document_ids &= cache.email_document_ids();
+        }
+
+        Ok(document_ids)
+    }
+
+    async fn ingest_redaction_stub(
+        &self,
+        access_token: &AccessToken,
+        document_id: u32,
+        reason: Option<&str>,
+        session_id: u64,
+    ) -> trc::Result<()> {
+        let account_id = access_token.primary_id;
+        let Some(data_) = self
+            .get_archive(account_id, Collection::Email, document_id)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(());
+        };
+        let data = data_
+            .deserialize::<MessageData>()
+            .caused_by(trc::location!())?;
+        let Some(metadata_) = self
+            .store()
+            .get_value::<Archive<AlignedBytes>>(ValueKey {
+                account_id,
+                collection: Collection::Email.into(),
+                document_id,
+                class: ValueClass::Property(Property::BodyStructure.into()),
+            })
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(());
+        };
+        let metadata = metadata_
+            .deserialize::<MessageMetadata>()
+            .caused_by(trc::location!())?;
+        let raw_message = self
+            .blob_store()
+            .get_blob(metadata.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap_or_default();
+
+        // Keep the addressing and threading headers, drop everything else
+        let mut stub = Vec::with_capacity(512);
+        if let Some(message) = MessageParser::new().parse_headers(&raw_message) {
+            for header in message.root_part().headers.iter() {
+                if STUB_HEADERS.contains(&header.name)
+                    && let Some(value) =
+                        raw_message.get(header.offset_field as usize..header.offset_end as usize)
+                {
+                    stub.extend_from_slice(value);
+                }
+            }
+        }
+        stub.extend_from_slice(
+            concat!(
+                "Subject: [Redacted]\r\n",
+                "MIME-Version: 1.0\r\n",
+                "Content-Type: text/plain; charset=utf-8\r\n",
+                "Content-Transfer-Encoding: 8bit\r\n\r\n",
+                "The contents of this message were removed by an administrator.\r\n"
+            )
+            .as_bytes(),
+        );
+        if let Some(reason) = reason {
+            stub.extend_from_slice(b"\r\nReason: ");
+            stub.extend_from_slice(reason.replace(['\r', '\n'], " ").as_bytes());
+            stub.extend_from_slice(b"\r\n");
+        }
+
+        self.email_ingest(IngestEmail {
+            raw_message: &stub,
+            message: MessageParser::new().parse(&stub),
+            access_token,
+            mailbox_ids: data.mailboxes.iter().map(|m| m.mailbox_id).collect(),
+            keywords: data.keywords,
+            received_at: metadata.received_at.into(),
+            source: IngestSource::Restore,
+            spam_classify: false,
+            spam_train: false,
+            session_id,
+        })
+        .await
+        .map(|_| ())
+    }
+}
@@ -13,7 +13,7 @@ use directory::Permission;
 use hyper::Method;
 use serde_json::json;
 use std::future::Future;
-use utils::url_params::UrlParams;
+use utils::{config::Config, url_params::UrlParams};
 
 use http_proto::*;
 
@@ -31,6 +31,8 @@ pub trait ManageReload: Sync + Send {
         path: Vec<&str>,
         access_token: &AccessToken,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn reload_settings(&self, dry_run: bool) -> impl Future<Output = trc::Result<Config>> + Send;
 }
 
 impl ManageReload for Server {
@@ -71,53 +73,57 @@ impl ManageReload for Server {
                 }))
                 .into_http_response())
             }
-            (_, &Method::GET) => {
-                let result = self.reload().await?;
-                if !UrlParams::new(req.uri().query()).has_key("dry-run") {
-                    if let Some(core) = result.new_core {
-                        // Update core
-                        self.inner.shared_core.store(core.into());
-
-                        self.cluster_broadcast(BroadcastEvent::ReloadSettings).await;
-                    }
-
-                    if let Some(tracers) = result.tracers {
-                        // Update tracers
-
-                        // SPDX-SnippetBegin
-                        // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
-                        // SPDX-License-Identifier: LicenseRef-SEL
-                        #[cfg(feature = "enterprise")]
-                        tracers.update(self.inner.shared_core.load().is_enterprise_edition());
-                        // SPDX-SnippetEnd
-                        #[cfg(not(feature = "enterprise"))]
-                        tracers.update(false);
-                    }
-
-                    // Reload settings
-                    self.inner
-                        .ipc
-                        .housekeeper_tx
-                        .send(HousekeeperEvent::ReloadSettings)
-                        .await
-                        .map_err(|err| {
-                            trc::EventType::Server(trc::ServerEvent::ThreadError)
-                                .reason(err)
-                                .details(concat!(
-                                    "Failed to send settings reload ",
-                                    "event to housekeeper"
-                                ))
-                                .caused_by(trc::location!())
-                        })?;
-                }
+            (_, &Method::GET) => Ok(JsonResponse::new(json!({
+                "data": self
+                    .reload_settings(UrlParams::new(req.uri().query()).has_key("dry-run"))
+                    .await?,
+            }))
+            .into_http_response()),
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
 
-                Ok(JsonResponse::new(json!({
-                    "data": result.config,
-                }))
-                .into_http_response())
+    async fn reload_settings(&self, dry_run: bool) -> trc::Result<Config> {
+        let result = self.reload().await?;
+        if !dry_run {
+            if let Some(core) = result.new_core {
+                // Update core
+                self.inner.shared_core.store(core.into());
+
+                self.cluster_broadcast(BroadcastEvent::ReloadSettings).await;
             }
-            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+
+            if let Some(tracers) = result.tracers {
+                // Update tracers
+
+                // SPDX-SnippetBegin
+                // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+                // SPDX-License-Identifier: LicenseRef-SEL
+                #[cfg(feature = "enterprise")]
+                tracers.update(self.inner.shared_core.load().is_enterprise_edition());
+                // SPDX-SnippetEnd
+                #[cfg(not(feature = "enterprise"))]
+                tracers.update(false);
+            }
+
+            // Reload settings
+            self.inner
+                .ipc
+                .housekeeper_tx
+                .send(HousekeeperEvent::ReloadSettings)
+                .await
+                .map_err(|err| {
+                    trc::EventType::Server(trc::ServerEvent::ThreadError)
+                        .reason(err)
+                        .details(concat!(
+                            "Failed to send settings reload ",
+                            "event to housekeeper"
+                        ))
+                        .caused_by(trc::location!())
+                })?;
         }
+
+        Ok(result.config)
     }
 
     async fn handle_manage_update(
@@ -9,12 +9,12 @@ use directory::{Permission, Type, backend::internal::manage::ManageDirectory};
 use http_proto::{request::decode_path_element, *};
 use hyper::Method;
 use mail_auth::report::{
-    Feedback,
+    ActionDisposition, DmarcResult, Feedback,
     tlsrpt::{FailureDetails, Policy, TlsReport},
 };
 use serde_json::json;
 use smtp::reporting::analysis::IncomingReport;
-use std::future::Future;
+use std::{collections::BTreeMap, future::Future, ops::RangeInclusive};
 use store::{
     Deserialize, IterateParams, Key, U64_LEN, ValueKey,
     write::{
@@ -99,6 +99,17 @@ impl ManageReports for Server {
                 }))
                 .into_http_response())
             }
+            ("dmarc", Some(summary), &Method::GET) if summary == "summary" => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::IncomingReportList)?;
+
+                let params = UrlParams::new(req.uri().query());
+
+                Ok(JsonResponse::new(json!({
+                        "data": summarize_dmarc_reports(self, &params, &tenant_domains).await?,
+                }))
+                .into_http_response())
+            }
             (class @ ("dmarc" | "tls" | "arf"), Some(report_id), &Method::GET) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::IncomingReportGet)?;
@@ -435,6 +446,129 @@ async fn fetch_incoming_reports(
         .map(|_| results)
 }
 
+#[derive(Debug, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AlignmentSummary {
+    timestamp: u64,
+    total: u64,
+    dkim_aligned: u64,
+    spf_aligned: u64,
+    dmarc_pass: u64,
+    quarantined: u64,
+    rejected: u64,
+}
+
+// Aggregates the records of the received DMARC reports into time buckets
+// based on the start of each report's date range
+async fn summarize_dmarc_reports(
+    server: &Server,
+    params: &UrlParams<'_>,
+    tenant_domains: &Option<Vec<String>>,
+) -> trc::Result<Vec<AlignmentSummary>> {
+    let domain = params.get("domain").map(|d| d.to_lowercase());
+    let range_start = params.parse::<u64>("range-start").unwrap_or_default();
+    let range_end = params.parse::<u64>("range-end").unwrap_or(u64::MAX);
+    let bucket = match params.get("bucket").unwrap_or("day") {
+        "hour" => 3600,
+        "week" => 7 * 86400,
+        _ => 86400,
+    };
+
+    // Report keys are ordered by expiration, so the whole DMARC key space is
+    // scanned and the range is matched against each report's date range
+    let mut summary: BTreeMap<u64, AlignmentSummary> = BTreeMap::new();
+    let mut last_id = 0;
+    server
+        .core
+        .storage
+        .data
+        .iterate(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Report(ReportClass::Dmarc { id: 0, expires: 0 })),
+                ValueKey::from(ValueClass::Report(ReportClass::Dmarc {
+                    id: u64::MAX,
+                    expires: u64::MAX,
+                })),
+            )
+            .ascending(),
+            |key, value| {
+                // Skip chunked records
+                let id = key.deserialize_be_u64(U64_LEN + 1)?;
+                if id == last_id {
+                    return Ok(true);
+                }
+                last_id = id;
+
+                let report = <Archive<AlignedBytes> as Deserialize>::deserialize(value)?
+                    .deserialize::<IncomingReport<mail_auth::report::Report>>()
+                    .caused_by(trc::location!())?;
+                if tenant_domains
+                    .as_ref()
+                    .is_some_and(|domains| !report.has_domain(domains))
+                    || domain
+                        .as_ref()
+                        .is_some_and(|domain| !report.report.domain().eq_ignore_ascii_case(domain))
+                {
+                    return Ok(true);
+                }
+
+                add_to_summary(
+                    &mut summary,
+                    &report.report,
+                    range_start..=range_end,
+                    bucket,
+                );
+
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())?;
+
+    Ok(summary.into_values().collect())
+}
+
+fn add_to_summary(
+    summary: &mut BTreeMap<u64, AlignmentSummary>,
+    report: &mail_auth::report::Report,
+    range: RangeInclusive<u64>,
+    bucket: u64,
+) {
+    let date_range_begin = report.date_range_begin();
+    if !range.contains(&date_range_begin) {
+        return;
+    }
+
+    let timestamp = date_range_begin / bucket * bucket;
+    let entry = summary
+        .entry(timestamp)
+        .or_insert_with(|| AlignmentSummary {
+            timestamp,
+            ..Default::default()
+        });
+    for record in report.records() {
+        let count = record.count() as u64;
+        let dkim_aligned = record.dmarc_dkim_result() == DmarcResult::Pass;
+        let spf_aligned = record.dmarc_spf_result() == DmarcResult::Pass;
+
+        entry.total += count;
+        if dkim_aligned {
+            entry.dkim_aligned += count;
+        }
+        if spf_aligned {
+            entry.spf_aligned += count;
+        }
+        if dkim_aligned || spf_aligned {
+            entry.dmarc_pass += count;
+        }
+        match record.action_disposition() {
+            ActionDisposition::Quarantine => entry.quarantined += count,
+            ActionDisposition::Reject => entry.rejected += count,
+            _ => {}
+        }
+    }
+}
+
 fn parse_incoming_report_id(class: &str, id: &str) -> Option<ReportClass> {
     let mut parts = id.split('_');
     let id = parts.next()?.parse().ok()?;
@@ -594,3 +728,53 @@ impl<T: Contains> Contains for IncomingReport<T> {
             || self.report.contains(text)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AlignmentSummary, add_to_summary};
+    use mail_auth::report::{ActionDisposition, DmarcResult, Record, Report};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn summary_date_range() {
+        let mut summary: BTreeMap<u64, AlignmentSummary> = BTreeMap::new();
+        for (date_range_begin, count, disposition) in [
+            (86400 - 1, 10, ActionDisposition::None),
+            (86400, 5, ActionDisposition::None),
+            (86400 + 3600, 2, ActionDisposition::Reject),
+            (2 * 86400 + 60, 3, ActionDisposition::Quarantine),
+            (3 * 86400, 7, ActionDisposition::None),
+        ] {
+            add_to_summary(
+                &mut summary,
+                &Report::new()
+                    .with_date_range_begin(date_range_begin)
+                    .with_record(
+                        Record::new()
+                            .with_count(count)
+                            .with_action_disposition(disposition)
+                            .with_dmarc_dkim_result(DmarcResult::Pass)
+                            .with_dmarc_spf_result(DmarcResult::Fail),
+                    ),
+                86400..=(3 * 86400 - 1),
+                86400,
+            );
+        }
+
+        assert_eq!(
+            summary
+                .into_values()
+                .map(|entry| (
+                    entry.timestamp,
+                    entry.total,
+                    entry.dkim_aligned,
+                    entry.spf_aligned,
+                    entry.dmarc_pass,
+                    entry.quarantined,
+                    entry.rejected
+                ))
+                .collect::<Vec<_>>(),
+            vec![(86400, 7, 7, 0, 7, 0, 2), (2 * 86400, 3, 3, 0, 3, 3, 0)]
+        );
+    }
+}
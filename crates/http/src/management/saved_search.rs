@@ -0,0 +1,173 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::backend::internal::manage::{self, not_found};
+use email::mailbox::search::{SavedSearch, SavedSearchManager, parse_saved_filter};
+use http_proto::*;
+use hyper::Method;
+use jmap_proto::types::id::Id;
+use serde::Deserialize;
+use serde_json::json;
+use std::future::Future;
+
+#[derive(Debug, Deserialize)]
+struct SavedSearchRequest {
+    name: String,
+    filter: serde_json::Value,
+}
+
+pub trait SavedSearchManagement: Sync + Send {
+    fn handle_saved_searches(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl SavedSearchManagement for Server {
+    async fn handle_saved_searches(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let account_id = access_token.primary_id();
+        let mut searches = self.get_saved_searches(account_id).await?;
+        let search_id = path
+            .get(2)
+            .map(|id| {
+                Id::from_bytes(id.as_bytes())
+                    .map(|id| id.document_id())
+                    .ok_or_else(|| not_found(id.to_string()))
+            })
+            .transpose()?;
+
+        match (search_id, req.method()) {
+            (None, &Method::GET) => Ok(JsonResponse::new(json!({
+                "data": searches.items.iter().map(|search| json!({
+                    "id": Id::from(search.id).to_string(),
+                    "name": search.name,
+                    "filter": serde_json::from_str::<serde_json::Value>(&search.filter)
+                        .unwrap_or_default(),
+                    "mailbox": format!(
+                        "{}/{}",
+                        self.core.jmap.saved_search_folder,
+                        search.name
+                    ),
+                })).collect::<Vec<_>>(),
+            }))
+            .into_http_response()),
+            (None, &Method::POST) => {
+                let request = parse_request(body.as_deref())?;
+                if searches.items.len() >= self.core.jmap.saved_search_max {
+                    return Err(manage::error(
+                        "Saved search limit reached",
+                        Some(format!(
+                            "Up to {} saved searches are allowed",
+                            self.core.jmap.saved_search_max
+                        )),
+                    ));
+                } else if searches
+                    .items
+                    .iter()
+                    .any(|search| search.name == request.name)
+                {
+                    return Err(manage::error(
+                        "A saved search with this name already exists",
+                        None::<u32>,
+                    ));
+                }
+
+                let id = searches.next_id;
+                searches.next_id += 1;
+                searches
+                    .items
+                    .push(SavedSearch::new(id, request.name, request.filter));
+                self.set_saved_searches(account_id, searches).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": Id::from(id).to_string(),
+                }))
+                .into_http_response())
+            }
+            (Some(search_id), &Method::PUT) => {
+                let request = parse_request(body.as_deref())?;
+                if searches
+                    .items
+                    .iter()
+                    .any(|search| search.id != search_id && search.name == request.name)
+                {
+                    return Err(manage::error(
+                        "A saved search with this name already exists",
+                        None::<u32>,
+                    ));
+                }
+                let search = searches
+                    .items
+                    .iter_mut()
+                    .find(|search| search.id == search_id)
+                    .ok_or_else(|| not_found(Id::from(search_id).to_string()))?;
+                search.set_filter(request.filter);
+                search.name = request.name;
+                self.set_saved_searches(account_id, searches).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            (Some(search_id), &Method::DELETE) => {
+                let num_searches = searches.items.len();
+                searches.items.retain(|search| search.id != search_id);
+                if searches.items.len() == num_searches {
+                    return Err(not_found(Id::from(search_id).to_string()));
+                }
+                self.set_saved_searches(account_id, searches).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+struct ValidatedRequest {
+    name: String,
+    filter: String,
+}
+
+fn parse_request(body: Option<&[u8]>) -> trc::Result<ValidatedRequest> {
+    let request = serde_json::from_slice::<SavedSearchRequest>(body.unwrap_or_default())
+        .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
+    let name = request.name.trim();
+    if name.is_empty() || name.len() > 255 || name.contains('/') {
+        return Err(manage::error(
+            "Invalid saved search name",
+            Some("Names must be between 1 and 255 characters and cannot contain '/'"),
+        ));
+    }
+
+    let filter = request.filter.to_string();
+    parse_saved_filter(&filter).map_err(|err| {
+        manage::error(
+            "Invalid saved search filter",
+            err.value_as_str(trc::Key::Details)
+                .map(|details| details.to_string()),
+        )
+    })?;
+
+    Ok(ValidatedRequest {
+        name: name.to_string(),
+        filter,
+    })
+}
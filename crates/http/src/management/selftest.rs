@@ -0,0 +1,579 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    borrow::Cow,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common::{
+    KV_SELF_TEST, Server,
+    auth::AccessToken,
+    listener::{ServerInstance, stream::NullIo},
+};
+use directory::{
+    Permission,
+    backend::internal::manage::{self, ManageDirectory},
+};
+use email::message::metadata::MessageMetadata;
+use http_proto::HttpSessionData;
+use jmap_proto::types::{collection::Collection, property::Property};
+use mail_auth::{AuthenticatedMessage, common::verify::VerifySignature};
+use mail_builder::{
+    MessageBuilder,
+    headers::{
+        HeaderType,
+        address::{Address, EmailAddress},
+    },
+    mime::make_boundary,
+};
+use mail_parser::MessageParser;
+use serde::{Deserialize, Serialize};
+use smtp::{
+    core::{Session, SessionData, State},
+    inbound::AuthResult,
+    queue::{self, spool::SmtpSpool},
+};
+use smtp_proto::{MailFrom, RcptTo};
+use store::{
+    IndexKey, IndexKeyPrefix, IterateParams, U32_LEN,
+    dispatch::lookup::KeyValue,
+    rand::{Rng, distr::Alphanumeric, rng},
+    write::key::DeserializeBigEndian,
+};
+use trc::AddContext;
+use utils::BlobHash;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+const SELF_TEST_EXPIRY: u64 = 3600;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestRequest {
+    #[serde(default)]
+    pub account: Option<String>,
+    #[serde(default)]
+    pub external: Option<String>,
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestReport {
+    pub message_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue_id: Option<u64>,
+    pub hops: Vec<SelfTestHop>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SelfTestHop {
+    pub hop: &'static str,
+    pub status: HopStatus,
+    pub elapsed: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub auth_results: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HopStatus {
+    Ok,
+    Failed,
+    Timeout,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "status")]
+pub enum SelfTestStatus {
+    Running,
+    Completed { report: SelfTestReport },
+    Failed { reason: String },
+}
+
+// Validates the request and runs the self-test in the background, the returned
+// task id is used to poll for the report
+pub async fn start_delivery_self_test(
+    server: &Server,
+    session: &HttpSessionData,
+    access_token: &AccessToken,
+    request: SelfTestRequest,
+) -> trc::Result<String> {
+    let account_id = if let Some(name) = &request.account {
+        server
+            .store()
+            .get_principal_info(name)
+            .await
+            .caused_by(trc::location!())?
+            .filter(|p| p.has_tenant_access(access_token.tenant.map(|t| t.id)))
+            .ok_or_else(|| trc::ManageEvent::NotFound.into_err().details(name.clone()))?
+            .id
+    } else {
+        access_token.primary_id()
+    };
+
+    // Sending on behalf of other accounts requires impersonation rights
+    if account_id != access_token.primary_id() {
+        access_token.assert_has_permission(Permission::Impersonate)?;
+    }
+
+    let account_token = server
+        .get_access_token(account_id)
+        .await
+        .caused_by(trc::location!())?;
+    let address = account_token.emails.first().cloned().ok_or_else(|| {
+        manage::error(
+            "Account has no email address",
+            account_token.name.to_string().into(),
+        )
+    })?;
+
+    // SPDX-SnippetBegin
+    // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+    // SPDX-License-Identifier: LicenseRef-SEL
+
+    // Limit to tenant domains
+    #[cfg(feature = "enterprise")]
+    if server.core.is_enterprise_edition()
+        && let Some(tenant) = access_token.tenant
+    {
+        let tenant_domains = server
+            .core
+            .storage
+            .data
+            .list_principals(
+                None,
+                tenant.id.into(),
+                &[directory::Type::Domain],
+                false,
+                0,
+                0,
+            )
+            .await
+            .caused_by(trc::location!())?;
+        let domain = address.rsplit_once('@').map(|(_, domain)| domain);
+        if !tenant_domains
+            .items
+            .iter()
+            .any(|principal| Some(principal.name.as_str()) == domain)
+        {
+            return Err(manage::error(
+                "Address does not belong to a tenant domain",
+                address.into(),
+            ));
+        }
+    }
+
+    // SPDX-SnippetEnd
+
+    let task_id = rng()
+        .sample_iter(Alphanumeric)
+        .take(20)
+        .map(char::from)
+        .collect::<String>();
+    let key = format!("{}:{task_id}", access_token.primary_id());
+    set_self_test_status(server, &key, SelfTestStatus::Running).await?;
+
+    let server = server.clone();
+    let instance = session.instance.clone();
+    let session_id = session.session_id;
+    tokio::spawn(async move {
+        let status = match delivery_self_test(
+            &server,
+            instance,
+            session_id,
+            account_token,
+            address,
+            request,
+        )
+        .await
+        {
+            Ok(report) => SelfTestStatus::Completed { report },
+            Err(err) => {
+                let reason = err.to_string();
+                trc::error!(err.span_id(session_id).details("Delivery self-test failed"));
+                SelfTestStatus::Failed { reason }
+            }
+        };
+        if let Err(err) = set_self_test_status(&server, &key, status).await {
+            trc::error!(err.span_id(session_id));
+        }
+    });
+
+    Ok(task_id)
+}
+
+pub async fn get_delivery_self_test(
+    server: &Server,
+    access_token: &AccessToken,
+    task_id: &str,
+) -> trc::Result<serde_json::Value> {
+    server
+        .in_memory_store()
+        .key_get::<String>(KeyValue::<()>::build_key(
+            KV_SELF_TEST,
+            format!("{}:{task_id}", access_token.primary_id()),
+        ))
+        .await
+        .caused_by(trc::location!())?
+        .and_then(|status| serde_json::from_str(&status).ok())
+        .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())
+}
+
+async fn set_self_test_status(
+    server: &Server,
+    key: &str,
+    status: SelfTestStatus,
+) -> trc::Result<()> {
+    server
+        .in_memory_store()
+        .key_set(
+            KeyValue::with_prefix(
+                KV_SELF_TEST,
+                key,
+                serde_json::to_string(&status)
+                    .unwrap_or_default()
+                    .into_bytes(),
+            )
+            .expires(SELF_TEST_EXPIRY),
+        )
+        .await
+        .caused_by(trc::location!())
+}
+
+// Sends a probe message from an account to itself (and optionally to an external
+// address that replies to it) and follows it through the submission, queue and delivery
+async fn delivery_self_test(
+    server: &Server,
+    instance: Arc<ServerInstance>,
+    session_id: u64,
+    account_token: Arc<AccessToken>,
+    address: String,
+    request: SelfTestRequest,
+) -> trc::Result<SelfTestReport> {
+    let account_id = account_token.primary_id();
+
+    // Build probe message
+    let message_id = format!(
+        "self-test.{}@{}",
+        make_boundary("."),
+        server.core.network.server_name
+    );
+    let mut rcpt_to = vec![address.clone()];
+    rcpt_to.extend(request.external.iter().cloned());
+    let message = MessageBuilder::new()
+        .from(address.as_str())
+        .header(
+            "To",
+            HeaderType::Address(Address::List(
+                rcpt_to
+                    .iter()
+                    .map(|rcpt| {
+                        Address::Address(EmailAddress {
+                            name: None,
+                            email: rcpt.into(),
+                        })
+                    })
+                    .collect(),
+            )),
+        )
+        .message_id(message_id.as_str())
+        .subject("Delivery self-test")
+        .text_body(format!(
+            "This message was sent by {} to verify mail delivery.\r\n",
+            server.core.network.server_name
+        ))
+        .write_to_vec()
+        .unwrap_or_default();
+
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(request.timeout.unwrap_or(60).clamp(1, 600));
+    let mut report = SelfTestReport {
+        message_id: message_id.clone(),
+        queue_id: None,
+        hops: Vec::with_capacity(4),
+    };
+
+    // Submission, the session is spawned to avoid overflowing the stack
+    let mut smtp_session = Session::<NullIo>::local(
+        server.clone(),
+        instance,
+        SessionData::local(account_token, None, vec![], vec![], session_id),
+    );
+    let submission = tokio::spawn(async move {
+        let _ = smtp_session
+            .handle_mail_from(MailFrom {
+                address: Cow::Owned(address),
+                ..Default::default()
+            })
+            .await;
+        if let Some(error) = smtp_session.has_failed() {
+            return Err(format!("MAIL FROM rejected: {error}"));
+        }
+        for rcpt in rcpt_to {
+            let _ = smtp_session
+                .handle_rcpt_to(RcptTo {
+                    address: Cow::Owned(rcpt.clone()),
+                    ..Default::default()
+                })
+                .await;
+            if let Some(error) = smtp_session.has_failed() {
+                return Err(format!("RCPT TO <{rcpt}> rejected: {error}"));
+            }
+        }
+        smtp_session.data.message = message;
+        let response = smtp_session.queue_message().await;
+        if let State::Accepted(queue_id) = smtp_session.state {
+            Ok(queue_id)
+        } else {
+            Err(format!(
+                "DATA rejected: {}",
+                String::from_utf8_lossy(&response).trim()
+            ))
+        }
+    })
+    .await
+    .map_err(|err| {
+        trc::EventType::Server(trc::ServerEvent::ThreadError)
+            .reason(err)
+            .caused_by(trc::location!())
+            .details("Join Error")
+    })?;
+    let queue_id = match submission {
+        Ok(queue_id) => queue_id,
+        Err(details) => {
+            report.hops.push(SelfTestHop {
+                hop: "submission",
+                status: HopStatus::Failed,
+                elapsed: elapsed_ms(started),
+                details: details.into(),
+                auth_results: vec![],
+            });
+            return Ok(report);
+        }
+    };
+    report.queue_id = Some(queue_id);
+    report.hops.push(SelfTestHop {
+        hop: "submission",
+        status: HopStatus::Ok,
+        elapsed: elapsed_ms(started),
+        details: None,
+        auth_results: queued_dkim_results(server, queue_id).await?,
+    });
+
+    // Follow the message until it leaves the queue and lands in the mailbox
+    let mut queue_hop = None;
+    let mut local_hop = None;
+    let mut external_hop = None;
+    loop {
+        if queue_hop.is_none() && server.read_message_archive(queue_id).await?.is_none() {
+            queue_hop = Some(elapsed_ms(started));
+        }
+
+        if local_hop.is_none() || (request.external.is_some() && external_hop.is_none()) {
+            for (document_id, is_probe) in
+                find_probe_copies(server, account_id, &message_id).await?
+            {
+                let hop = if is_probe {
+                    &mut local_hop
+                } else {
+                    &mut external_hop
+                };
+                if hop.is_none() {
+                    *hop = Some((
+                        elapsed_ms(started),
+                        delivered_auth_results(server, account_id, document_id).await?,
+                    ));
+                }
+            }
+        }
+
+        if (queue_hop.is_some()
+            && local_hop.is_some()
+            && (request.external.is_none() || external_hop.is_some()))
+            || Instant::now() >= deadline
+        {
+            break;
+        }
+
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+
+    let timeout_ms = deadline.duration_since(started).as_millis() as u64;
+    report.hops.push(SelfTestHop {
+        hop: "queue",
+        status: if queue_hop.is_some() {
+            HopStatus::Ok
+        } else {
+            HopStatus::Timeout
+        },
+        elapsed: queue_hop.unwrap_or(timeout_ms),
+        details: None,
+        auth_results: vec![],
+    });
+    let mut hops = vec![("local-delivery", local_hop)];
+    if request.external.is_some() {
+        hops.push(("external-loop", external_hop));
+    }
+    for (hop, result) in hops {
+        report.hops.push(match result {
+            Some((elapsed, auth_results)) => SelfTestHop {
+                hop,
+                status: HopStatus::Ok,
+                elapsed,
+                details: None,
+                auth_results,
+            },
+            None => SelfTestHop {
+                hop,
+                status: HopStatus::Timeout,
+                elapsed: timeout_ms,
+                details: None,
+                auth_results: vec![],
+            },
+        });
+    }
+
+    Ok(report)
+}
+
+// Verifies the DKIM signatures added to the queued copy of the probe
+async fn queued_dkim_results(server: &Server, queue_id: u64) -> trc::Result<Vec<String>> {
+    let Some(message_) = server.read_message_archive(queue_id).await? else {
+        return Ok(vec![]);
+    };
+    let blob_hash = BlobHash::from(&message_.unarchive::<queue::Message>()?.blob_hash);
+    let Some(raw_message) = server
+        .get_queued_blob(blob_hash.as_slice(), 0..usize::MAX)
+        .await
+        .caused_by(trc::location!())?
+    else {
+        return Ok(vec![]);
+    };
+    let Some(auth_message) = AuthenticatedMessage::parse(&raw_message) else {
+        return Ok(vec![]);
+    };
+
+    let dkim_output = server
+        .core
+        .smtp
+        .resolvers
+        .dns
+        .verify_dkim(server.inner.cache.build_auth_parameters(&auth_message))
+        .await;
+    if dkim_output.is_empty() {
+        Ok(vec!["dkim=none".to_string()])
+    } else {
+        Ok(dkim_output
+            .iter()
+            .map(|output| {
+                format!(
+                    "dkim={} header.d={}",
+                    output.result().as_str(),
+                    output.signature().map(|s| s.domain()).unwrap_or_default()
+                )
+            })
+            .collect())
+    }
+}
+
+// Returns the messages that either are the probe or reference it, such as replies
+async fn find_probe_copies(
+    server: &Server,
+    account_id: u32,
+    message_id: &str,
+) -> trc::Result<Vec<(u32, bool)>> {
+    let message_id = message_id.as_bytes();
+    let mut results = Vec::new();
+
+    server
+        .store()
+        .iterate(
+            IterateParams::new(
+                IndexKey {
+                    account_id,
+                    collection: Collection::Email.into(),
+                    document_id: 0,
+                    field: Property::References.into(),
+                    key: message_id.to_vec(),
+                },
+                IndexKey {
+                    account_id,
+                    collection: Collection::Email.into(),
+                    document_id: u32::MAX,
+                    field: Property::References.into(),
+                    key: [message_id, &[0]].concat(),
+                },
+            )
+            .no_values()
+            .ascending(),
+            |key, _| {
+                let id_pos = key.len() - U32_LEN;
+                let value = key
+                    .get(IndexKeyPrefix::len()..id_pos)
+                    .ok_or_else(|| trc::Error::corrupted_key(key, None, trc::location!()))?;
+
+                // The message's own Message-ID is indexed with a trailing zero
+                if let Some(value) = value.strip_suffix(&[0]) {
+                    if value == message_id {
+                        results.push((key.deserialize_be_u32(id_pos)?, true));
+                    }
+                } else if value == message_id {
+                    results.push((key.deserialize_be_u32(id_pos)?, false));
+                }
+
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())?;
+
+    Ok(results)
+}
+
+async fn delivered_auth_results(
+    server: &Server,
+    account_id: u32,
+    document_id: u32,
+) -> trc::Result<Vec<String>> {
+    let Some(metadata_) = server
+        .get_archive_by_property(
+            account_id,
+            Collection::Email,
+            document_id,
+            &Property::BodyStructure,
+        )
+        .await?
+    else {
+        return Ok(vec![]);
+    };
+    let metadata = metadata_
+        .unarchive::<MessageMetadata>()
+        .caused_by(trc::location!())?;
+
+    Ok(MessageParser::new()
+        .parse_headers(metadata.raw_headers.as_slice())
+        .map(|message| {
+            message
+                .headers()
+                .iter()
+                .filter(|header| header.name().eq_ignore_ascii_case("Authentication-Results"))
+                .filter_map(|header| header.value().as_text())
+                .map(|value| value.split_whitespace().collect::<Vec<_>>().join(" "))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+fn elapsed_ms(started: Instant) -> u64 {
+    started.elapsed().as_millis() as u64
+}
@@ -0,0 +1,203 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken, listener::sessions::ActiveSession};
+use directory::Permission;
+use http_proto::*;
+use hyper::Method;
+use serde::Serialize;
+use serde_json::json;
+use std::{future::Future, net::IpAddr, sync::Arc};
+use store::write::now;
+use utils::url_params::UrlParams;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Session {
+    id: String,
+    protocol: &'static str,
+    listener: String,
+    remote_ip: IpAddr,
+    remote_port: u16,
+    local_port: u16,
+    account: Option<String>,
+    account_id: Option<u32>,
+    created_at: u64,
+    age: u64,
+    idle: u64,
+    bytes_received: u64,
+    bytes_sent: u64,
+}
+
+pub trait SessionManagement: Sync + Send {
+    fn handle_manage_sessions(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn filter_sessions(
+        &self,
+        params: &UrlParams<'_>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<Vec<(Arc<ActiveSession>, Option<String>)>>> + Send;
+}
+
+impl SessionManagement for Server {
+    async fn handle_manage_sessions(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        let params = UrlParams::new(req.uri().query());
+
+        match (path.get(1).copied(), req.method()) {
+            (None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SessionsList)?;
+
+                let page: usize = params.parse("page").unwrap_or(0);
+                let limit: usize = params.parse("limit").unwrap_or(0);
+
+                let mut sessions = self.filter_sessions(&params, access_token).await?;
+                sessions.sort_unstable_by_key(|(session, _)| session.created_at);
+                let total = sessions.len();
+                let now = now();
+                let items = sessions
+                    .into_iter()
+                    .skip(page.saturating_sub(1) * limit)
+                    .take(if limit > 0 { limit } else { usize::MAX })
+                    .map(|(session, account)| Session {
+                        id: session.session_id.to_string(),
+                        protocol: session.protocol.as_str(),
+                        listener: session.listener_id.clone(),
+                        remote_ip: session.remote_ip,
+                        remote_port: session.remote_port,
+                        local_port: session.local_port,
+                        account,
+                        account_id: session.account_id(),
+                        created_at: session.created_at,
+                        age: now.saturating_sub(session.created_at),
+                        idle: now.saturating_sub(session.last_activity()),
+                        bytes_received: session.bytes_received(),
+                        bytes_sent: session.bytes_sent(),
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "items": items,
+                        "total": total,
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some(session_id), &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SessionsTerminate)?;
+
+                let session = session_id
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|session_id| self.inner.data.sessions.get(session_id))
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                // Tenants can only terminate sessions of their own accounts
+                if let Some(tenant) = access_token.tenant {
+                    let account_id = session
+                        .account_id()
+                        .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+                    if self
+                        .get_access_token(account_id)
+                        .await?
+                        .tenant
+                        .is_none_or(|account_tenant| account_tenant.id != tenant.id)
+                    {
+                        return Err(trc::ResourceEvent::NotFound.into_err());
+                    }
+                }
+
+                session.disconnect();
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            (None, &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SessionsTerminate)?;
+
+                // Refuse to terminate every session at once
+                if !["account", "ip", "protocol", "idle"]
+                    .iter()
+                    .any(|key| params.has_key(key))
+                {
+                    return Err(trc::ResourceEvent::BadParameters
+                        .into_err()
+                        .details("At least one session filter is required"));
+                }
+
+                let sessions = self.filter_sessions(&params, access_token).await?;
+                for (session, _) in &sessions {
+                    session.disconnect();
+                }
+
+                Ok(JsonResponse::new(json!({
+                    "data": sessions.len(),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+
+    async fn filter_sessions(
+        &self,
+        params: &UrlParams<'_>,
+        access_token: &AccessToken,
+    ) -> trc::Result<Vec<(Arc<ActiveSession>, Option<String>)>> {
+        let protocol = params.get("protocol");
+        let account = params.get("account");
+        let remote_ip = params.parse::<IpAddr>("ip");
+        let min_idle = params.parse::<u64>("idle");
+        let tenant_id = access_token.tenant.map(|tenant| tenant.id);
+        let now = now();
+
+        let mut results = Vec::new();
+        for session in self.inner.data.sessions.list() {
+            if protocol.is_some_and(|protocol| protocol != session.protocol.as_str())
+                || remote_ip.is_some_and(|remote_ip| remote_ip != session.remote_ip)
+                || min_idle.is_some_and(|idle| now.saturating_sub(session.last_activity()) < idle)
+            {
+                continue;
+            }
+
+            // Resolve the authenticated principal, if any
+            let account_token = match session.account_id() {
+                Some(account_id) => self.get_access_token(account_id).await.ok(),
+                None => None,
+            };
+            if tenant_id.is_some()
+                && account_token
+                    .as_ref()
+                    .is_none_or(|token| token.tenant.map(|tenant| tenant.id) != tenant_id)
+            {
+                continue;
+            }
+            let account_name = account_token.map(|token| token.name.clone());
+            if account.is_some_and(|account| account_name.as_deref() != Some(account)) {
+                continue;
+            }
+
+            results.push((session, account_name));
+        }
+
+        Ok(results)
+    }
+}
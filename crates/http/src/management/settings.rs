@@ -12,7 +12,9 @@ use store::ahash::AHashMap;
 use utils::{config::ConfigKey, map::vec_map::VecMap, url_params::UrlParams};
 
 use http_proto::{request::decode_path_element, *};
-use std::future::Future;
+
+use super::reload::ManageReload;
+use std::{collections::BTreeMap, future::Future};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type")]
@@ -33,6 +35,28 @@ pub enum UpdateSettings {
     },
 }
 
+#[derive(Debug, serde::Deserialize)]
+pub struct DiffSettings {
+    pub from: BTreeMap<String, String>,
+    #[serde(default)]
+    pub to: Option<BTreeMap<String, String>>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq, serde::Serialize)]
+pub struct SettingsDiff {
+    pub added: BTreeMap<String, String>,
+    pub removed: BTreeMap<String, String>,
+    pub changed: BTreeMap<String, SettingChange>,
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Serialize)]
+pub struct SettingChange {
+    pub from: String,
+    pub to: String,
+}
+
+const MASKED_VALUE: &str = "********";
+
 pub trait ManageSettings: Sync + Send {
     fn handle_manage_settings(
         &self,
@@ -255,13 +279,116 @@ impl ManageSettings for Server {
                 }))
                 .into_http_response())
             }
+            (Some("history"), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsList)?;
+
+                let params = UrlParams::new(req.uri().query());
+                let limit: usize = params.parse("limit").unwrap_or(0);
+                let offset = params.parse::<usize>("page").unwrap_or(0).saturating_sub(1) * limit;
+
+                let versions = self.core.storage.config.versions().await?;
+                let total = versions.len();
+                let items = versions
+                    .into_iter()
+                    .skip(offset)
+                    .take(if limit == 0 { total } else { limit })
+                    .map(|mut version| {
+                        for (key, value) in version.previous.iter_mut() {
+                            if is_secret_key(key) && value.is_some() {
+                                *value = Some(MASKED_VALUE.to_string());
+                            }
+                        }
+                        version
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "total": total,
+                        "items": items,
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some("rollback"), &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsUpdate)?;
+                access_token.assert_has_permission(Permission::SettingsReload)?;
+
+                let id = path
+                    .get(2)
+                    .and_then(|id| id.parse::<u64>().ok())
+                    .ok_or_else(|| trc::ResourceEvent::BadParameters.into_err())?;
+                let version = self
+                    .core
+                    .storage
+                    .config
+                    .rollback(id, &access_token.name)
+                    .await?
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "version": version,
+                        "reload": self.reload_settings(false).await?,
+                    },
+                }))
+                .into_http_response())
+            }
+            (Some("export"), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsList)?;
+
+                let params = UrlParams::new(req.uri().query());
+                let prefix = params.get("prefix").unwrap_or_default();
+                let config = effective_config(self, prefix).await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": config,
+                }))
+                .into_http_response())
+            }
+            (Some("diff"), &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsList)?;
+
+                let request =
+                    serde_json::from_slice::<DiffSettings>(body.as_deref().unwrap_or_default())
+                        .map_err(|err| {
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .from_json_error(err)
+                        })?;
+
+                // Compare against the running configuration when no target snapshot is provided
+                let to = match request.to {
+                    Some(to) => to,
+                    None => effective_config(self, "").await?,
+                };
+
+                Ok(JsonResponse::new(json!({
+                    "data": diff_settings(&request.from, &to),
+                }))
+                .into_http_response())
+            }
             (Some(prefix), &Method::DELETE) if !prefix.is_empty() => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::SettingsDelete)?;
 
                 let prefix = decode_path_element(prefix);
 
+                let previous = self
+                    .core
+                    .storage
+                    .config
+                    .snapshot_keys([prefix.to_string()])
+                    .await?;
                 self.core.storage.config.clear(prefix.as_ref()).await?;
+                self.core
+                    .storage
+                    .config
+                    .record_version(&access_token.name, None, previous)
+                    .await?;
 
                 Ok(JsonResponse::new(json!({
                     "data": (),
@@ -279,6 +406,41 @@ impl ManageSettings for Server {
                     trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
                 })?;
 
+                // Keep the current values of the modified keys so the change can be rolled back
+                let mut modified_keys = Vec::new();
+                for change in &changes {
+                    match change {
+                        UpdateSettings::Delete { keys } => {
+                            modified_keys.extend(keys.iter().cloned());
+                        }
+                        UpdateSettings::Clear { prefix, .. } => {
+                            modified_keys.extend(
+                                self.core
+                                    .storage
+                                    .config
+                                    .list(prefix, false)
+                                    .await?
+                                    .into_keys(),
+                            );
+                        }
+                        UpdateSettings::Insert { prefix, values, .. } => {
+                            modified_keys.extend(values.iter().map(|(key, _)| {
+                                if let Some(prefix) = prefix {
+                                    format!("{prefix}.{key}")
+                                } else {
+                                    key.clone()
+                                }
+                            }));
+                        }
+                    }
+                }
+                let previous = self
+                    .core
+                    .storage
+                    .config
+                    .snapshot_keys(modified_keys)
+                    .await?;
+
                 for change in changes {
                     match change {
                         UpdateSettings::Delete { keys } => {
@@ -344,6 +506,12 @@ impl ManageSettings for Server {
                     }
                 }
 
+                self.core
+                    .storage
+                    .config
+                    .record_version(&access_token.name, None, previous)
+                    .await?;
+
                 Ok(JsonResponse::new(json!({
                     "data": (),
                 }))
@@ -353,3 +521,160 @@ impl ManageSettings for Server {
         }
     }
 }
+
+// Local and database settings after macro expansion, with secrets masked
+async fn effective_config(server: &Server, prefix: &str) -> trc::Result<BTreeMap<String, String>> {
+    let config = server.core.storage.config.build_config(prefix).await?;
+
+    Ok(config
+        .keys
+        .into_iter()
+        .filter(|(key, _)| key.starts_with(prefix))
+        .map(|(key, value)| {
+            if is_secret_key(&key) {
+                (key, MASKED_VALUE.to_string())
+            } else {
+                (key, value)
+            }
+        })
+        .collect())
+}
+
+pub fn is_secret_key(key: &str) -> bool {
+    // Multi-valued settings end with an index
+    let mut parts = key
+        .split('.')
+        .rev()
+        .skip_while(|part| part.chars().all(|ch| ch.is_ascii_digit()));
+    let name = parts.next().unwrap_or(key);
+    let parent = parts.next().unwrap_or_default();
+
+    match name {
+        // Attribute, column and field mappings name where the secret is stored
+        _ if ["attributes", "columns", "fields"].contains(&parent) => false,
+        // Token lifetimes
+        _ if key.contains(".expiry.") => false,
+        // Symmetric keys, rate limiter and quota keys are expressions
+        "key" => key.starts_with("acme.") || ["oauth", "srs", "batv"].contains(&parent),
+        // Custom HTTP headers may include credentials, DKIM headers are a list of names
+        "headers" => !key.starts_with("signature."),
+        "credentials" | "consumer-key" => true,
+        _ => [
+            "secret",
+            "secret-key",
+            "password",
+            "private-key",
+            "license-key",
+            "api-key",
+            "access-key",
+            "signature-key",
+            "hmac-key",
+            "token",
+        ]
+        .iter()
+        .any(|suffix| name.ends_with(suffix)),
+    }
+}
+
+pub fn diff_settings(
+    from: &BTreeMap<String, String>,
+    to: &BTreeMap<String, String>,
+) -> SettingsDiff {
+    let mut diff = SettingsDiff::default();
+
+    for (key, from_value) in from {
+        match to.get(key) {
+            Some(to_value) if to_value != from_value => {
+                diff.changed.insert(
+                    key.clone(),
+                    SettingChange {
+                        from: from_value.clone(),
+                        to: to_value.clone(),
+                    },
+                );
+            }
+            Some(_) => {}
+            None => {
+                diff.removed.insert(key.clone(), from_value.clone());
+            }
+        }
+    }
+    for (key, to_value) in to {
+        if !from.contains_key(key) {
+            diff.added.insert(key.clone(), to_value.clone());
+        }
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_secret_key;
+
+    #[test]
+    fn secret_keys() {
+        for key in [
+            "authentication.fallback-admin.secret",
+            "authentication.master.secret",
+            "enterprise.license-key",
+            "enterprise.api-key",
+            "oauth.key",
+            "oauth.oidc.signature-key",
+            "session.srs.key",
+            "session.null-sender.batv.key",
+            "session.null-sender.batv.key.1",
+            "session.rspamd.default.password",
+            "session.hook.default.auth.secret",
+            "session.hook.default.headers.0",
+            "queue.route.relay.auth.secret",
+            "certificate.default.private-key",
+            "signature.rsa.private-key",
+            "acme.letsencrypt.secret",
+            "acme.letsencrypt.key",
+            "acme.letsencrypt.consumer-key",
+            "acme.letsencrypt.eab.hmac-key",
+            "store.postgresql.password",
+            "store.s3.access-key",
+            "store.s3.secret-key",
+            "store.s3.security-token",
+            "store.s3.session-token",
+            "store.azure.azure-access-key",
+            "store.azure.sas-token",
+            "store.nats.credentials",
+            "directory.ldap.bind.secret",
+            "directory.ldap.bind.auth.secret",
+            "directory.memory.principals.0.secret",
+            "directory.memory.principals.0.secret.1",
+            "directory.oidc.endpoint.auth.token",
+            "tracer.otel.headers.0",
+            "webhook.default.auth.secret",
+            "webhook.default.headers.1",
+            "metrics.prometheus.auth.secret",
+        ] {
+            assert!(is_secret_key(key), "{key}");
+        }
+
+        for key in [
+            "server.hostname",
+            "oauth.expiry.token",
+            "oauth.expiry.refresh-token",
+            "oauth.oidc.expiry.id-token",
+            "directory.ldap.attributes.secret",
+            "directory.ldap.attributes.secret-changed",
+            "directory.sql.columns.secret",
+            "directory.http.fields.secret",
+            "directory.oidc.endpoint.method",
+            "queue.limiter.inbound.ip.key",
+            "queue.limiter.inbound.sender.key.0",
+            "queue.quota.sender.key.1",
+            "signature.rsa.headers.0",
+            "spam-filter.bayes.classify.tokens.min",
+            "session.null-sender.batv.expiry",
+            "session.null-sender.batv.key-id",
+            "store.redis.key-prefix",
+        ] {
+            assert!(!is_secret_key(key), "{key}");
+        }
+    }
+}
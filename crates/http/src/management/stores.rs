@@ -16,13 +16,16 @@ use directory::{
     Permission,
     backend::internal::manage::{self, ManageDirectory},
 };
-use email::message::{ingest::EmailIngest, metadata::MessageData};
+use email::{
+    cache::{MessageCacheFetch, email::MessageCacheAccess},
+    message::{ingest::EmailIngest, metadata::MessageData},
+};
 use http_proto::{request::decode_path_element, *};
 use hyper::Method;
 use jmap_proto::types::{collection::Collection, property::Property};
 use serde_json::json;
 use services::task_manager::fts::FtsIndexTask;
-use std::future::Future;
+use std::{collections::BTreeMap, future::Future};
 use store::{
     Serialize, rand,
     write::{Archiver, BatchBuilder, ValueClass},
@@ -30,6 +33,11 @@ use store::{
 use trc::AddContext;
 use utils::url_params::UrlParams;
 
+use super::{
+    migrate::{ImapImportApi, MaildirImportApi},
+    redact::RedactApi,
+};
+
 // SPDX-SnippetBegin
 // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
 // SPDX-License-Identifier: LicenseRef-SEL
@@ -159,6 +167,8 @@ impl ManageStore for Server {
                     Some("reputation-domain") => vec![KV_REPUTATION_DOMAIN].into(),
                     Some("reputation-asn") => vec![KV_REPUTATION_ASN].into(),
                     Some("greylist") => vec![KV_GREYLIST].into(),
+                    Some("provider-backoff") => vec![KV_PROVIDER_BACKOFF].into(),
+                    Some("suppression") => vec![KV_SUPPRESSION].into(),
                     Some("bayes-account") => {
                         if let Some(account) = path.get(5).copied() {
                             let account_id = self
@@ -266,6 +276,25 @@ impl ManageStore for Server {
                 }
             }
             // SPDX-SnippetEnd
+            (Some("redact"), None, None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageRedact)?;
+
+                self.handle_redact_request(body, session, access_token)
+                    .await
+            }
+            (Some("import"), Some("imap"), None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageImport)?;
+
+                self.handle_imap_import(body, session).await
+            }
+            (Some("import"), Some("maildir"), None, &Method::POST) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MaildirImport)?;
+
+                self.handle_maildir_import(body, session).await
+            }
             (Some("uids"), Some(account_id), None, &Method::DELETE) => {
                 let account_id = self
                     .core
@@ -302,6 +331,61 @@ impl ManageStore for Server {
                 }))
                 .into_http_response())
             }
+            (
+                Some("keywords"),
+                Some(account_id),
+                None,
+                method @ (&Method::GET | &Method::DELETE),
+            ) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::PurgeAccount)?;
+
+                let account_id = self
+                    .core
+                    .storage
+                    .data
+                    .get_principal_id(decode_path_element(account_id).as_ref())
+                    .await?
+                    .ok_or_else(|| trc::ManageEvent::NotFound.into_err())?;
+
+                // Keywords no longer set on any message are only dropped from
+                // the account keyword table when the cache is rebuilt
+                let mut removed = 0;
+                if method == Method::DELETE {
+                    if let Some(cache) = self.inner.cache.messages.get(&account_id) {
+                        removed = cache.load_full().emails.keywords.len();
+                    }
+                    self.inner.cache.messages.remove(&account_id);
+                }
+
+                let cache = self.get_cached_messages(account_id).await?;
+                let keywords = cache
+                    .custom_keywords(None)
+                    .into_iter()
+                    .collect::<BTreeMap<_, _>>();
+                let mailboxes = cache
+                    .mailboxes
+                    .items
+                    .iter()
+                    .map(|mailbox| {
+                        (
+                            mailbox.path.clone(),
+                            cache.custom_keywords(Some(mailbox.document_id)).len(),
+                        )
+                    })
+                    .collect::<BTreeMap<_, _>>();
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "keywords": keywords,
+                        "mailboxes": mailboxes,
+                        "maxPerAccount": self.core.jmap.mail_max_keywords,
+                        "maxPerMailbox": self.core.jmap.mail_max_mailbox_keywords,
+                        "removed": removed.saturating_sub(cache.emails.keywords.len()),
+                    },
+                }))
+                .into_http_response())
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
@@ -0,0 +1,109 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, auth::AccessToken};
+use directory::Permission;
+use groupware::calendar::tzdata::import_tzdata;
+use hyper::Method;
+use serde_json::json;
+use utils::config::ConfigKey;
+
+use http_proto::*;
+use std::future::Future;
+
+const OVERRIDE_PREFIX: &str = "calendar.timezone.override";
+
+pub trait ManageTimezones: Sync + Send {
+    fn handle_manage_timezones(
+        &self,
+        req: &HttpRequest,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageTimezones for Server {
+    async fn handle_manage_timezones(
+        &self,
+        req: &HttpRequest,
+        body: Option<Vec<u8>>,
+        access_token: &AccessToken,
+    ) -> trc::Result<HttpResponse> {
+        match *req.method() {
+            Method::GET => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsList)?;
+
+                let overrides = self
+                    .core
+                    .storage
+                    .config
+                    .list(&format!("{OVERRIDE_PREFIX}."), true)
+                    .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": overrides,
+                }))
+                .into_http_response())
+            }
+            Method::POST => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsUpdate)?;
+
+                let tzdata = body
+                    .as_deref()
+                    .and_then(|body| std::str::from_utf8(body).ok())
+                    .ok_or_else(|| {
+                        trc::ResourceEvent::BadParameters
+                            .into_err()
+                            .details("Expected a tzdata source file")
+                    })?;
+                let import = import_tzdata(tzdata);
+                let imported = import.overrides.len();
+
+                // Imported records replace the existing overrides for the same zones
+                self.core
+                    .storage
+                    .config
+                    .set(
+                        import
+                            .overrides
+                            .into_iter()
+                            .map(|(name, target)| ConfigKey {
+                                key: format!("{OVERRIDE_PREFIX}.{name}"),
+                                value: target,
+                            }),
+                        true,
+                    )
+                    .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": {
+                        "imported": imported,
+                        "unsupported": import.unsupported,
+                    },
+                }))
+                .into_http_response())
+            }
+            Method::DELETE => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::SettingsUpdate)?;
+
+                self.core
+                    .storage
+                    .config
+                    .clear_prefix(&format!("{OVERRIDE_PREFIX}."))
+                    .await?;
+
+                Ok(JsonResponse::new(json!({
+                    "data": (),
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
@@ -19,7 +19,7 @@ use common::{
     },
     psl,
 };
-use directory::backend::internal::manage;
+use directory::backend::internal::manage::{self, ManageDirectory};
 use http_body_util::{StreamBody, combinators::BoxBody};
 use hyper::{
     Method, StatusCode,
@@ -34,17 +34,24 @@ use mail_auth::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use smtp::outbound::{
-    client::{SmtpClient, StartTlsResult},
-    dane::{dnssec::TlsaLookup, verify::TlsaVerify},
-    lookup::{DnsLookup, ToNextHop},
-    mta_sts::{lookup::MtaStsLookup, verify::VerifyPolicy},
+use smtp::{
+    core::{Session, SessionData},
+    inbound::simulate::Simulation,
+    outbound::{
+        client::{SmtpClient, StartTlsResult},
+        dane::{dnssec::TlsaLookup, verify::TlsaVerify},
+        lookup::{DnsLookup, ToNextHop},
+        mta_sts::{lookup::MtaStsLookup, verify::VerifyPolicy},
+    },
 };
 use tokio::{io::AsyncWriteExt, sync::mpsc};
+use trc::AddContext;
 use utils::url_params::UrlParams;
 
 use http_proto::{request::decode_path_element, *};
 
+use super::selftest::{SelfTestRequest, get_delivery_self_test, start_delivery_self_test};
+
 pub trait TroubleshootApi: Sync + Send {
     fn handle_troubleshoot_api_request(
         &self,
@@ -52,6 +59,7 @@ pub trait TroubleshootApi: Sync + Send {
         path: Vec<&str>,
         access_token: &AccessToken,
         body: Option<Vec<u8>>,
+        session: &HttpSessionData,
     ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
 }
 
@@ -62,6 +70,7 @@ impl TroubleshootApi for Server {
         path: Vec<&str>,
         access_token: &AccessToken,
         body: Option<Vec<u8>>,
+        session: &HttpSessionData,
     ) -> trc::Result<HttpResponse> {
         let params = UrlParams::new(req.uri().query());
         let account_id = access_token.primary_id();
@@ -121,11 +130,57 @@ impl TroubleshootApi for Server {
                 }))
                 .into_http_response())
             }
+            ("self-test", None, &Method::POST) => {
+                let request =
+                    serde_json::from_slice::<SelfTestRequest>(body.as_deref().unwrap_or_default())
+                        .map_err(|err| {
+                            trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .from_json_error(err)
+                        })?;
+
+                Ok(JsonResponse::new(json!({
+                        "data": start_delivery_self_test(self, session, access_token, request).await?,
+                }))
+                .into_http_response())
+            }
+            ("self-test", Some(task_id), &Method::GET) => Ok(JsonResponse::new(json!({
+                    "data": get_delivery_self_test(self, access_token, task_id).await?,
+            }))
+            .into_http_response()),
+            ("simulate", None, &Method::POST) => {
+                let request = serde_json::from_slice::<SimulationRequest>(
+                    body.as_deref().unwrap_or_default(),
+                )
+                .map_err(|err| {
+                    trc::EventType::Resource(trc::ResourceEvent::BadParameters).from_json_error(err)
+                })?;
+
+                Ok(JsonResponse::new(json!({
+                        "data": simulate_delivery(self, session, request).await?,
+                }))
+                .into_http_response())
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SimulationRequest {
+    remote_ip: IpAddr,
+    #[serde(default)]
+    local_ip: Option<IpAddr>,
+    #[serde(default)]
+    ehlo_domain: String,
+    #[serde(default)]
+    authenticated_as: Option<String>,
+    mail_from: String,
+    rcpt_to: Vec<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[serde(tag = "type")]
@@ -348,6 +403,7 @@ async fn delivery_troubleshoot(
         max_mx: mxs.len(),
         max_multi_homed: 10,
         ip_lookup_strategy: IpLookupStrategy::Ipv4thenIpv6,
+        proxy: None,
     };
     let hosts = if let Some(hosts) = mxs.to_remote_hosts(&domain, &mx_config) {
         tx.send(DeliveryStage::MxLookupSuccess {
@@ -1021,6 +1077,48 @@ async fn dmarc_troubleshoot(
     })
 }
 
+async fn simulate_delivery(
+    server: &Server,
+    session: &HttpSessionData,
+    request: SimulationRequest,
+) -> trc::Result<Simulation> {
+    let remote_ip = request.remote_ip;
+    let mut data = SessionData::new(
+        request
+            .local_ip
+            .unwrap_or(IpAddr::V4(std::net::Ipv4Addr::LOCALHOST)),
+        25,
+        remote_ip,
+        0,
+        server.lookup_asn_country(remote_ip).await,
+        session.session_id,
+    );
+    if let Some(name) = &request.authenticated_as {
+        let account_id = server
+            .store()
+            .get_principal_id(name)
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| trc::ManageEvent::NotFound.into_err().details(name.clone()))?;
+        data.authenticated_as = server
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?
+            .into();
+    }
+
+    Ok(
+        Session::local(server.clone(), session.instance.clone(), data)
+            .simulate(
+                &request.ehlo_domain,
+                request.mail_from,
+                request.rcpt_to,
+                request.message.map(String::into_bytes),
+            )
+            .await,
+    )
+}
+
 impl From<&SpfOutput> for AuthResult {
     fn from(value: &SpfOutput) -> Self {
         match value.result() {
@@ -0,0 +1,120 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use http_proto::{HtmlResponse, HttpRequest, HttpResponse, HttpSessionData, ToHttpResponse};
+use hyper::{Method, StatusCode};
+use smtp::queue::moderation::{ModerationAction, QueueModeration};
+use utils::url_params::UrlParams;
+
+use crate::auth::oauth::FormData;
+
+pub trait ModerationHandler: Sync + Send {
+    fn handle_moderation_request(
+        &self,
+        req: &mut HttpRequest,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ModerationHandler for Server {
+    async fn handle_moderation_request(
+        &self,
+        req: &mut HttpRequest,
+        session: &HttpSessionData,
+    ) -> trc::Result<HttpResponse> {
+        let (token, action) = if req.method() == Method::POST {
+            let form = FormData::from_request(req, 4096, session.session_id).await?;
+            (
+                form.get("i").unwrap_or_default().to_string(),
+                form.get("a").unwrap_or_default().to_string(),
+            )
+        } else {
+            let params = UrlParams::new(req.uri().query());
+            (
+                params.get("i").unwrap_or_default().to_string(),
+                params.get("a").unwrap_or_default().to_string(),
+            )
+        };
+        let action = match action.as_str() {
+            "approve" => ModerationAction::Approve,
+            "reject" => ModerationAction::Reject,
+            _ => {
+                return Ok(render_page(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid moderation request.",
+                ));
+            }
+        };
+        if token.is_empty()
+            || !token
+                .bytes()
+                .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, b'+' | b'/' | b'='))
+        {
+            return Ok(render_page(
+                StatusCode::BAD_REQUEST,
+                "Invalid moderation token.",
+            ));
+        }
+
+        // Links are confirmed with a POST request to prevent link scanners
+        // from approving or rejecting messages on behalf of the moderator
+        if req.method() != Method::POST {
+            let (label, value) = match action {
+                ModerationAction::Approve => ("Approve delivery", "approve"),
+                ModerationAction::Reject => ("Reject message", "reject"),
+            };
+            return Ok(HtmlResponse::new(format!(
+                concat!(
+                    "<!DOCTYPE html><html><head><title>Held message</title></head><body>",
+                    "<form method=\"post\"><input type=\"hidden\" name=\"i\" value=\"{}\">",
+                    "<input type=\"hidden\" name=\"a\" value=\"{}\">",
+                    "<button type=\"submit\">{}</button></form></body></html>"
+                ),
+                token, value, label
+            ))
+            .into_http_response()
+            .with_no_store());
+        }
+
+        match self.moderate_message(&token, action).await {
+            Ok(true) => Ok(render_page(
+                StatusCode::OK,
+                match action {
+                    ModerationAction::Approve => "The message has been released for delivery.",
+                    ModerationAction::Reject => "The message has been rejected.",
+                },
+            )),
+            Ok(false) => Ok(render_page(
+                StatusCode::NOT_FOUND,
+                "The message no longer exists or has already been moderated.",
+            )),
+            Err(err) if err.matches(trc::EventType::Auth(trc::AuthEvent::TokenExpired)) => Ok(
+                render_page(StatusCode::GONE, "The moderation link has expired."),
+            ),
+            Err(err) => {
+                trc::error!(err.span_id(session.session_id));
+                Ok(render_page(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid moderation token.",
+                ))
+            }
+        }
+    }
+}
+
+fn render_page(status: StatusCode, text: &str) -> HttpResponse {
+    HtmlResponse::with_status(
+        status,
+        format!(
+            "<!DOCTYPE html><html><head><title>Held message</title></head><body><p>{text}</p></body></html>"
+        ),
+    )
+    .into_http_response()
+    .with_no_store()
+}
@@ -0,0 +1,166 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, net::SocketAddr};
+
+use common::{KV_IMAGE_PROXY, Server, USER_AGENT, config::jmap::settings::ImageProxy};
+use http_proto::{HttpRequest, HttpResponse};
+use hyper::{StatusCode, header};
+use store::{
+    Serialize, blake3,
+    dispatch::lookup::KeyValue,
+    write::{AlignedBytes, Archive, Archiver},
+};
+use trc::AddContext;
+use utils::{HttpLimitResponse, is_public_ip, url_params::UrlParams};
+
+// SVG is excluded as it can embed scripts
+const ALLOWED_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/avif",
+    "image/bmp",
+    "image/x-icon",
+    "image/vnd.microsoft.icon",
+];
+
+#[derive(rkyv::Serialize, rkyv::Deserialize, rkyv::Archive, Debug)]
+pub struct ProxiedImage {
+    pub content_type: String,
+    pub contents: Vec<u8>,
+}
+
+pub trait ImageProxyHandler: Sync + Send {
+    fn handle_image_proxy_request(
+        &self,
+        req: &HttpRequest,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ImageProxyHandler for Server {
+    async fn handle_image_proxy_request(&self, req: &HttpRequest) -> trc::Result<HttpResponse> {
+        let Some(config) = &self.core.jmap.http_image_proxy else {
+            return Ok(HttpResponse::new(StatusCode::NOT_FOUND));
+        };
+        let params = UrlParams::new(req.uri().query());
+        let Some(url) = params
+            .get("url")
+            .and_then(|url| reqwest::Url::parse(url).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+        else {
+            return Ok(HttpResponse::new(StatusCode::BAD_REQUEST));
+        };
+
+        // Serve from cache
+        let key = blake3::hash(url.as_str().as_bytes());
+        let key = key.as_bytes().as_slice();
+        if let Some(image_) = self
+            .in_memory_store()
+            .key_get::<Archive<AlignedBytes>>(KeyValue::<()>::build_key(KV_IMAGE_PROXY, key))
+            .await
+            .caused_by(trc::location!())?
+        {
+            let image = image_
+                .deserialize::<ProxiedImage>()
+                .caused_by(trc::location!())?;
+            return Ok(image_response(config, image));
+        }
+
+        let image = match fetch_image(config, url).await {
+            Ok(image) => image,
+            Err(status) => return Ok(HttpResponse::new(status)),
+        };
+        self.in_memory_store()
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_IMAGE_PROXY,
+                    key,
+                    Archiver::new(ProxiedImage {
+                        content_type: image.content_type.clone(),
+                        contents: image.contents.clone(),
+                    })
+                    .untrusted()
+                    .serialize()
+                    .caused_by(trc::location!())?,
+                )
+                .expires(config.cache_ttl),
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        Ok(image_response(config, image))
+    }
+}
+
+async fn fetch_image(config: &ImageProxy, url: reqwest::Url) -> Result<ProxiedImage, StatusCode> {
+    // Resolve the host upfront so internal addresses can't be reached through the proxy
+    let host = url
+        .host_str()
+        .map(|host| {
+            host.trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string()
+        })
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let port = url.port_or_known_default().ok_or(StatusCode::BAD_REQUEST)?;
+    let addrs = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?
+        .collect::<Vec<SocketAddr>>();
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_public_ip(addr.ip())) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // No cookies, referrer or client headers are forwarded
+    let response = reqwest::Client::builder()
+        .timeout(config.timeout)
+        .user_agent(USER_AGENT)
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve_to_addrs(&host, &addrs)
+        .build()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .get(url)
+        .header(reqwest::header::ACCEPT, "image/*")
+        .send()
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?;
+    if !response.status().is_success() {
+        return Err(StatusCode::BAD_GATEWAY);
+    }
+
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|ct| ct.to_str().ok())
+        .map(|ct| ct.split_once(';').map_or(ct, |(ct, _)| ct).trim())
+        .map(|ct| ct.to_ascii_lowercase())
+        .filter(|ct| ALLOWED_TYPES.contains(&ct.as_str()))
+        .ok_or(StatusCode::UNSUPPORTED_MEDIA_TYPE)?;
+    let contents = response
+        .bytes_with_limit(config.max_size)
+        .await
+        .map_err(|_| StatusCode::BAD_GATEWAY)?
+        .ok_or(StatusCode::PAYLOAD_TOO_LARGE)?;
+
+    Ok(ProxiedImage {
+        content_type,
+        contents,
+    })
+}
+
+fn image_response(config: &ImageProxy, image: ProxiedImage) -> HttpResponse {
+    HttpResponse::new(StatusCode::OK)
+        .with_content_type(image.content_type)
+        .with_header(
+            header::CACHE_CONTROL,
+            format!("private, max-age={}", config.cache_ttl),
+        )
+        .with_header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .with_header(header::CONTENT_SECURITY_POLICY, "default-src 'none'")
+        .with_binary_body(image.contents)
+}
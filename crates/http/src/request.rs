@@ -18,6 +18,11 @@ use crate::{
     management::{
         ManagementApi, ToManageHttpResponse, UnauthorizedResponse, troubleshoot::TroubleshootApi,
     },
+    moderation::ModerationHandler,
+    proxy::ImageProxyHandler,
+    share::FileShareHandler,
+    unsubscribe::UnsubscribeHandler,
+    wkd::WkdHandler,
 };
 use common::{
     Inner, KV_ACME, Server,
@@ -334,6 +339,13 @@ impl ParseHttp for Server {
                         return self.handle_autoconfig_request(&req).await;
                     }
                 }
+                ("openpgpkey", &Method::GET) => {
+                    // Limit anonymous requests
+                    self.is_http_anonymous_request_allowed(&session.remote_ip)
+                        .await?;
+
+                    return self.handle_wkd_request(&req, path.collect()).await;
+                }
                 (_, &Method::OPTIONS) => {
                     return Ok(JsonProblemResponse(StatusCode::NO_CONTENT).into_http_response());
                 }
@@ -456,6 +468,7 @@ impl ParseHttp for Server {
                                         &AccessToken::from_id(token_info.account_id)
                                             .with_permission(Permission::Troubleshoot),
                                         None,
+                                        &session,
                                     )
                                     .await
                                 }
@@ -507,6 +520,48 @@ impl ParseHttp for Server {
                         });
                 }
             }
+            "queue" => {
+                if path.next().unwrap_or_default() == "moderate"
+                    && matches!(*req.method(), Method::GET | Method::POST)
+                {
+                    // Limit anonymous requests
+                    self.is_http_anonymous_request_allowed(&session.remote_ip)
+                        .await?;
+
+                    return self.handle_moderation_request(&mut req, &session).await;
+                }
+            }
+            "proxy" => {
+                if self.core.jmap.http_image_proxy.is_some()
+                    && req.method() == Method::GET
+                    && path.next().unwrap_or_default() == "image"
+                {
+                    // Authenticate request
+                    let (_in_flight, _) = self.authenticate_headers(&req, &session, false).await?;
+
+                    return self.handle_image_proxy_request(&req).await;
+                }
+            }
+            "share" => {
+                if self.core.groupware.file_share_url.is_some()
+                    && matches!(*req.method(), Method::GET | Method::HEAD)
+                {
+                    // Limit anonymous requests
+                    self.is_http_anonymous_request_allowed(&session.remote_ip)
+                        .await?;
+
+                    return self.handle_file_share_request(&req, &session).await;
+                }
+            }
+            "unsubscribe" => {
+                if matches!(*req.method(), Method::GET | Method::POST) {
+                    // Limit anonymous requests
+                    self.is_http_anonymous_request_allowed(&session.remote_ip)
+                        .await?;
+
+                    return self.handle_unsubscribe_request(&mut req, &session).await;
+                }
+            }
             "autodiscover" => {
                 if req.method() == Method::POST
                     && path.next().unwrap_or_default() == "autodiscover.xml"
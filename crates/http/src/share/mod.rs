@@ -0,0 +1,197 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, sync::Arc};
+
+use common::{KV_FILE_SHARE, Server, auth::AccessToken};
+use directory::backend::internal::manage;
+use groupware::{
+    cache::GroupwareCache,
+    file::{FileNode, share::FileShare},
+};
+use http_proto::{HttpRequest, HttpResponse, HttpSessionData, JsonResponse, ToHttpResponse};
+use hyper::{Method, StatusCode, header};
+use jmap_proto::types::collection::{Collection, SyncCollection};
+use serde::Deserialize;
+use serde_json::json;
+use store::dispatch::lookup::KeyValue;
+use trc::AddContext;
+use utils::url_params::UrlParams;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileShareRequest {
+    path: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+    #[serde(default)]
+    max_downloads: Option<u32>,
+}
+
+pub trait FileShareHandler: Sync + Send {
+    fn handle_file_share_request(
+        &self,
+        req: &HttpRequest,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn handle_file_share_create(
+        &self,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl FileShareHandler for Server {
+    async fn handle_file_share_request(
+        &self,
+        req: &HttpRequest,
+        session: &HttpSessionData,
+    ) -> trc::Result<HttpResponse> {
+        let params = UrlParams::new(req.uri().query());
+        let link = match self
+            .file_share_decode(params.get("t").unwrap_or_default())
+            .await
+        {
+            Ok(link) => link,
+            Err(err) if err.matches(trc::EventType::Auth(trc::AuthEvent::TokenExpired)) => {
+                return Ok(HttpResponse::new(StatusCode::GONE));
+            }
+            Err(err) => {
+                trc::error!(err.span_id(session.session_id));
+                return Ok(HttpResponse::new(StatusCode::NOT_FOUND));
+            }
+        };
+
+        // Fetch node
+        let Some(node_) = self
+            .get_archive(link.account_id, Collection::FileNode, link.document_id)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(HttpResponse::new(StatusCode::NOT_FOUND));
+        };
+        let node = node_.unarchive::<FileNode>().caused_by(trc::location!())?;
+        let Some(file) = node
+            .file
+            .as_ref()
+            .filter(|_| i64::from(node.created) == link.created)
+        else {
+            return Ok(HttpResponse::new(StatusCode::NOT_FOUND));
+        };
+
+        // Enforce download limits, HEAD requests do not count as downloads
+        if let Some(max_downloads) = link.max_downloads {
+            let mut key = Vec::with_capacity(16);
+            key.extend_from_slice(&link.account_id.to_be_bytes());
+            key.extend_from_slice(&link.document_id.to_be_bytes());
+            key.extend_from_slice(&link.issued_at.to_be_bytes());
+            let downloads = if req.method() == Method::HEAD {
+                self.in_memory_store()
+                    .counter_get(KeyValue::<()>::build_key(KV_FILE_SHARE, key))
+                    .await
+                    .caused_by(trc::location!())?
+                    + 1
+            } else {
+                self.in_memory_store()
+                    .counter_incr(
+                        KeyValue::with_prefix(KV_FILE_SHARE, key, 1).expires(link.expires_in),
+                        true,
+                    )
+                    .await
+                    .caused_by(trc::location!())?
+            };
+            if downloads > max_downloads as i64 {
+                return Ok(HttpResponse::new(StatusCode::GONE));
+            }
+        }
+
+        let file_name = node
+            .name
+            .chars()
+            .map(|ch| {
+                if (ch.is_ascii_graphic() && !matches!(ch, '"' | '\\')) || ch == ' ' {
+                    ch
+                } else {
+                    '_'
+                }
+            })
+            .collect::<String>();
+        let response = HttpResponse::new(StatusCode::OK)
+            .with_content_type(
+                file.media_type
+                    .as_ref()
+                    .map(|s| s.as_str())
+                    .unwrap_or("application/octet-stream"),
+            )
+            .with_header(
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{file_name}\""),
+            )
+            .with_etag(format!("\"{}\"", node_.version.hash().unwrap_or_default()))
+            .with_no_store();
+
+        if req.method() == Method::HEAD {
+            Ok(response.with_content_length(u32::from(file.size) as usize))
+        } else if let Some(bytes) = self
+            .blob_store()
+            .get_blob(file.blob_hash.0.as_ref(), 0..usize::MAX)
+            .await
+            .caused_by(trc::location!())?
+        {
+            Ok(response.with_binary_body(bytes))
+        } else {
+            Ok(HttpResponse::new(StatusCode::NOT_FOUND))
+        }
+    }
+
+    async fn handle_file_share_create(
+        &self,
+        access_token: Arc<AccessToken>,
+        body: Option<Vec<u8>>,
+    ) -> trc::Result<HttpResponse> {
+        let request =
+            serde_json::from_slice::<FileShareRequest>(body.as_deref().unwrap_or_default())
+                .map_err(|err| trc::ResourceEvent::BadParameters.into_err().reason(err))?;
+
+        // Only files owned by the account can be shared
+        let account_id = access_token.primary_id();
+        let files = self
+            .fetch_dav_resources(&access_token, account_id, SyncCollection::FileNode)
+            .await
+            .caused_by(trc::location!())?;
+        let resource = files
+            .by_path(request.path.trim_matches('/'))
+            .filter(|resource| !resource.is_container())
+            .ok_or_else(|| manage::not_found(request.path.clone()))?;
+        let node_ = self
+            .get_archive(account_id, Collection::FileNode, resource.document_id())
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| manage::not_found(request.path.clone()))?;
+        let node = node_.unarchive::<FileNode>().caused_by(trc::location!())?;
+
+        let url = self
+            .file_share_url(
+                account_id,
+                resource.document_id(),
+                i64::from(node.created),
+                request
+                    .expires_in
+                    .unwrap_or(self.core.groupware.file_share_max_expiration),
+                request.max_downloads,
+            )
+            .await?
+            .ok_or_else(|| {
+                manage::unsupported("File sharing has been disabled by the system administrator")
+            })?;
+
+        Ok(JsonResponse::new(json!({
+            "data": url,
+        }))
+        .into_http_response())
+    }
+}
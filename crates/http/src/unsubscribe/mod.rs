@@ -0,0 +1,88 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+use http_proto::{HtmlResponse, HttpRequest, HttpResponse, HttpSessionData, ToHttpResponse};
+use hyper::{Method, StatusCode};
+use smtp::reporting::feedback::FeedbackLoop;
+use utils::url_params::UrlParams;
+
+pub trait UnsubscribeHandler: Sync + Send {
+    fn handle_unsubscribe_request(
+        &self,
+        req: &mut HttpRequest,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl UnsubscribeHandler for Server {
+    async fn handle_unsubscribe_request(
+        &self,
+        req: &mut HttpRequest,
+        session: &HttpSessionData,
+    ) -> trc::Result<HttpResponse> {
+        // The token is part of the List-Unsubscribe URL, both for one-click
+        // POST requests (RFC 8058) and for links opened in a browser
+        let params = UrlParams::new(req.uri().query());
+        let token = params.get("t").unwrap_or_default();
+        if token.is_empty()
+            || !token
+                .bytes()
+                .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, b'+' | b'/' | b'='))
+        {
+            return Ok(render_page(
+                StatusCode::BAD_REQUEST,
+                "Invalid unsubscribe link.",
+            ));
+        }
+
+        // Browsers are asked to confirm to prevent link scanners from
+        // unsubscribing recipients
+        if req.method() != Method::POST {
+            return Ok(HtmlResponse::new(
+                concat!(
+                    "<!DOCTYPE html><html><head><title>Unsubscribe</title></head><body>",
+                    "<form method=\"post\"><input type=\"hidden\" name=\"List-Unsubscribe\" ",
+                    "value=\"One-Click\"><button type=\"submit\">Unsubscribe</button></form>",
+                    "</body></html>"
+                )
+                .to_string(),
+            )
+            .into_http_response()
+            .with_no_store());
+        }
+
+        match self.unsubscribe(token, session.session_id).await {
+            Ok(_) => Ok(render_page(
+                StatusCode::OK,
+                "You have been unsubscribed and will no longer receive these messages.",
+            )),
+            Err(err) if err.matches(trc::EventType::Auth(trc::AuthEvent::TokenExpired)) => Ok(
+                render_page(StatusCode::GONE, "The unsubscribe link has expired."),
+            ),
+            Err(err) => {
+                trc::error!(err.span_id(session.session_id));
+                Ok(render_page(
+                    StatusCode::BAD_REQUEST,
+                    "Invalid unsubscribe link.",
+                ))
+            }
+        }
+    }
+}
+
+fn render_page(status: StatusCode, text: &str) -> HttpResponse {
+    HtmlResponse::with_status(
+        status,
+        format!(
+            "<!DOCTYPE html><html><head><title>Unsubscribe</title></head><body><p>{text}</p></body></html>"
+        ),
+    )
+    .into_http_response()
+    .with_no_store()
+}
@@ -0,0 +1,142 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, manager::webadmin::Resource};
+use email::message::crypto::EncryptionParams;
+use http_proto::{HttpRequest, HttpResponse, ToHttpResponse};
+use hyper::header;
+use jmap_proto::types::{collection::Collection, property::Property};
+use sha1::{Digest, Sha1};
+use std::future::Future;
+use trc::AddContext;
+use utils::url_params::UrlParams;
+
+const ZBASE32_ALPHABET: &[u8] = b"ybndrfg8ejkmcpqxot1uwisza345h769";
+
+pub trait WkdHandler: Sync + Send {
+    fn handle_wkd_request(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl WkdHandler for Server {
+    async fn handle_wkd_request(
+        &self,
+        req: &HttpRequest,
+        path: Vec<&str>,
+    ) -> trc::Result<HttpResponse> {
+        if !self.core.jmap.encrypt_wkd {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+
+        // The advanced method includes the domain in the path, while the direct
+        // method obtains it from the host name
+        let (domain, path) = match path.as_slice() {
+            [domain, rest @ ..] if !matches!(*domain, "hu" | "policy") => {
+                (domain.to_lowercase(), rest)
+            }
+            rest => (
+                req.headers()
+                    .get(header::HOST)
+                    .and_then(|h| h.to_str().ok())
+                    .map(|h| h.rsplit_once(':').map_or(h, |(h, _)| h))
+                    .map(|h| h.strip_prefix("openpgpkey.").unwrap_or(h).to_lowercase())
+                    .unwrap_or_default(),
+                rest,
+            ),
+        };
+        if domain.is_empty()
+            || !self
+                .core
+                .storage
+                .directory
+                .is_local_domain(&domain)
+                .await
+                .caused_by(trc::location!())?
+        {
+            return Err(trc::ResourceEvent::NotFound.into_err());
+        }
+
+        match path {
+            ["policy"] => Ok(Resource::new("text/plain", Vec::new()).into_http_response()),
+            ["hu", hash] => {
+                // Keys are looked up by the local part provided by the client
+                let params = UrlParams::new(req.uri().query());
+                let local_part = params.get("l").unwrap_or_default().to_lowercase();
+                if local_part.is_empty() || wkd_hash(&local_part) != *hash {
+                    return Err(trc::ResourceEvent::NotFound.into_err());
+                }
+
+                let Some(account_id) = self
+                    .core
+                    .storage
+                    .directory
+                    .email_to_id(&format!("{local_part}@{domain}"))
+                    .await
+                    .caused_by(trc::location!())?
+                else {
+                    return Err(trc::ResourceEvent::NotFound.into_err());
+                };
+
+                self.get_archive_by_property(
+                    account_id,
+                    Collection::Principal,
+                    0,
+                    Property::Parameters,
+                )
+                .await
+                .caused_by(trc::location!())?
+                .map(|params| params.deserialize::<EncryptionParams>())
+                .transpose()
+                .caused_by(trc::location!())?
+                .and_then(|params| params.openpgp_public_key())
+                .map(|key| {
+                    Resource::new("application/octet-stream", key)
+                        .into_http_response()
+                        .with_header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+                })
+                .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+// Z-Base-32 encoded SHA-1 digest of the lowercase local part
+fn wkd_hash(local_part: &str) -> String {
+    let digest = Sha1::digest(local_part.as_bytes());
+    let mut result = String::with_capacity(32);
+    let mut buf = 0u32;
+    let mut bits = 0;
+
+    for byte in digest {
+        buf = (buf << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            result.push(ZBASE32_ALPHABET[((buf >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        result.push(ZBASE32_ALPHABET[((buf << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+
+    #[test]
+    fn wkd_hash() {
+        assert_eq!(
+            super::wkd_hash("joe.doe"),
+            "iy9q119eutrkn8s1mk4r39qejnbu3n5q"
+        );
+    }
+}
@@ -76,6 +76,7 @@ pub enum Command {
     // RFC 9208
     GetQuota,
     GetQuotaRoot,
+    SetQuota,
 }
 
 impl Command {
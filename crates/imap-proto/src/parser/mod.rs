@@ -80,6 +80,7 @@ impl CommandParser for Command {
             "ID" => Command::Id,
             "GETQUOTA" => Command::GetQuota,
             "GETQUOTAROOT" => Command::GetQuotaRoot,
+            "SETQUOTA" => Command::SetQuota,
         )
     }
 
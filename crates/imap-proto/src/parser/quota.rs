@@ -8,11 +8,13 @@ use compact_str::ToCompactString;
 
 use crate::{
     Command,
-    protocol::quota,
-    receiver::{Request, bad},
+    protocol::{capability::QuotaResourceName, quota},
+    receiver::{Request, Token, bad},
     utf7::utf7_maybe_decode,
 };
 
+use super::parse_number;
+
 impl Request<Command> {
     pub fn parse_get_quota_root(self, is_utf8: bool) -> trc::Result<quota::Arguments> {
         match self.tokens.len() {
@@ -49,11 +51,77 @@ impl Request<Command> {
             _ => Err(self.into_error("Too many arguments.")),
         }
     }
+
+    pub fn parse_set_quota(self) -> trc::Result<quota::SetArguments> {
+        if self.tokens.len() < 3 {
+            return Err(self.into_error("Missing arguments."));
+        }
+
+        let mut tokens = self.tokens.into_iter();
+        let name = tokens
+            .next()
+            .unwrap()
+            .unwrap_string()
+            .map_err(|v| bad(self.tag.to_compact_string(), v))?;
+        if tokens
+            .next()
+            .is_none_or(|token| !token.is_parenthesis_open())
+        {
+            return Err(bad(
+                self.tag.to_compact_string(),
+                "Expected parenthesis after quota root.",
+            ));
+        }
+
+        let mut limits = Vec::new();
+        loop {
+            match tokens.next() {
+                Some(Token::ParenthesisClose) => break,
+                Some(Token::Argument(resource)) => {
+                    let resource = QuotaResourceName::parse(&resource).ok_or_else(|| {
+                        bad(
+                            self.tag.to_compact_string(),
+                            format!(
+                                "Unsupported quota resource {:?}.",
+                                String::from_utf8_lossy(&resource)
+                            ),
+                        )
+                    })?;
+                    let limit = tokens
+                        .next()
+                        .ok_or_else(|| {
+                            bad(self.tag.to_compact_string(), "Missing resource limit.")
+                        })?
+                        .unwrap_bytes();
+                    limits.push((
+                        resource,
+                        parse_number::<u64>(&limit)
+                            .map_err(|v| bad(self.tag.to_compact_string(), v))?,
+                    ));
+                }
+                _ => {
+                    return Err(bad(
+                        self.tag.to_compact_string(),
+                        "Invalid quota resource list.",
+                    ));
+                }
+            }
+        }
+
+        Ok(quota::SetArguments {
+            tag: self.tag,
+            name,
+            limits,
+        })
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{protocol::quota, receiver::Receiver};
+    use crate::{
+        protocol::{capability::QuotaResourceName, quota},
+        receiver::Receiver,
+    };
 
     #[test]
     fn parse_quota() {
@@ -90,5 +158,33 @@ mod tests {
                 .unwrap(),
             arguments
         );
+
+        let (command, arguments) = (
+            "A001 SETQUOTA \"#1\" (STORAGE 512 MESSAGE 100)\r\n",
+            quota::SetArguments {
+                name: "#1".into(),
+                tag: "A001".into(),
+                limits: vec![
+                    (QuotaResourceName::Storage, 512),
+                    (QuotaResourceName::Message, 100),
+                ],
+            },
+        );
+        assert_eq!(
+            receiver
+                .parse(&mut command.as_bytes().iter())
+                .unwrap()
+                .parse_set_quota()
+                .unwrap(),
+            arguments
+        );
+
+        assert!(
+            receiver
+                .parse(&mut "A002 SETQUOTA \"#1\" (STORAGE)\r\n".as_bytes().iter())
+                .unwrap()
+                .parse_set_quota()
+                .is_err()
+        );
     }
 }
@@ -72,6 +72,17 @@ pub enum QuotaResourceName {
     AnnotationStorage,
 }
 
+impl QuotaResourceName {
+    pub fn parse(value: &[u8]) -> Option<Self> {
+        hashify::tiny_map_ignore_case!(value,
+            "STORAGE" => Self::Storage,
+            "MESSAGE" => Self::Message,
+            "MAILBOX" => Self::Mailbox,
+            "ANNOTATION-STORAGE" => Self::AnnotationStorage,
+        )
+    }
+}
+
 impl Capability {
     pub fn serialize(&self, buf: &mut Vec<u8>) {
         buf.extend_from_slice(match self {
@@ -173,6 +184,7 @@ impl Capability {
                 Capability::Preview,
                 Capability::Quota,
                 Capability::QuotaResource(QuotaResourceName::Storage),
+                Capability::QuotaResource(QuotaResourceName::Message),
             ]);
         } else {
             capabilities.extend([
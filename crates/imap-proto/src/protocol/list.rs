@@ -71,6 +71,7 @@ pub enum Attribute {
     Sent,
     Trash,
     Important,
+    Scheduled,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -142,6 +143,7 @@ impl Attribute {
             Attribute::Sent => b"\\Sent",
             Attribute::Trash => b"\\Trash",
             Attribute::Important => b"\\Important",
+            Attribute::Scheduled => b"\\Scheduled",
         });
     }
 }
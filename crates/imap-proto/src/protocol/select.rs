@@ -39,6 +39,8 @@ pub struct Response {
     pub is_utf8: bool,
     pub closed_previous: bool,
     pub highest_modseq: Option<HighestModSeq>,
+    pub keywords: Vec<String>,
+    pub can_create_keywords: bool,
     pub mailbox_id: String,
 }
 
@@ -57,15 +59,15 @@ impl ImapResponse for Response {
         }
         buf.extend_from_slice(b"* ");
         buf.extend_from_slice(self.total_messages.to_string().as_bytes());
+        buf.extend_from_slice(b" EXISTS\r\n* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft");
         if !self.is_rev2 && self.recent_messages > 0 {
-            buf.extend_from_slice(
-                b" EXISTS\r\n* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft \\Recent)\r\n",
-            );
-        } else {
-            buf.extend_from_slice(
-                b" EXISTS\r\n* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft)\r\n",
-            );
+            buf.extend_from_slice(b" \\Recent");
+        }
+        for keyword in &self.keywords {
+            buf.push(b' ');
+            buf.extend_from_slice(keyword.as_bytes());
         }
+        buf.extend_from_slice(b")\r\n");
         if self.is_rev2 {
             self.mailbox
                 .serialize(&mut buf, self.is_rev2, self.is_utf8, false);
@@ -80,8 +82,17 @@ impl ImapResponse for Response {
             }
         }
         buf.extend_from_slice(
-            b"* OK [PERMANENTFLAGS (\\Deleted \\Seen \\Answered \\Flagged \\Draft \\*)] All allowed\r\n",
+            b"* OK [PERMANENTFLAGS (\\Deleted \\Seen \\Answered \\Flagged \\Draft",
         );
+        for keyword in &self.keywords {
+            buf.push(b' ');
+            buf.extend_from_slice(keyword.as_bytes());
+        }
+        if self.can_create_keywords {
+            buf.extend_from_slice(b" \\*)] All allowed\r\n");
+        } else {
+            buf.extend_from_slice(b")] Keyword limit reached\r\n");
+        }
         buf.extend_from_slice(b"* OK [UIDVALIDITY ");
         buf.extend_from_slice(self.uid_validity.to_string().as_bytes());
         buf.extend_from_slice(b"] UIDs valid\r\n* OK [UIDNEXT ");
@@ -150,6 +161,8 @@ mod tests {
                     is_rev2: true,
                     is_utf8: true,
                     highest_modseq: HighestModSeq::new(100).into(),
+                    keywords: vec![],
+                    can_create_keywords: true,
                     mailbox_id: "abc".into(),
                 },
                 "A142",
@@ -187,16 +200,18 @@ mod tests {
                     is_rev2: true,
                     is_utf8: true,
                     highest_modseq: None,
+                    keywords: vec!["$label1".into(), "work".into()],
+                    can_create_keywords: false,
                     mailbox_id: "abc".into(),
                 },
                 "A142",
                 concat!(
                     "* OK [CLOSED] Closed previous mailbox\r\n",
                     "* 172 EXISTS\r\n",
-                    "* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft)\r\n",
+                    "* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft $label1 work)\r\n",
                     "* LIST () \"/\" \"~peter/mail/台北/日本語\" (\"OLDNAME\" ",
                     "(\"~peter/mail/&U,BTFw-/&ZeVnLIqe-\"))\r\n",
-                    "* OK [PERMANENTFLAGS (\\Deleted \\Seen \\Answered \\Flagged \\Draft \\*)] All allowed\r\n",
+                    "* OK [PERMANENTFLAGS (\\Deleted \\Seen \\Answered \\Flagged \\Draft $label1 work)] Keyword limit reached\r\n",
                     "* OK [UIDVALIDITY 3857529045] UIDs valid\r\n",
                     "* OK [UIDNEXT 4392] Next predicted UID\r\n",
                     "* OK [MAILBOXID (abc)] Unique Mailbox ID\r\n"
@@ -204,10 +219,10 @@ mod tests {
                 concat!(
                     "* OK [CLOSED] Closed previous mailbox\r\n",
                     "* 172 EXISTS\r\n",
-                    "* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft \\Recent)\r\n",
+                    "* FLAGS (\\Answered \\Flagged \\Deleted \\Seen \\Draft \\Recent $label1 work)\r\n",
                     "* 5 RECENT\r\n",
                     "* OK [UNSEEN 3] Unseen messages\r\n",
-                    "* OK [PERMANENTFLAGS (\\Deleted \\Seen \\Answered \\Flagged \\Draft \\*)] All allowed\r\n",
+                    "* OK [PERMANENTFLAGS (\\Deleted \\Seen \\Answered \\Flagged \\Draft $label1 work)] Keyword limit reached\r\n",
                     "* OK [UIDVALIDITY 3857529045] UIDs valid\r\n",
                     "* OK [UIDNEXT 4392] Next predicted UID\r\n",
                     "* OK [MAILBOXID (abc)] Unique Mailbox ID\r\n"
@@ -239,6 +239,10 @@ impl<T: SessionStream> Session<T> {
                     .handle_get_quota_root(request)
                     .await
                     .map(|_| SessionResult::Continue),
+                Command::SetQuota => self
+                    .handle_set_quota(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
                 Command::Unauthenticate => self
                     .handle_unauthenticate(request)
                     .await
@@ -379,7 +383,8 @@ impl<T: SessionStream> Session<T> {
             | Command::MyRights
             | Command::Unauthenticate
             | Command::GetQuota
-            | Command::GetQuotaRoot => {
+            | Command::GetQuotaRoot
+            | Command::SetQuota => {
                 if let State::Authenticated { .. } | State::Selected { .. } = state {
                     Ok(request)
                 } else {
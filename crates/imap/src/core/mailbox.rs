@@ -4,10 +4,14 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use super::{Account, MailboxId, MailboxSync, Session, SessionData};
+use super::{
+    Account, MailboxId, MailboxSync, SCHEDULED_MAILBOX_ID, SCHEDULED_UID_VALIDITY, Session,
+    SessionData, VIRTUAL_MAILBOX_ID,
+};
 use crate::core::Mailbox;
 use ahash::AHashMap;
 use common::{
+    MessageStoreCache,
     auth::AccessToken,
     config::jmap::settings::SpecialUse,
     listener::{SessionStream, limiter::InFlight},
@@ -17,7 +21,8 @@ use common::{
 use directory::backend::internal::manage::ManageDirectory;
 use email::{
     cache::{MessageCacheFetch, email::MessageCacheAccess, mailbox::MailboxCacheAccess},
-    mailbox::INBOX_ID,
+    mailbox::{INBOX_ID, search::SavedSearchManager},
+    submission::scheduled::ScheduledSubmissions,
 };
 use imap_proto::protocol::list::Attribute;
 use jmap_proto::types::{acl::Acl, collection::Collection, id::Id, keyword::Keyword};
@@ -26,6 +31,7 @@ use std::{
     collections::BTreeMap,
     sync::{Arc, atomic::Ordering},
 };
+use store::roaring::RoaringBitmap;
 use trc::AddContext;
 
 impl<T: SessionStream> SessionData<T> {
@@ -41,6 +47,7 @@ impl<T: SessionStream> SessionData<T> {
             session_id: session.session_id,
             mailboxes: Mutex::new(vec![]),
             state: access_token.state().into(),
+            bandwidth: session.bandwidth_limiters(&access_token).await,
             access_token,
             in_flight,
         };
@@ -197,9 +204,165 @@ impl<T: SessionStream> SessionData<T> {
             );
         }
 
+        // Add saved searches of the primary account
+        if account.prefix.is_none() {
+            let searches = self
+                .server
+                .get_saved_searches(account_id)
+                .await
+                .caused_by(trc::location!())?;
+            for search in searches.items {
+                let document_ids = match self
+                    .server
+                    .saved_search_results(account_id, &cache, &search)
+                    .await
+                {
+                    Ok(document_ids) => document_ids,
+                    Err(err) => {
+                        // A broken definition should not prevent the mailbox list from loading
+                        trc::error!(
+                            err.account_id(account_id)
+                                .details("Failed to evaluate saved search")
+                                .caused_by(trc::location!())
+                        );
+                        continue;
+                    }
+                };
+                let mailbox = MailboxId {
+                    account_id,
+                    mailbox_id: VIRTUAL_MAILBOX_ID | search.id,
+                };
+
+                account.mailbox_names.insert(
+                    format!(
+                        "{}/{}",
+                        self.server.core.jmap.saved_search_folder, search.name
+                    ),
+                    mailbox.mailbox_id,
+                );
+                account.mailbox_state.insert(
+                    mailbox.mailbox_id,
+                    self.virtual_mailbox(&mailbox, &cache, &document_ids, search.uid_validity)
+                        .await?,
+                );
+            }
+
+            // Add messages held in the queue for future delivery
+            if let Some(folder_name) = &self.server.core.jmap.scheduled_send_folder {
+                let mailbox = MailboxId {
+                    account_id,
+                    mailbox_id: SCHEDULED_MAILBOX_ID,
+                };
+                let document_ids = self
+                    .virtual_mailbox_ids(&mailbox, &cache)
+                    .await?
+                    .unwrap_or_default();
+                let mut mailbox_state = self
+                    .virtual_mailbox(&mailbox, &cache, &document_ids, SCHEDULED_UID_VALIDITY)
+                    .await?;
+                mailbox_state.special_use = Some(Attribute::Scheduled);
+
+                account
+                    .mailbox_names
+                    .insert(folder_name.clone(), mailbox.mailbox_id);
+                account
+                    .mailbox_state
+                    .insert(mailbox.mailbox_id, mailbox_state);
+            }
+        }
+
         Ok(account.into())
     }
 
+    async fn virtual_mailbox(
+        &self,
+        mailbox: &MailboxId,
+        cache: &MessageStoreCache,
+        document_ids: &RoaringBitmap,
+        uid_validity: u32,
+    ) -> trc::Result<Mailbox> {
+        let count_with_keyword = |keyword: &Keyword| {
+            document_ids
+                .iter()
+                .filter(|document_id| {
+                    cache
+                        .email_by_id(document_id)
+                        .is_some_and(|item| cache.has_keyword(item, keyword))
+                })
+                .count() as u64
+        };
+
+        Ok(Mailbox {
+            has_children: false,
+            is_subscribed: true,
+            special_use: None,
+            total_messages: document_ids.len(),
+            total_unseen: document_ids.len() - count_with_keyword(&Keyword::Seen),
+            total_deleted: count_with_keyword(&Keyword::Deleted),
+            uid_validity: uid_validity as u64,
+            uid_next: self
+                .server
+                .get_virtual_uids(mailbox.account_id, mailbox.mailbox_id, uid_validity)
+                .await
+                .caused_by(trc::location!())?
+                .uid_next as u64,
+            total_deleted_storage: None,
+            size: None,
+        })
+    }
+
+    pub async fn virtual_mailbox_ids(
+        &self,
+        mailbox: &MailboxId,
+        cache: &MessageStoreCache,
+    ) -> trc::Result<Option<RoaringBitmap>> {
+        self.virtual_mailbox_results(mailbox, cache)
+            .await
+            .map(|results| results.map(|(document_ids, _)| document_ids))
+    }
+
+    // Returns the messages in a virtual mailbox along with its UID validity
+    pub async fn virtual_mailbox_results(
+        &self,
+        mailbox: &MailboxId,
+        cache: &MessageStoreCache,
+    ) -> trc::Result<Option<(RoaringBitmap, u32)>> {
+        if mailbox.is_scheduled() {
+            let mut document_ids = RoaringBitmap::new();
+            for submission in self
+                .server
+                .scheduled_submissions(mailbox.account_id)
+                .await
+                .caused_by(trc::location!())?
+            {
+                if cache.email_by_id(&submission.email_id).is_some() {
+                    document_ids.insert(submission.email_id);
+                }
+            }
+            Ok(Some((document_ids, SCHEDULED_UID_VALIDITY)))
+        } else if let Some(search_id) = mailbox.saved_search_id() {
+            let search = self
+                .server
+                .get_saved_searches(mailbox.account_id)
+                .await
+                .caused_by(trc::location!())?
+                .items
+                .into_iter()
+                .find(|search| search.id == search_id)
+                .ok_or_else(|| {
+                    trc::ImapEvent::Error
+                        .caused_by(trc::location!())
+                        .details("Saved search no longer exists.")
+                })?;
+            self.server
+                .saved_search_results(mailbox.account_id, cache, &search)
+                .await
+                .map(|document_ids| Some((document_ids, search.uid_validity)))
+        } else {
+            Ok(None)
+        }
+    }
+
     pub async fn synchronize_mailboxes(
         &self,
         return_changes: bool,
@@ -395,6 +558,20 @@ impl<T: SessionStream> SessionData<T> {
         item: Acl,
     ) -> trc::Result<bool> {
         let access_token = self.get_access_token().await?;
+
+        // Saved search mailboxes are read-only views over the account's messages,
+        // while scheduled messages can also be flagged and expunged to cancel them
+        let mailbox = MailboxId {
+            account_id,
+            mailbox_id: document_id,
+        };
+        if mailbox.is_virtual() {
+            return Ok(access_token.is_member(account_id)
+                && (matches!(item, Acl::Read | Acl::ReadItems)
+                    || (mailbox.is_scheduled()
+                        && matches!(item, Acl::ModifyItems | Acl::RemoveItems))));
+        }
+
         Ok(access_token.is_member(account_id)
             || self
                 .server
@@ -6,7 +6,7 @@
 
 use ahash::AHashMap;
 use common::listener::SessionStream;
-use email::cache::MessageCacheFetch;
+use email::{cache::MessageCacheFetch, mailbox::search::SavedSearchManager};
 use imap_proto::protocol::{Sequence, expunge, select::Exists};
 use jmap_proto::types::{collection::Collection, property::Property};
 use std::collections::BTreeMap;
@@ -36,20 +36,41 @@ impl<T: SessionStream> SessionData<T> {
         }
 
         // Obtain UID next and assign UIDs
-        let uid_map = cached_messages
-            .emails
-            .items
-            .iter()
-            .filter_map(|item| {
-                item.mailboxes.iter().find_map(|m| {
-                    if m.mailbox_id == mailbox.mailbox_id {
-                        Some((m.uid, item.document_id))
-                    } else {
-                        None
-                    }
+        let uid_map = if let Some((document_ids, uid_validity)) = self
+            .virtual_mailbox_results(mailbox, &cached_messages)
+            .await
+            .caused_by(trc::location!())?
+        {
+            // Virtual mailboxes keep their own UID assignments
+            self.server
+                .assign_virtual_uids(
+                    mailbox.account_id,
+                    mailbox.mailbox_id,
+                    uid_validity,
+                    &document_ids,
+                )
+                .await
+                .caused_by(trc::location!())?
+                .items
+                .into_iter()
+                .map(|item| (item.uid, item.document_id))
+                .collect::<BTreeMap<u32, u32>>()
+        } else {
+            cached_messages
+                .emails
+                .items
+                .iter()
+                .filter_map(|item| {
+                    item.mailboxes.iter().find_map(|m| {
+                        if m.mailbox_id == mailbox.mailbox_id {
+                            Some((m.uid, item.document_id))
+                        } else {
+                            None
+                        }
+                    })
                 })
-            })
-            .collect::<BTreeMap<u32, u32>>();
+                .collect::<BTreeMap<u32, u32>>()
+        };
         let mut uid_max = 0;
         let mut id_to_imap = AHashMap::with_capacity(uid_map.len());
         let mut uid_to_id = AHashMap::with_capacity(uid_map.len());
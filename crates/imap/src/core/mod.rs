@@ -14,7 +14,10 @@ use ahash::AHashMap;
 use common::{
     Inner, Server,
     auth::AccessToken,
-    listener::{ServerInstance, SessionStream, limiter::InFlight},
+    listener::{
+        ServerInstance, SessionStream,
+        limiter::{BandwidthLimiter, InFlight},
+    },
 };
 
 use imap_proto::{
@@ -70,6 +73,7 @@ pub struct SessionData<T: SessionStream> {
     pub stream_tx: Arc<tokio::sync::Mutex<WriteHalf<T>>>,
     pub state: AtomicU32,
     pub in_flight: Option<InFlight>,
+    pub bandwidth: Vec<Arc<BandwidthLimiter>>,
 }
 
 pub struct SelectedMailbox {
@@ -92,6 +96,10 @@ pub struct MailboxId {
     pub mailbox_id: u32,
 }
 
+pub use email::mailbox::VIRTUAL_MAILBOX_ID;
+pub const SCHEDULED_MAILBOX_ID: u32 = u32::MAX - 2;
+pub const SCHEDULED_UID_VALIDITY: u32 = 1;
+
 #[derive(Debug, Clone, Default)]
 pub struct Account {
     pub account_id: u32,
@@ -218,8 +226,27 @@ impl<T: SessionStream> SessionData<T> {
             state: self.state,
             in_flight: self.in_flight,
             access_token: self.access_token,
+            bandwidth: self.bandwidth,
+        }
+    }
+}
+
+impl MailboxId {
+    pub fn saved_search_id(&self) -> Option<u32> {
+        if self.is_virtual() && !self.is_scheduled() {
+            Some(self.mailbox_id & !VIRTUAL_MAILBOX_ID)
+        } else {
+            None
         }
     }
+
+    pub fn is_virtual(&self) -> bool {
+        self.mailbox_id & VIRTUAL_MAILBOX_ID != 0
+    }
+
+    pub fn is_scheduled(&self) -> bool {
+        self.mailbox_id == SCHEDULED_MAILBOX_ID
+    }
 }
 
 impl MailboxState {
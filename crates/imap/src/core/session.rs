@@ -7,9 +7,18 @@
 use std::sync::Arc;
 
 use common::{
+    auth::AccessToken,
     core::BuildServer,
-    listener::{SessionData, SessionManager, SessionResult, SessionStream, stream::NullIo},
+    expr::{
+        V_AUTHENTICATED_AS, V_LISTENER, V_PROTOCOL, V_REMOTE_IP, V_TLS, Variable,
+        functions::ResolveVariable,
+    },
+    listener::{
+        SessionData, SessionManager, SessionResult, SessionStream, limiter::BandwidthLimiter,
+        stream::NullIo,
+    },
 };
+use compact_str::ToCompactString;
 use imap_proto::{
     protocol::{ProtocolVersion, SerializeResponse},
     receiver::Receiver,
@@ -259,6 +268,74 @@ impl<T: SessionStream> Session<T> {
     }
 }
 
+impl<T: SessionStream> Session<T> {
+    pub async fn bandwidth_limiters(
+        &self,
+        access_token: &AccessToken,
+    ) -> Vec<Arc<BandwidthLimiter>> {
+        let config = &self.server.core.imap;
+        let resolver = BandwidthResolver {
+            session: self,
+            access_token,
+        };
+        let mut limiters = Vec::new();
+
+        if let Some(if_block) = &config.bandwidth_session
+            && let Some(rate) = self
+                .server
+                .eval_if::<u64, _>(if_block, &resolver, self.session_id)
+                .await
+                .filter(|rate| *rate > 0)
+        {
+            limiters.push(Arc::new(BandwidthLimiter::new(rate)));
+        }
+
+        // Account limiters are shared by all sessions of the same account
+        if let Some(if_block) = &config.bandwidth_account
+            && let Some(rate) = self
+                .server
+                .eval_if::<u64, _>(if_block, &resolver, self.session_id)
+                .await
+                .filter(|rate| *rate > 0)
+        {
+            let mut accounts = self.server.inner.data.imap_bandwidth.lock();
+            accounts.retain(|_, limiter| Arc::strong_count(limiter) > 1);
+            let limiter = accounts
+                .entry(access_token.primary_id())
+                .or_insert_with(|| Arc::new(BandwidthLimiter::new(rate)));
+            limiter.set_rate(rate);
+            limiters.push(limiter.clone());
+        }
+
+        limiters
+    }
+}
+
+struct BandwidthResolver<'x, T: SessionStream> {
+    session: &'x Session<T>,
+    access_token: &'x AccessToken,
+}
+
+impl<T: SessionStream> ResolveVariable for BandwidthResolver<'_, T> {
+    fn resolve_variable(&self, variable: u32) -> Variable<'_> {
+        match variable {
+            V_AUTHENTICATED_AS => self.access_token.name.as_str().into(),
+            V_LISTENER => self.session.instance.id.as_str().into(),
+            V_PROTOCOL => "imap".into(),
+            V_REMOTE_IP => self.session.remote_addr.to_compact_string().into(),
+            V_TLS => self.session.is_tls.into(),
+            _ => Variable::default(),
+        }
+    }
+
+    fn resolve_global(&self, _: &str) -> Variable<'_> {
+        Variable::Integer(0)
+    }
+}
+
+// Maximum number of bytes written between bandwidth checks
+const BANDWIDTH_CHUNK_SIZE: usize = 16 * 1024;
+
 impl<T: SessionStream> super::SessionData<T> {
     pub async fn write_bytes(&self, bytes: impl AsRef<[u8]>) -> trc::Result<()> {
         let bytes = bytes.as_ref();
@@ -271,7 +348,29 @@ impl<T: SessionStream> super::SessionData<T> {
         );
 
         let mut stream = self.stream_tx.lock().await;
-        if let Err(err) = stream.write_all(bytes.as_ref()).await {
+        let result = if self.bandwidth.is_empty() {
+            stream.write_all(bytes).await
+        } else {
+            let mut result = Ok(());
+            for chunk in bytes.chunks(BANDWIDTH_CHUNK_SIZE) {
+                if let Some(delay) = self
+                    .bandwidth
+                    .iter()
+                    .filter_map(|limiter| limiter.reserve(chunk.len()))
+                    .max()
+                {
+                    let _ = stream.flush().await;
+                    tokio::time::sleep(delay).await;
+                }
+                result = stream.write_all(chunk).await;
+                if result.is_err() {
+                    break;
+                }
+            }
+            result
+        };
+
+        if let Err(err) = result {
             Err(trc::NetworkEvent::WriteError
                 .into_err()
                 .reason(err)
@@ -55,6 +55,7 @@ impl<T: SessionStream> Session<T> {
                 .append_messages(arguments, selected_mailbox, mailbox, is_qresync, op_start)
                 .await?
                 .into_bytes();
+            data.write_quota_alert(mailbox.account_id).await?;
 
             data.write_bytes(response).await
         })
@@ -146,6 +147,7 @@ impl<T: SessionStream> SessionData<T> {
                 .broadcast_state_change(
                     StateChange::new(account_id, change_id)
                         .with_change(DataType::Email)
+                        .with_change(DataType::Quota)
                         .with_change(DataType::Mailbox)
                         .with_change(DataType::Thread),
                 )
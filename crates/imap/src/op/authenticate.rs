@@ -9,13 +9,14 @@ use common::{
         AuthRequest,
         sasl::{sasl_decode_challenge_oauth, sasl_decode_challenge_plain},
     },
+    config::server::ServerProtocol,
     listener::{SessionStream, limiter::LimiterResult},
 };
 
 use directory::Permission;
 use imap_proto::{
     Command, ResponseCode, StatusResponse,
-    protocol::{authenticate::Mechanism, capability::Capability},
+    protocol::authenticate::Mechanism,
     receiver::{self, Request},
 };
 use mail_parser::decoders::base64::base64_decode;
@@ -115,6 +116,20 @@ impl<T: SessionStream> Session<T> {
             }
             LimiterResult::Disabled => None,
         };
+        let in_flight = self
+            .server
+            .limit_account_connections(
+                ServerProtocol::Imap,
+                access_token.primary_id(),
+                in_flight,
+                self.session_id,
+            )
+            .map_err(|err| err.id(tag.clone()))?;
+        self.server
+            .inner
+            .data
+            .sessions
+            .set_account_id(self.session_id, access_token.primary_id());
 
         // Create session
         self.state = State::Authenticated {
@@ -127,10 +142,7 @@ impl<T: SessionStream> Session<T> {
         self.write_bytes(
             StatusResponse::ok("Authentication successful")
                 .with_code(ResponseCode::Capability {
-                    capabilities: Capability::all_capabilities(
-                        true,
-                        !self.is_tls && self.instance.acceptor.is_tls(),
-                    ),
+                    capabilities: self.capabilities(),
                 })
                 .with_tag(tag)
                 .into_bytes(),
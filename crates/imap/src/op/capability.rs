@@ -6,7 +6,7 @@
 
 use std::time::Instant;
 
-use crate::core::Session;
+use crate::core::{Session, State};
 use common::listener::SessionStream;
 use directory::Permission;
 use imap_proto::{
@@ -37,10 +37,7 @@ impl<T: SessionStream> Session<T> {
                 .with_tag(request.tag)
                 .serialize(
                     Response {
-                        capabilities: Capability::all_capabilities(
-                            self.state.is_authenticated(),
-                            !self.is_tls && self.instance.acceptor.is_tls(),
-                        ),
+                        capabilities: self.capabilities(),
                     }
                     .serialize(),
                 ),
@@ -48,6 +45,24 @@ impl<T: SessionStream> Session<T> {
         .await
     }
 
+    pub fn capabilities(&self) -> Vec<Capability> {
+        let mut capabilities = Capability::all_capabilities(
+            self.state.is_authenticated(),
+            !self.is_tls && self.instance.acceptor.is_tls(),
+        );
+
+        // SETQUOTA is only available to administrators
+        if let State::Authenticated { data } | State::Selected { data, .. } = &self.state
+            && data
+                .access_token
+                .has_permission(Permission::PrincipalUpdate)
+        {
+            capabilities.push(Capability::QuotaSet);
+        }
+
+        capabilities
+    }
+
     pub async fn handle_id(&mut self, request: Request<Command>) -> trc::Result<()> {
         // Validate access
         self.assert_has_permission(Permission::ImapId)?;
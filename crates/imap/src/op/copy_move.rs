@@ -151,6 +151,15 @@ impl<T: SessionStream> SessionData<T> {
                 .await;
         }
 
+        // Scheduled messages stay where they are, expunging them cancels the submission
+        if is_move && src_mailbox.id.is_scheduled() {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Scheduled messages cannot be moved, delete them to cancel sending.")
+                .code(ResponseCode::Cannot)
+                .id(arguments.tag));
+        }
+
         // Verify that the user can delete messages from the source mailbox.
         if is_move
             && !self
@@ -441,6 +450,7 @@ impl<T: SessionStream> SessionData<T> {
                     .broadcast_state_change(
                         StateChange::new(dest_account_id, change_id)
                             .with_change(DataType::Email)
+                            .with_change(DataType::Quota)
                             .with_change(DataType::Thread)
                             .with_change(DataType::Mailbox),
                     )
@@ -7,7 +7,7 @@
 use std::time::Instant;
 
 use crate::{
-    core::{Session, SessionData},
+    core::{Session, SessionData, VIRTUAL_MAILBOX_ID},
     spawn_op,
 };
 use common::listener::SessionStream;
@@ -67,6 +67,13 @@ impl<T: SessionStream> SessionData<T> {
                     .code(ResponseCode::TryCreate)
                     .id(arguments.tag));
             };
+        if mailbox_id & VIRTUAL_MAILBOX_ID != 0 {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Virtual mailboxes cannot be deleted.")
+                .code(ResponseCode::Cannot)
+                .id(arguments.tag));
+        }
 
         // Delete message
         let access_token = self
@@ -7,12 +7,15 @@
 use super::{ImapContext, ToModSeq};
 use crate::core::{ImapId, SavedSearch, SelectedMailbox, Session, SessionData};
 use ahash::AHashMap;
-use common::{listener::SessionStream, storage::index::ObjectIndexBuilder};
+use common::{
+    config::smtp::queue::QueueName, listener::SessionStream, storage::index::ObjectIndexBuilder,
+};
 use directory::Permission;
 use email::{
     cache::{MessageCacheFetch, email::MessageCacheAccess},
     mailbox::TOMBSTONE_ID,
-    message::metadata::MessageData,
+    message::{delete::tombstone_mailbox_path, metadata::MessageData},
+    submission::{EmailSubmission, UndoStatus, scheduled::ScheduledSubmissions},
 };
 use imap_proto::{
     Command, ResponseCode, ResponseType, StatusResponse,
@@ -25,6 +28,7 @@ use jmap_proto::types::{
     keyword::Keyword,
     property::Property,
 };
+use smtp::queue::spool::SmtpSpool;
 use std::{sync::Arc, time::Instant};
 use store::{
     roaring::RoaringBitmap,
@@ -118,6 +122,12 @@ impl<T: SessionStream> SessionData<T> {
         sequence: Option<AHashMap<u32, ImapId>>,
         op_start: Instant,
     ) -> trc::Result<()> {
+        if mailbox.id.is_scheduled() {
+            return self
+                .cancel_scheduled_submissions(mailbox, sequence, op_start)
+                .await;
+        }
+
         // Obtain message ids
         let account_id = mailbox.id.account_id;
         let mut deleted_ids = RoaringBitmap::from_iter(
@@ -160,6 +170,127 @@ impl<T: SessionStream> SessionData<T> {
         Ok(())
     }
 
+    async fn cancel_scheduled_submissions(
+        &self,
+        mailbox: Arc<SelectedMailbox>,
+        sequence: Option<AHashMap<u32, ImapId>>,
+        op_start: Instant,
+    ) -> trc::Result<()> {
+        // Expunging a scheduled message cancels its submission while keeping the message
+        let account_id = mailbox.id.account_id;
+        let cache = self
+            .server
+            .get_cached_messages(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let submissions = self
+            .server
+            .scheduled_submissions(account_id)
+            .await
+            .caused_by(trc::location!())?
+            .into_iter()
+            .filter(|submission| {
+                cache
+                    .email_by_id(&submission.email_id)
+                    .is_some_and(|item| cache.has_keyword(item, &Keyword::Deleted))
+                    && sequence
+                        .as_ref()
+                        .is_none_or(|sequence| sequence.contains_key(&submission.email_id))
+            })
+            .collect::<Vec<_>>();
+
+        let mut batch = BatchBuilder::new();
+        let mut canceled_ids = RoaringBitmap::new();
+        batch.with_account_id(account_id);
+        for submission in submissions {
+            // Messages no longer in the queue have already been released for delivery
+            let Some(queue_message) = self
+                .server
+                .read_message(submission.queue_id, QueueName::default())
+                .await
+            else {
+                continue;
+            };
+            queue_message.remove(&self.server, None).await;
+            canceled_ids.insert(submission.email_id);
+
+            if let Some(submission_) = self
+                .server
+                .get_archive(
+                    account_id,
+                    Collection::EmailSubmission,
+                    submission.document_id,
+                )
+                .await
+                .caused_by(trc::location!())?
+            {
+                let submission_ = submission_
+                    .to_unarchived::<EmailSubmission>()
+                    .caused_by(trc::location!())?;
+                let mut new_submission = submission_
+                    .deserialize::<EmailSubmission>()
+                    .caused_by(trc::location!())?;
+                new_submission.undo_status = UndoStatus::Canceled;
+                batch
+                    .with_collection(Collection::EmailSubmission)
+                    .update_document(submission.document_id)
+                    .custom(
+                        ObjectIndexBuilder::new()
+                            .with_current(submission_)
+                            .with_changes(new_submission),
+                    )
+                    .caused_by(trc::location!())?
+                    .commit_point();
+            }
+
+            if let Some(metadata_) = self
+                .server
+                .get_archive(account_id, Collection::Email, submission.email_id)
+                .await
+                .caused_by(trc::location!())?
+            {
+                let metadata = metadata_
+                    .to_unarchived::<MessageData>()
+                    .caused_by(trc::location!())?;
+                let mut new_metadata = metadata
+                    .deserialize::<MessageData>()
+                    .caused_by(trc::location!())?;
+                new_metadata.remove_keyword(&Keyword::Deleted);
+                batch
+                    .with_collection(Collection::Email)
+                    .update_document(submission.email_id)
+                    .custom(
+                        ObjectIndexBuilder::new()
+                            .with_current(metadata)
+                            .with_changes(new_metadata),
+                    )
+                    .caused_by(trc::location!())?
+                    .commit_point();
+            }
+        }
+
+        trc::event!(
+            Imap(trc::ImapEvent::Expunge),
+            SpanId = self.session_id,
+            AccountId = account_id,
+            MailboxId = mailbox.id.mailbox_id,
+            DocumentId = canceled_ids
+                .iter()
+                .map(trc::Value::from)
+                .collect::<Vec<_>>(),
+            Elapsed = op_start.elapsed()
+        );
+
+        if !batch.is_empty() {
+            self.server
+                .commit_batch(batch)
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+
     pub async fn email_untag_or_delete(
         &self,
         account_id: u32,
@@ -170,6 +301,11 @@ impl<T: SessionStream> SessionData<T> {
         batch
             .with_account_id(account_id)
             .with_collection(Collection::Email);
+        let cache = self
+            .server
+            .get_cached_messages(account_id)
+            .await
+            .caused_by(trc::location!())?;
 
         self.server
             .get_archives(
@@ -194,8 +330,9 @@ impl<T: SessionStream> SessionData<T> {
                             batch
                                 .custom(ObjectIndexBuilder::<_, ()>::new().with_current(metadata))
                                 .caused_by(trc::location!())?
-                                .tag(Property::MailboxIds, TagValue::Id(TOMBSTONE_ID))
-                                .commit_point();
+                                .tag(Property::MailboxIds, TagValue::Id(TOMBSTONE_ID));
+                            tombstone_mailbox_path(&self.server, batch, &cache, mailbox_id);
+                            batch.commit_point();
                         } else {
                             // Untag message from this mailbox and remove Deleted flag
                             let mut new_metadata = metadata
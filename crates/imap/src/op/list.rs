@@ -7,7 +7,7 @@
 use std::time::Instant;
 
 use crate::{
-    core::{Session, SessionData},
+    core::{SCHEDULED_MAILBOX_ID, Session, SessionData, VIRTUAL_MAILBOX_ID},
     spawn_op,
 };
 use common::listener::SessionStream;
@@ -207,6 +207,21 @@ impl<T: SessionStream> SessionData<T> {
                         tags: vec![],
                     });
                 }
+            } else if !filter_subscribed
+                && account.mailbox_names.values().any(|mailbox_id| {
+                    mailbox_id & VIRTUAL_MAILBOX_ID != 0 && *mailbox_id != SCHEDULED_MAILBOX_ID
+                })
+                && matches_pattern(&patterns, &self.server.core.jmap.saved_search_folder)
+            {
+                list_items.push(ListItem {
+                    mailbox_name: self.server.core.jmap.saved_search_folder.as_str().into(),
+                    attributes: if include_children {
+                        vec![Attribute::HasChildren, Attribute::NoSelect]
+                    } else {
+                        vec![Attribute::NoSelect]
+                    },
+                    tags: vec![],
+                });
             }
 
             for (mailbox_name, mailbox_id) in &account.mailbox_names {
@@ -18,16 +18,24 @@ use crate::{
     spawn_op,
 };
 use common::listener::SessionStream;
-use directory::Permission;
+use directory::{
+    Permission,
+    backend::internal::{
+        PrincipalField, PrincipalUpdate, PrincipalValue,
+        manage::{ManageDirectory, UpdatePrincipal},
+    },
+};
+use email::cache::MessageCacheFetch;
 use imap_proto::{
     Command, ResponseCode, StatusResponse,
     protocol::{
         ImapResponse,
         capability::QuotaResourceName,
-        quota::{Arguments, QuotaItem, QuotaResource, Response},
+        quota::{Arguments, QuotaItem, QuotaResource, Response, SetArguments},
     },
     receiver::Request,
 };
+use trc::AddContext;
 
 impl<T: SessionStream> Session<T> {
     pub async fn handle_get_quota(&mut self, request: Request<Command>) -> trc::Result<()> {
@@ -78,6 +86,28 @@ impl<T: SessionStream> Session<T> {
     }
 }
 
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_set_quota(&mut self, request: Request<Command>) -> trc::Result<()> {
+        let data = self.state.session_data();
+
+        spawn_op!(data, {
+            match request.parse_set_quota() {
+                Ok(argument) => match data.set_quota(argument).await {
+                    Ok(response) => {
+                        data.write_bytes(response).await?;
+                    }
+                    Err(error) => {
+                        data.write_error(error).await?;
+                    }
+                },
+                Err(err) => data.write_error(err).await?,
+            }
+
+            Ok(())
+        })
+    }
+}
+
 impl<T: SessionStream> SessionData<T> {
     pub async fn get_quota(&self, arguments: Arguments) -> trc::Result<Vec<u8>> {
         let op_start = Instant::now();
@@ -106,9 +136,8 @@ impl<T: SessionStream> SessionData<T> {
             .get_access_token(account_id)
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
-        let used_quota = self
-            .server
-            .get_used_quota(account_id)
+        let resources = self
+            .quota_resources(account_id, access_token.quota, access_token.message_quota)
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
 
@@ -116,10 +145,13 @@ impl<T: SessionStream> SessionData<T> {
             Imap(trc::ImapEvent::GetQuota),
             SpanId = self.session_id,
             Id = arguments.name.clone(),
-            Details = vec![
-                trc::Value::from(used_quota),
-                trc::Value::from(access_token.quota)
-            ],
+            Details = resources
+                .iter()
+                .flat_map(|resource| [
+                    trc::Value::from(resource.used),
+                    trc::Value::from(resource.total)
+                ])
+                .collect::<Vec<_>>(),
             Elapsed = op_start.elapsed()
         );
 
@@ -128,11 +160,7 @@ impl<T: SessionStream> SessionData<T> {
             quota_root_items: vec![],
             quota_items: vec![QuotaItem {
                 name: arguments.name,
-                resources: vec![QuotaResource {
-                    resource: QuotaResourceName::Storage,
-                    total: access_token.quota,
-                    used: used_quota as u64,
-                }],
+                resources,
             }],
         };
 
@@ -166,9 +194,8 @@ impl<T: SessionStream> SessionData<T> {
             .get_access_token(account_id)
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
-        let used_quota = self
-            .server
-            .get_used_quota(account_id)
+        let resources = self
+            .quota_resources(account_id, access_token.quota, access_token.message_quota)
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
 
@@ -176,10 +203,13 @@ impl<T: SessionStream> SessionData<T> {
             Imap(trc::ImapEvent::GetQuota),
             SpanId = self.session_id,
             MailboxName = arguments.name.clone(),
-            Details = vec![
-                trc::Value::from(used_quota),
-                trc::Value::from(access_token.quota)
-            ],
+            Details = resources
+                .iter()
+                .flat_map(|resource| [
+                    trc::Value::from(resource.used),
+                    trc::Value::from(resource.total)
+                ])
+                .collect::<Vec<_>>(),
             Elapsed = op_start.elapsed()
         );
 
@@ -188,11 +218,7 @@ impl<T: SessionStream> SessionData<T> {
             quota_root_items: vec![arguments.name, format!("#{account_id}")],
             quota_items: vec![QuotaItem {
                 name: format!("#{account_id}"),
-                resources: vec![QuotaResource {
-                    resource: QuotaResourceName::Storage,
-                    total: access_token.quota,
-                    used: used_quota as u64,
-                }],
+                resources,
             }],
         };
 
@@ -200,4 +226,174 @@ impl<T: SessionStream> SessionData<T> {
             .with_tag(arguments.tag)
             .serialize(response.serialize()))
     }
+
+    pub async fn set_quota(&self, arguments: SetArguments) -> trc::Result<Vec<u8>> {
+        let op_start = Instant::now();
+
+        // Only administrators can change quotas
+        if !self
+            .access_token
+            .has_permission(Permission::PrincipalUpdate)
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("You do not have enough permissions to change quotas.")
+                .code(ResponseCode::NoPerm)
+                .id(arguments.tag));
+        }
+
+        // Validate quota root
+        let account_id: u32 = arguments
+            .name
+            .strip_prefix("#")
+            .and_then(|id| id.parse().ok())
+            .ok_or_else(|| {
+                trc::ImapEvent::Error
+                    .into_err()
+                    .details("Invalid quota root parameter.")
+                    .id(arguments.tag.to_string())
+            })?;
+
+        // Resources that are not listed have their limit removed
+        let mut quota = 0;
+        let mut message_quota = 0;
+        for (resource, limit) in &arguments.limits {
+            match resource {
+                QuotaResourceName::Storage => {
+                    quota = limit.saturating_mul(1024);
+                }
+                QuotaResourceName::Message => {
+                    message_quota = *limit;
+                }
+                _ => {
+                    return Err(trc::ImapEvent::Error
+                        .into_err()
+                        .details("Only the STORAGE and MESSAGE resource limits can be changed.")
+                        .code(ResponseCode::Cannot)
+                        .id(arguments.tag));
+                }
+            }
+        }
+
+        let changed_principals = self
+            .server
+            .core
+            .storage
+            .data
+            .update_principal(
+                UpdatePrincipal::by_id(account_id)
+                    .with_updates(vec![
+                        PrincipalUpdate::set(PrincipalField::Quota, PrincipalValue::Integer(quota)),
+                        PrincipalUpdate::set(
+                            PrincipalField::MessageQuota,
+                            PrincipalValue::Integer(message_quota),
+                        ),
+                    ])
+                    .with_tenant(self.access_token.tenant.map(|t| t.id)),
+            )
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+        self.server
+            .invalidate_principal_caches(changed_principals)
+            .await;
+
+        trc::event!(
+            Imap(trc::ImapEvent::SetQuota),
+            SpanId = self.session_id,
+            AccountId = account_id,
+            Limit = quota,
+            Elapsed = op_start.elapsed()
+        );
+
+        // Return the updated quota, accounts without a message limit fall back to the defaults
+        let message_quota = self
+            .server
+            .get_access_token(account_id)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?
+            .message_quota;
+        let resources = self
+            .quota_resources(account_id, quota, message_quota)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+        let response = Response {
+            quota_root_items: vec![],
+            quota_items: vec![QuotaItem {
+                name: arguments.name,
+                resources,
+            }],
+        };
+
+        Ok(StatusResponse::ok("SETQUOTA successful.")
+            .with_tag(arguments.tag)
+            .serialize(response.serialize()))
+    }
+
+    async fn quota_resources(
+        &self,
+        account_id: u32,
+        quota: u64,
+        message_quota: u64,
+    ) -> trc::Result<Vec<QuotaResource>> {
+        let mut resources = vec![QuotaResource {
+            resource: QuotaResourceName::Storage,
+            total: quota,
+            used: self
+                .server
+                .get_used_quota(account_id)
+                .await
+                .caused_by(trc::location!())? as u64,
+        }];
+
+        if message_quota != 0 {
+            resources.push(QuotaResource {
+                resource: QuotaResourceName::Message,
+                total: message_quota,
+                used: self
+                    .server
+                    .get_cached_messages(account_id)
+                    .await
+                    .caused_by(trc::location!())?
+                    .emails
+                    .items
+                    .len() as u64,
+            });
+        }
+
+        Ok(resources)
+    }
+}
+
+impl<T: SessionStream> SessionData<T> {
+    // Accounts above the soft limit receive an untagged ALERT
+    pub async fn write_quota_alert(&self, account_id: u32) -> trc::Result<()> {
+        let is_over_soft_quota = match self.server.get_access_token(account_id).await {
+            Ok(access_token) => {
+                self.server
+                    .is_over_soft_quota(account_id, access_token.quota, access_token.soft_quota)
+                    .await
+            }
+            Err(err) => Err(err),
+        };
+
+        match is_over_soft_quota {
+            Ok(true) => {
+                self.write_bytes(
+                    StatusResponse::ok("Mailbox is almost full, delete messages to free up space.")
+                        .with_code(ResponseCode::Alert)
+                        .into_bytes(),
+                )
+                .await
+            }
+            Ok(false) => Ok(()),
+            Err(err) => {
+                trc::error!(
+                    err.span_id(self.session_id)
+                        .account_id(account_id)
+                        .caused_by(trc::location!())
+                );
+                Ok(())
+            }
+        }
+    }
 }
@@ -7,7 +7,7 @@
 use std::time::Instant;
 
 use crate::{
-    core::{Session, SessionData},
+    core::{Session, SessionData, VIRTUAL_MAILBOX_ID},
     spawn_op,
 };
 use common::{listener::SessionStream, sharing::EffectiveAcl, storage::index::ObjectIndexBuilder};
@@ -82,6 +82,28 @@ impl<T: SessionStream> SessionData<T> {
                     .id(arguments.tag));
             }
         };
+        if mailbox_id & VIRTUAL_MAILBOX_ID != 0 {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Virtual mailboxes cannot be renamed.")
+                .code(ResponseCode::Cannot)
+                .id(arguments.tag));
+        }
+
+        // Provisioned system folders keep their name and location
+        if email::mailbox::is_protected_folder(&self.server.core.jmap, mailbox_id)
+            && !self
+                .get_access_token()
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?
+                .has_permission(Permission::DeleteSystemFolders)
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("System folders cannot be renamed.")
+                .code(ResponseCode::NoPerm)
+                .id(arguments.tag));
+        }
 
         // Obtain mailbox
         let mailbox_ = self
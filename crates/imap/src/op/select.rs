@@ -19,6 +19,7 @@ use imap_proto::{
 
 use crate::core::{SavedSearch, SelectedMailbox, Session, State};
 use common::listener::SessionStream;
+use email::cache::{MessageCacheFetch, email::MessageCacheAccess};
 use jmap_proto::types::id::Id;
 
 use super::{ImapContext, ToModSeq};
@@ -44,6 +45,9 @@ impl<T: SessionStream> Session<T> {
             .imap_ctx(&arguments.tag, trc::location!())?;
 
         if let Some(mailbox) = data.get_mailbox_by_name(&arguments.mailbox_name) {
+            // Saved searches can only be examined
+            let is_select = is_select && (!mailbox.is_virtual() || mailbox.is_scheduled());
+
             // Try obtaining the mailbox from the cache
             let state = data
                 .fetch_messages(&mailbox, None)
@@ -120,6 +124,20 @@ impl<T: SessionStream> Session<T> {
                 Elapsed = op_start.elapsed()
             );
 
+            // List the keywords that can be set and advertise \* only while
+            // new keywords can still be created
+            let cache = data
+                .server
+                .get_cached_messages(mailbox.id.account_id)
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?;
+            let keywords = cache
+                .permanent_keywords(mailbox.id.mailbox_id, &data.server.core.jmap)
+                .map(String::from)
+                .collect();
+            let can_create_keywords =
+                cache.can_create_keywords(mailbox.id.mailbox_id, &data.server.core.jmap);
+
             // Build response
             let response = Response {
                 mailbox: ListItem::new(arguments.mailbox_name),
@@ -132,10 +150,15 @@ impl<T: SessionStream> Session<T> {
                 is_rev2,
                 is_utf8,
                 highest_modseq,
+                keywords,
+                can_create_keywords,
                 mailbox_id: Id::from_parts(mailbox.id.account_id, mailbox.id.mailbox_id)
                     .to_string(),
             };
 
+            // Warn clients when the account is close to its quota
+            data.write_quota_alert(mailbox.id.account_id).await?;
+
             // Update state
             self.state = State::Selected { data, mailbox };
 
@@ -94,6 +94,7 @@ impl<T: SessionStream> SessionData<T> {
         } else {
             // Some IMAP clients will try to get the status of a mailbox with the NoSelect flag
             return if mailbox_name == self.server.core.jmap.shared_folder
+                || mailbox_name == self.server.core.jmap.saved_search_folder
                 || mailbox_name
                     .split_once('/')
                     .is_some_and(|(base_name, path)| {
@@ -216,25 +217,47 @@ impl<T: SessionStream> SessionData<T> {
                 .await
                 .caused_by(trc::location!())?;
 
+            let virtual_mailbox_ids = self
+                .virtual_mailbox_ids(&mailbox, &cache)
+                .await
+                .caused_by(trc::location!())?;
+
             for item in items_update {
                 let result = match item {
                     Status::DeletedStorage => self
                         .calculate_mailbox_size(
                             mailbox.account_id,
-                            &RoaringBitmap::from_iter(
-                                cache
-                                    .in_mailbox_with_keyword(mailbox.mailbox_id, &Keyword::Deleted)
-                                    .map(|x| x.document_id),
-                            ),
+                            &if let Some(document_ids) = &virtual_mailbox_ids {
+                                RoaringBitmap::from_iter(document_ids.iter().filter(
+                                    |document_id| {
+                                        cache.email_by_id(document_id).is_some_and(|item| {
+                                            cache.has_keyword(item, &Keyword::Deleted)
+                                        })
+                                    },
+                                ))
+                            } else {
+                                RoaringBitmap::from_iter(
+                                    cache
+                                        .in_mailbox_with_keyword(
+                                            mailbox.mailbox_id,
+                                            &Keyword::Deleted,
+                                        )
+                                        .map(|x| x.document_id),
+                                )
+                            },
                         )
                         .await
                         .caused_by(trc::location!())?,
                     Status::Size => self
                         .calculate_mailbox_size(
                             mailbox.account_id,
-                            &RoaringBitmap::from_iter(
-                                cache.in_mailbox(mailbox.mailbox_id).map(|x| x.document_id),
-                            ),
+                            &if let Some(document_ids) = &virtual_mailbox_ids {
+                                document_ids.clone()
+                            } else {
+                                RoaringBitmap::from_iter(
+                                    cache.in_mailbox(mailbox.mailbox_id).map(|x| x.document_id),
+                                )
+                            },
                         )
                         .await
                         .caused_by(trc::location!())?,
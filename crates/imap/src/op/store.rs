@@ -12,7 +12,12 @@ use crate::{
 use ahash::AHashSet;
 use common::{listener::SessionStream, storage::index::ObjectIndexBuilder};
 use directory::Permission;
-use email::message::{bayes::EmailBayesTrain, ingest::EmailIngest, metadata::MessageData};
+use email::{
+    cache::{MessageCacheFetch, email::MessageCacheAccess},
+    message::{
+        bayes::EmailBayesTrain, ingest::EmailIngest, mdn::queue_mdn_on_read, metadata::MessageData,
+    },
+};
 use imap_proto::{
     Command, ResponseCode, ResponseType, StatusResponse,
     protocol::{
@@ -188,6 +193,29 @@ impl<T: SessionStream> SessionData<T> {
             .iter()
             .map(|k| Keyword::from(k.clone()))
             .collect::<Vec<_>>();
+
+        // Enforce custom keyword limits
+        if matches!(arguments.operation, Operation::Set | Operation::Add)
+            && set_keywords.iter().any(|keyword| keyword.id().is_err())
+            && self
+                .server
+                .get_cached_messages(account_id)
+                .await
+                .imap_ctx(response.tag.as_ref().unwrap(), trc::location!())?
+                .exceeds_keyword_limits(
+                    &set_keywords,
+                    &[mailbox.id.mailbox_id],
+                    &self.server.core.jmap,
+                )
+                .is_some()
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("The maximum number of custom keywords has been reached.")
+                .id(response.tag.unwrap_or_default())
+                .code(ResponseCode::Limit)
+                .caused_by(trc::location!()));
+        }
         let access_token = self
             .server
             .get_access_token(account_id)
@@ -196,6 +224,8 @@ impl<T: SessionStream> SessionData<T> {
         let mut changed_mailboxes = AHashSet::new();
         let can_spam_train = self.server.email_bayes_can_train(&access_token);
         let mut has_spam_train_tasks = false;
+        let mut has_mdn_tasks = false;
+        let can_send_mdn = self.server.core.jmap.mdn_auto_send && account_id == self.account_id;
         let mut batch = BatchBuilder::new();
 
         for (id, imap_id) in &ids {
@@ -292,7 +322,11 @@ impl<T: SessionStream> SessionData<T> {
             batch
                 .with_account_id(account_id)
                 .with_collection(Collection::Email)
-                .update_document(*id)
+                .update_document(*id);
+            if can_send_mdn && queue_mdn_on_read(&mut batch, data.inner, &new_data) {
+                has_mdn_tasks = true;
+            }
+            batch
                 .custom(
                     ObjectIndexBuilder::new()
                         .with_current(data)
@@ -346,8 +380,8 @@ impl<T: SessionStream> SessionData<T> {
             }
         }
 
-        // Trigger Bayes training
-        if has_spam_train_tasks {
+        // Trigger Bayes training and read receipts
+        if has_spam_train_tasks || has_mdn_tasks {
             self.server.notify_task_queue();
         }
 
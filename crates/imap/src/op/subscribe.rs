@@ -7,7 +7,7 @@
 use std::time::Instant;
 
 use crate::{
-    core::{Session, SessionData},
+    core::{Session, SessionData, VIRTUAL_MAILBOX_ID},
     spawn_op,
 };
 use common::{listener::SessionStream, storage::index::ObjectIndexBuilder};
@@ -72,6 +72,13 @@ impl<T: SessionStream> SessionData<T> {
                     .caused_by(trc::location!()));
             }
         };
+        if mailbox_id & VIRTUAL_MAILBOX_ID != 0 {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Virtual mailboxes are always subscribed.")
+                .code(ResponseCode::Cannot)
+                .id(tag));
+        }
 
         // Verify if mailbox is already subscribed/unsubscribed
         for account in self.mailboxes.lock().iter_mut() {
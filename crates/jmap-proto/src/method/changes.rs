@@ -53,6 +53,8 @@ pub enum RequestArguments {
     Identity,
     EmailSubmission,
     Quota,
+    AddressBook,
+    ContactCard,
 }
 
 impl JsonObjectParser for ChangesRequest {
@@ -68,6 +70,8 @@ impl JsonObjectParser for ChangesRequest {
                 MethodObject::Identity => RequestArguments::Identity,
                 MethodObject::EmailSubmission => RequestArguments::EmailSubmission,
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::AddressBook => RequestArguments::AddressBook,
+                MethodObject::ContactCard => RequestArguments::ContactCard,
                 _ => {
                     return Err(trc::JmapEvent::UnknownMethod
                         .into_err()
@@ -45,6 +45,9 @@ pub enum RequestArguments {
     Principal,
     Quota,
     Blob(blob::GetArguments),
+    AddressBook,
+    ContactCard,
+    AutocryptKey,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -80,6 +83,9 @@ impl JsonObjectParser for GetRequest<RequestArguments> {
                 MethodObject::Principal => RequestArguments::Principal,
                 MethodObject::Blob => RequestArguments::Blob(Default::default()),
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::AddressBook => RequestArguments::AddressBook,
+                MethodObject::ContactCard => RequestArguments::ContactCard,
+                MethodObject::AutocryptKey => RequestArguments::AutocryptKey,
                 _ => {
                     return Err(trc::JmapEvent::UnknownMethod
                         .into_err()
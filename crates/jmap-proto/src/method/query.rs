@@ -100,6 +100,9 @@ pub enum Filter {
     IsActive(bool),
     Scope(String),
     ResourceType(String),
+    InAddressBook(Id),
+    Uid(String),
+    InSavedSearch(Id),
     _T(String),
 
     And,
@@ -148,6 +151,7 @@ pub enum RequestArguments {
     SieveScript,
     Principal,
     Quota,
+    ContactCard,
 }
 
 impl JsonObjectParser for QueryRequest<RequestArguments> {
@@ -163,6 +167,7 @@ impl JsonObjectParser for QueryRequest<RequestArguments> {
                 MethodObject::SieveScript => RequestArguments::SieveScript,
                 MethodObject::Principal => RequestArguments::Principal,
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::ContactCard => RequestArguments::ContactCard,
                 _ => {
                     return Err(trc::JmapEvent::UnknownMethod
                         .into_err()
@@ -425,6 +430,15 @@ pub fn parse_filter(parser: &mut Parser) -> trc::Result<Vec<Filter>> {
                                 .next_token::<String>()?
                                 .unwrap_string("resourceType")?,
                         ),
+                        (0x006b_6f6f_4273_7365_7264_6441_6e69, _) => Filter::InAddressBook(
+                            parser.next_token::<Id>()?.unwrap_string("inAddressBook")?,
+                        ),
+                        (0x0064_6975, _) => {
+                            Filter::Uid(parser.next_token::<String>()?.unwrap_string("uid")?)
+                        }
+                        (0x0068_6372_6165_5364_6576_6153_6e69, _) => Filter::InSavedSearch(
+                            parser.next_token::<Id>()?.unwrap_string("inSavedSearch")?,
+                        ),
                         _ => {
                             if parser.is_eof || parser.skip_string() {
                                 let filter = Filter::_T(
@@ -629,6 +643,9 @@ impl Display for Filter {
             Filter::IsActive(_) => "isActive",
             Filter::ResourceType(_) => "resourceType",
             Filter::Scope(_) => "scope",
+            Filter::InAddressBook(_) => "inAddressBook",
+            Filter::Uid(_) => "uid",
+            Filter::InSavedSearch(_) => "inSavedSearch",
             Filter::_T(v) => v.as_str(),
             Filter::And => "and",
             Filter::Or => "or",
@@ -189,7 +189,8 @@ impl JsonObjectParser for Object<SetValue> {
                     | Property::ReceivedAt
                     | Property::Expires
                     | Property::FromDate
-                    | Property::ToDate => parser
+                    | Property::ToDate
+                    | Property::SnoozedUntil => parser
                         .next_token::<UTCDate>()?
                         .unwrap_string_or_null("")?
                         .map(|date| SetValue::Value(Value::Date(date)))
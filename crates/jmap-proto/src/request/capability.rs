@@ -72,6 +72,8 @@ pub enum Capability {
     Blob = 1 << 8,
     #[serde(rename(serialize = "urn:ietf:params:jmap:quota"))]
     Quota = 1 << 9,
+    #[serde(rename(serialize = "urn:stalwart:jmap:autocrypt"))]
+    Autocrypt = 1 << 10,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -322,7 +324,28 @@ impl JsonObjectParser for Capability {
     where
         Self: Sized,
     {
-        for ch in b"urn:ietf:params:jmap:" {
+        for ch in b"urn:" {
+            if parser
+                .next_unescaped()?
+                .ok_or_else(|| parser.error_capability())?
+                != *ch
+            {
+                return Err(parser.error_capability());
+            }
+        }
+
+        // Vendor specific capabilities
+        let is_vendor = match parser.next_unescaped()? {
+            Some(b'i') => false,
+            Some(b's') => true,
+            _ => return Err(parser.error_capability()),
+        };
+        let prefix: &[u8] = if is_vendor {
+            b"talwart:jmap:"
+        } else {
+            b"etf:params:jmap:"
+        };
+        for ch in prefix {
             if parser
                 .next_unescaped()?
                 .ok_or_else(|| parser.error_capability())?
@@ -333,6 +356,10 @@ impl JsonObjectParser for Capability {
         }
 
         match u128::parse(parser) {
+            Ok(key) if is_vendor => match key {
+                0x0074_7079_7263_6f74_7561 => Ok(Capability::Autocrypt),
+                _ => Err(parser.error_capability()),
+            },
             Ok(key) => match key {
                 0x6572_6f63 => Ok(Capability::Core),
                 0x6c69_616d => Ok(Capability::Mail),
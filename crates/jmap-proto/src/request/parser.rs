@@ -134,6 +134,9 @@ impl Request {
                                 | MethodObject::SieveScript
                                 | MethodObject::Principal
                                 | MethodObject::Quota
+                                | MethodObject::AddressBook
+                                | MethodObject::ContactCard
+                                | MethodObject::AutocryptKey
                                 | MethodObject::Blob,
                             ) => GetRequest::parse(parser).map(RequestMethod::Get),
                             (MethodFunction::Get, MethodObject::SearchSnippet) => {
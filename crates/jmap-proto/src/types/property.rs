@@ -119,6 +119,24 @@ pub enum Property {
     WarnLimit,
     SoftLimit,
     Scope,
+    AddressBookIds,
+    Uid,
+    Emails,
+    Phones,
+    IsDefault,
+    Full,
+    Address,
+    Number,
+    SavedSearches,
+    SnoozedUntil,
+    MdnSettings,
+    Forwarding,
+    DisposableAliases,
+    Autocrypt,
+    KeyData,
+    PreferEncrypt,
+    IsGossip,
+    LastSeen,
     Digest(DigestProperty),
     Data(DataProperty),
     _T(String),
@@ -335,6 +353,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x6c63 => Property::Acl,
             0x7365_7361_696c => Property::Aliases,
             0x7374_6e65_6d68_6361_7474 => Property::Attachments,
+            0x0073_6449_6b6f_6f42_7373_6572_6464 => Property::AddressBookIds,
             _ => return None,
         },
         b'b' => match hash {
@@ -362,6 +381,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
         },
         b'e' => match hash {
             0x6c69_616d => Property::Email,
+            0x0073_6c69_616d => Property::Emails,
             0x6449_6c69_616d => Property::EmailId,
             0x0073_6449_6c69_616d => Property::EmailIds,
             0x0065_706f_6c65_766e => Property::Envelope,
@@ -385,17 +405,21 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0064_4979_7469_746e_6564 => Property::IdentityId,
             0x6f54_796c_7065_526e => Property::InReplyTo,
             0x0065_7669_7463_4173 => Property::IsActive,
+            0x746c_7561_6665_4473 => Property::IsDefault,
             0x6465_6c62_616e_4573 => Property::IsEnabled,
+            0x0070_6973_736f_4773 => Property::IsGossip,
             0x0064_6562_6972_6373_6275_5373 => Property::IsSubscribed,
             _ => return None,
         },
         b'k' => match hash {
+            0x6174_6144_7965 => Property::KeyData,
             0x0073_7965 => Property::Keys,
             0x0073_6472_6f77_7965 => Property::Keywords,
             _ => return None,
         },
         b'l' => match hash {
             0x0065_6761_7567_6e61 => Property::Language,
+            0x006e_6565_5374_7361 => Property::LastSeen,
             0x006e_6f69_7461_636f => Property::Location,
             _ => return None,
         },
@@ -415,7 +439,9 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
         b'p' => match hash {
             0x0064_4974_6e65_7261 => Property::ParentId,
             0x0064_4974_7261 => Property::PartId,
+            0x0073_656e_6f68 => Property::Phones,
             0x6572_7574_6369 => Property::Picture,
+            0x7470_7972_636e_4572_6566_6572 => Property::PreferEncrypt,
             0x7765_6976_6572 => Property::Preview,
             _ => return None,
         },
@@ -435,6 +461,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0074_4164_6e65 => Property::SendAt,
             0x0072_6564_6e65 => Property::Sender,
             0x0074_4174_6e65 => Property::SentAt,
+            0x006c_6974_6e55_6465_7a6f_6f6e => Property::SnoozedUntil,
             0x0065_7a69 => Property::Size,
             0x7265_6472_4f74_726f => Property::SortOrder,
             0x7463_656a_6275 => Property::Subject,
@@ -455,6 +482,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             _ => return None,
         },
         b'u' => match hash {
+            0x6469 => Property::Uid,
             0x0073_7574_6174_536f_646e => Property::UndoStatus,
             0x0073_6c69_616d_4564_6165_726e => Property::UnreadEmails,
             0x7364_6165_7268_5464_6165_726e => Property::UnreadThreads,
@@ -883,6 +911,24 @@ impl Display for Property {
             Property::Scope => write!(f, "scope"),
             Property::WarnLimit => write!(f, "warnLimit"),
             Property::SoftLimit => write!(f, "softLimit"),
+            Property::AddressBookIds => write!(f, "addressBookIds"),
+            Property::Uid => write!(f, "uid"),
+            Property::Emails => write!(f, "emails"),
+            Property::Phones => write!(f, "phones"),
+            Property::IsDefault => write!(f, "isDefault"),
+            Property::Full => write!(f, "full"),
+            Property::Address => write!(f, "address"),
+            Property::Number => write!(f, "number"),
+            Property::SavedSearches => write!(f, "savedSearches"),
+            Property::SnoozedUntil => write!(f, "snoozedUntil"),
+            Property::MdnSettings => write!(f, "mdnSettings"),
+            Property::Forwarding => write!(f, "forwarding"),
+            Property::DisposableAliases => write!(f, "disposableAliases"),
+            Property::Autocrypt => write!(f, "autocrypt"),
+            Property::KeyData => write!(f, "keyData"),
+            Property::PreferEncrypt => write!(f, "preferEncrypt"),
+            Property::IsGossip => write!(f, "isGossip"),
+            Property::LastSeen => write!(f, "lastSeen"),
             Property::_T(s) => write!(f, "{s}"),
         }
     }
@@ -994,6 +1040,24 @@ impl Property {
             Property::WarnLimit => "warnLimit",
             Property::SoftLimit => "softLimit",
             Property::Scope => "scope",
+            Property::AddressBookIds => "addressBookIds",
+            Property::Uid => "uid",
+            Property::Emails => "emails",
+            Property::Phones => "phones",
+            Property::IsDefault => "isDefault",
+            Property::Full => "full",
+            Property::Address => "address",
+            Property::Number => "number",
+            Property::SavedSearches => "savedSearches",
+            Property::SnoozedUntil => "snoozedUntil",
+            Property::MdnSettings => "mdnSettings",
+            Property::Forwarding => "forwarding",
+            Property::DisposableAliases => "disposableAliases",
+            Property::Autocrypt => "autocrypt",
+            Property::KeyData => "keyData",
+            Property::PreferEncrypt => "preferEncrypt",
+            Property::IsGossip => "isGossip",
+            Property::LastSeen => "lastSeen",
             Property::Data(data) => match data {
                 DataProperty::AsText => "data:asText",
                 DataProperty::AsBase64 => "data:asBase64",
@@ -1180,6 +1244,24 @@ impl From<&Property> for u8 {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::AddressBookIds => 104,
+            Property::Uid => 105,
+            Property::Emails => 106,
+            Property::Phones => 107,
+            Property::IsDefault => 108,
+            Property::Full => 109,
+            Property::Address => 110,
+            Property::Number => 111,
+            Property::SavedSearches => 112,
+            Property::SnoozedUntil => 113,
+            Property::MdnSettings => 114,
+            Property::Forwarding => 115,
+            Property::DisposableAliases => 116,
+            Property::Autocrypt => 117,
+            Property::KeyData => 118,
+            Property::PreferEncrypt => 119,
+            Property::IsGossip => 120,
+            Property::LastSeen => 121,
             Property::Digest(_) | Property::Data(_) => unreachable!("invalid property"),
         }
     }
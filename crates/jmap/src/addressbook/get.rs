@@ -0,0 +1,128 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::contact::visible_address_books;
+use common::{Server, auth::AccessToken};
+use groupware::{cache::GroupwareCache, contact::AddressBook};
+use jmap_proto::{
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    types::{
+        acl::Acl,
+        collection::{Collection, SyncCollection},
+        property::Property,
+        state::State,
+        value::{Object, Value},
+    },
+};
+use std::future::Future;
+use trc::AddContext;
+
+pub trait AddressBookGet: Sync + Send {
+    fn address_book_get(
+        &self,
+        request: GetRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<GetResponse>> + Send;
+}
+
+impl AddressBookGet for Server {
+    async fn address_book_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> trc::Result<GetResponse> {
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = request.unwrap_properties(&[
+            Property::Id,
+            Property::Name,
+            Property::Description,
+            Property::SortOrder,
+            Property::IsDefault,
+            Property::IsSubscribed,
+        ]);
+        let account_id = request.account_id.document_id();
+        let resources = self
+            .fetch_dav_resources(access_token, account_id, SyncCollection::AddressBook)
+            .await
+            .caused_by(trc::location!())?;
+        let book_ids = visible_address_books(&resources, access_token, account_id, Acl::Read);
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            book_ids
+                .iter()
+                .take(self.core.jmap.get_max_objects)
+                .map(Into::into)
+                .collect::<Vec<_>>()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: State::from(resources.container_change_id).into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            // Obtain the address book object
+            let document_id = id.document_id();
+            if !book_ids.contains(document_id) {
+                response.not_found.push(id.into());
+                continue;
+            }
+            let book_ = if let Some(book) = self
+                .get_archive(account_id, Collection::AddressBook, document_id)
+                .await?
+            {
+                book
+            } else {
+                response.not_found.push(id.into());
+                continue;
+            };
+            let book = book_
+                .unarchive::<AddressBook>()
+                .caused_by(trc::location!())?;
+            let mut result = Object::with_capacity(properties.len());
+            for property in &properties {
+                match property {
+                    Property::Id => {
+                        result.append(Property::Id, Value::Id(id));
+                    }
+                    Property::Name => {
+                        result.append(
+                            Property::Name,
+                            Value::from(book.display_name.as_ref().unwrap_or(&book.name)),
+                        );
+                    }
+                    Property::Description => {
+                        result.append(Property::Description, Value::from(&book.description));
+                    }
+                    Property::SortOrder => {
+                        result.append(Property::SortOrder, Value::from(&book.sort_order));
+                    }
+                    Property::IsDefault => {
+                        result.append(Property::IsDefault, Value::Bool(book.is_default));
+                    }
+                    Property::IsSubscribed => {
+                        result.append(
+                            Property::IsSubscribed,
+                            Value::Bool(
+                                book.subscribers
+                                    .iter()
+                                    .any(|id| u32::from(*id) == access_token.primary_id),
+                            ),
+                        );
+                    }
+                    property => {
+                        result.append(property.clone(), Value::Null);
+                    }
+                }
+            }
+            response.list.push(result);
+        }
+
+        Ok(response)
+    }
+}
@@ -71,7 +71,10 @@ impl ToRequestError for trc::Error {
                 trc::LimitEvent::SizeRequest => RequestError::limit(RequestLimitError::SizeRequest),
                 trc::LimitEvent::SizeUpload => RequestError::limit(RequestLimitError::SizeUpload),
                 trc::LimitEvent::CallsIn => RequestError::limit(RequestLimitError::CallsIn),
-                trc::LimitEvent::ConcurrentRequest | trc::LimitEvent::ConcurrentConnection => {
+                trc::LimitEvent::ConcurrentRequest
+                | trc::LimitEvent::ConcurrentConnection
+                | trc::LimitEvent::AccountConnections
+                | trc::LimitEvent::IpConnections => {
                     RequestError::limit(RequestLimitError::ConcurrentRequest)
                 }
                 trc::LimitEvent::ConcurrentUpload => {
@@ -88,6 +91,9 @@ impl ToRequestError for trc::Error {
                         .unwrap_or_default() as usize,
                 ),
                 trc::LimitEvent::TooManyRequests => RequestError::too_many_requests(),
+                trc::LimitEvent::QuotaWarningSent | trc::LimitEvent::QuotaWarningFailed => {
+                    RequestError::internal_server_error()
+                }
             },
             trc::EventType::Auth(cause) => match cause {
                 trc::AuthEvent::MissingTotp => {
@@ -101,8 +107,14 @@ impl ToRequestError for trc::Error {
                 | trc::SecurityEvent::ScanBan
                 | trc::SecurityEvent::AbuseBan
                 | trc::SecurityEvent::LoiterBan
-                | trc::SecurityEvent::IpBlocked => RequestError::too_many_auth_attempts(),
+                | trc::SecurityEvent::IpBlocked
+                | trc::SecurityEvent::SpamTrapBan
+                | trc::SecurityEvent::AccountLocked => RequestError::too_many_auth_attempts(),
                 trc::SecurityEvent::Unauthorized => RequestError::forbidden(),
+                trc::SecurityEvent::LegalHoldSet
+                | trc::SecurityEvent::LegalHoldReleased
+                | trc::SecurityEvent::MessageRedacted
+                | trc::SecurityEvent::AccountUnlocked => RequestError::internal_server_error(),
             },
             trc::EventType::Resource(cause) => match cause {
                 trc::ResourceEvent::NotFound => RequestError::not_found(),
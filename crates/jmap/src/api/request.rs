@@ -20,8 +20,11 @@ use jmap_proto::{
 use trc::JmapEvent;
 
 use crate::{
+    addressbook::get::AddressBookGet,
+    autocrypt::get::AutocryptKeyGet,
     blob::{copy::BlobCopy, get::BlobOperations, upload::BlobUpload},
     changes::{get::ChangesLookup, query::QueryChanges},
+    contact::{get::ContactCardGet, query::ContactCardQuery},
     email::{
         copy::JmapEmailCopy, get::EmailGet, import::EmailImport, parse::EmailParse,
         query::EmailQuery, set::EmailSet, snippet::EmailSearchSnippet,
@@ -221,6 +224,21 @@ impl RequestHandler for Server {
                         .await?
                         .into()
                 }
+                get::RequestArguments::AddressBook => {
+                    access_token.assert_has_access(req.account_id, Collection::AddressBook)?;
+
+                    self.address_book_get(req, access_token).await?.into()
+                }
+                get::RequestArguments::ContactCard => {
+                    access_token.assert_has_access(req.account_id, Collection::ContactCard)?;
+
+                    self.contact_card_get(req, access_token).await?.into()
+                }
+                get::RequestArguments::AutocryptKey => {
+                    access_token.assert_is_member(req.account_id)?;
+
+                    self.autocrypt_key_get(req).await?.into()
+                }
             },
             RequestMethod::Query(mut req) => match req.take_arguments() {
                 query::RequestArguments::Email(arguments) => {
@@ -255,6 +273,11 @@ impl RequestHandler for Server {
 
                     self.quota_query(req, access_token).await?.into()
                 }
+                query::RequestArguments::ContactCard => {
+                    access_token.assert_has_access(req.account_id, Collection::ContactCard)?;
+
+                    self.contact_card_query(req, access_token).await?.into()
+                }
             },
             RequestMethod::Set(mut req) => match req.take_arguments() {
                 set::RequestArguments::Email => {
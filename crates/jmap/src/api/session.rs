@@ -68,7 +68,12 @@ impl SessionHandler for Server {
                     .unwrap_or_else(|| Id::from(*id).to_string()),
                 is_personal,
                 is_readonly,
-                Some(&[Capability::Mail, Capability::Quota, Capability::Blob]),
+                Some(&[
+                    Capability::Mail,
+                    Capability::Quota,
+                    Capability::Blob,
+                    Capability::Contacts,
+                ]),
                 &self.core.jmap.capabilities.account,
             );
         }
@@ -0,0 +1,103 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use email::message::autocrypt::AutocryptManager;
+use jmap_proto::{
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    types::{
+        date::UTCDate,
+        id::Id,
+        property::Property,
+        state::State,
+        value::{Object, Value},
+    },
+};
+use std::future::Future;
+use trc::AddContext;
+
+pub trait AutocryptKeyGet: Sync + Send {
+    fn autocrypt_key_get(
+        &self,
+        request: GetRequest<RequestArguments>,
+    ) -> impl Future<Output = trc::Result<GetResponse>> + Send;
+}
+
+impl AutocryptKeyGet for Server {
+    async fn autocrypt_key_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+    ) -> trc::Result<GetResponse> {
+        if !self.core.jmap.autocrypt_enable {
+            return Err(trc::JmapEvent::UnknownMethod
+                .into_err()
+                .details("Autocrypt support is disabled."));
+        }
+
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = request.unwrap_properties(&[
+            Property::Id,
+            Property::Email,
+            Property::KeyData,
+            Property::PreferEncrypt,
+            Property::IsGossip,
+            Property::LastSeen,
+        ]);
+        let account_id = request.account_id.document_id();
+        let peers = self
+            .get_autocrypt_peers(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            peers
+                .items
+                .iter()
+                .take(self.core.jmap.get_max_objects)
+                .map(|peer| Id::from(peer.id))
+                .collect()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: State::Exact(peers.state()).into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            let document_id = id.document_id();
+            let Some(peer) = peers.items.iter().find(|peer| peer.id == document_id) else {
+                response.not_found.push(id.into());
+                continue;
+            };
+
+            let mut key_data = if properties.contains(&Property::KeyData) {
+                self.get_autocrypt_key(account_id, peer)
+                    .await
+                    .caused_by(trc::location!())?
+            } else {
+                None
+            };
+            let mut result = Object::with_capacity(properties.len());
+            for property in &properties {
+                let value = match property {
+                    Property::Id => Value::Id(id),
+                    Property::Email => peer.address.clone().into(),
+                    Property::KeyData => key_data.take().map_or(Value::Null, Value::from),
+                    Property::PreferEncrypt => peer.prefer_encrypt.into(),
+                    Property::IsGossip => peer.is_gossip.into(),
+                    Property::LastSeen => Value::Date(UTCDate::from(peer.last_seen)),
+                    _ => Value::Null,
+                };
+                result.append(property.clone(), value);
+            }
+            response.list.push(result);
+        }
+
+        Ok(response)
+    }
+}
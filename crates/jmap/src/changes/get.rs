@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use crate::quota::changes::QuotaChanges;
 use common::{Server, auth::AccessToken};
 use jmap_proto::{
     method::changes::{ChangesRequest, ChangesResponse, RequestArguments},
@@ -59,7 +60,17 @@ impl ChangesLookup for Server {
             RequestArguments::Quota => {
                 access_token.assert_is_member(request.account_id)?;
 
-                return Err(trc::JmapEvent::CannotCalculateChanges.into_err());
+                return self.quota_changes(request, access_token).await;
+            }
+            RequestArguments::AddressBook => {
+                access_token.assert_has_access(request.account_id, Collection::AddressBook)?;
+
+                (SyncCollection::AddressBook, true)
+            }
+            RequestArguments::ContactCard => {
+                access_token.assert_has_access(request.account_id, Collection::ContactCard)?;
+
+                (SyncCollection::AddressBook, false)
             }
         };
 
@@ -13,8 +13,8 @@ use jmap_proto::method::{
 use std::future::Future;
 
 use crate::{
-    email::query::EmailQuery, mailbox::query::MailboxQuery, quota::query::QuotaQuery,
-    submission::query::EmailSubmissionQuery,
+    contact::query::ContactCardQuery, email::query::EmailQuery, mailbox::query::MailboxQuery,
+    quota::query::QuotaQuery, submission::query::EmailSubmissionQuery,
 };
 
 use super::get::ChangesLookup;
@@ -47,6 +47,9 @@ impl QueryChanges for Server {
                             changes::RequestArguments::EmailSubmission
                         }
                         query::RequestArguments::Quota => changes::RequestArguments::Quota,
+                        query::RequestArguments::ContactCard => {
+                            changes::RequestArguments::ContactCard
+                        }
                         _ => {
                             return Err(trc::JmapEvent::UnknownMethod
                                 .into_err()
@@ -98,6 +101,9 @@ impl QueryChanges for Server {
                     self.email_submission_query(query).await?
                 }
                 query::RequestArguments::Quota => self.quota_query(query, access_token).await?,
+                query::RequestArguments::ContactCard => {
+                    self.contact_card_query(query, access_token).await?
+                }
                 _ => unreachable!(),
             };
 
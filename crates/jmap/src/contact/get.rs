@@ -0,0 +1,159 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{vcard_texts, visible_address_books, visible_contact_cards};
+use calcard::vcard::VCardProperty;
+use common::{Server, auth::AccessToken};
+use groupware::{cache::GroupwareCache, contact::ContactCard};
+use jmap_proto::{
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    types::{
+        acl::Acl,
+        collection::{Collection, SyncCollection},
+        id::Id,
+        property::Property,
+        state::State,
+        value::{Object, Value},
+    },
+};
+use std::future::Future;
+use trc::AddContext;
+
+pub trait ContactCardGet: Sync + Send {
+    fn contact_card_get(
+        &self,
+        request: GetRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<GetResponse>> + Send;
+}
+
+impl ContactCardGet for Server {
+    async fn contact_card_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> trc::Result<GetResponse> {
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = request.unwrap_properties(&[
+            Property::Id,
+            Property::Uid,
+            Property::AddressBookIds,
+            Property::Name,
+            Property::Emails,
+            Property::Phones,
+        ]);
+        let account_id = request.account_id.document_id();
+        let resources = self
+            .fetch_dav_resources(access_token, account_id, SyncCollection::AddressBook)
+            .await
+            .caused_by(trc::location!())?;
+        let book_ids = visible_address_books(&resources, access_token, account_id, Acl::ReadItems);
+        let card_ids = visible_contact_cards(&resources, &book_ids);
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            card_ids
+                .iter()
+                .take(self.core.jmap.get_max_objects)
+                .map(Into::into)
+                .collect::<Vec<_>>()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: State::from(resources.item_change_id).into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            // Obtain the contact card object
+            let document_id = id.document_id();
+            if !card_ids.contains(document_id) {
+                response.not_found.push(id.into());
+                continue;
+            }
+            let card_ = if let Some(card) = self
+                .get_archive(account_id, Collection::ContactCard, document_id)
+                .await?
+            {
+                card
+            } else {
+                response.not_found.push(id.into());
+                continue;
+            };
+            let card = card_
+                .unarchive::<ContactCard>()
+                .caused_by(trc::location!())?;
+            let mut result = Object::with_capacity(properties.len());
+            for property in &properties {
+                match property {
+                    Property::Id => {
+                        result.append(Property::Id, Value::Id(id));
+                    }
+                    Property::Uid => {
+                        result.append(
+                            Property::Uid,
+                            card.card.uid().map(Value::from).unwrap_or_default(),
+                        );
+                    }
+                    Property::AddressBookIds => {
+                        let mut obj = Object::with_capacity(card.names.len());
+                        for name in card.names.iter() {
+                            let parent_id = u32::from(name.parent_id);
+                            if book_ids.contains(parent_id) {
+                                obj.append(Property::_T(Id::from(parent_id).to_string()), true);
+                            }
+                        }
+                        result.append(Property::AddressBookIds, Value::Object(obj));
+                    }
+                    Property::Name => {
+                        result.append(
+                            Property::Name,
+                            vcard_texts(&card.card, &VCardProperty::Fn)
+                                .next()
+                                .map(|name| {
+                                    Value::Object(
+                                        Object::with_capacity(1)
+                                            .with_property(Property::Full, name),
+                                    )
+                                })
+                                .unwrap_or_default(),
+                        );
+                    }
+                    Property::Emails => {
+                        let mut obj = Object::with_capacity(1);
+                        for (pos, email) in
+                            vcard_texts(&card.card, &VCardProperty::Email).enumerate()
+                        {
+                            obj.append(
+                                Property::_T(format!("e{}", pos + 1)),
+                                Object::with_capacity(1).with_property(Property::Address, email),
+                            );
+                        }
+                        result.append(Property::Emails, Value::Object(obj));
+                    }
+                    Property::Phones => {
+                        let mut obj = Object::with_capacity(1);
+                        for (pos, phone) in vcard_texts(&card.card, &VCardProperty::Tel).enumerate()
+                        {
+                            obj.append(
+                                Property::_T(format!("p{}", pos + 1)),
+                                Object::with_capacity(1).with_property(Property::Number, phone),
+                            );
+                        }
+                        result.append(Property::Phones, Value::Object(obj));
+                    }
+                    property => {
+                        result.append(property.clone(), Value::Null);
+                    }
+                }
+            }
+            response.list.push(result);
+        }
+
+        Ok(response)
+    }
+}
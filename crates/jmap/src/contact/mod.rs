@@ -0,0 +1,58 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use calcard::vcard::{ArchivedVCard, VCardProperty};
+use common::{DavResources, auth::AccessToken};
+use jmap_proto::types::acl::Acl;
+use store::roaring::RoaringBitmap;
+
+pub mod get;
+pub mod query;
+
+// Returns the address books in an account the token has been granted the given right on
+pub(crate) fn visible_address_books(
+    resources: &DavResources,
+    access_token: &AccessToken,
+    account_id: u32,
+    acl: Acl,
+) -> RoaringBitmap {
+    if access_token.is_member(account_id) {
+        resources
+            .resources
+            .iter()
+            .filter(|resource| resource.is_container())
+            .map(|resource| resource.document_id)
+            .collect()
+    } else {
+        resources.shared_containers(access_token, [acl], true)
+    }
+}
+
+pub(crate) fn visible_contact_cards(
+    resources: &DavResources,
+    book_ids: &RoaringBitmap,
+) -> RoaringBitmap {
+    resources
+        .resources
+        .iter()
+        .filter(|resource| {
+            resource
+                .child_names()
+                .is_some_and(|names| names.iter().any(|name| book_ids.contains(name.parent_id)))
+        })
+        .map(|resource| resource.document_id)
+        .collect()
+}
+
+pub(crate) fn vcard_texts<'x>(
+    card: &'x ArchivedVCard,
+    property: &'x VCardProperty,
+) -> impl Iterator<Item = &'x str> {
+    card.properties(property)
+        .flat_map(|entry| entry.values.iter().filter_map(|value| value.as_text()))
+        .map(|text| text.trim())
+        .filter(|text| !text.is_empty())
+}
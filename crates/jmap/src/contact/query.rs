@@ -0,0 +1,219 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{vcard_texts, visible_address_books, visible_contact_cards};
+use crate::JmapMethods;
+use calcard::vcard::VCardProperty;
+use common::{IDX_UID, Server, auth::AccessToken};
+use groupware::{cache::GroupwareCache, contact::ContactCard};
+use jmap_proto::{
+    method::query::{
+        Comparator, Filter, QueryRequest, QueryResponse, RequestArguments, SortProperty,
+    },
+    types::{
+        acl::Acl,
+        collection::{Collection, SyncCollection},
+        state::State,
+    },
+};
+use std::{collections::BTreeSet, future::Future};
+use store::{
+    query::{self},
+    roaring::RoaringBitmap,
+};
+use trc::AddContext;
+
+pub trait ContactCardQuery: Sync + Send {
+    fn contact_card_query(
+        &self,
+        request: QueryRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<QueryResponse>> + Send;
+}
+
+struct CardSummary {
+    document_id: u32,
+    name: String,
+    emails: Vec<String>,
+    text: String,
+}
+
+impl ContactCardQuery for Server {
+    async fn contact_card_query(
+        &self,
+        mut request: QueryRequest<RequestArguments>,
+        access_token: &AccessToken,
+    ) -> trc::Result<QueryResponse> {
+        let account_id = request.account_id.document_id();
+        let resources = self
+            .fetch_dav_resources(access_token, account_id, SyncCollection::AddressBook)
+            .await
+            .caused_by(trc::location!())?;
+        let book_ids = visible_address_books(&resources, access_token, account_id, Acl::ReadItems);
+        let card_ids = visible_contact_cards(&resources, &book_ids);
+        let mut filters = Vec::with_capacity(request.filter.len());
+
+        // Names and addresses are not indexed, cards are only loaded
+        // when a filter or the sort order requires them
+        let needs_summaries = request
+            .filter
+            .iter()
+            .any(|cond| matches!(cond, Filter::Name(_) | Filter::Email(_) | Filter::Text(_)))
+            || request.sort.as_ref().is_none_or(|sort| {
+                sort.is_empty()
+                    || sort
+                        .iter()
+                        .any(|comparator| comparator.property == SortProperty::Name)
+            });
+        let summaries = if needs_summaries {
+            let mut summaries = Vec::with_capacity(card_ids.len() as usize);
+            for document_id in &card_ids {
+                if let Some(card_) = self
+                    .get_archive(account_id, Collection::ContactCard, document_id)
+                    .await
+                    .caused_by(trc::location!())?
+                {
+                    let card = card_
+                        .unarchive::<ContactCard>()
+                        .caused_by(trc::location!())?;
+                    let mut text = String::new();
+                    for property in [
+                        VCardProperty::Fn,
+                        VCardProperty::N,
+                        VCardProperty::Nickname,
+                        VCardProperty::Org,
+                        VCardProperty::Email,
+                        VCardProperty::Tel,
+                        VCardProperty::Note,
+                    ] {
+                        for value in vcard_texts(&card.card, &property) {
+                            text.push_str(&value.to_lowercase());
+                            text.push(' ');
+                        }
+                    }
+
+                    summaries.push(CardSummary {
+                        document_id,
+                        name: vcard_texts(&card.card, &VCardProperty::Fn)
+                            .next()
+                            .unwrap_or_default()
+                            .to_lowercase(),
+                        emails: vcard_texts(&card.card, &VCardProperty::Email)
+                            .map(|email| email.to_lowercase())
+                            .collect(),
+                        text,
+                    });
+                }
+            }
+            summaries
+        } else {
+            vec![]
+        };
+
+        for cond in std::mem::take(&mut request.filter) {
+            match cond {
+                Filter::InAddressBook(id) => {
+                    filters.push(query::Filter::is_in_set(
+                        resources
+                            .children(id.document_id())
+                            .map(|resource| resource.document_id())
+                            .collect::<RoaringBitmap>(),
+                    ));
+                }
+                Filter::Uid(uid) => {
+                    filters.push(query::Filter::eq(IDX_UID, uid.into_bytes()));
+                }
+                Filter::Name(name) => {
+                    let name = name.to_lowercase();
+                    filters.push(query::Filter::is_in_set(
+                        summaries
+                            .iter()
+                            .filter(|card| card.name.contains(&name))
+                            .map(|card| card.document_id)
+                            .collect::<RoaringBitmap>(),
+                    ));
+                }
+                Filter::Email(email) => {
+                    let email = email.to_lowercase();
+                    filters.push(query::Filter::is_in_set(
+                        summaries
+                            .iter()
+                            .filter(|card| card.emails.iter().any(|e| e.contains(&email)))
+                            .map(|card| card.document_id)
+                            .collect::<RoaringBitmap>(),
+                    ));
+                }
+                Filter::Text(text) => {
+                    let text = text.to_lowercase();
+                    filters.push(query::Filter::is_in_set(
+                        summaries
+                            .iter()
+                            .filter(|card| card.text.contains(&text))
+                            .map(|card| card.document_id)
+                            .collect::<RoaringBitmap>(),
+                    ));
+                }
+                Filter::Id(ids) => {
+                    filters.push(query::Filter::is_in_set(
+                        ids.into_iter()
+                            .map(|id| id.document_id())
+                            .collect::<RoaringBitmap>(),
+                    ));
+                }
+                Filter::And | Filter::Or | Filter::Not | Filter::Close => {
+                    filters.push(cond.into());
+                }
+                other => {
+                    return Err(trc::JmapEvent::UnsupportedFilter
+                        .into_err()
+                        .details(other.to_string()));
+                }
+            }
+        }
+
+        let mut result_set = self
+            .filter(account_id, Collection::ContactCard, filters)
+            .await?;
+        result_set.apply_mask(card_ids);
+        let (response, paginate) = self
+            .build_query_response(&result_set, State::from(resources.item_change_id), &request)
+            .await?;
+
+        if let Some(paginate) = paginate {
+            // Parse sort criteria
+            let mut comparators = Vec::with_capacity(request.sort.as_ref().map_or(1, |s| s.len()));
+            for comparator in request
+                .sort
+                .filter(|s| !s.is_empty())
+                .unwrap_or_else(|| vec![Comparator::ascending(SortProperty::Name)])
+            {
+                comparators.push(match comparator.property {
+                    SortProperty::Name => {
+                        let sorted_list = summaries
+                            .iter()
+                            .map(|card| (card.name.as_str(), card.document_id))
+                            .collect::<BTreeSet<_>>();
+
+                        query::Comparator::sorted_list(
+                            sorted_list.into_iter().map(|v| v.1).collect(),
+                            comparator.is_ascending,
+                        )
+                    }
+                    other => {
+                        return Err(trc::JmapEvent::UnsupportedSort
+                            .into_err()
+                            .details(other.to_string()));
+                    }
+                });
+            }
+
+            // Sort results
+            self.sort(result_set, comparators, paginate, response).await
+        } else {
+            Ok(response)
+        }
+    }
+}
@@ -14,7 +14,10 @@ use crate::{
 use common::{Server, auth::AccessToken};
 use email::{
     cache::{MessageCacheFetch, email::MessageCacheAccess},
-    message::metadata::{ArchivedMetadataPartType, MessageMetadata},
+    message::{
+        metadata::{ArchivedMetadataPartType, MessageMetadata},
+        snooze::EmailSnooze,
+    },
 };
 use jmap_proto::{
     method::get::{GetRequest, GetResponse},
@@ -248,6 +251,15 @@ impl EmailGet for Server {
                             )),
                         );
                     }
+                    Property::SnoozedUntil => {
+                        email.append(
+                            Property::SnoozedUntil,
+                            self.get_snoozed_until(account_id, id.document_id())
+                                .await?
+                                .map(|until| Value::Date(UTCDate::from_timestamp(until as i64)))
+                                .unwrap_or(Value::Null),
+                        );
+                    }
                     Property::Preview => {
                         if !metadata.preview.is_empty() {
                             email.append(Property::Preview, metadata.preview.to_string());
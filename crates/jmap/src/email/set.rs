@@ -15,7 +15,9 @@ use email::{
     message::{
         delete::EmailDeletion,
         ingest::{EmailIngest, IngestEmail, IngestSource},
+        mdn::queue_mdn_on_read,
         metadata::MessageData,
+        snooze::{EmailSnooze, set_snoozed_until},
     },
 };
 use http_proto::HttpSessionData;
@@ -43,7 +45,11 @@ use mail_builder::{
 };
 use mail_parser::MessageParser;
 use std::future::Future;
-use store::{ahash::AHashMap, roaring::RoaringBitmap, write::BatchBuilder};
+use store::{
+    ahash::AHashMap,
+    roaring::RoaringBitmap,
+    write::{BatchBuilder, now},
+};
 use trc::AddContext;
 
 pub trait EmailSet: Sync + Send {
@@ -751,6 +757,8 @@ impl EmailSet for Server {
         let mut batch = BatchBuilder::new();
         let mut changed_mailboxes: AHashMap<u32, Vec<u32>> = AHashMap::new();
         let mut will_update = Vec::with_capacity(request.update.as_ref().map_or(0, |u| u.len()));
+        let mut has_snooze_changes = false;
+        let mut has_mdn_tasks = false;
         'update: for (id, object) in request.unwrap_update() {
             // Make sure id won't be destroyed
             if will_destroy.contains(&id) {
@@ -776,6 +784,7 @@ impl EmailSet for Server {
             let mut new_data = data
                 .deserialize::<MessageData>()
                 .caused_by(trc::location!())?;
+            let mut snoozed_until = None;
 
             for (property, value) in object.0 {
                 let value = match response.eval_object_references(value) {
@@ -824,6 +833,22 @@ impl EmailSet for Server {
                             }
                         }
                     }
+                    (Property::SnoozedUntil, MaybePatchValue::Value(Value::Date(until))) => {
+                        if until.timestamp() > now() as i64 {
+                            snoozed_until = Some(Some(until.timestamp() as u64));
+                        } else {
+                            response.not_updated.append(
+                                id,
+                                SetError::invalid_properties()
+                                    .with_property(Property::SnoozedUntil)
+                                    .with_description("Snooze time must be in the future."),
+                            );
+                            continue 'update;
+                        }
+                    }
+                    (Property::SnoozedUntil, MaybePatchValue::Value(Value::Null)) => {
+                        snoozed_until = Some(None);
+                    }
                     (property, _) => {
                         response.invalid_property_update(id, property);
                         continue 'update;
@@ -833,7 +858,7 @@ impl EmailSet for Server {
 
             let has_keyword_changes = new_data.has_keyword_changes(data.inner);
             let has_mailbox_changes = new_data.has_mailbox_changes(data.inner);
-            if !has_keyword_changes && !has_mailbox_changes {
+            if !has_keyword_changes && !has_mailbox_changes && snoozed_until.is_none() {
                 response.not_updated.append(
                     id,
                     SetError::invalid_properties()
@@ -854,6 +879,28 @@ impl EmailSet for Server {
                     continue 'update;
                 }
 
+                // Enforce custom keyword limits
+                let added_keywords = new_data
+                    .added_keywords(data.inner)
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let mailbox_ids = new_data
+                    .mailboxes
+                    .iter()
+                    .map(|mailbox| mailbox.mailbox_id)
+                    .collect::<Vec<_>>();
+                if cache
+                    .exceeds_keyword_limits(&added_keywords, &mailbox_ids, &self.core.jmap)
+                    .is_some()
+                {
+                    response.not_updated.append(
+                        id,
+                        SetError::over_quota()
+                            .with_description("The maximum number of keywords has been reached."),
+                    );
+                    continue 'update;
+                }
+
                 // Set all current mailboxes as changed if the Seen tag changed
                 if new_data
                     .added_keywords(data.inner)
@@ -944,18 +991,50 @@ impl EmailSet for Server {
                 }
             }
 
+            // Process snooze
+            let snooze_change = if let Some(until) = snoozed_until {
+                // Verify permissions on shared accounts
+                if matches!(&can_modify_message_ids, Some(ids) if !ids.contains(document_id)) {
+                    response.not_updated.append(
+                        id,
+                        SetError::forbidden()
+                            .with_description("You are not allowed to snooze this message."),
+                    );
+                    continue 'update;
+                }
+
+                let current = self
+                    .get_snoozed_until(account_id, document_id)
+                    .await
+                    .caused_by(trc::location!())?;
+                (current != until).then_some((current, until))
+            } else {
+                None
+            };
+
             // Write changes
             batch
                 .with_account_id(account_id)
                 .with_collection(Collection::Email)
-                .update_document(document_id)
+                .update_document(document_id);
+            if self.core.jmap.mdn_auto_send
+                && !access_token.is_shared(account_id)
+                && queue_mdn_on_read(&mut batch, data.inner, &new_data)
+            {
+                has_mdn_tasks = true;
+            }
+            batch
                 .custom(
                     ObjectIndexBuilder::new()
                         .with_current(data)
                         .with_changes(new_data),
                 )
-                .caused_by(trc::location!())?
-                .commit_point();
+                .caused_by(trc::location!())?;
+            if let Some((current, until)) = snooze_change {
+                set_snoozed_until(&mut batch, current, until);
+                has_snooze_changes = true;
+            }
+            batch.commit_point();
             will_update.push(id);
         }
 
@@ -975,6 +1054,9 @@ impl EmailSet for Server {
             {
                 Ok(change_id) => {
                     last_change_id = change_id.into();
+                    if has_snooze_changes || has_mdn_tasks {
+                        self.notify_task_queue();
+                    }
 
                     // Add to updated list
                     for id in will_update {
@@ -1069,6 +1151,7 @@ impl EmailSet for Server {
                 self.broadcast_state_change(
                     StateChange::new(account_id, change_id)
                         .with_change(DataType::Email)
+                        .with_change(DataType::Quota)
                         .with_change(DataType::Mailbox)
                         .with_change(DataType::Thread),
                 )
@@ -5,7 +5,10 @@
  */
 
 use common::{Server, storage::index::ObjectIndexBuilder};
-use directory::QueryParams;
+use directory::{
+    QueryParams,
+    backend::internal::{PrincipalField, lookup::DirectoryStore},
+};
 use email::identity::{ArchivedEmailAddress, Identity};
 use jmap_proto::{
     method::get::{GetRequest, GetResponse, RequestArguments},
@@ -16,6 +19,7 @@ use jmap_proto::{
     },
 };
 use store::{
+    ahash::AHashSet,
     rkyv::{option::ArchivedOption, vec::ArchivedVec},
     roaring::RoaringBitmap,
     write::BatchBuilder,
@@ -37,6 +41,11 @@ pub trait IdentityGet: Sync + Send {
         &self,
         account_id: u32,
     ) -> impl Future<Output = trc::Result<RoaringBitmap>> + Send;
+
+    fn identity_sync_delegated(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<RoaringBitmap>> + Send;
 }
 
 impl IdentityGet for Server {
@@ -196,6 +205,100 @@ impl IdentityGet for Server {
         }
         self.commit_batch(batch).await.caused_by(trc::location!())?;
 
+        // Add the addresses delegated by other principals
+        self.identity_sync_delegated(account_id).await
+    }
+
+    async fn identity_sync_delegated(&self, account_id: u32) -> trc::Result<RoaringBitmap> {
+        let mut identity_ids = self
+            .get_document_ids(account_id, Collection::Identity)
+            .await?
+            .unwrap_or_default();
+        let Some(principal) = self
+            .store()
+            .query(QueryParams::id(account_id).with_return_member_of(false))
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(identity_ids);
+        };
+
+        // Obtain the current addresses of the principals that delegated to this account
+        let mut delegated = AHashSet::new();
+        for field in [PrincipalField::SendAs, PrincipalField::SendOnBehalf] {
+            for &delegate_id in principal.delegates(field) {
+                if let Some(delegate) = self
+                    .store()
+                    .query(QueryParams::id(delegate_id).with_return_member_of(false))
+                    .await
+                    .caused_by(trc::location!())?
+                {
+                    delegated.extend(
+                        delegate
+                            .emails
+                            .into_iter()
+                            .filter(|email| !email.starts_with('@')),
+                    );
+                }
+            }
+        }
+
+        // Remove identities for addresses that are no longer delegated
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Identity);
+        let mut emails = AHashSet::with_capacity(identity_ids.len() as usize);
+        for document_id in identity_ids.clone() {
+            let Some(identity) = self
+                .get_archive(account_id, Collection::Identity, document_id)
+                .await?
+            else {
+                continue;
+            };
+            let email = identity
+                .unarchive::<Identity>()
+                .caused_by(trc::location!())?
+                .email
+                .to_string();
+            if email.is_empty() || delegated.contains(&email) || principal.emails.contains(&email) {
+                emails.insert(email);
+            } else {
+                batch
+                    .delete_document(document_id)
+                    .clear(Property::Value)
+                    .log_item_delete(SyncCollection::Identity, None)
+                    .commit_point();
+                identity_ids.remove(document_id);
+            }
+        }
+
+        // Create identities for the addresses delegated to this account
+        delegated.retain(|email| !emails.contains(email));
+        if !delegated.is_empty() {
+            let mut next_document_id = self
+                .store()
+                .assign_document_ids(account_id, Collection::Identity, delegated.len() as u64)
+                .await
+                .caused_by(trc::location!())?;
+            for email in delegated {
+                let document_id = next_document_id;
+                next_document_id -= 1;
+                batch
+                    .create_document(document_id)
+                    .custom(ObjectIndexBuilder::<(), _>::new().with_changes(Identity {
+                        name: email.clone(),
+                        email,
+                        ..Default::default()
+                    }))
+                    .caused_by(trc::location!())?;
+                identity_ids.insert(document_id);
+            }
+        }
+        if !batch.is_empty() {
+            self.commit_batch(batch).await.caused_by(trc::location!())?;
+        }
+
         Ok(identity_ids)
     }
 }
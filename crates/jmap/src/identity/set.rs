@@ -5,8 +5,7 @@
  */
 
 use common::{Server, storage::index::ObjectIndexBuilder};
-use directory::QueryParams;
-use email::identity::{EmailAddress, Identity};
+use email::identity::{EmailAddress, Identity, IdentityAuthorization};
 use jmap_proto::{
     error::set::SetError,
     method::set::{RequestArguments, SetRequest, SetResponse},
@@ -42,15 +41,37 @@ impl IdentitySet for Server {
             .unwrap_or_default();
         let mut response = SetResponse::from_request(&request, self.core.jmap.set_max_objects)?;
         let will_destroy = request.unwrap_destroy();
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let max_signature_size = self.core.jmap.identity_max_signature_size;
+        let mut num_identities = identity_ids.len() as usize;
 
         // Process creates
         let mut batch = BatchBuilder::new();
         'create: for (id, object) in request.unwrap_create() {
+            if num_identities >= self.core.jmap.identity_max_identities {
+                response.not_created.append(
+                    id,
+                    SetError::over_quota().with_description(format!(
+                        "There are too many identities, maximum is {}.",
+                        self.core.jmap.identity_max_identities
+                    )),
+                );
+                continue 'create;
+            }
             let mut identity = Identity::default();
 
             for (property, value) in object.0 {
                 if let Err(err) = response.eval_object_references(value).and_then(|value| {
-                    validate_identity_value(&property, value, &mut identity, true)
+                    validate_identity_value(
+                        &property,
+                        value,
+                        &mut identity,
+                        max_signature_size,
+                        true,
+                    )
                 }) {
                     response.not_created.append(id, err);
                     continue 'create;
@@ -59,11 +80,10 @@ impl IdentitySet for Server {
 
             // Validate email address
             if !identity.email.is_empty() {
-                if self
-                    .directory()
-                    .query(QueryParams::id(account_id).with_return_member_of(false))
-                    .await?
-                    .is_none_or(|p| !p.emails.iter().any(|e| e == &identity.email))
+                if !self
+                    .is_identity_authorized(&access_token, &identity.email)
+                    .await
+                    .caused_by(trc::location!())?
                 {
                     response.not_created.append(
                         id,
@@ -99,6 +119,7 @@ impl IdentitySet for Server {
                 .caused_by(trc::location!())?
                 .commit_point();
             response.created(id, document_id);
+            num_identities += 1;
         }
 
         // Process updates
@@ -129,7 +150,13 @@ impl IdentitySet for Server {
 
             for (property, value) in object.0 {
                 if let Err(err) = response.eval_object_references(value).and_then(|value| {
-                    validate_identity_value(&property, value, &mut new_identity, false)
+                    validate_identity_value(
+                        &property,
+                        value,
+                        &mut new_identity,
+                        max_signature_size,
+                        false,
+                    )
                 }) {
                     response.not_updated.append(id, err);
                     continue 'update;
@@ -188,6 +215,7 @@ fn validate_identity_value(
     property: &Property,
     value: MaybePatchValue,
     identity: &mut Identity,
+    max_signature_size: usize,
     is_create: bool,
 ) -> Result<(), SetError> {
     match (property, value) {
@@ -204,12 +232,12 @@ fn validate_identity_value(
             })?;
         }
         (Property::TextSignature, MaybePatchValue::Value(Value::Text(value)))
-            if value.len() < 2048 =>
+            if value.len() <= max_signature_size =>
         {
             identity.text_signature = value;
         }
         (Property::HtmlSignature, MaybePatchValue::Value(Value::Text(value)))
-            if value.len() < 2048 =>
+            if value.len() <= max_signature_size =>
         {
             identity.html_signature = value;
         }
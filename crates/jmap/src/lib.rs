@@ -22,9 +22,12 @@ use store::{
 };
 use trc::AddContext;
 
+pub mod addressbook;
 pub mod api;
+pub mod autocrypt;
 pub mod blob;
 pub mod changes;
+pub mod contact;
 pub mod email;
 pub mod identity;
 pub mod mailbox;
@@ -466,6 +466,20 @@ impl MailboxSet for Server {
             }
         }
 
+        // Provisioned system folders keep their name and location
+        if let Some((document_id, mailbox)) = &update
+            && (mailbox.inner.name.as_str() != changes.name
+                || mailbox.inner.parent_id != changes.parent_id)
+            && email::mailbox::is_protected_folder(&self.core.jmap, *document_id)
+            && !ctx
+                .access_token
+                .has_permission(directory::Permission::DeleteSystemFolders)
+        {
+            return Ok(Err(SetError::forbidden().with_description(
+                "You are not allowed to rename or move system folders.",
+            )));
+        }
+
         // Verify that the mailbox name is unique.
         if !changes.name.is_empty() {
             // Obtain parent mailbox id
@@ -89,6 +89,10 @@ impl PrincipalGet for Server {
                         .first()
                         .map(|email| Value::Text(email.to_string()))
                         .unwrap_or(Value::Null),
+                    Property::Picture => principal
+                        .picture()
+                        .map(|picture| Value::Text(picture.to_string()))
+                        .unwrap_or(Value::Null),
                     _ => Value::Null,
                 };
 
@@ -0,0 +1,52 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{account_quotas, quota_state};
+use common::{Server, auth::AccessToken};
+use jmap_proto::{
+    method::changes::{ChangesRequest, ChangesResponse},
+    types::{id::Id, state::State},
+};
+use std::future::Future;
+
+pub trait QuotaChanges: Sync + Send {
+    fn quota_changes(
+        &self,
+        request: ChangesRequest,
+        access_token: &AccessToken,
+    ) -> impl Future<Output = trc::Result<ChangesResponse>> + Send;
+}
+
+impl QuotaChanges for Server {
+    async fn quota_changes(
+        &self,
+        request: ChangesRequest,
+        access_token: &AccessToken,
+    ) -> trc::Result<ChangesResponse> {
+        let quotas = account_quotas(self, request.account_id.document_id(), access_token).await?;
+        let new_state = quota_state(&quotas);
+        let mut response = ChangesResponse {
+            account_id: request.account_id,
+            old_state: request.since_state.clone(),
+            new_state: new_state.clone(),
+            has_more_changes: false,
+            created: vec![],
+            updated: vec![],
+            destroyed: vec![],
+            updated_properties: None,
+        };
+
+        // There is no changelog for quotas, any state other than the
+        // current one reports all quotas as updated.
+        if request.since_state == State::Initial {
+            response.created = quotas.iter().map(|quota| Id::from(quota.id)).collect();
+        } else if request.since_state != new_state {
+            response.updated = quotas.iter().map(|quota| Id::from(quota.id)).collect();
+        }
+
+        Ok(response)
+    }
+}
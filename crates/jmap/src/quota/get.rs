@@ -4,13 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use super::{QUOTA_OCTETS_ID, account_quotas, quota_state};
 use common::{Server, auth::AccessToken};
 use jmap_proto::{
     method::get::{GetRequest, GetResponse, RequestArguments},
     types::{
         id::Id,
         property::Property,
-        state::State,
         type_state::DataType,
         value::{Object, Value},
     },
@@ -45,49 +45,58 @@ impl QuotaGet for Server {
             Property::Types,
         ]);
         let account_id = request.account_id.document_id();
-        let quota_ids = if access_token.quota > 0 {
-            vec![0u32]
-        } else {
-            vec![]
-        };
+        let quotas = account_quotas(self, account_id, access_token).await?;
         let ids = if let Some(ids) = ids {
             ids
         } else {
-            quota_ids.iter().map(|id| Id::from(*id)).collect()
+            quotas.iter().map(|quota| Id::from(quota.id)).collect()
         };
         let mut response = GetResponse {
             account_id: request.account_id.into(),
-            state: State::Initial.into(),
+            state: quota_state(&quotas).into(),
             list: Vec::with_capacity(ids.len()),
             not_found: vec![],
         };
 
         for id in ids {
-            // Obtain the sieve script object
+            // Obtain the quota
             let document_id = id.document_id();
-            if !quota_ids.contains(&document_id) {
+            let Some(quota) = quotas.iter().find(|quota| quota.id == document_id) else {
                 response.not_found.push(id.into());
                 continue;
-            }
+            };
+            let is_octets = quota.id == QUOTA_OCTETS_ID;
 
             let mut result = Object::with_capacity(properties.len());
             for property in &properties {
                 let value = match property {
                     Property::Id => Value::Id(id),
-                    Property::ResourceType => "octets".to_string().into(),
-                    Property::Used => (self.get_used_quota(account_id).await? as u64).into(),
-                    Property::HardLimit => access_token.quota.into(),
+                    Property::ResourceType => if is_octets { "octets" } else { "count" }
+                        .to_string()
+                        .into(),
+                    Property::Used => quota.used.into(),
+                    Property::HardLimit => quota.hard_limit.into(),
+                    Property::SoftLimit => quota.soft_limit.into(),
                     Property::Scope => "account".to_string().into(),
-                    Property::Name => access_token.name.to_string().into(),
+                    Property::Name => if is_octets {
+                        access_token.name.to_string()
+                    } else {
+                        format!("{} (messages)", access_token.name)
+                    }
+                    .into(),
                     Property::Description => access_token
                         .description
                         .as_ref()
                         .map(|s| s.to_string())
                         .into(),
-                    Property::Types => vec![
-                        Value::Text(DataType::Email.to_string()),
-                        Value::Text(DataType::SieveScript.to_string()),
-                    ]
+                    Property::Types => if is_octets {
+                        vec![
+                            Value::Text(DataType::Email.to_string()),
+                            Value::Text(DataType::SieveScript.to_string()),
+                        ]
+                    } else {
+                        vec![Value::Text(DataType::Email.to_string())]
+                    }
                     .into(),
 
                     _ => Value::Null,
@@ -4,5 +4,92 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use common::{Server, auth::AccessToken};
+use email::cache::MessageCacheFetch;
+use jmap_proto::types::state::State;
+use store::xxhash_rust::xxh3::xxh3_64;
+use trc::AddContext;
+
+pub mod changes;
 pub mod get;
 pub mod query;
+
+pub const QUOTA_OCTETS_ID: u32 = 0;
+pub const QUOTA_COUNT_ID: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountQuota {
+    pub id: u32,
+    pub used: u64,
+    pub hard_limit: u64,
+    pub soft_limit: u64,
+}
+
+pub(crate) fn quota_ids(access_token: &AccessToken) -> Vec<u32> {
+    let mut ids = Vec::with_capacity(2);
+    if access_token.quota > 0 {
+        ids.push(QUOTA_OCTETS_ID);
+    }
+    if access_token.message_quota > 0 {
+        ids.push(QUOTA_COUNT_ID);
+    }
+    ids
+}
+
+pub(crate) async fn account_quotas(
+    server: &Server,
+    account_id: u32,
+    access_token: &AccessToken,
+) -> trc::Result<Vec<AccountQuota>> {
+    let mut quotas = Vec::with_capacity(2);
+    for id in quota_ids(access_token) {
+        let quota = if id == QUOTA_OCTETS_ID {
+            AccountQuota {
+                id,
+                used: server
+                    .get_used_quota(account_id)
+                    .await
+                    .caused_by(trc::location!())? as u64,
+                hard_limit: access_token.quota,
+                soft_limit: server
+                    .core
+                    .network
+                    .quota_warnings
+                    .soft_limit(access_token.quota, access_token.soft_quota),
+            }
+        } else {
+            AccountQuota {
+                id,
+                used: server
+                    .get_cached_messages(account_id)
+                    .await
+                    .caused_by(trc::location!())?
+                    .emails
+                    .items
+                    .len() as u64,
+                hard_limit: access_token.message_quota,
+                soft_limit: server
+                    .core
+                    .network
+                    .quota_warnings
+                    .soft_limit(access_token.message_quota, None),
+            }
+        };
+        quotas.push(quota);
+    }
+
+    Ok(quotas)
+}
+
+// Quotas are not stored objects, so their state is derived from the current usage and limits.
+// The hash has to be stable across restarts and cluster nodes.
+pub(crate) fn quota_state(quotas: &[AccountQuota]) -> State {
+    let mut bytes = Vec::with_capacity(quotas.len() * 28);
+    for quota in quotas {
+        bytes.extend_from_slice(&quota.id.to_be_bytes());
+        bytes.extend_from_slice(&quota.used.to_be_bytes());
+        bytes.extend_from_slice(&quota.hard_limit.to_be_bytes());
+        bytes.extend_from_slice(&quota.soft_limit.to_be_bytes());
+    }
+    State::Exact(xxh3_64(&bytes))
+}
@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use super::quota_ids;
 use common::{Server, auth::AccessToken};
 use jmap_proto::{
     method::query::{QueryRequest, QueryResponse, RequestArguments},
@@ -25,17 +26,18 @@ impl QuotaQuery for Server {
         request: QueryRequest<RequestArguments>,
         access_token: &AccessToken,
     ) -> trc::Result<QueryResponse> {
+        let ids = quota_ids(access_token)
+            .into_iter()
+            .map(Id::from)
+            .collect::<Vec<_>>();
+
         Ok(QueryResponse {
             account_id: request.account_id,
             query_state: State::Initial,
             can_calculate_changes: false,
             position: 0,
-            ids: if access_token.quota > 0 {
-                vec![Id::new(0)]
-            } else {
-                vec![]
-            },
-            total: Some(1),
+            total: Some(ids.len()),
+            ids,
             limit: None,
         })
 
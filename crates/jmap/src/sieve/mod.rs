@@ -4,7 +4,19 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use jmap_proto::error::set::{SetError, SetErrorType};
+use sieve::compiler::{CompileError, ErrorType};
+
 pub mod get;
 pub mod query;
 pub mod set;
 pub mod validate;
+
+pub(crate) fn compile_error_to_set_error(err: CompileError) -> SetError {
+    SetError::new(if let ErrorType::ScriptTooLong = err.error_type() {
+        SetErrorType::TooLarge
+    } else {
+        SetErrorType::InvalidScript
+    })
+    .with_description(err.to_string())
+}
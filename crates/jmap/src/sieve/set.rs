@@ -29,7 +29,6 @@ use jmap_proto::{
     },
 };
 use rand::distr::Alphanumeric;
-use sieve::compiler::ErrorType;
 use store::{
     BlobClass, Serialize,
     query::Filter,
@@ -38,6 +37,7 @@ use store::{
 };
 use trc::AddContext;
 
+use super::compile_error_to_set_error;
 use crate::{JmapMethods, blob::download::BlobDownload, changes::state::StateManager};
 use std::future::Future;
 
@@ -472,14 +472,7 @@ impl SieveScriptSet for Server {
                             bytes.into()
                         }
                         Err(err) => {
-                            return Ok(Err(SetError::new(
-                                if let ErrorType::ScriptTooLong = &err.error_type() {
-                                    SetErrorType::TooLarge
-                                } else {
-                                    SetErrorType::InvalidScript
-                                },
-                            )
-                            .with_description(err.to_string())));
+                            return Ok(Err(compile_error_to_set_error(err)));
                         }
                     }
                 } else {
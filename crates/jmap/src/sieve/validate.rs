@@ -11,6 +11,7 @@ use jmap_proto::{
 };
 use std::future::Future;
 
+use super::compile_error_to_set_error;
 use crate::blob::download::BlobDownload;
 
 pub trait SieveScriptValidate: Sync + Send {
@@ -35,9 +36,7 @@ impl SieveScriptValidate for Server {
                 .map(|bytes| self.core.sieve.untrusted_compiler.compile(&bytes))
             {
                 Some(Ok(_)) => None,
-                Some(Err(err)) => SetError::new(SetErrorType::InvalidScript)
-                    .with_description(err.to_string())
-                    .into(),
+                Some(Err(err)) => compile_error_to_set_error(err).into(),
                 None => SetError::new(SetErrorType::BlobNotFound).into(),
             },
         })
@@ -12,7 +12,7 @@ use common::{
     storage::index::ObjectIndexBuilder,
 };
 use email::{
-    identity::Identity,
+    identity::{Identity, IdentityAuthorization},
     message::metadata::MessageMetadata,
     submission::{Address, Delivered, DeliveryStatus, EmailSubmission, UndoStatus},
 };
@@ -39,7 +39,7 @@ use smtp::{
     core::{Session, SessionData},
     queue::spool::SmtpSpool,
 };
-use smtp_proto::{MailFrom, RcptTo, request::parser::Rfc5321Parser};
+use smtp_proto::{MAIL_SMTPUTF8, MailFrom, RcptTo, request::parser::Rfc5321Parser};
 use std::{borrow::Cow, future::Future};
 use std::{collections::HashMap, sync::Arc, time::Duration};
 use store::write::{BatchBuilder, now};
@@ -461,8 +461,24 @@ impl EmailSubmissionSet for Server {
                 .with_description("Identity not found.")));
         };
 
+        // Make sure the account is still authorized to send from the identity address
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        if !self
+            .is_identity_authorized(&access_token, &identity_mail_from)
+            .await
+            .caused_by(trc::location!())?
+        {
+            return Ok(Err(SetError::new(SetErrorType::ForbiddenFrom)
+                .with_description(
+                    "Identity email address is not authorized for this account.",
+                )));
+        }
+
         // Make sure the envelope address matches the identity email address
-        let mail_from = if let Some(mail_from) = mail_from {
+        let mut mail_from = if let Some(mail_from) = mail_from {
             if !mail_from.address.eq_ignore_ascii_case(&identity_mail_from) {
                 return Ok(Err(SetError::new(SetErrorType::ForbiddenFrom)
                     .with_description(
@@ -542,6 +558,11 @@ impl EmailSubmissionSet for Server {
                 .find(|header| matches!(header.name, ArchivedHeaderName::Bcc));
         }
 
+        // Internationalized addresses are sent using SMTPUTF8
+        if !mail_from.address.is_ascii() || rcpt_to.iter().any(|rcpt| !rcpt.address.is_ascii()) {
+            mail_from.flags |= MAIL_SMTPUTF8;
+        }
+
         // Update sendAt
         submission.send_at = if mail_from.hold_until > 0 {
             mail_from.hold_until
@@ -583,15 +604,7 @@ impl EmailSubmissionSet for Server {
         let mut session = Session::<NullIo>::local(
             self.clone(),
             instance.clone(),
-            SessionData::local(
-                self.get_access_token(account_id)
-                    .await
-                    .caused_by(trc::location!())?,
-                None,
-                vec![],
-                vec![],
-                0,
-            ),
+            SessionData::local(access_token, None, vec![], vec![], 0),
         );
 
         // Spawn SMTP session to avoid overflowing the stack
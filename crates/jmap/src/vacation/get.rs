@@ -5,7 +5,7 @@
  */
 
 use common::Server;
-use email::sieve::SieveScript;
+use email::sieve::{SieveScript, vacation::SieveVacation};
 use jmap_proto::{
     method::get::{GetRequest, GetResponse, RequestArguments},
     request::reference::MaybeReference,
@@ -19,21 +19,15 @@ use jmap_proto::{
     },
 };
 use std::future::Future;
-use store::query::Filter;
 use trc::AddContext;
 
-use crate::{JmapMethods, changes::state::StateManager};
+use crate::changes::state::StateManager;
 
 pub trait VacationResponseGet: Sync + Send {
     fn vacation_response_get(
         &self,
         request: GetRequest<RequestArguments>,
     ) -> impl Future<Output = trc::Result<GetResponse>> + Send;
-
-    fn get_vacation_sieve_script_id(
-        &self,
-        account_id: u32,
-    ) -> impl Future<Output = trc::Result<Option<u32>>> + Send;
 }
 
 impl VacationResponseGet for Server {
@@ -79,7 +73,7 @@ impl VacationResponseGet for Server {
             true
         };
         if do_get {
-            if let Some(document_id) = self.get_vacation_sieve_script_id(account_id).await? {
+            if let Some(document_id) = self.sieve_vacation_script_id(account_id).await? {
                 if let Some(sieve_) = self
                     .get_archive(account_id, Collection::SieveScript, document_id)
                     .await?
@@ -155,14 +149,4 @@ impl VacationResponseGet for Server {
 
         Ok(response)
     }
-
-    async fn get_vacation_sieve_script_id(&self, account_id: u32) -> trc::Result<Option<u32>> {
-        self.filter(
-            account_id,
-            Collection::SieveScript,
-            vec![Filter::eq(Property::Name, "vacation".as_bytes().to_vec())],
-        )
-        .await
-        .map(|r| r.results.min())
-    }
 }
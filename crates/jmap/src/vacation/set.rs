@@ -4,11 +4,11 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use super::get::VacationResponseGet;
 use crate::{JmapMethods, changes::state::StateManager};
 use common::{Server, auth::AccessToken, storage::index::ObjectIndexBuilder};
 use email::sieve::{
     SieveScript, VacationResponse, activate::SieveScriptActivate, delete::SieveScriptDelete,
+    vacation::SieveVacation,
 };
 use jmap_proto::{
     error::set::{SetError, SetErrorType},
@@ -16,20 +16,13 @@ use jmap_proto::{
     response::references::EvalObjectReferences,
     types::{
         collection::{Collection, SyncCollection},
-        date::UTCDate,
         id::Id,
         property::Property,
         value::{MaybePatchValue, Object, Value},
     },
 };
-use mail_builder::MessageBuilder;
-use mail_parser::decoders::html::html_to_text;
-use std::borrow::Cow;
 use std::future::Future;
-use store::{
-    Serialize,
-    write::{Archiver, BatchBuilder},
-};
+use store::write::BatchBuilder;
 use trc::AddContext;
 
 pub trait VacationResponseSet: Sync + Send {
@@ -38,8 +31,6 @@ pub trait VacationResponseSet: Sync + Send {
         request: SetRequest<RequestArguments>,
         access_token: &AccessToken,
     ) -> impl Future<Output = trc::Result<SetResponse>> + Send;
-
-    fn build_script(&self, obj: &mut SieveScript) -> trc::Result<Vec<u8>>;
 }
 
 impl VacationResponseSet for Server {
@@ -128,7 +119,7 @@ impl VacationResponseSet for Server {
         // Process changes
         if let Some(changes) = changes {
             // Obtain current script
-            let document_id = self.get_vacation_sieve_script_id(account_id).await?;
+            let document_id = self.sieve_vacation_script_id(account_id).await?;
             let mut was_active = false;
 
             let (mut sieve, prev_sieve) = if let Some(document_id) = document_id {
@@ -162,8 +153,8 @@ impl VacationResponseSet for Server {
                 )
             };
 
-            // Parse properties
-            let mut is_active = false;
+            // Parse properties, updates that omit isEnabled keep the current state
+            let mut is_active = create_id.is_none() && was_active;
             let mut build_script = create_id.is_some();
             let vacation = sieve.vacation_response.as_mut().unwrap();
 
@@ -248,6 +239,17 @@ impl VacationResponseSet for Server {
                     }
                 }
             }
+            if let (Some(from_date), Some(to_date)) = (vacation.from_date, vacation.to_date)
+                && from_date > to_date
+            {
+                return Ok(set_error(
+                    response,
+                    create_id,
+                    SetError::invalid_properties()
+                        .with_properties([Property::FromDate, Property::ToDate])
+                        .with_description("fromDate must be before toDate."),
+                ));
+            }
             sieve.is_active = is_active;
 
             let mut obj = ObjectIndexBuilder::new()
@@ -275,7 +277,7 @@ impl VacationResponseSet for Server {
                 obj.changes_mut().unwrap().blob_hash = self
                     .put_blob(
                         account_id,
-                        &self.build_script(obj.changes_mut().unwrap())?,
+                        &self.sieve_vacation_build(obj.changes_mut().unwrap())?,
                         false,
                     )
                     .await?
@@ -316,7 +318,7 @@ impl VacationResponseSet for Server {
         } else if !will_destroy.is_empty() {
             for id in will_destroy {
                 if id.is_singleton()
-                    && let Some(document_id) = self.get_vacation_sieve_script_id(account_id).await?
+                    && let Some(document_id) = self.sieve_vacation_script_id(account_id).await?
                 {
                     self.sieve_script_delete(&resource_token, document_id, false, &mut batch)
                         .await?;
@@ -341,127 +343,6 @@ impl VacationResponseSet for Server {
 
         Ok(response)
     }
-
-    fn build_script(&self, obj: &mut SieveScript) -> trc::Result<Vec<u8>> {
-        // Build Sieve script
-        let mut script = Vec::with_capacity(1024);
-        script.extend_from_slice(b"require [\"vacation\", \"relational\", \"date\"];\r\n\r\n");
-        let mut num_blocks = 0;
-
-        // Add start date
-        if let Some(value) = obj.vacation_response.as_ref().and_then(|v| v.from_date) {
-            script.extend_from_slice(b"if currentdate :value \"ge\" \"iso8601\" \"");
-            script.extend_from_slice(UTCDate::from(value).to_string().as_bytes());
-            script.extend_from_slice(b"\" {\r\n");
-            num_blocks += 1;
-        }
-
-        // Add end date
-        if let Some(value) = obj.vacation_response.as_ref().and_then(|v| v.to_date) {
-            script.extend_from_slice(b"if currentdate :value \"le\" \"iso8601\" \"");
-            script.extend_from_slice(UTCDate::from(value).to_string().as_bytes());
-            script.extend_from_slice(b"\" {\r\n");
-            num_blocks += 1;
-        }
-
-        script.extend_from_slice(b"vacation :mime ");
-        if let Some(value) = obj
-            .vacation_response
-            .as_ref()
-            .and_then(|v| v.subject.as_ref())
-        {
-            script.extend_from_slice(b":subject \"");
-            for &ch in value.as_bytes().iter() {
-                match ch {
-                    b'\\' | b'\"' => {
-                        script.push(b'\\');
-                    }
-                    b'\r' | b'\n' => {
-                        continue;
-                    }
-                    _ => (),
-                }
-                script.push(ch);
-            }
-            script.extend_from_slice(b"\" ");
-        }
-
-        let mut text_body = if let Some(value) = obj
-            .vacation_response
-            .as_ref()
-            .and_then(|v| v.text_body.as_ref())
-        {
-            Cow::from(value.as_str()).into()
-        } else {
-            None
-        };
-        let html_body = if let Some(value) = obj
-            .vacation_response
-            .as_ref()
-            .and_then(|v| v.html_body.as_ref())
-        {
-            Cow::from(value.as_str()).into()
-        } else {
-            None
-        };
-        match (&html_body, &text_body) {
-            (Some(html_body), None) => {
-                text_body = Cow::from(html_to_text(html_body.as_ref())).into();
-            }
-            (None, None) => {
-                text_body = Cow::from("I am away.").into();
-            }
-            _ => (),
-        }
-
-        let mut builder = MessageBuilder::new();
-        let mut body_len = 0;
-        if let Some(html_body) = html_body {
-            body_len = html_body.len();
-            builder = builder.html_body(html_body);
-        }
-        if let Some(text_body) = text_body {
-            body_len += text_body.len();
-            builder = builder.text_body(text_body);
-        }
-        let mut message_body = Vec::with_capacity(body_len + 128);
-        builder.write_body(&mut message_body).ok();
-
-        script.push(b'\"');
-        for ch in message_body {
-            if [b'\\', b'\"'].contains(&ch) {
-                script.push(b'\\');
-            }
-            script.push(ch);
-        }
-        script.extend_from_slice(b"\";\r\n");
-
-        // Close blocks
-        for _ in 0..num_blocks {
-            script.extend_from_slice(b"}\r\n");
-        }
-
-        match self.core.sieve.untrusted_compiler.compile(&script) {
-            Ok(compiled_script) => {
-                // Update blob length
-                obj.size = script.len() as u32;
-
-                // Serialize script
-                script.extend(
-                    Archiver::new(compiled_script)
-                        .untrusted()
-                        .serialize()
-                        .caused_by(trc::location!())?,
-                );
-
-                Ok(script)
-            }
-            Err(err) => Err(trc::StoreEvent::UnexpectedError
-                .caused_by(trc::location!())
-                .reason(err)
-                .details("Vacation Sieve Script failed to compile.")),
-        }
-    }
 }
 
 fn set_error(mut response: SetResponse, id: Option<String>, err: SetError) -> SetResponse {
@@ -6,13 +6,15 @@
 
 use std::{sync::Arc, time::Instant};
 
+use common::listener::limiter::LimiterResult;
 use common::{Server, auth::AccessToken};
-use futures_util::{SinkExt, StreamExt};
+use directory::Permission;
+use futures_util::{SinkExt, StreamExt, stream::FuturesUnordered};
 use http_proto::HttpSessionData;
 use hyper::upgrade::Upgraded;
 use hyper_util::rt::TokioIo;
 use jmap_proto::{
-    error::request::RequestError,
+    error::request::{RequestError, RequestLimitError},
     request::websocket::{
         WebSocketMessage, WebSocketRequestError, WebSocketResponse, WebSocketStateChange,
     },
@@ -81,6 +83,7 @@ impl WebSocketHandler for Server {
             }
         };
 
+        let mut pending = FuturesUnordered::new();
         let mut changes = WebSocketStateChange::new(None);
         let mut change_types: Bitmap<DataType> = Bitmap::new();
 
@@ -97,16 +100,46 @@ impl WebSocketHandler for Server {
                                         self.core.jmap.request_max_size,
                                     ) {
                                         Ok(WebSocketMessage::Request(request)) => {
-                                            let response = self
-                                                .handle_jmap_request(
-                                                    request.request,
-                                                    access_token.clone(),
-                                                    &session,
-                                                )
-                                                .await;
-
-                                            WebSocketResponse::from_response(response, request.id)
-                                            .to_json()
+                                            // Requests are processed concurrently and their responses
+                                            // are sent as soon as they complete
+                                            let in_flight = match access_token.is_http_request_allowed() {
+                                                LimiterResult::Allowed(in_flight) => Some(in_flight),
+                                                LimiterResult::Forbidden
+                                                    if !access_token
+                                                        .has_permission(Permission::UnlimitedRequests) =>
+                                                {
+                                                    WebSocketRequestError::from_error(
+                                                        RequestError::limit(
+                                                            RequestLimitError::ConcurrentRequest,
+                                                        ),
+                                                        request.id,
+                                                    )
+                                                    .to_json()
+                                                    .send(&mut stream, session.session_id)
+                                                    .await;
+                                                    last_request = Instant::now();
+                                                    continue;
+                                                }
+                                                _ => None,
+                                            };
+                                            let access_token = access_token.clone();
+                                            let session = &session;
+                                            pending.push(Box::pin(async move {
+                                                let response = self
+                                                    .handle_jmap_request(
+                                                        request.request,
+                                                        access_token,
+                                                        session,
+                                                    )
+                                                    .await;
+                                                drop(in_flight);
+
+                                                WebSocketResponse::from_response(response, request.id)
+                                                    .to_json()
+                                            }));
+                                            last_request = Instant::now();
+                                            last_heartbeat = Instant::now();
+                                            continue;
                                         }
                                         Ok(WebSocketMessage::PushEnable(push_enable)) => {
                                             change_types = if !push_enable.data_types.is_empty() {
@@ -126,13 +159,7 @@ impl WebSocketHandler for Server {
                                             response
                                         },
                                     };
-                                    if let Err(err) = stream.send(Message::Text(response.into())).await {
-                                        trc::event!(Jmap(JmapEvent::WebsocketError),
-                                                    Details = "Failed to send text message",
-                                                    SpanId = session.session_id,
-                                                    Reason = err.to_string()
-                                        );
-                                    }
+                                    response.send(&mut stream, session.session_id).await;
                                 }
                                 Message::Ping(bytes) => {
                                     if let Err(err) = stream.send(Message::Pong(bytes)).await {
@@ -176,6 +203,9 @@ impl WebSocketHandler for Server {
                         }
                     }
                 }
+                Some(response) = pending.next(), if !pending.is_empty() => {
+                    response.send(&mut stream, session.session_id).await;
+                }
                 state_change = change_rx.recv() => {
                     if let Some(state_change) = state_change {
                         let mut types = state_change.types;
@@ -234,3 +264,24 @@ impl WebSocketHandler for Server {
         }
     }
 }
+
+trait SendResponse {
+    fn send(
+        self,
+        stream: &mut WebSocketStream<TokioIo<Upgraded>>,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl SendResponse for String {
+    async fn send(self, stream: &mut WebSocketStream<TokioIo<Upgraded>>, session_id: u64) {
+        if let Err(err) = stream.send(Message::Text(self.into())).await {
+            trc::event!(
+                Jmap(JmapEvent::WebsocketError),
+                Details = "Failed to send text message",
+                SpanId = session_id,
+                Reason = err.to_string()
+            );
+        }
+    }
+}
@@ -9,6 +9,7 @@ use common::{
         AuthRequest,
         sasl::{sasl_decode_challenge_oauth, sasl_decode_challenge_plain},
     },
+    config::server::ServerProtocol,
     listener::{SessionStream, limiter::LimiterResult},
 };
 use directory::Permission;
@@ -108,6 +109,17 @@ impl<T: SessionStream> Session<T> {
             }
             LimiterResult::Disabled => None,
         };
+        let in_flight = self.server.limit_account_connections(
+            ServerProtocol::Pop3,
+            access_token.primary_id(),
+            in_flight,
+            self.session_id,
+        )?;
+        self.server
+            .inner
+            .data
+            .sessions
+            .set_account_id(self.session_id, access_token.primary_id());
 
         // Fetch mailbox
         let mailbox = self.fetch_mailbox(access_token.primary_id()).await?;
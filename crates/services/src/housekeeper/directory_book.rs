@@ -0,0 +1,257 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use calcard::{Entry, Parser};
+use common::{DavName, Server, config::groupware::DirectoryAddressBook};
+use directory::{Principal, Type, backend::internal::manage::ManageDirectory};
+use groupware::{
+    DestroyArchive,
+    cache::GroupwareCache,
+    contact::{AddressBook, ContactCard, directory::principal_vcard},
+};
+use jmap_proto::types::{
+    acl::Acl,
+    collection::{Collection, SyncCollection},
+    value::AclGrant,
+};
+use std::future::Future;
+use store::{ahash::AHashMap, write::BatchBuilder};
+use trc::AddContext;
+use utils::map::bitmap::Bitmap;
+
+pub trait DirectoryBookSync: Sync + Send {
+    fn sync_directory_books(&self) -> impl Future<Output = ()> + Send;
+
+    fn sync_domain_book(
+        &self,
+        domain: &Principal,
+        members: &[&Principal],
+        config: &DirectoryAddressBook,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl DirectoryBookSync for Server {
+    async fn sync_directory_books(&self) {
+        let Some(config) = &self.core.groupware.directory_addressbook else {
+            return;
+        };
+
+        let principals = match self
+            .store()
+            .list_principals(None, None, &[Type::Domain, Type::Individual], true, 0, 0)
+            .await
+        {
+            Ok(principals) => principals.items,
+            Err(err) => {
+                trc::error!(
+                    err.caused_by(trc::location!())
+                        .details("Failed to list principals")
+                );
+                return;
+            }
+        };
+
+        let (domains, individuals): (Vec<_>, Vec<_>) = principals
+            .iter()
+            .partition(|principal| principal.typ == Type::Domain);
+        for domain in domains {
+            let suffix = format!("@{}", domain.name());
+            let members = individuals
+                .iter()
+                .copied()
+                .filter(|principal| {
+                    principal.tenant == domain.tenant
+                        && principal
+                            .emails
+                            .iter()
+                            .any(|email| email.ends_with(&suffix))
+                })
+                .collect::<Vec<_>>();
+
+            if let Err(err) = self.sync_domain_book(domain, &members, config).await {
+                trc::error!(
+                    err.account_id(domain.id())
+                        .details("Failed to synchronize directory address book")
+                );
+            }
+        }
+    }
+
+    // The address book is stored in the domain's account and shared read-only
+    // with every member, so it is served by the regular sharing code paths
+    async fn sync_domain_book(
+        &self,
+        domain: &Principal,
+        members: &[&Principal],
+        config: &DirectoryAddressBook,
+    ) -> trc::Result<()> {
+        let account_id = domain.id();
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let resources = self
+            .fetch_dav_resources(&access_token, account_id, SyncCollection::AddressBook)
+            .await
+            .caused_by(trc::location!())?;
+        let mut acls = members
+            .iter()
+            .map(|member| AclGrant {
+                account_id: member.id(),
+                grants: Bitmap::from_iter([Acl::Read, Acl::ReadItems]),
+            })
+            .collect::<Vec<_>>();
+        acls.sort_unstable_by_key(|grant| grant.account_id);
+        let mut batch = BatchBuilder::new();
+
+        // Create the address book or update the list of members it is shared with
+        let book_id = if let Some(resource) = resources
+            .by_path(&config.name)
+            .filter(|resource| resource.is_container())
+        {
+            let document_id = resource.document_id();
+            let book_ = self
+                .get_archive(account_id, Collection::AddressBook, document_id)
+                .await
+                .caused_by(trc::location!())?
+                .ok_or_else(|| {
+                    trc::StoreEvent::NotFound
+                        .into_err()
+                        .caused_by(trc::location!())
+                })?;
+            let book = book_
+                .to_unarchived::<AddressBook>()
+                .caused_by(trc::location!())?;
+            if book.inner.acls.len() != acls.len()
+                || book.inner.acls.iter().zip(acls.iter()).any(|(a, b)| a != b)
+            {
+                self.refresh_archived_acls(&acls, book.inner.acls.as_slice())
+                    .await;
+                let mut new_book = book
+                    .deserialize::<AddressBook>()
+                    .caused_by(trc::location!())?;
+                new_book.acls = acls;
+                new_book
+                    .update(&access_token, book, account_id, document_id, &mut batch)
+                    .caused_by(trc::location!())?;
+            }
+            document_id
+        } else {
+            let document_id = self
+                .store()
+                .assign_document_ids(account_id, Collection::AddressBook, 1)
+                .await
+                .caused_by(trc::location!())?;
+            self.refresh_acls(&acls, None).await;
+            AddressBook {
+                name: config.name.clone(),
+                display_name: config.display_name.clone().into(),
+                acls,
+                ..Default::default()
+            }
+            .insert(&access_token, account_id, document_id, &mut batch)
+            .caused_by(trc::location!())?;
+            document_id
+        };
+
+        // Cards are named after the principal id they were generated from
+        let mut existing = AHashMap::new();
+        let mut stale = Vec::new();
+        for resource in resources.children(book_id) {
+            let item = (resource.document_id(), resource.path().to_string());
+            if let Some(principal_id) = resource
+                .path()
+                .rsplit_once('/')
+                .and_then(|(_, name)| name.strip_suffix(".vcf"))
+                .and_then(|id| id.parse::<u32>().ok())
+            {
+                existing.insert(principal_id, item);
+            } else {
+                stale.push(item);
+            }
+        }
+
+        for member in members {
+            let vcard_raw = principal_vcard(member);
+            let Entry::VCard(card) = Parser::new(&vcard_raw).entry() else {
+                continue;
+            };
+
+            if let Some((document_id, _)) = existing.remove(&member.id()) {
+                let Some(card_) = self
+                    .get_archive(account_id, Collection::ContactCard, document_id)
+                    .await
+                    .caused_by(trc::location!())?
+                else {
+                    continue;
+                };
+                let current = card_
+                    .to_unarchived::<ContactCard>()
+                    .caused_by(trc::location!())?;
+                let mut new_card = current
+                    .deserialize::<ContactCard>()
+                    .caused_by(trc::location!())?;
+                if new_card.card != card {
+                    new_card.card = card;
+                    new_card.size = vcard_raw.len() as u32;
+                    new_card
+                        .update(&access_token, current, account_id, document_id, &mut batch)
+                        .caused_by(trc::location!())?;
+                }
+            } else {
+                let document_id = self
+                    .store()
+                    .assign_document_ids(account_id, Collection::ContactCard, 1)
+                    .await
+                    .caused_by(trc::location!())?;
+                ContactCard {
+                    names: vec![DavName::new(format!("{}.vcf", member.id()), book_id)],
+                    card,
+                    size: vcard_raw.len() as u32,
+                    ..Default::default()
+                }
+                .insert(&access_token, account_id, document_id, &mut batch)
+                .caused_by(trc::location!())?;
+            }
+
+            if batch.is_large_batch() {
+                self.commit_batch(std::mem::take(&mut batch))
+                    .await
+                    .caused_by(trc::location!())?;
+            }
+        }
+
+        // Remove principals that left the domain
+        for (document_id, path) in existing.into_values().chain(stale) {
+            if let Some(card_) = self
+                .get_archive(account_id, Collection::ContactCard, document_id)
+                .await
+                .caused_by(trc::location!())?
+            {
+                DestroyArchive(
+                    card_
+                        .to_unarchived::<ContactCard>()
+                        .caused_by(trc::location!())?,
+                )
+                .delete(
+                    &access_token,
+                    account_id,
+                    document_id,
+                    book_id,
+                    path.into(),
+                    &mut batch,
+                )
+                .caused_by(trc::location!())?;
+            }
+        }
+
+        if !batch.is_empty() {
+            self.commit_batch(batch).await.caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+}
@@ -10,7 +10,9 @@ use common::{
     core::BuildServer,
     ipc::{BroadcastEvent, HousekeeperEvent, PurgeType},
 };
+use directory_book::DirectoryBookSync;
 use email::message::delete::EmailDeletion;
+use out_of_office::OutOfOfficeSync;
 use smtp::reporting::SmtpReporting;
 use std::{
     collections::BinaryHeap,
@@ -27,11 +29,14 @@ use trc::{Collector, MetricType, PurgeEvent};
 // SPDX-License-Identifier: LicenseRef-SEL
 #[cfg(feature = "enterprise")]
 use common::telemetry::{
-    metrics::store::{MetricsStore, SharedMetricHistory},
+    metrics::store::{MetricsStore, SharedMetricHistory, UsageStore},
     tracers::store::TracingStore,
 };
 // SPDX-SnippetEnd
 
+pub mod directory_book;
+pub mod out_of_office;
+
 #[derive(PartialEq, Eq)]
 struct Action {
     due: Instant,
@@ -45,6 +50,8 @@ enum ActionClass {
     Acme(String),
     OtelMetrics,
     CalculateMetrics,
+    OutOfOffice,
+    DirectoryBook,
     // SPDX-SnippetBegin
     // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
     // SPDX-License-Identifier: LicenseRef-SEL
@@ -87,6 +94,26 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                 );
             }
 
+            // Out of office synchronization
+            if server.core.network.roles.purge_accounts
+                && let Some(out_of_office) = &server.core.groupware.out_of_office
+            {
+                queue.schedule(
+                    Instant::now() + out_of_office.frequency.time_to_next(),
+                    ActionClass::OutOfOffice,
+                );
+            }
+
+            // Directory address book synchronization
+            if server.core.network.roles.purge_accounts
+                && let Some(directory_book) = &server.core.groupware.directory_addressbook
+            {
+                queue.schedule(
+                    Instant::now() + directory_book.frequency.time_to_next(),
+                    ActionClass::DirectoryBook,
+                );
+            }
+
             // Store purges
             if server.core.network.roles.purge_stores {
                 for (idx, schedule) in server.core.storage.purge_schedules.iter().enumerate() {
@@ -187,6 +214,29 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                 _ => {}
                             }
 
+                            // Reload out of office synchronization
+                            if server.core.network.roles.purge_accounts
+                                && let Some(out_of_office) = &server.core.groupware.out_of_office
+                                && !queue.has_action(&ActionClass::OutOfOffice)
+                            {
+                                queue.schedule(
+                                    Instant::now() + out_of_office.frequency.time_to_next(),
+                                    ActionClass::OutOfOffice,
+                                );
+                            }
+
+                            // Reload directory address book synchronization
+                            if server.core.network.roles.purge_accounts
+                                && let Some(directory_book) =
+                                    &server.core.groupware.directory_addressbook
+                                && !queue.has_action(&ActionClass::DirectoryBook)
+                            {
+                                queue.schedule(
+                                    Instant::now() + directory_book.frequency.time_to_next(),
+                                    ActionClass::DirectoryBook,
+                                );
+                            }
+
                             // SPDX-SnippetBegin
                             // SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
                             // SPDX-License-Identifier: LicenseRef-SEL
@@ -346,6 +396,44 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                     server.purge(PurgeType::Account(None), 0).await;
                                 });
                             }
+                            ActionClass::OutOfOffice => {
+                                if let Some(out_of_office) = &server.core.groupware.out_of_office {
+                                    trc::event!(
+                                        Housekeeper(trc::HousekeeperEvent::Run),
+                                        Type = "out_of_office"
+                                    );
+
+                                    queue.schedule(
+                                        Instant::now() + out_of_office.frequency.time_to_next(),
+                                        ActionClass::OutOfOffice,
+                                    );
+
+                                    let server = server.clone();
+                                    tokio::spawn(async move {
+                                        server.sync_out_of_office().await;
+                                    });
+                                }
+                            }
+                            ActionClass::DirectoryBook => {
+                                if let Some(directory_book) =
+                                    &server.core.groupware.directory_addressbook
+                                {
+                                    trc::event!(
+                                        Housekeeper(trc::HousekeeperEvent::Run),
+                                        Type = "directory_book"
+                                    );
+
+                                    queue.schedule(
+                                        Instant::now() + directory_book.frequency.time_to_next(),
+                                        ActionClass::DirectoryBook,
+                                    );
+
+                                    let server = server.clone();
+                                    tokio::spawn(async move {
+                                        server.sync_directory_books().await;
+                                    });
+                                }
+                            }
                             ActionClass::Store(idx) => {
                                 if let Some(schedule) =
                                     server.core.storage.purge_schedules.get(idx).cloned()
@@ -539,16 +627,29 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                         ActionClass::InternalMetrics,
                                     );
 
+                                    let track_usage = metrics_store.usage;
                                     let metrics_store = metrics_store.store.clone();
                                     let metrics_history = metrics_history.clone();
-                                    let core = server.core.clone();
+                                    let server = server.clone();
                                     tokio::spawn(async move {
+                                        let timestamp = now();
                                         if let Err(err) = metrics_store
-                                            .write_metrics(core, now(), metrics_history)
+                                            .write_metrics(
+                                                server.core.clone(),
+                                                timestamp,
+                                                metrics_history,
+                                            )
                                             .await
                                         {
                                             trc::error!(err.details("Failed to write metrics"));
                                         }
+
+                                        if track_usage
+                                            && let Err(err) =
+                                                metrics_store.write_usage(&server, timestamp).await
+                                        {
+                                            trc::error!(err.details("Failed to write usage"));
+                                        }
                                     });
                                 }
                             }
@@ -734,7 +835,10 @@ impl Purge for Server {
                 // SPDX-SnippetEnd
             }
             PurgeType::Blobs { store, blob_store } => {
-                if let Err(err) = store.purge_blobs(blob_store).await {
+                // Queued messages may be spooled to a separate blob store
+                let mut blob_stores = vec![blob_store];
+                blob_stores.extend(self.core.storage.queue.clone());
+                if let Err(err) = store.purge_blobs_from(&blob_stores).await {
                     trc::error!(err.details("Failed to purge blob store"));
                 }
             }
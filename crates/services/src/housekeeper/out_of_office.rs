@@ -0,0 +1,403 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use calcard::common::timezone::Tz;
+use common::{
+    KV_OUT_OF_OFFICE, Server, config::groupware::OutOfOffice, storage::index::ObjectIndexBuilder,
+};
+use directory::backend::internal::manage::ManageDirectory;
+use email::sieve::{
+    SieveScript, VacationResponse, activate::SieveScriptActivate, vacation::SieveVacation,
+};
+use groupware::calendar::{
+    CalendarEvent,
+    out_of_office::{OutOfOfficePeriod, OutOfOfficeState},
+};
+use jmap_proto::types::{collection::Collection, property::Property};
+use std::future::Future;
+use store::{
+    Serialize,
+    dispatch::lookup::KeyValue,
+    query::Filter,
+    write::{AlignedBytes, Archive, Archiver, BatchBuilder, now},
+};
+use trc::AddContext;
+
+// Keep the state around after the period ends in case the housekeeper misses a run
+const STATE_RETENTION: u64 = 7 * 24 * 60 * 60;
+
+pub trait OutOfOfficeSync: Sync + Send {
+    fn sync_out_of_office(&self) -> impl Future<Output = ()> + Send;
+
+    fn sync_account_out_of_office(
+        &self,
+        account_id: u32,
+        config: &OutOfOffice,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl OutOfOfficeSync for Server {
+    async fn sync_out_of_office(&self) {
+        let Some(config) = &self.core.groupware.out_of_office else {
+            return;
+        };
+
+        // Only accounts that opted in are visited, opting out is synchronized on update
+        match self.store().get_out_of_office_principals().await {
+            Ok(account_ids) => {
+                for account_id in account_ids {
+                    if let Err(err) = self.sync_account_out_of_office(account_id, config).await {
+                        trc::error!(
+                            err.account_id(account_id)
+                                .details("Failed to synchronize out of office status")
+                        );
+                    }
+                }
+            }
+            Err(err) => {
+                trc::error!(
+                    err.caused_by(trc::location!())
+                        .details("Failed to obtain account ids")
+                );
+            }
+        }
+    }
+
+    async fn sync_account_out_of_office(
+        &self,
+        account_id: u32,
+        config: &OutOfOffice,
+    ) -> trc::Result<()> {
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let state = self
+            .in_memory_store()
+            .key_get::<Archive<AlignedBytes>>(KeyValue::<()>::build_key(
+                KV_OUT_OF_OFFICE,
+                account_id.to_be_bytes(),
+            ))
+            .await
+            .caused_by(trc::location!())?
+            .map(|archive| archive.deserialize::<OutOfOfficeState>())
+            .transpose()
+            .caused_by(trc::location!())?;
+
+        // Find the out of office event that ends last
+        let now = now();
+        let mut period: Option<OutOfOfficePeriod> = None;
+        if access_token.calendar_out_of_office
+            && let Some(document_ids) = self
+                .get_document_ids(account_id, Collection::CalendarEvent)
+                .await
+                .caused_by(trc::location!())?
+        {
+            self.get_archives(
+                account_id,
+                Collection::CalendarEvent,
+                &document_ids,
+                |_, archive| {
+                    let event = archive
+                        .unarchive::<CalendarEvent>()
+                        .caused_by(trc::location!())?;
+                    if let Some(event_period) =
+                        event
+                            .data
+                            .out_of_office_at(now as i64, Tz::Floating, &config.summaries)
+                        && period.as_ref().is_none_or(|p| event_period.end > p.end)
+                    {
+                        period = Some(event_period);
+                    }
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+        }
+
+        match (period, state) {
+            (Some(period), state) if state.is_none_or(|s| s.until != period.end as u64) => {
+                self.enable_out_of_office(account_id, config, period, state)
+                    .await
+            }
+            (None, Some(state)) => {
+                // Restore the previous response unless the user changed it in the meantime
+                if !state.released
+                    && let Some((document_id, prev_sieve)) =
+                        self.managed_vacation_script(account_id, &state).await?
+                {
+                    let mut sieve = prev_sieve.inner.clone();
+                    if let Some(vacation) = sieve.vacation_response.as_mut() {
+                        vacation.from_date = state.previous_from_date;
+                        vacation.to_date = state.previous_to_date;
+                    }
+                    self.write_vacation_script(account_id, Some((document_id, prev_sieve)), sieve)
+                        .await?;
+                    self.sieve_activate_script(account_id, state.previous_script_id)
+                        .await?;
+                }
+
+                self.in_memory_store()
+                    .key_delete(KeyValue::<()>::build_key(
+                        KV_OUT_OF_OFFICE,
+                        account_id.to_be_bytes(),
+                    ))
+                    .await
+                    .caused_by(trc::location!())?;
+
+                trc::event!(
+                    Calendar(trc::CalendarEvent::OutOfOfficeDisabled),
+                    AccountId = account_id,
+                );
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+trait OutOfOfficeEnable: Sync + Send {
+    fn enable_out_of_office(
+        &self,
+        account_id: u32,
+        config: &OutOfOffice,
+        period: OutOfOfficePeriod,
+        state: Option<OutOfOfficeState>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn managed_vacation_script(
+        &self,
+        account_id: u32,
+        state: &OutOfOfficeState,
+    ) -> impl Future<Output = trc::Result<Option<(u32, Archive<SieveScript>)>>> + Send;
+
+    fn write_vacation_script(
+        &self,
+        account_id: u32,
+        current: Option<(u32, Archive<SieveScript>)>,
+        sieve: SieveScript,
+    ) -> impl Future<Output = trc::Result<u32>> + Send;
+
+    fn store_out_of_office_state(
+        &self,
+        account_id: u32,
+        state: OutOfOfficeState,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl OutOfOfficeEnable for Server {
+    async fn enable_out_of_office(
+        &self,
+        account_id: u32,
+        config: &OutOfOffice,
+        period: OutOfOfficePeriod,
+        state: Option<OutOfOfficeState>,
+    ) -> trc::Result<()> {
+        let until = period.end as u64;
+        let released = OutOfOfficeState {
+            until,
+            previous_script_id: None,
+            previous_from_date: None,
+            previous_to_date: None,
+            released: true,
+        };
+
+        // Obtain current vacation script
+        let state = state.filter(|state| !state.released);
+        let current = if let Some(state) = &state {
+            // The period changed, update the response only if it is still the one enabled here
+            let current = self.managed_vacation_script(account_id, state).await?;
+            if current.is_none() {
+                return self.store_out_of_office_state(account_id, released).await;
+            }
+            current
+        } else if let Some(document_id) = self.sieve_vacation_script_id(account_id).await? {
+            let prev_sieve = self
+                .get_archive(account_id, Collection::SieveScript, document_id)
+                .await?
+                .ok_or_else(|| {
+                    trc::StoreEvent::NotFound
+                        .into_err()
+                        .caused_by(trc::location!())
+                })?
+                .into_deserialized::<SieveScript>()
+                .caused_by(trc::location!())?;
+
+            // Vacation responses enabled by the user are left untouched
+            if prev_sieve.inner.is_active {
+                return self.store_out_of_office_state(account_id, released).await;
+            }
+
+            Some((document_id, prev_sieve))
+        } else {
+            None
+        };
+        let mut sieve = current
+            .as_ref()
+            .map(|(_, prev_sieve)| prev_sieve.inner.clone())
+            .unwrap_or_else(|| SieveScript {
+                name: "vacation".into(),
+                is_active: false,
+                blob_hash: Default::default(),
+                size: 0,
+                vacation_response: None,
+            });
+
+        let new_state = if let Some(state) = state {
+            OutOfOfficeState { until, ..state }
+        } else {
+            OutOfOfficeState {
+                until,
+                previous_script_id: self
+                    .store()
+                    .filter(
+                        account_id,
+                        Collection::SieveScript,
+                        vec![Filter::eq(Property::IsActive, vec![1u8])],
+                    )
+                    .await
+                    .caused_by(trc::location!())?
+                    .results
+                    .min(),
+                previous_from_date: sieve.vacation_response.as_ref().and_then(|v| v.from_date),
+                previous_to_date: sieve.vacation_response.as_ref().and_then(|v| v.to_date),
+                released: false,
+            }
+        };
+
+        // Update vacation response
+        let vacation = sieve
+            .vacation_response
+            .get_or_insert_with(VacationResponse::default);
+        vacation.from_date = Some(period.start as u64);
+        vacation.to_date = Some(until);
+        if vacation.subject.is_none() {
+            vacation.subject = Some(period.summary);
+        }
+        if vacation.text_body.is_none() && vacation.html_body.is_none() {
+            vacation.text_body = Some(config.message.clone());
+        }
+        sieve.is_active = true;
+        let document_id = self
+            .write_vacation_script(account_id, current, sieve)
+            .await?;
+
+        // Deactivate other scripts
+        self.sieve_activate_script(account_id, document_id.into())
+            .await?;
+
+        // Store state
+        self.store_out_of_office_state(account_id, new_state)
+            .await?;
+
+        trc::event!(
+            Calendar(trc::CalendarEvent::OutOfOfficeEnabled),
+            AccountId = account_id,
+            DocumentId = document_id,
+            Expires = trc::Value::Timestamp(until),
+        );
+
+        Ok(())
+    }
+
+    async fn managed_vacation_script(
+        &self,
+        account_id: u32,
+        state: &OutOfOfficeState,
+    ) -> trc::Result<Option<(u32, Archive<SieveScript>)>> {
+        // The response is no longer managed here once the user deactivates it or edits its dates
+        if let Some(document_id) = self.sieve_vacation_script_id(account_id).await?
+            && let Some(prev_sieve) = self
+                .get_archive(account_id, Collection::SieveScript, document_id)
+                .await?
+        {
+            let prev_sieve = prev_sieve
+                .into_deserialized::<SieveScript>()
+                .caused_by(trc::location!())?;
+            if prev_sieve.inner.is_active
+                && prev_sieve
+                    .inner
+                    .vacation_response
+                    .as_ref()
+                    .is_some_and(|v| v.to_date == Some(state.until))
+            {
+                return Ok(Some((document_id, prev_sieve)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    async fn write_vacation_script(
+        &self,
+        account_id: u32,
+        current: Option<(u32, Archive<SieveScript>)>,
+        sieve: SieveScript,
+    ) -> trc::Result<u32> {
+        let access_token = self
+            .get_access_token(account_id)
+            .await
+            .caused_by(trc::location!())?;
+        let resource_token = self.get_resource_token(&access_token, account_id).await?;
+        let (document_id, prev_sieve) = current.unzip();
+        let mut obj = ObjectIndexBuilder::new()
+            .with_current_opt(prev_sieve)
+            .with_changes(sieve)
+            .with_tenant_id(&resource_token);
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::SieveScript);
+        let document_id = if let Some(document_id) = document_id {
+            batch.update_document(document_id);
+            document_id
+        } else {
+            let document_id = self
+                .store()
+                .assign_document_ids(account_id, Collection::SieveScript, 1)
+                .await
+                .caused_by(trc::location!())?;
+            batch.create_document(document_id);
+            document_id
+        };
+        obj.changes_mut().unwrap().blob_hash = self
+            .put_blob(
+                account_id,
+                &self.sieve_vacation_build(obj.changes_mut().unwrap())?,
+                false,
+            )
+            .await?
+            .hash;
+        batch.custom(obj).caused_by(trc::location!())?;
+        self.commit_batch(batch).await.caused_by(trc::location!())?;
+
+        Ok(document_id)
+    }
+
+    async fn store_out_of_office_state(
+        &self,
+        account_id: u32,
+        state: OutOfOfficeState,
+    ) -> trc::Result<()> {
+        self.in_memory_store()
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_OUT_OF_OFFICE,
+                    account_id.to_be_bytes(),
+                    Archiver::new(state)
+                        .untrusted()
+                        .serialize()
+                        .caused_by(trc::location!())?,
+                )
+                .expires(state.until.saturating_sub(now()) + STATE_RETENTION),
+            )
+            .await
+            .caused_by(trc::location!())
+    }
+}
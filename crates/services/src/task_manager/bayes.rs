@@ -10,6 +10,7 @@ use common::Server;
 use email::message::bayes::EmailBayesTrain;
 use jmap_proto::types::collection::Collection;
 use mail_parser::MessageParser;
+use smtp::inbound::rspamd::RspamdLearn;
 use trc::{SpamEvent, TaskQueueEvent};
 use utils::BlobHash;
 
@@ -42,6 +43,10 @@ impl BayesTrainTask for Server {
             )
             .await;
 
+            // Report the training action to any configured Rspamd controllers
+            self.rspamd_learn(&raw_message, learn_spam, task.account_id)
+                .await;
+
             trc::event!(
                 Spam(SpamEvent::TrainAccount),
                 AccountId = task.account_id,
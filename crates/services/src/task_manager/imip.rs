@@ -32,7 +32,7 @@ use mail_builder::{
 };
 use mail_parser::decoders::html::html_to_text;
 use smtp::core::{Session, SessionData};
-use smtp_proto::{MailFrom, RcptTo};
+use smtp_proto::{MAIL_SMTPUTF8, MailFrom, RcptTo};
 use std::{str::FromStr, sync::Arc, time::Duration};
 use store::{
     ValueKey,
@@ -224,6 +224,11 @@ async fn send_imip(
                 let _ = session
                     .handle_mail_from(MailFrom {
                         address: from.as_str().into(),
+                        flags: if from.is_ascii() && to.is_ascii() {
+                            0
+                        } else {
+                            MAIL_SMTPUTF8
+                        },
                         ..Default::default()
                     })
                     .await;
@@ -0,0 +1,248 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::Task;
+use chrono::DateTime;
+use common::{
+    Server,
+    auth::login_alert::LoginAlert,
+    config::network::LoginAlertDelivery,
+    i18n,
+    listener::{ServerInstance, stream::NullIo},
+};
+use directory::{QueryParams, backend::internal::lookup::DirectoryStore};
+use mail_builder::{MessageBuilder, headers::HeaderType};
+use smtp::core::{Session, SessionData};
+use smtp_proto::{MailFrom, RcptTo};
+use std::{fmt::Write, sync::Arc, time::Duration};
+use store::{
+    ValueKey,
+    write::{AlignedBytes, Archive, TaskQueueClass, ValueClass},
+};
+use trc::AddContext;
+
+pub trait SendLoginAlertTask: Sync + Send {
+    fn send_login_alert(
+        &self,
+        task: &Task,
+        server_instance: Arc<ServerInstance>,
+    ) -> impl Future<Output = bool> + Send;
+}
+
+impl SendLoginAlertTask for Server {
+    async fn send_login_alert(&self, task: &Task, server_instance: Arc<ServerInstance>) -> bool {
+        match send_login_alert(self, task, server_instance).await {
+            Ok(result) => result,
+            Err(err) => {
+                trc::error!(
+                    err.account_id(task.account_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to send login alert")
+                );
+                false
+            }
+        }
+    }
+}
+
+async fn send_login_alert(
+    server: &Server,
+    task: &Task,
+    server_instance: Arc<ServerInstance>,
+) -> trc::Result<bool> {
+    let Some(config) = &server.core.network.login_alerts else {
+        return Ok(true);
+    };
+
+    // Obtain access token
+    let access_token = server
+        .get_access_token(task.account_id)
+        .await
+        .caused_by(trc::location!())?;
+    let Some(account_main_email) = access_token.emails.first() else {
+        trc::event!(
+            Auth(trc::AuthEvent::LoginAlertFailed),
+            AccountId = task.account_id,
+            Reason = "Account does not have any email addresses",
+        );
+        return Ok(true);
+    };
+    let account_main_domain = account_main_email.rsplit('@').next().unwrap_or("localhost");
+
+    // Obtain login details
+    let Some(archive) = server
+        .store()
+        .get_value::<Archive<AlignedBytes>>(ValueKey {
+            account_id: task.account_id,
+            collection: 0,
+            document_id: task.document_id,
+            class: ValueClass::TaskQueue(TaskQueueClass::SendLoginAlert {
+                due: task.due,
+                is_payload: true,
+            }),
+        })
+        .await
+        .caused_by(trc::location!())?
+    else {
+        trc::event!(
+            Auth(trc::AuthEvent::LoginAlertFailed),
+            AccountId = task.account_id,
+            Reason = "Missing login alert payload",
+        );
+        return Ok(true);
+    };
+    let alert = archive
+        .deserialize::<LoginAlert>()
+        .caused_by(trc::location!())?;
+
+    // Deliver to the recovery address when requested, or to the account's inbox
+    let rcpt_to = if config.deliver_to == LoginAlertDelivery::Recovery {
+        server
+            .store()
+            .query(QueryParams::id(task.account_id).with_return_member_of(false))
+            .await
+            .caused_by(trc::location!())?
+            .and_then(|principal| principal.recovery_email().map(|email| email.to_string()))
+    } else {
+        None
+    }
+    .unwrap_or_else(|| account_main_email.to_string());
+
+    // Build message using the branding of the account's domain
+    let branding = server.core.network.branding(account_main_domain);
+    let locale = i18n::locale_or_default(
+        access_token
+            .locale
+            .as_deref()
+            .or_else(|| branding.and_then(|branding| branding.locale.as_deref()))
+            .unwrap_or("en"),
+    );
+    let mut body = String::with_capacity(512);
+    let _ = write!(
+        &mut body,
+        concat!(
+            "{}\r\n\r\n",
+            "Account: {}\r\n",
+            "Date: {}\r\n",
+            "IP address: {}\r\n"
+        ),
+        match (alert.new_location, alert.new_client) {
+            (true, true) => locale.login_alert_new_location_device,
+            (true, false) => locale.login_alert_new_location,
+            _ => locale.login_alert_new_device,
+        },
+        access_token.name,
+        DateTime::from_timestamp(alert.timestamp as i64, 0)
+            .unwrap_or_default()
+            .to_rfc2822(),
+        alert.remote_ip,
+    );
+    if let Some(country) = &alert.country {
+        let _ = write!(&mut body, "Country: {country}\r\n");
+    }
+    if let Some(client) = &alert.client {
+        let _ = write!(&mut body, "Client: {client}\r\n");
+    }
+    let _ = write!(&mut body, "\r\n{}\r\n", locale.login_alert_action);
+    if let Some(footer) = branding.and_then(|branding| branding.footer.as_deref()) {
+        let _ = write!(&mut body, "\r\n--\r\n{footer}\r\n");
+    }
+
+    let from_email = config
+        .from_email
+        .clone()
+        .unwrap_or_else(|| format!("no-reply@{account_main_domain}"));
+    let from_name = branding
+        .and_then(|branding| branding.name.as_deref())
+        .unwrap_or(config.from_name.as_str());
+    let message = MessageBuilder::new()
+        .from((from_name, from_email.as_str()))
+        .header("To", HeaderType::Text(rcpt_to.as_str().into()))
+        .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+        .subject(locale.login_alert_subject)
+        .text_body(body)
+        .write_to_vec()
+        .unwrap_or_default();
+
+    // Send message
+    let server_ = server.clone();
+    let mail_from = account_main_email.to_string();
+    let to = rcpt_to.clone();
+    let result = tokio::spawn(async move {
+        let mut session = Session::<NullIo>::local(
+            server_,
+            server_instance,
+            SessionData::local(access_token, None, vec![], vec![], 0),
+        );
+
+        // MAIL FROM
+        let _ = session
+            .handle_mail_from(MailFrom {
+                address: mail_from.into(),
+                ..Default::default()
+            })
+            .await;
+        if let Some(error) = session.has_failed() {
+            return Err(format!("Server rejected MAIL-FROM: {}", error.trim()));
+        }
+
+        // RCPT TO
+        session.params.rcpt_errors_wait = Duration::from_secs(0);
+        let _ = session
+            .handle_rcpt_to(RcptTo {
+                address: to.into(),
+                ..Default::default()
+            })
+            .await;
+        if let Some(error) = session.has_failed() {
+            return Err(format!("Server rejected RCPT-TO: {}", error.trim()));
+        }
+
+        // DATA
+        session.data.message = message;
+        let response = session.queue_message().await;
+        if let smtp::core::State::Accepted(queue_id) = session.state {
+            Ok(queue_id)
+        } else {
+            Err(format!(
+                "Server rejected DATA: {}",
+                std::str::from_utf8(&response).unwrap().trim()
+            ))
+        }
+    })
+    .await;
+
+    match result {
+        Ok(Ok(queue_id)) => {
+            trc::event!(
+                Auth(trc::AuthEvent::LoginAlertSent),
+                AccountId = task.account_id,
+                RemoteIp = alert.remote_ip,
+                To = rcpt_to,
+                QueueId = queue_id,
+            );
+        }
+        Ok(Err(err)) => {
+            trc::event!(
+                Auth(trc::AuthEvent::LoginAlertFailed),
+                AccountId = task.account_id,
+                To = rcpt_to,
+                Reason = err,
+            );
+        }
+        Err(_) => {
+            trc::event!(
+                Server(trc::ServerEvent::ThreadError),
+                Details = "Join Error",
+                AccountId = task.account_id,
+                CausedBy = trc::location!(),
+            );
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
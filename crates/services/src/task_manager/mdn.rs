@@ -0,0 +1,273 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::Task;
+use common::{
+    Server,
+    listener::{ServerInstance, stream::NullIo},
+};
+use email::message::{
+    mdn::{EmailMdn, mdn_request_address},
+    metadata::{MessageData, MessageMetadata},
+};
+use jmap_proto::types::{collection::Collection, keyword::Keyword, property::Property};
+use mail_builder::{
+    MessageBuilder,
+    headers::{HeaderType, content_type::ContentType},
+    mime::{BodyPart, MimePart, make_boundary},
+};
+use mail_parser::MessageParser;
+use smtp::core::{Session, SessionAddress, SessionData};
+use smtp_proto::RcptTo;
+use std::{fmt::Write, sync::Arc, time::Duration};
+use trc::AddContext;
+
+pub trait SendMdnTask: Sync + Send {
+    fn send_mdn(
+        &self,
+        task: &Task,
+        server_instance: Arc<ServerInstance>,
+    ) -> impl Future<Output = bool> + Send;
+}
+
+impl SendMdnTask for Server {
+    async fn send_mdn(&self, task: &Task, server_instance: Arc<ServerInstance>) -> bool {
+        match send_mdn(self, task, server_instance).await {
+            Ok(result) => result,
+            Err(err) => {
+                trc::error!(
+                    err.account_id(task.account_id)
+                        .document_id(task.document_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to send read receipt")
+                );
+                false
+            }
+        }
+    }
+}
+
+async fn send_mdn(
+    server: &Server,
+    task: &Task,
+    server_instance: Arc<ServerInstance>,
+) -> trc::Result<bool> {
+    // The message might have been deleted, marked as unread or answered by the client
+    let Some(data_) = server
+        .get_archive(task.account_id, Collection::Email, task.document_id)
+        .await
+        .caused_by(trc::location!())?
+    else {
+        return Ok(true);
+    };
+    let data = data_
+        .unarchive::<MessageData>()
+        .caused_by(trc::location!())?;
+    if !data
+        .keywords
+        .iter()
+        .any(|keyword| keyword == &Keyword::Seen)
+        || data
+            .keywords
+            .iter()
+            .any(|keyword| keyword == &Keyword::MdnSent)
+    {
+        return Ok(true);
+    }
+
+    // Receipts are only sent automatically when the user opted in
+    if !server
+        .get_mdn_settings(task.account_id)
+        .await
+        .caused_by(trc::location!())?
+        .auto_send
+    {
+        return Ok(true);
+    }
+
+    // Obtain the requested receipt address
+    let Some(metadata_) = server
+        .get_archive_by_property(
+            task.account_id,
+            Collection::Email,
+            task.document_id,
+            &Property::BodyStructure,
+        )
+        .await
+        .caused_by(trc::location!())?
+    else {
+        return Ok(true);
+    };
+    let metadata = metadata_
+        .unarchive::<MessageMetadata>()
+        .caused_by(trc::location!())?;
+    let Some(message) = MessageParser::new().parse_headers(metadata.raw_headers.as_slice()) else {
+        return Ok(true);
+    };
+    let Some(rcpt_to) = mdn_request_address(&message) else {
+        return Ok(true);
+    };
+
+    // Receipts are never sent automatically outside the server
+    if !server
+        .is_local_mdn_request(&rcpt_to)
+        .await
+        .caused_by(trc::location!())?
+    {
+        return Ok(true);
+    }
+
+    let access_token = server
+        .get_access_token(task.account_id)
+        .await
+        .caused_by(trc::location!())?;
+    let Some(final_recipient) = access_token.emails.first().cloned() else {
+        return Ok(true);
+    };
+
+    // Build RFC 8098 disposition notification
+    let subject = message.subject().unwrap_or_default();
+    let message_id = message.message_id();
+    let mut text = String::with_capacity(256);
+    let _ = write!(
+        &mut text,
+        concat!(
+            "The message sent to {} with subject \"{}\" was displayed.\r\n",
+            "This is no guarantee that the message has been read or understood.\r\n"
+        ),
+        final_recipient, subject,
+    );
+    let mut mdn = String::with_capacity(256);
+    let _ = write!(
+        &mut mdn,
+        "Reporting-UA: {}; Stalwart\r\nFinal-Recipient: rfc822;{}\r\n",
+        server.core.network.server_name, final_recipient,
+    );
+    if let Some(message_id) = message_id {
+        let _ = write!(&mut mdn, "Original-Message-ID: <{message_id}>\r\n");
+    }
+    mdn.push_str("Disposition: automatic-action/MDN-sent-automatically; displayed\r\n");
+
+    let mut builder = MessageBuilder::new()
+        .from(final_recipient.as_str())
+        .header("To", HeaderType::Text(rcpt_to.as_str().into()))
+        .header("Auto-Submitted", HeaderType::Text("auto-replied".into()))
+        .message_id(format!(
+            "<{}@{}>",
+            make_boundary("."),
+            server.core.network.server_name
+        ))
+        .subject(format!("Read: {subject}"));
+    if let Some(message_id) = message_id {
+        builder = builder.in_reply_to(message_id).references(message_id);
+    }
+    let raw_message = builder
+        .body(MimePart::new(
+            ContentType::new("multipart/report")
+                .attribute("report-type", "disposition-notification"),
+            BodyPart::Multipart(vec![
+                MimePart::new(ContentType::new("text/plain"), BodyPart::Text(text.into())),
+                MimePart::new(
+                    ContentType::new("message/disposition-notification"),
+                    BodyPart::Text(mdn.into()),
+                ),
+            ]),
+        ))
+        .write_to_vec()
+        .unwrap_or_default();
+
+    // Send message using a null return path
+    let server_ = server.clone();
+    let to = rcpt_to.clone();
+    let result = tokio::spawn(async move {
+        let mut session = Session::<NullIo>::local(
+            server_,
+            server_instance,
+            SessionData::local(
+                access_token,
+                Some(SessionAddress {
+                    address: String::new(),
+                    address_lcase: String::new(),
+                    domain: String::new(),
+                    flags: 0,
+                    dsn_info: None,
+                }),
+                vec![],
+                vec![],
+                0,
+            ),
+        );
+
+        // RCPT TO
+        session.params.rcpt_errors_wait = Duration::from_secs(0);
+        let _ = session
+            .handle_rcpt_to(RcptTo {
+                address: to.into(),
+                ..Default::default()
+            })
+            .await;
+        if let Some(error) = session.has_failed() {
+            return Err(format!("Server rejected RCPT-TO: {}", error.trim()));
+        }
+
+        // DATA
+        session.data.message = raw_message;
+        let response = session.queue_message().await;
+        if let smtp::core::State::Accepted(queue_id) = session.state {
+            Ok(queue_id)
+        } else {
+            Err(format!(
+                "Server rejected DATA: {}",
+                std::str::from_utf8(&response).unwrap().trim()
+            ))
+        }
+    })
+    .await;
+
+    match result {
+        Ok(Ok(queue_id)) => {
+            trc::event!(
+                TaskQueue(trc::TaskQueueEvent::MdnSent),
+                AccountId = task.account_id,
+                DocumentId = task.document_id,
+                To = rcpt_to,
+                QueueId = queue_id,
+            );
+
+            // The receipt is not sent twice even if the keyword could not be set
+            if let Err(err) = server.set_mdn_sent(task.account_id, task.document_id).await {
+                trc::error!(
+                    err.account_id(task.account_id)
+                        .document_id(task.document_id)
+                        .caused_by(trc::location!())
+                );
+            }
+
+            Ok(true)
+        }
+        Ok(Err(err)) => {
+            trc::event!(
+                TaskQueue(trc::TaskQueueEvent::MdnFailed),
+                AccountId = task.account_id,
+                DocumentId = task.document_id,
+                To = rcpt_to,
+                Reason = err,
+            );
+
+            Ok(true)
+        }
+        Err(_) => {
+            trc::event!(
+                Server(trc::ServerEvent::ThreadError),
+                Details = "Join Error",
+                AccountId = task.account_id,
+                CausedBy = trc::location!(),
+            );
+
+            Ok(false)
+        }
+    }
+}
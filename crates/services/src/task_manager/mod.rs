@@ -14,6 +14,10 @@ use common::listener::{ServerInstance, TcpAcceptor};
 use common::{Inner, KV_LOCK_TASK, Server, core::BuildServer};
 use fts::FtsIndexTask;
 use groupware::calendar::alarm::CalendarAlarm;
+use login_alert::SendLoginAlertTask;
+use mdn::SendMdnTask;
+use quota_warning::SendQuotaWarningTask;
+use snooze::UnsnoozeTask;
 use std::collections::hash_map::Entry;
 use std::future::Future;
 use std::time::Duration;
@@ -38,6 +42,10 @@ pub mod alarm;
 pub mod bayes;
 pub mod fts;
 pub mod imip;
+pub mod login_alert;
+pub mod mdn;
+pub mod quota_warning;
+pub mod snooze;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct Task {
@@ -53,6 +61,10 @@ pub enum TaskAction {
     BayesTrain { hash: BlobHash, learn_spam: bool },
     SendAlarm { alarm: CalendarAlarm },
     SendImip,
+    SendLoginAlert,
+    Unsnooze,
+    SendQuotaWarning,
+    SendMdn,
 }
 
 const FTS_LOCK_EXPIRY: u64 = 60 * 5; // 5 minutes
@@ -122,6 +134,24 @@ pub fn spawn_task_manager(inner: Arc<Inner>) {
                                 true
                             }
                         }
+                        TaskAction::SendLoginAlert => {
+                            server
+                                .send_login_alert(&task, server_instance.clone())
+                                .await
+                        }
+                        TaskAction::Unsnooze => server.unsnooze(&task).await,
+                        TaskAction::SendQuotaWarning => {
+                            server
+                                .send_quota_warning(&task, server_instance.clone())
+                                .await
+                        }
+                        TaskAction::SendMdn => {
+                            if server.core.jmap.mdn_auto_send {
+                                server.send_mdn(&task, server_instance.clone()).await
+                            } else {
+                                true
+                            }
+                        }
                     };
 
                     // Remove entry from queue
@@ -266,7 +296,11 @@ impl TaskQueueManager for Server {
             let tx = match &event.action {
                 TaskAction::Index { .. } => &ipc.tx_fts,
                 TaskAction::BayesTrain { .. } => &ipc.tx_bayes,
-                TaskAction::SendAlarm { .. } => &ipc.tx_alarm,
+                TaskAction::SendAlarm { .. }
+                | TaskAction::SendLoginAlert
+                | TaskAction::Unsnooze
+                | TaskAction::SendQuotaWarning
+                | TaskAction::SendMdn => &ipc.tx_alarm,
                 TaskAction::SendImip => &ipc.tx_imip,
             };
             if tx.send(event).await.is_err() {
@@ -359,6 +393,30 @@ impl Task {
                 .write_leb128(self.account_id)
                 .write_leb128(self.document_id)
                 .finalize(),
+            TaskAction::SendLoginAlert => KeySerializer::new((U32_LEN * 2) + U64_LEN + 1)
+                .write(4u8)
+                .write(self.due)
+                .write_leb128(self.account_id)
+                .write_leb128(self.document_id)
+                .finalize(),
+            TaskAction::Unsnooze => KeySerializer::new((U32_LEN * 2) + U64_LEN + 1)
+                .write(5u8)
+                .write(self.due)
+                .write_leb128(self.account_id)
+                .write_leb128(self.document_id)
+                .finalize(),
+            TaskAction::SendQuotaWarning => KeySerializer::new((U32_LEN * 2) + U64_LEN + 1)
+                .write(6u8)
+                .write(self.due)
+                .write_leb128(self.account_id)
+                .write_leb128(self.document_id)
+                .finalize(),
+            TaskAction::SendMdn => KeySerializer::new((U32_LEN * 2) + U64_LEN + 1)
+                .write(7u8)
+                .write(self.due)
+                .write_leb128(self.account_id)
+                .write_leb128(self.document_id)
+                .finalize(),
         }
     }
 
@@ -366,7 +424,12 @@ impl Task {
         match self.action {
             TaskAction::Index { .. } => FTS_LOCK_EXPIRY,
             TaskAction::BayesTrain { .. } => BAYES_LOCK_EXPIRY,
-            TaskAction::SendAlarm { .. } | TaskAction::SendImip => ALARM_EXPIRY,
+            TaskAction::SendAlarm { .. }
+            | TaskAction::SendImip
+            | TaskAction::SendLoginAlert
+            | TaskAction::Unsnooze
+            | TaskAction::SendQuotaWarning
+            | TaskAction::SendMdn => ALARM_EXPIRY,
         }
     }
 
@@ -391,13 +454,27 @@ impl Task {
                     due: self.due,
                     is_payload: false,
                 },
+                TaskAction::SendLoginAlert => TaskQueueClass::SendLoginAlert {
+                    due: self.due,
+                    is_payload: false,
+                },
+                TaskAction::Unsnooze => TaskQueueClass::Unsnooze { due: self.due },
+                TaskAction::SendQuotaWarning => TaskQueueClass::SendQuotaWarning { due: self.due },
+                TaskAction::SendMdn => TaskQueueClass::SendMdn { due: self.due },
             })),
-            (matches!(self.action, TaskAction::SendImip)).then_some(ValueClass::TaskQueue(
-                TaskQueueClass::SendImip {
+            match self.action {
+                TaskAction::SendImip => Some(ValueClass::TaskQueue(TaskQueueClass::SendImip {
                     due: self.due,
                     is_payload: true,
-                },
-            )),
+                })),
+                TaskAction::SendLoginAlert => {
+                    Some(ValueClass::TaskQueue(TaskQueueClass::SendLoginAlert {
+                        due: self.due,
+                        is_payload: true,
+                    }))
+                }
+                _ => None,
+            },
         ]
         .into_iter()
         .flatten()
@@ -451,6 +528,10 @@ impl Task {
                     },
                 },
                 Some(4) => TaskAction::SendImip,
+                Some(6) => TaskAction::SendLoginAlert,
+                Some(8) => TaskAction::Unsnooze,
+                Some(9) => TaskAction::SendQuotaWarning,
+                Some(10) => TaskAction::SendMdn,
                 _ => return Err(trc::Error::corrupted_key(key, None, trc::location!())),
             },
         })
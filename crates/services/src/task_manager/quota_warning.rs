@@ -0,0 +1,203 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::Task;
+use common::{
+    Server,
+    config::network::QuotaWarnings,
+    i18n,
+    listener::{ServerInstance, stream::NullIo},
+};
+use mail_builder::{MessageBuilder, headers::HeaderType};
+use smtp::core::{Session, SessionData};
+use smtp_proto::{MailFrom, RcptTo};
+use std::{fmt::Write, sync::Arc, time::Duration};
+use trc::AddContext;
+
+pub trait SendQuotaWarningTask: Sync + Send {
+    fn send_quota_warning(
+        &self,
+        task: &Task,
+        server_instance: Arc<ServerInstance>,
+    ) -> impl Future<Output = bool> + Send;
+}
+
+impl SendQuotaWarningTask for Server {
+    async fn send_quota_warning(&self, task: &Task, server_instance: Arc<ServerInstance>) -> bool {
+        match send_quota_warning(self, task, server_instance).await {
+            Ok(result) => result,
+            Err(err) => {
+                trc::error!(
+                    err.account_id(task.account_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to send quota warning")
+                );
+                false
+            }
+        }
+    }
+}
+
+async fn send_quota_warning(
+    server: &Server,
+    task: &Task,
+    server_instance: Arc<ServerInstance>,
+) -> trc::Result<bool> {
+    let config = &server.core.network.quota_warnings;
+
+    // Obtain access token
+    let access_token = server
+        .get_access_token(task.account_id)
+        .await
+        .caused_by(trc::location!())?;
+    let Some(account_main_email) = access_token.emails.first() else {
+        trc::event!(
+            Limit(trc::LimitEvent::QuotaWarningFailed),
+            AccountId = task.account_id,
+            Reason = "Account does not have any email addresses",
+        );
+        return Ok(true);
+    };
+    let account_main_domain = account_main_email.rsplit('@').next().unwrap_or("localhost");
+
+    // Usage might have dropped since the warning was queued
+    let quota = access_token.quota;
+    let used_quota = server
+        .get_used_quota(task.account_id)
+        .await
+        .caused_by(trc::location!())? as u64;
+    let Some(usage) = QuotaWarnings::usage_percent(used_quota, quota)
+        .filter(|usage| *usage >= task.document_id as u64)
+    else {
+        return Ok(true);
+    };
+
+    // Build message using the branding of the account's domain
+    let branding = server.core.network.branding(account_main_domain);
+    let locale = i18n::locale_or_default(
+        access_token
+            .locale
+            .as_deref()
+            .or_else(|| branding.and_then(|branding| branding.locale.as_deref()))
+            .unwrap_or("en"),
+    );
+    let mut body = String::with_capacity(512);
+    let _ = write!(
+        &mut body,
+        concat!(
+            "{}\r\n\r\n",
+            "Account: {}\r\n",
+            "Usage: {}% ({} of {} bytes)\r\n\r\n",
+            "{}\r\n"
+        ),
+        locale.quota_warning_text,
+        access_token.name,
+        usage,
+        used_quota,
+        quota,
+        locale.quota_warning_action,
+    );
+    if let Some(footer) = branding.and_then(|branding| branding.footer.as_deref()) {
+        let _ = write!(&mut body, "\r\n--\r\n{footer}\r\n");
+    }
+
+    let from_email = config
+        .from_email
+        .clone()
+        .unwrap_or_else(|| format!("no-reply@{account_main_domain}"));
+    let from_name = branding
+        .and_then(|branding| branding.name.as_deref())
+        .unwrap_or(config.from_name.as_str());
+    let rcpt_to = account_main_email.to_string();
+    let message = MessageBuilder::new()
+        .from((from_name, from_email.as_str()))
+        .header("To", HeaderType::Text(rcpt_to.as_str().into()))
+        .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+        .subject(locale.quota_warning_subject)
+        .text_body(body)
+        .write_to_vec()
+        .unwrap_or_default();
+
+    // Send message
+    let server_ = server.clone();
+    let mail_from = rcpt_to.clone();
+    let to = rcpt_to.clone();
+    let result = tokio::spawn(async move {
+        let mut session = Session::<NullIo>::local(
+            server_,
+            server_instance,
+            SessionData::local(access_token, None, vec![], vec![], 0),
+        );
+
+        // MAIL FROM
+        let _ = session
+            .handle_mail_from(MailFrom {
+                address: mail_from.into(),
+                ..Default::default()
+            })
+            .await;
+        if let Some(error) = session.has_failed() {
+            return Err(format!("Server rejected MAIL-FROM: {}", error.trim()));
+        }
+
+        // RCPT TO
+        session.params.rcpt_errors_wait = Duration::from_secs(0);
+        let _ = session
+            .handle_rcpt_to(RcptTo {
+                address: to.into(),
+                ..Default::default()
+            })
+            .await;
+        if let Some(error) = session.has_failed() {
+            return Err(format!("Server rejected RCPT-TO: {}", error.trim()));
+        }
+
+        // DATA
+        session.data.message = message;
+        let response = session.queue_message().await;
+        if let smtp::core::State::Accepted(queue_id) = session.state {
+            Ok(queue_id)
+        } else {
+            Err(format!(
+                "Server rejected DATA: {}",
+                std::str::from_utf8(&response).unwrap().trim()
+            ))
+        }
+    })
+    .await;
+
+    match result {
+        Ok(Ok(queue_id)) => {
+            trc::event!(
+                Limit(trc::LimitEvent::QuotaWarningSent),
+                AccountId = task.account_id,
+                Size = used_quota,
+                Limit = quota,
+                To = rcpt_to,
+                QueueId = queue_id,
+            );
+        }
+        Ok(Err(err)) => {
+            trc::event!(
+                Limit(trc::LimitEvent::QuotaWarningFailed),
+                AccountId = task.account_id,
+                To = rcpt_to,
+                Reason = err,
+            );
+        }
+        Err(_) => {
+            trc::event!(
+                Server(trc::ServerEvent::ThreadError),
+                Details = "Join Error",
+                AccountId = task.account_id,
+                CausedBy = trc::location!(),
+            );
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
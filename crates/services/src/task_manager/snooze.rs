@@ -0,0 +1,34 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::Task;
+use common::Server;
+use email::message::snooze::EmailSnooze;
+use std::future::Future;
+
+pub trait UnsnoozeTask: Sync + Send {
+    fn unsnooze(&self, task: &Task) -> impl Future<Output = bool> + Send;
+}
+
+impl UnsnoozeTask for Server {
+    async fn unsnooze(&self, task: &Task) -> bool {
+        match self
+            .unsnooze_email(task.account_id, task.document_id, task.due)
+            .await
+        {
+            Ok(result) => result,
+            Err(err) => {
+                trc::error!(
+                    err.account_id(task.account_id)
+                        .document_id(task.document_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to unsnooze message")
+                );
+                false
+            }
+        }
+    }
+}
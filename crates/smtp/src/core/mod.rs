@@ -14,19 +14,20 @@ use std::{
 use common::{
     Inner, Server,
     auth::AccessToken,
-    config::smtp::auth::VerifyStrategy,
+    config::smtp::{auth::VerifyStrategy, session::ConformanceAction},
     listener::{ServerInstance, asn::AsnGeoLookupResult},
 };
 
 use directory::Directory;
 use mail_auth::{IprevOutput, SpfOutput};
+use parking_lot::Mutex;
 use smtp_proto::request::receiver::{
     BdatReceiver, DataReceiver, DummyDataReceiver, DummyLineReceiver, LineReceiver, RequestReceiver,
 };
 use tokio::io::{AsyncRead, AsyncWrite};
 
 use crate::{
-    inbound::auth::SaslToken,
+    inbound::{auth::SaslToken, simulate::Simulation},
     queue::{DomainPart, QueueId},
 };
 
@@ -97,6 +98,22 @@ pub struct SessionData {
     pub spf_ehlo: Option<SpfOutput>,
     pub spf_mail_from: Option<SpfOutput>,
     pub dnsbl_error: Option<Vec<u8>>,
+
+    // Protocol conformance
+    pub pipelining: bool,
+    pub ends_with_cr: bool,
+    pub early_input: Vec<u8>,
+    pub conformance_tags: Vec<&'static str>,
+
+    // Per-recipient data responses
+    pub prdr_offered: bool,
+    pub prdr: bool,
+    pub prdr_rejected: Vec<(String, String)>,
+    pub command_line: Vec<u8>,
+    pub command_line_complete: bool,
+
+    // Policy checks recorded in simulation mode
+    pub simulation: Option<Mutex<Simulation>>,
 }
 
 #[derive(Clone, Debug)]
@@ -127,6 +144,7 @@ pub struct SessionParameters {
     pub rcpt_errors_max: usize,
     pub rcpt_errors_wait: Duration,
     pub rcpt_max: usize,
+    pub rcpt_domains_max: usize,
     pub rcpt_dsn: bool,
     pub can_expn: bool,
     pub can_vrfy: bool,
@@ -136,6 +154,10 @@ pub struct SessionParameters {
     pub iprev: VerifyStrategy,
     pub spf_ehlo: VerifyStrategy,
     pub spf_mail_from: VerifyStrategy,
+
+    // Protocol conformance
+    pub conformance_bare_lf: ConformanceAction,
+    pub conformance_pipelining: ConformanceAction,
 }
 
 impl SessionData {
@@ -174,6 +196,16 @@ impl SessionData {
             spf_ehlo: None,
             spf_mail_from: None,
             dnsbl_error: None,
+            pipelining: false,
+            ends_with_cr: false,
+            early_input: Vec::new(),
+            conformance_tags: Vec::new(),
+            prdr_offered: false,
+            prdr: false,
+            prdr_rejected: Vec::new(),
+            command_line: Vec::new(),
+            command_line_complete: true,
+            simulation: None,
         }
     }
 }
@@ -237,6 +269,7 @@ impl Session<common::listener::stream::NullIo> {
                 rcpt_errors_max: Default::default(),
                 rcpt_errors_wait: Default::default(),
                 rcpt_max: Default::default(),
+                rcpt_domains_max: Default::default(),
                 rcpt_dsn: Default::default(),
                 max_message_size: Default::default(),
                 iprev: VerifyStrategy::Disable,
@@ -244,6 +277,8 @@ impl Session<common::listener::stream::NullIo> {
                 spf_mail_from: VerifyStrategy::Disable,
                 can_expn: false,
                 can_vrfy: false,
+                conformance_bare_lf: ConformanceAction::Disable,
+                conformance_pipelining: ConformanceAction::Disable,
             },
         }
     }
@@ -298,6 +333,16 @@ impl SessionData {
             spf_ehlo: None,
             spf_mail_from: None,
             dnsbl_error: None,
+            pipelining: false,
+            ends_with_cr: false,
+            early_input: Vec::new(),
+            conformance_tags: Vec::new(),
+            prdr_offered: false,
+            prdr: false,
+            prdr_rejected: Vec::new(),
+            command_line: Vec::new(),
+            command_line_complete: true,
+            simulation: None,
         }
     }
 }
@@ -106,6 +106,19 @@ impl<T: SessionStream> Session<T> {
             .eval_if(&ec.vrfy, self, self.data.session_id)
             .await
             .unwrap_or(false);
+
+        // Protocol conformance parameters
+        let cc = &self.server.core.smtp.session.conformance;
+        self.params.conformance_bare_lf = self
+            .server
+            .eval_if(&cc.bare_lf, self, self.data.session_id)
+            .await
+            .unwrap_or_default();
+        self.params.conformance_pipelining = self
+            .server
+            .eval_if(&cc.pipelining, self, self.data.session_id)
+            .await
+            .unwrap_or_default();
     }
 
     pub async fn eval_post_auth_params(&mut self) {
@@ -140,6 +153,11 @@ impl<T: SessionStream> Session<T> {
             .eval_if(&rc.max_recipients, self, self.data.session_id)
             .await
             .unwrap_or(100);
+        self.params.rcpt_domains_max = self
+            .server
+            .eval_if(&rc.max_domains, self, self.data.session_id)
+            .await
+            .unwrap_or(0);
         self.params.rcpt_dsn = self
             .server
             .eval_if(
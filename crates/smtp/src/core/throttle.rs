@@ -124,7 +124,14 @@ impl NewKey for QueueRateLimiter {
             hasher.update(e.resolve_variable(V_MX).to_string().as_bytes());
         }
         if (self.keys & THROTTLE_REMOTE_IP) != 0 {
-            hasher.update(e.resolve_variable(V_REMOTE_IP).to_string().as_bytes());
+            let remote_ip = e.resolve_variable(V_REMOTE_IP);
+            let remote_ip = remote_ip.to_string();
+            if remote_ip.as_str() != "0.0.0.0" {
+                hasher.update(remote_ip.as_bytes());
+            } else {
+                // Hosts resolved by a proxy have no known address, use the host name instead
+                hasher.update(e.resolve_variable(V_MX).to_string().as_bytes());
+            }
         }
         if (self.keys & THROTTLE_LOCAL_IP) != 0 {
             hasher.update(e.resolve_variable(V_LOCAL_IP).to_string().as_bytes());
@@ -209,6 +216,24 @@ impl<T: SessionStream> Session<T> {
         true
     }
 
+    // Limits the rate of messages with an empty return path for each remote host
+    pub async fn is_null_sender_allowed(&self) -> bool {
+        match &self.server.core.smtp.session.null_sender.rate {
+            Some(rate)
+                if !self.is_submission()
+                    && self
+                        .data
+                        .mail_from
+                        .as_ref()
+                        .is_some_and(|mail_from| mail_from.address.is_empty()) =>
+            {
+                self.throttle_rcpt(&self.data.remote_ip_str, rate, "null-sender")
+                    .await
+            }
+            _ => true,
+        }
+    }
+
     pub async fn throttle_rcpt(&self, rcpt: &str, rate: &Rate, ctx: &str) -> bool {
         let mut hasher = blake3::Hasher::new();
         hasher.update(rcpt.as_bytes());
@@ -120,6 +120,11 @@ impl<T: SessionStream> Session<T> {
 
             match result {
                 Ok(access_token) => {
+                    self.server
+                        .inner
+                        .data
+                        .sessions
+                        .set_account_id(self.data.session_id, access_token.primary_id());
                     self.data.authenticated_as = access_token.into();
                     self.eval_post_auth_params().await;
                     self.write(b"235 2.7.0 Authentication succeeded.\r\n")
@@ -209,6 +214,55 @@ impl<T: SessionStream> Session<T> {
         self.data.authenticated_as.is_some()
     }
 
+    // Authenticated users can send from their own addresses, the senders granted
+    // to their principal and their active disposable aliases
+    pub async fn is_allowed_sender(&self, address_lcase: &str) -> bool {
+        let Some(token) = &self.data.authenticated_as else {
+            return false;
+        };
+        if token.name == address_lcase
+            || token.emails.iter().any(|e| {
+                e == address_lcase || (e.starts_with('@') && address_lcase.ends_with(e.as_str()))
+            })
+            || token
+                .allowed_senders
+                .iter()
+                .chain(token.send_as.iter())
+                .chain(token.send_on_behalf.iter())
+                .any(|e| e == address_lcase)
+        {
+            return true;
+        }
+
+        match self.server.disposable_alias_to_id(address_lcase).await {
+            Ok(account_id) => account_id == Some(token.primary_id()),
+            Err(err) => {
+                trc::error!(
+                    err.span_id(self.data.session_id)
+                        .caused_by(trc::location!())
+                );
+                false
+            }
+        }
+    }
+
+    // Returns the address to add as the Sender when submitting on behalf of a delegator
+    pub fn on_behalf_sender(&self, address_lcase: &str) -> Option<&str> {
+        let token = self.data.authenticated_as.as_ref()?;
+        if token.send_on_behalf.iter().any(|e| e == address_lcase)
+            && !token
+                .emails
+                .iter()
+                .chain(token.allowed_senders.iter())
+                .chain(token.send_as.iter())
+                .any(|e| e == address_lcase)
+        {
+            token.emails.first().map(|e| e.as_str())
+        } else {
+            None
+        }
+    }
+
     pub fn authenticated_emails(&self) -> &[String] {
         self.data
             .authenticated_as
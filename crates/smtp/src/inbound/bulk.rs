@@ -0,0 +1,94 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::listener::SessionStream;
+use mail_parser::MessageParser;
+
+use crate::{core::Session, reporting::feedback::FeedbackLoop};
+
+impl<T: SessionStream> Session<T> {
+    pub async fn add_bulk_headers(&self, raw_message: &[u8]) -> Option<Vec<u8>> {
+        let mail_from = self.data.mail_from.as_ref()?;
+        let sender_domain = mail_from.domain.to_lowercase();
+
+        for bulk in &self.server.core.smtp.session.bulk {
+            if !bulk.domains.contains(&sender_domain)
+                || !self
+                    .server
+                    .eval_if(&bulk.enable, self, self.data.session_id)
+                    .await
+                    .unwrap_or(false)
+            {
+                continue;
+            }
+
+            // Headers set by the sender are left untouched
+            let message = MessageParser::new().parse_headers(raw_message)?;
+            let has_header = |name: &str| {
+                message
+                    .root_part()
+                    .headers
+                    .iter()
+                    .any(|header| header.name.as_str().eq_ignore_ascii_case(name))
+            };
+            let mut headers = Vec::new();
+
+            if let Some(feedback_id) = &bulk.feedback_id
+                && !has_header("Feedback-ID")
+            {
+                headers.extend_from_slice(b"Feedback-ID: ");
+                headers.extend_from_slice(feedback_id.as_bytes());
+                headers.extend_from_slice(b"\r\n");
+            }
+
+            // One-click links identify a single recipient (RFC 8058)
+            if let (Some(unsubscribe), [rcpt]) = (&bulk.unsubscribe, self.data.rcpt_to.as_slice())
+                && !has_header("List-Unsubscribe")
+                && rcpt.address_lcase.is_ascii()
+            {
+                match self
+                    .server
+                    .unsubscribe_token(&sender_domain, &rcpt.address_lcase, unsubscribe.expiry)
+                    .await
+                {
+                    Ok(token) => {
+                        let url = form_urlencoded::Serializer::new(format!("{}?", unsubscribe.url))
+                            .append_pair("t", &token)
+                            .finish();
+                        headers.extend_from_slice(b"List-Unsubscribe: <");
+                        headers.extend_from_slice(url.as_bytes());
+                        headers.extend_from_slice(
+                            b">\r\nList-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n",
+                        );
+                    }
+                    Err(err) => {
+                        trc::error!(
+                            err.span_id(self.data.session_id)
+                                .caused_by(trc::location!())
+                                .details("Failed to generate unsubscribe token")
+                        );
+                    }
+                }
+            }
+
+            if headers.is_empty() {
+                return None;
+            }
+
+            trc::event!(
+                Smtp(trc::SmtpEvent::BulkHeadersAdded),
+                SpanId = self.data.session_id,
+                Id = bulk.id.clone(),
+                Domain = sender_domain,
+            );
+
+            headers.extend_from_slice(raw_message);
+            return Some(headers);
+        }
+
+        None
+    }
+}
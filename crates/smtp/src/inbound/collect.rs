@@ -0,0 +1,87 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashMap;
+use common::listener::SessionStream;
+use groupware::contact::collect::ContactCollect;
+use mail_parser::Message;
+
+use crate::core::Session;
+
+impl<T: SessionStream> Session<T> {
+    // Returns the recipients to be added to the sender's collected addresses,
+    // along with the display names found in the To and Cc headers
+    pub fn collected_recipients(
+        &self,
+        message: &Message<'_>,
+    ) -> Option<Vec<(String, Option<String>)>> {
+        let access_token = self
+            .data
+            .authenticated_as
+            .as_ref()
+            .filter(|access_token| access_token.collect_contacts)?;
+        self.server
+            .core
+            .groupware
+            .collected_addressbook_name
+            .as_ref()?;
+
+        let mut names = AHashMap::new();
+        for addr in message
+            .to()
+            .into_iter()
+            .chain(message.cc())
+            .flat_map(|addr| addr.iter())
+        {
+            if let (Some(address), Some(name)) = (addr.address(), addr.name()) {
+                names.insert(address.to_lowercase(), name.trim());
+            }
+        }
+
+        let recipients = self
+            .data
+            .rcpt_to
+            .iter()
+            .filter(|rcpt| !access_token.emails.contains(&rcpt.address_lcase))
+            .map(|rcpt| {
+                (
+                    rcpt.address_lcase.clone(),
+                    names.get(&rcpt.address_lcase).map(|name| name.to_string()),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        (!recipients.is_empty()).then_some(recipients)
+    }
+
+    pub fn collect_contacts(&self, recipients: Vec<(String, Option<String>)>) {
+        let Some(access_token) = self.data.authenticated_as.clone() else {
+            return;
+        };
+        let server = self.server.clone();
+        let session_id = self.data.session_id;
+
+        tokio::spawn(async move {
+            match server.collect_contacts(&access_token, recipients).await {
+                Ok(0) => {}
+                Ok(total) => {
+                    trc::event!(
+                        Smtp(trc::SmtpEvent::ContactsCollected),
+                        SpanId = session_id,
+                        AccountId = access_token.primary_id,
+                        Total = total,
+                    );
+                }
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(session_id)
+                            .details("Failed to collect recipient addresses")
+                    );
+                }
+            }
+        });
+    }
+}
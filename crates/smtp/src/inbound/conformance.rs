@@ -0,0 +1,70 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::core::Session;
+use common::{config::smtp::session::ConformanceAction, listener::SessionStream};
+use trc::SmtpEvent;
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_conformance_violation(
+        &mut self,
+        action: ConformanceAction,
+        tag: &'static str,
+        event: SmtpEvent,
+    ) -> Result<(), ()> {
+        match action {
+            ConformanceAction::Disable => Ok(()),
+            ConformanceAction::Tag => {
+                // Violations are reported to the spam filter once per session
+                if !self.data.conformance_tags.contains(&tag) {
+                    trc::event!(
+                        Smtp(event),
+                        SpanId = self.data.session_id,
+                        RemoteIp = self.data.remote_ip,
+                    );
+                    self.data.conformance_tags.push(tag);
+                }
+                Ok(())
+            }
+            ConformanceAction::Reject => {
+                trc::event!(
+                    Smtp(event),
+                    SpanId = self.data.session_id,
+                    RemoteIp = self.data.remote_ip,
+                );
+                let _ = self
+                    .write(b"554 5.5.0 Protocol violation, closing connection.\r\n")
+                    .await;
+                Err(())
+            }
+        }
+    }
+
+    pub fn has_bare_lf(&self, bytes: &[u8]) -> bool {
+        let mut prev_cr = self.data.ends_with_cr;
+        let mut line_start = 0;
+
+        for (pos, &ch) in bytes.iter().enumerate() {
+            if ch == b'\n' {
+                if !prev_cr {
+                    return true;
+                }
+
+                // Anything after a BDAT command is binary content
+                if bytes
+                    .get(line_start..line_start + 5)
+                    .is_some_and(|cmd| cmd.eq_ignore_ascii_case(b"BDAT "))
+                {
+                    return false;
+                }
+                line_start = pos + 1;
+            }
+            prev_cr = ch == b'\r';
+        }
+
+        false
+    }
+}
@@ -4,13 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use super::{ArcSeal, AuthResult, DkimSign};
+use super::{ArcSeal, AuthResult, DkimSign, prdr::prdr_reply, spam::RecipientVariables};
 use crate::{
     core::{Session, SessionAddress, State},
     inbound::milter::Modification,
     queue::{
-        self, DomainPart, Message, MessageSource, MessageWrapper, QueueEnvelope,
-        quota::HasQueueQuota,
+        self, DomainPart, HELD_FOR_APPROVAL, Message, MessageSource, MessageWrapper, QueueEnvelope,
+        TLS_OPTIONAL, moderation::QueueModeration, quota::HasQueueQuota, spool::SmtpSpool,
     },
     reporting::analysis::AnalyzeReport,
     scripts::ScriptResult,
@@ -20,7 +20,7 @@ use common::{
         smtp::{
             auth::VerifyStrategy,
             queue::{QueueExpiry, QueueName},
-            session::Stage,
+            session::{DlpAction, Stage},
         },
         spamfilter::SpamFilterAction,
     },
@@ -28,22 +28,24 @@ use common::{
     psl,
     scripts::ScriptModification,
 };
+use email::message::autocrypt::AutocryptManager;
 use mail_auth::{
-    AuthenticatedMessage, AuthenticationResults, DkimResult, DmarcResult, ReceivedSpf,
+    AuthenticatedMessage, AuthenticationResults, DkimResult, DmarcResult, ReceivedSpf, SpfResult,
     common::{headers::HeaderWriter, verify::VerifySignature},
     dmarc::{self, verify::DmarcParameters},
 };
 use mail_builder::headers::{date::Date, message_id::generate_message_id_header};
-use mail_parser::MessageParser;
-use sieve::runtime::Variable;
+use mail_parser::{HeaderName, MessageParser};
+use sieve::{Envelope, runtime::Variable};
 use smtp_proto::{
-    MAIL_BY_RETURN, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
+    MAIL_BY_RETURN, MAIL_REQUIRETLS, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER,
+    RCPT_NOTIFY_SUCCESS,
 };
 use std::{
     borrow::Cow,
     time::{Instant, SystemTime},
 };
-use trc::SmtpEvent;
+use trc::{SieveEvent, SmtpEvent};
 use utils::config::Rate;
 
 impl<T: SessionStream> Session<T> {
@@ -65,6 +67,41 @@ impl<T: SessionStream> Session<T> {
             }
         };
 
+        // Make sure that the From header matches the envelope sender
+        if self.is_authenticated()
+            && self
+                .server
+                .eval_if(
+                    &self.server.core.smtp.session.auth.must_match_from,
+                    self,
+                    self.data.session_id,
+                )
+                .await
+                .unwrap_or(false)
+            && let Some(mail_from) = self
+                .data
+                .mail_from
+                .as_ref()
+                .filter(|mail_from| !mail_from.address_lcase.is_empty())
+            && let Some(from) = parsed_message.from().and_then(|from| {
+                from.iter().find_map(|addr| {
+                    addr.address()
+                        .map(|address| address.trim().to_lowercase())
+                        .filter(|address| address != &mail_from.address_lcase)
+                })
+            })
+        {
+            trc::event!(
+                Smtp(SmtpEvent::FromHeaderUnauthorized),
+                SpanId = self.data.session_id,
+                From = mail_from.address_lcase.clone(),
+                Details = from,
+            );
+
+            return (&b"550 5.7.1 The From header does not match the envelope sender.\r\n"[..])
+                .into();
+        }
+
         // Authenticate message
         let auth_message = AuthenticatedMessage::from_parsed(
             &parsed_message,
@@ -72,6 +109,30 @@ impl<T: SessionStream> Session<T> {
         );
         let has_date_header = auth_message.has_date_header();
         let has_message_id_header = auth_message.has_message_id_header();
+        let on_behalf_sender = if parsed_message.sender().is_none() {
+            self.data
+                .mail_from
+                .as_ref()
+                .and_then(|mail_from| self.on_behalf_sender(&mail_from.address_lcase))
+                .map(|sender| sender.to_string())
+        } else {
+            None
+        };
+        let add_autocrypt_header = self.server.core.jmap.autocrypt_add_header
+            && !parsed_message.root_part().headers.iter().any(|header| {
+                matches!(&header.name, HeaderName::Other(name)
+                    if name.eq_ignore_ascii_case("Autocrypt"))
+            });
+
+        // Senders can request TLS policies to be ignored (RFC 8689 Section 5)
+        let tls_optional = parsed_message.root_part().headers.iter().any(|header| {
+            matches!(&header.name, HeaderName::Other(name)
+                if name.eq_ignore_ascii_case("TLS-Required"))
+                && header
+                    .value()
+                    .as_text()
+                    .is_some_and(|value| value.trim().eq_ignore_ascii_case("no"))
+        });
 
         // Loop detection
         let dc = &self.server.core.smtp.session.data;
@@ -122,10 +183,11 @@ impl<T: SessionStream> Session<T> {
             let rejected = strict && !pass;
 
             // Send reports for failed signatures
-            if let Some(rate) = self
-                .server
-                .eval_if::<Rate, _>(&rc.dkim.send, self, self.data.session_id)
-                .await
+            if !self.is_simulation()
+                && let Some(rate) = self
+                    .server
+                    .eval_if::<Rate, _>(&rc.dkim.send, self, self.data.session_id)
+                    .await
             {
                 for output in &dkim_output {
                     if let Some(rcpt) = output.failure_report_addr() {
@@ -146,6 +208,15 @@ impl<T: SessionStream> Session<T> {
                 Result = dkim_output.iter().map(trc::Error::from).collect::<Vec<_>>(),
                 Elapsed = time.elapsed(),
             );
+            for output in &dkim_output {
+                self.simulation_step(
+                    "data",
+                    "dkim",
+                    output.signature().map(|s| s.domain().to_string()),
+                    output.result().as_str(),
+                    None,
+                );
+            }
 
             if rejected {
                 // 'Strict' mode violates the advice of Section 6.1 of RFC6376
@@ -200,6 +271,7 @@ impl<T: SessionStream> Session<T> {
                 Result = trc::Error::from(arc_output.result()),
                 Elapsed = time.elapsed(),
             );
+            self.simulation_step("data", "arc", None, arc_output.result().as_str(), None);
 
             if strict && !pass {
                 return if matches!(arc_output.result(), DkimResult::TempError(_)) {
@@ -301,9 +373,16 @@ impl<T: SessionStream> Session<T> {
                     Result = trc::Error::from(&dmarc_result),
                     Elapsed = time.elapsed(),
                 );
+                self.simulation_step(
+                    "data",
+                    "dmarc",
+                    dmarc_output.domain().to_string().into(),
+                    dmarc_result.as_str(),
+                    dmarc_policy.as_str().to_string().into(),
+                );
 
                 // Send DMARC report
-                if dmarc_output.requested_reports() && !is_report {
+                if dmarc_output.requested_reports() && !is_report && !self.is_simulation() {
                     self.send_dmarc_report(
                         &auth_message,
                         &auth_results,
@@ -329,7 +408,32 @@ impl<T: SessionStream> Session<T> {
         };
 
         // Analyze reports
-        if is_report {
+        if is_report && !self.is_simulation() {
+            // Reporters are authenticated by a passing DKIM signature or SPF
+            // check aligned with the From domain
+            let from_domain = auth_message
+                .from()
+                .rsplit_once('@')
+                .map(|(_, domain)| domain.to_lowercase())
+                .unwrap_or_default();
+            let is_aligned = |domain: &str| {
+                let domain = domain.to_lowercase();
+                psl::domain_str(&domain).unwrap_or(&domain)
+                    == psl::domain_str(&from_domain).unwrap_or(&from_domain)
+            };
+            let is_authenticated = !from_domain.is_empty()
+                && (dkim_output.iter().any(|output| {
+                    matches!(output.result(), DkimResult::Pass)
+                        && output
+                            .signature()
+                            .is_some_and(|signature| is_aligned(signature.domain()))
+                }) || (self
+                    .data
+                    .spf_mail_from
+                    .as_ref()
+                    .is_some_and(|spf| spf.result() == SpfResult::Pass)
+                    && is_aligned(&mail_from.domain)));
+
             if !rc.analysis.forward {
                 self.server.analyze_report(
                     mail_parser::Message {
@@ -343,6 +447,7 @@ impl<T: SessionStream> Session<T> {
                             .collect(),
                         raw_message: b"".into(),
                     },
+                    is_authenticated,
                     self.data.session_id,
                 );
                 self.data.messages_sent += 1;
@@ -360,6 +465,7 @@ impl<T: SessionStream> Session<T> {
                             .collect(),
                         raw_message: b"".into(),
                     },
+                    is_authenticated,
                     self.data.session_id,
                 );
             }
@@ -425,6 +531,7 @@ impl<T: SessionStream> Session<T> {
         }
 
         // Run SPAM filter
+        let mut spam_status = None;
         if self.server.core.spam.enabled
             && self
                 .server
@@ -432,7 +539,7 @@ impl<T: SessionStream> Session<T> {
                 .await
                 .unwrap_or(true)
         {
-            match self
+            let classification = self
                 .spam_classify(
                     &parsed_message,
                     &dkim_output,
@@ -440,25 +547,103 @@ impl<T: SessionStream> Session<T> {
                     dmarc_result.as_ref(),
                     dmarc_policy.as_ref(),
                 )
-                .await
-            {
-                SpamFilterAction::Allow(spam_headers) => {
-                    if !spam_headers.is_empty() {
-                        headers.extend_from_slice(spam_headers.as_bytes());
+                .await;
+
+            if let Some(verdicts) = self.spam_recipient_verdicts(&classification).await {
+                // Apply each recipient's filtering profile
+                let mut spam_rcpts = Vec::new();
+                let mut has_rejects = false;
+                let mut rcpt_to = Vec::with_capacity(self.data.rcpt_to.len());
+                for (rcpt, verdict) in std::mem::take(&mut self.data.rcpt_to)
+                    .into_iter()
+                    .zip(verdicts)
+                {
+                    match verdict {
+                        SpamFilterAction::Allow(is_spam) => {
+                            if is_spam {
+                                spam_rcpts.push(rcpt.address_lcase.clone());
+                            }
+                            rcpt_to.push(rcpt);
+                        }
+                        SpamFilterAction::Discard => (),
+                        SpamFilterAction::Reject => {
+                            trc::event!(
+                                Smtp(SmtpEvent::RecipientSpamRejected),
+                                SpanId = self.data.session_id,
+                                To = rcpt.address_lcase.clone(),
+                                Details = classification.score,
+                            );
+
+                            self.data.prdr_rejected.push((
+                                rcpt.address_lcase,
+                                format!(
+                                    "550 5.7.1 <{}> Message rejected due to excessive spam score.\r\n",
+                                    rcpt.address
+                                ),
+                            ));
+                            has_rejects = true;
+                        }
                     }
                 }
-                SpamFilterAction::Discard => {
+                self.data.rcpt_to = rcpt_to;
+
+                // Without PRDR a single reply covers all recipients
+                if self.data.rcpt_to.is_empty() || (has_rejects && !self.data.prdr) {
                     self.data.messages_sent += 1;
-                    return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
+                    return if has_rejects {
+                        (b"550 5.7.1 Message rejected due to excessive spam score.\r\n"[..]).into()
+                    } else {
+                        (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
+                    };
                 }
-                SpamFilterAction::Reject => {
-                    self.data.messages_sent += 1;
-                    return (b"550 5.7.1 Message rejected due to excessive spam score.\r\n"[..])
-                        .into();
+
+                headers.extend_from_slice(classification.results.as_bytes());
+                spam_status = Some((headers.len(), spam_rcpts, classification.score));
+            } else {
+                match classification.action {
+                    SpamFilterAction::Allow(spam_headers) => {
+                        if !spam_headers.is_empty() {
+                            headers.extend_from_slice(spam_headers.as_bytes());
+                        }
+                    }
+                    SpamFilterAction::Discard => {
+                        self.data.messages_sent += 1;
+                        return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
+                    }
+                    SpamFilterAction::Reject => {
+                        self.data.messages_sent += 1;
+                        return (b"550 5.7.1 Message rejected due to excessive spam score.\r\n"[..])
+                            .into();
+                    }
                 }
             }
         }
 
+        // Run DLP rules
+        // Messages can only be held when there is a moderator to release them
+        let dlp_action = self.run_dlp_rules(&parsed_message).await;
+        if let Some(action) = dlp_action {
+            self.simulation_step("data", "dlp", None, action.as_str(), None);
+        }
+        if dlp_action == Some(DlpAction::Reject)
+            || (dlp_action == Some(DlpAction::Quarantine)
+                && self
+                    .server
+                    .eval_if::<String, _>(
+                        &self.server.core.smtp.queue.moderation.address,
+                        self,
+                        self.data.session_id,
+                    )
+                    .await
+                    .is_none())
+        {
+            self.data.messages_sent += 1;
+            return (b"550 5.7.1 Message rejected due to content policy.\r\n"[..]).into();
+        }
+
+        // Gather recipients for the sender's collected addresses
+        let collected_recipients = self.collected_recipients(&parsed_message);
+
         // Run Milter filters
         let mut modifications = Vec::new();
         match self.run_milters(Stage::Data, (&auth_message).into()).await {
@@ -488,6 +673,26 @@ impl<T: SessionStream> Session<T> {
             }
         };
 
+        // Run SpamAssassin
+        match self.run_spamd(&auth_message).await {
+            Ok(modifications_) => {
+                modifications.extend(modifications_);
+            }
+            Err(response) => {
+                return response.into_bytes();
+            }
+        };
+
+        // Run Rspamd
+        match self.run_rspamd(&auth_message).await {
+            Ok(modifications_) => {
+                modifications.extend(modifications_);
+            }
+            Err(response) => {
+                return response.into_bytes();
+            }
+        };
+
         // Apply modifications
         let mut edited_message = if !modifications.is_empty() {
             self.data
@@ -496,19 +701,24 @@ impl<T: SessionStream> Session<T> {
             None
         };
 
-        // Sieve filtering
-        if let Some((script, script_id)) = self
-            .server
-            .eval_if::<String, _>(&dc.script, self, self.data.session_id)
-            .await
-            .and_then(|name| {
+        // Sieve filtering, each recipient may select a different script
+        let mut scripts = Vec::with_capacity(self.data.rcpt_to.len());
+        for rcpt in &self.data.rcpt_to {
+            scripts.push(
                 self.server
-                    .get_trusted_sieve_script(&name, self.data.session_id)
-                    .map(|s| (s, name))
-            })
-        {
-            let params = self
-                .build_script_parameters("data")
+                    .eval_if::<String, _>(
+                        &dc.script,
+                        &RecipientVariables {
+                            session: self,
+                            rcpt,
+                        },
+                        self.data.session_id,
+                    )
+                    .await,
+            );
+        }
+        let build_params = || {
+            self.build_script_parameters("data")
                 .with_auth_headers(&headers)
                 .set_variable(
                     "arc.result",
@@ -554,129 +764,415 @@ impl<T: SessionStream> Session<T> {
                         .map(|a| a.as_str())
                         .unwrap_or_default(),
                 )
-                .with_message(parsed_message);
-
-            let modifications = match self.run_script(script_id, script.clone(), params).await {
-                ScriptResult::Accept { modifications } => modifications,
-                ScriptResult::Replace {
-                    message,
-                    modifications,
-                } => {
-                    edited_message = message.into();
-                    modifications
+        };
+
+        let mut rcpt_headers = Vec::new();
+        if scripts.windows(2).all(|w| w[0] == w[1]) {
+            if let Some((script, script_id)) =
+                scripts.into_iter().next().flatten().and_then(|name| {
+                    self.server
+                        .get_trusted_sieve_script(&name, self.data.session_id)
+                        .map(|s| (s, name))
+                })
+            {
+                let params = build_params().with_message(parsed_message);
+                let modifications = match self.run_script(script_id, script.clone(), params).await {
+                    ScriptResult::Accept { modifications } => modifications,
+                    ScriptResult::Replace {
+                        message,
+                        modifications,
+                    } => {
+                        edited_message = message.into();
+                        modifications
+                    }
+                    ScriptResult::Reject(message) => {
+                        return message.as_bytes().to_vec().into();
+                    }
+                    ScriptResult::Discard => {
+                        return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
+                    }
+                };
+
+                // Apply modifications
+                for modification in modifications {
+                    match modification {
+                        ScriptModification::AddHeader { name, value } => {
+                            write_header(&mut headers, &name, &value);
+                        }
+                        ScriptModification::SetEnvelope { name, value } => {
+                            self.data.apply_envelope_modification(name, value);
+                        }
+                    }
                 }
-                ScriptResult::Reject(message) => {
-                    return message.as_bytes().to_vec().into();
+            }
+        } else {
+            // Run each script once for the recipients that selected it. Only the headers
+            // added by each script are applied per copy, message replacements and envelope
+            // changes cannot be honoured when recipients share a single DATA transaction.
+            let mut results: Vec<Result<Vec<u8>, Option<String>>> =
+                vec![Ok(Vec::new()); scripts.len()];
+            let mut script_ids = scripts.iter().flatten().collect::<Vec<_>>();
+            script_ids.sort_unstable();
+            script_ids.dedup();
+            for script_id in script_ids {
+                let Some(script) = self
+                    .server
+                    .get_trusted_sieve_script(script_id, self.data.session_id)
+                else {
+                    continue;
+                };
+                let params = build_params()
+                    .with_message(parsed_message.clone())
+                    .replace_envelope(
+                        Envelope::To,
+                        self.data
+                            .rcpt_to
+                            .iter()
+                            .zip(&scripts)
+                            .filter(|(_, name)| name.as_ref() == Some(script_id))
+                            .map(|(rcpt, _)| Variable::from(rcpt.address_lcase.to_string()))
+                            .collect::<Vec<_>>(),
+                    );
+
+                let result = match self
+                    .run_script(script_id.clone(), script.clone(), params)
+                    .await
+                {
+                    ScriptResult::Accept { modifications } => Ok(modifications),
+                    ScriptResult::Replace { modifications, .. } => {
+                        trc::event!(
+                            Sieve(SieveEvent::NotSupported),
+                            SpanId = self.data.session_id,
+                            Id = script_id.clone(),
+                            Details = "Message replacement ignored for diverging recipients"
+                        );
+                        Ok(modifications)
+                    }
+                    ScriptResult::Reject(message) => Err(Some(message)),
+                    ScriptResult::Discard => Err(None),
                 }
-                ScriptResult::Discard => {
-                    return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
+                .map(|modifications| {
+                    let mut extra_headers = Vec::new();
+                    for modification in modifications {
+                        match modification {
+                            ScriptModification::AddHeader { name, value } => {
+                                write_header(&mut extra_headers, &name, &value);
+                            }
+                            ScriptModification::SetEnvelope { .. } => {
+                                trc::event!(
+                                    Sieve(SieveEvent::NotSupported),
+                                    SpanId = self.data.session_id,
+                                    Id = script_id.clone(),
+                                    Details = "Envelope change ignored for diverging recipients"
+                                );
+                            }
+                        }
+                    }
+                    extra_headers
+                });
+                for (rcpt_result, name) in results.iter_mut().zip(&scripts) {
+                    if name.as_ref() == Some(script_id) {
+                        *rcpt_result = result.clone();
+                    }
                 }
-            };
+            }
 
-            // Apply modifications
-            for modification in modifications {
-                match modification {
-                    ScriptModification::AddHeader { name, value } => {
-                        headers.extend_from_slice(name.as_bytes());
-                        headers.extend_from_slice(b": ");
-                        headers.extend_from_slice(value.as_bytes());
-                        if !value.ends_with('\n') {
-                            headers.extend_from_slice(b"\r\n");
-                        }
+            let mut reject_reply = None;
+            let mut rcpt_to = Vec::with_capacity(self.data.rcpt_to.len());
+            for (rcpt, result) in std::mem::take(&mut self.data.rcpt_to)
+                .into_iter()
+                .zip(results)
+            {
+                match result {
+                    Ok(extra_headers) => {
+                        rcpt_to.push(rcpt);
+                        rcpt_headers.push(extra_headers);
                     }
-                    ScriptModification::SetEnvelope { name, value } => {
-                        self.data.apply_envelope_modification(name, value);
+                    Err(Some(message)) => {
+                        // Without PRDR a single reply covers all recipients
+                        if !self.data.prdr {
+                            self.data.messages_sent += 1;
+                            return message.into_bytes().into();
+                        }
+                        let reply = prdr_reply(&message, &rcpt.address);
+                        self.data.prdr_rejected.push((rcpt.address_lcase, reply));
+                        reject_reply = Some(message);
                     }
+                    Err(None) => {}
                 }
             }
+            self.data.rcpt_to = rcpt_to;
+
+            if self.data.rcpt_to.is_empty() {
+                self.data.messages_sent += 1;
+                return reject_reply.map_or(
+                    (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into(),
+                    |reply| reply.into_bytes().into(),
+                );
+            }
         }
 
-        // Build message
-        let mail_from = self.data.mail_from.clone().unwrap();
-        let rcpt_to = std::mem::take(&mut self.data.rcpt_to);
-        let mut message = self
-            .build_message(mail_from, rcpt_to, message_id, self.data.session_id)
-            .await;
+        // Simulations end once every policy has been applied
+        if self.is_simulation() {
+            self.simulation(|simulation| {
+                simulation.delivered = self
+                    .data
+                    .rcpt_to
+                    .iter()
+                    .map(|rcpt| {
+                        (
+                            rcpt.address_lcase.clone(),
+                            spam_status
+                                .as_ref()
+                                .map(|(_, spam_rcpts, _)| spam_rcpts.contains(&rcpt.address_lcase)),
+                        )
+                    })
+                    .collect();
+            });
+            return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
+        }
 
-        // Add Return-Path
-        if self
-            .server
-            .eval_if(&dc.add_return_path, self, self.data.session_id)
+        // Apply subject and header tags
+        if let Some(message) = self
+            .apply_message_tags(edited_message.as_deref().unwrap_or(raw_message.as_slice()))
             .await
-            .unwrap_or(true)
         {
-            headers.extend_from_slice(b"Return-Path: <");
-            headers.extend_from_slice(message.message.return_path.as_bytes());
-            headers.extend_from_slice(b">\r\n");
+            edited_message = message.into();
         }
 
-        // Add any missing headers
-        if !has_date_header
-            && self
-                .server
-                .eval_if(&dc.add_date, self, self.data.session_id)
-                .await
-                .unwrap_or(true)
+        // Offload large attachments
+        if let Some(message) = self
+            .offload_attachments(edited_message.as_deref().unwrap_or(raw_message.as_slice()))
+            .await
         {
-            headers.extend_from_slice(b"Date: ");
-            headers.extend_from_slice(Date::now().to_rfc822().as_bytes());
-            headers.extend_from_slice(b"\r\n");
+            edited_message = message.into();
         }
-        if !has_message_id_header
-            && self
-                .server
-                .eval_if(&dc.add_message_id, self, self.data.session_id)
-                .await
-                .unwrap_or(true)
+
+        // Add disclaimer
+        if let Some(message) = self
+            .add_disclaimer(edited_message.as_deref().unwrap_or(raw_message.as_slice()))
+            .await
         {
-            headers.extend_from_slice(b"Message-ID: ");
-            let _ = generate_message_id_header(&mut headers, &self.hostname);
-            headers.extend_from_slice(b"\r\n");
+            edited_message = message.into();
         }
 
-        // DKIM sign
-        let raw_message = edited_message.as_deref().unwrap_or(raw_message.as_slice());
-        for signer in self
-            .server
-            .eval_if::<Vec<String>, _>(&ac.dkim.sign, self, self.data.session_id)
+        // Remove user agent headers from trusted relays
+        let is_trusted_relay = self.is_trusted_relay();
+        if is_trusted_relay
+            && let Some(message) = self
+                .remove_relay_headers(edited_message.as_deref().unwrap_or(raw_message.as_slice()))
+        {
+            edited_message = message.into();
+        }
+
+        // Add bulk sender headers
+        if let Some(message) = self
+            .add_bulk_headers(edited_message.as_deref().unwrap_or(raw_message.as_slice()))
             .await
-            .unwrap_or_default()
         {
-            if let Some(signer) = self.server.get_dkim_signer(&signer, self.data.session_id) {
-                match signer.sign_chained(&[headers.as_ref(), raw_message]) {
-                    Ok(signature) => {
-                        signature.write_header(&mut headers);
-                    }
-                    Err(err) => {
-                        trc::error!(
-                            trc::Error::from(err)
-                                .span_id(self.data.session_id)
-                                .details("Failed to DKIM sign message")
-                        );
-                    }
+            edited_message = message.into();
+        }
+
+        // Encrypt the message for its recipients when required by a DLP rule,
+        // messages that cannot be encrypted for every recipient are rejected
+        if dlp_action == Some(DlpAction::Encrypt) {
+            match self
+                .dlp_encrypt(edited_message.as_deref().unwrap_or(raw_message.as_slice()))
+                .await
+            {
+                Ok(Some(message)) => {
+                    edited_message = message.into();
+                }
+                Ok(None) => (),
+                Err(err) => {
+                    trc::error!(err.span_id(self.data.session_id));
+                    self.data.messages_sent += 1;
+                    return (b"550 5.7.1 Message could not be encrypted as required by content policy.\r\n"[..])
+                        .into();
                 }
             }
         }
 
-        // Update size
-        message.message.size = (raw_message.len() + headers.len()) as u64;
+        // Build message
+        let mail_from = self.data.mail_from.clone().unwrap();
+        let rcpt_to = std::mem::take(&mut self.data.rcpt_to);
 
-        // Verify queue quota
-        if self.server.has_quota(&mut message).await {
-            // Prepare webhook event
-            let queue_id = message.queue_id;
+        // Advertise the sender's Autocrypt key on submission
+        let autocrypt_header = if add_autocrypt_header
+            && let Some(token) = &self.data.authenticated_as
+            && token.emails.contains(&mail_from.address_lcase)
+        {
+            self.server
+                .autocrypt_header(token.primary_id(), &mail_from.address_lcase)
+                .await
+                .unwrap_or_else(|err| {
+                    trc::error!(
+                        err.span_id(self.data.session_id)
+                            .caused_by(trc::location!())
+                    );
+                    None
+                })
+        } else {
+            None
+        };
 
-            // Queue message
-            let source = if !self.is_authenticated() {
-                MessageSource::Unauthenticated(
-                    dmarc_result.is_some_and(|result| result == DmarcResult::Pass),
-                )
+        // Queue a separate copy for each combination of spam verdict and Sieve headers
+        let mut copies: Vec<(bool, &[u8], Vec<SessionAddress>)> = Vec::with_capacity(1);
+        for (rcpt_num, rcpt) in rcpt_to.into_iter().enumerate() {
+            let is_spam = spam_status
+                .as_ref()
+                .is_some_and(|(_, spam_rcpts, _)| spam_rcpts.contains(&rcpt.address_lcase));
+            let extra_headers = rcpt_headers
+                .get(rcpt_num)
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            if let Some((_, _, rcpt_to)) = copies
+                .iter_mut()
+                .find(|(spam, extra, _)| *spam == is_spam && *extra == extra_headers)
+            {
+                rcpt_to.push(rcpt);
             } else {
-                MessageSource::Authenticated
+                copies.push((is_spam, extra_headers, vec![rcpt]));
+            }
+        }
+
+        // Build all copies and verify their quotas before queuing any of them,
+        // a partial failure would otherwise duplicate the queued copies on retry
+        let raw_message = edited_message.as_deref().unwrap_or(raw_message.as_slice());
+        let mut messages = Vec::with_capacity(copies.len());
+        for (copy_num, (is_spam, extra_headers, rcpt_to)) in copies.into_iter().enumerate() {
+            let mut headers = headers.clone();
+            if let Some((pos, _, score)) = &spam_status {
+                let mut status = String::new();
+                self.server
+                    .core
+                    .spam
+                    .headers
+                    .write_status(&mut status, is_spam, *score);
+                headers.splice(*pos..*pos, status.into_bytes());
+            }
+            headers.extend_from_slice(extra_headers);
+
+            let message_id = if copy_num == 0 {
+                message_id
+            } else {
+                self.server.inner.data.queue_id_gen.generate()
             };
+            let mut message = self
+                .build_message(mail_from.clone(), rcpt_to, message_id, self.data.session_id)
+                .await;
+
+            // Apply DLP action
+            match dlp_action {
+                Some(DlpAction::RequireTls) => {
+                    message.message.flags |= MAIL_REQUIRETLS;
+                }
+                Some(DlpAction::Quarantine) => {
+                    message.message.flags |= HELD_FOR_APPROVAL;
+                }
+                _ => {}
+            }
+
+            // REQUIRETLS takes precedence over the TLS-Required header
+            if tls_optional && (message.message.flags & MAIL_REQUIRETLS) == 0 {
+                message.message.flags |= TLS_OPTIONAL;
+            }
+
+            // Add Return-Path
+            if self
+                .server
+                .eval_if(&dc.add_return_path, self, self.data.session_id)
+                .await
+                .unwrap_or(true)
+            {
+                headers.extend_from_slice(b"Return-Path: <");
+                headers.extend_from_slice(message.message.return_path.as_bytes());
+                headers.extend_from_slice(b">\r\n");
+            }
+
+            // Add any missing headers
+            if !has_date_header
+                && (is_trusted_relay
+                    || self
+                        .server
+                        .eval_if(&dc.add_date, self, self.data.session_id)
+                        .await
+                        .unwrap_or(true))
+            {
+                headers.extend_from_slice(b"Date: ");
+                headers.extend_from_slice(Date::now().to_rfc822().as_bytes());
+                headers.extend_from_slice(b"\r\n");
+            }
+            if !has_message_id_header
+                && (is_trusted_relay
+                    || self
+                        .server
+                        .eval_if(&dc.add_message_id, self, self.data.session_id)
+                        .await
+                        .unwrap_or(true))
+            {
+                headers.extend_from_slice(b"Message-ID: ");
+                let _ = generate_message_id_header(&mut headers, &self.hostname);
+                headers.extend_from_slice(b"\r\n");
+            }
+            if let Some(sender) = &on_behalf_sender {
+                headers.extend_from_slice(b"Sender: <");
+                headers.extend_from_slice(sender.as_bytes());
+                headers.extend_from_slice(b">\r\n");
+            }
+            if let Some(autocrypt_header) = &autocrypt_header {
+                headers.extend_from_slice(autocrypt_header.as_bytes());
+            }
+
+            // DKIM sign
+            for signer in self
+                .server
+                .eval_if::<Vec<String>, _>(&ac.dkim.sign, self, self.data.session_id)
+                .await
+                .unwrap_or_default()
+            {
+                if let Some(signer) = self.server.get_dkim_signer(&signer, self.data.session_id) {
+                    match signer.sign_chained(&[headers.as_ref(), raw_message]) {
+                        Ok(signature) => {
+                            signature.write_header(&mut headers);
+                        }
+                        Err(err) => {
+                            trc::error!(
+                                trc::Error::from(err)
+                                    .span_id(self.data.session_id)
+                                    .details("Failed to DKIM sign message")
+                            );
+                        }
+                    }
+                }
+            }
+
+            // Update size
+            message.message.size = (raw_message.len() + headers.len()) as u64;
+
+            // Verify queue quota
+            if !self.server.has_quota(&mut message).await {
+                return (b"452 4.3.1 Mail system full, try again later.\r\n"[..]).into();
+            }
+
+            messages.push((message, headers));
+        }
+
+        // Queue messages
+        let source = if !self.is_submission() {
+            MessageSource::Unauthenticated(
+                dmarc_result.is_some_and(|result| result == DmarcResult::Pass),
+            )
+        } else {
+            MessageSource::Authenticated
+        };
+        let mut queued = Vec::with_capacity(messages.len());
+        for (message, headers) in &messages {
+            let queue_id = message.queue_id;
             if message
+                .clone()
                 .queue(
-                    Some(&headers),
+                    Some(headers),
                     raw_message,
                     self.data.session_id,
                     &self.server,
@@ -684,17 +1180,58 @@ impl<T: SessionStream> Session<T> {
                 )
                 .await
             {
-                self.state = State::Accepted(queue_id);
-                self.data.messages_sent += 1;
-                format!("250 2.0.0 Message queued with id {queue_id:x}.\r\n")
-                    .into_bytes()
-                    .into()
+                queued.push(queue_id);
             } else {
-                (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into()
+                // Remove the copies queued so far so the client can safely retry
+                for queue_id in queued {
+                    if let Some(message) = self
+                        .server
+                        .read_message(queue_id, QueueName::default())
+                        .await
+                    {
+                        message.remove(&self.server, None).await;
+                    }
+                }
+
+                return (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into();
             }
-        } else {
-            (b"452 4.3.1 Mail system full, try again later.\r\n"[..]).into()
         }
+
+        let mut response = None;
+        self.data.messages_sent += 1;
+        for (message, headers) in messages {
+            let queue_id = message.queue_id;
+            self.state = State::Accepted(queue_id);
+            let result = if dlp_action == Some(DlpAction::Quarantine) {
+                trc::event!(
+                    Queue(trc::QueueEvent::MessageHeld),
+                    SpanId = self.data.session_id,
+                    QueueId = queue_id,
+                    From = message.message.return_path.clone(),
+                );
+
+                // Notify moderator
+                let mut held_raw_message = Vec::with_capacity(headers.len() + raw_message.len());
+                held_raw_message.extend_from_slice(&headers);
+                held_raw_message.extend_from_slice(raw_message);
+                self.server
+                    .notify_moderator(&message, &held_raw_message)
+                    .await;
+
+                format!("250 2.0.0 Message held for approval with id {queue_id:x}.\r\n")
+            } else {
+                format!("250 2.0.0 Message queued with id {queue_id:x}.\r\n")
+            };
+            response.get_or_insert(result);
+        }
+
+        if let Some(recipients) = collected_recipients {
+            self.collect_contacts(recipients);
+        }
+
+        response
+            .map(|response| response.into_bytes().into())
+            .unwrap_or_else(|| (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into())
     }
 
     pub async fn build_message(
@@ -927,3 +1464,12 @@ impl<T: SessionStream> Session<T> {
         headers.extend_from_slice(b"\r\n");
     }
 }
+
+fn write_header(headers: &mut Vec<u8>, name: &str, value: &str) {
+    headers.extend_from_slice(name.as_bytes());
+    headers.extend_from_slice(b": ");
+    headers.extend_from_slice(value.as_bytes());
+    if !value.ends_with('\n') {
+        headers.extend_from_slice(b"\r\n");
+    }
+}
@@ -0,0 +1,246 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{
+    config::smtp::session::{Disclaimer, SignedMessagePolicy},
+    listener::SessionStream,
+};
+use mail_builder::{encoders::base64::base64_encode_mime, mime::make_boundary};
+use mail_parser::{
+    Encoding, HeaderName, Message, MessageParser, MessagePart, MimeHeaders, PartType,
+};
+use utils::splice_bytes;
+
+use crate::core::Session;
+
+impl<T: SessionStream> Session<T> {
+    pub async fn add_disclaimer(&self, raw_message: &[u8]) -> Option<Vec<u8>> {
+        for disclaimer in &self.server.core.smtp.session.disclaimers {
+            if self
+                .server
+                .eval_if(&disclaimer.enable, self, self.data.session_id)
+                .await
+                .unwrap_or(false)
+            {
+                let result = MessageParser::new()
+                    .parse(raw_message)
+                    .and_then(|message| apply_disclaimer(&message, disclaimer));
+
+                if result.is_some() {
+                    trc::event!(
+                        Smtp(trc::SmtpEvent::DisclaimerAdded),
+                        SpanId = self.data.session_id,
+                        Id = disclaimer.id.clone(),
+                    );
+                }
+
+                return result;
+            }
+        }
+
+        None
+    }
+}
+
+// Returns the message with the disclaimer added, or None if the message was not modified
+pub fn apply_disclaimer(message: &Message<'_>, disclaimer: &Disclaimer) -> Option<Vec<u8>> {
+    let root = message.root_part();
+
+    // Any change to the body would invalidate existing DKIM signatures
+    if root
+        .headers
+        .iter()
+        .any(|h| h.name.as_str().eq_ignore_ascii_case("DKIM-Signature"))
+    {
+        return None;
+    }
+
+    let is_signed = root.content_type().is_some_and(|ct| {
+        let subtype = ct.subtype().unwrap_or_default();
+        (ct.ctype().eq_ignore_ascii_case("multipart")
+            && (subtype.eq_ignore_ascii_case("signed")
+                || subtype.eq_ignore_ascii_case("encrypted")))
+            || (ct.ctype().eq_ignore_ascii_case("application")
+                && (subtype.eq_ignore_ascii_case("pkcs7-mime")
+                    || subtype.eq_ignore_ascii_case("x-pkcs7-mime")))
+    });
+
+    if !is_signed {
+        append_disclaimer(message, disclaimer)
+    } else if disclaimer.on_signed == SignedMessagePolicy::Wrap {
+        wrap_message(message, disclaimer)
+    } else {
+        None
+    }
+}
+
+// Appends the disclaimer to each plain text and HTML body part
+fn append_disclaimer(message: &Message<'_>, disclaimer: &Disclaimer) -> Option<Vec<u8>> {
+    let raw_message = message.raw_message();
+    let mut edits: Vec<(usize, usize, Vec<u8>)> = Vec::new();
+    let mut part_ids = message
+        .text_body
+        .iter()
+        .chain(message.html_body.iter())
+        .copied()
+        .collect::<Vec<_>>();
+    part_ids.sort_unstable();
+    part_ids.dedup();
+
+    for part_id in part_ids {
+        let part = message.parts.get(part_id as usize)?;
+        let (contents, ctype, append) = match &part.body {
+            PartType::Text(text) => (
+                append_text(text, &disclaimer.text),
+                "text/plain",
+                disclaimer.text.as_str(),
+            ),
+            PartType::Html(html) => (
+                append_html(html, &disclaimer.html),
+                "text/html",
+                disclaimer.html.as_str(),
+            ),
+            _ => continue,
+        };
+
+        // Only rewrite parts that can be re-encoded without charset conversion
+        let charset = part.content_type().and_then(|ct| ct.attribute("charset"));
+        let is_utf8 = charset.is_some_and(|charset| {
+            charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("utf8")
+        });
+        if !charset.is_none_or(|charset| is_utf8 || charset.eq_ignore_ascii_case("us-ascii")) {
+            continue;
+        }
+
+        if part.encoding == Encoding::None && (is_utf8 || append.is_ascii()) {
+            edits.push((
+                part.offset_body as usize,
+                part.offset_end as usize,
+                contents.into_bytes(),
+            ));
+        } else {
+            // Re-encode the part as UTF-8 base64
+            let mut body = Vec::with_capacity(contents.len() * 4 / 3 + 4);
+            base64_encode_mime(contents.as_bytes(), &mut body, false).ok()?;
+            set_part_header(
+                &mut edits,
+                part,
+                HeaderName::ContentType,
+                format!("Content-Type: {ctype}; charset=utf-8\r\n"),
+            );
+            set_part_header(
+                &mut edits,
+                part,
+                HeaderName::ContentTransferEncoding,
+                "Content-Transfer-Encoding: base64\r\n".to_string(),
+            );
+            edits.push((part.offset_body as usize, part.offset_end as usize, body));
+        }
+    }
+
+    if edits.is_empty() {
+        return None;
+    }
+
+    splice_bytes(raw_message, edits)
+}
+
+// Encloses signed or encrypted messages in a multipart/mixed container so the
+// original entity, and its signature, are left untouched
+fn wrap_message(message: &Message<'_>, disclaimer: &Disclaimer) -> Option<Vec<u8>> {
+    let raw_message = message.raw_message();
+    let root = message.root_part();
+    let boundary = make_boundary("_");
+    let mut result = Vec::with_capacity(raw_message.len() + 1024);
+    let mut content_headers = Vec::new();
+
+    for header in &root.headers {
+        let bytes = raw_message.get(header.offset_field as usize..header.offset_end as usize)?;
+        let name = header.name.as_str();
+        if name
+            .get(..8)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("Content-"))
+        {
+            content_headers.extend_from_slice(bytes);
+        } else if !name.eq_ignore_ascii_case("MIME-Version") {
+            result.extend_from_slice(bytes);
+        }
+    }
+
+    result
+        .extend_from_slice(b"MIME-Version: 1.0\r\nContent-Type: multipart/mixed;\r\n\tboundary=\"");
+    result.extend_from_slice(boundary.as_bytes());
+    result.extend_from_slice(b"\"\r\n\r\n--");
+    result.extend_from_slice(boundary.as_bytes());
+    result.extend_from_slice(b"\r\n");
+    result.extend_from_slice(&content_headers);
+    result.extend_from_slice(b"\r\n");
+    result.extend_from_slice(raw_message.get(root.offset_body as usize..root.offset_end as usize)?);
+    result.extend_from_slice(b"\r\n--");
+    result.extend_from_slice(boundary.as_bytes());
+    result.extend_from_slice(
+        concat!(
+            "\r\nContent-Type: text/plain; charset=utf-8\r\n",
+            "Content-Disposition: inline\r\n",
+            "Content-Transfer-Encoding: base64\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    base64_encode_mime(disclaimer.text.as_bytes(), &mut result, false).ok()?;
+    result.extend_from_slice(b"\r\n--");
+    result.extend_from_slice(boundary.as_bytes());
+    result.extend_from_slice(b"--\r\n");
+
+    Some(result)
+}
+
+fn append_text(text: &str, disclaimer: &str) -> String {
+    let text = text.trim_end();
+    let mut result = String::with_capacity(text.len() + disclaimer.len() + 8);
+    result.push_str(text);
+    result.push_str("\r\n\r\n");
+    result.push_str(disclaimer);
+    result.push_str("\r\n");
+    result
+}
+
+fn append_html(html: &str, disclaimer: &str) -> String {
+    // Insert the disclaimer before the closing body tag, if present
+    let pos = html
+        .to_ascii_lowercase()
+        .rfind("</body")
+        .unwrap_or_else(|| html.trim_end().len());
+    let mut result = String::with_capacity(html.len() + disclaimer.len() + 2);
+    result.push_str(&html[..pos]);
+    result.push_str(disclaimer);
+    result.push_str(&html[pos..]);
+    if !result.ends_with('\n') {
+        result.push_str("\r\n");
+    }
+    result
+}
+
+fn set_part_header(
+    edits: &mut Vec<(usize, usize, Vec<u8>)>,
+    part: &MessagePart<'_>,
+    name: HeaderName<'static>,
+    value: String,
+) {
+    if let Some(header) = part.headers.iter().find(|h| h.name == name) {
+        edits.push((
+            header.offset_field as usize,
+            header.offset_end as usize,
+            value.into_bytes(),
+        ));
+    } else {
+        let pos = part
+            .headers
+            .last()
+            .map(|h| h.offset_end)
+            .unwrap_or(part.offset_header) as usize;
+        edits.push((pos, pos, value.into_bytes()));
+    }
+}
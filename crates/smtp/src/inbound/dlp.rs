@@ -0,0 +1,284 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::{Arc, LazyLock};
+
+use common::{
+    config::smtp::session::{DlpAction, DlpDictionary, DlpMatcher},
+    listener::SessionStream,
+};
+use email::message::{
+    autocrypt::AutocryptManager,
+    crypto::{Algorithm, EncryptMessage, EncryptMessageError, EncryptionMethod, EncryptionParams},
+};
+use mail_parser::{Message, MessageParser, PartType, decoders::html::html_to_text};
+use regex::Regex;
+use store::{
+    Deserialize, Serialize,
+    write::{AlignedBytes, Archive, Archiver},
+};
+use trc::{AddContext, SmtpEvent};
+
+use crate::core::Session;
+
+static CREDIT_CARD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:\d[ -]?){12,18}\d\b").unwrap());
+static IBAN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]){11,30}\b").unwrap());
+
+impl<T: SessionStream> Session<T> {
+    pub async fn run_dlp_rules(&self, message: &Message<'_>) -> Option<DlpAction> {
+        let rules = &self.server.core.smtp.session.dlp;
+        if rules.is_empty() {
+            return None;
+        }
+
+        let mut contents: Option<Vec<String>> = None;
+        let mut result = None;
+
+        for rule in rules {
+            if !self
+                .server
+                .eval_if(&rule.enable, self, self.data.session_id)
+                .await
+                .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let dictionary = match &rule.matcher {
+                DlpMatcher::Regex {
+                    dictionary: Some(dictionary),
+                    ..
+                } => self.dlp_dictionary(dictionary).await,
+                _ => None,
+            };
+            let matches = contents
+                .get_or_insert_with(|| message_text(message))
+                .iter()
+                .map(|text| {
+                    count_matches(
+                        &rule.matcher,
+                        dictionary.as_ref().map(|d| d.as_slice()),
+                        text,
+                    )
+                })
+                .sum::<usize>();
+
+            if matches >= rule.threshold && matches > 0 {
+                trc::event!(
+                    Smtp(SmtpEvent::DlpMatch),
+                    SpanId = self.data.session_id,
+                    Id = rule.id.clone(),
+                    Total = matches,
+                    Details = rule.action.as_str(),
+                );
+
+                result = result.max(Some(rule.action));
+            }
+        }
+
+        result
+    }
+
+    // Encrypts the message with the OpenPGP keys of all recipients, taken from their
+    // local accounts or from the sender's Autocrypt peers. Returns None when the
+    // message is already encrypted.
+    pub async fn dlp_encrypt(&self, raw_message: &[u8]) -> trc::Result<Option<Vec<u8>>> {
+        let message = MessageParser::default().parse(raw_message).ok_or_else(|| {
+            SmtpEvent::DlpEncryptionFailed
+                .into_err()
+                .details("Failed to parse message")
+        })?;
+        if message.is_encrypted() {
+            return Ok(None);
+        }
+
+        let account_id = self
+            .data
+            .authenticated_as
+            .as_ref()
+            .map(|token| token.primary_id());
+        let mut certs = Vec::with_capacity(self.data.rcpt_to.len());
+        for rcpt in &self.data.rcpt_to {
+            match self
+                .server
+                .get_encryption_key(account_id, &rcpt.address_lcase)
+                .await?
+            {
+                Some(key) => certs.push(key),
+                None => {
+                    return Err(SmtpEvent::DlpEncryptionFailed
+                        .into_err()
+                        .ctx(trc::Key::To, rcpt.address_lcase.clone())
+                        .details("No OpenPGP key available for recipient"));
+                }
+            }
+        }
+
+        let params = Archiver::new(EncryptionParams {
+            method: EncryptionMethod::PGP,
+            algo: Algorithm::Aes256,
+            certs,
+        })
+        .serialize()
+        .caused_by(trc::location!())?;
+        match message
+            .encrypt(
+                <Archive<AlignedBytes> as Deserialize>::deserialize(params.as_slice())?
+                    .unarchive::<EncryptionParams>()?,
+            )
+            .await
+        {
+            Ok(raw_message) => Ok(Some(raw_message)),
+            Err(EncryptMessageError::AlreadyEncrypted) => Ok(None),
+            Err(EncryptMessageError::Error(reason)) => {
+                Err(SmtpEvent::DlpEncryptionFailed.into_err().reason(reason))
+            }
+        }
+    }
+
+    async fn dlp_dictionary(&self, dictionary: &DlpDictionary) -> Option<Arc<Vec<Regex>>> {
+        let Some(store) = self.server.core.storage.lookups.get(&dictionary.store) else {
+            trc::error!(
+                trc::StoreEvent::NotConfigured
+                    .into_err()
+                    .span_id(self.data.session_id)
+                    .ctx(trc::Key::Id, dictionary.store.clone())
+                    .details("Unknown DLP dictionary store")
+            );
+            return None;
+        };
+
+        let patterns = match store.key_get::<String>(dictionary.key.as_str()).await {
+            Ok(Some(patterns)) => patterns,
+            Ok(None) => return None,
+            Err(err) => {
+                trc::error!(
+                    err.span_id(self.data.session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to load DLP dictionary")
+                );
+                return None;
+            }
+        };
+
+        let mut compiled = dictionary.compiled.lock();
+        if let Some((source, regexes)) = compiled.as_ref()
+            && *source == patterns
+        {
+            return Some(regexes.clone());
+        }
+
+        let mut regexes = Vec::new();
+        for pattern in patterns.lines().map(str::trim).filter(|p| !p.is_empty()) {
+            match Regex::new(pattern) {
+                Ok(regex) => regexes.push(regex),
+                Err(err) => {
+                    trc::event!(
+                        Config(trc::ConfigEvent::ParseError),
+                        SpanId = self.data.session_id,
+                        Id = dictionary.key.clone(),
+                        Details = pattern.to_string(),
+                        Reason = err.to_string(),
+                    );
+                }
+            }
+        }
+        let regexes = Arc::new(regexes);
+        *compiled = Some((patterns, regexes.clone()));
+        Some(regexes)
+    }
+}
+
+fn message_text(message: &Message<'_>) -> Vec<String> {
+    let mut contents = Vec::with_capacity(message.parts.len() + 1);
+
+    if let Some(subject) = message.subject() {
+        contents.push(subject.to_string());
+    }
+
+    for part in &message.parts {
+        match &part.body {
+            PartType::Text(text) => {
+                contents.push(text.to_string());
+            }
+            PartType::Html(html) => {
+                contents.push(html_to_text(html));
+            }
+            _ => {}
+        }
+    }
+
+    contents
+}
+
+fn count_matches(matcher: &DlpMatcher, dictionary: Option<&[Regex]>, text: &str) -> usize {
+    match matcher {
+        DlpMatcher::CreditCard => CREDIT_CARD
+            .find_iter(text)
+            .filter(|m| is_valid_card_number(m.as_str()))
+            .count(),
+        DlpMatcher::Iban => IBAN
+            .find_iter(text)
+            .filter(|m| is_valid_iban(m.as_str()))
+            .count(),
+        DlpMatcher::Regex { patterns, .. } => patterns
+            .iter()
+            .chain(dictionary.unwrap_or_default())
+            .map(|regex| regex.find_iter(text).count())
+            .sum(),
+    }
+}
+
+// Luhn checksum
+fn is_valid_card_number(number: &str) -> bool {
+    let digits = number
+        .bytes()
+        .filter(|ch| ch.is_ascii_digit())
+        .map(|ch| (ch - b'0') as u32)
+        .collect::<Vec<_>>();
+
+    (13..=19).contains(&digits.len())
+        && digits
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(pos, &digit)| {
+                if pos % 2 == 1 {
+                    let digit = digit * 2;
+                    if digit > 9 { digit - 9 } else { digit }
+                } else {
+                    digit
+                }
+            })
+            .sum::<u32>()
+            % 10
+            == 0
+}
+
+// ISO 13616 mod-97 checksum
+fn is_valid_iban(iban: &str) -> bool {
+    let iban = iban
+        .bytes()
+        .filter(|ch| !ch.is_ascii_whitespace())
+        .collect::<Vec<_>>();
+
+    if !(15..=34).contains(&iban.len()) {
+        return false;
+    }
+
+    let mut remainder = 0u32;
+    for &ch in iban[4..].iter().chain(iban[..4].iter()) {
+        remainder = match ch {
+            b'0'..=b'9' => (remainder * 10 + (ch - b'0') as u32) % 97,
+            b'A'..=b'Z' => (remainder * 100 + (ch - b'A' + 10) as u32) % 97,
+            _ => return false,
+        };
+    }
+
+    remainder == 1
+}
@@ -6,7 +6,10 @@
 
 use crate::{core::Session, scripts::ScriptResult};
 use common::{
-    config::smtp::session::{Mechanism, Stage},
+    config::{
+        server::ServerProtocol,
+        smtp::session::{Mechanism, Stage},
+    },
     listener::SessionStream,
 };
 use mail_auth::{
@@ -137,6 +140,8 @@ impl<T: SessionStream> Session<T> {
         }
 
         if !is_extended {
+            self.data.pipelining = false;
+            self.data.prdr_offered = false;
             return self
                 .write(format!("250 {} you had me at HELO\r\n", self.hostname).as_bytes())
                 .await;
@@ -153,12 +158,12 @@ impl<T: SessionStream> Session<T> {
         let dc = &self.server.core.smtp.session.data;
 
         // Pipelining
-        if self
+        self.data.pipelining = self
             .server
             .eval_if(&ec.pipelining, self, self.data.session_id)
             .await
-            .unwrap_or(true)
-        {
+            .unwrap_or(true);
+        if self.data.pipelining {
             response.capabilities |= EXT_PIPELINING;
         }
 
@@ -284,9 +289,71 @@ impl<T: SessionStream> Session<T> {
             };
         }
 
+        // Per-Recipient Data Response
+        self.data.prdr_offered = self.instance.protocol == ServerProtocol::Smtp
+            && self
+                .server
+                .eval_if(&ec.prdr, self, self.data.session_id)
+                .await
+                .unwrap_or(false);
+
         // Generate response
         let mut buf = Vec::with_capacity(64);
         response.write(&mut buf).ok();
+        if self.data.prdr_offered
+            && let Some(pos) = buf.windows(2).position(|w| w == b"\r\n")
+        {
+            buf.splice(pos + 2..pos + 2, b"250-PRDR\r\n".iter().copied());
+        }
+
+        // Limits (RFC 9422)
+        if self
+            .server
+            .eval_if(&ec.limits, self, self.data.session_id)
+            .await
+            .unwrap_or(false)
+        {
+            let rc = &self.server.core.smtp.session.rcpt;
+            let mut limits = String::new();
+            for (name, value) in [
+                (
+                    "RCPTMAX",
+                    self.server
+                        .eval_if(&rc.max_recipients, self, self.data.session_id)
+                        .await
+                        .unwrap_or(100usize),
+                ),
+                (
+                    "MAILMAX",
+                    self.server
+                        .eval_if(&dc.max_messages, self, self.data.session_id)
+                        .await
+                        .unwrap_or(10usize),
+                ),
+                (
+                    "RCPTDOMAINMAX",
+                    self.server
+                        .eval_if(&rc.max_domains, self, self.data.session_id)
+                        .await
+                        .unwrap_or(0usize),
+                ),
+            ] {
+                if value > 0 {
+                    limits.push_str(&format!(" {name}={value}"));
+                }
+            }
+
+            // Insert after the greeting line
+            if !limits.is_empty()
+                && let Some(pos) = buf.windows(2).position(|w| w == b"\r\n")
+            {
+                buf.splice(
+                    pos + 2..pos + 2,
+                    format!("250-LIMITS{limits}\r\n").into_bytes(),
+                );
+            }
+        }
+
         self.write(&buf).await
     }
 }
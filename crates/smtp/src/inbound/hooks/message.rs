@@ -9,11 +9,13 @@ use std::time::Instant;
 use ahash::AHashMap;
 use common::{
     DAEMON_NAME,
-    config::smtp::session::{MTAHook, Stage},
+    config::smtp::session::{HookMessageFormat, MTAHook, Stage},
     listener::SessionStream,
 };
 
 use mail_auth::AuthenticatedMessage;
+use mail_builder::encoders::base64::base64_encode;
+use mail_parser::{MessageParser, MimeHeaders, PartType, decoders::base64::base64_decode};
 use trc::MtaHookEvent;
 
 use crate::{
@@ -21,7 +23,8 @@ use crate::{
     inbound::{
         FilterResponse,
         hooks::{
-            Address, Client, Context, Envelope, Message, Protocol, Request, Sasl, Server, Tls,
+            Address, Client, Context, Envelope, Message, MimePart, Protocol, Request, Sasl, Server,
+            Tls,
         },
         milter::Modification,
     },
@@ -38,7 +41,7 @@ impl<T: SessionStream> Session<T> {
         queue_id: Option<QueueId>,
     ) -> Result<Vec<Modification>, FilterResponse> {
         let mta_hooks = &self.server.core.smtp.session.hooks;
-        if mta_hooks.is_empty() {
+        if mta_hooks.is_empty() || self.is_simulation() {
             return Ok(Vec::new());
         }
 
@@ -54,6 +57,13 @@ impl<T: SessionStream> Session<T> {
                 continue;
             }
 
+            // Messages exceeding the size limit are not sent to the hook
+            if let (Some(message), Some(max_size)) = (message, mta_hook.max_message_size)
+                && message.raw_message().len() > max_size
+            {
+                continue;
+            }
+
             let time = Instant::now();
             match self.run_mta_hook(stage, mta_hook, message, queue_id).await {
                 Ok(response) => {
@@ -92,6 +102,36 @@ impl<T: SessionStream> Session<T> {
                                     value: value.as_bytes().to_vec(),
                                 }
                             }
+                            super::Modification::ReplaceMessage { value, encoding } => {
+                                let value = if encoding
+                                    .as_deref()
+                                    .is_some_and(|e| e.eq_ignore_ascii_case("base64"))
+                                {
+                                    match base64_decode(value.as_bytes()) {
+                                        Some(value) => value,
+                                        None => {
+                                            trc::event!(
+                                                MtaHook(MtaHookEvent::Error),
+                                                SpanId = self.data.session_id,
+                                                Id = mta_hook.id.clone(),
+                                                Reason = "Failed to decode replacement message",
+                                            );
+                                            continue;
+                                        }
+                                    }
+                                } else {
+                                    value.into_bytes()
+                                };
+
+                                // Outbound messages are signed again after all transformations
+                                Modification::ReplaceMessage {
+                                    value: if self.is_authenticated() {
+                                        strip_dkim_signatures(value)
+                                    } else {
+                                        value
+                                    },
+                                }
+                            }
                             super::Modification::AddHeader { name, value } => {
                                 Modification::AddHeader { name, value }
                             }
@@ -245,7 +285,20 @@ impl<T: SessionStream> Session<T> {
                     })
                     .collect(),
                 server_headers: vec![],
-                contents: String::from_utf8_lossy(message.raw_body()).into_owned(),
+                contents: if mta_hook.message_format != HookMessageFormat::Raw {
+                    String::from_utf8_lossy(message.raw_body()).into_owned()
+                } else {
+                    String::new()
+                },
+                raw: (mta_hook.message_format == HookMessageFormat::Raw).then(|| {
+                    String::from_utf8(base64_encode(message.raw_message()).unwrap_or_default())
+                        .unwrap_or_default()
+                }),
+                parts: if mta_hook.message_format == HookMessageFormat::Mime {
+                    mime_structure(message.raw_message())
+                } else {
+                    vec![]
+                },
                 size: message.raw_message().len(),
             }),
         };
@@ -254,6 +307,72 @@ impl<T: SessionStream> Session<T> {
     }
 }
 
+fn mime_structure(raw_message: &[u8]) -> Vec<MimePart> {
+    let Some(message) = MessageParser::new().parse(raw_message) else {
+        return vec![];
+    };
+
+    message
+        .parts
+        .iter()
+        .map(|part| {
+            let content_type = part.content_type();
+            MimePart {
+                content_type: content_type
+                    .map(|ct| match ct.subtype() {
+                        Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                        None => ct.ctype().to_string(),
+                    })
+                    .unwrap_or_else(|| "text/plain".to_string())
+                    .to_ascii_lowercase(),
+                charset: content_type
+                    .and_then(|ct| ct.attribute("charset"))
+                    .map(|charset| charset.to_string()),
+                disposition: part
+                    .content_disposition()
+                    .map(|cd| cd.ctype().to_ascii_lowercase()),
+                name: part.attachment_name().map(|name| name.to_string()),
+                offset_header: part.offset_header,
+                offset_body: part.offset_body,
+                offset_end: part.offset_end,
+                parts: match &part.body {
+                    PartType::Multipart(parts) => parts.clone(),
+                    _ => vec![],
+                },
+            }
+        })
+        .collect()
+}
+
+fn strip_dkim_signatures(raw_message: Vec<u8>) -> Vec<u8> {
+    let Some(message) = AuthenticatedMessage::parse(&raw_message) else {
+        return raw_message;
+    };
+    let headers = message.raw_parsed_headers();
+    if !headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case(b"DKIM-Signature"))
+    {
+        return raw_message;
+    }
+
+    let mut result = Vec::with_capacity(raw_message.len());
+    for (name, value) in headers {
+        if !name.eq_ignore_ascii_case(b"DKIM-Signature") {
+            result.extend_from_slice(name);
+            result.push(b':');
+            result.extend_from_slice(value);
+            if value.last().is_none_or(|c| *c != b'\n') {
+                result.extend_from_slice(b"\r\n");
+            }
+        }
+    }
+    result.extend_from_slice(b"\r\n");
+    result.extend_from_slice(message.raw_body());
+
+    result
+}
+
 fn flatten_parameters(parameters: AHashMap<String, Option<String>>) -> String {
     let mut arguments = String::new();
     for (key, value) in parameters {
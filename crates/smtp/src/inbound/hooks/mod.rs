@@ -120,9 +120,40 @@ pub struct Message {
     #[serde(default)]
     pub server_headers: Vec<(String, String)>,
     pub contents: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub raw: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub parts: Vec<MimePart>,
     pub size: usize,
 }
 
+// Part offsets are relative to the start of the message, headers included
+#[derive(Serialize, Deserialize)]
+pub struct MimePart {
+    #[serde(rename = "contentType")]
+    pub content_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub charset: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub disposition: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(rename = "offsetHeader")]
+    pub offset_header: u32,
+    #[serde(rename = "offsetBody")]
+    pub offset_body: u32,
+    #[serde(rename = "offsetEnd")]
+    pub offset_end: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub parts: Vec<u32>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Response {
     pub action: Action,
@@ -175,6 +206,12 @@ pub enum Modification {
     DeleteRecipient { value: String },
     #[serde(rename = "replaceContents")]
     ReplaceContents { value: String },
+    #[serde(rename = "replaceMessage")]
+    ReplaceMessage {
+        value: String,
+        #[serde(default)]
+        encoding: Option<String>,
+    },
     #[serde(rename = "addHeader")]
     AddHeader { name: String, value: String },
     #[serde(rename = "insertHeader")]
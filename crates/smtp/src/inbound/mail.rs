@@ -11,7 +11,9 @@ use crate::{
 };
 use common::{config::smtp::session::Stage, listener::SessionStream, scripts::ScriptModification};
 use mail_auth::{IprevOutput, IprevResult, SpfOutput, SpfResult, spf::verify::SpfParameters};
-use smtp_proto::{MAIL_BY_NOTIFY, MAIL_BY_RETURN, MAIL_REQUIRETLS, MailFrom, MtPriority};
+use smtp_proto::{
+    MAIL_BY_NOTIFY, MAIL_BY_RETURN, MAIL_REQUIRETLS, MAIL_SMTPUTF8, MailFrom, MtPriority,
+};
 use std::{
     borrow::Cow,
     time::{Duration, Instant, SystemTime},
@@ -43,7 +45,7 @@ impl<T: SessionStream> Session<T> {
             return self
                 .write(b"503 5.5.1 Multiple MAIL commands not allowed.\r\n")
                 .await;
-        } else if self.params.auth_require && !self.is_authenticated() {
+        } else if self.params.auth_require && !self.is_submission() {
             trc::event!(
                 Smtp(SmtpEvent::MailFromUnauthenticated),
                 SpanId = self.data.session_id,
@@ -52,6 +54,16 @@ impl<T: SessionStream> Session<T> {
             return self
                 .write(b"503 5.5.1 You must authenticate first.\r\n")
                 .await;
+        } else if !from.address.is_ascii() && (from.flags & MAIL_SMTPUTF8) == 0 {
+            trc::event!(
+                Smtp(SmtpEvent::SmtpUtf8Required),
+                SpanId = self.data.session_id,
+                From = from.address.to_string(),
+            );
+
+            return self
+                .write(b"553 5.6.7 Non-ASCII addresses require SMTPUTF8.\r\n")
+                .await;
         } else if self.data.iprev.is_none() && self.params.iprev.verify() {
             let time = Instant::now();
             let iprev = self
@@ -244,12 +256,7 @@ impl<T: SessionStream> Session<T> {
                     .unwrap_or(true) =>
             {
                 let address_lcase = self.data.mail_from.as_ref().unwrap().address_lcase.as_str();
-                if authenticated_as != address_lcase
-                    && !self.authenticated_emails().iter().any(|e| {
-                        e == address_lcase
-                            || (e.starts_with('@') && address_lcase.ends_with(e.as_str()))
-                    })
-                {
+                if !self.is_allowed_sender(address_lcase).await {
                     trc::event!(
                         Smtp(SmtpEvent::MailFromUnauthorized),
                         SpanId = self.data.session_id,
@@ -437,7 +444,7 @@ impl<T: SessionStream> Session<T> {
                 .await;
         }
 
-        if self.is_allowed().await {
+        if self.is_allowed().await && self.is_null_sender_allowed().await {
             // Verify SPF
             if self.params.spf_mail_from.verify() {
                 let time = Instant::now();
@@ -549,16 +556,18 @@ impl<T: SessionStream> Session<T> {
         };
 
         // Send report
-        if let (Some(recipient), Some(rate)) = (
-            spf_output.report_address(),
-            self.server
-                .eval_if::<Rate, _>(
-                    &self.server.core.smtp.report.spf.send,
-                    self,
-                    self.data.session_id,
-                )
-                .await,
-        ) {
+        if !self.is_simulation()
+            && let (Some(recipient), Some(rate)) = (
+                spf_output.report_address(),
+                self.server
+                    .eval_if::<Rate, _>(
+                        &self.server.core.smtp.report.spf.send,
+                        self,
+                        self.data.session_id,
+                    )
+                    .await,
+            )
+        {
             // Do not send SPF auth failures to local domains, as they are likely relay attempts (which are blocked later on)
             match self
                 .server
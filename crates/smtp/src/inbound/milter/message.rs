@@ -37,7 +37,7 @@ impl<T: SessionStream> Session<T> {
         message: Option<&AuthenticatedMessage<'_>>,
     ) -> Result<Vec<Modification>, FilterResponse> {
         let milters = &self.server.core.smtp.session.milters;
-        if milters.is_empty() {
+        if milters.is_empty() || self.is_simulation() {
             return Ok(Vec::new());
         }
 
@@ -297,6 +297,7 @@ impl SessionData {
         message: &AuthenticatedMessage<'_>,
     ) -> Option<Vec<u8>> {
         let mut body = Vec::new();
+        let mut replacement = None;
         let mut header_changes = Vec::new();
         let mut needs_rewrite = false;
 
@@ -381,6 +382,9 @@ impl SessionData {
                 Modification::ReplaceBody { value } => {
                     body.extend(value);
                 }
+                Modification::ReplaceMessage { value } => {
+                    replacement = Some(value);
+                }
                 Modification::AddHeader { name, value } => {
                     header_changes.push((0, name, value, false));
                 }
@@ -407,6 +411,21 @@ impl SessionData {
             }
         }
 
+        // Replacing the entire message supersedes any body changes,
+        // header changes are applied on top of the replacement
+        if replacement.is_some() && header_changes.is_empty() {
+            return replacement;
+        }
+        let replaced_message = replacement.as_deref().and_then(AuthenticatedMessage::parse);
+        let message = match &replaced_message {
+            Some(replaced_message) => {
+                body.clear();
+                replaced_message
+            }
+            None if replacement.is_some() => return replacement.clone(),
+            None => message,
+        };
+
         // If there are no header changes return
         if header_changes.is_empty() {
             return if !body.is_empty() {
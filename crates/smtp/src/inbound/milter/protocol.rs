@@ -349,8 +349,8 @@ impl Response {
     }
 
     #[cfg(feature = "test_mode")]
-    pub fn serialize(&self) -> Vec<u8> {
-        match self {
+    pub fn serialize(&self) -> super::Result<Vec<u8>> {
+        Ok(match self {
             Response::Action(action) => match action {
                 Action::Accept => Command::build(SMFIR_ACCEPT, 0),
                 Action::Continue => Command::build(SMFIR_CONTINUE, 0),
@@ -400,6 +400,10 @@ impl Response {
                     buf.extend(value);
                     buf
                 }
+                Modification::ReplaceMessage { .. } => {
+                    // Milters cannot replace the entire message
+                    return Err(Error::Unexpected(Response::Modification(modif.clone())));
+                }
                 Modification::AddHeader { name, value } => {
                     let mut buf =
                         Command::build(SMFIR_ADDHEADER, name.len() as u32 + value.len() as u32 + 2);
@@ -456,7 +460,7 @@ impl Response {
                 buf.extend(opt.protocol.to_be_bytes().as_ref());
                 buf
             }
-        }
+        })
     }
 }
 
@@ -13,15 +13,27 @@ use mail_auth::{
 };
 
 pub mod auth;
+pub mod bulk;
+pub mod collect;
+pub mod conformance;
 pub mod data;
+pub mod disclaimer;
+pub mod dlp;
 pub mod ehlo;
 pub mod hooks;
 pub mod mail;
 pub mod milter;
+pub mod offload;
+pub mod prdr;
 pub mod rcpt;
+pub mod relay;
+pub mod rspamd;
 pub mod session;
+pub mod simulate;
 pub mod spam;
+pub mod spamd;
 pub mod spawn;
+pub mod tag;
 pub mod vrfy;
 
 #[derive(Debug, Default)]
@@ -0,0 +1,208 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::{auth::AccessToken, listener::SessionStream};
+use groupware::{DestroyArchive, file::share::FileShare};
+use mail_builder::encoders::base64::base64_encode_mime;
+use mail_parser::{DateTime, MessageParser, MimeHeaders, PartType};
+use store::write::now;
+use utils::splice_bytes;
+
+use crate::core::Session;
+
+impl<T: SessionStream> Session<T> {
+    // Replaces attachments above the configured size with a link to a copy
+    // stored in the sender's file area
+    pub async fn offload_attachments(&self, raw_message: &[u8]) -> Option<Vec<u8>> {
+        let access_token = self.data.authenticated_as.as_ref()?;
+        let config = &self.server.core.smtp.session.attachment_offload;
+        let session_id = self.data.session_id;
+        if !self
+            .server
+            .eval_if(&config.enable, self, session_id)
+            .await
+            .unwrap_or(false)
+        {
+            return None;
+        }
+        let min_size = self
+            .server
+            .eval_if::<u64, _>(&config.min_size, self, session_id)
+            .await
+            .unwrap_or(u64::MAX) as usize;
+        let expiry = self
+            .server
+            .eval_if::<Duration, _>(&config.expiry, self, session_id)
+            .await
+            .unwrap_or(Duration::from_secs(7 * 24 * 60 * 60))
+            .as_secs();
+        let max_downloads = self
+            .server
+            .eval_if::<u64, _>(&config.max_downloads, self, session_id)
+            .await
+            .filter(|max| *max > 0)
+            .map(|max| max.min(u32::MAX as u64) as u32);
+
+        let message = MessageParser::new().parse(raw_message)?;
+
+        // Any change to the body would invalidate existing DKIM signatures
+        if message
+            .root_part()
+            .headers
+            .iter()
+            .any(|h| h.name.as_str().eq_ignore_ascii_case("DKIM-Signature"))
+        {
+            return None;
+        }
+
+        let mut edits: Vec<(usize, usize, Vec<u8>)> = Vec::new();
+        let mut stored_ids = Vec::new();
+        for &part_id in &message.attachments {
+            // Single part messages can't be replaced without losing the body
+            if part_id == 0 {
+                continue;
+            }
+            let Some(part) = message.parts.get(part_id as usize) else {
+                continue;
+            };
+            let contents = match &part.body {
+                PartType::Binary(_)
+                | PartType::InlineBinary(_)
+                | PartType::Text(_)
+                | PartType::Html(_) => part.contents(),
+                _ => continue,
+            };
+            if contents.len() < min_size {
+                continue;
+            }
+
+            let name = part.attachment_name().unwrap_or("attachment");
+            let media_type = part.content_type().map(|ct| match ct.subtype() {
+                Some(subtype) => format!("{}/{}", ct.ctype(), subtype),
+                None => ct.ctype().to_string(),
+            });
+            let url = match self
+                .server
+                .file_share_store(
+                    access_token,
+                    &config.folder,
+                    name,
+                    media_type.as_deref(),
+                    contents,
+                )
+                .await
+            {
+                Ok((document_id, created)) => {
+                    match self
+                        .server
+                        .file_share_url(
+                            access_token.primary_id,
+                            document_id,
+                            created,
+                            expiry,
+                            max_downloads,
+                        )
+                        .await
+                    {
+                        Ok(Some(url)) => {
+                            stored_ids.push(document_id);
+                            url
+                        }
+                        Ok(None) => {
+                            stored_ids.push(document_id);
+                            self.remove_offloaded(access_token, stored_ids).await;
+                            return None;
+                        }
+                        Err(err) => {
+                            trc::error!(
+                                err.span_id(session_id)
+                                    .caused_by(trc::location!())
+                                    .details("Failed to create attachment link")
+                            );
+                            self.remove_offloaded(access_token, vec![document_id]).await;
+                            continue;
+                        }
+                    }
+                }
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(session_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to store attachment")
+                    );
+                    continue;
+                }
+            };
+
+            let mut stub = format!(
+                concat!(
+                    "The attachment \"{}\" ({} bytes) was removed from this message ",
+                    "and can be downloaded from:\r\n\r\n{}\r\n\r\nThe link expires on {}."
+                ),
+                name,
+                contents.len(),
+                url,
+                DateTime::from_timestamp((now() + expiry) as i64).to_rfc822()
+            );
+            if let Some(max_downloads) = max_downloads {
+                stub.push_str(&format!(
+                    " It can be downloaded up to {max_downloads} times."
+                ));
+            }
+            stub.push_str("\r\n");
+
+            let mut bytes = Vec::with_capacity(stub.len() * 4 / 3 + 128);
+            bytes.extend_from_slice(
+                concat!(
+                    "Content-Type: text/plain; charset=utf-8\r\n",
+                    "Content-Disposition: inline\r\n",
+                    "Content-Transfer-Encoding: base64\r\n\r\n"
+                )
+                .as_bytes(),
+            );
+            if base64_encode_mime(stub.as_bytes(), &mut bytes, false).is_err() {
+                self.remove_offloaded(access_token, stored_ids).await;
+                return None;
+            }
+            edits.push((part.offset_header as usize, part.offset_end as usize, bytes));
+
+            trc::event!(
+                Smtp(trc::SmtpEvent::AttachmentOffloaded),
+                SpanId = session_id,
+                AccountId = access_token.primary_id,
+                Size = contents.len(),
+                Expires = trc::Value::Timestamp(now() + expiry),
+            );
+        }
+
+        if edits.is_empty() {
+            return None;
+        }
+
+        let result = splice_bytes(message.raw_message(), edits);
+        if result.is_none() {
+            self.remove_offloaded(access_token, stored_ids).await;
+        }
+        result
+    }
+
+    // Files are only kept when the message is rewritten to link to them
+    async fn remove_offloaded(&self, access_token: &AccessToken, document_ids: Vec<u32>) {
+        if !document_ids.is_empty()
+            && let Err(err) = DestroyArchive(document_ids)
+                .delete(&self.server, access_token, access_token.primary_id, None)
+                .await
+        {
+            trc::error!(
+                err.span_id(self.data.session_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to remove offloaded attachments")
+            );
+        }
+    }
+}
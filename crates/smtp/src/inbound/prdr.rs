@@ -0,0 +1,118 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::borrow::Cow;
+
+use common::listener::SessionStream;
+use smtp_proto::Request;
+use trc::SmtpEvent;
+
+use crate::core::Session;
+
+impl<T: SessionStream> Session<T> {
+    // The PRDR parameter is not understood by the command parser, keep a copy of the current
+    // command line so MAIL FROM can be parsed again without it.
+    pub fn capture_command_line(&mut self, bytes: &[u8], is_partial: bool) {
+        if self.data.command_line_complete {
+            self.data.command_line.clear();
+        }
+        self.data.command_line.extend_from_slice(bytes);
+        self.data.command_line_complete = !is_partial;
+    }
+
+    pub async fn handle_prdr_mail_from(&mut self) -> Result<(), ()> {
+        let line = std::mem::take(&mut self.data.command_line);
+        let mut command = Vec::with_capacity(line.len());
+        for token in line
+            .trim_ascii_end()
+            .split(|&ch| ch == b' ')
+            .filter(|token| !token.eq_ignore_ascii_case(b"PRDR"))
+        {
+            if !command.is_empty() {
+                command.push(b' ');
+            }
+            command.extend_from_slice(token);
+        }
+        command.extend_from_slice(b"\r\n");
+
+        match Request::parse(&mut command.iter()) {
+            Ok(Request::Mail { from }) => {
+                let had_mail_from = self.data.mail_from.is_some();
+                self.handle_mail_from(from).await?;
+                if !had_mail_from && self.data.mail_from.is_some() {
+                    self.data.prdr = true;
+                }
+                Ok(())
+            }
+            _ => {
+                trc::event!(
+                    Smtp(SmtpEvent::InvalidParameter),
+                    SpanId = self.data.session_id,
+                    Details = "PRDR"
+                );
+
+                self.write(b"501 5.5.4 Invalid parameter \"PRDR\".\r\n")
+                    .await
+            }
+        }
+    }
+
+    pub async fn queue_message_prdr(&mut self) -> Cow<'static, [u8]> {
+        if !self.data.prdr {
+            return self.queue_message().await;
+        }
+
+        let rcpt_to = self
+            .data
+            .rcpt_to
+            .iter()
+            .map(|rcpt| (rcpt.address.clone(), rcpt.address_lcase.clone()))
+            .collect::<Vec<_>>();
+        let response = self.queue_message().await;
+
+        // A single response applies to all recipients unless their verdicts diverge
+        if self.data.prdr_rejected.is_empty() || !response.starts_with(b"2") {
+            return response;
+        }
+
+        let mut buf = Vec::with_capacity(64 * (rcpt_to.len() + 2));
+        buf.extend_from_slice(b"353 Content analysis has begun.\r\n");
+        for (address, address_lcase) in rcpt_to {
+            if let Some((_, reply)) = self
+                .data
+                .prdr_rejected
+                .iter()
+                .find(|(rejected, _)| rejected == &address_lcase)
+            {
+                buf.extend_from_slice(reply.as_bytes());
+            } else {
+                buf.extend_from_slice(
+                    format!("250 2.1.5 <{address}> Message accepted.\r\n").as_bytes(),
+                );
+            }
+        }
+        buf.extend_from_slice(&response);
+        buf.into()
+    }
+}
+
+// Rewrites a rejection reply so it names the recipient it applies to
+pub(crate) fn prdr_reply(reply: &str, address: &str) -> String {
+    let line = reply.lines().next().unwrap_or_default().trim_end();
+    let code = line.get(..3).unwrap_or("550");
+    let text = line
+        .get(3..)
+        .unwrap_or_default()
+        .trim_start_matches(['-', ' ']);
+    match text.split_once(' ') {
+        Some((status, text))
+            if status.starts_with(|ch: char| ch.is_ascii_digit()) && status.contains('.') =>
+        {
+            format!("{code} {status} <{address}> {text}\r\n")
+        }
+        _ => format!("{code} <{address}> {text}\r\n"),
+    }
+}
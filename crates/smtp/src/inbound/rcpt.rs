@@ -7,19 +7,24 @@
 use std::borrow::Cow;
 
 use common::{
-    KV_GREYLIST, config::smtp::session::Stage, listener::SessionStream, scripts::ScriptModification,
+    KV_GREYLIST,
+    config::smtp::session::{BatvAddress, SrsAddress, Stage},
+    listener::SessionStream,
+    scripts::ScriptModification,
 };
 
 use directory::backend::RcptType;
 use smtp_proto::{
-    RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS, RcptTo,
+    MAIL_SMTPUTF8, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
+    RcptTo,
 };
-use store::dispatch::lookup::KeyValue;
+use store::{dispatch::lookup::KeyValue, write::now};
 use trc::{SecurityEvent, SmtpEvent};
 
 use crate::{
     core::{Session, SessionAddress},
     queue::DomainPart,
+    reporting::feedback::FeedbackLoop,
     scripts::ScriptResult,
 };
 
@@ -56,6 +61,24 @@ impl<T: SessionStream> Session<T> {
             return self.write(b"455 4.5.3 Too many recipients.\r\n").await;
         }
 
+        // Internationalized addresses require SMTPUTF8 (RFC 6531)
+        if !to.address.is_ascii()
+            && self
+                .data
+                .mail_from
+                .as_ref()
+                .is_some_and(|mail_from| (mail_from.flags & MAIL_SMTPUTF8) == 0)
+        {
+            trc::event!(
+                Smtp(SmtpEvent::SmtpUtf8Required),
+                SpanId = self.data.session_id,
+                To = to.address.to_string(),
+            );
+            return self
+                .write(b"553 5.6.7 Non-ASCII addresses require SMTPUTF8.\r\n")
+                .await;
+        }
+
         // Verify parameters
         if ((to.flags
             & (RCPT_NOTIFY_DELAY | RCPT_NOTIFY_NEVER | RCPT_NOTIFY_SUCCESS | RCPT_NOTIFY_FAILURE)
@@ -71,7 +94,7 @@ impl<T: SessionStream> Session<T> {
 
         // Build RCPT
         let address_lcase = to.address.to_lowercase();
-        let rcpt = SessionAddress {
+        let mut rcpt = SessionAddress {
             domain: address_lcase.domain_part().into(),
             address_lcase,
             address: to.address.into_owned(),
@@ -79,6 +102,128 @@ impl<T: SessionStream> Session<T> {
             dsn_info: to.orcpt.map(|e| e.into_owned()),
         };
 
+        // Remove the tag from signed return paths
+        let null_sender = &self.server.core.smtp.session.null_sender;
+        let batv = null_sender
+            .batv
+            .as_ref()
+            .map(|batv| (batv.verify(&rcpt.address, now()), batv.require));
+        if let Some((BatvAddress::Valid(address), _)) = &batv {
+            rcpt.address_lcase = address.to_lowercase();
+            rcpt.domain = rcpt.address_lcase.domain_part().into();
+            rcpt.address = address.clone();
+        }
+
+        // Backscatter protection
+        let mut is_srs_bounce = false;
+        if self
+            .data
+            .mail_from
+            .as_ref()
+            .is_some_and(|mail_from| mail_from.address.is_empty())
+            && !self.is_submission()
+        {
+            if null_sender.single_recipient && !self.data.rcpt_to.is_empty() {
+                trc::event!(
+                    Smtp(SmtpEvent::NullSenderMultipleRecipients),
+                    SpanId = self.data.session_id,
+                    To = rcpt.address_lcase,
+                );
+                return self
+                    .write(b"550 5.5.3 Null sender messages must have a single recipient.\r\n")
+                    .await;
+            }
+
+            if let Some((batv, require_tag)) = batv {
+                match batv {
+                    BatvAddress::Valid(_) => {}
+                    BatvAddress::Invalid => {
+                        trc::event!(
+                            Smtp(SmtpEvent::BounceTagInvalid),
+                            SpanId = self.data.session_id,
+                            To = rcpt.address_lcase.clone(),
+                        );
+                        return self
+                            .rcpt_error(
+                                b"550 5.7.1 Invalid or expired bounce address tag.\r\n",
+                                rcpt.address_lcase,
+                            )
+                            .await;
+                    }
+                    BatvAddress::Unsigned if require_tag => {
+                        match self
+                            .server
+                            .core
+                            .storage
+                            .directory
+                            .is_local_domain(&rcpt.domain)
+                            .await
+                        {
+                            Ok(true) => {
+                                trc::event!(
+                                    Smtp(SmtpEvent::BounceTagMissing),
+                                    SpanId = self.data.session_id,
+                                    To = rcpt.address_lcase.clone(),
+                                );
+                                return self
+                                    .rcpt_error(
+                                        b"550 5.7.1 Bounces are not accepted for this address.\r\n",
+                                        rcpt.address_lcase,
+                                    )
+                                    .await;
+                            }
+                            Ok(false) => {}
+                            Err(err) => {
+                                trc::error!(
+                                    err.span_id(self.data.session_id)
+                                        .caused_by(trc::location!())
+                                        .details("Failed to verify address.")
+                                );
+                                return self
+                                    .write(b"451 4.4.3 Unable to verify address at this time.\r\n")
+                                    .await;
+                            }
+                        }
+                    }
+                    BatvAddress::Unsigned => {}
+                }
+            }
+
+            // Bounces of forwarded messages are relayed back to the original sender
+            if let Some(srs) = self
+                .server
+                .core
+                .smtp
+                .session
+                .srs
+                .as_ref()
+                .map(|srs| srs.reverse(&rcpt.address, now()))
+            {
+                match srs {
+                    SrsAddress::Valid(address) => {
+                        rcpt.address_lcase = address.to_lowercase();
+                        rcpt.domain = rcpt.address_lcase.domain_part().into();
+                        rcpt.address = address;
+                        is_srs_bounce = true;
+                    }
+                    SrsAddress::Invalid => {
+                        trc::event!(
+                            Smtp(SmtpEvent::BounceTagInvalid),
+                            SpanId = self.data.session_id,
+                            To = rcpt.address_lcase.clone(),
+                        );
+                        return self
+                            .rcpt_error(
+                                b"550 5.7.1 Invalid or expired forwarding address.\r\n",
+                                rcpt.address_lcase,
+                            )
+                            .await;
+                    }
+                    SrsAddress::Unsigned => {}
+                }
+            }
+        }
+
         if self.data.rcpt_to.contains(&rcpt) {
             trc::event!(
                 Smtp(SmtpEvent::RcptToDuplicate),
@@ -87,6 +232,28 @@ impl<T: SessionStream> Session<T> {
             );
             self.data.rcpt_oks += 1;
             return self.write(b"250 2.1.5 OK\r\n").await;
+        } else if self.params.rcpt_domains_max > 0
+            && !self.data.rcpt_to.iter().any(|r| r.domain == rcpt.domain)
+        {
+            let mut domains = self
+                .data
+                .rcpt_to
+                .iter()
+                .map(|r| r.domain.as_str())
+                .collect::<Vec<_>>();
+            domains.sort_unstable();
+            domains.dedup();
+            if domains.len() >= self.params.rcpt_domains_max {
+                trc::event!(
+                    Smtp(SmtpEvent::TooManyRecipientDomains),
+                    SpanId = self.data.session_id,
+                    Domain = rcpt.domain,
+                    Limit = self.params.rcpt_domains_max,
+                );
+                return self
+                    .write(b"452 4.5.3 Too many recipient domains.\r\n")
+                    .await;
+            }
         }
         self.data.rcpt_to.push(rcpt);
 
@@ -248,15 +415,17 @@ impl<T: SessionStream> Session<T> {
                     }
                 }
                 Ok(false) => {
-                    if !self
-                        .server
-                        .eval_if(
-                            &self.server.core.smtp.session.rcpt.relay,
-                            self,
-                            self.data.session_id,
-                        )
-                        .await
-                        .unwrap_or(false)
+                    if !self.is_trusted_relay()
+                        && !is_srs_bounce
+                        && !self
+                            .server
+                            .eval_if(
+                                &self.server.core.smtp.session.rcpt.relay,
+                                self,
+                                self.data.session_id,
+                            )
+                            .await
+                            .unwrap_or(false)
                     {
                         trc::event!(
                             Smtp(SmtpEvent::RelayNotAllowed),
@@ -283,15 +452,17 @@ impl<T: SessionStream> Session<T> {
                         .await;
                 }
             }
-        } else if !self
-            .server
-            .eval_if(
-                &self.server.core.smtp.session.rcpt.relay,
-                self,
-                self.data.session_id,
-            )
-            .await
-            .unwrap_or(false)
+        } else if !self.is_trusted_relay()
+            && !is_srs_bounce
+            && !self
+                .server
+                .eval_if(
+                    &self.server.core.smtp.session.rcpt.relay,
+                    self,
+                    self.data.session_id,
+                )
+                .await
+                .unwrap_or(false)
         {
             trc::event!(
                 Smtp(SmtpEvent::RelayNotAllowed),
@@ -305,6 +476,33 @@ impl<T: SessionStream> Session<T> {
                 .await;
         }
 
+        // Suppress recipients that complained about messages from this sender domain
+        if self.data.authenticated_as.is_some()
+            && let Some(sender_domain) = self
+                .data
+                .mail_from
+                .as_ref()
+                .map(|from| from.domain.as_str())
+                .filter(|domain| !domain.is_empty())
+            && let Some(rcpt) = self.data.rcpt_to.last()
+            && self
+                .server
+                .is_suppressed(sender_domain, &rcpt.address_lcase, self.data.session_id)
+                .await
+        {
+            if let Some(rcpt) = self.data.rcpt_to.pop() {
+                trc::event!(
+                    Smtp(SmtpEvent::RcptToSuppressed),
+                    SpanId = self.data.session_id,
+                    To = rcpt.address_lcase,
+                );
+            }
+
+            return self
+                .write(b"550 5.7.1 Recipient has reported messages from this sender as spam.\r\n")
+                .await;
+        }
+
         if self.is_allowed().await {
             // Greylist
             if let Some(greylist_duration) = self
@@ -414,6 +612,11 @@ impl<T: SessionStream> Session<T> {
     }
 
     async fn rcpt_error(&mut self, response: &[u8], rcpt: String) -> Result<(), ()> {
+        // Simulated failures do not count towards bans
+        if self.is_simulation() {
+            return self.write(response).await;
+        }
+
         tokio::time::sleep(self.params.rcpt_errors_wait).await;
         self.data.rcpt_errors += 1;
         let has_too_many_errors = self.data.rcpt_errors >= self.params.rcpt_errors_max;
@@ -0,0 +1,64 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::listener::SessionStream;
+use mail_parser::MessageParser;
+
+use crate::core::Session;
+
+impl<T: SessionStream> Session<T> {
+    pub fn is_trusted_relay(&self) -> bool {
+        !self.is_authenticated()
+            && self
+                .server
+                .core
+                .smtp
+                .session
+                .trusted_relay
+                .networks
+                .iter()
+                .any(|network| network.matches(&self.data.remote_ip))
+    }
+
+    // Authenticated sessions and trusted relays are both treated as submissions
+    pub fn is_submission(&self) -> bool {
+        self.is_authenticated() || self.is_trusted_relay()
+    }
+
+    // Removes headers that identify the software used by the relaying application
+    pub fn remove_relay_headers(&self, raw_message: &[u8]) -> Option<Vec<u8>> {
+        let remove_headers = &self.server.core.smtp.session.trusted_relay.remove_headers;
+        let message = MessageParser::new().parse_headers(raw_message)?;
+        let mut removed = Vec::new();
+        let mut result = Vec::with_capacity(raw_message.len());
+        let mut last_pos = 0;
+
+        for header in &message.root_part().headers {
+            if let Some(name) = remove_headers
+                .iter()
+                .find(|name| header.name.as_str().eq_ignore_ascii_case(name))
+            {
+                result.extend_from_slice(raw_message.get(last_pos..header.offset_field as usize)?);
+                last_pos = header.offset_end as usize;
+                removed.push(name.clone());
+            }
+        }
+
+        if removed.is_empty() {
+            return None;
+        }
+
+        result.extend_from_slice(raw_message.get(last_pos..)?);
+
+        trc::event!(
+            Smtp(trc::SmtpEvent::RelayHeadersRemoved),
+            SpanId = self.data.session_id,
+            Details = removed,
+        );
+
+        Some(result)
+    }
+}
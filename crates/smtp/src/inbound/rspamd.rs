@@ -0,0 +1,382 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{borrow::Cow, time::Instant};
+
+use ahash::AHashMap;
+use common::{Server, config::smtp::session::Rspamd, listener::SessionStream};
+use mail_auth::AuthenticatedMessage;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use trc::SpamEvent;
+use utils::HttpLimitResponse;
+
+use crate::core::Session;
+
+use super::{FilterResponse, milter::Modification};
+
+#[derive(Debug, Deserialize)]
+pub struct RspamdResponse {
+    pub action: String,
+    #[serde(default)]
+    pub score: f64,
+    #[serde(default)]
+    pub required_score: f64,
+    #[serde(default)]
+    pub symbols: AHashMap<String, RspamdSymbol>,
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub milter: Option<RspamdMilter>,
+    #[serde(default)]
+    pub messages: AHashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RspamdSymbol {
+    #[serde(default)]
+    pub score: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct RspamdMilter {
+    #[serde(default)]
+    pub add_headers: AHashMap<String, RspamdHeader>,
+    #[serde(default)]
+    pub remove_headers: AHashMap<String, i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum RspamdHeader {
+    Value(String),
+    Object(RspamdHeaderValue),
+    List(Vec<RspamdHeaderValue>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RspamdHeaderValue {
+    pub value: String,
+}
+
+pub trait RspamdLearn: Sync + Send {
+    fn rspamd_learn(
+        &self,
+        raw_message: &[u8],
+        learn_spam: bool,
+        account_id: u32,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl<T: SessionStream> Session<T> {
+    pub async fn run_rspamd(
+        &self,
+        message: &AuthenticatedMessage<'_>,
+    ) -> Result<Vec<Modification>, FilterResponse> {
+        let mut modifications = Vec::new();
+        if self.is_simulation() {
+            return Ok(modifications);
+        }
+
+        for rspamd in &self.server.core.smtp.session.rspamd {
+            if message.raw_message().len() > rspamd.max_message_size
+                || !self
+                    .server
+                    .eval_if(&rspamd.enable, self, self.data.session_id)
+                    .await
+                    .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let time = Instant::now();
+            let response = match send_rspamd_request(
+                rspamd,
+                &format!("{}/checkv2", rspamd.url),
+                self.rspamd_headers(),
+                message.raw_message(),
+            )
+            .await
+            .and_then(|response| {
+                serde_json::from_slice::<RspamdResponse>(&response)
+                    .map_err(|err| format!("Failed to parse rspamd response: {err}"))
+            }) {
+                Ok(response) => response,
+                Err(err) => {
+                    trc::event!(
+                        Spam(SpamEvent::RspamdError),
+                        SpanId = self.data.session_id,
+                        Id = rspamd.id.clone(),
+                        Reason = err,
+                        Elapsed = time.elapsed(),
+                    );
+
+                    if rspamd.tempfail_on_error {
+                        return Err(FilterResponse::server_failure());
+                    }
+                    continue;
+                }
+            };
+
+            let mut symbols = response.symbols.keys().cloned().collect::<Vec<_>>();
+            symbols.sort_unstable();
+
+            trc::event!(
+                Spam(SpamEvent::Rspamd),
+                SpanId = self.data.session_id,
+                Id = rspamd.id.clone(),
+                Result = response.action.clone(),
+                Value = response.score,
+                Limit = response.required_score,
+                Details = symbols,
+                Elapsed = time.elapsed(),
+            );
+
+            let smtp_message = response
+                .messages
+                .get("smtp_message")
+                .and_then(|message| message.as_str())
+                .filter(|message| !message.is_empty() && !message.contains(['\r', '\n']));
+            match response.action.as_str() {
+                "reject" => {
+                    return Err(FilterResponse {
+                        message: match smtp_message {
+                            Some(text) => format!("550 5.7.1 {text}\r\n").into(),
+                            None => Cow::Borrowed("550 5.7.1 Message rejected as spam.\r\n"),
+                        },
+                        disconnect: false,
+                    });
+                }
+                "soft reject" | "greylist" => {
+                    return Err(FilterResponse {
+                        message: match smtp_message {
+                            Some(text) => format!("451 4.7.1 {text}\r\n").into(),
+                            None => Cow::Borrowed("451 4.7.1 Try again later.\r\n"),
+                        },
+                        disconnect: false,
+                    });
+                }
+                "discard" => {
+                    return Err(FilterResponse::accept());
+                }
+                "quarantine" => {
+                    modifications.push(Modification::Quarantine {
+                        reason: format!("Rspamd score {:.2}", response.score),
+                    });
+                }
+                _ => {}
+            }
+
+            modifications.extend(rspamd_modifications(&response, message));
+        }
+
+        Ok(modifications)
+    }
+
+    fn rspamd_headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        let mut add_header = |name: &'static str, value: &str| {
+            if let Ok(value) = HeaderValue::from_str(value) {
+                headers.append(HeaderName::from_static(name), value);
+            }
+        };
+
+        add_header("ip", &self.data.remote_ip.to_string());
+        if !self.data.helo_domain.is_empty() {
+            add_header("helo", &self.data.helo_domain);
+        }
+        if let Some(ptr) = self
+            .data
+            .iprev
+            .as_ref()
+            .and_then(|ip_rev| ip_rev.ptr.as_ref())
+            .and_then(|ptrs| ptrs.first())
+        {
+            add_header("hostname", ptr);
+        }
+        if let Some(user) = self.authenticated_as() {
+            add_header("user", user);
+        }
+        if let Some(from) = &self.data.mail_from {
+            add_header("from", &from.address);
+        }
+        for rcpt in &self.data.rcpt_to {
+            add_header("rcpt", &rcpt.address);
+        }
+        let (tls_version, tls_cipher) = self.stream.tls_version_and_cipher();
+        if !tls_version.is_empty() {
+            add_header("tls-version", tls_version.as_ref());
+            add_header("tls-cipher", tls_cipher.as_ref());
+        }
+        add_header("mta-name", &self.hostname);
+
+        headers
+    }
+}
+
+impl RspamdLearn for Server {
+    async fn rspamd_learn(&self, raw_message: &[u8], learn_spam: bool, account_id: u32) {
+        for rspamd in &self.core.smtp.session.rspamd {
+            let Some(learn_url) = &rspamd.learn_url else {
+                continue;
+            };
+            if raw_message.len() > rspamd.max_message_size {
+                continue;
+            }
+
+            let time = Instant::now();
+            let url = format!(
+                "{learn_url}/{}",
+                if learn_spam { "learnspam" } else { "learnham" }
+            );
+            match send_rspamd_request(rspamd, &url, HeaderMap::new(), raw_message).await {
+                Ok(_) => {
+                    trc::event!(
+                        Spam(SpamEvent::RspamdLearn),
+                        AccountId = account_id,
+                        Id = rspamd.id.clone(),
+                        Details = if learn_spam { "spam" } else { "ham" },
+                        Elapsed = time.elapsed(),
+                    );
+                }
+                Err(err) => {
+                    trc::event!(
+                        Spam(SpamEvent::RspamdLearnError),
+                        AccountId = account_id,
+                        Id = rspamd.id.clone(),
+                        Reason = err,
+                        Elapsed = time.elapsed(),
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub async fn send_rspamd_request(
+    rspamd: &Rspamd,
+    url: &str,
+    headers: HeaderMap,
+    raw_message: &[u8],
+) -> Result<Vec<u8>, String> {
+    let response = reqwest::Client::builder()
+        .timeout(rspamd.timeout)
+        .danger_accept_invalid_certs(rspamd.tls_allow_invalid_certs)
+        .build()
+        .map_err(|err| format!("Failed to create HTTP client: {}", err))?
+        .post(url)
+        .headers(rspamd.headers.clone())
+        .headers(headers)
+        .body(raw_message.to_vec())
+        .send()
+        .await
+        .map_err(|err| format!("Rspamd request failed: {err}"))?;
+
+    // Rspamd replies with 208 when the message was already learned
+    if response.status().is_success() {
+        response
+            .bytes_with_limit(rspamd.max_response_size)
+            .await
+            .map_err(|err| format!("Failed to read rspamd response: {}", err))?
+            .ok_or_else(|| "Rspamd response too large".to_string())
+    } else {
+        Err(format!(
+            "Rspamd request failed with code {}: {}",
+            response.status().as_u16(),
+            response.status().canonical_reason().unwrap_or("Unknown")
+        ))
+    }
+}
+
+fn rspamd_modifications(
+    response: &RspamdResponse,
+    message: &AuthenticatedMessage<'_>,
+) -> Vec<Modification> {
+    let mut modifications = Vec::new();
+    let header_count = |name: &str| {
+        message
+            .raw_parsed_headers()
+            .iter()
+            .filter(|(n, _)| n.eq_ignore_ascii_case(name.as_bytes()))
+            .count()
+    };
+    let milter = response.milter.as_ref();
+
+    // Removals go first, as indexes refer to the original headers
+    if let Some(milter) = milter {
+        for (name, index) in &milter.remove_headers {
+            let count = header_count(name);
+            let index = match *index {
+                0 => None,
+                index if index > 0 => Some(index as usize),
+                index => (count as i64 + index + 1).try_into().ok(),
+            };
+            match index {
+                Some(index) if index > 0 && index <= count => {
+                    modifications.push(Modification::ChangeHeader {
+                        index: index as u32,
+                        name: name.clone(),
+                        value: String::new(),
+                    });
+                }
+                None => {
+                    for _ in 0..count {
+                        modifications.push(Modification::ChangeHeader {
+                            index: 1,
+                            name: name.clone(),
+                            value: String::new(),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    if response.action == "rewrite subject"
+        && let Some(subject) = &response.subject
+    {
+        if header_count("Subject") > 0 {
+            modifications.push(Modification::ChangeHeader {
+                index: 1,
+                name: "Subject".into(),
+                value: subject.clone(),
+            });
+        } else {
+            modifications.push(Modification::AddHeader {
+                name: "Subject".into(),
+                value: subject.clone(),
+            });
+        }
+    }
+
+    let mut has_spam_header = false;
+    if let Some(milter) = milter {
+        for (name, header) in &milter.add_headers {
+            has_spam_header |= name.eq_ignore_ascii_case("X-Spam");
+            let values = match header {
+                RspamdHeader::Value(value) => vec![value.as_str()],
+                RspamdHeader::Object(value) => vec![value.value.as_str()],
+                RspamdHeader::List(values) => values.iter().map(|v| v.value.as_str()).collect(),
+            };
+            for value in values {
+                modifications.push(Modification::AddHeader {
+                    name: name.clone(),
+                    value: value.to_string(),
+                });
+            }
+        }
+    }
+
+    if !has_spam_header && matches!(response.action.as_str(), "add header" | "rewrite subject") {
+        modifications.push(Modification::AddHeader {
+            name: "X-Spam".into(),
+            value: "Yes".into(),
+        });
+    }
+
+    modifications
+}
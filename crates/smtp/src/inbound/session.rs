@@ -5,7 +5,10 @@
  */
 
 use common::{
-    config::{server::ServerProtocol, smtp::session::Mechanism},
+    config::{
+        server::ServerProtocol,
+        smtp::session::{ConformanceAction, Mechanism},
+    },
     expr::{self, functions::ResolveVariable, *},
     listener::SessionStream,
 };
@@ -27,13 +30,53 @@ use super::auth::SaslToken;
 
 impl<T: SessionStream> Session<T> {
     pub async fn ingest(&mut self, bytes: &[u8]) -> Result<bool, ()> {
+        // Enforce CRLF line endings
+        if self.params.conformance_bare_lf != ConformanceAction::Disable
+            && matches!(self.state, State::Request(_) | State::Data(_))
+        {
+            if self.has_bare_lf(bytes) {
+                self.handle_conformance_violation(
+                    self.params.conformance_bare_lf,
+                    "SMTP_BARE_LF",
+                    SmtpEvent::BareLineFeed,
+                )
+                .await?;
+            }
+            self.data.ends_with_cr = bytes.last() == Some(&b'\r');
+        }
+
         let mut iter = bytes.iter();
         let mut state = std::mem::replace(&mut self.state, State::None);
 
         'outer: loop {
             match &mut state {
                 State::Request(receiver) => loop {
-                    match receiver.ingest(&mut iter) {
+                    let line_start = iter.as_slice();
+                    let result = receiver.ingest(&mut iter);
+                    if self.data.prdr_offered {
+                        self.capture_command_line(
+                            &line_start[..line_start.len() - iter.as_slice().len()],
+                            matches!(result, Err(Error::NeedsMoreData { .. })),
+                        );
+                    }
+
+                    // Detect commands pipelined without negotiating PIPELINING
+                    if !self.data.pipelining
+                        && self.params.conformance_pipelining != ConformanceAction::Disable
+                        && !iter.as_slice().is_empty()
+                        && result
+                            .as_ref()
+                            .is_ok_and(|request| !matches!(request, Request::Bdat { .. }))
+                    {
+                        self.handle_conformance_violation(
+                            self.params.conformance_pipelining,
+                            "SMTP_PIPELINING",
+                            SmtpEvent::UnexpectedPipelining,
+                        )
+                        .await?;
+                    }
+
+                    match result {
                         Ok(request) => match request {
                             Request::Rcpt { to } => {
                                 self.handle_rcpt_to(to).await?;
@@ -323,6 +366,11 @@ impl<T: SessionStream> Session<T> {
                                 )
                                 .await?;
                             }
+                            Error::UnsupportedParameter { param }
+                                if self.data.prdr_offered && param.eq_ignore_ascii_case("PRDR") =>
+                            {
+                                self.handle_prdr_mail_from().await?;
+                            }
                             Error::UnsupportedParameter { param } => {
                                 trc::event!(
                                     Smtp(SmtpEvent::UnsupportedParameter),
@@ -346,7 +394,7 @@ impl<T: SessionStream> Session<T> {
                 State::Data(receiver) => {
                     if self.data.message.len() + bytes.len() < self.params.max_message_size {
                         if receiver.ingest(&mut iter, &mut self.data.message) {
-                            let message = self.queue_message().await;
+                            let message = self.queue_message_prdr().await;
                             let num_responses = if self.instance.protocol == ServerProtocol::Smtp {
                                 1
                             } else {
@@ -373,7 +421,7 @@ impl<T: SessionStream> Session<T> {
                     if receiver.ingest(&mut iter, &mut self.data.message) {
                         if self.can_send_data().await? {
                             if receiver.is_last {
-                                let message = self.queue_message().await;
+                                let message = self.queue_message_prdr().await;
                                 if !message.is_empty() {
                                     let num_responses =
                                         if self.instance.protocol == ServerProtocol::Smtp {
@@ -474,6 +522,8 @@ impl<T: AsyncWrite + AsyncRead + Unpin> Session<T> {
         self.data.delivery_by = 0;
         self.data.future_release = 0;
         self.data.rcpt_oks = 0;
+        self.data.prdr = false;
+        self.data.prdr_rejected.clear();
     }
 
     #[inline(always)]
@@ -0,0 +1,289 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::borrow::Cow;
+
+use common::listener::{SessionStream, stream::NullIo};
+use parking_lot::Mutex;
+use serde::Serialize;
+use smtp_proto::{MailFrom, RcptTo};
+
+use crate::{core::Session, inbound::AuthResult, scripts::ScriptResult};
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Simulation {
+    pub verdict: SimulationVerdict,
+    pub steps: Vec<SimulationStep>,
+    pub recipients: Vec<SimulatedRecipient>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spam: Option<SimulatedSpam>,
+    // Recipients that the message would have been queued for
+    #[serde(skip)]
+    pub delivered: Vec<(String, Option<bool>)>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[serde(tag = "action")]
+pub enum SimulationVerdict {
+    #[default]
+    Accept,
+    Discard {
+        stage: &'static str,
+    },
+    Reject {
+        stage: &'static str,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationStep {
+    pub stage: &'static str,
+    pub check: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub result: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedRecipient {
+    pub address: String,
+    pub accepted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rewritten_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_spam: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulatedSpam {
+    pub score: f64,
+    pub tags: Vec<String>,
+}
+
+impl Session<NullIo> {
+    // Runs an envelope and message through the inbound session stages without queueing anything.
+    // Sieve scripts run in dry-run mode, reports are not sent and external filters
+    // (milters, MTA hooks, spamd and rspamd) are not contacted.
+    pub async fn simulate(
+        &mut self,
+        ehlo_domain: &str,
+        mail_from: String,
+        rcpt_to: Vec<String>,
+        message: Option<Vec<u8>>,
+    ) -> Simulation {
+        self.data.simulation = Some(Mutex::new(Simulation::default()));
+        let verdict = self
+            .simulate_stages(ehlo_domain, mail_from, rcpt_to, message)
+            .await;
+        let mut simulation = self.data.simulation.take().unwrap_or_default().into_inner();
+        simulation.verdict = verdict;
+        simulation
+    }
+
+    async fn simulate_stages(
+        &mut self,
+        ehlo_domain: &str,
+        mail_from: String,
+        rcpt_to: Vec<String>,
+        message: Option<Vec<u8>>,
+    ) -> SimulationVerdict {
+        // Connect stage
+        if !self.init_conn().await {
+            return SimulationVerdict::reject("connect", self.has_failed());
+        }
+        self.stream.tx_buf.clear();
+
+        // EHLO stage
+        if !ehlo_domain.is_empty() {
+            let _ = self.handle_ehlo(Cow::Borrowed(ehlo_domain), true).await;
+            if let Some(spf_output) = &self.data.spf_ehlo {
+                self.simulation_step(
+                    "ehlo",
+                    "spf",
+                    None,
+                    spf_output.result().as_str(),
+                    spf_output.domain().to_string().into(),
+                );
+            }
+            if let Some(reason) = self.has_failed() {
+                return SimulationVerdict::reject("ehlo", Some(reason));
+            }
+        }
+
+        // MAIL FROM stage
+        let default_from = mail_from.clone();
+        let _ = self
+            .handle_mail_from(MailFrom {
+                address: mail_from.into(),
+                ..Default::default()
+            })
+            .await;
+        if let Some(iprev) = &self.data.iprev {
+            self.simulation_step(
+                "mail",
+                "iprev",
+                None,
+                iprev.result().as_str(),
+                iprev
+                    .ptr
+                    .as_ref()
+                    .and_then(|ptr| ptr.first())
+                    .map(|ptr| ptr.to_string()),
+            );
+        }
+        if let Some(spf_output) = &self.data.spf_mail_from {
+            self.simulation_step(
+                "mail",
+                "spf",
+                None,
+                spf_output.result().as_str(),
+                spf_output.domain().to_string().into(),
+            );
+        }
+        if let Some(reason) = self.has_failed() {
+            return SimulationVerdict::reject("mail", Some(reason));
+        }
+
+        // RCPT TO stage
+        let mut recipients = Vec::with_capacity(rcpt_to.len());
+        for address in rcpt_to {
+            let address = address.to_lowercase();
+            let num_rcpts = self.data.rcpt_to.len();
+            let _ = self
+                .handle_rcpt_to(RcptTo {
+                    address: address.clone().into(),
+                    ..Default::default()
+                })
+                .await;
+            let reason = self.has_failed();
+            let rewritten_to = self
+                .data
+                .rcpt_to
+                .get(num_rcpts)
+                .filter(|rcpt| rcpt.address_lcase != address)
+                .map(|rcpt| rcpt.address_lcase.clone());
+            recipients.push(SimulatedRecipient {
+                address,
+                accepted: reason.is_none(),
+                rewritten_to,
+                reason,
+                is_spam: None,
+            });
+        }
+        self.simulation(|simulation| simulation.recipients = recipients);
+        if self.data.rcpt_to.is_empty() {
+            return SimulationVerdict::reject("rcpt", "No valid recipients.".to_string().into());
+        }
+
+        // DATA stage
+        self.data.message = message.unwrap_or_else(|| {
+            format!("From: {default_from}\r\nSubject: test\r\n\r\ntest\r\n").into_bytes()
+        });
+        let response = self.queue_message().await;
+        let response = String::from_utf8_lossy(&response).trim().to_string();
+        if !response.starts_with('2') {
+            return SimulationVerdict::reject("data", response.into());
+        }
+
+        // Recipients dropped at the DATA stage were either discarded or rejected
+        let prdr_rejected = std::mem::take(&mut self.data.prdr_rejected);
+        self.simulation(|simulation| {
+            let delivered = std::mem::take(&mut simulation.delivered);
+            for result in simulation.recipients.iter_mut().filter(|r| r.accepted) {
+                let address = result
+                    .rewritten_to
+                    .clone()
+                    .unwrap_or_else(|| result.address.clone());
+                if let Some((_, is_spam)) = delivered.iter().find(|(rcpt, _)| *rcpt == address) {
+                    result.is_spam = *is_spam;
+                } else {
+                    result.accepted = false;
+                    result.reason = prdr_rejected
+                        .iter()
+                        .find(|(rcpt, _)| *rcpt == address)
+                        .map_or_else(
+                            || "Discarded.".to_string(),
+                            |(_, reply)| reply.trim().to_string(),
+                        )
+                        .into();
+                }
+            }
+
+            if delivered.is_empty() {
+                SimulationVerdict::Discard { stage: "data" }
+            } else {
+                SimulationVerdict::Accept
+            }
+        })
+        .unwrap_or_default()
+    }
+}
+
+impl<T: SessionStream> Session<T> {
+    pub fn is_simulation(&self) -> bool {
+        self.data.simulation.is_some()
+    }
+
+    pub fn simulation<R>(&self, f: impl FnOnce(&mut Simulation) -> R) -> Option<R> {
+        self.data
+            .simulation
+            .as_ref()
+            .map(|simulation| f(&mut simulation.lock()))
+    }
+
+    pub fn simulation_step(
+        &self,
+        stage: &'static str,
+        check: &'static str,
+        id: Option<String>,
+        result: &str,
+        details: Option<String>,
+    ) {
+        self.simulation(|simulation| {
+            simulation.steps.push(SimulationStep {
+                stage,
+                check,
+                id,
+                result: result.to_string(),
+                details,
+            })
+        });
+    }
+
+    pub fn simulation_script_step(
+        &self,
+        stage: &'static str,
+        script_id: String,
+        result: &ScriptResult,
+    ) {
+        let (result, details) = match result {
+            ScriptResult::Accept { .. } => ("accept", None),
+            ScriptResult::Replace { .. } => ("replace", None),
+            ScriptResult::Reject(reason) => ("reject", reason.trim().to_string().into()),
+            ScriptResult::Discard => ("discard", None),
+        };
+        self.simulation_step(stage, "sieve", script_id.into(), result, details);
+    }
+}
+
+impl SimulationVerdict {
+    fn reject(stage: &'static str, reason: Option<String>) -> Self {
+        SimulationVerdict::Reject {
+            stage,
+            reason: reason.unwrap_or_else(|| "Connection refused.".to_string()),
+        }
+    }
+}
@@ -4,7 +4,11 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::{config::spamfilter::SpamFilterAction, listener::SessionStream};
+use common::{
+    config::spamfilter::SpamFilterAction,
+    expr::{self, V_RECIPIENT, V_RECIPIENT_DOMAIN, functions::ResolveVariable},
+    listener::SessionStream,
+};
 use mail_auth::{ArcOutput, DkimOutput, DmarcResult, dmarc::Policy};
 use mail_parser::Message;
 use spam_filter::{
@@ -15,7 +19,22 @@ use spam_filter::{
     },
 };
 
-use crate::core::Session;
+use crate::{
+    core::{Session, SessionAddress},
+    inbound::simulate::SimulatedSpam,
+};
+
+pub struct SpamClassification {
+    pub action: SpamFilterAction<String>,
+    pub score: f64,
+    pub results: String,
+    pub is_forced: bool,
+}
+
+pub(crate) struct RecipientVariables<'x, T: SessionStream> {
+    pub session: &'x Session<T>,
+    pub rcpt: &'x SessionAddress,
+}
 
 impl<T: SessionStream> Session<T> {
     pub async fn spam_classify<'x>(
@@ -25,7 +44,7 @@ impl<T: SessionStream> Session<T> {
         arc_result: Option<&'x ArcOutput<'x>>,
         dmarc_result: Option<&'x DmarcResult>,
         dmarc_policy: Option<&'x Policy>,
-    ) -> SpamFilterAction<String> {
+    ) -> SpamClassification {
         let server = &self.server;
         let mut ctx = server.spam_filter_init(self.build_spam_input(
             message,
@@ -35,16 +54,86 @@ impl<T: SessionStream> Session<T> {
             dmarc_policy,
         ));
 
-        if !self.is_authenticated() {
+        if !self.is_submission() {
+            // Protocol conformance violations
+            for tag in &self.data.conformance_tags {
+                ctx.result.add_tag(*tag);
+            }
+
             // Spam classification
-            server.spam_filter_classify(&mut ctx).await
+            let action = server.spam_filter_classify(&mut ctx).await;
+            self.simulation(|simulation| {
+                let mut tags = ctx
+                    .result
+                    .tags
+                    .iter()
+                    .map(|tag| tag.to_string())
+                    .collect::<Vec<_>>();
+                tags.sort_unstable();
+                simulation.spam = Some(SimulatedSpam {
+                    score: ctx.result.score,
+                    tags,
+                });
+            });
+
+            // Verdicts not derived from the score, such as spam traps, apply to all recipients
+            let is_forced = ctx.result.has_tag("SPAM_TRAP")
+                || !matches!(
+                    (&action, server.core.spam.scores.verdict(ctx.result.score)),
+                    (SpamFilterAction::Allow(_), SpamFilterAction::Allow(_))
+                        | (SpamFilterAction::Discard, SpamFilterAction::Discard)
+                        | (SpamFilterAction::Reject, SpamFilterAction::Reject)
+                );
+
+            SpamClassification {
+                action,
+                score: ctx.result.score,
+                results: ctx.result.header.take().unwrap_or_default(),
+                is_forced,
+            }
         } else {
             // Trusted reply tracking
             server.spam_filter_analyze_reply_out(&mut ctx).await;
-            SpamFilterAction::Allow(String::new())
+            SpamClassification {
+                action: SpamFilterAction::Allow(String::new()),
+                score: 0.0,
+                results: String::new(),
+                is_forced: true,
+            }
         }
     }
 
+    // Obtains the verdict for each recipient when filtering profiles are in use
+    pub async fn spam_recipient_verdicts(
+        &self,
+        classification: &SpamClassification,
+    ) -> Option<Vec<SpamFilterAction<bool>>> {
+        let spam = &self.server.core.spam;
+        if spam.profiles.is_empty() || classification.is_forced {
+            return None;
+        }
+
+        let mut verdicts = Vec::with_capacity(self.data.rcpt_to.len());
+        for rcpt in &self.data.rcpt_to {
+            let profile = self
+                .server
+                .eval_if::<String, _>(
+                    &self.server.core.smtp.session.data.spam_profile,
+                    &RecipientVariables {
+                        session: self,
+                        rcpt,
+                    },
+                    self.data.session_id,
+                )
+                .await
+                .and_then(|profile| spam.profiles.get(&profile))
+                .unwrap_or(&spam.scores);
+            verdicts.push(profile.verdict(classification.score));
+        }
+
+        Some(verdicts)
+    }
+
     pub fn build_spam_input<'x>(
         &'x self,
         message: &'x Message<'x>,
@@ -88,7 +177,21 @@ impl<T: SessionStream> Session<T> {
                 .map(|r| r.address_lcase.as_str())
                 .collect(),
             account_id: None,
-            is_test: false,
+            is_test: self.is_simulation(),
+        }
+    }
+}
+
+impl<T: SessionStream> ResolveVariable for RecipientVariables<'_, T> {
+    fn resolve_variable(&self, variable: u32) -> expr::Variable<'_> {
+        match variable {
+            V_RECIPIENT => self.rcpt.address_lcase.as_str().into(),
+            V_RECIPIENT_DOMAIN => self.rcpt.domain.as_str().into(),
+            _ => self.session.resolve_variable(variable),
         }
     }
+
+    fn resolve_global(&self, variable: &str) -> expr::Variable<'_> {
+        self.session.resolve_global(variable)
+    }
 }
@@ -0,0 +1,329 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{borrow::Cow, time::Instant};
+
+use common::{
+    config::smtp::session::{Spamd, SpamdAction, SpamdCommand},
+    listener::SessionStream,
+};
+use mail_auth::AuthenticatedMessage;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use trc::SpamEvent;
+
+use crate::core::Session;
+
+use super::{FilterResponse, milter::Modification};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpamdResponse {
+    pub is_spam: bool,
+    pub score: f64,
+    pub threshold: f64,
+    pub body: String,
+}
+
+impl<T: SessionStream> Session<T> {
+    pub async fn run_spamd(
+        &self,
+        message: &AuthenticatedMessage<'_>,
+    ) -> Result<Vec<Modification>, FilterResponse> {
+        let mut modifications = Vec::new();
+        if self.is_simulation() {
+            return Ok(modifications);
+        }
+
+        for spamd in &self.server.core.smtp.session.spamd {
+            // Large messages are not scanned, as spamc does by default
+            if message.raw_message().len() > spamd.max_message_size
+                || !self
+                    .server
+                    .eval_if(&spamd.enable, self, self.data.session_id)
+                    .await
+                    .unwrap_or(false)
+            {
+                continue;
+            }
+
+            let time = Instant::now();
+            let response = match send_spamd_request(spamd, message.raw_message()).await {
+                Ok(response) => response,
+                Err(err) => {
+                    trc::event!(
+                        Spam(SpamEvent::SpamdError),
+                        SpanId = self.data.session_id,
+                        Id = spamd.id.clone(),
+                        Reason = err,
+                        Elapsed = time.elapsed(),
+                    );
+
+                    if spamd.tempfail_on_error {
+                        return Err(FilterResponse::server_failure());
+                    }
+                    continue;
+                }
+            };
+
+            let action = if !response.is_spam {
+                SpamdAction::Accept
+            } else if spamd
+                .reject_score
+                .is_some_and(|reject_score| response.score >= reject_score)
+            {
+                SpamdAction::Reject
+            } else {
+                spamd.on_spam
+            };
+
+            trc::event!(
+                Spam(SpamEvent::Spamd),
+                SpanId = self.data.session_id,
+                Id = spamd.id.clone(),
+                Result = response.is_spam,
+                Value = response.score,
+                Limit = response.threshold,
+                Details = format!("{action:?}"),
+                Elapsed = time.elapsed(),
+            );
+
+            match action {
+                SpamdAction::Reject => {
+                    return Err(FilterResponse {
+                        message: Cow::Borrowed("550 5.7.1 Message rejected as spam.\r\n"),
+                        disconnect: false,
+                    });
+                }
+                SpamdAction::Discard => {
+                    return Err(FilterResponse::accept());
+                }
+                SpamdAction::Quarantine => {
+                    modifications.push(Modification::Quarantine {
+                        reason: format!("SpamAssassin score {:.1}", response.score),
+                    });
+                }
+                SpamdAction::Accept => {}
+            }
+
+            modifications.extend(spamd_modifications(spamd.command, &response, message));
+        }
+
+        Ok(modifications)
+    }
+}
+
+pub async fn send_spamd_request(
+    spamd: &Spamd,
+    raw_message: &[u8],
+) -> Result<SpamdResponse, String> {
+    let mut stream = tokio::time::timeout(
+        spamd.timeout_connect,
+        TcpStream::connect(spamd.addrs.as_slice()),
+    )
+    .await
+    .map_err(|_| "Connection timed out".to_string())?
+    .map_err(|err| format!("Failed to connect to spamd: {err}"))?;
+
+    tokio::time::timeout(spamd.timeout_command, async {
+        let mut request = Vec::with_capacity(raw_message.len() + 128);
+        request.extend_from_slice(
+            format!(
+                "{} SPAMC/1.5\r\nContent-length: {}\r\n",
+                spamd.command.as_str(),
+                raw_message.len()
+            )
+            .as_bytes(),
+        );
+        if let Some(user) = &spamd.user {
+            request.extend_from_slice(format!("User: {user}\r\n").as_bytes());
+        }
+        request.extend_from_slice(b"\r\n");
+        request.extend_from_slice(raw_message);
+
+        stream
+            .write_all(&request)
+            .await
+            .map_err(|err| format!("Failed to write to spamd: {err}"))?;
+        stream
+            .shutdown()
+            .await
+            .map_err(|err| format!("Failed to write to spamd: {err}"))?;
+
+        let mut response = Vec::with_capacity(1024);
+        (&mut stream)
+            .take(spamd.max_response_size as u64 + 1)
+            .read_to_end(&mut response)
+            .await
+            .map_err(|err| format!("Failed to read from spamd: {err}"))?;
+        if response.len() > spamd.max_response_size {
+            return Err("Spamd response too large".to_string());
+        }
+
+        parse_spamd_response(&response)
+    })
+    .await
+    .map_err(|_| "Spamd request timed out".to_string())?
+}
+
+pub fn parse_spamd_response(response: &[u8]) -> Result<SpamdResponse, String> {
+    let response = String::from_utf8_lossy(response);
+    let (headers, body) = response
+        .split_once("\r\n\r\n")
+        .or_else(|| response.split_once("\n\n"))
+        .unwrap_or((response.as_ref(), ""));
+    let mut lines = headers.lines();
+
+    // SPAMD/1.1 0 EX_OK
+    let status = lines.next().unwrap_or_default().trim();
+    let mut status_parts = status.split_ascii_whitespace();
+    if !status_parts
+        .next()
+        .is_some_and(|protocol| protocol.starts_with("SPAMD/"))
+    {
+        return Err(format!("Invalid spamd response: {status}"));
+    }
+    if status_parts.next() != Some("0") {
+        return Err(format!("Spamd returned an error: {status}"));
+    }
+
+    // Spam: True ; 15.0 / 5.0
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if !name.trim().eq_ignore_ascii_case("Spam") {
+            continue;
+        }
+        let (verdict, scores) = value
+            .split_once(';')
+            .ok_or_else(|| format!("Invalid spamd verdict: {value}"))?;
+        let (score, threshold) = scores
+            .split_once('/')
+            .ok_or_else(|| format!("Invalid spamd verdict: {value}"))?;
+
+        return Ok(SpamdResponse {
+            is_spam: matches!(verdict.trim().to_ascii_lowercase().as_str(), "true" | "yes"),
+            score: score
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid spamd score: {score}"))?,
+            threshold: threshold
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid spamd threshold: {threshold}"))?,
+            body: body.to_string(),
+        });
+    }
+
+    Err("Spamd response is missing the Spam header".to_string())
+}
+
+fn spamd_modifications(
+    command: SpamdCommand,
+    response: &SpamdResponse,
+    message: &AuthenticatedMessage<'_>,
+) -> Vec<Modification> {
+    let mut modifications = Vec::new();
+
+    if command == SpamdCommand::Headers {
+        // Trust the headers rewritten by SpamAssassin
+        let mut name = String::new();
+        let mut value = String::new();
+        for line in response.body.lines().chain(std::iter::once("")) {
+            if line.starts_with([' ', '\t']) && !name.is_empty() {
+                value.push_str("\r\n");
+                value.push_str(line);
+                continue;
+            }
+            if !name.is_empty() {
+                let value = std::mem::take(&mut value);
+                if name.eq_ignore_ascii_case("Subject") {
+                    if message
+                        .raw_parsed_headers()
+                        .iter()
+                        .any(|(n, _)| n.eq_ignore_ascii_case(b"Subject"))
+                    {
+                        modifications.push(Modification::ChangeHeader {
+                            index: 1,
+                            name: std::mem::take(&mut name),
+                            value,
+                        });
+                    } else {
+                        modifications.push(Modification::AddHeader {
+                            name: std::mem::take(&mut name),
+                            value,
+                        });
+                    }
+                } else if name
+                    .get(..7)
+                    .is_some_and(|prefix| prefix.eq_ignore_ascii_case("X-Spam-"))
+                {
+                    modifications.push(Modification::AddHeader {
+                        name: std::mem::take(&mut name),
+                        value,
+                    });
+                }
+                name.clear();
+            }
+            if let Some((header, header_value)) = line.split_once(':') {
+                name = header.trim().to_string();
+                value = header_value.trim().to_string();
+            }
+        }
+
+        if !modifications.is_empty() {
+            return modifications;
+        }
+    }
+
+    modifications.push(Modification::AddHeader {
+        name: "X-Spam-Flag".into(),
+        value: if response.is_spam { "YES" } else { "NO" }.into(),
+    });
+    modifications.push(Modification::AddHeader {
+        name: "X-Spam-Score".into(),
+        value: format!("{:.1}", response.score),
+    });
+
+    let mut status = format!(
+        "{}, score={:.1} required={:.1}",
+        if response.is_spam { "Yes" } else { "No" },
+        response.score,
+        response.threshold
+    );
+    match command {
+        SpamdCommand::Symbols => {
+            let symbols = response.body.trim();
+            if !symbols.is_empty() {
+                status.push_str("\r\n\ttests=");
+                status.push_str(symbols);
+            }
+        }
+        SpamdCommand::Report => {
+            let report = response
+                .body
+                .lines()
+                .map(|line| line.trim_end())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>();
+            if !report.is_empty() {
+                modifications.push(Modification::AddHeader {
+                    name: "X-Spam-Report".into(),
+                    value: report.join("\r\n\t"),
+                });
+            }
+        }
+        SpamdCommand::Check | SpamdCommand::Headers => {}
+    }
+    modifications.push(Modification::AddHeader {
+        name: "X-Spam-Status".into(),
+        value: status,
+    });
+
+    modifications
+}
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use common::{
     config::smtp::session::Stage,
@@ -78,6 +78,28 @@ impl<T: SessionStream> Session<T> {
 
         let config = &self.server.core.smtp.session.connect;
 
+        // Refuse sources temporarily banned after hitting a spam trap
+        if self.server.core.spam.spam_trap.block_for.is_some() {
+            match self.server.is_ip_banned(self.data.remote_ip).await {
+                Ok(true) => {
+                    trc::event!(
+                        Security(SecurityEvent::IpBlocked),
+                        SpanId = self.data.session_id,
+                        RemoteIp = self.data.remote_ip,
+                    );
+                    return false;
+                }
+                Ok(false) => (),
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(self.data.session_id)
+                            .caused_by(trc::location!())
+                            .details("Failed to check if IP is banned.")
+                    );
+                }
+            }
+        }
+
         // Sieve filtering
         if let Some((script, script_id)) = self
             .server
@@ -135,6 +157,45 @@ impl<T: SessionStream> Session<T> {
             .map(|g| format!("220 {}\r\n", g))
             .unwrap_or_else(|| "220 Stalwart ESMTP at your service.\r\n".to_string());
 
+        // Detect clients that talk before the greeting
+        if let Some(greeting_delay) = self
+            .server
+            .eval_if::<Duration, _>(&config.greeting_delay, self, self.data.session_id)
+            .await
+            .filter(|delay| !delay.is_zero() && !self.is_simulation())
+        {
+            let mut buf = vec![0; 1024];
+            if let Ok(result) = tokio::time::timeout(greeting_delay, self.read(&mut buf)).await {
+                match result {
+                    Ok(bytes_read) if bytes_read > 0 => {
+                        let action = self
+                            .server
+                            .eval_if(
+                                &self.server.core.smtp.session.conformance.early_talker,
+                                self,
+                                self.data.session_id,
+                            )
+                            .await
+                            .unwrap_or_default();
+                        if self
+                            .handle_conformance_violation(
+                                action,
+                                "SMTP_EARLY_TALKER",
+                                SmtpEvent::EarlyTalker,
+                            )
+                            .await
+                            .is_err()
+                        {
+                            return false;
+                        }
+                        buf.truncate(bytes_read);
+                        self.data.early_input = buf;
+                    }
+                    _ => return false,
+                }
+            }
+        }
+
         if self.write(greeting.as_bytes()).await.is_err() {
             return false;
         }
@@ -146,6 +207,17 @@ impl<T: SessionStream> Session<T> {
         let mut buf = vec![0; 8192];
         let mut shutdown_rx = self.instance.shutdown_rx.clone();
 
+        // Process any input received before the greeting
+        if !self.data.early_input.is_empty() {
+            let early_input = std::mem::take(&mut self.data.early_input);
+            self.data.bytes_left = self.data.bytes_left.saturating_sub(early_input.len());
+            match self.ingest(&early_input).await {
+                Ok(true) => (),
+                Ok(false) => return true,
+                Err(_) => return false,
+            }
+        }
+
         loop {
             tokio::select! {
                 result = tokio::time::timeout(
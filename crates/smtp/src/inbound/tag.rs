@@ -0,0 +1,129 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::AHashMap;
+use common::{config::smtp::session::MessageTag, listener::SessionStream};
+use mail_parser::{HeaderName, Message, MessageParser};
+use utils::splice_bytes;
+
+use crate::core::Session;
+
+impl<T: SessionStream> Session<T> {
+    pub async fn apply_message_tags(&self, raw_message: &[u8]) -> Option<Vec<u8>> {
+        let mut tags = Vec::new();
+        for tag in &self.server.core.smtp.session.tags {
+            if self
+                .server
+                .eval_if(&tag.enable, self, self.data.session_id)
+                .await
+                .unwrap_or(false)
+            {
+                tags.push(tag);
+            }
+        }
+        if tags.is_empty() {
+            return None;
+        }
+
+        let result = MessageParser::new()
+            .parse(raw_message)
+            .and_then(|message| tag_message(&message, &tags));
+
+        if result.is_some() {
+            trc::event!(
+                Smtp(trc::SmtpEvent::MessageTagged),
+                SpanId = self.data.session_id,
+                Id = tags.iter().map(|tag| tag.id.clone()).collect::<Vec<_>>(),
+            );
+        }
+
+        result
+    }
+}
+
+// Tags are idempotent: subjects already containing the tag are left as is and
+// stamped headers replace any existing headers with the same name.
+// Returns None if the message was not modified.
+pub fn tag_message(message: &Message<'_>, tags: &[&MessageTag]) -> Option<Vec<u8>> {
+    let raw_message = message.raw_message();
+    let root = message.root_part();
+    let mut edits: Vec<(usize, usize, Vec<u8>)> = Vec::new();
+    let mut stamped = Vec::new();
+
+    // Tag subject
+    let subject = message.subject().unwrap_or_default().to_lowercase();
+    let mut prefix = String::new();
+    for tag in tags {
+        if let Some(tag) = &tag.subject {
+            let tag_lcase = tag.to_lowercase();
+            if !subject.contains(&tag_lcase) && !prefix.to_lowercase().contains(&tag_lcase) {
+                prefix.push_str(tag);
+                prefix.push(' ');
+            }
+        }
+    }
+    if !prefix.is_empty() {
+        if let Some(header) = root.headers.iter().find(|h| h.name == HeaderName::Subject) {
+            let pos = raw_message
+                .get(header.offset_start as usize..header.offset_end as usize)?
+                .iter()
+                .position(|ch| !matches!(ch, b' ' | b'\t'))
+                .unwrap_or_default()
+                + header.offset_start as usize;
+            edits.push((pos, pos, prefix.into_bytes()));
+        } else {
+            stamped.extend_from_slice(b"Subject: ");
+            stamped.extend_from_slice(prefix.trim_end().as_bytes());
+            stamped.extend_from_slice(b"\r\n");
+        }
+    }
+
+    // Stamp headers, later tags override earlier ones
+    let mut headers: AHashMap<String, (&str, &str)> = AHashMap::new();
+    for tag in tags {
+        for (name, value) in &tag.headers {
+            headers.insert(name.to_lowercase(), (name.as_str(), value.as_str()));
+        }
+    }
+    for tag in tags {
+        for (name, _) in &tag.headers {
+            let Some((name, value)) = headers.remove(&name.to_lowercase()) else {
+                continue;
+            };
+            let existing = root
+                .headers
+                .iter()
+                .filter(|h| h.name.as_str().eq_ignore_ascii_case(name))
+                .collect::<Vec<_>>();
+            if existing.len() == 1
+                && raw_message
+                    .get(existing[0].offset_start as usize..existing[0].offset_end as usize)
+                    .is_some_and(|raw| raw.trim_ascii() == value.as_bytes())
+            {
+                continue;
+            }
+
+            for header in existing {
+                edits.push((
+                    header.offset_field as usize,
+                    header.offset_end as usize,
+                    Vec::new(),
+                ));
+            }
+            stamped.extend_from_slice(name.as_bytes());
+            stamped.extend_from_slice(b": ");
+            stamped.extend_from_slice(value.as_bytes());
+            stamped.extend_from_slice(b"\r\n");
+        }
+    }
+
+    if edits.is_empty() && stamped.is_empty() {
+        return None;
+    }
+
+    edits.insert(0, (0, 0, stamped));
+    splice_bytes(raw_message, edits)
+}
@@ -4,8 +4,11 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use super::session::SessionParams;
+use super::{proxy::ProxyTarget, session::SessionParams};
 use crate::queue::{Error, ErrorDetails, HostResponse, MessageWrapper, Status};
+use common::config::smtp::queue::ProxyConfig;
+use mail_builder::encoders::base64::base64_encode_mime;
+use mail_parser::{HeaderName, MessageParser, PartType};
 use mail_send::{Credentials, smtp::AssertReply};
 use rustls::ClientConnection;
 use rustls_pki_types::ServerName;
@@ -27,11 +30,21 @@ use tokio::{
 };
 use tokio_rustls::{TlsConnector, client::TlsStream};
 use trc::DeliveryEvent;
+use utils::splice_bytes;
 
 pub struct SmtpClient<T: AsyncRead + AsyncWrite> {
     pub stream: T,
     pub timeout: Duration,
     pub session_id: u64,
+    pub limits: SmtpLimits,
+}
+
+// Limits advertised by the remote server in its EHLO response (RFC 9422)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SmtpLimits {
+    pub rcpt_max: Option<usize>,
+    pub mail_max: Option<usize>,
+    pub rcpt_domain_max: Option<usize>,
 }
 
 impl<T: AsyncRead + AsyncWrite + Unpin> SmtpClient<T> {
@@ -174,15 +187,35 @@ impl<T: AsyncRead + AsyncWrite + Unpin> SmtpClient<T> {
         &mut self,
         message: &MessageWrapper,
         bdat_cmd: &Option<String>,
+        encode_binary: bool,
         params: &SessionParams<'_>,
     ) -> Result<(), Status<HostResponse<String>, ErrorDetails>> {
         match params
             .server
-            .blob_store()
-            .get_blob(message.message.blob_hash.as_slice(), 0..usize::MAX)
+            .get_queued_blob(message.message.blob_hash.as_slice(), 0..usize::MAX)
             .await
         {
-            Ok(Some(raw_message)) => {
+            Ok(Some(mut raw_message)) => {
+                // Hosts without BINARYMIME receive binary parts encoded as base64 (RFC 3030)
+                let mut encoded_bdat_cmd = None;
+                if encode_binary && let Some(encoded) = encode_binary_parts(&raw_message) {
+                    trc::event!(
+                        Delivery(DeliveryEvent::BinaryMimeDowngrade),
+                        SpanId = self.session_id,
+                        Size = encoded.len(),
+                    );
+
+                    if bdat_cmd.is_some() {
+                        encoded_bdat_cmd = Some(format!("BDAT {} LAST\r\n", encoded.len()));
+                    }
+                    raw_message = encoded;
+                }
+                let bdat_cmd = if encoded_bdat_cmd.is_some() {
+                    &encoded_bdat_cmd
+                } else {
+                    bdat_cmd
+                };
+
                 tokio::time::timeout(params.conn_strategy.timeout_data, async {
                     if let Some(bdat_cmd) = bdat_cmd {
                         trc::event!(
@@ -309,17 +342,21 @@ impl<T: AsyncRead + AsyncWrite + Unpin> SmtpClient<T> {
                 Size = br,
             );
 
-            let mut iter = if buf_concat.is_empty() {
-                buf[..br].iter()
+            let data = if buf_concat.is_empty() {
+                &buf[..br]
             } else if br + buf_concat.len() < MAX_RESPONSE_LENGTH {
                 buf_concat.extend_from_slice(&buf[..br]);
-                buf_concat.iter()
+                &buf_concat[..]
             } else {
                 return Err(mail_send::Error::UnparseableReply);
             };
+            let mut iter = data.iter();
 
             match EhloResponse::parse(&mut iter) {
-                Ok(reply) => return Ok(reply),
+                Ok(reply) => {
+                    self.limits = SmtpLimits::parse(data);
+                    return Ok(reply);
+                }
                 Err(err) => match err {
                     smtp_proto::Error::NeedsMoreData { .. } => {
                         if buf_concat.is_empty() {
@@ -524,6 +561,7 @@ impl SmtpClient<TcpStream> {
                     })?,
                 timeout: self.timeout,
                 session_id: self.session_id,
+                limits: SmtpLimits::default(),
             })
         })
         .await
@@ -543,12 +581,63 @@ impl SmtpClient<TcpStream> {
                 stream: TcpStream::connect(remote_addr).await?,
                 timeout,
                 session_id,
+                limits: SmtpLimits::default(),
             })
         })
         .await
         .map_err(|_| mail_send::Error::Timeout)?
     }
 
+    /// Connects to a remote host address, optionally binding to a local IP
+    /// or tunneling through a proxy
+    pub async fn connect_from(
+        local_ip: Option<IpAddr>,
+        remote_addr: SocketAddr,
+        proxy: Option<(&ProxyConfig, ProxyTarget<'_>)>,
+        timeout: Duration,
+        session_id: u64,
+    ) -> mail_send::Result<Self> {
+        if let Some((proxy, target)) = proxy {
+            Self::connect_proxy(proxy, target, timeout, session_id).await
+        } else if let Some(local_ip) = local_ip {
+            Self::connect_using(local_ip, remote_addr, timeout, session_id).await
+        } else {
+            Self::connect(remote_addr, timeout, session_id).await
+        }
+    }
+
+    /// Starts a fallback attempt if the primary one has not completed after
+    /// the specified delay, returns which attempts were made (RFC 8305)
+    pub async fn connect_racing(
+        primary: impl Future<Output = mail_send::Result<Self>>,
+        fallback: impl Future<Output = mail_send::Result<Self>>,
+        delay: Duration,
+    ) -> (RaceResult, mail_send::Result<Self>) {
+        tokio::pin!(primary);
+        tokio::select! {
+            result = &mut primary => return (RaceResult::Primary, result),
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        tokio::pin!(fallback);
+        tokio::select! {
+            result = &mut primary => match result {
+                Ok(smtp_client) => (RaceResult::Primary, Ok(smtp_client)),
+                Err(err) => match fallback.await {
+                    Ok(smtp_client) => (RaceResult::Fallback, Ok(smtp_client)),
+                    Err(_) => (RaceResult::BothFailed, Err(err)),
+                },
+            },
+            result = &mut fallback => match result {
+                Ok(smtp_client) => (RaceResult::Fallback, Ok(smtp_client)),
+                Err(_) => match primary.await {
+                    Ok(smtp_client) => (RaceResult::Primary, Ok(smtp_client)),
+                    Err(err) => (RaceResult::BothFailed, Err(err)),
+                },
+            },
+        }
+    }
+
     /// Connects to a remote host address using the provided local IP
     pub async fn connect_using(
         local_ip: IpAddr,
@@ -568,6 +657,7 @@ impl SmtpClient<TcpStream> {
                 stream: socket.connect(remote_addr).await?,
                 timeout,
                 session_id,
+                limits: SmtpLimits::default(),
             })
         })
         .await
@@ -606,12 +696,87 @@ impl SmtpClient<TcpStream> {
     }
 }
 
+impl SmtpLimits {
+    pub fn parse(response: &[u8]) -> Self {
+        let mut limits = SmtpLimits::default();
+
+        for line in response.split(|&ch| ch == b'\n') {
+            let Some(line) = line
+                .get(4..)
+                .and_then(|line| std::str::from_utf8(line).ok())
+                .filter(|_| line.starts_with(b"250"))
+            else {
+                continue;
+            };
+            let mut params = line.split_ascii_whitespace();
+            if !params
+                .next()
+                .is_some_and(|keyword| keyword.eq_ignore_ascii_case("LIMITS"))
+            {
+                continue;
+            }
+
+            for param in params {
+                if let Some((name, value)) = param.split_once('=') {
+                    let value = value.parse::<usize>().ok().filter(|&value| value > 0);
+                    match name.to_ascii_uppercase().as_str() {
+                        "RCPTMAX" => limits.rcpt_max = value,
+                        "MAILMAX" => limits.mail_max = value,
+                        "RCPTDOMAINMAX" => limits.rcpt_domain_max = value,
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        limits
+    }
+
+    // Splits the recipients into transactions that fit within the limits
+    pub fn batches<'x>(
+        &self,
+        rcpt_idxs: &[usize],
+        domain: impl Fn(usize) -> &'x str,
+    ) -> Vec<Vec<usize>> {
+        let mut batches: Vec<Vec<usize>> = Vec::new();
+        let mut domains: Vec<&str> = Vec::new();
+
+        for &rcpt_idx in rcpt_idxs {
+            let rcpt_domain = domain(rcpt_idx);
+            let is_new_domain = !domains.contains(&rcpt_domain);
+            let is_full = batches.last().is_none_or(|batch| {
+                self.rcpt_max.is_some_and(|max| batch.len() >= max)
+                    || (is_new_domain
+                        && self.rcpt_domain_max.is_some_and(|max| domains.len() >= max))
+            });
+
+            if is_full {
+                batches.push(Vec::new());
+                domains.clear();
+            }
+            if !domains.contains(&rcpt_domain) {
+                domains.push(rcpt_domain);
+            }
+            batches.last_mut().unwrap().push(rcpt_idx);
+        }
+
+        batches
+    }
+}
+
 impl SmtpClient<TlsStream<TcpStream>> {
     pub fn tls_connection(&self) -> &ClientConnection {
         self.stream.get_ref().1
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RaceResult {
+    Primary,
+    Fallback,
+    BothFailed,
+}
+
 #[allow(clippy::large_enum_variant)]
 pub enum StartTlsResult {
     Success {
@@ -689,3 +854,40 @@ pub(crate) fn from_error_details(err: &Error) -> trc::Error {
         Error::Io(err) => event.details("I/O Error").reason(err),
     }
 }
+
+// Re-encodes the parts using the binary transfer encoding as base64, returns None
+// if the message has no binary parts
+pub fn encode_binary_parts(raw_message: &[u8]) -> Option<Vec<u8>> {
+    let message = MessageParser::new().parse(raw_message)?;
+    let mut edits = Vec::new();
+
+    for part in &message.parts {
+        if matches!(part.body, PartType::Multipart(_) | PartType::Message(_)) {
+            continue;
+        }
+        let Some(header) = part.headers.iter().find(|header| {
+            header.name == HeaderName::ContentTransferEncoding
+                && header
+                    .value
+                    .as_text()
+                    .is_some_and(|value| value.trim().eq_ignore_ascii_case("binary"))
+        }) else {
+            continue;
+        };
+        let body = raw_message.get(part.offset_body as usize..part.offset_end as usize)?;
+        let mut encoded = Vec::with_capacity(body.len() * 4 / 3 + 4);
+        base64_encode_mime(body, &mut encoded, false).ok()?;
+        edits.push((
+            header.offset_field as usize,
+            header.offset_end as usize,
+            b"Content-Transfer-Encoding: base64\r\n".to_vec(),
+        ));
+        edits.push((part.offset_body as usize, part.offset_end as usize, encoded));
+    }
+
+    if !edits.is_empty() {
+        splice_bytes(raw_message, edits)
+    } else {
+        None
+    }
+}
@@ -7,24 +7,28 @@
 use super::{NextHop, lookup::ToNextHop, mta_sts, session::SessionParams};
 use crate::outbound::DeliveryResult;
 use crate::outbound::client::{
-    SmtpClient, from_error_details, from_error_status, from_mail_send_error,
+    RaceResult, SmtpClient, from_error_details, from_error_status, from_mail_send_error,
 };
 use crate::outbound::dane::dnssec::TlsaLookup;
-use crate::outbound::lookup::{DnsLookup, SourceIp};
+use crate::outbound::lookup::{DnsLookup, IpLookupResult, SourceIp};
 use crate::outbound::mta_sts::lookup::MtaStsLookup;
 use crate::outbound::mta_sts::verify::VerifyPolicy;
+use crate::outbound::posture::{TlsPosture, TlsPostureHistory};
+use crate::outbound::proxy::ProxyTarget;
 use crate::outbound::{client::StartTlsResult, dane::verify::TlsaVerify};
 use crate::queue::dsn::SendDsn;
+use crate::queue::moderation::QueueModeration;
 use crate::queue::spool::SmtpSpool;
-use crate::queue::throttle::IsAllowed;
+use crate::queue::throttle::{IsAllowed, IsProviderAllowed};
 use crate::queue::{
-    Error, FROM_REPORT, HostResponse, MessageWrapper, QueueEnvelope, QueuedMessage, Status,
+    Error, FROM_REPORT, HELD_FOR_APPROVAL, HostResponse, MessageWrapper, QueueEnvelope,
+    QueuedMessage, Status, TLS_OPTIONAL,
 };
 use crate::reporting::SmtpReporting;
 use crate::{queue::ErrorDetails, reporting::tls::TlsRptOptions};
 use ahash::AHashMap;
 use common::Server;
-use common::config::smtp::queue::RoutingStrategy;
+use common::config::smtp::queue::{MtaStsFallback, ProviderProfile, RoutingStrategy};
 use common::config::{server::ServerProtocol, smtp::report::AggregateFrequency};
 use common::ipc::{PolicyType, QueueEvent, QueueEventStatus, TlsEvent};
 use compact_str::ToCompactString;
@@ -35,11 +39,16 @@ use mail_auth::{
 use smtp_proto::MAIL_REQUIRETLS;
 use std::sync::Arc;
 use std::{
+    borrow::Cow,
     net::{IpAddr, Ipv4Addr, SocketAddr},
-    time::Instant,
+    time::{Duration, Instant},
 };
 use store::write::{BatchBuilder, QueueClass, ValueClass, now};
 use trc::{DaneEvent, DeliveryEvent, MtaStsEvent, ServerEvent, TlsRptEvent};
+use utils::domain_to_ascii;
+
+// How long to remember the address family that last connected to a host
+const IP_FAMILY_TTL: Duration = Duration::from_secs(10 * 60);
 
 impl QueuedMessage {
     pub fn try_deliver(self, server: Server) {
@@ -83,9 +92,15 @@ impl QueuedMessage {
                         Total = message.message.recipients.len(),
                     );
 
-                    // Attempt delivery
+                    // Attempt delivery, held messages are only scheduled to be bounced
+                    // once the approval window elapses
                     let start_time = Instant::now();
-                    let queue_event = self.deliver_task(server.clone(), message).await;
+                    let queue_event = if message.message.flags & HELD_FOR_APPROVAL == 0 {
+                        self.deliver_task(server.clone(), message).await
+                    } else {
+                        server.expire_held_message(message, self.due).await;
+                        QueueEventStatus::Completed
+                    };
 
                     trc::event!(
                         Delivery(DeliveryEvent::AttemptEnd),
@@ -244,6 +259,10 @@ impl QueuedMessage {
         let no_ip = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
         let mut delivery_results: Vec<DeliveryResult> = Vec::new();
         'next_route: for ((domain, route), rcpt_idxs) in routes {
+            // IDNs are resolved using their ASCII form
+            let domain = domain_to_ascii(domain).unwrap_or(Cow::Borrowed(domain));
+            let domain = domain.as_ref();
+
             trc::event!(
                 Delivery(DeliveryEvent::DomainDeliveryStart),
                 SpanId = message.span_id,
@@ -253,6 +272,7 @@ impl QueuedMessage {
             // Build envelope
             let mut envelope =
                 QueueEnvelope::new(&message.message, &message.message.recipients[rcpt_idxs[0]]);
+            let route_results = delivery_results.len();
 
             // Throttle recipient domain
             for throttle in &queue_config.outbound_limiters.rcpt {
@@ -372,7 +392,13 @@ impl QueuedMessage {
                 };
 
             // Obtain MTA-STS policy for domain
-            let mta_sts_policy = if mx_config.is_some() && tls_strategy.try_mta_sts() && is_smtp {
+            let mut require_dane = false;
+            let require_tls = queue_config.is_tls_required_for(domain);
+            let is_requiretls = (message.message.flags & MAIL_REQUIRETLS) != 0;
+            // Messages with a "TLS-Required: No" header ignore MTA-STS and DANE (RFC 8689)
+            let tls_optional = !is_requiretls && (message.message.flags & TLS_OPTIONAL) != 0;
+            let try_mta_sts = tls_strategy.try_mta_sts() && !tls_optional;
+            let mta_sts_policy = if mx_config.is_some() && try_mta_sts && is_smtp {
                 let time = Instant::now();
                 match server
                     .lookup_mta_sts_policy(domain, tls_strategy.timeout_mta_sts)
@@ -474,12 +500,35 @@ impl QueuedMessage {
                             }
                         }
 
+                        // Policies that can't be fetched and aren't cached are handled
+                        // according to the configured fallback
+                        let fallback = if matches!(
+                            err,
+                            mta_sts::Error::Dns(mail_auth::Error::DnsRecordNotFound(_))
+                        ) {
+                            MtaStsFallback::EnforceCached
+                        } else {
+                            tls_strategy.mta_sts_fallback
+                        };
                         if strict {
-                            delivery_results.push(DeliveryResult::domain(
-                                Status::from_mta_sts_error(domain, err),
-                                rcpt_idxs,
-                            ));
-                            continue 'next_route;
+                            match fallback {
+                                MtaStsFallback::EnforceCached => {
+                                    delivery_results.push(DeliveryResult::domain(
+                                        Status::from_mta_sts_error(domain, err),
+                                        rcpt_idxs,
+                                    ));
+                                    continue 'next_route;
+                                }
+                                MtaStsFallback::Dane | MtaStsFallback::Deliver => {
+                                    require_dane = fallback == MtaStsFallback::Dane;
+                                    trc::event!(
+                                        MtaSts(MtaStsEvent::PolicyFetchFallback),
+                                        SpanId = message.span_id,
+                                        Domain = domain.to_string(),
+                                        Details = if require_dane { "dane" } else { "deliver" },
+                                    );
+                                }
+                            }
                         }
 
                         None
@@ -566,6 +615,7 @@ impl QueuedMessage {
 
             // Try delivering message
             let mut last_status: Status<HostResponse<String>, ErrorDetails> = Status::Scheduled;
+            let mut provider = None;
             'next_host: for remote_host in &remote_hosts {
                 // Validate MTA-STS
                 envelope.mx = remote_host.hostname();
@@ -627,38 +677,61 @@ impl QueuedMessage {
                     }
                 }
 
+                // Throttle destination provider, once per provider and route
+                let profile = if is_smtp && mx_config.is_some() {
+                    queue_config.provider_profile(envelope.mx)
+                } else {
+                    None
+                };
+                if let Some(profile) = profile
+                    && provider.is_none_or(|p: &ProviderProfile| p.id != profile.id)
+                    && let Err(retry_at) =
+                        server.is_provider_allowed(profile, message.span_id).await
+                {
+                    delivery_results.push(DeliveryResult::rate_limited(rcpt_idxs, retry_at));
+                    continue 'next_route;
+                }
+                provider = profile;
+
                 // Obtain source and remote IPs
                 let time = Instant::now();
-                let resolve_result = match server.resolve_host(remote_host, &envelope).await {
-                    Ok(result) => {
-                        trc::event!(
-                            Delivery(DeliveryEvent::IpLookup),
-                            SpanId = message.span_id,
-                            Domain = domain.to_string(),
-                            Hostname = envelope.mx.to_string(),
-                            Details = result
-                                .remote_ips
-                                .iter()
-                                .map(|ip| trc::Value::from(*ip))
-                                .collect::<Vec<_>>(),
-                            Limit = remote_host.max_multi_homed(),
-                            Elapsed = time.elapsed(),
-                        );
-
-                        result
+                let resolve_result = if remote_host.proxy().is_some_and(|proxy| proxy.remote_dns) {
+                    // Hostnames are resolved by the proxy
+                    IpLookupResult {
+                        remote_ips: vec![no_ip],
                     }
-                    Err(status) => {
-                        trc::event!(
-                            Delivery(DeliveryEvent::IpLookupFailed),
-                            SpanId = message.span_id,
-                            Domain = domain.to_string(),
-                            Hostname = envelope.mx.to_string(),
-                            Details = status.to_string(),
-                            Elapsed = time.elapsed(),
-                        );
+                } else {
+                    match server.resolve_host(remote_host, &envelope).await {
+                        Ok(result) => {
+                            trc::event!(
+                                Delivery(DeliveryEvent::IpLookup),
+                                SpanId = message.span_id,
+                                Domain = domain.to_string(),
+                                Hostname = envelope.mx.to_string(),
+                                Details = result
+                                    .remote_ips
+                                    .iter()
+                                    .map(|ip| trc::Value::from(*ip))
+                                    .collect::<Vec<_>>(),
+                                Limit = remote_host.max_multi_homed(),
+                                Elapsed = time.elapsed(),
+                            );
+
+                            result
+                        }
+                        Err(status) => {
+                            trc::event!(
+                                Delivery(DeliveryEvent::IpLookupFailed),
+                                SpanId = message.span_id,
+                                Domain = domain.to_string(),
+                                Hostname = envelope.mx.to_string(),
+                                Details = status.to_string(),
+                                Elapsed = time.elapsed(),
+                            );
 
-                        last_status = status;
-                        continue 'next_host;
+                            last_status = status;
+                            continue 'next_host;
+                        }
                     }
                 };
 
@@ -672,9 +745,12 @@ impl QueuedMessage {
                 );
 
                 // Lookup DANE policy
-                let dane_policy = if tls_strategy.try_dane() && is_smtp {
+                let dane_policy = if (tls_strategy.try_dane() || require_dane)
+                    && is_smtp
+                    && !tls_optional
+                {
                     let time = Instant::now();
-                    let strict = tls_strategy.is_dane_required();
+                    let strict = tls_strategy.is_dane_required() || require_dane;
                     match server
                         .tlsa_lookup(format!("_25._tcp.{}.", envelope.mx))
                         .await
@@ -832,8 +908,10 @@ impl QueuedMessage {
                     None
                 };
 
-                // Try each IP address
-                'next_ip: for remote_ip in resolve_result.remote_ips {
+                // Try each IP address, alternating address families
+                let mut remote_ips = resolve_result
+                    .into_attempt_order(server.inner.cache.ip_family.get(envelope.mx));
+                'next_ip: while let Some(mut remote_ip) = remote_ips.pop_front() {
                     // Throttle remote host
                     envelope.remote_ip = remote_ip;
                     for throttle in &queue_config.outbound_limiters.remote {
@@ -867,29 +945,98 @@ impl QueuedMessage {
                     );
 
                     // Set source IP, if any
-                    let ip_host = conn_strategy.source_ip(remote_ip.is_ipv4());
+                    let proxy = remote_host.proxy();
+                    let mut ip_host = if proxy.is_none() {
+                        conn_strategy.source_ip(remote_ip.is_ipv4())
+                    } else {
+                        None
+                    };
 
-                    // Connect
+                    // Connect, racing against the other address family (RFC 8305)
                     let time = Instant::now();
-                    let mut smtp_client = match if let Some(ip_host) = ip_host {
-                        envelope.local_ip = ip_host.ip;
-                        SmtpClient::connect_using(
-                            ip_host.ip,
-                            SocketAddr::new(remote_ip, remote_host.port()),
-                            conn_strategy.timeout_connect,
-                            span_id,
+                    let connect = SmtpClient::connect_from(
+                        ip_host.map(|ip_host| ip_host.ip),
+                        SocketAddr::new(remote_ip, remote_host.port()),
+                        proxy.map(|proxy| {
+                            (
+                                proxy,
+                                if proxy.remote_dns {
+                                    ProxyTarget::Host(envelope.mx, remote_host.port())
+                                } else {
+                                    ProxyTarget::Addr(SocketAddr::new(
+                                        remote_ip,
+                                        remote_host.port(),
+                                    ))
+                                },
+                            )
+                        }),
+                        conn_strategy.timeout_connect,
+                        span_id,
+                    );
+                    let fallback = conn_strategy
+                        .happy_eyeballs
+                        .filter(|_| proxy.is_none())
+                        .and_then(|delay| {
+                            remote_ips
+                                .iter()
+                                .position(|ip| ip.is_ipv4() != remote_ip.is_ipv4())
+                                .map(|pos| (delay, pos))
+                        });
+                    let result = if let Some((delay, pos)) = fallback {
+                        let fallback_ip = remote_ips[pos];
+                        let fallback_host = conn_strategy.source_ip(fallback_ip.is_ipv4());
+                        let (race, result) = SmtpClient::connect_racing(
+                            connect,
+                            SmtpClient::connect_from(
+                                fallback_host.map(|ip_host| ip_host.ip),
+                                SocketAddr::new(fallback_ip, remote_host.port()),
+                                None,
+                                conn_strategy.timeout_connect,
+                                span_id,
+                            ),
+                            delay,
                         )
-                        .await
+                        .await;
+                        if race != RaceResult::Primary {
+                            remote_ips.remove(pos);
+                        }
+                        if race == RaceResult::Fallback {
+                            remote_ip = fallback_ip;
+                            ip_host = fallback_host;
+                            envelope.remote_ip = remote_ip;
+
+                            // Throttle the address that won the race
+                            for throttle in &queue_config.outbound_limiters.remote {
+                                if let Err(retry_at) = server
+                                    .is_allowed(throttle, &envelope, message.span_id)
+                                    .await
+                                {
+                                    trc::event!(
+                                        Delivery(DeliveryEvent::RateLimitExceeded),
+                                        SpanId = message.span_id,
+                                        Id = throttle.id.clone(),
+                                        RemoteIp = remote_ip,
+                                    );
+                                    delivery_results
+                                        .push(DeliveryResult::rate_limited(rcpt_idxs, retry_at));
+                                    continue 'next_route;
+                                }
+                            }
+                        }
+                        result
                     } else {
-                        envelope.local_ip = no_ip;
-                        SmtpClient::connect(
-                            SocketAddr::new(remote_ip, remote_host.port()),
-                            conn_strategy.timeout_connect,
-                            span_id,
-                        )
-                        .await
-                    } {
+                        connect.await
+                    };
+                    envelope.local_ip = ip_host.map_or(no_ip, |ip_host| ip_host.ip);
+                    let mut smtp_client = match result {
                         Ok(smtp_client) => {
+                            if proxy.is_none() {
+                                server.inner.cache.ip_family.insert(
+                                    envelope.mx.to_string(),
+                                    remote_ip.is_ipv4(),
+                                    IP_FAMILY_TTL,
+                                );
+                            }
                             trc::event!(
                                 Delivery(DeliveryEvent::Connect),
                                 SpanId = message.span_id,
@@ -938,13 +1085,17 @@ impl QueuedMessage {
                     };
 
                     // Prepare TLS connector
-                    let is_strict_tls = tls_strategy.is_tls_required()
-                        || (message.message.flags & MAIL_REQUIRETLS) != 0
+                    let is_strict_tls = require_tls
+                        || is_requiretls
+                        || (!tls_optional && tls_strategy.is_tls_required())
                         || mta_sts_policy.is_some()
                         || dane_policy.is_some();
                     // As per RFC7671 Section 5.1, DANE-EE(3) allows name mismatch
-                    let tls_connector = if tls_strategy.allow_invalid_certs
-                        || remote_host.allow_invalid_certs()
+                    let tls_connector = if (!require_tls
+                        && !is_requiretls
+                        && (tls_optional
+                            || tls_strategy.allow_invalid_certs
+                            || remote_host.allow_invalid_certs()))
                         || dane_policy.as_ref().is_some_and(|t| t.has_end_entities)
                     {
                         &server.inner.data.smtp_connectors.dummy_verify
@@ -952,6 +1103,7 @@ impl QueuedMessage {
                         &server.inner.data.smtp_connectors.pki_verify
                     };
 
+                    let tls_posture: Option<TlsPosture>;
                     if !remote_host.implicit_tls() {
                         // Read greeting
                         smtp_client.timeout = conn_strategy.timeout_greeting;
@@ -998,8 +1150,8 @@ impl QueuedMessage {
                             }
                         };
 
-                        // Try starting TLS
-                        if tls_strategy.try_start_tls() {
+                        // Try starting TLS, the strategy is ignored when TLS is required
+                        if tls_strategy.try_start_tls() || require_tls || is_requiretls {
                             let time = Instant::now();
                             smtp_client.timeout = tls_strategy.timeout_tls;
                             match smtp_client
@@ -1075,6 +1227,14 @@ impl QueuedMessage {
                                             .await;
                                     }
 
+                                    tls_posture = TlsPosture::encrypted(
+                                        envelope.mx,
+                                        mta_sts_policy.is_some(),
+                                        dane_policy.is_some(),
+                                        smtp_client.tls_connection(),
+                                    )
+                                    .into();
+
                                     // Deliver message over TLS
                                     message
                                         .deliver(
@@ -1127,12 +1287,23 @@ impl QueuedMessage {
                                             .await;
                                     }
 
-                                    if is_strict_tls {
+                                    if require_tls {
+                                        trc::event!(
+                                            Delivery(DeliveryEvent::TlsRequired),
+                                            SpanId = message.span_id,
+                                            Domain = domain.to_string(),
+                                            Hostname = envelope.mx.to_string(),
+                                        );
+
+                                        last_status = Status::tls_required(envelope.mx, domain);
+                                        continue 'next_host;
+                                    } else if is_strict_tls {
                                         last_status =
                                             Status::from_starttls_error(envelope.mx, response);
                                         continue 'next_host;
                                     } else {
                                         // TLS is not required, proceed in plain-text
+                                        tls_posture = TlsPosture::plaintext(envelope.mx).into();
                                         params.capabilities = Some(capabilities);
                                         message
                                             .deliver(
@@ -1175,6 +1346,16 @@ impl QueuedMessage {
                                             .await;
                                     }
 
+                                    if require_tls {
+                                        trc::event!(
+                                            Delivery(DeliveryEvent::TlsRequired),
+                                            SpanId = message.span_id,
+                                            Domain = domain.to_string(),
+                                            Hostname = envelope.mx.to_string(),
+                                            Reason = from_mail_send_error(&error),
+                                        );
+                                    }
+
                                     last_status = if is_strict_tls {
                                         Status::from_tls_error(envelope.mx, error)
                                     } else {
@@ -1192,6 +1373,8 @@ impl QueuedMessage {
                                 Hostname = envelope.mx.to_string(),
                             );
 
+                            tls_posture = TlsPosture::plaintext(envelope.mx).into();
+
                             message
                                 .deliver(smtp_client, rcpt_idxs, &mut delivery_results, params)
                                 .await
@@ -1231,17 +1414,59 @@ impl QueuedMessage {
                             continue 'next_host;
                         }
 
+                        tls_posture = TlsPosture::encrypted(
+                            envelope.mx,
+                            mta_sts_policy.is_some(),
+                            dane_policy.is_some(),
+                            smtp_client.tls_connection(),
+                        )
+                        .into();
+
                         // Deliver message
                         message
                             .deliver(smtp_client, rcpt_idxs, &mut delivery_results, params)
                             .await
                     }
 
+                    // Keep track of the TLS posture of domains that accepted the message
+                    if is_smtp
+                        && let Some(posture) = tls_posture
+                        && delivery_results[route_results..]
+                            .iter()
+                            .any(|result| result.is_completed())
+                    {
+                        server.record_tls_posture(domain, posture).await;
+                    }
+
+                    // Back off from providers deferring messages due to the sending rate
+                    if let Some(profile) = provider
+                        && let Some(response) = delivery_results[route_results..]
+                            .iter()
+                            .find_map(|result| result.temporary_response())
+                    {
+                        server
+                            .provider_backoff(profile, &response.to_string(), message.span_id)
+                            .await;
+                    } else if let Some(profile) = provider
+                        && delivery_results[route_results..]
+                            .iter()
+                            .any(|result| result.is_completed())
+                    {
+                        server.provider_reset(profile, message.span_id).await;
+                    }
+
                     // Continue with the next domain/route
                     continue 'next_route;
                 }
             }
 
+            // Back off from providers rejecting connections due to the sending rate
+            if let (Some(profile), Some(response)) = (provider, last_status.temporary_response()) {
+                server
+                    .provider_backoff(profile, &response.to_string(), message.span_id)
+                    .await;
+            }
+
             // Update status
             delivery_results.push(DeliveryResult::domain(last_status, rcpt_idxs));
         }
@@ -13,12 +13,40 @@ use common::{
 };
 use mail_auth::{IpLookupStrategy, MX};
 use rand::{Rng, seq::SliceRandom};
-use std::{future::Future, net::IpAddr, sync::Arc};
+use std::{collections::VecDeque, future::Future, net::IpAddr, sync::Arc};
 
 pub struct IpLookupResult {
     pub remote_ips: Vec<IpAddr>,
 }
 
+impl IpLookupResult {
+    // Alternates address families starting with the preferred one (RFC 8305)
+    pub fn into_attempt_order(self, prefer_ipv4: Option<bool>) -> VecDeque<IpAddr> {
+        let Some(first) = self.remote_ips.first() else {
+            return VecDeque::new();
+        };
+        let prefer_ipv4 = prefer_ipv4.unwrap_or(first.is_ipv4());
+        let (mut preferred, mut other): (VecDeque<_>, VecDeque<_>) = self
+            .remote_ips
+            .into_iter()
+            .partition(|ip| ip.is_ipv4() == prefer_ipv4);
+        let mut remote_ips = VecDeque::with_capacity(preferred.len() + other.len());
+
+        loop {
+            match (preferred.pop_front(), other.pop_front()) {
+                (Some(a), Some(b)) => {
+                    remote_ips.push_back(a);
+                    remote_ips.push_back(b);
+                }
+                (Some(ip), None) | (None, Some(ip)) => remote_ips.push_back(ip),
+                (None, None) => break,
+            }
+        }
+
+        remote_ips
+    }
+}
+
 pub trait DnsLookup: Sync + Send {
     fn ip_lookup(
         &self,
@@ -7,7 +7,7 @@
 use crate::queue::{Error, ErrorDetails, HostResponse, Status, UnexpectedResponse};
 use common::config::{
     server::ServerProtocol,
-    smtp::queue::{MxConfig, RelayConfig},
+    smtp::queue::{MxConfig, ProxyConfig, RelayConfig},
 };
 use mail_auth::IpLookupStrategy;
 use mail_send::Credentials;
@@ -20,6 +20,8 @@ pub mod delivery;
 pub mod local;
 pub mod lookup;
 pub mod mta_sts;
+pub mod posture;
+pub mod proxy;
 pub mod session;
 
 pub(super) enum DeliveryResult {
@@ -38,6 +40,16 @@ pub(super) enum DeliveryResult {
 }
 
 impl Status<HostResponse<String>, ErrorDetails> {
+    pub fn temporary_response(&self) -> Option<&Response<String>> {
+        match self {
+            Status::TemporaryFailure(ErrorDetails {
+                details: Error::UnexpectedResponse(response),
+                ..
+            }) => Some(&response.response),
+            _ => None,
+        }
+    }
+
     pub fn from_smtp_error(hostname: &str, command: &str, err: mail_send::Error) -> Self {
         match err {
             mail_send::Error::Io(_)
@@ -150,6 +162,15 @@ impl Status<HostResponse<String>, ErrorDetails> {
         })
     }
 
+    pub fn tls_required(hostname: &str, domain: &str) -> Self {
+        Status::PermanentFailure(ErrorDetails {
+            entity: hostname.into(),
+            details: Error::TlsError(format!(
+                "Delivery to {domain} requires a verified TLS connection."
+            )),
+        })
+    }
+
     pub fn from_mail_auth_error(entity: &str, err: mail_auth::Error) -> Self {
         match &err {
             mail_auth::Error::DnsRecordNotFound(code) => Status::PermanentFailure(ErrorDetails {
@@ -271,6 +292,14 @@ impl NextHop<'_> {
         }
     }
 
+    #[inline(always)]
+    pub fn proxy(&self) -> Option<&ProxyConfig> {
+        match self {
+            NextHop::MX { config, .. } => config.proxy.as_ref(),
+            NextHop::Relay(host) => host.proxy.as_ref(),
+        }
+    }
+
     #[inline(always)]
     fn port(&self) -> u16 {
         match self {
@@ -338,4 +367,26 @@ impl DeliveryResult {
     pub fn account(status: Status<HostResponse<String>, ErrorDetails>, rcpt_idx: usize) -> Self {
         DeliveryResult::Account { status, rcpt_idx }
     }
+
+    pub fn temporary_response(&self) -> Option<&Response<String>> {
+        match self {
+            DeliveryResult::Domain { status, .. } | DeliveryResult::Account { status, .. } => {
+                status.temporary_response()
+            }
+            DeliveryResult::RateLimited { .. } => None,
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        matches!(
+            self,
+            DeliveryResult::Domain {
+                status: Status::Completed(_),
+                ..
+            } | DeliveryResult::Account {
+                status: Status::Completed(_),
+                ..
+            }
+        )
+    }
 }
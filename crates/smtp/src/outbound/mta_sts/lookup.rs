@@ -9,8 +9,9 @@ use std::{fmt::Display, sync::Arc, time::Duration};
 #[cfg(feature = "test_mode")]
 pub static STS_TEST_POLICY: parking_lot::Mutex<Vec<u8>> = parking_lot::Mutex::new(Vec::new());
 
-use common::{Server, config::smtp::resolver::Policy};
+use common::{KV_MTA_STS_POLICY, Server, config::smtp::resolver::Policy};
 use mail_auth::{mta_sts::MtaSts, report::tlsrpt::ResultType};
+use store::{dispatch::lookup::KeyValue, write::now};
 
 use super::{Error, parse::ParsePolicy};
 
@@ -20,6 +21,12 @@ use utils::HttpLimitResponse;
 #[cfg(not(feature = "test_mode"))]
 const MAX_POLICY_SIZE: usize = 1024 * 1024;
 
+#[derive(serde::Serialize, serde::Deserialize)]
+struct StoredPolicy {
+    policy: Policy,
+    expires: u64,
+}
+
 pub trait MtaStsLookup: Sync + Send {
     fn lookup_mta_sts_policy(
         &self,
@@ -50,7 +57,7 @@ impl MtaStsLookup for Server {
             Ok(record) => record,
             Err(err) => {
                 // Return the cached policy in case of failure
-                return if let Some(value) = self.inner.cache.dbs_mta_sts.get(domain) {
+                return if let Some(value) = cached_policy(self, domain).await {
                     Ok(value)
                 } else {
                     Err(err.into())
@@ -59,46 +66,127 @@ impl MtaStsLookup for Server {
         };
 
         // Check if the policy has been cached
-        if let Some(value) = self.inner.cache.dbs_mta_sts.get(domain)
+        let cached = cached_policy(self, domain).await;
+        if let Some(value) = &cached
             && value.id == record.id
         {
-            return Ok(value);
+            return Ok(value.clone());
         }
 
-        // Fetch policy
-        #[cfg(not(feature = "test_mode"))]
-        let bytes = reqwest::Client::builder()
-            .user_agent(common::USER_AGENT)
-            .timeout(timeout)
-            .redirect(reqwest::redirect::Policy::none())
-            .build()?
-            .get(format!("https://mta-sts.{domain}/.well-known/mta-sts.txt"))
-            .send()
-            .await?
-            .bytes_with_limit(MAX_POLICY_SIZE)
-            .await?
-            .ok_or_else(|| Error::InvalidPolicy("Policy too large".to_string()))?;
-        #[cfg(feature = "test_mode")]
-        let bytes = STS_TEST_POLICY.lock().clone();
-
-        // Parse policy
-        let policy = Arc::new(Policy::parse(
-            std::str::from_utf8(&bytes).map_err(|err| Error::InvalidPolicy(err.to_string()))?,
-            record.id.clone(),
-        )?);
-
-        self.inner.cache.dbs_mta_sts.insert(
-            domain.to_string(),
-            policy.clone(),
-            Duration::from_secs(if (3600..31557600).contains(&policy.max_age) {
-                policy.max_age
+        // Unexpired cached policies are used when a new policy can't be fetched
+        match fetch_policy(self, domain, record.id.clone(), timeout).await {
+            Ok(policy) => Ok(policy),
+            Err(err) => cached.ok_or(err),
+        }
+    }
+}
+
+async fn cached_policy(server: &Server, domain: &str) -> Option<Arc<Policy>> {
+    if let Some(value) = server.inner.cache.dbs_mta_sts.get(domain) {
+        return Some(value);
+    }
+
+    // Policies are persisted so their max_age is honored across restarts
+    match server
+        .in_memory_store()
+        .key_get::<String>(KeyValue::<()>::build_key(KV_MTA_STS_POLICY, domain))
+        .await
+    {
+        Ok(Some(value)) => {
+            let stored = serde_json::from_str::<StoredPolicy>(&value).ok()?;
+            let now = now();
+            if stored.expires > now {
+                let policy = Arc::new(stored.policy);
+                server.inner.cache.dbs_mta_sts.insert(
+                    domain.to_string(),
+                    policy.clone(),
+                    Duration::from_secs(stored.expires - now),
+                );
+                Some(policy)
             } else {
-                86400
-            }),
-        );
+                None
+            }
+        }
+        Ok(None) => None,
+        Err(err) => {
+            trc::error!(
+                err.details("Failed to obtain cached MTA-STS policy")
+                    .caused_by(trc::location!())
+            );
+            None
+        }
+    }
+}
 
-        Ok(policy)
+#[allow(unused_variables)]
+async fn fetch_policy(
+    server: &Server,
+    domain: &str,
+    id: String,
+    timeout: Duration,
+) -> Result<Arc<Policy>, Error> {
+    // Fetch policy
+    #[cfg(not(feature = "test_mode"))]
+    let bytes = reqwest::Client::builder()
+        .user_agent(common::USER_AGENT)
+        .timeout(timeout)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()?
+        .get(format!("https://mta-sts.{domain}/.well-known/mta-sts.txt"))
+        .send()
+        .await?
+        .bytes_with_limit(MAX_POLICY_SIZE)
+        .await?
+        .ok_or_else(|| Error::InvalidPolicy("Policy too large".to_string()))?;
+    #[cfg(feature = "test_mode")]
+    let bytes = STS_TEST_POLICY.lock().clone();
+
+    // Parse policy
+    let policy = Arc::new(Policy::parse(
+        std::str::from_utf8(&bytes).map_err(|err| Error::InvalidPolicy(err.to_string()))?,
+        id,
+    )?);
+
+    let max_age = if (3600..31557600).contains(&policy.max_age) {
+        policy.max_age
+    } else {
+        86400
+    };
+    server.inner.cache.dbs_mta_sts.insert(
+        domain.to_string(),
+        policy.clone(),
+        Duration::from_secs(max_age),
+    );
+    match serde_json::to_string(&StoredPolicy {
+        policy: policy.as_ref().clone(),
+        expires: now() + max_age,
+    }) {
+        Ok(value) => {
+            if let Err(err) = server
+                .in_memory_store()
+                .key_set(
+                    KeyValue::with_prefix(KV_MTA_STS_POLICY, domain, value.into_bytes())
+                        .expires(max_age),
+                )
+                .await
+            {
+                trc::error!(
+                    err.details("Failed to store MTA-STS policy")
+                        .caused_by(trc::location!())
+                );
+            }
+        }
+        Err(err) => {
+            trc::error!(
+                trc::StoreEvent::DeserializeError
+                    .reason(err)
+                    .details("Failed to serialize MTA-STS policy")
+                    .caused_by(trc::location!())
+            );
+        }
     }
+
+    Ok(policy)
 }
 
 impl From<&Error> for ResultType {
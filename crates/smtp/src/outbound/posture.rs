@@ -0,0 +1,143 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{KV_TLS_POSTURE, Server};
+use rustls::ClientConnection;
+use std::future::Future;
+use store::{dispatch::lookup::KeyValue, write::now};
+use trc::AddContext;
+
+const MAX_HISTORY: i64 = 50;
+const HISTORY_EXPIRY: u64 = 90 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TlsPosture {
+    pub timestamp: u64,
+    pub mx: String,
+    pub tls: bool,
+    pub dane: bool,
+    pub mta_sts: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_version: Option<String>,
+}
+
+pub trait TlsPostureHistory: Sync + Send {
+    fn record_tls_posture(
+        &self,
+        domain: &str,
+        posture: TlsPosture,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn tls_posture_history(
+        &self,
+        domain: &str,
+    ) -> impl Future<Output = trc::Result<Vec<TlsPosture>>> + Send;
+}
+
+impl TlsPostureHistory for Server {
+    async fn record_tls_posture(&self, domain: &str, posture: TlsPosture) {
+        // Each delivery is stored under its own key, a per domain counter
+        // assigns the slot so only the most recent deliveries are kept
+        let result = async {
+            let seq = self
+                .in_memory_store()
+                .counter_incr(
+                    KeyValue::with_prefix(KV_TLS_POSTURE, domain.as_bytes(), 1)
+                        .expires(HISTORY_EXPIRY),
+                    true,
+                )
+                .await?;
+            let value = serde_json::to_vec(&posture).map_err(|err| {
+                trc::StoreEvent::DeserializeError
+                    .into_err()
+                    .reason(err)
+                    .details("Failed to serialize TLS posture")
+            })?;
+            self.in_memory_store()
+                .key_set(
+                    KeyValue::with_prefix(KV_TLS_POSTURE, slot_key(domain, seq), value)
+                        .expires(HISTORY_EXPIRY),
+                )
+                .await
+        }
+        .await;
+
+        if let Err(err) = result {
+            trc::error!(
+                err.ctx(trc::Key::Domain, domain.to_string())
+                    .details("Failed to record TLS posture")
+                    .caused_by(trc::location!())
+            );
+        }
+    }
+
+    async fn tls_posture_history(&self, domain: &str) -> trc::Result<Vec<TlsPosture>> {
+        let last_seq = self
+            .in_memory_store()
+            .counter_get(KeyValue::<()>::build_key(KV_TLS_POSTURE, domain.as_bytes()))
+            .await
+            .caused_by(trc::location!())?;
+
+        let mut history = Vec::new();
+        for seq in (last_seq - MAX_HISTORY + 1).max(1)..=last_seq {
+            // Slots that expired or are still being written are skipped
+            if let Some(value) = self
+                .in_memory_store()
+                .key_get::<String>(KeyValue::<()>::build_key(
+                    KV_TLS_POSTURE,
+                    slot_key(domain, seq),
+                ))
+                .await
+                .caused_by(trc::location!())?
+            {
+                history.push(serde_json::from_str::<TlsPosture>(&value).map_err(|err| {
+                    trc::StoreEvent::DeserializeError
+                        .into_err()
+                        .reason(err)
+                        .details("Failed to deserialize TLS posture")
+                })?);
+            }
+        }
+        history.sort_by_key(|posture| posture.timestamp);
+
+        Ok(history)
+    }
+}
+
+// Domain names cannot contain NUL, so slots never collide with the counter key
+fn slot_key(domain: &str, seq: i64) -> Vec<u8> {
+    let mut key = Vec::with_capacity(domain.len() + 5);
+    key.extend_from_slice(domain.as_bytes());
+    key.push(0);
+    key.extend_from_slice(&(seq.rem_euclid(MAX_HISTORY) as u32).to_be_bytes());
+    key
+}
+
+impl TlsPosture {
+    pub fn plaintext(mx: &str) -> Self {
+        TlsPosture {
+            timestamp: now(),
+            mx: mx.to_string(),
+            tls: false,
+            dane: false,
+            mta_sts: false,
+            tls_version: None,
+        }
+    }
+
+    pub fn encrypted(mx: &str, mta_sts: bool, dane: bool, conn: &ClientConnection) -> Self {
+        TlsPosture {
+            timestamp: now(),
+            mx: mx.to_string(),
+            tls: true,
+            dane,
+            mta_sts,
+            tls_version: conn
+                .protocol_version()
+                .map(|version| format!("{version:?}")),
+        }
+    }
+}
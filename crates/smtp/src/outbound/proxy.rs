@@ -0,0 +1,198 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{net::SocketAddr, time::Duration};
+
+use common::config::smtp::queue::{ProxyConfig, ProxyProtocol};
+use mail_builder::encoders::base64::base64_encode;
+use mail_send::Credentials;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::client::{SmtpClient, SmtpLimits};
+
+pub enum ProxyTarget<'x> {
+    Host(&'x str, u16),
+    Addr(SocketAddr),
+}
+
+impl SmtpClient<TcpStream> {
+    /// Connects to a remote host through a SOCKS5 or HTTP CONNECT proxy
+    pub async fn connect_proxy(
+        proxy: &ProxyConfig,
+        target: ProxyTarget<'_>,
+        timeout: Duration,
+        session_id: u64,
+    ) -> mail_send::Result<Self> {
+        tokio::time::timeout(timeout, async {
+            let mut stream = TcpStream::connect((proxy.address.as_str(), proxy.port)).await?;
+            match proxy.protocol {
+                ProxyProtocol::Socks5 => {
+                    socks5_connect(&mut stream, proxy.auth.as_ref(), &target).await?
+                }
+                ProxyProtocol::Http => {
+                    http_connect(&mut stream, proxy.auth.as_ref(), &target).await?
+                }
+            }
+
+            Ok(SmtpClient {
+                stream,
+                timeout,
+                session_id,
+                limits: SmtpLimits::default(),
+            })
+        })
+        .await
+        .map_err(|_| mail_send::Error::Timeout)?
+    }
+}
+
+// SOCKS5 handshake (RFC 1928) with optional username/password authentication (RFC 1929)
+pub async fn socks5_connect<T: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut T,
+    auth: Option<&Credentials<String>>,
+    target: &ProxyTarget<'_>,
+) -> mail_send::Result<()> {
+    let auth = auth.and_then(|auth| match auth {
+        Credentials::Plain { username, secret } => Some((username, secret)),
+        _ => None,
+    });
+    if auth.is_some() {
+        stream.write_all(&[0x05, 0x02, 0x00, 0x02]).await?;
+    } else {
+        stream.write_all(&[0x05, 0x01, 0x00]).await?;
+    }
+
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    match (reply, auth) {
+        ([0x05, 0x00], _) => {}
+        ([0x05, 0x02], Some((username, secret))) => {
+            if username.len() > 255 || secret.len() > 255 {
+                return Err(proxy_error("SOCKS5 credentials are too long"));
+            }
+            let mut request = Vec::with_capacity(username.len() + secret.len() + 3);
+            request.push(0x01);
+            request.push(username.len() as u8);
+            request.extend_from_slice(username.as_bytes());
+            request.push(secret.len() as u8);
+            request.extend_from_slice(secret.as_bytes());
+            stream.write_all(&request).await?;
+
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0x00 {
+                return Err(proxy_error("SOCKS5 authentication failed"));
+            }
+        }
+        _ => return Err(proxy_error("SOCKS5 authentication method not supported")),
+    }
+
+    // CONNECT request
+    let mut request = vec![0x05, 0x01, 0x00];
+    let port = match target {
+        ProxyTarget::Host(host, port) => {
+            let host = host.strip_suffix('.').unwrap_or(host);
+            if host.len() > 255 {
+                return Err(proxy_error("Hostname is too long"));
+            }
+            request.push(0x03);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+            *port
+        }
+        ProxyTarget::Addr(SocketAddr::V4(addr)) => {
+            request.push(0x01);
+            request.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        ProxyTarget::Addr(SocketAddr::V6(addr)) => {
+            request.push(0x04);
+            request.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+    };
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != 0x05 {
+        return Err(proxy_error("Invalid SOCKS5 reply"));
+    } else if reply[1] != 0x00 {
+        return Err(proxy_error(format!(
+            "SOCKS5 proxy refused connection (code {})",
+            reply[1]
+        )));
+    }
+
+    // Skip bound address
+    let addr_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        _ => return Err(proxy_error("Invalid SOCKS5 address type")),
+    };
+    let mut bound_addr = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound_addr).await?;
+
+    Ok(())
+}
+
+// HTTP CONNECT tunnel (RFC 9110, Section 9.3.6)
+pub async fn http_connect<T: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut T,
+    auth: Option<&Credentials<String>>,
+    target: &ProxyTarget<'_>,
+) -> mail_send::Result<()> {
+    let authority = match target {
+        ProxyTarget::Host(host, port) => {
+            format!("{}:{port}", host.strip_suffix('.').unwrap_or(host))
+        }
+        ProxyTarget::Addr(addr) => addr.to_string(),
+    };
+    let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+    if let Some(Credentials::Plain { username, secret }) = auth {
+        let credentials = base64_encode(format!("{username}:{secret}").as_bytes())
+            .map_err(mail_send::Error::from)?;
+        request.push_str("Proxy-Authorization: Basic ");
+        request.push_str(&String::from_utf8_lossy(&credentials));
+        request.push_str("\r\n");
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    // Read the response headers one byte at a time to avoid consuming the SMTP greeting
+    let mut response = Vec::with_capacity(128);
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= 8192 {
+            return Err(proxy_error("HTTP proxy response is too large"));
+        }
+        response.push(stream.read_u8().await?);
+    }
+
+    let status = std::str::from_utf8(&response)
+        .ok()
+        .and_then(|response| response.split(' ').nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .unwrap_or(0);
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(proxy_error(format!(
+            "HTTP proxy refused connection (status {status})"
+        )))
+    }
+}
+
+fn proxy_error(message: impl Into<String>) -> mail_send::Error {
+    mail_send::Error::Io(std::io::Error::new(
+        std::io::ErrorKind::ConnectionRefused,
+        message.into(),
+    ))
+}
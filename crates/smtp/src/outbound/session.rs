@@ -7,19 +7,22 @@
 use super::client::SmtpClient;
 use crate::outbound::DeliveryResult;
 use crate::outbound::client::{from_error_status, from_mail_send_error};
-use crate::queue::{Error, MessageWrapper, Recipient, Status};
+use crate::queue::{DomainPart, Error, MessageWrapper, Recipient, Status};
 use crate::queue::{ErrorDetails, HostResponse, UnexpectedResponse};
 use common::Server;
 use common::config::smtp::queue::ConnectionStrategy;
 use mail_send::Credentials;
 use smtp_proto::{
-    EXT_CHUNKING, EXT_DSN, EXT_REQUIRE_TLS, EXT_SIZE, EXT_SMTP_UTF8, EhloResponse, MAIL_REQUIRETLS,
-    MAIL_RET_FULL, MAIL_RET_HDRS, MAIL_SMTPUTF8, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE,
-    RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS, Severity,
+    EXT_8BIT_MIME, EXT_BINARY_MIME, EXT_CHUNKING, EXT_DSN, EXT_MT_PRIORITY, EXT_REQUIRE_TLS,
+    EXT_SIZE, EXT_SMTP_UTF8, EhloResponse, MAIL_BODY_8BITMIME, MAIL_BODY_BINARYMIME,
+    MAIL_REQUIRETLS, MAIL_RET_FULL, MAIL_RET_HDRS, MAIL_SMTPUTF8, RCPT_NOTIFY_DELAY,
+    RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS, Severity,
 };
-use std::{fmt::Write, time::Instant};
+use std::{borrow::Cow, fmt::Write, time::Instant};
+use store::write::now;
 use tokio::io::{AsyncRead, AsyncWrite};
 use trc::DeliveryEvent;
+use utils::address_to_ascii;
 
 pub struct SessionParams<'x> {
     pub server: &'x Server,
@@ -117,222 +120,277 @@ impl MessageWrapper {
             };*/
         }
 
-        // MAIL FROM
-        let time = Instant::now();
-        smtp_client.timeout = params.conn_strategy.timeout_mail;
-        let cmd = self.build_mail_from(&capabilities);
-        match smtp_client.cmd(cmd.as_bytes()).await.and_then(|r| {
-            if r.is_positive_completion() {
-                Ok(r)
-            } else {
-                Err(mail_send::Error::UnexpectedReply(r))
-            }
-        }) {
-            Ok(response) => {
-                trc::event!(
-                    Delivery(DeliveryEvent::MailFrom),
-                    SpanId = params.session_id,
-                    Hostname = params.hostname.to_string(),
-                    From = self.message.return_path.to_string(),
-                    Code = response.code,
-                    Details = response.message.to_string(),
-                    Elapsed = time.elapsed(),
-                );
-            }
-            Err(err) => {
-                trc::event!(
-                    Delivery(DeliveryEvent::MailFromRejected),
-                    SpanId = params.session_id,
-                    Hostname = params.hostname.to_string(),
-                    CausedBy = from_mail_send_error(&err),
-                    Elapsed = time.elapsed(),
-                );
+        // Hosts without SMTPUTF8 receive the envelope with ASCII domains (RFC 6531)
+        let downgrade = !capabilities.has_capability(EXT_SMTP_UTF8);
+        let Some(return_path) = envelope_address(&self.message.return_path, downgrade) else {
+            trc::event!(
+                Delivery(DeliveryEvent::SmtpUtf8Unsupported),
+                SpanId = params.session_id,
+                Hostname = params.hostname.to_string(),
+                From = self.message.return_path.to_string(),
+            );
 
-                smtp_client.quit().await;
-                statuses.push(DeliveryResult::domain(
-                    Status::from_smtp_error(params.hostname, &cmd, err),
-                    rcpt_idxs,
-                ));
-                return;
-            }
+            smtp_client.quit().await;
+            statuses.push(DeliveryResult::domain(
+                Status::PermanentFailure(smtputf8_unsupported(params.hostname)),
+                rcpt_idxs,
+            ));
+            return;
+        };
+        // Messages sent with REQUIRETLS are only relayed to hosts supporting it (RFC 8689)
+        if self.has_flag(MAIL_REQUIRETLS)
+            && params.is_smtp
+            && !capabilities.has_capability(EXT_REQUIRE_TLS)
+        {
+            trc::event!(
+                Delivery(DeliveryEvent::RequireTlsUnsupported),
+                SpanId = params.session_id,
+                Hostname = params.hostname.to_string(),
+            );
+
+            smtp_client.quit().await;
+            statuses.push(DeliveryResult::domain(
+                Status::PermanentFailure(requiretls_unsupported(params.hostname)),
+                rcpt_idxs,
+            ));
+            return;
+        }
+        if downgrade && self.has_flag(MAIL_SMTPUTF8) {
+            trc::event!(
+                Delivery(DeliveryEvent::SmtpUtf8Downgrade),
+                SpanId = params.session_id,
+                Hostname = params.hostname.to_string(),
+            );
         }
 
-        // RCPT TO
-        let mut accepted_rcpts = Vec::new();
-        smtp_client.timeout = params.conn_strategy.timeout_rcpt;
-        for rcpt_idx in &rcpt_idxs {
-            let time = Instant::now();
-            let rcpt = &self.message.recipients[*rcpt_idx];
-            if matches!(
-                &rcpt.status,
-                Status::Completed(_) | Status::PermanentFailure(_)
-            ) {
-                continue;
+        // Sign the return path of local senders so that forged bounces can be detected
+        let return_path = match &params.server.core.smtp.session.null_sender.batv {
+            Some(batv)
+                if params.is_smtp
+                    && !return_path.is_empty()
+                    && params
+                        .server
+                        .core
+                        .storage
+                        .directory
+                        .is_local_domain(&self.message.return_path.domain_part().to_lowercase())
+                        .await
+                        .unwrap_or(false) =>
+            {
+                Cow::Owned(batv.sign(&return_path, now()))
             }
+            _ => return_path,
+        };
 
-            let cmd = self.build_rcpt_to(rcpt, &capabilities);
-            match smtp_client.cmd(cmd.as_bytes()).await {
-                Ok(response) => match response.severity() {
-                    Severity::PositiveCompletion => {
-                        trc::event!(
-                            Delivery(DeliveryEvent::RcptTo),
-                            SpanId = params.session_id,
-                            Hostname = params.hostname.to_string(),
-                            To = rcpt.address().to_string(),
-                            Code = response.code,
-                            Details = response.message.to_string(),
-                            Elapsed = time.elapsed(),
-                        );
+        // Split recipients into transactions that fit the server limits (RFC 9422)
+        let batches = smtp_client.limits.batches(&rcpt_idxs, |rcpt_idx| {
+            self.message.recipients[rcpt_idx].domain_part()
+        });
+        for (batch_num, batch) in batches.iter().enumerate() {
+            let pending = batches[batch_num..].concat();
+            if batch_num > 0 {
+                // Recipients exceeding the transaction limit are retried over a new connection
+                if smtp_client
+                    .limits
+                    .mail_max
+                    .is_some_and(|mail_max| batch_num >= mail_max)
+                {
+                    statuses.push(DeliveryResult::rate_limited(pending, now()));
+                    break;
+                }
 
-                        accepted_rcpts.push((
-                            rcpt,
-                            rcpt_idx,
-                            Status::Completed(HostResponse {
-                                hostname: params.hostname.into(),
-                                response,
-                            }),
-                        ));
+                if let Err(err) = smtp_client.cmd(b"RSET\r\n").await.and_then(|r| {
+                    if r.is_positive_completion() {
+                        Ok(r)
+                    } else {
+                        Err(mail_send::Error::UnexpectedReply(r))
                     }
-                    severity => {
-                        trc::event!(
-                            Delivery(DeliveryEvent::RcptToRejected),
-                            SpanId = params.session_id,
-                            Hostname = params.hostname.to_string(),
-                            To = rcpt.address().to_string(),
-                            Code = response.code,
-                            Details = response.message.to_string(),
-                            Elapsed = time.elapsed(),
-                        );
+                }) {
+                    smtp_client.quit().await;
+                    statuses.push(DeliveryResult::domain(
+                        Status::from_smtp_error(params.hostname, "RSET", err),
+                        pending,
+                    ));
+                    return;
+                }
+            }
 
-                        let response = ErrorDetails {
-                            entity: params.hostname.into(),
-                            details: Error::UnexpectedResponse(UnexpectedResponse {
-                                command: cmd.trim().into(),
-                                response,
-                            }),
-                        };
-                        statuses.push(DeliveryResult::account(
-                            if severity == Severity::PermanentNegativeCompletion {
-                                Status::PermanentFailure(response)
-                            } else {
-                                Status::TemporaryFailure(response)
-                            },
-                            *rcpt_idx,
-                        ));
-                    }
-                },
+            // MAIL FROM
+            let time = Instant::now();
+            smtp_client.timeout = params.conn_strategy.timeout_mail;
+            let cmd = self.build_mail_from(&return_path, &capabilities);
+            match smtp_client.cmd(cmd.as_bytes()).await.and_then(|r| {
+                if r.is_positive_completion() {
+                    Ok(r)
+                } else {
+                    Err(mail_send::Error::UnexpectedReply(r))
+                }
+            }) {
+                Ok(response) => {
+                    trc::event!(
+                        Delivery(DeliveryEvent::MailFrom),
+                        SpanId = params.session_id,
+                        Hostname = params.hostname.to_string(),
+                        From = self.message.return_path.to_string(),
+                        Code = response.code,
+                        Details = response.message.to_string(),
+                        Elapsed = time.elapsed(),
+                    );
+                }
                 Err(err) => {
                     trc::event!(
-                        Delivery(DeliveryEvent::RcptToFailed),
+                        Delivery(DeliveryEvent::MailFromRejected),
                         SpanId = params.session_id,
                         Hostname = params.hostname.to_string(),
-                        To = rcpt.address().to_string(),
                         CausedBy = from_mail_send_error(&err),
                         Elapsed = time.elapsed(),
                     );
 
-                    // Something went wrong, abort.
                     smtp_client.quit().await;
                     statuses.push(DeliveryResult::domain(
-                        Status::from_smtp_error(params.hostname, "", err),
-                        rcpt_idxs,
+                        Status::from_smtp_error(params.hostname, &cmd, err),
+                        pending,
                     ));
                     return;
                 }
             }
-        }
 
-        // Send message
-        if !accepted_rcpts.is_empty() {
-            let time = Instant::now();
-            let bdat_cmd = capabilities
-                .has_capability(EXT_CHUNKING)
-                .then(|| format!("BDAT {} LAST\r\n", self.message.size));
-
-            if let Err(status) = smtp_client.send_message(self, &bdat_cmd, &params).await {
-                trc::event!(
-                    Delivery(DeliveryEvent::MessageRejected),
-                    SpanId = params.session_id,
-                    Hostname = params.hostname.to_string(),
-                    CausedBy = from_error_status(&status),
-                    Elapsed = time.elapsed(),
-                );
+            // RCPT TO
+            let mut accepted_rcpts = Vec::new();
+            smtp_client.timeout = params.conn_strategy.timeout_rcpt;
+            for rcpt_idx in batch {
+                let time = Instant::now();
+                let rcpt = &self.message.recipients[*rcpt_idx];
+                if matches!(
+                    &rcpt.status,
+                    Status::Completed(_) | Status::PermanentFailure(_)
+                ) {
+                    continue;
+                }
 
-                smtp_client.quit().await;
-                statuses.push(DeliveryResult::domain(status, rcpt_idxs));
-                return;
-            }
+                let Some(address) = envelope_address(rcpt.address(), downgrade) else {
+                    trc::event!(
+                        Delivery(DeliveryEvent::SmtpUtf8Unsupported),
+                        SpanId = params.session_id,
+                        Hostname = params.hostname.to_string(),
+                        To = rcpt.address().to_string(),
+                    );
 
-            if params.is_smtp {
-                // Handle SMTP response
-                match smtp_client
-                    .read_smtp_data_response(params.hostname, &bdat_cmd)
-                    .await
-                {
-                    Ok(response) => {
-                        // Mark recipients as delivered
-                        if response.code() == 250 {
-                            for (rcpt, rcpt_idx, status) in accepted_rcpts {
-                                trc::event!(
-                                    Delivery(DeliveryEvent::Delivered),
-                                    SpanId = params.session_id,
-                                    Hostname = params.hostname.to_string(),
-                                    To = rcpt.address().to_string(),
-                                    Code = response.code,
-                                    Details = response.message.to_string(),
-                                    Elapsed = time.elapsed(),
-                                );
+                    statuses.push(DeliveryResult::account(
+                        Status::PermanentFailure(smtputf8_unsupported(params.hostname)),
+                        *rcpt_idx,
+                    ));
+                    continue;
+                };
+                let cmd = self.build_rcpt_to(rcpt, &address, &capabilities);
+                match smtp_client.cmd(cmd.as_bytes()).await {
+                    Ok(response) => match response.severity() {
+                        Severity::PositiveCompletion => {
+                            trc::event!(
+                                Delivery(DeliveryEvent::RcptTo),
+                                SpanId = params.session_id,
+                                Hostname = params.hostname.to_string(),
+                                To = rcpt.address().to_string(),
+                                Code = response.code,
+                                Details = response.message.to_string(),
+                                Elapsed = time.elapsed(),
+                            );
 
-                                statuses.push(DeliveryResult::account(status, *rcpt_idx));
-                            }
-                        } else {
+                            accepted_rcpts.push((
+                                rcpt,
+                                rcpt_idx,
+                                Status::Completed(HostResponse {
+                                    hostname: params.hostname.into(),
+                                    response,
+                                }),
+                            ));
+                        }
+                        severity => {
                             trc::event!(
-                                Delivery(DeliveryEvent::MessageRejected),
+                                Delivery(DeliveryEvent::RcptToRejected),
                                 SpanId = params.session_id,
                                 Hostname = params.hostname.to_string(),
+                                To = rcpt.address().to_string(),
                                 Code = response.code,
                                 Details = response.message.to_string(),
                                 Elapsed = time.elapsed(),
                             );
 
-                            smtp_client.quit().await;
-                            statuses.push(DeliveryResult::domain(
-                                Status::from_smtp_error(
-                                    params.hostname,
-                                    bdat_cmd.as_deref().unwrap_or("DATA"),
-                                    mail_send::Error::UnexpectedReply(response),
-                                ),
-                                rcpt_idxs,
+                            let response = ErrorDetails {
+                                entity: params.hostname.into(),
+                                details: Error::UnexpectedResponse(UnexpectedResponse {
+                                    command: cmd.trim().into(),
+                                    response,
+                                }),
+                            };
+                            statuses.push(DeliveryResult::account(
+                                if severity == Severity::PermanentNegativeCompletion {
+                                    Status::PermanentFailure(response)
+                                } else {
+                                    Status::TemporaryFailure(response)
+                                },
+                                *rcpt_idx,
                             ));
-                            return;
                         }
-                    }
-                    Err(status) => {
+                    },
+                    Err(err) => {
                         trc::event!(
-                            Delivery(DeliveryEvent::MessageRejected),
+                            Delivery(DeliveryEvent::RcptToFailed),
                             SpanId = params.session_id,
                             Hostname = params.hostname.to_string(),
-                            CausedBy = from_error_status(&status),
+                            To = rcpt.address().to_string(),
+                            CausedBy = from_mail_send_error(&err),
                             Elapsed = time.elapsed(),
                         );
 
+                        // Something went wrong, abort.
                         smtp_client.quit().await;
-                        statuses.push(DeliveryResult::domain(status, rcpt_idxs));
+                        statuses.push(DeliveryResult::domain(
+                            Status::from_smtp_error(params.hostname, "", err),
+                            pending,
+                        ));
                         return;
                     }
                 }
-            } else {
-                // Handle LMTP responses
-                match smtp_client
-                    .read_lmtp_data_response(params.hostname, accepted_rcpts.len())
+            }
+
+            // Send message
+            if !accepted_rcpts.is_empty() {
+                let time = Instant::now();
+                let bdat_cmd = capabilities
+                    .has_capability(EXT_CHUNKING)
+                    .then(|| format!("BDAT {} LAST\r\n", self.message.size));
+                let encode_binary = self.has_flag(MAIL_BODY_BINARYMIME)
+                    && !(capabilities.has_capability(EXT_BINARY_MIME)
+                        && capabilities.has_capability(EXT_CHUNKING));
+
+                if let Err(status) = smtp_client
+                    .send_message(self, &bdat_cmd, encode_binary, &params)
                     .await
                 {
-                    Ok(responses) => {
-                        for ((rcpt, rcpt_idx, _), response) in
-                            accepted_rcpts.into_iter().zip(responses)
-                        {
-                            let status = match response.severity() {
-                                Severity::PositiveCompletion => {
+                    trc::event!(
+                        Delivery(DeliveryEvent::MessageRejected),
+                        SpanId = params.session_id,
+                        Hostname = params.hostname.to_string(),
+                        CausedBy = from_error_status(&status),
+                        Elapsed = time.elapsed(),
+                    );
+
+                    smtp_client.quit().await;
+                    statuses.push(DeliveryResult::domain(status, pending));
+                    return;
+                }
+
+                if params.is_smtp {
+                    // Handle SMTP response
+                    match smtp_client
+                        .read_smtp_data_response(params.hostname, &bdat_cmd)
+                        .await
+                    {
+                        Ok(response) => {
+                            // Mark recipients as delivered
+                            if response.code() == 250 {
+                                for (rcpt, rcpt_idx, status) in accepted_rcpts {
                                     trc::event!(
                                         Delivery(DeliveryEvent::Delivered),
                                         SpanId = params.session_id,
@@ -343,52 +401,118 @@ impl MessageWrapper {
                                         Elapsed = time.elapsed(),
                                     );
 
-                                    Status::Completed(HostResponse {
-                                        hostname: params.hostname.to_string(),
-                                        response,
-                                    })
+                                    statuses.push(DeliveryResult::account(status, *rcpt_idx));
                                 }
-                                severity => {
-                                    trc::event!(
-                                        Delivery(DeliveryEvent::RcptToRejected),
-                                        SpanId = params.session_id,
-                                        Hostname = params.hostname.to_string(),
-                                        To = rcpt.address().to_string(),
-                                        Code = response.code,
-                                        Details = response.message.to_string(),
-                                        Elapsed = time.elapsed(),
-                                    );
+                            } else {
+                                trc::event!(
+                                    Delivery(DeliveryEvent::MessageRejected),
+                                    SpanId = params.session_id,
+                                    Hostname = params.hostname.to_string(),
+                                    Code = response.code,
+                                    Details = response.message.to_string(),
+                                    Elapsed = time.elapsed(),
+                                );
 
-                                    let response = ErrorDetails {
-                                        entity: params.hostname.into(),
-                                        details: Error::UnexpectedResponse(UnexpectedResponse {
-                                            command: bdat_cmd.as_deref().unwrap_or("DATA").into(),
+                                smtp_client.quit().await;
+                                statuses.push(DeliveryResult::domain(
+                                    Status::from_smtp_error(
+                                        params.hostname,
+                                        bdat_cmd.as_deref().unwrap_or("DATA"),
+                                        mail_send::Error::UnexpectedReply(response),
+                                    ),
+                                    pending,
+                                ));
+                                return;
+                            }
+                        }
+                        Err(status) => {
+                            trc::event!(
+                                Delivery(DeliveryEvent::MessageRejected),
+                                SpanId = params.session_id,
+                                Hostname = params.hostname.to_string(),
+                                CausedBy = from_error_status(&status),
+                                Elapsed = time.elapsed(),
+                            );
+
+                            smtp_client.quit().await;
+                            statuses.push(DeliveryResult::domain(status, pending));
+                            return;
+                        }
+                    }
+                } else {
+                    // Handle LMTP responses
+                    match smtp_client
+                        .read_lmtp_data_response(params.hostname, accepted_rcpts.len())
+                        .await
+                    {
+                        Ok(responses) => {
+                            for ((rcpt, rcpt_idx, _), response) in
+                                accepted_rcpts.into_iter().zip(responses)
+                            {
+                                let status = match response.severity() {
+                                    Severity::PositiveCompletion => {
+                                        trc::event!(
+                                            Delivery(DeliveryEvent::Delivered),
+                                            SpanId = params.session_id,
+                                            Hostname = params.hostname.to_string(),
+                                            To = rcpt.address().to_string(),
+                                            Code = response.code,
+                                            Details = response.message.to_string(),
+                                            Elapsed = time.elapsed(),
+                                        );
+
+                                        Status::Completed(HostResponse {
+                                            hostname: params.hostname.to_string(),
                                             response,
-                                        }),
-                                    };
-                                    if severity == Severity::PermanentNegativeCompletion {
-                                        Status::PermanentFailure(response)
-                                    } else {
-                                        Status::TemporaryFailure(response)
+                                        })
                                     }
-                                }
-                            };
+                                    severity => {
+                                        trc::event!(
+                                            Delivery(DeliveryEvent::RcptToRejected),
+                                            SpanId = params.session_id,
+                                            Hostname = params.hostname.to_string(),
+                                            To = rcpt.address().to_string(),
+                                            Code = response.code,
+                                            Details = response.message.to_string(),
+                                            Elapsed = time.elapsed(),
+                                        );
+
+                                        let response = ErrorDetails {
+                                            entity: params.hostname.into(),
+                                            details: Error::UnexpectedResponse(
+                                                UnexpectedResponse {
+                                                    command: bdat_cmd
+                                                        .as_deref()
+                                                        .unwrap_or("DATA")
+                                                        .into(),
+                                                    response,
+                                                },
+                                            ),
+                                        };
+                                        if severity == Severity::PermanentNegativeCompletion {
+                                            Status::PermanentFailure(response)
+                                        } else {
+                                            Status::TemporaryFailure(response)
+                                        }
+                                    }
+                                };
 
-                            statuses.push(DeliveryResult::account(status, *rcpt_idx));
+                                statuses.push(DeliveryResult::account(status, *rcpt_idx));
+                            }
                         }
-                    }
-                    Err(status) => {
-                        trc::event!(
-                            Delivery(DeliveryEvent::MessageRejected),
-                            SpanId = params.session_id,
-                            Hostname = params.hostname.to_string(),
-                            CausedBy = from_error_status(&status),
-                            Elapsed = time.elapsed(),
-                        );
+                        Err(status) => {
+                            trc::event!(
+                                Delivery(DeliveryEvent::MessageRejected),
+                                SpanId = params.session_id,
+                                Hostname = params.hostname.to_string(),
+                                CausedBy = from_error_status(&status),
+                                Elapsed = time.elapsed(),
+                            );
 
-                        smtp_client.quit().await;
-                        statuses.push(DeliveryResult::domain(status, rcpt_idxs));
-                        return;
+                            smtp_client.quit().await;
+                            statuses.push(DeliveryResult::domain(status, pending));
+                            return;
+                        }
                     }
                 }
             }
@@ -397,18 +521,33 @@ impl MessageWrapper {
         smtp_client.quit().await;
     }
 
-    fn build_mail_from(&self, capabilities: &EhloResponse<String>) -> String {
-        let mut mail_from = String::with_capacity(self.message.return_path.len() + 60);
-        let _ = write!(mail_from, "MAIL FROM:<{}>", self.message.return_path);
+    fn build_mail_from(&self, return_path: &str, capabilities: &EhloResponse<String>) -> String {
+        let mut mail_from = String::with_capacity(return_path.len() + 60);
+        let _ = write!(mail_from, "MAIL FROM:<{return_path}>");
         if capabilities.has_capability(EXT_SIZE) {
             let _ = write!(mail_from, " SIZE={}", self.message.size);
         }
-        if self.has_flag(MAIL_REQUIRETLS) & capabilities.has_capability(EXT_REQUIRE_TLS) {
+        // Binary content can only be transferred using BDAT (RFC 3030), otherwise
+        // binary parts are re-encoded as base64 before sending
+        if self.has_flag(MAIL_BODY_BINARYMIME)
+            && capabilities.has_capability(EXT_BINARY_MIME)
+            && capabilities.has_capability(EXT_CHUNKING)
+        {
+            mail_from.push_str(" BODY=BINARYMIME");
+        } else if self.has_flag(MAIL_BODY_8BITMIME | MAIL_BODY_BINARYMIME)
+            && capabilities.has_capability(EXT_8BIT_MIME)
+        {
+            mail_from.push_str(" BODY=8BITMIME");
+        }
+        if self.has_flag(MAIL_REQUIRETLS) && capabilities.has_capability(EXT_REQUIRE_TLS) {
             mail_from.push_str(" REQUIRETLS");
         }
         if self.has_flag(MAIL_SMTPUTF8) & capabilities.has_capability(EXT_SMTP_UTF8) {
             mail_from.push_str(" SMTPUTF8");
         }
+        if self.message.priority != 0 && capabilities.has_capability(EXT_MT_PRIORITY) {
+            let _ = write!(mail_from, " MT-PRIORITY={}", self.message.priority);
+        }
         if capabilities.has_capability(EXT_DSN) {
             if self.has_flag(MAIL_RET_FULL) {
                 mail_from.push_str(" RET=FULL");
@@ -424,9 +563,14 @@ impl MessageWrapper {
         mail_from
     }
 
-    fn build_rcpt_to(&self, rcpt: &Recipient, capabilities: &EhloResponse<String>) -> String {
-        let mut rcpt_to = String::with_capacity(rcpt.address().len() + 60);
-        let _ = write!(rcpt_to, "RCPT TO:<{}>", rcpt.address());
+    fn build_rcpt_to(
+        &self,
+        rcpt: &Recipient,
+        address: &str,
+        capabilities: &EhloResponse<String>,
+    ) -> String {
+        let mut rcpt_to = String::with_capacity(address.len() + 60);
+        let _ = write!(rcpt_to, "RCPT TO:<{address}>");
         if capabilities.has_capability(EXT_DSN) {
             if rcpt.has_flag(RCPT_NOTIFY_SUCCESS | RCPT_NOTIFY_FAILURE | RCPT_NOTIFY_DELAY) {
                 rcpt_to.push_str(" NOTIFY=");
@@ -470,3 +614,27 @@ impl Recipient {
         (self.flags & flag) != 0
     }
 }
+
+fn envelope_address(address: &str, downgrade: bool) -> Option<Cow<'_, str>> {
+    if downgrade {
+        address_to_ascii(address)
+    } else {
+        Some(Cow::Borrowed(address))
+    }
+}
+
+fn requiretls_unsupported(hostname: &str) -> ErrorDetails {
+    ErrorDetails {
+        entity: hostname.into(),
+        details: Error::TlsError("Host does not support REQUIRETLS required by the message".into()),
+    }
+}
+
+fn smtputf8_unsupported(hostname: &str) -> ErrorDetails {
+    ErrorDetails {
+        entity: hostname.into(),
+        details: Error::ConnectionError(
+            "Host does not support SMTPUTF8 required by a non-ASCII address".into(),
+        ),
+    }
+}
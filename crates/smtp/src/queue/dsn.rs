@@ -11,7 +11,7 @@ use super::{
 };
 use crate::queue::{MessageWrapper, UnexpectedResponse};
 use crate::reporting::SmtpReporting;
-use common::Server;
+use common::{Server, i18n};
 use mail_builder::MessageBuilder;
 use mail_builder::headers::HeaderType;
 use mail_builder::headers::content_type::ContentType;
@@ -194,58 +194,55 @@ impl MessageWrapper {
         let has_delay = !txt_delay.is_empty();
         let has_failure = !txt_failed.is_empty();
 
+        // System-generated messages follow the branding of the sender's domain
+        let branding = server.core.network.branding(
+            self.message
+                .return_path
+                .rsplit_once('@')
+                .map(|(_, domain)| domain)
+                .unwrap_or_default(),
+        );
+        let locale = i18n::locale_or_default(
+            branding
+                .and_then(|branding| branding.locale.as_deref())
+                .unwrap_or("en"),
+        );
+
         let mut txt = String::with_capacity(txt_len + 128);
-        let (subject, is_mixed) = if has_success && !has_delay && !has_failure {
-            txt.push_str(
-                "Your message has been successfully delivered to the following recipients:\r\n\r\n",
-            );
-            ("Successfully delivered message", false)
+        let (subject, text, is_mixed) = if has_success && !has_delay && !has_failure {
+            (
+                locale.dsn_subject_delivered,
+                locale.dsn_text_delivered,
+                false,
+            )
         } else if has_delay && !has_success && !has_failure {
-            txt.push_str("There was a temporary problem delivering your message to the following recipients:\r\n\r\n");
-            ("Warning: Delay in message delivery", false)
+            (locale.dsn_subject_delayed, locale.dsn_text_delayed, false)
         } else if has_failure && !has_success && !has_delay {
-            txt.push_str(
-                "Your message could not be delivered to the following recipients:\r\n\r\n",
-            );
-            ("Failed to deliver message", false)
+            (locale.dsn_subject_failed, locale.dsn_text_failed, false)
         } else if has_success {
-            txt.push_str("Your message has been partially delivered:\r\n\r\n");
-            ("Partially delivered message", true)
+            (locale.dsn_subject_partial, locale.dsn_text_partial, true)
         } else {
-            txt.push_str("Your message could not be delivered to some recipients:\r\n\r\n");
-            (
-                "Warning: Temporary and permanent failures during message delivery",
-                true,
-            )
+            (locale.dsn_subject_mixed, locale.dsn_text_mixed, true)
         };
+        txt.push_str(text);
+        txt.push_str("\r\n\r\n");
+
+        for (is_section, section, section_txt) in [
+            (has_success, locale.dsn_section_delivered, &txt_success),
+            (has_delay, locale.dsn_section_delayed, &txt_delay),
+            (has_failure, locale.dsn_section_failed, &txt_failed),
+        ] {
+            if is_section {
+                if is_mixed {
+                    let _ = write!(txt, "    ----- {section} -----\r\n");
+                }
 
-        if has_success {
-            if is_mixed {
-                txt.push_str(
-                    "    ----- Delivery to the following addresses was successful -----\r\n",
-                );
-            }
-
-            txt.push_str(&txt_success);
-            txt.push_str("\r\n");
-        }
-
-        if has_delay {
-            if is_mixed {
-                txt.push_str(
-                    "    ----- There was a temporary problem delivering to these addresses -----\r\n",
-                );
+                txt.push_str(section_txt);
+                txt.push_str("\r\n");
             }
-            txt.push_str(&txt_delay);
-            txt.push_str("\r\n");
         }
-
-        if has_failure {
-            if is_mixed {
-                txt.push_str("    ----- Delivery to the following addresses failed -----\r\n");
-            }
-            txt.push_str(&txt_failed);
-            txt.push_str("\r\n");
+        if let Some(footer) = branding.and_then(|branding| branding.footer.as_deref()) {
+            let _ = write!(txt, "--\r\n{footer}\r\n");
         }
 
         // Update next delay notification time
@@ -287,10 +284,14 @@ impl MessageWrapper {
         }
 
         // Obtain hostname and sender addresses
-        let from_name = server
-            .eval_if(&config.dsn.name, &self.message, self.span_id)
-            .await
-            .unwrap_or_else(|| String::from("Mail Delivery Subsystem"));
+        let from_name = if let Some(name) = branding.and_then(|branding| branding.name.as_ref()) {
+            name.clone()
+        } else {
+            server
+                .eval_if(&config.dsn.name, &self.message, self.span_id)
+                .await
+                .unwrap_or_else(|| String::from("Mail Delivery Subsystem"))
+        };
         let from_addr = server
             .eval_if(&config.dsn.address, &self.message, self.span_id)
             .await
@@ -312,8 +313,7 @@ impl MessageWrapper {
 
         // Fetch up to 1024 bytes of message headers
         let headers = match server
-            .blob_store()
-            .get_blob(self.message.blob_hash.as_slice(), 0..1024)
+            .get_queued_blob(self.message.blob_hash.as_slice(), 0..1024)
             .await
         {
             Ok(Some(mut buf)) => {
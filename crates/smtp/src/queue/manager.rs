@@ -4,7 +4,10 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use super::{Message, QueueId, Status, spool::SmtpSpool};
+use super::{
+    Message, QueueId, Status,
+    spool::{QueuedMessages, SmtpSpool},
+};
 use crate::queue::{Recipient, spool::LOCK_EXPIRY};
 use ahash::AHashMap;
 use common::{
@@ -99,10 +102,7 @@ impl Queue {
                     // Process queue events
                     let server = self.core.build_server();
                     let mut queue_events = server.next_event(self).await;
-
-                    if queue_events.messages.len() > 3 {
-                        queue_events.messages.shuffle(&mut rand::rng());
-                    }
+                    queue_events.prioritize();
 
                     for queue_event in &queue_events.messages {
                         // Fetch queue stats
@@ -222,6 +222,18 @@ impl Queue {
     }
 }
 
+impl QueuedMessages {
+    // Higher priority messages are delivered first when capacity is limited,
+    // messages sharing a priority are delivered in random order
+    pub fn prioritize(&mut self) {
+        if self.messages.len() > 3 {
+            self.messages.shuffle(&mut rand::rng());
+        }
+        self.messages
+            .sort_by_key(|event| std::cmp::Reverse(event.priority));
+    }
+}
+
 impl Message {
     pub fn next_event(&self, queue: Option<QueueName>) -> Option<u64> {
         let mut next_event = None;
@@ -335,6 +347,16 @@ impl Message {
 
         next_events
     }
+
+    // The MT-PRIORITY of a message is stored alongside its queue events so the
+    // scheduler can order due deliveries without fetching every message
+    pub fn event_value(&self) -> Vec<u8> {
+        if self.priority != 0 {
+            self.priority.to_be_bytes().to_vec()
+        } else {
+            Vec::new()
+        }
+    }
 }
 
 impl Recipient {
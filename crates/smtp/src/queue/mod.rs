@@ -20,6 +20,7 @@ use utils::BlobHash;
 
 pub mod dsn;
 pub mod manager;
+pub mod moderation;
 pub mod quota;
 pub mod spool;
 pub mod throttle;
@@ -37,6 +38,7 @@ pub struct QueuedMessage {
     pub due: u64,
     pub queue_id: QueueId,
     pub queue_name: QueueName,
+    pub priority: i16,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -120,6 +122,8 @@ pub const FROM_UNAUTHENTICATED_DMARC: u64 = 1 << 34;
 pub const FROM_DSN: u64 = 1 << 35;
 pub const FROM_REPORT: u64 = 1 << 36;
 pub const FROM_AUTOGENERATED: u64 = 1 << 37;
+pub const HELD_FOR_APPROVAL: u64 = 1 << 38;
+pub const TLS_OPTIONAL: u64 = 1 << 39;
 
 pub const RCPT_DSN_SENT: u64 = 1 << 32;
 pub const RCPT_STATUS_CHANGED: u64 = 1 << 33;
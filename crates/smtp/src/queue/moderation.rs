@@ -0,0 +1,277 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{Server, auth::oauth::GrantType, config::smtp::queue::QueueName, ipc::QueueEvent};
+use mail_builder::{
+    MessageBuilder,
+    headers::{HeaderType, content_type::ContentType},
+    mime::{BodyPart, MimePart, make_boundary},
+};
+use store::write::now;
+
+use super::{
+    Error, ErrorDetails, HELD_FOR_APPROVAL, MessageWrapper, QueueExpiry, QueueId, Status,
+    dsn::SendDsn, spool::SmtpSpool,
+};
+use crate::reporting::SmtpReporting;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModerationAction {
+    Approve,
+    Reject,
+}
+
+pub trait QueueModeration: Sync + Send {
+    fn notify_moderator(
+        &self,
+        message: &MessageWrapper,
+        raw_message: &[u8],
+    ) -> impl Future<Output = ()> + Send;
+
+    fn moderate_message(
+        &self,
+        token: &str,
+        action: ModerationAction,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn expire_held_message(
+        &self,
+        message: MessageWrapper,
+        due: u64,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl QueueModeration for Server {
+    async fn notify_moderator(&self, message: &MessageWrapper, raw_message: &[u8]) {
+        let config = &self.core.smtp.queue;
+        let Some(moderator) = self
+            .eval_if::<String, _>(
+                &config.moderation.address,
+                &message.message,
+                message.span_id,
+            )
+            .await
+        else {
+            return;
+        };
+
+        let token = match self
+            .encode_access_token(
+                GrantType::Moderation,
+                0,
+                &format!("{:x}", message.queue_id),
+                config.moderation.expiry,
+            )
+            .await
+        {
+            Ok(token) => token,
+            Err(err) => {
+                trc::error!(
+                    err.span_id(message.span_id)
+                        .details("Failed to generate moderation token")
+                        .caused_by(trc::location!())
+                );
+                return;
+            }
+        };
+
+        let from_name = self
+            .eval_if(&config.dsn.name, &message.message, message.span_id)
+            .await
+            .unwrap_or_else(|| String::from("Mail Delivery Subsystem"));
+        let from_addr = self
+            .eval_if(&config.dsn.address, &message.message, message.span_id)
+            .await
+            .unwrap_or_else(|| String::from("MAILER-DAEMON@localhost"));
+        let hostname = self
+            .eval_if(
+                &self.core.smtp.report.submitter,
+                &message.message,
+                message.span_id,
+            )
+            .await
+            .unwrap_or_else(|| String::from("localhost"));
+
+        let url = &config.moderation.url;
+        let link = |action: &str| {
+            form_urlencoded::Serializer::new(format!("{url}?"))
+                .append_pair("i", &token)
+                .append_pair("a", action)
+                .finish()
+        };
+        let recipients = message
+            .message
+            .recipients
+            .iter()
+            .map(|rcpt| rcpt.address.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let body = format!(
+            concat!(
+                "A message from <{}> to {} has been held for approval.\r\n\r\n",
+                "Approve delivery:\r\n{}\r\n\r\n",
+                "Reject and bounce to the sender:\r\n{}\r\n"
+            ),
+            message.message.return_path,
+            recipients,
+            link("approve"),
+            link("reject")
+        );
+
+        let notification = MessageBuilder::new()
+            .from((from_name.as_str(), from_addr.as_str()))
+            .header("To", HeaderType::Text(moderator.as_str().into()))
+            .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+            .message_id(format!("<{}@{}>", make_boundary("."), hostname))
+            .subject(format!(
+                "Message from {} held for approval",
+                message.message.return_path
+            ))
+            .body(MimePart::new(
+                ContentType::new("multipart/mixed"),
+                BodyPart::Multipart(vec![
+                    MimePart::new(ContentType::new("text/plain"), BodyPart::Text(body.into())),
+                    MimePart::new(
+                        ContentType::new("message/rfc822"),
+                        BodyPart::Binary(raw_message.into()),
+                    ),
+                ]),
+            ))
+            .write_to_vec()
+            .unwrap_or_default();
+
+        self.send_autogenerated(
+            from_addr,
+            [moderator].into_iter(),
+            notification,
+            Some(&config.dsn.sign),
+            message.span_id,
+        )
+        .await;
+    }
+
+    async fn moderate_message(&self, token: &str, action: ModerationAction) -> trc::Result<bool> {
+        let token = self
+            .validate_access_token(GrantType::Moderation.into(), token)
+            .await?;
+        let queue_id = QueueId::from_str_radix(&token.client_id, 16).map_err(|_| {
+            trc::AuthEvent::Error
+                .into_err()
+                .details("Invalid moderation token")
+        })?;
+
+        // Held messages are only scheduled in the default queue, holding its lock
+        // prevents concurrent moderation or expiry of the same message
+        let queue_name = QueueName::default();
+        if !self.try_lock_event(queue_id, queue_name).await {
+            return Ok(false);
+        }
+        let result = moderate_held(self, queue_id, action).await;
+        self.unlock_event(queue_id, queue_name).await;
+
+        result
+    }
+
+    async fn expire_held_message(&self, mut message: MessageWrapper, due: u64) {
+        trc::event!(
+            Queue(trc::QueueEvent::MessageExpired),
+            SpanId = message.span_id,
+            QueueId = message.queue_id,
+            From = message.message.return_path.clone(),
+        );
+
+        message.message.flags &= !HELD_FOR_APPROVAL;
+        message.is_multi_queue = false;
+        bounce_held(
+            self,
+            message,
+            "Message not approved by moderator in time.",
+            due,
+        )
+        .await;
+    }
+}
+
+async fn moderate_held(
+    server: &Server,
+    queue_id: QueueId,
+    action: ModerationAction,
+) -> trc::Result<bool> {
+    let Some(mut message) = server
+        .read_message(queue_id, QueueName::default())
+        .await
+        .filter(|message| message.message.flags & HELD_FOR_APPROVAL != 0)
+    else {
+        return Ok(false);
+    };
+    message.message.flags &= !HELD_FOR_APPROVAL;
+    message.is_multi_queue = false;
+    let held_until = message.message.created + server.core.smtp.queue.moderation.expiry;
+
+    match action {
+        ModerationAction::Approve => {
+            trc::event!(
+                Queue(trc::QueueEvent::MessageApproved),
+                SpanId = message.span_id,
+                QueueId = queue_id,
+                From = message.message.return_path.clone(),
+            );
+
+            // Recipients whose expiry elapsed while the message was held are
+            // granted a fixed number of delivery attempts
+            let now = now();
+            let release_attempts = server.core.smtp.queue.moderation.release_attempts;
+            for rcpt in &mut message.message.recipients {
+                rcpt.retry.due = now;
+                if rcpt
+                    .expiration_time(message.message.created)
+                    .is_some_and(|expires| expires <= now)
+                {
+                    rcpt.expires = QueueExpiry::Attempts(rcpt.retry.inner + release_attempts);
+                }
+            }
+
+            if !message.save_changes(server, held_until.into()).await {
+                return Err(trc::StoreEvent::UnexpectedError
+                    .into_err()
+                    .details("Failed to release held message")
+                    .caused_by(trc::location!()));
+            }
+            let _ = server.inner.ipc.queue_tx.send(QueueEvent::Refresh).await;
+        }
+        ModerationAction::Reject => {
+            trc::event!(
+                Queue(trc::QueueEvent::MessageRejected),
+                SpanId = message.span_id,
+                QueueId = queue_id,
+                From = message.message.return_path.clone(),
+            );
+
+            bounce_held(
+                server,
+                message,
+                "Message rejected by moderator.",
+                held_until,
+            )
+            .await;
+        }
+    }
+
+    Ok(true)
+}
+
+async fn bounce_held(server: &Server, mut message: MessageWrapper, reason: &str, due: u64) {
+    for rcpt in &mut message.message.recipients {
+        rcpt.status = Status::PermanentFailure(ErrorDetails {
+            entity: "localhost".to_string(),
+            details: Error::Io(reason.to_string()),
+        });
+    }
+    server.send_dsn(&mut message).await;
+    message.remove(server, due.into()).await;
+}
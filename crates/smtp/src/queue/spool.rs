@@ -11,10 +11,12 @@ use super::{
 use crate::queue::manager::{LockedMessage, Queue};
 use crate::queue::{
     DomainPart, FROM_AUTHENTICATED, FROM_AUTOGENERATED, FROM_DSN, FROM_REPORT,
-    FROM_UNAUTHENTICATED, FROM_UNAUTHENTICATED_DMARC, MessageWrapper,
+    FROM_UNAUTHENTICATED, FROM_UNAUTHENTICATED_DMARC, HELD_FOR_APPROVAL, MessageWrapper,
 };
+use ahash::AHashMap;
 use common::config::smtp::queue::QueueName;
 use common::ipc::QueueEvent;
+use common::telemetry::metrics::usage::UsageEvent;
 use common::{KV_LOCK_QUEUE_MESSAGE, Server};
 use std::borrow::Cow;
 use std::collections::hash_map::Entry;
@@ -120,8 +122,8 @@ impl SmtpSpool for Server {
         let result = self
             .store()
             .iterate(
-                IterateParams::new(from_key, to_key).ascending().no_values(),
-                |key, _| {
+                IterateParams::new(from_key, to_key).ascending(),
+                |key, value| {
                     let due = key.deserialize_be_u64(0)?;
 
                     if due <= now {
@@ -169,6 +171,10 @@ impl SmtpSpool for Server {
                                 due,
                                 queue_id,
                                 queue_name,
+                                priority: value
+                                    .try_into()
+                                    .map(i16::from_be_bytes)
+                                    .unwrap_or_default(),
                             });
                         }
 
@@ -354,7 +360,7 @@ impl MessageWrapper {
             return false;
         }
         if let Err(err) = server
-            .blob_store()
+            .queue_blob_store()
             .put_blob(self.message.blob_hash.as_slice(), message.as_ref())
             .await
         {
@@ -390,6 +396,11 @@ impl MessageWrapper {
             NextDsn = self.message.next_dsn(None).map(trc::Value::Timestamp),
             Expires = self.message.expires(None).map(trc::Value::Timestamp),
         );
+        if matches!(source, MessageSource::Authenticated) {
+            server
+                .record_usage(&self.message.return_path, UsageEvent::MessageSent)
+                .await;
+        }
 
         // Write message to queue
         let mut batch = BatchBuilder::new();
@@ -409,14 +420,24 @@ impl MessageWrapper {
             }
         }
 
-        for (queue_name, due) in self.message.next_events() {
+        // Held messages are not scheduled until they are released, they are
+        // bounced if no moderator acts before the approval window elapses
+        let next_events = if self.message.flags & HELD_FOR_APPROVAL == 0 {
+            self.message.next_events()
+        } else {
+            AHashMap::from_iter([(
+                QueueName::default(),
+                self.message.created + server.core.smtp.queue.moderation.expiry,
+            )])
+        };
+        for (queue_name, due) in next_events {
             batch.set(
                 ValueClass::Queue(QueueClass::MessageEvent(store::write::QueueEvent {
                     due,
                     queue_id: self.queue_id,
                     queue_name: queue_name.into_inner(),
                 })),
-                Vec::new(),
+                self.message.event_value(),
             );
         }
 
@@ -528,7 +549,7 @@ impl MessageWrapper {
                     queue_id: self.queue_id,
                     queue_name: queue_name.into_inner(),
                 })),
-                Vec::new(),
+                self.message.event_value(),
             );
         }
 
@@ -6,10 +6,12 @@
 
 use crate::core::throttle::NewKey;
 use common::{
-    KV_RATE_LIMIT_SMTP, Server, config::smtp::QueueRateLimiter, expr::functions::ResolveVariable,
+    KV_PROVIDER_BACKOFF, KV_RATE_LIMIT_SMTP, Server,
+    config::smtp::{QueueRateLimiter, queue::ProviderProfile},
+    expr::functions::ResolveVariable,
 };
 use std::future::Future;
-use store::write::now;
+use store::{dispatch::lookup::KeyValue, write::now};
 
 pub trait IsAllowed: Sync + Send {
     fn is_allowed<'x>(
@@ -20,6 +22,27 @@ pub trait IsAllowed: Sync + Send {
     ) -> impl Future<Output = Result<(), u64>> + Send;
 }
 
+pub trait IsProviderAllowed: Sync + Send {
+    fn is_provider_allowed(
+        &self,
+        profile: &ProviderProfile,
+        session_id: u64,
+    ) -> impl Future<Output = Result<(), u64>> + Send;
+
+    fn provider_backoff(
+        &self,
+        profile: &ProviderProfile,
+        response: &str,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn provider_reset(
+        &self,
+        profile: &ProviderProfile,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+}
+
 impl IsAllowed for Server {
     async fn is_allowed<'x>(
         &'x self,
@@ -65,3 +88,133 @@ impl IsAllowed for Server {
         Ok(())
     }
 }
+
+impl IsProviderAllowed for Server {
+    async fn is_provider_allowed(
+        &self,
+        profile: &ProviderProfile,
+        session_id: u64,
+    ) -> Result<(), u64> {
+        // Delivery is paused while the provider is backing off
+        let store = self.in_memory_store();
+        match store
+            .key_get::<i64>(KeyValue::<()>::build_key(
+                KV_PROVIDER_BACKOFF,
+                profile.id.as_bytes(),
+            ))
+            .await
+        {
+            Ok(Some(retry_at)) if retry_at as u64 > now() => {
+                trc::event!(
+                    Delivery(trc::DeliveryEvent::RateLimitExceeded),
+                    SpanId = session_id,
+                    Id = profile.id.clone(),
+                    NextRetry = trc::Value::Timestamp(retry_at as u64),
+                );
+
+                return Err(retry_at as u64);
+            }
+            Err(err) => {
+                trc::error!(err.span_id(session_id).caused_by(trc::location!()));
+            }
+            _ => (),
+        }
+
+        if let Some(rate) = profile.current_rate(now()) {
+            let mut key = Vec::with_capacity(profile.id.len() + 9);
+            key.extend_from_slice(b"provider:");
+            key.extend_from_slice(profile.id.as_bytes());
+
+            match store
+                .is_rate_allowed(KV_RATE_LIMIT_SMTP, &key, rate, false)
+                .await
+            {
+                Ok(Some(next_refill)) => {
+                    trc::event!(
+                        Delivery(trc::DeliveryEvent::RateLimitExceeded),
+                        SpanId = session_id,
+                        Id = profile.id.clone(),
+                        Limit = vec![
+                            trc::Value::from(rate.requests),
+                            trc::Value::from(rate.period)
+                        ],
+                    );
+
+                    return Err(now() + next_refill);
+                }
+                Err(err) => {
+                    trc::error!(err.span_id(session_id).caused_by(trc::location!()));
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn provider_backoff(&self, profile: &ProviderProfile, response: &str, session_id: u64) {
+        if !profile.is_backoff_response(response) {
+            return;
+        }
+
+        // Each consecutive deferral doubles the pause, up to the configured maximum
+        let store = self.in_memory_store();
+        let strikes = match store
+            .counter_incr(
+                KeyValue::with_prefix(KV_PROVIDER_BACKOFF, format!("{}:strikes", profile.id), 1)
+                    .expires(profile.backoff_max * 2),
+                true,
+            )
+            .await
+        {
+            Ok(strikes) => strikes.clamp(1, 32) as u32,
+            Err(err) => {
+                trc::error!(err.span_id(session_id).caused_by(trc::location!()));
+                1
+            }
+        };
+        let delay = profile
+            .backoff_initial
+            .saturating_mul(1u64 << (strikes - 1).min(20))
+            .min(profile.backoff_max)
+            .max(1);
+        let retry_at = now() + delay;
+
+        if let Err(err) = store
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_PROVIDER_BACKOFF,
+                    profile.id.as_bytes(),
+                    (retry_at as i64).to_be_bytes().to_vec(),
+                )
+                .expires(delay),
+            )
+            .await
+        {
+            trc::error!(err.span_id(session_id).caused_by(trc::location!()));
+        }
+
+        trc::event!(
+            Delivery(trc::DeliveryEvent::ProviderBackoff),
+            SpanId = session_id,
+            Id = profile.id.clone(),
+            Details = response.to_string(),
+            Total = strikes,
+            NextRetry = trc::Value::Timestamp(retry_at),
+        );
+    }
+
+    async fn provider_reset(&self, profile: &ProviderProfile, session_id: u64) {
+        // A successful delivery ends the streak of consecutive deferrals
+        if let Err(err) = self
+            .in_memory_store()
+            .counter_delete(KeyValue::<()>::build_key(
+                KV_PROVIDER_BACKOFF,
+                format!("{}:strikes", profile.id),
+            ))
+            .await
+        {
+            trc::error!(err.span_id(session_id).caused_by(trc::location!()));
+        }
+    }
+}
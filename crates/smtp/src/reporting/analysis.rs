@@ -11,7 +11,7 @@ use mail_auth::{
     report::{ActionDisposition, DmarcResult, Feedback, Report, tlsrpt::TlsReport},
     zip,
 };
-use mail_parser::{Message, MimeHeaders, PartType};
+use mail_parser::{Message, MessageParser, MimeHeaders, PartType};
 use std::{
     borrow::Cow,
     collections::hash_map::Entry,
@@ -23,6 +23,8 @@ use store::{
 };
 use trc::IncomingReportEvent;
 
+use super::feedback::FeedbackLoop;
+
 enum Compression {
     None,
     Gzip,
@@ -52,11 +54,11 @@ pub struct IncomingReport<T> {
 }
 
 pub trait AnalyzeReport: Sync + Send {
-    fn analyze_report(&self, message: Message<'static>, session_id: u64);
+    fn analyze_report(&self, message: Message<'static>, is_authenticated: bool, session_id: u64);
 }
 
 impl AnalyzeReport for Server {
-    fn analyze_report(&self, message: Message<'static>, session_id: u64) {
+    fn analyze_report(&self, message: Message<'static>, is_authenticated: bool, session_id: u64) {
         let core = self.clone();
         tokio::spawn(async move {
             let from: String = message
@@ -150,6 +152,20 @@ impl AnalyzeReport for Server {
                 }
             }
 
+            // Original message or headers included in feedback reports
+            let original = message.parts.iter().find_map(|part| match &part.body {
+                PartType::Message(original) => Some(Cow::Borrowed(original)),
+                PartType::Text(headers)
+                    if part.is_content_type("text", "rfc822-headers")
+                        || part.is_content_type("message", "rfc822-headers") =>
+                {
+                    MessageParser::new()
+                        .parse(headers.as_bytes())
+                        .map(Cow::Owned)
+                }
+                _ => None,
+            });
+
             for report in reports {
                 let data = match report.compression {
                     Compression::None => Cow::Borrowed(report.data),
@@ -256,6 +272,16 @@ impl AnalyzeReport for Server {
                         Some(report) => {
                             // Log
                             report.log();
+
+                            // Correlate complaints and suppress complainants
+                            core.process_complaint(
+                                &report,
+                                original.as_deref(),
+                                is_authenticated,
+                                session_id,
+                            )
+                            .await;
+
                             Format::Arf(report.into_owned())
                         }
                         None => {
@@ -133,10 +133,24 @@ impl<T: SessionStream> Session<T> {
                     .eval_if(&config.address, self, self.data.session_id)
                     .await
                     .unwrap_or_else(|| "MAILER-DAEMON@localhost".to_compact_string());
+                let redaction = self.server.core.smtp.report.dmarc_redaction;
+                let headers = String::from_utf8_lossy(message.raw_headers());
+                let headers = if redaction.recipients {
+                    redact_recipients(&headers).into()
+                } else {
+                    headers
+                };
                 let mut auth_failure = self
                     .new_auth_failure(AuthFailureType::Dmarc, rejected)
-                    .with_authentication_results(auth_results.to_string())
-                    .with_headers(std::str::from_utf8(message.raw_headers()).unwrap_or_default());
+                    .with_authentication_results(auth_results.to_string());
+                auth_failure = if redaction.body {
+                    auth_failure.with_headers(headers)
+                } else {
+                    auth_failure.with_message(format!(
+                        "{headers}{}",
+                        String::from_utf8_lossy(message.raw_body())
+                    ))
+                };
 
                 // Report the first failed signature
                 let dkim_failed = if let (
@@ -312,6 +326,84 @@ impl<T: SessionStream> Session<T> {
     }
 }
 
+// Replaces the recipient addresses found in the message headers with
+// a placeholder, keeping only the domain part (RFC 6590)
+pub fn redact_recipients(headers: &str) -> String {
+    const RCPT_HEADERS: [&str; 7] = [
+        "to",
+        "cc",
+        "bcc",
+        "resent-to",
+        "resent-cc",
+        "delivered-to",
+        "x-original-to",
+    ];
+
+    let mut result = String::with_capacity(headers.len());
+    let mut redacted: Option<(&str, Vec<&str>)> = None;
+    for line in headers.split_inclusive('\n') {
+        let value = if line.starts_with([' ', '\t']) {
+            if redacted.is_none() {
+                result.push_str(line);
+                continue;
+            }
+            line
+        } else {
+            flush_redacted(&mut result, &mut redacted);
+            match line.split_once(':') {
+                Some((name, value))
+                    if RCPT_HEADERS
+                        .iter()
+                        .any(|rcpt| name.trim().eq_ignore_ascii_case(rcpt)) =>
+                {
+                    redacted = Some((name.trim(), Vec::new()));
+                    value
+                }
+                _ => {
+                    result.push_str(line);
+                    continue;
+                }
+            }
+        };
+
+        if let Some((_, domains)) = &mut redacted {
+            domains.extend(
+                value
+                    .split(|ch: char| ch.is_ascii_whitespace() || matches!(ch, ',' | ';'))
+                    .filter_map(|token| {
+                        token
+                            .trim_matches(|ch| matches!(ch, '<' | '>' | '"' | '(' | ')'))
+                            .rsplit_once('@')
+                            .map(|(_, domain)| domain)
+                            .filter(|domain| !domain.is_empty())
+                    }),
+            );
+        }
+    }
+    flush_redacted(&mut result, &mut redacted);
+
+    result
+}
+
+fn flush_redacted(result: &mut String, redacted: &mut Option<(&str, Vec<&str>)>) {
+    if let Some((name, domains)) = redacted.take() {
+        result.push_str(name);
+        result.push_str(": ");
+        if !domains.is_empty() {
+            for (pos, domain) in domains.into_iter().enumerate() {
+                if pos > 0 {
+                    result.push_str(", ");
+                }
+                result.push_str("redacted@");
+                result.push_str(domain);
+            }
+        } else {
+            result.push_str("undisclosed-recipients:;");
+        }
+        result.push_str("\r\n");
+    }
+}
+
 pub trait DmarcReporting: Sync + Send {
     fn send_dmarc_aggregate_report(&self, event: ReportEvent) -> impl Future<Output = ()> + Send;
     fn generate_dmarc_aggregate_report(
@@ -382,7 +474,7 @@ impl DmarcReporting for Server {
                 if !rcpts.is_empty() {
                     rcpts
                         .into_iter()
-                        .map(|u| u.uri().to_string())
+                        .map(|u| (u.uri().to_string(), u.max_size()))
                         .collect::<Vec<_>>()
                 } else {
                     trc::event!(
@@ -423,35 +515,64 @@ impl DmarcReporting for Server {
             )
             .await
             .unwrap_or_else(|| "MAILER-DAEMON@localhost".to_compact_string());
-        let mut message = Vec::with_capacity(2048);
-        let _ = report.write_rfc5322(
-            &self
-                .eval_if(
-                    &self.core.smtp.report.submitter,
-                    &RecipientDomain::new(event.domain.as_str()),
-                    span_id,
-                )
-                .await
-                .unwrap_or_else(|| "localhost".to_compact_string()),
-            (
-                self.eval_if(
-                    &config.name,
-                    &RecipientDomain::new(event.domain.as_str()),
-                    span_id,
-                )
-                .await
-                .unwrap_or_else(|| "Mail Delivery Subsystem".to_compact_string())
-                .as_str(),
-                from_addr.as_str(),
-            ),
-            rua.iter().map(|a| a.as_str()),
-            &mut message,
-        );
+        let submitter = self
+            .eval_if(
+                &self.core.smtp.report.submitter,
+                &RecipientDomain::new(event.domain.as_str()),
+                span_id,
+            )
+            .await
+            .unwrap_or_else(|| "localhost".to_compact_string());
+        let from_name = self
+            .eval_if(
+                &config.name,
+                &RecipientDomain::new(event.domain.as_str()),
+                span_id,
+            )
+            .await
+            .unwrap_or_else(|| "Mail Delivery Subsystem".to_compact_string());
+        let write_message = |rua: &[(String, usize)]| {
+            let mut message = Vec::with_capacity(2048);
+            let _ = report.write_rfc5322(
+                &submitter,
+                (from_name.as_str(), from_addr.as_str()),
+                rua.iter().map(|(a, _)| a.as_str()),
+                &mut message,
+            );
+            message
+        };
+        let mut message = write_message(&rua);
+
+        // Skip reporting URIs with a maximum report size below the report size
+        let (rua, rejected): (Vec<_>, Vec<_>) = rua
+            .into_iter()
+            .partition(|(_, max_size)| *max_size == 0 || message.len() <= *max_size);
+        if !rejected.is_empty() {
+            trc::event!(
+                OutgoingReport(OutgoingReportEvent::ReportSizeExceeded),
+                SpanId = span_id,
+                Url = rejected
+                    .iter()
+                    .map(|(a, _)| trc::Value::String(a.to_compact_string()))
+                    .collect::<Vec<_>>(),
+                Size = message.len(),
+                Limit = rejected
+                    .iter()
+                    .map(|(_, max_size)| trc::Value::from(*max_size))
+                    .collect::<Vec<_>>(),
+            );
+
+            if rua.is_empty() {
+                self.delete_dmarc_report(event).await;
+                return;
+            }
+            message = write_message(&rua);
+        }
 
         // Send report
         self.send_report(
             &from_addr,
-            rua.iter(),
+            rua.iter().map(|(a, _)| a),
             message,
             &config.sign,
             false,
@@ -0,0 +1,304 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{KV_SUPPRESSION, Server, auth::oauth::GrantType};
+use mail_auth::report::{Feedback, FeedbackType};
+use mail_parser::{HeaderName, Message};
+use store::dispatch::lookup::KeyValue;
+use trc::{AddContext, IncomingReportEvent};
+
+use crate::queue::{DomainPart, QueueId};
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FeedbackComplaint {
+    pub complainant: Option<String>,
+    pub sender_domain: Option<String>,
+    pub feedback_id: Option<String>,
+    pub queue_id: Option<QueueId>,
+}
+
+pub trait FeedbackLoop: Sync + Send {
+    fn process_complaint(
+        &self,
+        report: &Feedback<'_>,
+        original: Option<&Message<'_>>,
+        is_authenticated: bool,
+        session_id: u64,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn suppress_recipient(
+        &self,
+        sender_domain: &str,
+        rcpt: &str,
+        expires: Option<u64>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn unsubscribe_token(
+        &self,
+        sender_domain: &str,
+        rcpt: &str,
+        expiry: u64,
+    ) -> impl Future<Output = trc::Result<String>> + Send;
+
+    fn unsubscribe(
+        &self,
+        token: &str,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn is_suppressed(
+        &self,
+        sender_domain: &str,
+        rcpt: &str,
+        session_id: u64,
+    ) -> impl Future<Output = bool> + Send;
+}
+
+impl FeedbackLoop for Server {
+    async fn process_complaint(
+        &self,
+        report: &Feedback<'_>,
+        original: Option<&Message<'_>>,
+        is_authenticated: bool,
+        session_id: u64,
+    ) {
+        let complaint = FeedbackComplaint::parse(report, original);
+
+        trc::event!(
+            IncomingReport(IncomingReportEvent::ComplaintReceived),
+            SpanId = session_id,
+            Type = format!("{:?}", report.feedback_type()),
+            Domain = complaint.sender_domain.clone(),
+            To = complaint.complainant.clone(),
+            Id = complaint.feedback_id.clone(),
+            QueueId = complaint.queue_id,
+            Hostname = report.reporting_mta().map(|d| trc::Value::String(d.into())),
+        );
+
+        // Only abuse complaints from authenticated reporters suppress further
+        // messages to the complainant, and only for local sender domains
+        if !is_authenticated || !matches!(report.feedback_type(), FeedbackType::Abuse) {
+            return;
+        }
+        let (Some(expires), Some(complainant), Some(sender_domain)) = (
+            self.core.smtp.report.analysis.suppress,
+            complaint.complainant,
+            complaint.sender_domain,
+        ) else {
+            return;
+        };
+        match self
+            .core
+            .storage
+            .directory
+            .is_local_domain(&sender_domain)
+            .await
+        {
+            Ok(true) => (),
+            Ok(false) => return,
+            Err(err) => {
+                trc::error!(
+                    err.span_id(session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to verify complaint sender domain")
+                );
+                return;
+            }
+        }
+
+        match self
+            .suppress_recipient(&sender_domain, &complainant, Some(expires.as_secs()))
+            .await
+        {
+            Ok(_) => {
+                trc::event!(
+                    IncomingReport(IncomingReportEvent::RecipientSuppressed),
+                    SpanId = session_id,
+                    Domain = sender_domain,
+                    To = complainant,
+                    Expires = trc::Value::Duration(expires.as_millis() as u64),
+                );
+            }
+            Err(err) => {
+                trc::error!(
+                    err.span_id(session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to suppress complainant")
+                );
+            }
+        }
+    }
+
+    async fn suppress_recipient(
+        &self,
+        sender_domain: &str,
+        rcpt: &str,
+        expires: Option<u64>,
+    ) -> trc::Result<()> {
+        self.in_memory_store()
+            .key_set(
+                KeyValue::with_prefix(KV_SUPPRESSION, suppression_key(sender_domain, rcpt), vec![])
+                    .expires_opt(expires),
+            )
+            .await
+    }
+
+    async fn unsubscribe_token(
+        &self,
+        sender_domain: &str,
+        rcpt: &str,
+        expiry: u64,
+    ) -> trc::Result<String> {
+        self.encode_access_token(
+            GrantType::Unsubscribe,
+            0,
+            &suppression_key(sender_domain, rcpt),
+            expiry,
+        )
+        .await
+    }
+
+    async fn unsubscribe(&self, token: &str, session_id: u64) -> trc::Result<()> {
+        let token = self
+            .validate_access_token(GrantType::Unsubscribe.into(), token)
+            .await?;
+        let (sender_domain, rcpt) = token.client_id.split_once(':').ok_or_else(|| {
+            trc::AuthEvent::Error
+                .into_err()
+                .details("Invalid unsubscribe token")
+        })?;
+
+        // Unsubscribe requests are honoured until removed by an administrator
+        self.suppress_recipient(sender_domain, rcpt, None)
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            IncomingReport(IncomingReportEvent::Unsubscribe),
+            SpanId = session_id,
+            Domain = sender_domain.to_string(),
+            To = rcpt.to_string(),
+        );
+
+        Ok(())
+    }
+
+    async fn is_suppressed(&self, sender_domain: &str, rcpt: &str, session_id: u64) -> bool {
+        if self.core.smtp.report.analysis.suppress.is_none()
+            && self.core.smtp.session.bulk.is_empty()
+        {
+            return false;
+        }
+
+        match self
+            .in_memory_store()
+            .key_exists(KeyValue::<()>::build_key(
+                KV_SUPPRESSION,
+                suppression_key(sender_domain, rcpt),
+            ))
+            .await
+        {
+            Ok(exists) => exists,
+            Err(err) => {
+                trc::error!(
+                    err.span_id(session_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to lookup suppression list")
+                );
+                false
+            }
+        }
+    }
+}
+
+impl FeedbackComplaint {
+    pub fn parse(report: &Feedback<'_>, original: Option<&Message<'_>>) -> FeedbackComplaint {
+        let mut complaint = FeedbackComplaint {
+            complainant: report.original_rcpt_to().and_then(normalize_address),
+            sender_domain: report
+                .original_mail_from()
+                .and_then(normalize_address)
+                .map(|address| address.domain_part().to_string())
+                .filter(|domain| !domain.is_empty()),
+            ..Default::default()
+        };
+
+        if let Some(original) = original {
+            if complaint.complainant.is_none() {
+                complaint.complainant = original
+                    .to()
+                    .and_then(|to| to.first())
+                    .and_then(|addr| addr.address())
+                    .and_then(normalize_address);
+            }
+            if complaint.sender_domain.is_none() {
+                complaint.sender_domain = original
+                    .from()
+                    .and_then(|from| from.first())
+                    .and_then(|addr| addr.address())
+                    .and_then(normalize_address)
+                    .map(|address| address.domain_part().to_string())
+                    .filter(|domain| !domain.is_empty());
+            }
+
+            let root = original.root_part();
+            complaint.feedback_id = root
+                .headers
+                .iter()
+                .find(|header| header.name.as_str().eq_ignore_ascii_case("Feedback-ID"))
+                .and_then(|header| header.value.as_text())
+                .map(|value| value.trim().to_string());
+
+            // Locate the queue id in the earliest Received header added by Stalwart,
+            // the hostname is not matched as it can be overridden per listener
+            complaint.queue_id = root
+                .headers
+                .iter()
+                .rev()
+                .filter(|header| header.name == HeaderName::Received)
+                .filter_map(|header| {
+                    original
+                        .raw_message()
+                        .get(header.offset_start as usize..header.offset_end as usize)
+                        .and_then(|value| std::str::from_utf8(value).ok())
+                })
+                .find_map(parse_received_queue_id);
+        }
+
+        if complaint.sender_domain.is_none() {
+            complaint.sender_domain = report
+                .reported_domain()
+                .first()
+                .map(|domain| domain.trim().to_ascii_lowercase());
+        }
+
+        complaint
+    }
+}
+
+fn parse_received_queue_id(value: &str) -> Option<QueueId> {
+    let value = value.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+    let (_, rest) = value.split_once(" (Stalwart SMTP) with ")?;
+    let (_, id) = rest.split_once(" id ")?;
+    let id = id
+        .split(|c: char| c == ';' || c.is_ascii_whitespace())
+        .next()?;
+    QueueId::from_str_radix(id, 16).ok()
+}
+
+fn normalize_address(address: &str) -> Option<String> {
+    let address = address
+        .trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .trim()
+        .to_lowercase();
+    address.contains('@').then_some(address)
+}
+
+fn suppression_key(sender_domain: &str, rcpt: &str) -> String {
+    format!("{}:{}", sender_domain.to_lowercase(), rcpt.to_lowercase())
+}
@@ -27,6 +27,7 @@ use tokio::io::{AsyncRead, AsyncWrite};
 pub mod analysis;
 pub mod dkim;
 pub mod dmarc;
+pub mod feedback;
 pub mod scheduler;
 pub mod spf;
 pub mod tls;
@@ -157,6 +157,17 @@ impl RunScript for Server {
                         by_time,
                         message_id,
                     } => {
+                        if params.dry_run {
+                            trc::event!(
+                                Sieve(SieveEvent::SendMessage),
+                                Id = script_id.clone(),
+                                SpanId = session_id,
+                                Details = "Message not sent during simulation",
+                            );
+                            input = true.into();
+                            continue;
+                        }
+
                         // Build message
                         let mut message = self.new_message(params.return_path.as_str(), session_id);
                         match recipient {
@@ -69,7 +69,9 @@ impl<T: SessionStream> Session<T> {
             )
             .set_variable("tls.version", tls_version)
             .set_variable("tls.cipher", tls_cipher)
-            .set_variable("stage", stage);
+            .set_variable("stage", stage)
+            .with_dry_run(self.is_simulation());
+        params.stage = stage;
         if let Some(ip_rev) = &self.data.iprev {
             params = params.set_variable("iprev.result", ip_rev.result().as_str());
             if let Some(ptr) = ip_rev.ptr.as_ref().and_then(|addrs| addrs.first()) {
@@ -146,7 +148,10 @@ impl<T: SessionStream> Session<T> {
         script: Arc<Sieve>,
         params: ScriptParameters<'_>,
     ) -> ScriptResult {
-        self.server
+        let stage = params.stage;
+        let simulated_id = self.is_simulation().then(|| script_id.clone());
+        let result = self
+            .server
             .run_script(
                 script_id,
                 script,
@@ -155,6 +160,12 @@ impl<T: SessionStream> Session<T> {
                     .with_envelope(&self.server, self, self.data.session_id)
                     .await,
             )
-            .await
+            .await;
+
+        if let Some(script_id) = simulated_id {
+            self.simulation_script_step(stage, script_id, &result);
+        }
+
+        result
     }
 }
@@ -42,6 +42,8 @@ pub struct ScriptParameters<'x> {
     sign: Vec<String>,
     access_token: Option<&'x AccessToken>,
     session_id: u64,
+    stage: &'static str,
+    dry_run: bool,
 }
 
 impl<'x> ScriptParameters<'x> {
@@ -57,6 +59,8 @@ impl<'x> ScriptParameters<'x> {
             sign: Default::default(),
             access_token: None,
             session_id: Default::default(),
+            stage: Default::default(),
+            dry_run: false,
         }
     }
 
@@ -112,6 +116,12 @@ impl<'x> ScriptParameters<'x> {
         self
     }
 
+    pub fn replace_envelope(mut self, envelope: Envelope, value: impl Into<Variable>) -> Self {
+        self.envelope
+            .retain(|(name, _)| std::mem::discriminant(name) != std::mem::discriminant(&envelope));
+        self.set_envelope(envelope, value)
+    }
+
     pub fn with_access_token(mut self, access_token: &'x AccessToken) -> Self {
         self.access_token = Some(access_token);
         self
@@ -121,6 +131,12 @@ impl<'x> ScriptParameters<'x> {
         self.session_id = session_id;
         self
     }
+
+    // Messages generated by the script are not queued during simulations
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
 }
 
 impl Default for ScriptParameters<'_> {
@@ -45,12 +45,14 @@ impl SpamFilterAnalyzeBayes for Server {
     }
 
     async fn spam_filter_analyze_spam_trap(&self, ctx: &mut SpamFilterContext<'_>) -> bool {
+        let mut is_trap = false;
+
         if let Some(store) = self.get_in_memory_store("spam-traps") {
             for addr in &ctx.output.env_to_addr {
                 match store.key_exists(addr.address.as_str()).await {
                     Ok(true) => {
-                        ctx.result.add_tag("SPAM_TRAP");
-                        return true;
+                        is_trap = true;
+                        break;
                     }
                     Ok(false) => (),
                     Err(err) => {
@@ -60,6 +62,59 @@ impl SpamFilterAnalyzeBayes for Server {
             }
         }
 
-        false
+        // Look for accounts flagged as spam traps in the directory
+        if !is_trap {
+            for addr in &ctx.output.env_to_addr {
+                match self
+                    .core
+                    .storage
+                    .directory
+                    .email_to_id(addr.address.as_str())
+                    .await
+                {
+                    Ok(Some(account_id)) => match self.get_access_token(account_id).await {
+                        Ok(access_token) if access_token.spam_trap => {
+                            is_trap = true;
+                            break;
+                        }
+                        Ok(_) => (),
+                        Err(err) => {
+                            trc::error!(err.span_id(ctx.input.span_id).caused_by(trc::location!()));
+                        }
+                    },
+                    Ok(None) => (),
+                    Err(err) => {
+                        trc::error!(err.span_id(ctx.input.span_id).caused_by(trc::location!()));
+                    }
+                }
+            }
+        }
+
+        if !is_trap {
+            return false;
+        }
+        ctx.result.add_tag("SPAM_TRAP");
+
+        // Temporarily ban the source
+        if let Some(block_for) = self.core.spam.spam_trap.block_for
+            && !ctx.input.is_test
+            && !self.is_ip_allowed(&ctx.input.remote_ip)
+        {
+            match self.ban_ip_for(ctx.input.remote_ip, block_for).await {
+                Ok(_) => {
+                    trc::event!(
+                        Security(trc::SecurityEvent::SpamTrapBan),
+                        SpanId = ctx.input.span_id,
+                        RemoteIp = ctx.input.remote_ip,
+                        Expires = trc::Value::Duration(block_for * 1000),
+                    );
+                }
+                Err(err) => {
+                    trc::error!(err.span_id(ctx.input.span_id).caused_by(trc::location!()));
+                }
+            }
+        }
+
+        true
     }
 }
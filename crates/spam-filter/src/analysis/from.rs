@@ -6,13 +6,15 @@
 
 use std::future::Future;
 
-use common::Server;
+use common::{Server, scripts::IsMixedCharset};
 use mail_parser::HeaderName;
 use nlp::tokenizers::types::{TokenType, TypesTokenizer};
 use smtp_proto::{MAIL_BODY_8BITMIME, MAIL_BODY_BINARYMIME, MAIL_SMTPUTF8};
 
 use crate::{Email, SpamFilterContext};
 
+use super::is_trusted_domain_homograph;
+
 pub trait SpamFilterAnalyzeFrom: Sync + Send {
     fn spam_filter_analyze_from(
         &self,
@@ -116,6 +118,19 @@ impl SpamFilterAnalyzeFrom for Server {
                 }
             }
 
+            // Check for lookalike domains
+            if from_addr_is_valid && !from_addr.domain_part.fqdn.is_ascii() {
+                if from_addr.domain_part.fqdn.is_mixed_charset() {
+                    ctx.result.add_tag("FROM_MIXED_CHARSET");
+                }
+
+                if is_trusted_domain_homograph(self, &from_addr.domain_part, ctx.input.span_id)
+                    .await
+                {
+                    ctx.result.add_tag("FROM_HOMOGRAPH");
+                }
+            }
+
             // Check sender
             if ctx.output.env_from_postmaster {
                 ctx.result.add_tag("FROM_BOUNCE");
@@ -60,6 +60,8 @@ impl SpamFilterAnalyzeHtml for Server {
             let mut html_img_words = 0;
             let mut in_head: i32 = 0;
             let mut in_body: i32 = 0;
+            let mut hidden_tags: Vec<u64> = Vec::new();
+            let mut hidden_text_chars = 0;
 
             for token in html_tokens {
                 match token {
@@ -67,169 +69,208 @@ impl SpamFilterAnalyzeHtml for Server {
                         name,
                         attributes,
                         is_self_closing,
-                    } => match *name {
-                        A => {
-                            if let Some(attr) = attributes.iter().find_map(|(attr, value)| {
-                                if *attr == HREF {
-                                    value.as_deref()
-                                } else {
-                                    None
-                                }
-                            }) {
-                                let url = attr.trim().to_lowercase();
-                                let url_parsed = url.parse::<Uri>().ok();
-                                let href = Href {
-                                    host: url_parsed
-                                        .as_ref()
-                                        .and_then(|uri| uri.host().map(Hostname::new)),
-                                    url_parsed,
-                                };
+                    } => {
+                        if is_body_part {
+                            if attributes.iter().any(|(attr, _)| is_event_handler(*attr)) {
+                                // HTML element has an inline JavaScript event handler
+                                ctx.result.add_tag("HTML_EVENT_HANDLER");
+                            }
 
-                                if is_body_part
-                                    && attr.starts_with("data:")
-                                    && attr.contains(";base64,")
-                                {
-                                    // Has Data URI encoding
-                                    ctx.result.add_tag("HAS_DATA_URI");
-                                    if attr.contains("text/") {
-                                        //  Uses Data URI encoding to obfuscate plain or HTML in base64
-                                        ctx.result.add_tag("DATA_URI_OBFU");
+                            if !*is_self_closing && is_hidden_element(*name, attributes) {
+                                hidden_tags.push(*name);
+                            }
+                        }
+
+                        match *name {
+                            A => {
+                                if let Some(attr) = attributes.iter().find_map(|(attr, value)| {
+                                    if *attr == HREF {
+                                        value.as_deref()
+                                    } else {
+                                        None
+                                    }
+                                }) {
+                                    let url = attr.trim().to_lowercase();
+                                    let url_parsed = url.parse::<Uri>().ok();
+                                    let href = Href {
+                                        host: url_parsed
+                                            .as_ref()
+                                            .and_then(|uri| uri.host().map(Hostname::new)),
+                                        url_parsed,
+                                    };
+
+                                    if is_body_part && url.starts_with("javascript:") {
+                                        // HTML anchor executes JavaScript
+                                        ctx.result.add_tag("HTML_JAVASCRIPT");
+                                    }
+
+                                    if is_body_part
+                                        && attr.starts_with("data:")
+                                        && attr.contains(";base64,")
+                                    {
+                                        // Has Data URI encoding
+                                        ctx.result.add_tag("HAS_DATA_URI");
+                                        if attr.contains("text/") {
+                                            //  Uses Data URI encoding to obfuscate plain or HTML in base64
+                                            ctx.result.add_tag("DATA_URI_OBFU");
+                                        }
+                                    } else if href.host.as_ref().is_some_and(|h| h.ip.is_some()) {
+                                        // HTML anchor points to an IP address
+                                        ctx.result.add_tag("HTTP_TO_IP");
                                     }
-                                } else if href.host.as_ref().is_some_and(|h| h.ip.is_some()) {
-                                    // HTML anchor points to an IP address
-                                    ctx.result.add_tag("HTTP_TO_IP");
-                                }
 
-                                if !*is_self_closing {
-                                    last_href = Some(href);
+                                    if !*is_self_closing {
+                                        last_href = Some(href);
+                                    }
                                 }
                             }
-                        }
-                        IMG if is_body_part => {
-                            let mut img_width = 800;
-                            let mut img_height = 600;
+                            IMG if is_body_part => {
+                                if is_tracking_pixel(attributes) {
+                                    // Remote image with a 1x1 or hidden size
+                                    ctx.result.add_tag("HTML_TRACKING_PIXEL");
+                                }
 
-                            for (attr, value) in attributes {
-                                if let Some(value) =
-                                    value.as_deref().map(|v| v.trim()).filter(|v| !v.is_empty())
-                                {
-                                    let dimension = match *attr {
-                                        WIDTH => &mut img_width,
-                                        HEIGHT => &mut img_height,
-                                        SRC => {
-                                            let src = value.to_ascii_lowercase();
-                                            if src.starts_with("data:") && src.contains(";base64,")
-                                            {
-                                                // Has Data URI encoding
-                                                ctx.result.add_tag("HAS_DATA_URI");
-                                            } else if src.starts_with("https://")
-                                                || src.starts_with("http://")
-                                            {
-                                                // Has external image
-                                                ctx.result.add_tag("HAS_EXTERNAL_IMG");
+                                let mut img_width = 800;
+                                let mut img_height = 600;
+
+                                for (attr, value) in attributes {
+                                    if let Some(value) =
+                                        value.as_deref().map(|v| v.trim()).filter(|v| !v.is_empty())
+                                    {
+                                        let dimension = match *attr {
+                                            WIDTH => &mut img_width,
+                                            HEIGHT => &mut img_height,
+                                            SRC => {
+                                                let src = value.to_ascii_lowercase();
+                                                if src.starts_with("data:")
+                                                    && src.contains(";base64,")
+                                                {
+                                                    // Has Data URI encoding
+                                                    ctx.result.add_tag("HAS_DATA_URI");
+                                                } else if src.starts_with("https://")
+                                                    || src.starts_with("http://")
+                                                {
+                                                    // Has external image
+                                                    ctx.result.add_tag("HAS_EXTERNAL_IMG");
+                                                }
+                                                continue;
                                             }
-                                            continue;
-                                        }
-                                        _ => {
-                                            continue;
-                                        }
-                                    };
-                                    if let Some(pct) = value.strip_suffix('%') {
-                                        if let Ok(pct) = pct.trim().parse::<u64>() {
-                                            *dimension = (*dimension * pct) / 100;
+                                            _ => {
+                                                continue;
+                                            }
+                                        };
+                                        if let Some(pct) = value.strip_suffix('%') {
+                                            if let Ok(pct) = pct.trim().parse::<u64>() {
+                                                *dimension = (*dimension * pct) / 100;
+                                            }
+                                        } else if let Ok(value) = value.parse::<u64>() {
+                                            *dimension = value;
                                         }
-                                    } else if let Ok(value) = value.parse::<u64>() {
-                                        *dimension = value;
                                     }
                                 }
-                            }
-                            let dimensions = img_width + img_height;
-
-                            if last_href.is_some() {
-                                if dimensions >= 210 {
-                                    ctx.result.add_tag("HAS_LINK_TO_LARGE_IMG");
-                                    has_link_to_img = true;
-                                } else {
-                                    ctx.result.add_tag("HAS_LINK_TO_IMG");
+                                let dimensions = img_width + img_height;
+
+                                if last_href.is_some() {
+                                    if dimensions >= 210 {
+                                        ctx.result.add_tag("HAS_LINK_TO_LARGE_IMG");
+                                        has_link_to_img = true;
+                                    } else {
+                                        ctx.result.add_tag("HAS_LINK_TO_IMG");
+                                    }
                                 }
-                            }
 
-                            if dimensions > 100 {
-                                // We assume that a single picture 100x200 contains approx 3 words of text
-                                html_img_words += dimensions / 100;
+                                if dimensions > 100 {
+                                    // We assume that a single picture 100x200 contains approx 3 words of text
+                                    html_img_words += dimensions / 100;
+                                }
                             }
-                        }
-                        META => {
-                            let mut has_equiv_refresh = false;
-                            let mut has_content_url = false;
+                            META => {
+                                let mut has_equiv_refresh = false;
+                                let mut has_content_url = false;
 
-                            for (attr, value) in attributes {
-                                if let Some(value) =
-                                    value.as_deref().map(|v| v.trim()).filter(|v| !v.is_empty())
-                                {
-                                    if *attr == HTTP_EQUIV {
-                                        if value.eq_ignore_ascii_case("refresh") {
-                                            has_equiv_refresh = true;
-                                        }
-                                    } else if *attr == CONTENT
-                                        && value.to_ascii_lowercase().contains("url=")
+                                for (attr, value) in attributes {
+                                    if let Some(value) =
+                                        value.as_deref().map(|v| v.trim()).filter(|v| !v.is_empty())
                                     {
-                                        has_content_url = true;
+                                        if *attr == HTTP_EQUIV {
+                                            if value.eq_ignore_ascii_case("refresh") {
+                                                has_equiv_refresh = true;
+                                            }
+                                        } else if *attr == CONTENT
+                                            && value.to_ascii_lowercase().contains("url=")
+                                        {
+                                            has_content_url = true;
+                                        }
                                     }
                                 }
-                            }
 
-                            if has_equiv_refresh && has_content_url {
-                                // HTML meta refresh tag
-                                ctx.result.add_tag("HTML_META_REFRESH_URL");
+                                if has_equiv_refresh && has_content_url {
+                                    // HTML meta refresh tag
+                                    ctx.result.add_tag("HTML_META_REFRESH_URL");
+                                }
                             }
-                        }
-                        LINK if is_body_part => {
-                            let mut has_rel_style = false;
-                            let mut has_href_css = false;
+                            SCRIPT if is_body_part => {
+                                // HTML contains a script element
+                                ctx.result.add_tag("HTML_JAVASCRIPT");
+                            }
+                            LINK if is_body_part => {
+                                let mut has_rel_style = false;
+                                let mut has_href_css = false;
 
-                            for (attr, value) in attributes {
-                                if let Some(value) =
-                                    value.as_deref().map(|v| v.trim()).filter(|v| !v.is_empty())
-                                {
-                                    if *attr == REL {
-                                        if value.to_ascii_lowercase().contains("stylesheet") {
-                                            has_rel_style = true;
-                                        }
-                                    } else if *attr == HREF
-                                        && value.to_ascii_lowercase().contains(".css")
+                                for (attr, value) in attributes {
+                                    if let Some(value) =
+                                        value.as_deref().map(|v| v.trim()).filter(|v| !v.is_empty())
                                     {
-                                        has_href_css = true;
+                                        if *attr == REL {
+                                            if value.to_ascii_lowercase().contains("stylesheet") {
+                                                has_rel_style = true;
+                                            }
+                                        } else if *attr == HREF
+                                            && value.to_ascii_lowercase().contains(".css")
+                                        {
+                                            has_href_css = true;
+                                        }
                                     }
                                 }
-                            }
 
-                            if has_rel_style || has_href_css {
-                                // Has external CSS
-                                ctx.result.add_tag("EXT_CSS");
+                                if has_rel_style || has_href_css {
+                                    // Has external CSS
+                                    ctx.result.add_tag("EXT_CSS");
+                                }
                             }
+                            HEAD if !*is_self_closing => {
+                                in_head += 1;
+                            }
+                            BODY if !*is_self_closing => {
+                                in_body += 1;
+                            }
+                            _ => {}
                         }
-                        HEAD if !*is_self_closing => {
-                            in_head += 1;
-                        }
-                        BODY if !*is_self_closing => {
-                            in_body += 1;
-                        }
-                        _ => {}
-                    },
-                    HtmlToken::EndTag { name } => match *name {
-                        A => {
-                            last_href = None;
-                        }
-                        HEAD => {
-                            in_head -= 1;
+                    }
+                    HtmlToken::EndTag { name } => {
+                        if let Some(pos) = hidden_tags.iter().rposition(|tag| tag == name) {
+                            hidden_tags.truncate(pos);
                         }
-                        BODY => {
-                            in_body -= 1;
+
+                        match *name {
+                            A => {
+                                last_href = None;
+                            }
+                            HEAD => {
+                                in_head -= 1;
+                            }
+                            BODY => {
+                                in_body -= 1;
+                            }
+                            _ => (),
                         }
-                        _ => (),
-                    },
+                    }
                     HtmlToken::Text { text } if in_head == 0 => {
+                        if !hidden_tags.is_empty() {
+                            hidden_text_chars +=
+                                text.chars().filter(|ch| !ch.is_whitespace()).count();
+                        }
+
                         if let Some((href_url, href_host)) = last_href
                             .as_ref()
                             .and_then(|href| Some((href.url_parsed.as_ref()?, href.host.as_ref()?)))
@@ -288,6 +329,11 @@ impl SpamFilterAnalyzeHtml for Server {
                     ctx.result.add_tag("HTML_UNBALANCED_TAG");
                 }
 
+                if hidden_text_chars > 0 {
+                    // HTML contains text that is not rendered
+                    ctx.result.add_tag("HTML_HIDDEN_TEXT");
+                }
+
                 let mut html_words = 0;
                 let mut html_uris = 0;
                 let mut html_text_chars = 0;
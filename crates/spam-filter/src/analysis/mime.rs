@@ -16,7 +16,7 @@ use common::{
 use mail_parser::{HeaderName, MimeHeaders, PartType};
 use nlp::tokenizers::types::TokenType;
 
-use crate::{SpamFilterContext, TextPart};
+use crate::{SpamFilterContext, TextPart, modules::office::inspect_office_document};
 
 pub trait SpamFilterAnalyzeMime: Sync + Send {
     fn spam_filter_analyze_mime(
@@ -377,6 +377,21 @@ impl SpamFilterAnalyzeMime for Server {
                         ctx.result.add_tag("MIME_BAD");
                     }
                 }
+
+                if let Some(office) = inspect_office_document(part.contents()) {
+                    if office.has_macros {
+                        // Office document contains VBA macros
+                        ctx.result.add_tag("MIME_OFFICE_MACRO");
+                    }
+                    if office.has_ole_objects {
+                        // Office document contains embedded OLE objects
+                        ctx.result.add_tag("MIME_OFFICE_OLE");
+                    }
+                    if office.has_executable {
+                        // Office document contains an embedded executable
+                        ctx.result.add_tag("MIME_OFFICE_EXECUTABLE");
+                    }
+                }
             }
 
             // Analyze attachment name
@@ -14,7 +14,8 @@ use compact_str::CompactString;
 use mail_parser::{Header, parsers::MessageStream};
 
 use crate::{
-    Recipient, SpamFilterContext, SpamFilterInput, SpamFilterOutput, SpamFilterResult, TextPart,
+    Hostname, Recipient, SpamFilterContext, SpamFilterInput, SpamFilterOutput, SpamFilterResult,
+    TextPart,
 };
 
 pub mod bayes;
@@ -143,6 +144,33 @@ pub(crate) async fn is_trusted_domain(server: &Server, domain: &str, span_id: u6
     }
 }
 
+// Returns true when a non-ASCII domain is a visual imitation of a local or trusted domain
+pub(crate) async fn is_trusted_domain_homograph(
+    server: &Server,
+    host: &Hostname,
+    span_id: u64,
+) -> bool {
+    if host.fqdn.is_ascii() || is_trusted_domain(server, &host.fqdn, span_id).await {
+        return false;
+    }
+
+    for name in [Some(&host.fqdn), host.sld.as_ref()].into_iter().flatten() {
+        if !name.is_ascii()
+            && let Ok(cured) = decancer::cure(name, decancer::Options::default())
+        {
+            let cured = cured.to_string();
+            if cured != name.as_str()
+                && cured.is_ascii()
+                && is_trusted_domain(server, &cured, span_id).await
+            {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
 pub(crate) async fn is_url_redirector(server: &Server, url: &str, span_id: u64) -> bool {
     if let Some(store) = server.core.storage.lookups.get("url-redirectors") {
         match store.key_exists(url).await {
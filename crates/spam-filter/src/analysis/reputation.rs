@@ -84,7 +84,18 @@ impl SpamFilterAnalyzeReputation for Server {
         if let Some(config) = &self.core.spam.reputation {
             let mut reputation = 0.0;
 
+            // Messages sent to spam traps weigh more heavily on the source IP
+            let trap_penalty = if ctx.result.has_tag("SPAM_TRAP") {
+                self.core.spam.spam_trap.reputation_penalty
+            } else {
+                0.0
+            };
+
             for (rep_type, key) in types {
+                let score = match rep_type {
+                    Type::Ip => ctx.result.score + trap_penalty,
+                    _ => ctx.result.score,
+                };
                 let token = match key_get::<Reputation>(
                     self,
                     ctx.input.span_id,
@@ -100,12 +111,7 @@ impl SpamFilterAnalyzeReputation for Server {
                             KeyValue::with_prefix(
                                 rep_type.prefix(),
                                 key.as_ref(),
-                                Reputation {
-                                    count: 1,
-                                    score: ctx.result.score,
-                                }
-                                .serialize()
-                                .unwrap(),
+                                Reputation { count: 1, score }.serialize().unwrap(),
                             )
                             .expires(config.expiry),
                         )
@@ -117,7 +123,7 @@ impl SpamFilterAnalyzeReputation for Server {
 
                 // Update reputation
                 let updated_score = (token.count + 1) as f64
-                    * (ctx.result.score + config.token_score * token.score)
+                    * (score + config.token_score * token.score)
                     / (config.token_score * token.count as f64 + 1.0);
                 let updated_count = token.count + 1;
 
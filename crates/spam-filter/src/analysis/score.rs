@@ -117,30 +117,22 @@ impl SpamFilterAnalyzeScore for Server {
             }
         }
 
-        if self.core.spam.scores.reject_threshold > 0.0
-            && ctx.result.score >= self.core.spam.scores.reject_threshold
-        {
-            SpamFilterAction::Reject
-        } else if self.core.spam.scores.discard_threshold > 0.0
-            && ctx.result.score >= self.core.spam.scores.discard_threshold
-        {
-            SpamFilterAction::Discard
-        } else {
-            let mut header = std::mem::take(&mut ctx.result.header).unwrap_or_default();
-            if let Some(header_name) = &self.core.spam.headers.status {
-                let _ = write!(
-                    &mut header,
-                    "{}: {}, score={:.2}\r\n",
-                    header_name,
-                    if ctx.result.score >= self.core.spam.scores.spam_threshold {
-                        "Yes"
-                    } else {
-                        "No"
-                    },
-                    ctx.result.score
-                );
+        match self.core.spam.scores.verdict(ctx.result.score) {
+            // Never reveal spam traps to the sender
+            SpamFilterAction::Reject if ctx.result.has_tag("SPAM_TRAP") => {
+                SpamFilterAction::Discard
+            }
+            SpamFilterAction::Reject => SpamFilterAction::Reject,
+            SpamFilterAction::Discard => SpamFilterAction::Discard,
+            SpamFilterAction::Allow(is_spam) => {
+                // Keep the results header, it is reused when verdicts are computed per recipient
+                let mut header = ctx.result.header.clone().unwrap_or_default();
+                self.core
+                    .spam
+                    .headers
+                    .write_status(&mut header, is_spam, ctx.result.score);
+                SpamFilterAction::Allow(header)
             }
-            SpamFilterAction::Allow(header)
         }
     }
 
@@ -221,6 +213,13 @@ impl SpamFilterAnalyzeScore for Server {
         // Calculate score
         match self.spam_filter_score(ctx).await {
             SpamFilterAction::Allow(_) => (),
+            _ if ctx.result.has_tag("SPAM_TRAP") => {
+                // Spam traps still train the classifier and penalize the source,
+                // and the message is silently dropped
+                self.spam_filter_analyze_reputation(ctx).await;
+                self.spam_filter_finalize(ctx).await;
+                return SpamFilterAction::Discard;
+            }
             SpamFilterAction::Discard => return SpamFilterAction::Discard,
             SpamFilterAction::Reject => return SpamFilterAction::Reject,
         }
@@ -24,7 +24,7 @@ use crate::{
     modules::html::{A, HREF, HtmlToken},
 };
 
-use super::{ElementLocation, is_trusted_domain, is_url_redirector};
+use super::{ElementLocation, is_trusted_domain, is_trusted_domain_homograph, is_url_redirector};
 
 pub trait SpamFilterAnalyzeUrl: Sync + Send {
     fn spam_filter_analyze_url(
@@ -249,6 +249,10 @@ impl SpamFilterAnalyzeUrl for Server {
                     if host.fqdn.is_mixed_charset() {
                         ctx.result.add_tag("MIXED_CHARSET_URL");
                     }
+
+                    if is_trusted_domain_homograph(self, host, ctx.input.span_id).await {
+                        ctx.result.add_tag("HOMOGRAPH_TRUSTED_URL");
+                    }
                 }
 
                 // Check Domain DNSBL
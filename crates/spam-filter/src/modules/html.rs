@@ -5,7 +5,11 @@
  */
 
 use compact_str::CompactString;
-use mail_parser::decoders::html::add_html_token;
+use mail_builder::encoders::base64::base64_encode_mime;
+use mail_parser::{
+    Encoding, HeaderName, Message, MimeHeaders, PartType, decoders::html::add_html_token,
+};
+use utils::splice_bytes;
 
 #[derive(Debug, Eq, PartialEq, Clone)]
 pub enum HtmlToken {
@@ -35,6 +39,14 @@ pub(crate) const META: u64 =
     (b'm' as u64) | ((b'e' as u64) << 8) | ((b't' as u64) << 16) | ((b'a' as u64) << 24);
 pub(crate) const LINK: u64 =
     (b'l' as u64) | ((b'i' as u64) << 8) | ((b'n' as u64) << 16) | ((b'k' as u64) << 24);
+pub(crate) const FONT: u64 =
+    (b'f' as u64) | ((b'o' as u64) << 8) | ((b'n' as u64) << 16) | ((b't' as u64) << 24);
+pub(crate) const SCRIPT: u64 = (b's' as u64)
+    | ((b'c' as u64) << 8)
+    | ((b'r' as u64) << 16)
+    | ((b'i' as u64) << 24)
+    | ((b'p' as u64) << 32)
+    | ((b't' as u64) << 40);
 
 pub(crate) const HREF: u64 =
     (b'h' as u64) | ((b'r' as u64) << 8) | ((b'e' as u64) << 16) | ((b'f' as u64) << 24);
@@ -58,6 +70,19 @@ pub(crate) const CONTENT: u64 = (b'c' as u64)
     | ((b'e' as u64) << 32)
     | ((b'n' as u64) << 40)
     | ((b't' as u64) << 48);
+pub(crate) const STYLE: u64 = (b's' as u64)
+    | ((b't' as u64) << 8)
+    | ((b'y' as u64) << 16)
+    | ((b'l' as u64) << 24)
+    | ((b'e' as u64) << 32);
+pub(crate) const SIZE: u64 =
+    (b's' as u64) | ((b'i' as u64) << 8) | ((b'z' as u64) << 16) | ((b'e' as u64) << 24);
+pub(crate) const HIDDEN: u64 = (b'h' as u64)
+    | ((b'i' as u64) << 8)
+    | ((b'd' as u64) << 16)
+    | ((b'd' as u64) << 24)
+    | ((b'e' as u64) << 32)
+    | ((b'n' as u64) << 40);
 pub(crate) const HTTP_EQUIV: u64 = (b'h' as u64)
     | ((b't' as u64) << 8)
     | ((b't' as u64) << 16)
@@ -151,6 +176,7 @@ pub fn html_to_tokens(input: &str) -> Vec<HtmlToken> {
                     }
 
                     let mut in_quote = false;
+                    let mut quote_char = b'"';
                     let mut is_self_closing = false;
 
                     let mut key: u64 = 0;
@@ -214,13 +240,15 @@ pub fn html_to_tokens(input: &str) -> Vec<HtmlToken> {
                                             }
                                             break 'outer;
                                         }
-                                        b'"' => {
-                                            if in_quote {
-                                                in_quote = false;
-                                                break;
-                                            } else {
-                                                in_quote = true;
-                                            }
+                                        b'"' | b'\'' if in_quote && ch == quote_char => {
+                                            in_quote = false;
+                                            break;
+                                        }
+                                        b'"' | b'\''
+                                            if !in_quote && (ch == b'"' || value.is_empty()) =>
+                                        {
+                                            in_quote = true;
+                                            quote_char = ch;
                                         }
                                         b' ' | b'\t' | b'\r' | b'\n' if !in_quote => {
                                             break;
@@ -329,6 +357,230 @@ pub fn html_to_tokens(input: &str) -> Vec<HtmlToken> {
 
     tags
 }
+// Returns true for "on*" attributes such as onclick or onload
+pub fn is_event_handler(attr: u64) -> bool {
+    attr & 0xFFFF == ((b'o' as u64) | ((b'n' as u64) << 8)) && attr > 0xFFFF
+}
+
+pub fn style_declarations(style: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    style.split(';').filter_map(|decl| {
+        let (name, value) = decl.split_once(':')?;
+        let name = name.trim().to_ascii_lowercase();
+        let value = value
+            .trim()
+            .trim_end_matches("!important")
+            .trim()
+            .to_ascii_lowercase();
+        if !name.is_empty() && !value.is_empty() {
+            Some((name, value))
+        } else {
+            None
+        }
+    })
+}
+
+fn is_zero_length(value: &str) -> bool {
+    let value = value.trim_end_matches(|c: char| c.is_ascii_alphabetic() || c == '%');
+    value.parse::<f64>().is_ok_and(|v| v <= 0.0)
+}
+
+fn parse_pixels(value: &str) -> Option<u64> {
+    let value = value.trim();
+    value
+        .strip_suffix("px")
+        .unwrap_or(value)
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .map(|v| v.max(0.0) as u64)
+}
+
+// Returns true if the inline style renders the element's contents invisible
+pub fn is_hidden_style(style: &str) -> bool {
+    style_declarations(style).any(|(name, value)| match name.as_str() {
+        "display" => value == "none",
+        "visibility" => value == "hidden" || value == "collapse",
+        "opacity" => value.parse::<f64>().is_ok_and(|v| v <= 0.0),
+        "font-size" | "line-height" | "max-height" | "max-width" => is_zero_length(&value),
+        _ => false,
+    })
+}
+
+// Returns true if the element hides its text content
+pub fn is_hidden_element(name: u64, attributes: &[(u64, Option<CompactString>)]) -> bool {
+    attributes.iter().any(|(attr, value)| match *attr {
+        HIDDEN => true,
+        STYLE => value.as_deref().is_some_and(is_hidden_style),
+        SIZE if name == FONT => value
+            .as_deref()
+            .is_some_and(|v| v.trim().parse::<i64>().is_ok_and(|v| v <= 0)),
+        _ => false,
+    })
+}
+
+// Returns true if the image is a remote resource rendered at 1x1 pixels or less
+pub fn is_tracking_pixel(attributes: &[(u64, Option<CompactString>)]) -> bool {
+    let mut is_remote = false;
+    let mut is_hidden = false;
+    let mut width = None;
+    let mut height = None;
+
+    for (attr, value) in attributes {
+        if let Some(value) = value.as_deref().map(|v| v.trim()).filter(|v| !v.is_empty()) {
+            match *attr {
+                SRC => {
+                    let src = value.to_ascii_lowercase();
+                    is_remote = src.starts_with("https://")
+                        || src.starts_with("http://")
+                        || src.starts_with("//");
+                }
+                WIDTH => {
+                    width = parse_pixels(value);
+                }
+                HEIGHT => {
+                    height = parse_pixels(value);
+                }
+                STYLE => {
+                    for (name, value) in style_declarations(value) {
+                        match name.as_str() {
+                            "width" => {
+                                width = parse_pixels(&value);
+                            }
+                            "height" => {
+                                height = parse_pixels(&value);
+                            }
+                            "display" if value == "none" => {
+                                is_hidden = true;
+                            }
+                            "visibility" if value == "hidden" => {
+                                is_hidden = true;
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    is_remote
+        && (is_hidden
+            || matches!((width, height), (Some(width), Some(height)) if width <= 1 && height <= 1))
+}
+
+// Removes remote tracking pixels from an HTML document, returns None if no changes were made
+pub fn strip_tracking_pixels(html: &str) -> Option<String> {
+    let bytes = html.as_bytes();
+    let mut result = String::new();
+    let mut last_pos = 0;
+    let mut pos = 0;
+
+    while let Some(offset) = html[pos..].find('<') {
+        let start = pos + offset;
+        pos = start + 1;
+
+        if bytes
+            .get(start + 1..start + 4)
+            .is_some_and(|tag| tag.eq_ignore_ascii_case(b"img"))
+            && bytes
+                .get(start + 4)
+                .is_some_and(|ch| ch.is_ascii_whitespace() || *ch == b'/')
+        {
+            let mut quote = None;
+            let Some(end) = bytes[start..].iter().enumerate().find_map(|(idx, ch)| {
+                match (ch, quote) {
+                    (b'"' | b'\'', None) => quote = Some(*ch),
+                    (_, Some(q)) if *ch == q => quote = None,
+                    (b'>', None) => return Some(start + idx + 1),
+                    _ => (),
+                }
+                None
+            }) else {
+                break;
+            };
+
+            if let Some(HtmlToken::StartTag { attributes, .. }) =
+                html_to_tokens(&html[start..end]).into_iter().next()
+                && is_tracking_pixel(&attributes)
+            {
+                if result.is_empty() {
+                    result.reserve(html.len());
+                }
+                result.push_str(&html[last_pos..start]);
+                last_pos = end;
+            }
+            pos = end;
+        }
+    }
+
+    if last_pos > 0 {
+        result.push_str(&html[last_pos..]);
+        Some(result)
+    } else {
+        None
+    }
+}
+
+// Rewrites the HTML parts of a message without tracking pixels, returns None if no changes were made
+pub fn strip_message_trackers(message: &Message<'_>) -> Option<Vec<u8>> {
+    let raw_message = message.raw_message();
+    let mut edits: Vec<(usize, usize, Vec<u8>)> = Vec::new();
+
+    for part in &message.parts {
+        let PartType::Html(html) = &part.body else {
+            continue;
+        };
+
+        // Only rewrite parts that can be re-encoded without charset conversion
+        if !part
+            .content_type()
+            .and_then(|ct| ct.attribute("charset"))
+            .is_none_or(|charset| {
+                charset.eq_ignore_ascii_case("utf-8")
+                    || charset.eq_ignore_ascii_case("utf8")
+                    || charset.eq_ignore_ascii_case("us-ascii")
+            })
+        {
+            continue;
+        }
+
+        let Some(html) = strip_tracking_pixels(html) else {
+            continue;
+        };
+
+        match part.encoding {
+            Encoding::None => {
+                edits.push((
+                    part.offset_body as usize,
+                    part.offset_end as usize,
+                    html.into_bytes(),
+                ));
+            }
+            Encoding::QuotedPrintable | Encoding::Base64 => {
+                let header = part
+                    .headers
+                    .iter()
+                    .find(|h| h.name == HeaderName::ContentTransferEncoding)?;
+                let mut body = Vec::with_capacity(html.len() * 4 / 3 + 4);
+                base64_encode_mime(html.as_bytes(), &mut body, false).ok()?;
+                edits.push((
+                    header.offset_start as usize,
+                    header.offset_end as usize,
+                    b" base64\r\n".to_vec(),
+                ));
+                edits.push((part.offset_body as usize, part.offset_end as usize, body));
+            }
+        }
+    }
+
+    if edits.is_empty() {
+        return None;
+    }
+
+    splice_bytes(raw_message, edits)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -441,4 +693,49 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_tracking_pixels() {
+        for (html, expected) in [
+            (
+                r#"<p>Hello</p><img src="https://t.example.com/open?id=1" width="1" height="1"><p>Bye</p>"#,
+                Some("<p>Hello</p><p>Bye</p>"),
+            ),
+            (
+                r#"<img style="display:none" src="http://t.example.com/p.gif"/>Text"#,
+                Some("Text"),
+            ),
+            (
+                r#"<img src="https://example.com/logo.png" width="200" height="50">"#,
+                None,
+            ),
+            (
+                r#"<img src='https://t.example.com/open?a=1>2' width='1' height='1'><p>Bye</p>"#,
+                Some("<p>Bye</p>"),
+            ),
+            (
+                r#"<img alt='say "hi"' src='https://t.example.com/p.gif' width='1' height='1'>Text"#,
+                Some("Text"),
+            ),
+            (r#"<img src="cid:logo" width="1" height="1">"#, None),
+            (r#"<p>No images here</p>"#, None),
+        ] {
+            assert_eq!(strip_tracking_pixels(html).as_deref(), expected, "{html}");
+        }
+    }
+
+    #[test]
+    fn test_hidden_styles() {
+        for (style, expected) in [
+            ("display: none", true),
+            ("color: red; visibility:hidden", true),
+            ("font-size:0px", true),
+            ("opacity: 0.0", true),
+            ("max-height: 0", true),
+            ("font-size: 12px", false),
+            ("display: block !important", false),
+        ] {
+            assert_eq!(is_hidden_style(style), expected, "{style}");
+        }
+    }
 }
@@ -14,6 +14,7 @@ pub mod bayes;
 pub mod dnsbl;
 pub mod expression;
 pub mod html;
+pub mod office;
 pub mod pyzor;
 pub mod sanitize;
 
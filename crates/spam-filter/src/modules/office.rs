@@ -0,0 +1,298 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::io::{Cursor, Read};
+
+const OLE_SIGNATURE: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+const PE_STUB: &[u8] = b"This program cannot be run in DOS mode";
+const MAX_EMBEDDED_SIZE: u64 = 10 * 1024 * 1024;
+const MAX_EMBEDDED_OBJECTS: usize = 32;
+
+const OLE_MACRO_STREAMS: &[&str] = &[
+    "_VBA_PROJECT_CUR",
+    "_VBA_PROJECT",
+    "VBA",
+    "Macros",
+    "PROJECTwm",
+];
+const OLE_NATIVE_STREAM: &str = "\u{1}Ole10Native";
+// Word keeps embedded objects under ObjectPool, Excel in MBD* storages
+const OLE_OBJECT_POOL: &str = "ObjectPool";
+const OLE_OBJECT_STORAGE_PREFIX: &str = "MBD";
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    ".exe", ".scr", ".com", ".pif", ".bat", ".cmd", ".js", ".jse", ".vbs", ".vbe", ".wsf", ".ps1",
+    ".hta", ".lnk", ".dll", ".msi", ".cpl", ".jar",
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct OfficeInspection {
+    pub has_macros: bool,
+    pub has_ole_objects: bool,
+    pub has_executable: bool,
+}
+
+// Inspects OOXML (docm, xlsm, pptm) and legacy OLE (doc, xls, ppt) documents,
+// returns None if the contents are not an Office document
+pub fn inspect_office_document(bytes: &[u8]) -> Option<OfficeInspection> {
+    if bytes.starts_with(OLE_SIGNATURE) {
+        Some(inspect_ole(bytes))
+    } else if bytes.starts_with(ZIP_SIGNATURE) {
+        inspect_ooxml(bytes)
+    } else {
+        None
+    }
+}
+
+fn inspect_ooxml(bytes: &[u8]) -> Option<OfficeInspection> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).ok()?;
+    let mut result = OfficeInspection::default();
+    let mut is_office = false;
+    let mut embedded_objects = Vec::new();
+
+    for name in archive.file_names() {
+        let name = name.to_ascii_lowercase();
+        let file_name = name.rsplit_once('/').map(|(_, n)| n).unwrap_or(&name);
+
+        if name == "[content_types].xml" {
+            is_office = true;
+        } else if file_name == "vbaproject.bin" || file_name == "vbadata.xml" {
+            result.has_macros = true;
+        } else if name.contains("/embeddings/") || name.contains("/activex/") {
+            result.has_ole_objects = true;
+
+            if EXECUTABLE_EXTENSIONS
+                .iter()
+                .any(|ext| file_name.ends_with(ext))
+            {
+                result.has_executable = true;
+            } else if file_name.ends_with(".bin") && embedded_objects.len() < MAX_EMBEDDED_OBJECTS {
+                embedded_objects.push(name.clone());
+            }
+        }
+    }
+
+    if !is_office {
+        return None;
+    }
+
+    // Look for executables inside embedded OLE objects
+    if !result.has_executable {
+        let names = archive
+            .file_names()
+            .filter(|name| embedded_objects.contains(&name.to_ascii_lowercase()))
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+
+        for name in names {
+            if let Ok(file) = archive.by_name(&name) {
+                let mut contents = Vec::new();
+                if file
+                    .take(MAX_EMBEDDED_SIZE)
+                    .read_to_end(&mut contents)
+                    .is_ok()
+                    && contents.starts_with(OLE_SIGNATURE)
+                {
+                    let embedded = inspect_ole(&contents);
+                    result.has_macros |= embedded.has_macros;
+                    if embedded.has_executable {
+                        result.has_executable = true;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    Some(result)
+}
+
+fn inspect_ole(bytes: &[u8]) -> OfficeInspection {
+    let mut result = OfficeInspection::default();
+    let Ok(mut file) = cfb::CompoundFile::open(Cursor::new(bytes)) else {
+        return result;
+    };
+
+    // Match the compound file directory entries by name
+    let mut native_streams = Vec::new();
+    for entry in file.walk() {
+        let name = entry.name();
+        if OLE_MACRO_STREAMS.contains(&name) {
+            result.has_macros = true;
+        } else if name == OLE_NATIVE_STREAM
+            || (entry.is_storage() && name.starts_with(OLE_OBJECT_STORAGE_PREFIX))
+            || entry
+                .path()
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .is_some_and(|parent| parent == OLE_OBJECT_POOL)
+        {
+            result.has_ole_objects = true;
+        }
+
+        if entry.is_stream()
+            && name == OLE_NATIVE_STREAM
+            && native_streams.len() < MAX_EMBEDDED_OBJECTS
+        {
+            native_streams.push(entry.path().to_path_buf());
+        }
+    }
+
+    // Look for executables inside embedded packages
+    for path in native_streams {
+        let mut contents = Vec::new();
+        if file
+            .open_stream(&path)
+            .and_then(|stream| stream.take(MAX_EMBEDDED_SIZE).read_to_end(&mut contents))
+            .is_ok()
+            && (contains(&contents, PE_STUB)
+                || EXECUTABLE_EXTENSIONS.iter().any(|ext| {
+                    let mut ext = ext.as_bytes().to_vec();
+                    ext.push(0);
+                    contains_ignore_case(&contents, &ext)
+                }))
+        {
+            result.has_executable = true;
+            break;
+        }
+    }
+
+    result
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    !needle.is_empty() && haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+fn contains_ignore_case(haystack: &[u8], needle: &[u8]) -> bool {
+    !needle.is_empty()
+        && haystack
+            .windows(needle.len())
+            .any(|w| w.eq_ignore_ascii_case(needle))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Write, path::Path};
+
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    fn build_zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn build_ole<T: AsRef<[u8]>>(streams: &[(&str, T)]) -> Vec<u8> {
+        let mut ole = cfb::CompoundFile::create(Cursor::new(Vec::new())).unwrap();
+        for (path, contents) in streams {
+            let path = Path::new(path);
+            if let Some(parent) = path.parent().filter(|parent| *parent != Path::new("/")) {
+                ole.create_storage_all(parent).unwrap();
+            }
+            let mut stream = ole.create_stream(path).unwrap();
+            stream.write_all(contents.as_ref()).unwrap();
+            stream.flush().unwrap();
+        }
+        ole.flush().unwrap();
+        ole.into_inner().into_inner()
+    }
+
+    #[test]
+    fn inspect_office_documents() {
+        // Plain OOXML document
+        let docx = build_zip(&[
+            ("[Content_Types].xml", b"<Types/>".as_slice()),
+            ("word/document.xml", b"<w:document/>".as_slice()),
+        ]);
+        assert_eq!(
+            inspect_office_document(&docx),
+            Some(OfficeInspection::default())
+        );
+
+        // Macro-enabled OOXML document
+        let docm = build_zip(&[
+            ("[Content_Types].xml", b"<Types/>".as_slice()),
+            ("word/document.xml", b"<w:document/>".as_slice()),
+            (
+                "word/vbaProject.bin",
+                build_ole(&[("/VBA/_VBA_PROJECT", b"")]).as_slice(),
+            ),
+        ]);
+        assert_eq!(
+            inspect_office_document(&docm),
+            Some(OfficeInspection {
+                has_macros: true,
+                ..Default::default()
+            })
+        );
+
+        // OOXML document with an embedded executable
+        let xlsx = build_zip(&[
+            ("[Content_Types].xml", b"<Types/>".as_slice()),
+            (
+                "xl/embeddings/oleObject1.bin",
+                build_ole(&[("/\u{1}Ole10Native", b"invoice.exe\0MZ")]).as_slice(),
+            ),
+        ]);
+        assert_eq!(
+            inspect_office_document(&xlsx),
+            Some(OfficeInspection {
+                has_macros: false,
+                has_ole_objects: true,
+                has_executable: true,
+            })
+        );
+
+        // Legacy OLE document with macros
+        assert_eq!(
+            inspect_office_document(&build_ole(&[("/Macros/VBA/_VBA_PROJECT", b"")])),
+            Some(OfficeInspection {
+                has_macros: true,
+                ..Default::default()
+            })
+        );
+
+        // Legacy OLE document mentioning macros in its text
+        let text = "Enable VBA Macros"
+            .encode_utf16()
+            .flat_map(|ch| ch.to_le_bytes())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            inspect_office_document(&build_ole(&[
+                ("/\u{1}CompObj", b"".as_slice()),
+                ("/WordDocument", text.as_slice()),
+            ])),
+            Some(OfficeInspection::default())
+        );
+
+        // Legacy OLE document with an embedded object
+        assert_eq!(
+            inspect_office_document(&build_ole(&[(
+                "/ObjectPool/_1234/\u{1}Ole",
+                b"".as_slice()
+            )])),
+            Some(OfficeInspection {
+                has_ole_objects: true,
+                ..Default::default()
+            })
+        );
+
+        // Regular archive
+        assert_eq!(
+            inspect_office_document(&build_zip(&[("readme.txt", b"hello".as_slice())])),
+            None
+        );
+        assert_eq!(inspect_office_document(b"%PDF-1.4"), None);
+    }
+}
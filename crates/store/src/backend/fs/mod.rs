@@ -15,13 +15,16 @@ use utils::{
     config::{Config, utils::AsKey},
 };
 
+const TMP_EXTENSION: &str = "tmp";
+
 pub struct FsStore {
     path: PathBuf,
     hash_levels: usize,
+    fsync: bool,
 }
 
 impl FsStore {
-    pub async fn open(config: &mut Config, prefix: impl AsKey) -> Option<Self> {
+    pub async fn open(config: &mut Config, prefix: impl AsKey, fsync: bool) -> Option<Self> {
         let prefix = prefix.as_key();
         let path = PathBuf::from(config.value_require((&prefix, "path"))?);
         if !path.exists() {
@@ -36,7 +39,7 @@ impl FsStore {
                 .ok()?;
         }
 
-        Some(FsStore {
+        let store = FsStore {
             path,
             hash_levels: std::cmp::min(
                 config
@@ -44,7 +47,22 @@ impl FsStore {
                     .unwrap_or(2),
                 5,
             ),
-        })
+            fsync: config
+                .property_or_default((&prefix, "fsync"), if fsync { "true" } else { "false" })
+                .unwrap_or(fsync),
+        };
+
+        // Remove temporary files left behind by writes interrupted by a crash
+        if store.fsync
+            && let Err(err) = store.remove_temporary_files().await
+        {
+            config.new_build_warning(
+                (&prefix, "path"),
+                format!("Failed to remove temporary files: {err}"),
+            );
+        }
+
+        Some(store)
     }
 
     pub(crate) async fn get_blob(
@@ -88,12 +106,34 @@ impl FsStore {
             .await
             .map_or(true, |m| m.len() as usize != data.len())
         {
-            fs::create_dir_all(blob_path.parent().unwrap())
-                .await
-                .map_err(into_error)?;
-            let mut blob_file = File::create(&blob_path).await.map_err(into_error)?;
-            blob_file.write_all(data).await.map_err(into_error)?;
-            blob_file.flush().await.map_err(into_error)?;
+            let blob_dir = blob_path.parent().unwrap();
+            fs::create_dir_all(blob_dir).await.map_err(into_error)?;
+
+            if self.fsync {
+                // Write to a temporary file first so a crash never leaves a truncated blob behind
+                let tmp_path = blob_path
+                    .with_extension(format!("{:x}.{TMP_EXTENSION}", rand::random::<u64>()));
+                let mut blob_file = File::create(&tmp_path).await.map_err(into_error)?;
+                blob_file.write_all(data).await.map_err(into_error)?;
+                blob_file.flush().await.map_err(into_error)?;
+                blob_file.sync_all().await.map_err(into_error)?;
+                drop(blob_file);
+                fs::rename(&tmp_path, &blob_path)
+                    .await
+                    .map_err(into_error)?;
+
+                // Persist the directory entry as well
+                File::open(blob_dir)
+                    .await
+                    .map_err(into_error)?
+                    .sync_all()
+                    .await
+                    .map_err(into_error)?;
+            } else {
+                let mut blob_file = File::create(&blob_path).await.map_err(into_error)?;
+                blob_file.write_all(data).await.map_err(into_error)?;
+                blob_file.flush().await.map_err(into_error)?;
+            }
         }
 
         Ok(())
@@ -109,6 +149,28 @@ impl FsStore {
         }
     }
 
+    async fn remove_temporary_files(&self) -> std::io::Result<()> {
+        let mut dirs = vec![(self.path.clone(), 0)];
+        while let Some((dir, depth)) = dirs.pop() {
+            let mut entries = fs::read_dir(&dir).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    if depth < self.hash_levels {
+                        dirs.push((path, depth + 1));
+                    }
+                } else if path
+                    .extension()
+                    .is_some_and(|extension| extension == TMP_EXTENSION)
+                {
+                    fs::remove_file(&path).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     fn build_path(&self, key: &[u8]) -> PathBuf {
         let mut path = self.path.clone();
 
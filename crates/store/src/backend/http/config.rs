@@ -6,17 +6,19 @@
 
 use std::{
     collections::hash_map::Entry,
+    str::FromStr,
     sync::atomic::{AtomicBool, AtomicU64},
     time::Duration,
 };
 
 use ahash::AHashMap;
 use arc_swap::ArcSwap;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use utils::config::Config;
 
 use crate::{InMemoryStore, Stores};
 
-use super::{HttpStore, HttpStoreConfig, HttpStoreFormat};
+use super::{HttpQuery, HttpStore, HttpStoreConfig, HttpStoreFormat};
 
 impl Stores {
     pub fn parse_http_stores(&mut self, config: &mut Config, is_reload: bool) {
@@ -35,6 +37,12 @@ impl Stores {
                 .unwrap_or_default()
             {
                 "list" => HttpStoreFormat::List,
+                "query" => {
+                    if let Some(query) = parse_http_query(config, id_) {
+                        self.http_queries.insert(id.clone(), query.into());
+                    }
+                    continue;
+                }
                 "csv" => HttpStoreFormat::Csv {
                     index_key: config
                         .property_require(("http-lookup", id_, "index.key"))
@@ -111,3 +119,81 @@ impl Stores {
         }
     }
 }
+
+fn parse_http_query(config: &mut Config, id: &str) -> Option<HttpQuery> {
+    let url = config.value_require(("http-lookup", id, "url"))?;
+    let url = reqwest::Url::parse(url)
+        .map_err(|err| {
+            config.new_parse_error(("http-lookup", id, "url"), format!("Invalid URL: {err}"))
+        })
+        .ok()?;
+
+    let mut headers = HeaderMap::new();
+    for (header, value) in config
+        .values(("http-lookup", id, "headers"))
+        .map(|(_, v)| {
+            if let Some((k, v)) = v.split_once(':') {
+                Ok((
+                    HeaderName::from_str(k.trim()).map_err(|err| {
+                        format!(
+                            "Invalid header found in property \"http-lookup.{id}.headers\": {err}",
+                        )
+                    })?,
+                    HeaderValue::from_str(v.trim()).map_err(|err| {
+                        format!(
+                            "Invalid header found in property \"http-lookup.{id}.headers\": {err}",
+                        )
+                    })?,
+                ))
+            } else {
+                Err(format!(
+                    "Invalid header found in property \"http-lookup.{id}.headers\": {v}",
+                ))
+            }
+        })
+        .collect::<Result<Vec<(HeaderName, HeaderValue)>, String>>()
+        .map_err(|e| config.new_parse_error(("http-lookup", id, "headers"), e))
+        .unwrap_or_default()
+    {
+        headers.insert(header, value);
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(
+            config
+                .property_or_default::<Duration>(("http-lookup", id, "timeout"), "10s")
+                .unwrap_or(Duration::from_secs(10)),
+        )
+        .danger_accept_invalid_certs(
+            config
+                .property_or_default::<bool>(
+                    ("http-lookup", id, "tls.allow-invalid-certs"),
+                    "false",
+                )
+                .unwrap_or(false),
+        )
+        .default_headers(headers)
+        .build()
+        .map_err(|err| {
+            config.new_build_error(
+                ("http-lookup", id),
+                format!("Failed to build HTTP client: {err}"),
+            )
+        })
+        .ok()?;
+
+    Some(HttpQuery {
+        id: id.to_string(),
+        url,
+        client,
+        max_size: config
+            .property_or_default::<usize>(("http-lookup", id, "limits.size"), "65536")
+            .unwrap_or(65536),
+        ttl_positive: config
+            .property_or_default::<Duration>(("http-lookup", id, "cache.ttl.positive"), "5m")
+            .unwrap_or(Duration::from_secs(300)),
+        ttl_negative: config
+            .property_or_default::<Duration>(("http-lookup", id, "cache.ttl.negative"), "1m")
+            .unwrap_or(Duration::from_secs(60)),
+    })
+}
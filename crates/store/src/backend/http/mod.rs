@@ -6,6 +6,7 @@
 
 pub mod config;
 pub mod lookup;
+pub mod query;
 
 use std::{
     sync::atomic::{AtomicBool, AtomicU64},
@@ -42,6 +43,16 @@ pub enum HttpStoreFormat {
     },
 }
 
+#[derive(Debug)]
+pub struct HttpQuery {
+    pub id: String,
+    pub url: reqwest::Url,
+    pub client: reqwest::Client,
+    pub max_size: usize,
+    pub ttl_positive: Duration,
+    pub ttl_negative: Duration,
+}
+
 #[derive(Debug)]
 pub struct HttpStore {
     pub entries: ArcSwap<AHashMap<String, Value<'static>>>,
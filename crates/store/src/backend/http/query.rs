@@ -0,0 +1,138 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use compact_str::ToCompactString;
+use reqwest::{Method, StatusCode, Url};
+use utils::HttpLimitResponse;
+
+use super::HttpQuery;
+
+impl HttpQuery {
+    // Scheme, host and port must match exactly, the path must start with the endpoint's path
+    pub fn matches(&self, url: &Url) -> bool {
+        if url.scheme() != self.url.scheme()
+            || url.host() != self.url.host()
+            || url.port_or_known_default() != self.url.port_or_known_default()
+            || !url.username().is_empty()
+            || url.password().is_some()
+        {
+            return false;
+        }
+
+        let prefix = self.url.path();
+        url.path()
+            .strip_prefix(prefix)
+            .is_some_and(|rest| prefix.ends_with('/') || rest.is_empty() || rest.starts_with('/'))
+    }
+
+    pub async fn query(&self, method: &str, url: &Url, body: &str) -> trc::Result<Option<String>> {
+        let time = Instant::now();
+        let method = if !method.is_empty() {
+            Method::from_bytes(method.to_ascii_uppercase().as_bytes()).map_err(|err| {
+                trc::StoreEvent::HttpStoreError
+                    .into_err()
+                    .reason(err)
+                    .ctx(trc::Key::Url, url.to_compact_string())
+                    .details("Invalid HTTP method")
+            })?
+        } else {
+            Method::GET
+        };
+
+        let mut request = self.client.request(method, url.clone());
+        if !body.is_empty() {
+            request = request.body(body.to_string());
+        }
+        let response = request.send().await.map_err(|err| {
+            trc::StoreEvent::HttpStoreError
+                .into_err()
+                .reason(err)
+                .ctx(trc::Key::Url, url.to_compact_string())
+                .ctx(trc::Key::Elapsed, time.elapsed())
+                .details("HTTP request failed")
+        })?;
+
+        match response.status() {
+            status if status.is_success() => {}
+            StatusCode::NOT_FOUND => return Ok(None),
+            status => {
+                trc::bail!(
+                    trc::StoreEvent::HttpStoreError
+                        .into_err()
+                        .ctx(trc::Key::Code, status.as_u16())
+                        .ctx(trc::Key::Url, url.to_compact_string())
+                        .ctx(trc::Key::Elapsed, time.elapsed())
+                        .details("HTTP query returned an error")
+                );
+            }
+        }
+
+        let bytes = response
+            .bytes_with_limit(self.max_size)
+            .await
+            .map_err(|err| {
+                trc::StoreEvent::HttpStoreError
+                    .into_err()
+                    .reason(err)
+                    .ctx(trc::Key::Url, url.to_compact_string())
+                    .ctx(trc::Key::Elapsed, time.elapsed())
+                    .details("Failed to fetch resource")
+            })?
+            .ok_or_else(|| {
+                trc::StoreEvent::HttpStoreError
+                    .into_err()
+                    .ctx(trc::Key::Url, url.to_compact_string())
+                    .ctx(trc::Key::Elapsed, time.elapsed())
+                    .details("Resource is too large")
+            })?;
+
+        Ok(Some(String::from_utf8_lossy(&bytes).trim().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use reqwest::Url;
+
+    use super::HttpQuery;
+
+    #[test]
+    fn http_query_url_matching() {
+        let query = HttpQuery {
+            id: "test".into(),
+            url: Url::parse("https://lookup.example.org/api").unwrap(),
+            client: reqwest::Client::new(),
+            max_size: 1024,
+            ttl_positive: Duration::from_secs(60),
+            ttl_negative: Duration::from_secs(60),
+        };
+
+        for (url, expected) in [
+            ("https://lookup.example.org/api", true),
+            ("https://lookup.example.org/api?q=1", true),
+            ("https://lookup.example.org/api/user", true),
+            ("https://LOOKUP.example.org:443/api/user", true),
+            ("https://lookup.example.org/api2", false),
+            ("https://lookup.example.org/other", false),
+            ("https://lookup.example.org/api/../admin", false),
+            ("https://lookup.example.org:8443/api", false),
+            ("http://lookup.example.org/api", false),
+            ("https://lookup.example.org.evil.com/api", false),
+            ("https://lookup.example.org@evil.com/api", false),
+            ("https://user@lookup.example.org/api", false),
+        ] {
+            assert_eq!(
+                query.matches(&Url::parse(url).unwrap()),
+                expected,
+                "failed for {url}"
+            );
+        }
+    }
+}
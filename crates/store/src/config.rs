@@ -181,7 +181,13 @@ impl Stores {
                     }
                 }
                 "fs" => {
-                    if let Some(db) = FsStore::open(config, prefix).await.map(BlobStore::from) {
+                    // Writes to the queue spool are synced to disk unless disabled
+                    let fsync = config.value("storage.queue") == Some(id)
+                        && config.value("storage.blob") != Some(id);
+                    if let Some(db) = FsStore::open(config, prefix, fsync)
+                        .await
+                        .map(BlobStore::from)
+                    {
                         self.blob_stores
                             .insert(store_id, db.with_compression(compression_algo));
                     }
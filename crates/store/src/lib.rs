@@ -22,7 +22,11 @@ pub use roaring;
 pub use xxhash_rust;
 
 use ahash::AHashMap;
-use backend::{fs::FsStore, http::HttpStore, memory::StaticMemoryStore};
+use backend::{
+    fs::FsStore,
+    http::{HttpQuery, HttpStore},
+    memory::StaticMemoryStore,
+};
 use utils::config::cron::SimpleCron;
 use write::{BitmapClass, ValueClass};
 
@@ -158,6 +162,7 @@ pub struct Stores {
     pub fts_stores: AHashMap<String, FtsStore>,
     pub in_memory_stores: AHashMap<String, InMemoryStore>,
     pub pubsub_stores: AHashMap<String, PubSubStore>,
+    pub http_queries: AHashMap<String, Arc<HttpQuery>>,
     pub purge_schedules: Vec<PurgeSchedule>,
 }
 
@@ -601,6 +606,16 @@ impl From<Value<'static>> for i64 {
     }
 }
 
+impl From<Value<'static>> for u32 {
+    fn from(value: Value<'static>) -> Self {
+        if let Value::Integer(value) = value {
+            value as u32
+        } else {
+            0
+        }
+    }
+}
+
 impl From<u64> for Value<'_> {
     fn from(value: u64) -> Self {
         Self::Integer(value as i64)
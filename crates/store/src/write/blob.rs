@@ -116,6 +116,10 @@ impl Store {
     }
 
     pub async fn purge_blobs(&self, blob_store: BlobStore) -> trc::Result<()> {
+        self.purge_blobs_from(&[blob_store]).await
+    }
+
+    pub async fn purge_blobs_from(&self, blob_stores: &[BlobStore]) -> trc::Result<()> {
         // Remove expired temporary blobs
         let from_key = ValueKey {
             account_id: 0,
@@ -204,10 +208,12 @@ impl Store {
         // Delete expired or unlinked blobs
         for (_, op) in &delete_keys {
             if let BlobOp::Commit { hash } = op {
-                blob_store
-                    .delete_blob(hash.as_ref())
-                    .await
-                    .caused_by(trc::location!())?;
+                for blob_store in blob_stores {
+                    blob_store
+                        .delete_blob(hash.as_ref())
+                        .await
+                        .caused_by(trc::location!())?;
+                }
             }
         }
 
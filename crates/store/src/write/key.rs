@@ -324,6 +324,37 @@ impl ValueClass {
                             .write(*due)
                     }
                 }
+                TaskQueueClass::SendLoginAlert { due, is_payload } => {
+                    if !*is_payload {
+                        serializer
+                            .write(*due)
+                            .write(account_id)
+                            .write(6u8)
+                            .write(document_id)
+                    } else {
+                        serializer
+                            .write(u64::MAX)
+                            .write(account_id)
+                            .write(7u8)
+                            .write(document_id)
+                            .write(*due)
+                    }
+                }
+                TaskQueueClass::Unsnooze { due } => serializer
+                    .write(*due)
+                    .write(account_id)
+                    .write(8u8)
+                    .write(document_id),
+                TaskQueueClass::SendQuotaWarning { due } => serializer
+                    .write(*due)
+                    .write(account_id)
+                    .write(9u8)
+                    .write(document_id),
+                TaskQueueClass::SendMdn { due } => serializer
+                    .write(*due)
+                    .write(account_id)
+                    .write(10u8)
+                    .write(document_id),
             },
             ValueClass::Blob(op) => match op {
                 BlobOp::Reserve { hash, until } => serializer
@@ -598,7 +629,11 @@ impl ValueClass {
                     (BLOB_HASH_LEN + U64_LEN * 2) + 1
                 }
                 TaskQueueClass::SendAlarm { .. } => U64_LEN + (U32_LEN * 3) + 1,
-                TaskQueueClass::SendImip { is_payload, .. } => {
+                TaskQueueClass::Unsnooze { .. }
+                | TaskQueueClass::SendQuotaWarning { .. }
+                | TaskQueueClass::SendMdn { .. } => U64_LEN + (U32_LEN * 2) + 1,
+                TaskQueueClass::SendImip { is_payload, .. }
+                | TaskQueueClass::SendLoginAlert { is_payload, .. } => {
                     if *is_payload {
                         (U64_LEN * 2) + (U32_LEN * 2) + 1
                     } else {
@@ -213,6 +213,19 @@ pub enum TaskQueueClass {
         due: u64,
         is_payload: bool,
     },
+    SendLoginAlert {
+        due: u64,
+        is_payload: bool,
+    },
+    Unsnooze {
+        due: u64,
+    },
+    SendQuotaWarning {
+        due: u64,
+    },
+    SendMdn {
+        due: u64,
+    },
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
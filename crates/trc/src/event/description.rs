@@ -191,6 +191,9 @@ impl HousekeeperEvent {
 impl TaskQueueEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            TaskQueueEvent::MdnFailed => "Failed to send read receipt",
+            TaskQueueEvent::MdnSent => "Read receipt sent",
+            TaskQueueEvent::MessageUnsnoozed => "Snoozed message returned to Inbox",
             TaskQueueEvent::TaskAcquired => "Task acquired from queue",
             TaskQueueEvent::TaskLocked => "Task is locked by another process",
             TaskQueueEvent::BlobNotFound => "Blob not found for task",
@@ -200,6 +203,9 @@ impl TaskQueueEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            TaskQueueEvent::MdnFailed => "A message disposition notification could not be sent",
+            TaskQueueEvent::MdnSent => "A message disposition notification was sent after the message was read",
+            TaskQueueEvent::MessageUnsnoozed => "A snoozed message was returned to the Inbox",
             TaskQueueEvent::TaskAcquired => "A task has been acquired from the queue",
             TaskQueueEvent::TaskLocked => "The task id is locked by another process",
             TaskQueueEvent::BlobNotFound => "The requested blob was not found for task",
@@ -211,6 +217,7 @@ impl TaskQueueEvent {
 impl ImapEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            ImapEvent::SetQuota => "IMAP SETQUOTA command",
             ImapEvent::GetAcl => "IMAP GET ACL command",
             ImapEvent::SetAcl => "IMAP SET ACL command",
             ImapEvent::MyRights => "IMAP MYRIGHTS command",
@@ -253,6 +260,7 @@ impl ImapEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            ImapEvent::SetQuota => "Client changed the storage quota of an account",
             ImapEvent::GetAcl => "Client requested mailbox ACL",
             ImapEvent::SetAcl => "Client set mailbox ACL",
             ImapEvent::MyRights => "Client requested mailbox rights",
@@ -395,6 +403,25 @@ impl ManageSieveEvent {
 impl SmtpEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            SmtpEvent::FromHeaderUnauthorized => "From header unauthorized",
+            SmtpEvent::BounceTagMissing => "Bounce to unsigned address",
+            SmtpEvent::BounceTagInvalid => "Invalid bounce address tag",
+            SmtpEvent::NullSenderMultipleRecipients => "Null sender message with multiple recipients",
+            SmtpEvent::RelayHeadersRemoved => "Relay headers removed",
+            SmtpEvent::SmtpUtf8Required => "SMTPUTF8 required",
+            SmtpEvent::ContactsCollected => "Recipients added to collected addresses",
+            SmtpEvent::AttachmentOffloaded => "Attachment offloaded",
+            SmtpEvent::RecipientSpamRejected => "Message rejected for recipient",
+            SmtpEvent::UnexpectedPipelining => "Commands pipelined without PIPELINING",
+            SmtpEvent::BareLineFeed => "Bare line feed received",
+            SmtpEvent::EarlyTalker => "Client sent data before the greeting",
+            SmtpEvent::TooManyRecipientDomains => "Too many recipient domains",
+            SmtpEvent::BulkHeadersAdded => "Bulk sender headers added",
+            SmtpEvent::RcptToSuppressed => "Recipient is suppressed",
+            SmtpEvent::MessageTagged => "Message tagged",
+            SmtpEvent::DisclaimerAdded => "Disclaimer added",
+            SmtpEvent::DlpMatch => "DLP rule matched",
+            SmtpEvent::DlpEncryptionFailed => "DLP encryption failed",
             SmtpEvent::Error => "SMTP error occurred",
             SmtpEvent::IdNotFound => "Strategy not found",
             SmtpEvent::ConcurrencyLimitExceeded => "Concurrency limit exceeded",
@@ -482,6 +509,27 @@ impl SmtpEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            SmtpEvent::FromHeaderUnauthorized => "The From header of a submitted message does not match the envelope sender",
+            SmtpEvent::BounceTagMissing => "A bounce was sent to a local address that does not carry a BATV tag",
+            SmtpEvent::BounceTagInvalid => "A bounce was sent to a signed return path with an invalid or expired BATV tag",
+            SmtpEvent::NullSenderMultipleRecipients => "A message with an empty return path was sent to more than one recipient",
+            SmtpEvent::RelayHeadersRemoved => "Headers identifying the user agent were removed from a message sent by a trusted relay",
+            SmtpEvent::SmtpUtf8Required => "The address contains non-ASCII characters but SMTPUTF8 was not requested",
+            SmtpEvent::ContactsCollected => "The recipients of a message sent by an authenticated user were added to the user's collected addresses book.",
+            SmtpEvent::AttachmentOffloaded => "A large attachment was replaced with a download link.",
+            SmtpEvent::RecipientSpamRejected => "The message was rejected for one of its recipients based on the recipient's filtering profile",
+            SmtpEvent::UnexpectedPipelining => "The remote client pipelined commands without having negotiated the PIPELINING extension",
+            SmtpEvent::BareLineFeed => "The remote client sent a line terminated by a bare LF instead of CRLF",
+            SmtpEvent::EarlyTalker => "The remote client sent data before the SMTP greeting was sent, which is typical of spam bots",
+            SmtpEvent::TooManyRecipientDomains => "The remote server exceeded the number of recipient domains allowed per transaction.",
+            SmtpEvent::BulkHeadersAdded => "Feedback-ID and List-Unsubscribe headers were added to an outgoing bulk message.",
+            SmtpEvent::RcptToSuppressed => "The recipient complained about messages from this sender domain and has been suppressed.",
+            SmtpEvent::MessageTagged => "The message subject or headers were tagged by a policy.",
+            SmtpEvent::DisclaimerAdded => "A disclaimer was added to the message.",
+            SmtpEvent::DlpMatch => "The message content matched a data loss prevention rule",
+            SmtpEvent::DlpEncryptionFailed => {
+                "The message had to be encrypted by a data loss prevention rule but it could not be encrypted for all recipients"
+            }
             SmtpEvent::Error => "An error occurred during an SMTP command",
             SmtpEvent::IdNotFound => "The strategy ID was not found in the configuration",
             SmtpEvent::ConcurrencyLimitExceeded => "The concurrency limit was exceeded",
@@ -605,6 +653,12 @@ impl SmtpEvent {
 impl DeliveryEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            DeliveryEvent::BinaryMimeDowngrade => "BINARYMIME downgrade",
+            DeliveryEvent::RequireTlsUnsupported => "REQUIRETLS not supported",
+            DeliveryEvent::TlsRequired => "TLS required by policy",
+            DeliveryEvent::SmtpUtf8Unsupported => "SMTPUTF8 not supported",
+            DeliveryEvent::SmtpUtf8Downgrade => "SMTPUTF8 downgrade",
+            DeliveryEvent::ProviderBackoff => "Provider backoff",
             DeliveryEvent::AttemptStart => "Delivery attempt started",
             DeliveryEvent::AttemptEnd => "Delivery attempt ended",
             DeliveryEvent::Completed => "Delivery completed",
@@ -648,6 +702,12 @@ impl DeliveryEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            DeliveryEvent::BinaryMimeDowngrade => "The remote host does not support BINARYMIME and the binary parts of the message were encoded as base64",
+            DeliveryEvent::RequireTlsUnsupported => "The remote host does not support REQUIRETLS and the message requires it",
+            DeliveryEvent::TlsRequired => "Delivery was refused because the destination domain requires a verified TLS connection and none could be established",
+            DeliveryEvent::SmtpUtf8Unsupported => "The remote host does not support SMTPUTF8 and the address cannot be converted to ASCII",
+            DeliveryEvent::SmtpUtf8Downgrade => "The remote host does not support SMTPUTF8 and the envelope was converted to ASCII",
+            DeliveryEvent::ProviderBackoff => "Delivery to a destination provider was paused after it deferred messages due to the sending rate.",
             DeliveryEvent::AttemptStart => "A new delivery attempt for the message has started",
             DeliveryEvent::AttemptEnd => "The delivery attempt has ended",
             DeliveryEvent::Completed => "Delivery was completed for all recipients",
@@ -707,6 +767,10 @@ impl DeliveryEvent {
 impl QueueEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            QueueEvent::MessageRejected => "Held message rejected",
+            QueueEvent::MessageApproved => "Held message approved",
+            QueueEvent::MessageExpired => "Held message expired",
+            QueueEvent::MessageHeld => "Message held for approval",
             QueueEvent::Rescheduled => "Message rescheduled for delivery",
             QueueEvent::Locked => "Queue event is locked by another process",
             QueueEvent::BlobNotFound => "Message blob not found",
@@ -724,6 +788,10 @@ impl QueueEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            QueueEvent::MessageRejected => "A moderator rejected the held message and it was bounced to the sender",
+            QueueEvent::MessageApproved => "A moderator approved the held message and it was released for delivery",
+            QueueEvent::MessageExpired => "The held message was not moderated before the approval window elapsed and it was bounced to the sender",
+            QueueEvent::MessageHeld => "The message was held in the queue pending moderator approval",
             QueueEvent::Rescheduled => "The message was rescheduled for delivery",
             QueueEvent::Locked => "The queue event is locked by another process",
             QueueEvent::BlobNotFound => "The message blob was not found",
@@ -747,6 +815,9 @@ impl QueueEvent {
 impl IncomingReportEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            IncomingReportEvent::Unsubscribe => "One-click unsubscribe request received",
+            IncomingReportEvent::RecipientSuppressed => "Recipient suppressed",
+            IncomingReportEvent::ComplaintReceived => "Complaint received",
             IncomingReportEvent::DmarcReport => "DMARC report received",
             IncomingReportEvent::DmarcReportWithWarnings => "DMARC report received with warnings",
             IncomingReportEvent::TlsReport => "TLS report received",
@@ -767,6 +838,9 @@ impl IncomingReportEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            IncomingReportEvent::Unsubscribe => "A recipient unsubscribed from a bulk sender using the one-click List-Unsubscribe link.",
+            IncomingReportEvent::RecipientSuppressed => "A complainant was added to the suppression list of the sending domain.",
+            IncomingReportEvent::ComplaintReceived => "A feedback loop complaint was received for a message sent from this server.",
             IncomingReportEvent::DmarcReport => "A DMARC report has been received",
             IncomingReportEvent::DmarcReportWithWarnings => {
                 "A DMARC report with warnings has been received"
@@ -797,6 +871,7 @@ impl IncomingReportEvent {
 impl OutgoingReportEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            OutgoingReportEvent::ReportSizeExceeded => "Report exceeds reporting address size limit",
             OutgoingReportEvent::SpfReport => "SPF report sent",
             OutgoingReportEvent::SpfRateLimited => "SPF report rate limited",
             OutgoingReportEvent::DkimReport => "DKIM report sent",
@@ -819,6 +894,7 @@ impl OutgoingReportEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            OutgoingReportEvent::ReportSizeExceeded => "The report was not sent to one or more reporting addresses because it exceeds the maximum size they accept.",
             OutgoingReportEvent::SpfReport => "An SPF report has been sent",
             OutgoingReportEvent::SpfRateLimited => "The SPF report was rate limited",
             OutgoingReportEvent::DkimReport => "A DKIM report has been sent",
@@ -845,6 +921,7 @@ impl OutgoingReportEvent {
 impl MtaStsEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            MtaStsEvent::PolicyFetchFallback => "MTA-STS policy fetch failed, falling back",
             MtaStsEvent::Authorized => "Host authorized by MTA-STS policy",
             MtaStsEvent::NotAuthorized => "Host not authorized by MTA-STS policy",
             MtaStsEvent::PolicyFetch => "Fetched MTA-STS policy",
@@ -856,6 +933,7 @@ impl MtaStsEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            MtaStsEvent::PolicyFetchFallback => "The MTA-STS policy could not be fetched and no cached policy was available, delivery continues according to the configured fallback.",
             MtaStsEvent::Authorized => "The host is authorized by the MTA-STS policy",
             MtaStsEvent::NotAuthorized => "The host is not authorized by the MTA-STS policy",
             MtaStsEvent::PolicyFetch => "The MTA-STS policy has been fetched",
@@ -1007,6 +1085,12 @@ impl PushSubscriptionEvent {
 impl SpamEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            SpamEvent::RspamdLearnError => "Rspamd training failed",
+            SpamEvent::RspamdLearn => "Rspamd training completed",
+            SpamEvent::RspamdError => "Rspamd check failed",
+            SpamEvent::Rspamd => "Rspamd check completed",
+            SpamEvent::SpamdError => "SpamAssassin check failed",
+            SpamEvent::Spamd => "SpamAssassin check completed",
             SpamEvent::Pyzor => "Pyzor success",
             SpamEvent::PyzorError => "Pyzor error",
             SpamEvent::Train => "Training spam filter",
@@ -1022,6 +1106,12 @@ impl SpamEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            SpamEvent::RspamdLearnError => "An error occurred while reporting the message to Rspamd for training.",
+            SpamEvent::RspamdLearn => "The message was reported to Rspamd for training.",
+            SpamEvent::RspamdError => "An error occurred while checking the message with an Rspamd server.",
+            SpamEvent::Rspamd => "The message was checked by an Rspamd server.",
+            SpamEvent::SpamdError => "An error occurred while checking the message with a SpamAssassin spamd server.",
+            SpamEvent::Spamd => "The message was checked by a SpamAssassin spamd server.",
             SpamEvent::PyzorError => "An error occurred with Pyzor",
             SpamEvent::Train => "The spam filter is being trained with the message",
             SpamEvent::TrainBalance => "The spam filter training data is verified for balance",
@@ -1039,6 +1129,9 @@ impl SpamEvent {
 impl SieveEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            SieveEvent::NotifyRateLimited => "Sieve notification rate limited",
+            SieveEvent::NotifyFailed => "Sieve notification failed",
+            SieveEvent::NotifySent => "Sieve notification sent",
             SieveEvent::ActionAccept => "Sieve action: Accept",
             SieveEvent::ActionAcceptReplace => "Sieve action: Accept and replace",
             SieveEvent::ActionDiscard => "Sieve action: Discard",
@@ -1056,6 +1149,9 @@ impl SieveEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            SieveEvent::NotifyRateLimited => "A Sieve notification was dropped because the account exceeded its notification rate",
+            SieveEvent::NotifyFailed => "A Sieve notification could not be delivered to its destination",
+            SieveEvent::NotifySent => "A Sieve script sent a notification using the notify action",
             SieveEvent::ActionAccept => "The Sieve script requested to accept the message",
             SieveEvent::ActionAcceptReplace => {
                 "The Sieve script requested to accept the message and replace its contents"
@@ -1101,6 +1197,7 @@ impl TlsEvent {
 impl NetworkEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            NetworkEvent::SessionTerminated => "Session terminated",
             NetworkEvent::ListenStart => "Network listener started",
             NetworkEvent::ListenStop => "Network listener stopped",
             NetworkEvent::ListenError => "Network listener error",
@@ -1119,6 +1216,7 @@ impl NetworkEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            NetworkEvent::SessionTerminated => "A live session was terminated by an administrator.",
             NetworkEvent::ListenStart => "The network listener has started",
             NetworkEvent::ListenStop => "The network listener has stopped",
             NetworkEvent::ListenError => "An error occurred with the network listener",
@@ -1165,6 +1263,7 @@ impl ServerEvent {
 impl TelemetryEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            TelemetryEvent::SyslogError => "Syslog collector error",
             TelemetryEvent::Alert => "Alert triggered",
             TelemetryEvent::LogError => "Log collector error",
             TelemetryEvent::WebhookError => "Webhook collector error",
@@ -1177,6 +1276,7 @@ impl TelemetryEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            TelemetryEvent::SyslogError => "An error occurred with the syslog collector",
             TelemetryEvent::Alert => "An alert was triggered",
             TelemetryEvent::LogError => "An error occurred with the log collector",
             TelemetryEvent::WebhookError => "An error occurred with the webhook collector",
@@ -1263,6 +1363,7 @@ impl AcmeEvent {
 impl PurgeEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            PurgeEvent::LegalHold => "Purge skipped due to legal hold",
             PurgeEvent::Started => "Purge started",
             PurgeEvent::Finished => "Purge finished",
             PurgeEvent::Running => "Purge running",
@@ -1275,6 +1376,7 @@ impl PurgeEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            PurgeEvent::LegalHold => "Deleted messages were not purged because the account is under legal hold",
             PurgeEvent::Started => "The purge has started",
             PurgeEvent::Finished => "The purge has finished",
             PurgeEvent::Running => "The purge is running",
@@ -1605,6 +1707,13 @@ impl StoreEvent {
 impl MessageIngestEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            MessageIngestEvent::Forwarded => "Message forwarded",
+            MessageIngestEvent::ForwardLoop => "Forwarding loop detected",
+            MessageIngestEvent::MdnSuppressed => "Read receipt request suppressed",
+            MessageIngestEvent::Recalled => "Message recalled",
+            MessageIngestEvent::ImportError => "IMAP import failed",
+            MessageIngestEvent::ImportComplete => "IMAP import completed",
+            MessageIngestEvent::ImportStart => "IMAP import started",
             MessageIngestEvent::Ham => "Message ingested",
             MessageIngestEvent::Spam => "Possible spam message ingested",
             MessageIngestEvent::ImapAppend => "Message appended via IMAP",
@@ -1617,6 +1726,13 @@ impl MessageIngestEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            MessageIngestEvent::Forwarded => "A message was forwarded to the addresses configured by the recipient",
+            MessageIngestEvent::ForwardLoop => "A message was not forwarded because it was already forwarded by the recipient or exceeded the maximum number of hops",
+            MessageIngestEvent::MdnSuppressed => "A read receipt request from an external sender was suppressed",
+            MessageIngestEvent::Recalled => "A message was removed from the mailboxes of its local recipients at the request of its sender",
+            MessageIngestEvent::ImportError => "An import of messages from a remote IMAP server failed",
+            MessageIngestEvent::ImportComplete => "An import of messages from a remote IMAP server completed",
+            MessageIngestEvent::ImportStart => "An import of messages from a remote IMAP server started",
             MessageIngestEvent::Ham => "The message has been ingested",
             MessageIngestEvent::Spam => "A possible spam message has been ingested",
             MessageIngestEvent::ImapAppend => "The message has been appended via IMAP",
@@ -1689,6 +1805,10 @@ impl JmapEvent {
 impl LimitEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            LimitEvent::QuotaWarningFailed => "Quota warning failed",
+            LimitEvent::QuotaWarningSent => "Quota warning sent",
+            LimitEvent::IpConnections => "Too many connections from IP address",
+            LimitEvent::AccountConnections => "Too many connections for account",
             LimitEvent::SizeRequest => "Request size limit reached",
             LimitEvent::SizeUpload => "Upload size limit reached",
             LimitEvent::CallsIn => "Incoming calls limit reached",
@@ -1704,6 +1824,10 @@ impl LimitEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            LimitEvent::QuotaWarningFailed => "A quota usage warning could not be sent",
+            LimitEvent::QuotaWarningSent => "A quota usage warning was sent to the account",
+            LimitEvent::IpConnections => "The remote IP address exceeded the maximum number of concurrent connections allowed for the protocol",
+            LimitEvent::AccountConnections => "The account exceeded the maximum number of concurrent connections allowed for the protocol",
             LimitEvent::SizeRequest => "The request size limit has been reached",
             LimitEvent::SizeUpload => "The upload size limit has been reached",
             LimitEvent::CallsIn => "The incoming calls limit has been reached",
@@ -1745,6 +1869,8 @@ impl ManageEvent {
 impl AuthEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            AuthEvent::LoginAlertFailed => "Login alert failed",
+            AuthEvent::LoginAlertSent => "Login alert sent",
             AuthEvent::Success => "Authentication successful",
             AuthEvent::Failed => "Authentication failed",
             AuthEvent::MissingTotp => "Missing TOTP for authentication",
@@ -1757,6 +1883,8 @@ impl AuthEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            AuthEvent::LoginAlertFailed => "A notification about a login from a new location or device could not be sent",
+            AuthEvent::LoginAlertSent => "A notification about a login from a new location or device was sent",
             AuthEvent::Success => "Successful authentication",
             AuthEvent::Failed => "Failed authentication",
             AuthEvent::MissingTotp => "TOTP is missing for authentication",
@@ -1793,6 +1921,12 @@ impl ResourceEvent {
 impl SecurityEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            SecurityEvent::SpamTrapBan => "Banned due to spam trap hit",
+            SecurityEvent::AccountUnlocked => "Account lockout cleared",
+            SecurityEvent::AccountLocked => "Account locked after repeated authentication failures",
+            SecurityEvent::MessageRedacted => "Message redacted",
+            SecurityEvent::LegalHoldReleased => "Legal hold lifted from account",
+            SecurityEvent::LegalHoldSet => "Legal hold placed on account",
             SecurityEvent::AuthenticationBan => "Banned due to authentication errors",
             SecurityEvent::AbuseBan => "Banned due to abuse",
             SecurityEvent::LoiterBan => "Banned due to loitering",
@@ -1804,6 +1938,12 @@ impl SecurityEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            SecurityEvent::SpamTrapBan => "The remote IP address has been temporarily banned after sending a message to a spam trap address",
+            SecurityEvent::AccountUnlocked => "An administrator cleared the lockout of an account",
+            SecurityEvent::AccountLocked => "The account has been temporarily locked after too many failed authentication attempts",
+            SecurityEvent::MessageRedacted => "A message was removed or replaced with a redaction notice by an administrator",
+            SecurityEvent::LegalHoldReleased => "A legal hold was lifted from an account, deleted data will be purged normally",
+            SecurityEvent::LegalHoldSet => "A legal hold was placed on an account, deleted data will be retained until the hold is lifted",
             SecurityEvent::AuthenticationBan => {
                 "IP address was banned due to multiple authentication errors"
             }
@@ -1888,6 +2028,8 @@ impl WebDavEvent {
 impl CalendarEvent {
     pub fn description(&self) -> &'static str {
         match self {
+            CalendarEvent::OutOfOfficeDisabled => "Out of office disabled",
+            CalendarEvent::OutOfOfficeEnabled => "Out of office enabled",
             CalendarEvent::RuleExpansionError => "Calendar rule expansion error",
             CalendarEvent::AlarmSent => "Calendar alarm sent",
             CalendarEvent::AlarmSkipped => "Calendar alarm skipped",
@@ -1901,6 +2043,8 @@ impl CalendarEvent {
 
     pub fn explain(&self) -> &'static str {
         match self {
+            CalendarEvent::OutOfOfficeDisabled => "The vacation response enabled from an out of office calendar event was disabled",
+            CalendarEvent::OutOfOfficeEnabled => "The vacation response was enabled from an out of office calendar event",
             CalendarEvent::RuleExpansionError => {
                 "An error occurred while expanding calendar recurrences"
             }
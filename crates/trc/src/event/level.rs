@@ -50,6 +50,7 @@ impl EventType {
             },
             EventType::Jmap(_) => Level::Debug,
             EventType::Imap(event) => match event {
+                ImapEvent::SetQuota => Level::Debug,
                 ImapEvent::ConnectionStart | ImapEvent::ConnectionEnd => Level::Debug,
                 ImapEvent::GetAcl
                 | ImapEvent::SetAcl
@@ -124,6 +125,25 @@ impl EventType {
                 Pop3Event::RawInput | Pop3Event::RawOutput => Level::Trace,
             },
             EventType::Smtp(event) => match event {
+                SmtpEvent::FromHeaderUnauthorized => Level::Info,
+                SmtpEvent::BounceTagMissing => Level::Info,
+                SmtpEvent::BounceTagInvalid => Level::Info,
+                SmtpEvent::NullSenderMultipleRecipients => Level::Info,
+                SmtpEvent::RelayHeadersRemoved => Level::Info,
+                SmtpEvent::SmtpUtf8Required => Level::Info,
+                SmtpEvent::ContactsCollected => Level::Info,
+                SmtpEvent::AttachmentOffloaded => Level::Info,
+                SmtpEvent::EarlyTalker
+                | SmtpEvent::BareLineFeed
+                | SmtpEvent::UnexpectedPipelining
+                | SmtpEvent::RecipientSpamRejected => Level::Info,
+                SmtpEvent::TooManyRecipientDomains => Level::Info,
+                SmtpEvent::BulkHeadersAdded => Level::Debug,
+                SmtpEvent::RcptToSuppressed => Level::Info,
+                SmtpEvent::MessageTagged => Level::Info,
+                SmtpEvent::DisclaimerAdded => Level::Info,
+                SmtpEvent::DlpMatch => Level::Info,
+                SmtpEvent::DlpEncryptionFailed => Level::Info,
                 SmtpEvent::ConnectionStart | SmtpEvent::ConnectionEnd => Level::Debug,
                 SmtpEvent::DidNotSayEhlo
                 | SmtpEvent::EhloExpected
@@ -205,6 +225,7 @@ impl EventType {
                 SmtpEvent::RawInput | SmtpEvent::RawOutput => Level::Trace,
             },
             EventType::Network(event) => match event {
+                NetworkEvent::SessionTerminated => Level::Info,
                 NetworkEvent::ReadError
                 | NetworkEvent::WriteError
                 | NetworkEvent::FlushError
@@ -218,6 +239,10 @@ impl EventType {
                 NetworkEvent::ProxyError => Level::Warn,
             },
             EventType::Limit(cause) => match cause {
+                LimitEvent::QuotaWarningFailed => Level::Warn,
+                LimitEvent::QuotaWarningSent => Level::Info,
+                LimitEvent::IpConnections => Level::Warn,
+                LimitEvent::AccountConnections => Level::Warn,
                 LimitEvent::SizeRequest => Level::Debug,
                 LimitEvent::SizeUpload => Level::Debug,
                 LimitEvent::CallsIn => Level::Debug,
@@ -233,9 +258,11 @@ impl EventType {
             EventType::Auth(cause) => match cause {
                 AuthEvent::Failed | AuthEvent::TokenExpired => Level::Debug,
                 AuthEvent::MissingTotp => Level::Trace,
-                AuthEvent::TooManyAttempts => Level::Warn,
+                AuthEvent::TooManyAttempts | AuthEvent::LoginAlertFailed => Level::Warn,
                 AuthEvent::Error => Level::Error,
-                AuthEvent::Success | AuthEvent::ClientRegistration => Level::Info,
+                AuthEvent::Success | AuthEvent::ClientRegistration | AuthEvent::LoginAlertSent => {
+                    Level::Info
+                }
             },
             EventType::Config(cause) => match cause {
                 ConfigEvent::ParseError
@@ -269,6 +296,7 @@ impl EventType {
             },
             EventType::MailAuth(_) => Level::Debug,
             EventType::Purge(event) => match event {
+                PurgeEvent::LegalHold => Level::Debug,
                 PurgeEvent::Started => Level::Debug,
                 PurgeEvent::Finished => Level::Debug,
                 PurgeEvent::Running => Level::Info,
@@ -330,16 +358,24 @@ impl EventType {
                 | SieveEvent::QuotaExceeded
                 | SieveEvent::ListNotFound
                 | SieveEvent::ScriptNotFound
-                | SieveEvent::MessageTooLarge => Level::Warn,
-                SieveEvent::SendMessage => Level::Info,
+                | SieveEvent::MessageTooLarge
+                | SieveEvent::NotifyRateLimited => Level::Warn,
+                SieveEvent::SendMessage | SieveEvent::NotifySent => Level::Info,
                 SieveEvent::UnexpectedError => Level::Error,
                 SieveEvent::ActionAccept
                 | SieveEvent::RuntimeError
                 | SieveEvent::ActionAcceptReplace
                 | SieveEvent::ActionDiscard
-                | SieveEvent::ActionReject => Level::Debug,
+                | SieveEvent::ActionReject
+                | SieveEvent::NotifyFailed => Level::Debug,
             },
             EventType::Spam(event) => match event {
+                SpamEvent::RspamdLearnError => Level::Warn,
+                SpamEvent::RspamdLearn => Level::Info,
+                SpamEvent::RspamdError => Level::Warn,
+                SpamEvent::Rspamd => Level::Info,
+                SpamEvent::SpamdError => Level::Warn,
+                SpamEvent::Spamd => Level::Info,
                 SpamEvent::PyzorError
                 | SpamEvent::TrainError
                 | SpamEvent::DnsblError
@@ -377,6 +413,9 @@ impl EventType {
                 HousekeeperEvent::Run | HousekeeperEvent::Schedule => Level::Debug,
             },
             EventType::TaskQueue(event) => match event {
+                TaskQueueEvent::MdnFailed => Level::Warn,
+                TaskQueueEvent::MdnSent => Level::Info,
+                TaskQueueEvent::MessageUnsnoozed => Level::Info,
                 TaskQueueEvent::BlobNotFound
                 | TaskQueueEvent::TaskAcquired
                 | TaskQueueEvent::TaskLocked
@@ -423,6 +462,12 @@ impl EventType {
                 | DaneEvent::TlsaRecordInvalid => Level::Info,
             },
             EventType::Delivery(event) => match event {
+                DeliveryEvent::BinaryMimeDowngrade => Level::Info,
+                DeliveryEvent::RequireTlsUnsupported => Level::Info,
+                DeliveryEvent::TlsRequired => Level::Warn,
+                DeliveryEvent::SmtpUtf8Unsupported => Level::Info,
+                DeliveryEvent::SmtpUtf8Downgrade => Level::Info,
+                DeliveryEvent::ProviderBackoff => Level::Info,
                 DeliveryEvent::AttemptStart
                 | DeliveryEvent::AttemptEnd
                 | DeliveryEvent::Completed
@@ -462,6 +507,10 @@ impl EventType {
                 DeliveryEvent::RawInput | DeliveryEvent::RawOutput => Level::Trace,
             },
             EventType::Queue(event) => match event {
+                QueueEvent::MessageRejected => Level::Info,
+                QueueEvent::MessageApproved => Level::Info,
+                QueueEvent::MessageExpired => Level::Info,
+                QueueEvent::MessageHeld => Level::Info,
                 QueueEvent::BackPressure => Level::Warn,
                 QueueEvent::QueueMessage
                 | QueueEvent::QueueMessageAuthenticated
@@ -480,6 +529,7 @@ impl EventType {
                 | TlsRptEvent::RecordNotFound => Level::Info,
             },
             EventType::MtaSts(event) => match event {
+                MtaStsEvent::PolicyFetchFallback => Level::Info,
                 MtaStsEvent::PolicyFetch
                 | MtaStsEvent::PolicyNotFound
                 | MtaStsEvent::PolicyFetchError
@@ -488,6 +538,9 @@ impl EventType {
                 | MtaStsEvent::Authorized => Level::Info,
             },
             EventType::IncomingReport(event) => match event {
+                IncomingReportEvent::Unsubscribe => Level::Info,
+                IncomingReportEvent::RecipientSuppressed => Level::Info,
+                IncomingReportEvent::ComplaintReceived => Level::Info,
                 IncomingReportEvent::DmarcReportWithWarnings
                 | IncomingReportEvent::TlsReportWithWarnings => Level::Warn,
                 IncomingReportEvent::DmarcReport
@@ -505,6 +558,7 @@ impl EventType {
                 | IncomingReportEvent::DecompressError => Level::Info,
             },
             EventType::OutgoingReport(event) => match event {
+                OutgoingReportEvent::ReportSizeExceeded => Level::Info,
                 OutgoingReportEvent::Locked | OutgoingReportEvent::NotFound => Level::Info,
                 OutgoingReportEvent::SpfReport
                 | OutgoingReportEvent::SpfRateLimited
@@ -522,6 +576,13 @@ impl EventType {
             },
             EventType::Telemetry(_) => Level::Warn,
             EventType::MessageIngest(event) => match event {
+                MessageIngestEvent::Forwarded => Level::Info,
+                MessageIngestEvent::ForwardLoop => Level::Info,
+                MessageIngestEvent::MdnSuppressed => Level::Debug,
+                MessageIngestEvent::Recalled => Level::Info,
+                MessageIngestEvent::ImportError => Level::Warn,
+                MessageIngestEvent::ImportComplete => Level::Info,
+                MessageIngestEvent::ImportStart => Level::Info,
                 MessageIngestEvent::Ham
                 | MessageIngestEvent::Spam
                 | MessageIngestEvent::ImapAppend
@@ -539,7 +600,9 @@ impl EventType {
             EventType::Calendar(event) => match event {
                 CalendarEvent::ItipMessageSent
                 | CalendarEvent::ItipMessageReceived
-                | CalendarEvent::AlarmSent => Level::Info,
+                | CalendarEvent::AlarmSent
+                | CalendarEvent::OutOfOfficeEnabled
+                | CalendarEvent::OutOfOfficeDisabled => Level::Info,
                 CalendarEvent::AlarmFailed => Level::Warn,
                 CalendarEvent::RuleExpansionError
                 | CalendarEvent::AlarmSkipped
@@ -208,7 +208,11 @@ impl Error {
             self.0.inner,
             EventType::Network(_)
                 | EventType::Auth(AuthEvent::TooManyAttempts)
-                | EventType::Limit(LimitEvent::ConcurrentRequest | LimitEvent::TooManyRequests)
+                | EventType::Limit(
+                    LimitEvent::ConcurrentRequest
+                        | LimitEvent::TooManyRequests
+                        | LimitEvent::AccountConnections
+                )
                 | EventType::Security(_)
         )
     }
@@ -522,6 +526,10 @@ impl LimitEvent {
             Self::BlobQuota => "Blob quota exceeded",
             Self::TooManyRequests => "Too many requests",
             Self::TenantQuota => "Tenant quota exceeded",
+            Self::AccountConnections => "Too many connections for account",
+            Self::IpConnections => "Too many connections from IP address",
+            Self::QuotaWarningSent => "Quota warning sent",
+            Self::QuotaWarningFailed => "Quota warning failed",
         }
     }
 }
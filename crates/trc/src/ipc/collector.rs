@@ -15,7 +15,7 @@ use atomics::bitset::AtomicBitset;
 use ipc::{
     USIZE_BITS,
     channel::{CHANNEL_FLAGS, CHANNEL_UPDATE_MARKER, Receiver},
-    subscriber::{Interests, Subscriber},
+    subscriber::{EventFilter, Interests, Subscriber},
 };
 use parking_lot::Mutex;
 
@@ -45,6 +45,7 @@ pub(crate) enum Update {
         id: String,
         interests: Interests,
         lossy: bool,
+        filter: Option<Arc<dyn EventFilter>>,
     },
     UpdateLevels {
         levels: AHashMap<EventType, Level>,
@@ -251,11 +252,13 @@ impl Collector {
                     id,
                     interests,
                     lossy,
+                    filter,
                 } => {
                     for subscriber in self.subscribers.iter_mut() {
                         if subscriber.id == id {
                             subscriber.interests = interests;
                             subscriber.lossy = lossy;
+                            subscriber.filter = filter;
                             break;
                         }
                     }
@@ -308,11 +311,17 @@ impl Collector {
             .push(Update::UpdateLevels { levels });
     }
 
-    pub fn update_subscriber(id: String, interests: Interests, lossy: bool) {
+    pub fn update_subscriber(
+        id: String,
+        interests: Interests,
+        lossy: bool,
+        filter: Option<Arc<dyn EventFilter>>,
+    ) {
         COLLECTOR_UPDATES.lock().push(Update::UpdateSubscriber {
             id,
             interests,
             lossy,
+            filter,
         });
     }
 
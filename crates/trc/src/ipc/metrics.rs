@@ -669,7 +669,8 @@ impl EventType {
                 | TelemetryEvent::OtelExporterError
                 | TelemetryEvent::OtelMetricsExporterError
                 | TelemetryEvent::PrometheusExporterError
-                | TelemetryEvent::JournalError,
+                | TelemetryEvent::JournalError
+                | TelemetryEvent::SyslogError,
             ) => true,
             EventType::Calendar(
                 CalendarEvent::AlarmSent
@@ -29,18 +29,30 @@ pub(crate) struct Subscriber {
     pub tx: mpsc::Sender<EventBatch>,
     pub lossy: bool,
     pub batch: EventBatch,
+    pub filter: Option<Arc<dyn EventFilter>>,
 }
 
 pub struct SubscriberBuilder {
     pub id: String,
     pub interests: Interests,
     pub lossy: bool,
+    pub filter: Option<Arc<dyn EventFilter>>,
+}
+
+// Called from the collector thread for every event the subscriber is interested in
+pub trait EventFilter: Send + Sync + std::fmt::Debug {
+    fn accept(&self, event: &Event<EventDetails>) -> bool;
 }
 
 impl Subscriber {
     #[inline(always)]
     pub fn push_event(&mut self, event_id: usize, trace: Arc<Event<EventDetails>>) {
-        if self.interests.get(event_id) {
+        if self.interests.get(event_id)
+            && self
+                .filter
+                .as_ref()
+                .is_none_or(|filter| filter.accept(&trace))
+        {
             self.batch.push(trace);
         }
     }
@@ -76,6 +88,7 @@ impl SubscriberBuilder {
             id,
             interests: Default::default(),
             lossy: true,
+            filter: None,
         }
     }
 
@@ -105,6 +118,11 @@ impl SubscriberBuilder {
         self
     }
 
+    pub fn with_filter(mut self, filter: Option<Arc<dyn EventFilter>>) -> Self {
+        self.filter = filter;
+        self
+    }
+
     pub fn register(self) -> (mpsc::Sender<EventBatch>, mpsc::Receiver<EventBatch>) {
         let (tx, rx) = mpsc::channel(8192);
 
@@ -115,6 +133,7 @@ impl SubscriberBuilder {
                 tx: tx.clone(),
                 lossy: self.lossy,
                 batch: Vec::new(),
+                filter: self.filter,
             },
         });
 
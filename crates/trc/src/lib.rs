@@ -211,6 +211,12 @@ pub enum SecurityEvent {
     LoiterBan,
     IpBlocked,
     Unauthorized,
+    LegalHoldSet,
+    LegalHoldReleased,
+    MessageRedacted,
+    AccountLocked,
+    AccountUnlocked,
+    SpamTrapBan,
 }
 
 #[event_type]
@@ -241,6 +247,9 @@ pub enum TaskQueueEvent {
     TaskLocked,
     BlobNotFound,
     MetadataNotFound,
+    MessageUnsnoozed,
+    MdnSent,
+    MdnFailed,
 }
 
 #[event_type]
@@ -288,6 +297,7 @@ pub enum ImapEvent {
     // Debugging
     RawInput,
     RawOutput,
+    SetQuota,
 }
 
 #[event_type]
@@ -431,6 +441,25 @@ pub enum SmtpEvent {
     UnsupportedParameter,
     SyntaxError,
     RequestTooLarge,
+    DlpMatch,
+    DlpEncryptionFailed,
+    DisclaimerAdded,
+    MessageTagged,
+    RcptToSuppressed,
+    BulkHeadersAdded,
+    TooManyRecipientDomains,
+    EarlyTalker,
+    BareLineFeed,
+    UnexpectedPipelining,
+    RecipientSpamRejected,
+    AttachmentOffloaded,
+    ContactsCollected,
+    SmtpUtf8Required,
+    RelayHeadersRemoved,
+    NullSenderMultipleRecipients,
+    BounceTagInvalid,
+    BounceTagMissing,
+    FromHeaderUnauthorized,
 }
 
 #[event_type]
@@ -473,6 +502,12 @@ pub enum DeliveryEvent {
     DsnPermFail,
     RawInput,
     RawOutput,
+    ProviderBackoff,
+    SmtpUtf8Downgrade,
+    SmtpUtf8Unsupported,
+    TlsRequired,
+    RequireTlsUnsupported,
+    BinaryMimeDowngrade,
 }
 
 #[event_type]
@@ -489,6 +524,10 @@ pub enum QueueEvent {
     ConcurrencyLimitExceeded,
     QuotaExceeded,
     BackPressure,
+    MessageHeld,
+    MessageApproved,
+    MessageRejected,
+    MessageExpired,
 }
 
 #[event_type]
@@ -508,6 +547,9 @@ pub enum IncomingReportEvent {
     TlsRpcParseFailed,
     ArfParseFailed,
     DecompressError,
+    ComplaintReceived,
+    RecipientSuppressed,
+    Unsubscribe,
 }
 
 #[event_type]
@@ -527,6 +569,7 @@ pub enum OutgoingReportEvent {
     SubmissionError,
     NoRecipientsFound,
     Locked,
+    ReportSizeExceeded,
 }
 
 #[event_type]
@@ -537,6 +580,7 @@ pub enum MtaStsEvent {
     PolicyNotFound,
     PolicyFetchError,
     InvalidPolicy,
+    PolicyFetchFallback,
 }
 
 #[event_type]
@@ -609,6 +653,12 @@ pub enum SpamEvent {
     Classify,
     ClassifyError,
     TrainAccount,
+    Spamd,
+    SpamdError,
+    Rspamd,
+    RspamdError,
+    RspamdLearn,
+    RspamdLearnError,
 }
 
 #[event_type]
@@ -625,6 +675,9 @@ pub enum SieveEvent {
     UnexpectedError,
     NotSupported,
     QuotaExceeded,
+    NotifySent,
+    NotifyFailed,
+    NotifyRateLimited,
 }
 
 #[event_type]
@@ -652,6 +705,7 @@ pub enum NetworkEvent {
     Closed,
     ProxyError,
     SetOptError,
+    SessionTerminated,
 }
 
 #[event_type]
@@ -672,6 +726,7 @@ pub enum TelemetryEvent {
     OtelMetricsExporterError,
     PrometheusExporterError,
     JournalError,
+    SyslogError,
 }
 
 #[event_type]
@@ -714,6 +769,7 @@ pub enum PurgeEvent {
     InProgress,
     AutoExpunge,
     TombstoneCleanup,
+    LegalHold,
 }
 
 #[event_type]
@@ -872,6 +928,13 @@ pub enum MessageIngestEvent {
     Duplicate,
     Error,
     FtsIndex,
+    ImportStart,
+    ImportComplete,
+    ImportError,
+    Recalled,
+    MdnSuppressed,
+    Forwarded,
+    ForwardLoop,
 }
 
 #[event_type]
@@ -919,6 +982,10 @@ pub enum LimitEvent {
     BlobQuota,
     TenantQuota,
     TooManyRequests,
+    AccountConnections,
+    IpConnections,
+    QuotaWarningSent,
+    QuotaWarningFailed,
 }
 
 #[event_type]
@@ -940,6 +1007,8 @@ pub enum AuthEvent {
     TooManyAttempts,
     ClientRegistration,
     Error,
+    LoginAlertSent,
+    LoginAlertFailed,
 }
 
 #[event_type]
@@ -992,6 +1061,8 @@ pub enum CalendarEvent {
     ItipMessageSent,
     ItipMessageReceived,
     ItipMessageError,
+    OutOfOfficeEnabled,
+    OutOfOfficeDisabled,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -893,6 +893,77 @@ impl EventType {
             EventType::Calendar(CalendarEvent::ItipMessageSent) => 583,
             EventType::Calendar(CalendarEvent::ItipMessageReceived) => 584,
             EventType::Calendar(CalendarEvent::ItipMessageError) => 585,
+            EventType::Smtp(SmtpEvent::DlpMatch) => 586,
+            EventType::Queue(QueueEvent::MessageHeld) => 587,
+            EventType::Queue(QueueEvent::MessageApproved) => 588,
+            EventType::Queue(QueueEvent::MessageRejected) => 589,
+            EventType::Smtp(SmtpEvent::DisclaimerAdded) => 590,
+            EventType::Smtp(SmtpEvent::MessageTagged) => 591,
+            EventType::Spam(SpamEvent::Spamd) => 592,
+            EventType::Spam(SpamEvent::SpamdError) => 593,
+            EventType::Spam(SpamEvent::Rspamd) => 594,
+            EventType::Spam(SpamEvent::RspamdError) => 595,
+            EventType::Spam(SpamEvent::RspamdLearn) => 596,
+            EventType::Spam(SpamEvent::RspamdLearnError) => 597,
+            EventType::Delivery(DeliveryEvent::ProviderBackoff) => 598,
+            EventType::IncomingReport(IncomingReportEvent::ComplaintReceived) => 599,
+            EventType::IncomingReport(IncomingReportEvent::RecipientSuppressed) => 600,
+            EventType::Smtp(SmtpEvent::RcptToSuppressed) => 601,
+            EventType::IncomingReport(IncomingReportEvent::Unsubscribe) => 602,
+            EventType::Smtp(SmtpEvent::BulkHeadersAdded) => 603,
+            EventType::OutgoingReport(OutgoingReportEvent::ReportSizeExceeded) => 604,
+            EventType::Smtp(SmtpEvent::TooManyRecipientDomains) => 605,
+            EventType::Security(SecurityEvent::LegalHoldSet) => 606,
+            EventType::Security(SecurityEvent::LegalHoldReleased) => 607,
+            EventType::Purge(PurgeEvent::LegalHold) => 608,
+            EventType::Security(SecurityEvent::MessageRedacted) => 609,
+            EventType::MessageIngest(MessageIngestEvent::ImportStart) => 610,
+            EventType::MessageIngest(MessageIngestEvent::ImportComplete) => 611,
+            EventType::MessageIngest(MessageIngestEvent::ImportError) => 612,
+            EventType::Limit(LimitEvent::AccountConnections) => 613,
+            EventType::Limit(LimitEvent::IpConnections) => 614,
+            EventType::Network(NetworkEvent::SessionTerminated) => 615,
+            EventType::Auth(AuthEvent::LoginAlertSent) => 616,
+            EventType::Auth(AuthEvent::LoginAlertFailed) => 617,
+            EventType::Security(SecurityEvent::AccountLocked) => 618,
+            EventType::Security(SecurityEvent::AccountUnlocked) => 619,
+            EventType::Security(SecurityEvent::SpamTrapBan) => 620,
+            EventType::Smtp(SmtpEvent::EarlyTalker) => 621,
+            EventType::Smtp(SmtpEvent::BareLineFeed) => 622,
+            EventType::Smtp(SmtpEvent::UnexpectedPipelining) => 623,
+            EventType::Smtp(SmtpEvent::RecipientSpamRejected) => 624,
+            EventType::Sieve(SieveEvent::NotifySent) => 625,
+            EventType::Sieve(SieveEvent::NotifyFailed) => 626,
+            EventType::Sieve(SieveEvent::NotifyRateLimited) => 627,
+            EventType::Calendar(CalendarEvent::OutOfOfficeEnabled) => 628,
+            EventType::Calendar(CalendarEvent::OutOfOfficeDisabled) => 629,
+            EventType::Smtp(SmtpEvent::AttachmentOffloaded) => 630,
+            EventType::Smtp(SmtpEvent::ContactsCollected) => 631,
+            EventType::TaskQueue(TaskQueueEvent::MessageUnsnoozed) => 632,
+            EventType::Limit(LimitEvent::QuotaWarningSent) => 633,
+            EventType::Limit(LimitEvent::QuotaWarningFailed) => 634,
+            EventType::Imap(ImapEvent::SetQuota) => 635,
+            EventType::Telemetry(TelemetryEvent::SyslogError) => 636,
+            EventType::Smtp(SmtpEvent::SmtpUtf8Required) => 637,
+            EventType::Delivery(DeliveryEvent::SmtpUtf8Downgrade) => 638,
+            EventType::Delivery(DeliveryEvent::SmtpUtf8Unsupported) => 639,
+            EventType::Smtp(SmtpEvent::RelayHeadersRemoved) => 640,
+            EventType::Smtp(SmtpEvent::NullSenderMultipleRecipients) => 641,
+            EventType::Smtp(SmtpEvent::BounceTagInvalid) => 642,
+            EventType::Smtp(SmtpEvent::BounceTagMissing) => 643,
+            EventType::MessageIngest(MessageIngestEvent::Recalled) => 644,
+            EventType::TaskQueue(TaskQueueEvent::MdnSent) => 645,
+            EventType::TaskQueue(TaskQueueEvent::MdnFailed) => 646,
+            EventType::MessageIngest(MessageIngestEvent::MdnSuppressed) => 647,
+            EventType::MessageIngest(MessageIngestEvent::Forwarded) => 648,
+            EventType::Smtp(SmtpEvent::FromHeaderUnauthorized) => 649,
+            EventType::MtaSts(MtaStsEvent::PolicyFetchFallback) => 650,
+            EventType::Delivery(DeliveryEvent::TlsRequired) => 651,
+            EventType::Delivery(DeliveryEvent::RequireTlsUnsupported) => 652,
+            EventType::Delivery(DeliveryEvent::BinaryMimeDowngrade) => 653,
+            EventType::MessageIngest(MessageIngestEvent::ForwardLoop) => 654,
+            EventType::Queue(QueueEvent::MessageExpired) => 655,
+            EventType::Smtp(SmtpEvent::DlpEncryptionFailed) => 656,
         }
     }
 
@@ -1524,6 +1595,77 @@ impl EventType {
             583 => Some(EventType::Calendar(CalendarEvent::ItipMessageSent)),
             584 => Some(EventType::Calendar(CalendarEvent::ItipMessageReceived)),
             585 => Some(EventType::Calendar(CalendarEvent::ItipMessageError)),
+            586 => Some(EventType::Smtp(SmtpEvent::DlpMatch)),
+            587 => Some(EventType::Queue(QueueEvent::MessageHeld)),
+            588 => Some(EventType::Queue(QueueEvent::MessageApproved)),
+            589 => Some(EventType::Queue(QueueEvent::MessageRejected)),
+            590 => Some(EventType::Smtp(SmtpEvent::DisclaimerAdded)),
+            591 => Some(EventType::Smtp(SmtpEvent::MessageTagged)),
+            592 => Some(EventType::Spam(SpamEvent::Spamd)),
+            593 => Some(EventType::Spam(SpamEvent::SpamdError)),
+            594 => Some(EventType::Spam(SpamEvent::Rspamd)),
+            595 => Some(EventType::Spam(SpamEvent::RspamdError)),
+            596 => Some(EventType::Spam(SpamEvent::RspamdLearn)),
+            597 => Some(EventType::Spam(SpamEvent::RspamdLearnError)),
+            598 => Some(EventType::Delivery(DeliveryEvent::ProviderBackoff)),
+            599 => Some(EventType::IncomingReport(IncomingReportEvent::ComplaintReceived)),
+            600 => Some(EventType::IncomingReport(IncomingReportEvent::RecipientSuppressed)),
+            601 => Some(EventType::Smtp(SmtpEvent::RcptToSuppressed)),
+            602 => Some(EventType::IncomingReport(IncomingReportEvent::Unsubscribe)),
+            603 => Some(EventType::Smtp(SmtpEvent::BulkHeadersAdded)),
+            604 => Some(EventType::OutgoingReport(OutgoingReportEvent::ReportSizeExceeded)),
+            605 => Some(EventType::Smtp(SmtpEvent::TooManyRecipientDomains)),
+            606 => Some(EventType::Security(SecurityEvent::LegalHoldSet)),
+            607 => Some(EventType::Security(SecurityEvent::LegalHoldReleased)),
+            608 => Some(EventType::Purge(PurgeEvent::LegalHold)),
+            609 => Some(EventType::Security(SecurityEvent::MessageRedacted)),
+            610 => Some(EventType::MessageIngest(MessageIngestEvent::ImportStart)),
+            611 => Some(EventType::MessageIngest(MessageIngestEvent::ImportComplete)),
+            612 => Some(EventType::MessageIngest(MessageIngestEvent::ImportError)),
+            613 => Some(EventType::Limit(LimitEvent::AccountConnections)),
+            614 => Some(EventType::Limit(LimitEvent::IpConnections)),
+            615 => Some(EventType::Network(NetworkEvent::SessionTerminated)),
+            616 => Some(EventType::Auth(AuthEvent::LoginAlertSent)),
+            617 => Some(EventType::Auth(AuthEvent::LoginAlertFailed)),
+            618 => Some(EventType::Security(SecurityEvent::AccountLocked)),
+            619 => Some(EventType::Security(SecurityEvent::AccountUnlocked)),
+            620 => Some(EventType::Security(SecurityEvent::SpamTrapBan)),
+            621 => Some(EventType::Smtp(SmtpEvent::EarlyTalker)),
+            622 => Some(EventType::Smtp(SmtpEvent::BareLineFeed)),
+            623 => Some(EventType::Smtp(SmtpEvent::UnexpectedPipelining)),
+            624 => Some(EventType::Smtp(SmtpEvent::RecipientSpamRejected)),
+            625 => Some(EventType::Sieve(SieveEvent::NotifySent)),
+            626 => Some(EventType::Sieve(SieveEvent::NotifyFailed)),
+            627 => Some(EventType::Sieve(SieveEvent::NotifyRateLimited)),
+            628 => Some(EventType::Calendar(CalendarEvent::OutOfOfficeEnabled)),
+            629 => Some(EventType::Calendar(CalendarEvent::OutOfOfficeDisabled)),
+            630 => Some(EventType::Smtp(SmtpEvent::AttachmentOffloaded)),
+            631 => Some(EventType::Smtp(SmtpEvent::ContactsCollected)),
+            632 => Some(EventType::TaskQueue(TaskQueueEvent::MessageUnsnoozed)),
+            633 => Some(EventType::Limit(LimitEvent::QuotaWarningSent)),
+            634 => Some(EventType::Limit(LimitEvent::QuotaWarningFailed)),
+            635 => Some(EventType::Imap(ImapEvent::SetQuota)),
+            636 => Some(EventType::Telemetry(TelemetryEvent::SyslogError)),
+            637 => Some(EventType::Smtp(SmtpEvent::SmtpUtf8Required)),
+            638 => Some(EventType::Delivery(DeliveryEvent::SmtpUtf8Downgrade)),
+            639 => Some(EventType::Delivery(DeliveryEvent::SmtpUtf8Unsupported)),
+            640 => Some(EventType::Smtp(SmtpEvent::RelayHeadersRemoved)),
+            641 => Some(EventType::Smtp(SmtpEvent::NullSenderMultipleRecipients)),
+            642 => Some(EventType::Smtp(SmtpEvent::BounceTagInvalid)),
+            643 => Some(EventType::Smtp(SmtpEvent::BounceTagMissing)),
+            644 => Some(EventType::MessageIngest(MessageIngestEvent::Recalled)),
+            645 => Some(EventType::TaskQueue(TaskQueueEvent::MdnSent)),
+            646 => Some(EventType::TaskQueue(TaskQueueEvent::MdnFailed)),
+            647 => Some(EventType::MessageIngest(MessageIngestEvent::MdnSuppressed)),
+            648 => Some(EventType::MessageIngest(MessageIngestEvent::Forwarded)),
+            649 => Some(EventType::Smtp(SmtpEvent::FromHeaderUnauthorized)),
+            650 => Some(EventType::MtaSts(MtaStsEvent::PolicyFetchFallback)),
+            651 => Some(EventType::Delivery(DeliveryEvent::TlsRequired)),
+            652 => Some(EventType::Delivery(DeliveryEvent::RequireTlsUnsupported)),
+            653 => Some(EventType::Delivery(DeliveryEvent::BinaryMimeDowngrade)),
+            654 => Some(EventType::MessageIngest(MessageIngestEvent::ForwardLoop)),
+            655 => Some(EventType::Queue(QueueEvent::MessageExpired)),
+            656 => Some(EventType::Smtp(SmtpEvent::DlpEncryptionFailed)),
             _ => None,
         }
     }
@@ -431,6 +431,14 @@ impl ParseValue for f64 {
     }
 }
 
+impl ParseValue for u8 {
+    fn parse_value(value: &str) -> super::Result<Self> {
+        value
+            .parse()
+            .map_err(|_| format!("Invalid integer value {:?}.", value))
+    }
+}
+
 impl ParseValue for u16 {
     fn parse_value(value: &str) -> super::Result<Self> {
         value
@@ -4,7 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{fmt::Display, sync::Arc};
+use std::{borrow::Cow, fmt::Display, net::IpAddr, sync::Arc};
 
 pub mod bimap;
 pub mod cache;
@@ -369,6 +369,45 @@ impl ServerCertVerifier for DummyVerifier {
     }
 }
 
+// Converts an IDN to its ASCII form (A-labels)
+pub fn domain_to_ascii(domain: &str) -> Option<Cow<'_, str>> {
+    if domain.is_ascii() {
+        Some(Cow::Borrowed(domain))
+    } else {
+        idna::domain_to_ascii(domain).ok().map(Cow::Owned)
+    }
+}
+
+// Domains are stored and looked up in their ASCII form, invalid IDNs are left as is
+pub fn normalize_domain(domain: &str) -> Cow<'_, str> {
+    domain_to_ascii(domain).unwrap_or(Cow::Borrowed(domain))
+}
+
+// Normalizes the domain of an address to its ASCII form, the local part is kept as is
+pub fn normalize_address(address: &str) -> Cow<'_, str> {
+    if let Some((local, domain)) = address.rsplit_once('@')
+        && let Cow::Owned(domain) = normalize_domain(domain)
+    {
+        Cow::Owned(format!("{local}@{domain}"))
+    } else {
+        Cow::Borrowed(address)
+    }
+}
+
+// Downgrades an address for hosts that do not support SMTPUTF8 (RFC 6531),
+// only the domain can be converted so non-ASCII local parts cannot be downgraded
+pub fn address_to_ascii(address: &str) -> Option<Cow<'_, str>> {
+    if address.is_ascii() {
+        Some(Cow::Borrowed(address))
+    } else if let Some((local, domain)) = address.rsplit_once('@')
+        && local.is_ascii()
+    {
+        domain_to_ascii(domain).map(|domain| Cow::Owned(format!("{local}@{domain}")))
+    } else {
+        None
+    }
+}
+
 // Basic email sanitizer
 pub fn sanitize_email(email: &str) -> Option<String> {
     let mut result = String::with_capacity(email.len());
@@ -402,8 +441,138 @@ pub fn sanitize_email(email: &str) -> Option<String> {
         && last_ch != '.'
         && psl::domain(result.as_bytes()).is_some_and(|d| d.suffix().typ().is_some())
     {
-        Some(result)
+        // Addresses are stored with ASCII domains
+        match normalize_address(&result) {
+            Cow::Owned(address) => Some(address),
+            Cow::Borrowed(_) => Some(result),
+        }
     } else {
         None
     }
 }
+
+// Addresses that are not reachable from the Internet, used to prevent
+// outbound requests from being directed at internal services
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let octets = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || octets[0] == 0
+                || (octets[0] == 100 && (octets[1] & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(ip));
+            }
+            let segments = ip.segments();
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+// Rebuilds a raw message replacing the (start, end) byte ranges with new contents,
+// ranges nested inside an already replaced range are skipped
+pub fn splice_bytes(bytes: &[u8], mut edits: Vec<(usize, usize, Vec<u8>)>) -> Option<Vec<u8>> {
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut result = Vec::with_capacity(
+        bytes.len() + edits.iter().map(|(_, _, bytes)| bytes.len()).sum::<usize>(),
+    );
+    let mut last_pos = 0;
+    for (start, end, replacement) in edits {
+        if start < last_pos {
+            continue;
+        }
+        result.extend_from_slice(bytes.get(last_pos..start)?);
+        result.extend_from_slice(&replacement);
+        last_pos = end;
+    }
+    result.extend_from_slice(bytes.get(last_pos..)?);
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{is_public_ip, normalize_address, normalize_domain, sanitize_email, splice_bytes};
+
+    #[test]
+    fn splice() {
+        let raw = b"Subject: test\r\n\r\nhello world\r\n";
+        assert_eq!(
+            splice_bytes(
+                raw,
+                vec![
+                    (17, 22, b"goodbye".to_vec()),
+                    (0, 0, b"X-Tag: 1\r\n".to_vec()),
+                    (18, 20, b"nested".to_vec()),
+                ],
+            )
+            .unwrap(),
+            b"X-Tag: 1\r\nSubject: test\r\n\r\ngoodbye world\r\n"
+        );
+        assert_eq!(splice_bytes(raw, vec![]).unwrap(), raw);
+        assert!(splice_bytes(raw, vec![(100, 101, vec![])]).is_none());
+    }
+
+    #[test]
+    fn idn_normalization() {
+        for (domain, expected) in [
+            ("example.org", "example.org"),
+            ("bücher.org", "xn--bcher-kva.org"),
+            ("BÜCHER.org", "xn--bcher-kva.org"),
+            ("xn--bcher-kva.org", "xn--bcher-kva.org"),
+        ] {
+            assert_eq!(normalize_domain(domain), expected, "{domain}");
+        }
+        for (address, expected) in [
+            ("jane@example.org", "jane@example.org"),
+            ("jane@bücher.org", "jane@xn--bcher-kva.org"),
+            ("jane@xn--bcher-kva.org", "jane@xn--bcher-kva.org"),
+            ("josé@bücher.org", "josé@xn--bcher-kva.org"),
+        ] {
+            assert_eq!(normalize_address(address), expected, "{address}");
+        }
+
+        // Unicode and punycode forms are stored identically
+        assert_eq!(
+            sanitize_email("Jane@Bücher.org").as_deref(),
+            Some("jane@xn--bcher-kva.org")
+        );
+        assert_eq!(
+            sanitize_email("jane@xn--bcher-kva.org").as_deref(),
+            Some("jane@xn--bcher-kva.org")
+        );
+    }
+
+    #[test]
+    fn public_ip() {
+        for (ip, expected) in [
+            ("8.8.8.8", true),
+            ("2001:4860:4860::8888", true),
+            ("127.0.0.1", false),
+            ("10.1.2.3", false),
+            ("172.16.0.1", false),
+            ("192.168.1.1", false),
+            ("169.254.169.254", false),
+            ("100.64.0.1", false),
+            ("0.0.0.0", false),
+            ("::1", false),
+            ("fd00::1", false),
+            ("fe80::1", false),
+            ("::ffff:127.0.0.1", false),
+        ] {
+            assert_eq!(is_public_ip(ip.parse().unwrap()), expected, "{ip}");
+        }
+    }
+}
@@ -0,0 +1,193 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use directory::{QueryParams, backend::RcptType};
+use http_proto::{JsonProblemResponse, JsonResponse, ToHttpResponse};
+use hyper::{Method, StatusCode};
+use mail_send::Credentials;
+use serde_json::json;
+
+use crate::{
+    directory::DirectoryTest,
+    http_server::{HttpMessage, spawn_mock_http_server},
+};
+
+static TEST_TOKEN: &str = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiJqZG9lIn0";
+
+#[tokio::test]
+async fn http_directory() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Obtain directory handle
+    let mut config = DirectoryTest::new("rocksdb".into()).await;
+    let core = config.server;
+
+    // Spawn mock directory service
+    let _tx = spawn_mock_http_server(Arc::new(|req: HttpMessage| {
+        let john = JsonResponse::new(json!({
+            "name": "john",
+            "description": "John Doe",
+            "secret": "12345",
+            "email": ["john@example.org", "john.doe@example.org"],
+            "quota": 1024,
+        }))
+        .into_http_response();
+        let not_found = JsonProblemResponse(StatusCode::NOT_FOUND).into_http_response();
+
+        match (req.method.clone(), req.uri.path()) {
+            (Method::GET, "/name/john") | (Method::GET, "/email/john@example.org") => john,
+            (Method::GET, "/name/jane") => JsonResponse::new(json!({
+                "name": "jane",
+                "description": "x".repeat(1024),
+            }))
+            .into_http_response(),
+            (Method::GET, _) => not_found,
+            (Method::POST, "/auth") => {
+                let body = serde_json::from_slice::<serde_json::Value>(
+                    req.body.as_deref().unwrap_or_default(),
+                )
+                .unwrap();
+                match req.headers.get("authorization") {
+                    Some(auth) if auth == &format!("Bearer {TEST_TOKEN}") => {
+                        assert_eq!(body, json!({}));
+                        john
+                    }
+                    Some(auth) if auth.starts_with("Bearer ") => {
+                        JsonProblemResponse(StatusCode::UNAUTHORIZED).into_http_response()
+                    }
+                    _ if body == json!({"username": "john", "secret": "12345"}) => john,
+                    _ => JsonProblemResponse(StatusCode::UNAUTHORIZED).into_http_response(),
+                }
+            }
+            _ => panic!("Unexpected request: {:?}", req),
+        }
+    }))
+    .await;
+
+    // Secrets are verified locally when there is no authentication endpoint
+    let handle = config.directories.directories.remove("http").unwrap();
+    let principal = handle
+        .query(
+            QueryParams::credentials(&Credentials::Plain {
+                username: "john".into(),
+                secret: "12345".into(),
+            })
+            .with_return_member_of(false),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(principal.name(), "john");
+    assert_eq!(principal.description(), Some("John Doe"));
+    assert_eq!(
+        principal.emails,
+        vec![
+            "john@example.org".to_string(),
+            "john.doe@example.org".to_string()
+        ]
+    );
+    assert!(
+        handle
+            .query(
+                QueryParams::credentials(&Credentials::Plain {
+                    username: "john".into(),
+                    secret: "invalid".into(),
+                })
+                .with_return_member_of(false),
+            )
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Bearer tokens are never compared against stored secrets
+    assert!(
+        handle
+            .query(
+                QueryParams::credentials(&Credentials::OAuthBearer {
+                    token: "12345".into(),
+                })
+                .with_return_member_of(false),
+            )
+            .await
+            .unwrap()
+            .is_none()
+    );
+
+    // Lookups by name and address
+    assert_eq!(
+        handle
+            .query(QueryParams::name("john").with_return_member_of(false))
+            .await
+            .unwrap()
+            .unwrap()
+            .id(),
+        principal.id()
+    );
+    assert!(
+        handle
+            .query(QueryParams::name("unknown").with_return_member_of(false))
+            .await
+            .unwrap()
+            .is_none()
+    );
+    assert_eq!(
+        core.rcpt(&handle, "john@example.org", 0).await.unwrap(),
+        RcptType::Mailbox
+    );
+    assert_eq!(
+        core.rcpt(&handle, "unknown@example.org", 0).await.unwrap(),
+        RcptType::Invalid
+    );
+
+    // Responses over the configured size limit are rejected
+    assert!(
+        handle
+            .query(QueryParams::name("jane").with_return_member_of(false))
+            .await
+            .is_err()
+    );
+
+    // Credentials are forwarded to the authentication endpoint
+    let handle = config.directories.directories.remove("http-auth").unwrap();
+    for credentials in [
+        Credentials::Plain {
+            username: "john".into(),
+            secret: "12345".into(),
+        },
+        Credentials::OAuthBearer {
+            token: TEST_TOKEN.into(),
+        },
+    ] {
+        let principal = handle
+            .query(QueryParams::credentials(&credentials).with_return_member_of(false))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(principal.name(), "john");
+        assert_eq!(principal.description(), Some("John Doe"));
+    }
+    for credentials in [
+        Credentials::Plain {
+            username: "john".into(),
+            secret: "invalid".into(),
+        },
+        Credentials::OAuthBearer {
+            token: "invalid_or_expired_token".into(),
+        },
+    ] {
+        assert!(
+            handle
+                .query(QueryParams::credentials(&credentials).with_return_member_of(false))
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+}
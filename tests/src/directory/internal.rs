@@ -6,7 +6,7 @@
 
 use ahash::AHashSet;
 use directory::{
-    Permission, QueryBy, QueryParams, Type,
+    Permission, PrincipalData, QueryBy, QueryParams, Type,
     backend::{
         RcptType,
         internal::{
@@ -109,6 +109,23 @@ async fn internal_directory() {
         assert!(store.is_local_domain("example.org").await.unwrap());
         assert!(!store.is_local_domain("otherdomain.org").await.unwrap());
 
+        // Domains pending verification are not local until activated
+        for (status, is_local) in [("pending-verification", false), ("active", true)] {
+            store
+                .update_principal(UpdatePrincipal::by_name("example.org").with_updates(vec![
+                    PrincipalUpdate::set(
+                        PrincipalField::DomainStatus,
+                        PrincipalValue::String(status.into()),
+                    ),
+                ]))
+                .await
+                .unwrap();
+            assert_eq!(
+                store.is_local_domain("example.org").await.unwrap(),
+                is_local
+            );
+        }
+
         // Add an email address
         assert!(
             store
@@ -143,6 +160,69 @@ async fn internal_directory() {
             Err(manage::not_found("otherdomain.org"))
         );
 
+        // Allowed senders must belong to an existing domain
+        assert_eq!(
+            store
+                .update_principal(UpdatePrincipal::by_name("john").with_updates(vec![
+                    PrincipalUpdate::add_item(
+                        PrincipalField::AllowedSenders,
+                        PrincipalValue::String("noreply@otherdomain.org".into()),
+                    )
+                ]))
+                .await,
+            Err(manage::not_found("otherdomain.org"))
+        );
+        assert_eq!(
+            store
+                .update_principal(UpdatePrincipal::by_name("john").with_updates(vec![
+                    PrincipalUpdate::set(
+                        PrincipalField::AllowedSenders,
+                        PrincipalValue::StringList(vec![
+                            "noreply@example.org".into(),
+                            "billing@otherdomain.org".into()
+                        ]),
+                    )
+                ]))
+                .await,
+            Err(manage::not_found("otherdomain.org"))
+        );
+        assert!(
+            store
+                .update_principal(UpdatePrincipal::by_name("john").with_updates(vec![
+                    PrincipalUpdate::add_item(
+                        PrincipalField::AllowedSenders,
+                        PrincipalValue::String("NoReply@Example.org".into()),
+                    )
+                ]))
+                .await
+                .is_ok()
+        );
+        assert_eq!(
+            store
+                .query(QueryParams::name("john").with_return_member_of(false))
+                .await
+                .unwrap()
+                .unwrap()
+                .data
+                .into_iter()
+                .filter(|data| matches!(data, PrincipalData::AllowedSenders(_)))
+                .collect::<Vec<_>>(),
+            vec![PrincipalData::AllowedSenders(vec![
+                "noreply@example.org".into()
+            ])]
+        );
+        assert!(
+            store
+                .update_principal(UpdatePrincipal::by_name("john").with_updates(vec![
+                    PrincipalUpdate::set(
+                        PrincipalField::AllowedSenders,
+                        PrincipalValue::StringList(vec![]),
+                    )
+                ]))
+                .await
+                .is_ok()
+        );
+
         // Create an account with an email address
         let jane_id = store
             .create_principal(
@@ -522,6 +602,27 @@ async fn internal_directory() {
         )
         .await;
 
+        // Accounts opting into calendar based out of office responses are indexed
+        for enabled in [1, 0] {
+            store
+                .update_principal(UpdatePrincipal::by_name("john.doe").with_updates(vec![
+                    PrincipalUpdate::set(
+                        PrincipalField::CalendarOutOfOffice,
+                        PrincipalValue::Integer(enabled),
+                    ),
+                ]))
+                .await
+                .unwrap();
+            assert_eq!(
+                store.get_out_of_office_principals().await.unwrap(),
+                if enabled == 1 {
+                    RoaringBitmap::from_iter([john_id])
+                } else {
+                    RoaringBitmap::new()
+                }
+            );
+        }
+
         // Field validation
         assert_eq!(
             store
@@ -4,6 +4,7 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod http;
 pub mod imap;
 pub mod internal;
 pub mod ldap;
@@ -66,6 +67,14 @@ emails = "SELECT address FROM emails WHERE name = ? AND type != 'list' ORDER BY
 verify = "SELECT address FROM emails WHERE address LIKE '%' || ? || '%' AND type = 'primary' ORDER BY address LIMIT 5"
 expand = "SELECT p.address FROM emails AS p JOIN emails AS l ON p.name = l.name WHERE p.type = 'primary' AND l.address = ? AND l.type = 'list' ORDER BY p.address LIMIT 50"
 domains = "SELECT 1 FROM emails WHERE address LIKE '%@' || ? LIMIT 1"
+insert = "INSERT INTO accounts (name, type, secret, description, quota) VALUES (?, ?, ?, ?, ?)"
+update = "UPDATE accounts SET type = ?, secret = ?, description = ?, quota = ? WHERE name = ?"
+rename = "UPDATE accounts SET name = ? WHERE name = ?"
+delete = "DELETE FROM accounts WHERE name = ?"
+insert-email = "INSERT INTO emails (name, address, type) VALUES (?, ?, 'primary')"
+delete-emails = "DELETE FROM emails WHERE name = ?"
+insert-member = "INSERT INTO group_members (name, member_of) VALUES (?, ?)"
+delete-members = "DELETE FROM group_members WHERE name = ?"
 
 [storage]
 lookup = "sqlite"
@@ -100,6 +109,14 @@ emails = "SELECT address FROM emails WHERE name = $1 AND type != 'list' ORDER BY
 verify = "SELECT address FROM emails WHERE address LIKE '%' || $1 || '%' AND type = 'primary' ORDER BY address LIMIT 5"
 expand = "SELECT p.address FROM emails AS p JOIN emails AS l ON p.name = l.name WHERE p.type = 'primary' AND l.address = $1 AND l.type = 'list' ORDER BY p.address LIMIT 50"
 domains = "SELECT 1 FROM emails WHERE address LIKE '%@' || $1 LIMIT 1"
+insert = "INSERT INTO accounts (name, type, secret, description, quota) VALUES ($1, $2, $3, $4, $5::BIGINT)"
+update = "UPDATE accounts SET type = $1, secret = $2, description = $3, quota = $4::BIGINT WHERE name = $5"
+rename = "UPDATE accounts SET name = $1 WHERE name = $2"
+delete = "DELETE FROM accounts WHERE name = $1"
+insert-email = "INSERT INTO emails (name, address, type) VALUES ($1, $2, 'primary')"
+delete-emails = "DELETE FROM emails WHERE name = $1"
+insert-member = "INSERT INTO group_members (name, member_of) VALUES ($1, $2)"
+delete-members = "DELETE FROM group_members WHERE name = $1"
 
 ##############################################################################
 
@@ -131,6 +148,14 @@ emails = "SELECT address FROM emails WHERE name = ? AND type != 'list' ORDER BY
 verify = "SELECT address FROM emails WHERE address LIKE CONCAT('%', ?, '%') AND type = 'primary' ORDER BY address LIMIT 5"
 expand = "SELECT p.address FROM emails AS p JOIN emails AS l ON p.name = l.name WHERE p.type = 'primary' AND l.address = ? AND l.type = 'list' ORDER BY p.address LIMIT 50"
 domains = "SELECT 1 FROM emails WHERE address LIKE CONCAT('%@', ?) LIMIT 1"
+insert = "INSERT INTO accounts (name, type, secret, description, quota) VALUES (?, ?, ?, ?, ?)"
+update = "UPDATE accounts SET type = ?, secret = ?, description = ?, quota = ? WHERE name = ?"
+rename = "UPDATE accounts SET name = ? WHERE name = ?"
+delete = "DELETE FROM accounts WHERE name = ?"
+insert-email = "INSERT INTO emails (name, address, type) VALUES (?, ?, 'primary')"
+delete-emails = "DELETE FROM emails WHERE name = ?"
+insert-member = "INSERT INTO group_members (name, member_of) VALUES (?, ?)"
+delete-members = "DELETE FROM group_members WHERE name = ?"
 
 ##############################################################################
 
@@ -348,6 +373,26 @@ fields.email = "email"
 fields.username = "preferred_username"
 fields.full-name = "name"
 
+##############################################################################
+
+[directory."http"]
+type = "http"
+store = "rocksdb"
+timeout = "1s"
+endpoint.name = "https://127.0.0.1:9090/name/{name}"
+endpoint.email = "https://127.0.0.1:9090/email/{address}"
+tls.allow-invalid-certs = true
+limits.size = 512
+
+[directory."http-auth"]
+type = "http"
+store = "rocksdb"
+timeout = "1s"
+endpoint.name = "https://127.0.0.1:9090/name/{name}"
+endpoint.email = "https://127.0.0.1:9090/email/{address}"
+endpoint.auth = "https://127.0.0.1:9090/auth"
+tls.allow-invalid-certs = true
+
 "#;
 
 pub struct DirectoryStore {
@@ -5,8 +5,14 @@
  */
 
 use directory::{
-    QueryParams, ROLE_USER, Type,
-    backend::{RcptType, internal::manage::ManageDirectory},
+    Directory, DirectoryInner, QueryParams, ROLE_USER, Type,
+    backend::{
+        RcptType,
+        internal::{
+            PrincipalField, PrincipalUpdate, PrincipalValue,
+            manage::{ManageDirectory, UpdatePrincipal},
+        },
+    },
 };
 use mail_send::Credentials;
 
@@ -14,7 +20,8 @@ use mail_send::Credentials;
 use store::{InMemoryStore, Store};
 
 use crate::directory::{
-    DirectoryTest, IntoTestPrincipal, TestPrincipal, map_account_id, map_account_ids,
+    DirectoryTest, IntoTestPrincipal, TestPrincipal, internal::TestInternalDirectory,
+    map_account_id, map_account_ids,
 };
 
 use super::DirectoryStore;
@@ -350,9 +357,148 @@ async fn sql_directory() {
             core.expn(&handle, "john@example.org", 0).await.unwrap(),
             Vec::<String>::new()
         );*/
+
+        // Provision a principal from the internal directory
+        let DirectoryInner::Sql(sql) = &handle.store else {
+            panic!("Expected a SQL directory");
+        };
+        let writers_id = base_store
+            .create_test_group("writers", "Writers", &[])
+            .await;
+        let carol_id = base_store
+            .create_test_user(
+                "carol",
+                "carol_secret",
+                "Carol Foobar",
+                &["carol@example.org"],
+            )
+            .await;
+        base_store.add_to_group("carol", "writers").await;
+        sql.store_principal(carol_id, None).await.unwrap();
+        let carol = TestPrincipal {
+            id: carol_id,
+            name: "carol".into(),
+            description: Some("Carol Foobar".into()),
+            typ: Type::Individual,
+            secrets: vec!["carol_secret".into()],
+            member_of: map_account_ids(base_store, vec!["writers"])
+                .await
+                .into_iter()
+                .map(|v| v.to_string())
+                .collect(),
+            emails: vec!["carol@example.org".into()],
+            roles: vec![ROLE_USER.to_string()],
+            ..Default::default()
+        };
+        assert_eq!(sql_principal(&handle, "carol").await, Some(carol.clone()));
+
+        // Update the account and its addresses
+        update_principal(
+            base_store,
+            carol_id,
+            vec![
+                PrincipalUpdate::set(
+                    PrincipalField::Description,
+                    PrincipalValue::String("Carol Smith".into()),
+                ),
+                PrincipalUpdate::set(PrincipalField::Quota, PrincipalValue::Integer(1024)),
+                PrincipalUpdate::add_item(
+                    PrincipalField::Emails,
+                    PrincipalValue::String("csmith@example.org".into()),
+                ),
+            ],
+        )
+        .await;
+        sql.store_principal(carol_id, Some("carol")).await.unwrap();
+        let mut carol = TestPrincipal {
+            description: Some("Carol Smith".into()),
+            quota: 1024,
+            emails: vec!["carol@example.org".into(), "csmith@example.org".into()],
+            ..carol
+        };
+        assert_eq!(sql_principal(&handle, "carol").await, Some(carol.clone()));
+
+        // Renames carry over addresses and memberships
+        update_principal(
+            base_store,
+            carol_id,
+            vec![PrincipalUpdate::set(
+                PrincipalField::Name,
+                PrincipalValue::String("caroline".into()),
+            )],
+        )
+        .await;
+        sql.store_principal(carol_id, Some("carol")).await.unwrap();
+        carol.name = "caroline".into();
+        assert_eq!(sql_principal(&handle, "carol").await, None);
+        assert_eq!(
+            sql_principal(&handle, "caroline").await,
+            Some(carol.clone())
+        );
+        assert!(!store.has_test_rows("emails", "carol").await);
+        assert!(!store.has_test_rows("group_members", "carol").await);
+
+        // Failed writes are reverted in the internal directory
+        let previous = sql.principal_state(carol_id).await.unwrap();
+        update_principal(
+            base_store,
+            carol_id,
+            vec![
+                PrincipalUpdate::set(
+                    PrincipalField::Description,
+                    PrincipalValue::String("Someone else".into()),
+                ),
+                PrincipalUpdate::remove_item(
+                    PrincipalField::Emails,
+                    PrincipalValue::String("csmith@example.org".into()),
+                ),
+                PrincipalUpdate::remove_item(
+                    PrincipalField::MemberOf,
+                    PrincipalValue::String("writers".into()),
+                ),
+            ],
+        )
+        .await;
+        sql.revert_principal(previous).await.unwrap();
+        let principal = base_store.get_principal(carol_id).await.unwrap().unwrap();
+        assert_eq!(principal.description(), Some("Carol Smith"));
+        assert_eq!(principal.emails, carol.emails);
+        assert!(
+            base_store
+                .get_member_of(carol_id)
+                .await
+                .unwrap()
+                .iter()
+                .any(|member| member.principal_id == writers_id)
+        );
+        assert_eq!(
+            sql_principal(&handle, "caroline").await,
+            Some(carol.clone())
+        );
+
+        // Removing the principal deletes its addresses and memberships
+        sql.remove_principal("caroline").await.unwrap();
+        assert_eq!(sql_principal(&handle, "caroline").await, None);
+        assert!(!store.has_test_rows("emails", "caroline").await);
+        assert!(!store.has_test_rows("group_members", "caroline").await);
     }
 }
 
+async fn sql_principal(handle: &Directory, name: &str) -> Option<TestPrincipal> {
+    handle
+        .query(QueryParams::name(name).with_return_member_of(true))
+        .await
+        .unwrap()
+        .map(|principal| principal.into_test())
+}
+
+async fn update_principal(store: &Store, principal_id: u32, changes: Vec<PrincipalUpdate>) {
+    store
+        .update_principal(UpdatePrincipal::by_id(principal_id).with_updates(changes))
+        .await
+        .unwrap();
+}
+
 impl DirectoryStore {
     pub async fn create_test_directory(&self) {
         // Create tables
@@ -541,6 +687,20 @@ impl DirectoryStore {
             .unwrap();
     }
 
+    pub async fn has_test_rows(&self, table: &str, login: &str) -> bool {
+        self.store
+            .sql_query::<bool>(
+                &if self.is_postgresql() {
+                    format!("SELECT 1 FROM {table} WHERE name = $1")
+                } else {
+                    format!("SELECT 1 FROM {table} WHERE name = ?")
+                },
+                vec![login.into()],
+            )
+            .await
+            .unwrap()
+    }
+
     fn is_mysql(&self) -> bool {
         #[cfg(feature = "mysql")]
         {
@@ -0,0 +1,68 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::expr::if_block::IfBlock;
+use imap_proto::ResponseType;
+
+use crate::imap::Type;
+
+use super::{IMAPTest, ImapConnection};
+
+pub async fn test(handle: &IMAPTest) {
+    println!("Running bandwidth limit tests...");
+
+    // Limit sessions to 32KB per second, limiters are resolved at login
+    let core = handle.server.inner.shared_core.load_full();
+    let mut limited_core = core.as_ref().clone();
+    limited_core.imap.bandwidth_session = Some(IfBlock::new::<()>(
+        "imap.rate-limit.bandwidth.session",
+        [],
+        "32768",
+    ));
+    handle.server.inner.shared_core.store(limited_core.into());
+    let mut imap = ImapConnection::connect(b"_w ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.authenticate("jdoe@example.com", "secret").await;
+    handle.server.inner.shared_core.store(core);
+
+    // Append a message of about 96KB
+    let mut raw_message = String::from("Subject: Bandwidth test\r\n\r\n");
+    for line in 0..1500 {
+        raw_message.push_str(&format!("{line:0>62}\r\n"));
+    }
+    imap.send("CREATE Bandwidth").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send(&format!("APPEND Bandwidth {{{}}}", raw_message.len()))
+        .await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged(&raw_message).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("SELECT Bandwidth").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // After the initial burst, the remaining 64KB take about two seconds
+    let start = Instant::now();
+    imap.send("FETCH 1 BODY[]").await;
+    let lines = imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let elapsed = start.elapsed();
+    assert!(
+        lines.iter().any(|line| line == &format!("{:0>62}", 1499)),
+        "Message body was not fetched"
+    );
+    assert!(
+        elapsed >= Duration::from_millis(1500),
+        "FETCH was not throttled, took {elapsed:?}"
+    );
+
+    imap.send("UNSELECT").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("DELETE Bandwidth").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("LOGOUT").await;
+    imap.assert_read(Type::Untagged, ResponseType::Bye).await;
+}
@@ -0,0 +1,103 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    time::Duration,
+};
+
+use common::config::server::ServerProtocol;
+use directory::backend::internal::manage::ManageDirectory;
+use imap_proto::ResponseType;
+use tokio::{io::AsyncReadExt, net::TcpStream};
+
+use crate::imap::Type;
+
+use super::{IMAPTest, ImapConnection};
+
+pub async fn test(handle: &IMAPTest) {
+    println!("Running connection limit tests...");
+    let account_id = handle
+        .server
+        .store()
+        .get_principal_id("jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap();
+    let localhost = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let account_connections = || {
+        handle
+            .server
+            .inner
+            .data
+            .connections
+            .account_connections()
+            .into_iter()
+            .find(|(key, _)| *key == (ServerProtocol::Imap, account_id))
+            .map_or(0, |(_, connections)| connections)
+    };
+    let ip_connections = || {
+        handle
+            .server
+            .inner
+            .data
+            .connections
+            .ip_connections()
+            .into_iter()
+            .find(|(key, _)| *key == (ServerProtocol::Imap, localhost))
+            .map_or(0, |(_, connections)| connections)
+    };
+
+    // Allow one more session for the account and two more connections from localhost
+    let open_sessions = account_connections();
+    let open_connections = ip_connections();
+    let core = handle.server.inner.shared_core.load_full();
+    let mut limited_core = core.as_ref().clone();
+    limited_core.network.connection_limits.imap.per_account = Some(open_sessions + 1);
+    limited_core.network.connection_limits.imap.per_ip = Some(open_connections + 2);
+    handle.server.inner.shared_core.store(limited_core.into());
+
+    let mut imap_a = ImapConnection::connect(b"_a ").await;
+    let mut imap_b = ImapConnection::connect(b"_b ").await;
+    for imap in [&mut imap_a, &mut imap_b] {
+        imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    }
+    assert_eq!(ip_connections(), open_connections + 2);
+
+    // Connections over the per-IP limit are dropped before the greeting
+    let mut stream = TcpStream::connect("127.0.0.1:9991").await.unwrap();
+    let mut buf = Vec::new();
+    let result = tokio::time::timeout(Duration::from_secs(2), stream.read_to_end(&mut buf)).await;
+    assert!(result.is_ok(), "Connection was not dropped");
+    assert!(buf.is_empty());
+
+    // Sessions over the per-account limit are rejected at login
+    imap_a
+        .send("AUTHENTICATE PLAIN {32+}\r\nAGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0")
+        .await;
+    imap_a.assert_read(Type::Tagged, ResponseType::Ok).await;
+    assert_eq!(account_connections(), open_sessions + 1);
+    imap_b
+        .send("AUTHENTICATE PLAIN {32+}\r\nAGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0")
+        .await;
+    imap_b.assert_read(Type::Tagged, ResponseType::No).await;
+
+    // Closing a session frees up its slot
+    imap_a.send("LOGOUT").await;
+    imap_a.assert_read(Type::Untagged, ResponseType::Bye).await;
+    drop(imap_a);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(account_connections(), open_sessions);
+    imap_b
+        .send("AUTHENTICATE PLAIN {32+}\r\nAGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0")
+        .await;
+    imap_b.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_b.send("LOGOUT").await;
+    imap_b.assert_read(Type::Untagged, ResponseType::Bye).await;
+
+    // Restore limits
+    handle.server.inner.shared_core.store(core);
+}
@@ -7,7 +7,7 @@
 use imap::op::list::matches_pattern;
 use imap_proto::ResponseType;
 
-use super::{AssertResult, ImapConnection, Type};
+use super::{AssertResult, IMAPTest, ImapConnection, Type};
 
 pub async fn test(mut imap: &mut ImapConnection, mut imap_check: &mut ImapConnection) {
     println!("Running mailbox tests...");
@@ -323,6 +323,54 @@ pub async fn test(mut imap: &mut ImapConnection, mut imap_check: &mut ImapConnec
     imap.assert_read(Type::Tagged, ResponseType::Ok).await;
 }
 
+pub async fn test_protected_folders(handle: &IMAPTest) {
+    println!("Running protected folder tests...");
+
+    // Protect all system folders
+    let core = handle.server.inner.shared_core.load_full();
+    let mut protected_core = core.as_ref().clone();
+    for folder in &mut protected_core.jmap.default_folders {
+        folder.protect = true;
+    }
+    handle.server.inner.shared_core.store(protected_core.into());
+
+    // Protected folders cannot be renamed, moved or deleted
+    let mut imap = ImapConnection::connect(b"_p ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.send("AUTHENTICATE PLAIN {32+}\r\nAGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    for command in [
+        "RENAME \"Sent Items\" \"Sent\"",
+        "RENAME \"Deleted Items\" \"INBOX/Deleted Items\"",
+    ] {
+        imap.send(command).await;
+        imap.assert_read(Type::Tagged, ResponseType::No)
+            .await
+            .assert_response_code("NOPERM");
+    }
+    for mailbox in ["Drafts", "Junk Mail"] {
+        imap.send(&format!("DELETE \"{mailbox}\"")).await;
+        imap.assert_read(Type::Tagged, ResponseType::No).await;
+    }
+    imap.send("LIST \"\" \"*\"").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_folders(
+            [
+                ("Sent Items", [""]),
+                ("Deleted Items", [""]),
+                ("Drafts", [""]),
+                ("Junk Mail", [""]),
+            ],
+            false,
+        );
+    imap.send("LOGOUT").await;
+
+    // Restore the configuration
+    handle.server.inner.shared_core.store(core);
+}
+
 #[test]
 fn mailbox_matches_pattern() {
     let mailboxes = [
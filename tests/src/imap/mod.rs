@@ -6,10 +6,12 @@
 
 pub mod acl;
 pub mod append;
+pub mod bandwidth;
 pub mod basic;
 pub mod bayes;
 pub mod body_structure;
 pub mod condstore;
+pub mod connections;
 pub mod copy_move;
 pub mod fetch;
 pub mod idle;
@@ -83,6 +85,9 @@ pub async fn imap_tests() {
         imap.assert_read(Type::Tagged, ResponseType::Ok).await;
     }
 
+    // System folders are protected unless configured otherwise
+    mailbox::test_protected_folders(&handle).await;
+
     // Delete folders
     for mailbox in ["Drafts", "Junk Mail", "Sent Items"] {
         imap.send(&format!("DELETE \"{}\"", mailbox)).await;
@@ -99,6 +104,8 @@ pub async fn imap_tests() {
     idle::test(&mut imap, &mut imap_check, false).await;
     condstore::test(&mut imap, &mut imap_check).await;
     acl::test(&mut imap, &mut imap_check).await;
+    connections::test(&handle).await;
+    bandwidth::test(&handle).await;
 
     // Logout
     for imap in [&mut imap, &mut imap_check] {
@@ -381,6 +388,14 @@ impl ImapConnection {
         }
     }
 
+    pub async fn assert_disconnected(&mut self) {
+        match tokio::time::timeout(Duration::from_millis(1500), self.reader.next_line()).await {
+            Ok(Ok(None)) | Ok(Err(_)) => (),
+            Ok(Ok(Some(line))) => panic!("Expected disconnection, found {line:?}"),
+            Err(_) => panic!("Timeout while waiting for disconnection"),
+        }
+    }
+
     pub async fn authenticate(&mut self, user: &str, pass: &str) {
         let creds = general_purpose::STANDARD.encode(format!("\0{user}\0{pass}"));
         self.send(&format!(
@@ -750,6 +765,9 @@ private-key = "%{file:{PK}}%"
 [imap.protocol]
 uidplus = true
 
+[imap.rate-limit.bandwidth]
+account = 104857600
+
 [storage]
 data = "{STORE}"
 fts = "{STORE}"
@@ -794,18 +812,26 @@ subscribe = false
 [email.folders.sent]
 name = "Sent Items"
 subscribe = false
+protect = false
 
 [email.folders.trash]
 name = "Deleted Items"
 subscribe = false
+protect = false
 
 [email.folders.junk]
 name = "Junk Mail"
 subscribe = false
+protect = false
 
 [email.folders.drafts]
 name = "Drafts"
 subscribe = false
+protect = false
+
+[email.folders.archive]
+name = "Archive"
+create = false
 
 [store."auth"]
 type = "sqlite"
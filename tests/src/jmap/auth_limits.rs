@@ -10,7 +10,11 @@ use std::{
     time::Duration,
 };
 
-use common::listener::blocked::BLOCKED_IP_KEY;
+use common::{
+    auth::lockout::{AccountLockout, LockoutStatus},
+    core::BuildServer,
+    listener::blocked::BLOCKED_IP_KEY,
+};
 use imap_proto::ResponseType;
 use jmap_client::{
     client::{Client, Credentials},
@@ -18,12 +22,14 @@ use jmap_client::{
     mailbox::{self},
 };
 use jmap_proto::types::id::Id;
+use serde_json::Value;
 use store::write::now;
+use utils::config::Rate;
 
 use crate::{
     directory::internal::TestInternalDirectory,
     imap::{ImapConnection, Type},
-    jmap::{assert_is_empty, mailbox::destroy_all_mailboxes},
+    jmap::{ManagementApi, assert_is_empty, mailbox::destroy_all_mailboxes},
 };
 
 use super::JMAPTest;
@@ -245,6 +251,78 @@ pub async fn test(params: &mut JMAPTest) {
         client.upload(None, b"sleep".to_vec(), None).await,
         Err(jmap_client::Error::Problem(err)) if err.status() == Some(400)));
 
+    // Accounts are locked out after too many failures, backing off exponentially
+    let lockout = AccountLockout {
+        rate: Rate {
+            requests: 3,
+            period: Duration::from_secs(60),
+        },
+        lockout: Duration::from_secs(60),
+        max_lockout: Duration::from_secs(300),
+    };
+    assert_eq!(lockout.lockout_duration(1), 60);
+    assert_eq!(lockout.lockout_duration(2), 120);
+    assert_eq!(lockout.lockout_duration(3), 240);
+    assert_eq!(lockout.lockout_duration(4), 300);
+    let core = server.inner.shared_core.load_full();
+    let mut lockout_core = core.as_ref().clone();
+    lockout_core.network.security.account_lockout = Some(lockout);
+    server.inner.shared_core.store(lockout_core.into());
+    let lockout_server = server.inner.build_server();
+    let remote_ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    let login = lockout_server
+        .lockout_login(Some("JDoe@example.com"), remote_ip)
+        .unwrap();
+    assert_eq!(login, "jdoe@example.com");
+    for _ in 0..2 {
+        lockout_server
+            .record_auth_failure(&login, remote_ip)
+            .await
+            .unwrap();
+    }
+    lockout_server
+        .assert_account_unlocked(&login)
+        .await
+        .unwrap();
+    assert!(
+        lockout_server
+            .record_auth_failure(&login, remote_ip)
+            .await
+            .unwrap_err()
+            .matches(trc::EventType::Security(trc::SecurityEvent::AccountLocked))
+    );
+    assert!(
+        lockout_server
+            .assert_account_unlocked(&login)
+            .await
+            .unwrap_err()
+            .matches(trc::EventType::Security(trc::SecurityEvent::AccountLocked))
+    );
+
+    // View and clear the lockout
+    let api = ManagementApi::new(8899, "admin", "secret");
+    let status = api
+        .get::<Value>("/api/lockout/jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(status["locked"], true, "{status:?}");
+    assert_eq!(status["strikes"], 1, "{status:?}");
+    assert_eq!(status["failures"], 0, "{status:?}");
+    api.delete::<Value>("/api/lockout/jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    lockout_server
+        .assert_account_unlocked(&login)
+        .await
+        .unwrap();
+    assert_eq!(
+        lockout_server.account_lockout(&login).await.unwrap(),
+        LockoutStatus::default()
+    );
+    server.inner.shared_core.store(core);
+
     // Destroy test accounts
     params.client.set_default_account_id(&account_id);
     destroy_all_mailboxes(params).await;
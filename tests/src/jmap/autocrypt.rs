@@ -0,0 +1,155 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use email::message::{
+    autocrypt::AutocryptManager,
+    delivery::{IngestMessage, MailDelivery},
+};
+use jmap_proto::types::{collection::Collection, id::Id, property::Property};
+use serde_json::{Value, json};
+use store::write::BatchBuilder;
+use utils::BlobHash;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{assert_is_empty, jmap_json_request, mailbox::destroy_all_mailboxes},
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Autocrypt tests...");
+    let server = params.server.clone();
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+
+    // Keys from senders that did not pass DMARC are ignored
+    deliver(
+        &server,
+        false,
+        "Mon, 13 Oct 2025 10:00:00 +0000",
+        "a2V5MQ==",
+    )
+    .await;
+    assert!(
+        server
+            .get_autocrypt_peers(account_id)
+            .await
+            .unwrap()
+            .items
+            .is_empty()
+    );
+
+    // Keys from authenticated senders are stored
+    deliver(&server, true, "Mon, 13 Oct 2025 10:00:00 +0000", "a2V5MQ==").await;
+    assert_eq!(peer_keys(&server, account_id).await, vec!["a2V5MQ=="]);
+
+    // The received time is used, a forged Date header cannot pin an older key
+    deliver(&server, true, "Thu, 01 Jan 1970 00:00:00 +0000", "a2V5Mg==").await;
+    assert_eq!(peer_keys(&server, account_id).await, vec!["a2V5Mg=="]);
+
+    // The state does not change between requests
+    let response = autocrypt_key_get(account_id).await;
+    assert_eq!(
+        response["list"][0]["email"], "bill@remote.org",
+        "{response}"
+    );
+    assert_eq!(response["list"][0]["keyData"], "a2V5Mg==", "{response}");
+    assert_eq!(
+        response["state"],
+        autocrypt_key_get(account_id).await["state"],
+        "{response}"
+    );
+
+    // Remove test data
+    let peers = server.get_autocrypt_peers(account_id).await.unwrap();
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id)
+        .with_collection(Collection::Principal);
+    for peer in &peers.items {
+        batch
+            .update_document(peer.key_document_id())
+            .clear(Property::KeyData);
+    }
+    batch.update_document(0).clear(Property::Autocrypt);
+    server.store().write(batch.build_all()).await.unwrap();
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn deliver(server: &Server, sender_authenticated: bool, date: &str, key_data: &str) {
+    let message = format!(
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Date: {}\r\n",
+            "Autocrypt: addr=bill@remote.org; keydata={}\r\n",
+            "Subject: Autocrypt\r\n",
+            "\r\n",
+            "Test message.\r\n"
+        ),
+        date, key_data
+    );
+    let message_blob = BlobHash::generate(message.as_bytes());
+    server
+        .blob_store()
+        .put_blob(message_blob.as_ref(), message.as_bytes())
+        .await
+        .unwrap();
+    server
+        .deliver_message(IngestMessage {
+            sender_address: "bill@remote.org".to_string(),
+            sender_authenticated,
+            recipients: vec!["jdoe@example.com".to_string()],
+            message_blob,
+            message_size: message.len() as u64,
+            session_id: 0,
+        })
+        .await;
+}
+
+async fn peer_keys(server: &Server, account_id: u32) -> Vec<String> {
+    let mut keys = Vec::new();
+    for peer in server.get_autocrypt_peers(account_id).await.unwrap().items {
+        keys.push(
+            server
+                .get_autocrypt_key(account_id, &peer)
+                .await
+                .unwrap()
+                .unwrap(),
+        );
+    }
+    keys
+}
+
+async fn autocrypt_key_get(account_id: u32) -> Value {
+    let mut response = jmap_json_request(
+        json!([[
+            "AutocryptKey/get",
+            {"accountId": Id::from(account_id).to_string(), "ids": null},
+            "0"
+        ]])
+        .to_string(),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    response["methodResponses"][0][1].take()
+}
@@ -0,0 +1,155 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::core::BuildServer;
+use futures::future::join_all;
+use jmap_proto::types::id::Id;
+use serde_json::{Value, json};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        ManagementApi, Response, assert_is_empty, delivery::SmtpConnection,
+        mailbox::destroy_all_mailboxes,
+    },
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running disposable alias tests...");
+    let server = params.server.clone();
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    let api = ManagementApi::new(8899, "jdoe@example.com", "12345");
+
+    // Disposable aliases are disabled by default
+    api.post::<Value>("/api/account/aliases", &json!({}))
+        .await
+        .unwrap()
+        .expect_error("disabled by the system administrator");
+    let core = params.server.inner.shared_core.load_full();
+    let mut alias_core = core.as_ref().clone();
+    alias_core.jmap.disposable_alias_enable = true;
+    alias_core.jmap.disposable_alias_max = 5;
+    params.server.inner.shared_core.store(alias_core.into());
+    let alias_server = params.server.inner.build_server();
+
+    // Concurrent requests do not overwrite each other or exceed the limit
+    let requests = (0..6)
+        .map(|i| json!({"description": format!("Alias {i}")}))
+        .collect::<Vec<_>>();
+    let results = join_all(
+        requests
+            .iter()
+            .map(|request| api.post::<Value>("/api/account/aliases", request)),
+    )
+    .await
+    .into_iter()
+    .map(|result| result.unwrap())
+    .collect::<Vec<_>>();
+    let mut addresses = Vec::new();
+    for result in results {
+        match result {
+            Response::Data { data } => {
+                addresses.push(data["address"].as_str().unwrap().to_string());
+            }
+            result => result.expect_error("Disposable alias limit reached"),
+        }
+    }
+    assert_eq!(addresses.len(), 5);
+    let aliases = api
+        .get::<Value>("/api/account/aliases")
+        .await
+        .unwrap()
+        .unwrap_data();
+    let mut ids = aliases
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|alias| alias["id"].as_str().unwrap().to_string())
+        .collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.dedup();
+    assert_eq!(ids.len(), 5, "{aliases}");
+
+    // Messages sent to active aliases are delivered and counted
+    let mut lmtp = SmtpConnection::connect().await;
+    lmtp.ingest(
+        "bill@remote.org",
+        &[&addresses[0]],
+        &format!(
+            "From: bill@remote.org\r\nTo: {}\r\nSubject: Hi\r\n\r\nHi!\r\n",
+            addresses[0]
+        ),
+    )
+    .await;
+    let aliases = api
+        .get::<Value>("/api/account/aliases")
+        .await
+        .unwrap()
+        .unwrap_data();
+    let alias = aliases
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|alias| alias["address"] == addresses[0])
+        .unwrap();
+    assert_eq!(alias["received"], 1, "{aliases}");
+
+    // Disabled aliases are no longer resolved
+    api.put::<Value>(
+        &format!("/api/account/aliases/{}", alias["id"].as_str().unwrap()),
+        &json!({"enabled": false}),
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    assert_eq!(
+        alias_server
+            .disposable_alias_to_id(&addresses[0])
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        alias_server
+            .disposable_alias_to_id(&addresses[1])
+            .await
+            .unwrap(),
+        Some(account_id)
+    );
+
+    // Deleting the account removes its aliases
+    destroy_all_mailboxes(params).await;
+    ManagementApi::new(8899, "admin", "secret")
+        .delete::<()>("/api/principal/jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    for address in &addresses {
+        assert_eq!(
+            alias_server.disposable_alias_to_id(address).await.unwrap(),
+            None
+        );
+    }
+
+    // Restore the configuration
+    params.server.inner.shared_core.store(core);
+    assert_is_empty(server).await;
+}
@@ -0,0 +1,101 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use email::cache::{MessageCacheFetch, email::MessageCacheAccess};
+use jmap_proto::types::{id::Id, keyword::Keyword};
+use serde_json::{Value, json};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        ManagementApi, assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes,
+    },
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running read receipt tests...");
+    let server = params.server.clone();
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    let message = concat!(
+        "From: bill@remote.org\r\n",
+        "To: jdoe@example.com\r\n",
+        "Disposition-Notification-To: bill@remote.org\r\n",
+        "Subject: Read receipt requested\r\n",
+        "\r\n",
+        "Please confirm.\r\n"
+    );
+
+    // Read receipt requests from external senders are kept by default
+    assert!(!server.core.jmap.mdn_suppress_external);
+    let mut lmtp = SmtpConnection::connect().await;
+    lmtp.ingest("bill@remote.org", &["jdoe@example.com"], message)
+        .await;
+    assert_eq!(num_emails(&server, account_id).await, (1, 0));
+
+    // Once enabled, requests from unauthenticated senders are suppressed
+    let core = params.server.inner.shared_core.load_full();
+    let mut suppress_core = core.as_ref().clone();
+    suppress_core.jmap.mdn_suppress_external = true;
+    params.server.inner.shared_core.store(suppress_core.into());
+    lmtp.ingest("bill@remote.org", &["jdoe@example.com"], message)
+        .await;
+    assert_eq!(num_emails(&server, account_id).await, (2, 1));
+    params.server.inner.shared_core.store(core);
+
+    // Automatic read receipts are managed per account
+    let api = ManagementApi::new(8899, "jdoe@example.com", "12345");
+    assert_eq!(
+        api.get::<Value>("/api/account/read-receipts")
+            .await
+            .unwrap()
+            .unwrap_data(),
+        json!({"autoSend": false})
+    );
+    api.put::<Value>("/api/account/read-receipts", &json!({"autoSend": true}))
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(
+        api.get::<Value>("/api/account/read-receipts")
+            .await
+            .unwrap()
+            .unwrap_data(),
+        json!({"autoSend": true})
+    );
+    api.put::<Value>("/api/account/read-receipts", &json!({"autoSend": false}))
+        .await
+        .unwrap()
+        .unwrap_data();
+
+    // Remove test data
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+// Returns the total number of messages and those flagged as having a receipt sent
+async fn num_emails(server: &Server, account_id: u32) -> (u64, usize) {
+    let cache = server.get_cached_messages(account_id).await.unwrap();
+    (
+        cache.email_document_ids().len(),
+        cache.with_keyword(&Keyword::MdnSent).count(),
+    )
+}
@@ -0,0 +1,184 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use directory::Permission;
+use email::{
+    cache::{MessageCacheFetch, email::MessageCacheAccess},
+    mailbox::INBOX_ID,
+};
+use jmap_client::{client::Client, mailbox::Role};
+use jmap_proto::types::id::Id;
+use serde_json::{Value, json};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{ManagementApi, assert_is_empty, mailbox::destroy_all_mailboxes},
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Email recall tests...");
+    let server = params.server.clone();
+    let store = &server.core.storage.data;
+    let john_id = store
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    let jane_id = store
+        .create_test_user(
+            "jane@example.com",
+            "abcde",
+            "Jane Smith",
+            &["jane@example.com"],
+        )
+        .await;
+    let bill_id = store
+        .create_test_user(
+            "bill@example.com",
+            "098765",
+            "Bill Foobar",
+            &["bill@example.com"],
+        )
+        .await;
+    store
+        .add_permissions("jdoe@example.com", [Permission::EmailRecall])
+        .await;
+    let john_api = ManagementApi::new(8899, "jdoe@example.com", "12345");
+    let jane_api = ManagementApi::new(8899, "jane@example.com", "abcde");
+
+    // Build the sender's Sent folder and the recipients' copies
+    params
+        .client
+        .set_default_account_id(Id::from(john_id).to_string());
+    let sent_id = params
+        .client
+        .mailbox_create("Sent Items", None::<String>, Role::Sent)
+        .await
+        .unwrap()
+        .take_id();
+    let inbox_id = Id::from(INBOX_ID).to_string();
+    let recalled = message("jdoe@example.com", "recall-1@example.com");
+    let spoofed = message("boss@example.com", "recall-2@example.com");
+    import(&mut params.client, john_id, &recalled, &sent_id, false).await;
+    import(&mut params.client, john_id, &spoofed, &sent_id, false).await;
+    import(
+        &mut params.client,
+        john_id,
+        &message("jdoe@example.com", "recall-3@example.com"),
+        &inbox_id,
+        false,
+    )
+    .await;
+    import(&mut params.client, jane_id, &recalled, &inbox_id, false).await;
+    import(
+        &mut params.client,
+        jane_id,
+        &message("mallory@example.com", "recall-1@example.com"),
+        &inbox_id,
+        false,
+    )
+    .await;
+    import(&mut params.client, bill_id, &recalled, &inbox_id, true).await;
+
+    // Recalling requires a permission that users do not have by default
+    jane_api
+        .post::<Value>(
+            "/api/account/recall",
+            &json!({"messageId": "recall-1@example.com"}),
+        )
+        .await
+        .unwrap()
+        .expect_request_error("Forbidden");
+
+    // Only messages in the Sent folder sent from the caller's addresses can be recalled
+    john_api
+        .post::<Value>(
+            "/api/account/recall",
+            &json!({"messageId": "recall-3@example.com"}),
+        )
+        .await
+        .unwrap()
+        .expect_error("notFound");
+    john_api
+        .post::<Value>(
+            "/api/account/recall",
+            &json!({"messageId": "<recall-2@example.com>"}),
+        )
+        .await
+        .unwrap()
+        .expect_error("Only messages sent from your own addresses can be recalled");
+
+    // Unread copies sent by the caller are removed, copies from other senders are kept
+    assert_eq!(num_emails(&server, jane_id).await, 2);
+    let results = john_api
+        .post::<Value>(
+            "/api/account/recall",
+            &json!({"messageId": "<recall-1@example.com>"}),
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(
+        results,
+        json!([
+            {"recipient": "jane@example.com", "status": "recalled"},
+            {"recipient": "bill@example.com", "status": "alreadyRead"},
+        ])
+    );
+    assert_eq!(num_emails(&server, jane_id).await, 1);
+    assert_eq!(num_emails(&server, bill_id).await, 1);
+
+    // The sender receives a report
+    assert_eq!(num_emails(&server, john_id).await, 4);
+
+    // Remove test data
+    for account_id in [john_id, jane_id, bill_id] {
+        params
+            .client
+            .set_default_account_id(Id::from(account_id).to_string());
+        destroy_all_mailboxes(params).await;
+    }
+    assert_is_empty(server).await;
+}
+
+fn message(from: &str, message_id: &str) -> Vec<u8> {
+    format!(
+        concat!(
+            "From: {}\r\n",
+            "To: jane@example.com\r\n",
+            "Cc: bill@example.com\r\n",
+            "Message-ID: <{}>\r\n",
+            "Subject: Quarterly results\r\n",
+            "\r\n",
+            "Please find the results attached.\r\n"
+        ),
+        from, message_id
+    )
+    .into_bytes()
+}
+
+async fn import(client: &mut Client, account_id: u32, raw: &[u8], mailbox_id: &str, seen: bool) {
+    client
+        .set_default_account_id(Id::from(account_id).to_string())
+        .email_import(raw.to_vec(), [mailbox_id], seen.then_some(["$seen"]), None)
+        .await
+        .unwrap();
+}
+
+async fn num_emails(server: &Server, account_id: u32) -> u64 {
+    server
+        .get_cached_messages(account_id)
+        .await
+        .unwrap()
+        .email_document_ids()
+        .len()
+}
@@ -0,0 +1,180 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use email::{
+    cache::{MessageCacheFetch, email::MessageCacheAccess},
+    mailbox::INBOX_ID,
+    message::snooze::EmailSnooze,
+};
+use jmap_client::mailbox::Role;
+use jmap_proto::types::{collection::Collection, date::UTCDate, id::Id};
+use serde_json::Value;
+use store::write::{BatchBuilder, TaskQueueClass, ValueClass, now};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{assert_is_empty, jmap_json_request, mailbox::destroy_all_mailboxes},
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Email snooze tests...");
+    let server = params.server.clone();
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    let account = Id::from(account_id).to_string();
+    params.client.set_default_account_id(&account);
+
+    // Import a message into a folder other than the Inbox
+    let mailbox_id = params
+        .client
+        .mailbox_create("Later", None::<String>, Role::None)
+        .await
+        .unwrap()
+        .take_id();
+    let email_id = params
+        .client
+        .email_import(
+            b"From: bill@example.com\r\nSubject: Reminder\r\n\r\nCall me back.".to_vec(),
+            [&mailbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let document_id = Id::from_bytes(email_id.as_bytes()).unwrap().document_id();
+
+    // Snooze times in the past are rejected
+    let response = set_snoozed_until(&account, &email_id, "\"2001-01-01T00:00:00Z\"").await;
+    assert_eq!(
+        response
+            .pointer(&format!("/methodResponses/0/1/notUpdated/{email_id}/type"))
+            .and_then(|v| v.as_str()),
+        Some("invalidProperties"),
+        "{response}"
+    );
+    assert_eq!(get_snoozed_until(&account, &email_id).await, Value::Null);
+
+    // Snoozing sets the property and schedules a task
+    let due = now() + 3600;
+    let until = format!("\"{}\"", UTCDate::from_timestamp(due as i64));
+    let response = set_snoozed_until(&account, &email_id, &until).await;
+    assert!(
+        response
+            .pointer(&format!("/methodResponses/0/1/updated/{email_id}"))
+            .is_some(),
+        "{response}"
+    );
+    assert_eq!(
+        get_snoozed_until(&account, &email_id).await,
+        Value::String(until.trim_matches('"').to_string())
+    );
+    assert_eq!(
+        server
+            .get_snoozed_until(account_id, document_id)
+            .await
+            .unwrap(),
+        Some(due)
+    );
+
+    // Tasks left behind by a rescheduled snooze are discarded
+    assert!(
+        server
+            .unsnooze_email(account_id, document_id, due - 60)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        mailbox_ids(&server, account_id, document_id).await,
+        vec![Id::from_bytes(mailbox_id.as_bytes()).unwrap().document_id()]
+    );
+
+    // Once the snooze expires the message is moved back to the Inbox
+    assert!(
+        server
+            .unsnooze_email(account_id, document_id, due)
+            .await
+            .unwrap()
+    );
+    assert_eq!(
+        mailbox_ids(&server, account_id, document_id).await,
+        vec![INBOX_ID]
+    );
+    assert_eq!(get_snoozed_until(&account, &email_id).await, Value::Null);
+    let mut batch = BatchBuilder::new();
+    batch
+        .with_account_id(account_id)
+        .with_collection(Collection::Email)
+        .update_document(document_id)
+        .clear(ValueClass::TaskQueue(TaskQueueClass::Unsnooze { due }));
+    server.store().write(batch.build_all()).await.unwrap();
+
+    // Cancelling a snooze clears the property
+    set_snoozed_until(&account, &email_id, &until).await;
+    let response = set_snoozed_until(&account, &email_id, "null").await;
+    assert!(
+        response
+            .pointer(&format!("/methodResponses/0/1/updated/{email_id}"))
+            .is_some(),
+        "{response}"
+    );
+    assert_eq!(get_snoozed_until(&account, &email_id).await, Value::Null);
+
+    // Remove test data
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn set_snoozed_until(account: &str, email_id: &str, until: &str) -> Value {
+    jmap_json_request(
+        format!(
+            r#"[["Email/set", {{"accountId": "{account}", "update": {{"{email_id}": {{"snoozedUntil": {until}}}}}}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await
+}
+
+async fn get_snoozed_until(account: &str, email_id: &str) -> Value {
+    let response = jmap_json_request(
+        format!(
+            r#"[["Email/get", {{"accountId": "{account}", "ids": ["{email_id}"], "properties": ["snoozedUntil"]}}, "0"]]"#
+        ),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    response
+        .pointer("/methodResponses/0/1/list/0/snoozedUntil")
+        .cloned()
+        .unwrap_or_else(|| panic!("{response}"))
+}
+
+async fn mailbox_ids(server: &Server, account_id: u32, document_id: u32) -> Vec<u32> {
+    server
+        .get_cached_messages(account_id)
+        .await
+        .unwrap()
+        .email_by_id(&document_id)
+        .unwrap()
+        .mailboxes
+        .iter()
+        .map(|m| m.mailbox_id)
+        .collect()
+}
@@ -0,0 +1,122 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Server, config::smtp::session::AddressMapping};
+use email::{
+    cache::{MessageCacheFetch, email::MessageCacheAccess, mailbox::MailboxCacheAccess},
+    mailbox::INBOX_ID,
+};
+use jmap_proto::types::id::Id;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{assert_is_empty, delivery::SmtpConnection, mailbox::destroy_all_mailboxes},
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running sub-address folder tests...");
+    let server = params.server.clone();
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+
+    // Enable filing and creating folders by sub-address
+    let core = params.server.inner.shared_core.load_full();
+    let mut subaddress_core = core.as_ref().clone();
+    subaddress_core.smtp.session.rcpt.subaddressing = AddressMapping::Enable;
+    subaddress_core.jmap.subaddress_folder = true;
+    subaddress_core.jmap.subaddress_folder_create = true;
+    let num_mailboxes = server
+        .get_cached_messages(account_id)
+        .await
+        .unwrap()
+        .mailboxes
+        .items
+        .len();
+    subaddress_core.jmap.subaddress_folder_max = num_mailboxes + 1;
+    params
+        .server
+        .inner
+        .shared_core
+        .store(subaddress_core.into());
+
+    // Top-level user folders are created on first use
+    let mut lmtp = SmtpConnection::connect().await;
+    deliver(&mut lmtp, "jdoe+Projects@example.com").await;
+    let projects_id = mailbox_id(&server, account_id, "Projects").await.unwrap();
+    deliver(&mut lmtp, "jdoe+projects@example.com").await;
+    assert_eq!(num_emails(&server, account_id, projects_id).await, 2);
+
+    // System folders, nested folders and folders over the limit are not used
+    for rcpt in [
+        "jdoe+Drafts@example.com",
+        "jdoe+Projects/2025@example.com",
+        "jdoe+Invoices@example.com",
+    ] {
+        deliver(&mut lmtp, rcpt).await;
+    }
+    assert_eq!(mailbox_id(&server, account_id, "Projects/2025").await, None);
+    assert_eq!(mailbox_id(&server, account_id, "Invoices").await, None);
+    let drafts_id = mailbox_id(&server, account_id, "Drafts").await.unwrap();
+    assert_eq!(num_emails(&server, account_id, drafts_id).await, 0);
+    assert_eq!(num_emails(&server, account_id, INBOX_ID).await, 3);
+
+    // Restore the configuration
+    params.server.inner.shared_core.store(core);
+
+    // Remove test data
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn deliver(lmtp: &mut SmtpConnection, rcpt: &str) {
+    lmtp.ingest(
+        "bill@remote.org",
+        &[rcpt],
+        &format!(
+            concat!(
+                "From: bill@remote.org\r\n",
+                "To: {}\r\n",
+                "Subject: Filed by sub-address\r\n",
+                "\r\n",
+                "Test message.\r\n"
+            ),
+            rcpt
+        ),
+    )
+    .await;
+}
+
+async fn mailbox_id(server: &Server, account_id: u32, path: &str) -> Option<u32> {
+    server
+        .get_cached_messages(account_id)
+        .await
+        .unwrap()
+        .mailbox_by_path(path)
+        .map(|mailbox| mailbox.document_id)
+}
+
+async fn num_emails(server: &Server, account_id: u32, mailbox_id: u32) -> usize {
+    server
+        .get_cached_messages(account_id)
+        .await
+        .unwrap()
+        .in_mailbox(mailbox_id)
+        .count()
+}
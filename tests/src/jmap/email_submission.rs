@@ -5,6 +5,7 @@
  */
 
 use ahash::AHashMap;
+use imap_proto::ResponseType;
 use jmap_client::{
     Error,
     core::set::{SetError, SetErrorType, SetObject},
@@ -27,6 +28,7 @@ use tokio::{
 
 use crate::{
     directory::internal::TestInternalDirectory,
+    imap::{AssertResult, ImapConnection, Type},
     jmap::{assert_is_empty, email_set::assert_email_properties, mailbox::destroy_all_mailboxes},
     smtp::DnsCache,
 };
@@ -439,6 +441,45 @@ pub async fn test(params: &mut JMAPTest) {
         ),])
     );
 
+    // Held messages are listed in the scheduled IMAP folder
+    let mut imap = ImapConnection::connect(b"_x ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.send("LOGIN \"jdoe@example.com\" \"12345\"").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("LIST \"\" \"*\"").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_folders([("Scheduled", ["\\Scheduled"])], false);
+    imap.send("SELECT Scheduled").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXISTS");
+
+    // Scheduled messages cannot be moved out of the folder
+    imap.send("MOVE 1 INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::No).await;
+
+    // Expunging a scheduled message cancels the submission and keeps the message
+    imap.send("STORE 1 +FLAGS (\\Deleted)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("EXPUNGE").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let email_submission = client
+        .email_submission_get(&email_submission_id, None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        email_submission.undo_status().unwrap(),
+        &UndoStatus::Canceled
+    );
+    assert_email_properties(client, &email_id, &[&mailbox_id], &[]).await;
+    imap.send("SELECT Scheduled").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 0 EXISTS");
+    imap.send("LOGOUT").await;
+
     // Verify onSuccessUpdateEmail action
     let mut request = client.build();
     let set_request = request.set_email_submission();
@@ -103,6 +103,7 @@ pub async fn test(params: &mut JMAPTest) {
             retention: Some(Duration::from_secs(1)),
             store: core.storage.data.clone(),
             interval: SimpleCron::Day { hour: 0, minute: 0 },
+            usage: false,
         }
         .into(),
         metrics_alerts: parse_metric_alerts(&mut config),
@@ -424,6 +425,7 @@ async fn undelete(_params: &mut JMAPTest) {
                 collection: deleted.collection,
                 time: deleted.deleted_at,
                 cancel_deletion: deleted.expires_at.into(),
+                mailbox: deleted.mailbox,
             }],
         )
         .await
@@ -445,6 +447,50 @@ async fn undelete(_params: &mut JMAPTest) {
     imap.assert_read(Type::Tagged, ResponseType::Ok)
         .await
         .assert_contains("Subject: undelete test");
+
+    // Delete a folder containing a message
+    imap.send("CREATE Projects").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send(&format!("APPEND Projects {{{}}}", RAW_MESSAGE.len()))
+        .await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged(RAW_MESSAGE).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("DELETE Projects").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    api.get::<serde_json::Value>("/api/store/purge/account/jdoe@example.com")
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+
+    // The original folder is recorded
+    let deleted = api
+        .get::<List<DeletedBlob<String, String, String>>>(
+            "/api/store/undelete/jdoe@example.com?mailbox=Projects",
+        )
+        .await
+        .unwrap()
+        .unwrap_data()
+        .items;
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].mailbox.as_deref(), Some("Projects"));
+
+    // Restore the whole folder
+    let result = api
+        .post::<Vec<UndeleteResponse>>(
+            "/api/store/undelete/jdoe@example.com?mailbox=Projects",
+            &None::<Vec<UndeleteRequest<String, String, String>>>,
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(result, vec![UndeleteResponse::Success]);
+
+    // Make sure the folder was recreated
+    imap.send("STATUS Projects (MESSAGES)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("MESSAGES 1");
 }
 
 pub async fn insert_test_metrics(core: Arc<Core>) {
@@ -0,0 +1,158 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Server;
+use directory::backend::internal::{PrincipalField, PrincipalUpdate, PrincipalValue};
+use email::identity::Identity;
+use jmap::identity::get::IdentityGet;
+use jmap_proto::types::{collection::Collection, id::Id};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{ManagementApi, assert_is_empty, mailbox::destroy_all_mailboxes},
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running delegated identity tests...");
+    let server = params.server.clone();
+    let store = &server.core.storage.data;
+    let john_id = store
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    store
+        .create_test_user(
+            "jane@example.com",
+            "abcde",
+            "Jane Smith",
+            &["jane@example.com"],
+        )
+        .await;
+    params
+        .client
+        .set_default_account_id(Id::from(john_id).to_string());
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    // Identities are created for the account's own addresses
+    server.identity_get_or_create(john_id).await.unwrap();
+    assert_eq!(
+        identity_emails(&server, john_id).await,
+        vec!["jdoe@example.com"]
+    );
+
+    // Granting a delegation creates identities for the delegator's current addresses
+    update(
+        &api,
+        "jane@example.com",
+        PrincipalUpdate::add_item(
+            PrincipalField::Emails,
+            PrincipalValue::String("jane.smith@example.com".into()),
+        ),
+    )
+    .await;
+    update(
+        &api,
+        "jdoe@example.com",
+        PrincipalUpdate::add_item(
+            PrincipalField::SendAs,
+            PrincipalValue::String("jane@example.com".into()),
+        ),
+    )
+    .await;
+    assert_eq!(
+        identity_emails(&server, john_id).await,
+        vec![
+            "jane.smith@example.com",
+            "jane@example.com",
+            "jdoe@example.com"
+        ]
+    );
+
+    // Identities removed by the user are not created again
+    let (document_id, _) = identities(&server, john_id)
+        .await
+        .into_iter()
+        .find(|(_, email)| email == "jane.smith@example.com")
+        .unwrap();
+    params
+        .client
+        .identity_destroy(&Id::from(document_id).to_string())
+        .await
+        .unwrap();
+    server.identity_get_or_create(john_id).await.unwrap();
+    assert_eq!(
+        identity_emails(&server, john_id).await,
+        vec!["jane@example.com", "jdoe@example.com"]
+    );
+
+    // Revoking the delegation removes the delegated identities
+    update(
+        &api,
+        "jdoe@example.com",
+        PrincipalUpdate::remove_item(
+            PrincipalField::SendAs,
+            PrincipalValue::String("jane@example.com".into()),
+        ),
+    )
+    .await;
+    assert_eq!(
+        identity_emails(&server, john_id).await,
+        vec!["jdoe@example.com"]
+    );
+
+    // Remove test data
+    for (document_id, _) in identities(&server, john_id).await {
+        params
+            .client
+            .identity_destroy(&Id::from(document_id).to_string())
+            .await
+            .unwrap();
+    }
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn update(api: &ManagementApi, name: &str, update: PrincipalUpdate) {
+    api.patch::<()>(&format!("/api/principal/{name}"), &vec![update])
+        .await
+        .unwrap()
+        .unwrap_data();
+}
+
+// Returns the identities of an account sorted by address
+async fn identities(server: &Server, account_id: u32) -> Vec<(u32, String)> {
+    let mut identities = Vec::new();
+    for document_id in server
+        .get_document_ids(account_id, Collection::Identity)
+        .await
+        .unwrap()
+        .unwrap_or_default()
+    {
+        let identity = server
+            .get_archive(account_id, Collection::Identity, document_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let email = identity.unarchive::<Identity>().unwrap().email.to_string();
+        identities.push((document_id, email));
+    }
+    identities.sort_unstable_by(|a, b| a.1.cmp(&b.1));
+    identities
+}
+
+async fn identity_emails(server: &Server, account_id: u32) -> Vec<String> {
+    identities(server, account_id)
+        .await
+        .into_iter()
+        .map(|(_, email)| email)
+        .collect()
+}
@@ -0,0 +1,167 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::backend::internal::{PrincipalField, PrincipalUpdate, PrincipalValue};
+use email::mailbox::INBOX_ID;
+use jmap_proto::types::id::Id;
+use serde_json::{Value, json};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{ManagementApi, assert_is_empty, jmap_json_request, mailbox::destroy_all_mailboxes},
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Identity/set tests...");
+    let server = params.server.clone();
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+            &["jdoe@example.com"],
+        )
+        .await;
+    params
+        .client
+        .set_default_account_id(Id::from(account_id).to_string());
+    let api = ManagementApi::new(8899, "admin", "secret");
+    api.patch::<()>(
+        "/api/principal/jdoe@example.com",
+        &vec![PrincipalUpdate::add_item(
+            PrincipalField::AllowedSenders,
+            PrincipalValue::String("noreply@example.com".into()),
+        )],
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    let account_id = Id::from(account_id).to_string();
+
+    // Identities can only use the account's own addresses and its allowed senders
+    let response = identity_set(
+        &account_id,
+        json!({
+            "a": {"name": "No Reply", "email": "noreply@example.com"},
+            "b": {"name": "CEO", "email": "ceo@example.com"},
+            "c": {
+                "name": "John Doe",
+                "email": "jdoe@example.com",
+                "textSignature": "x".repeat(server.core.jmap.identity_max_signature_size + 1)
+            },
+            "d": {
+                "name": "John Doe",
+                "email": "jdoe@example.com",
+                "htmlSignature": "x".repeat(server.core.jmap.identity_max_signature_size)
+            },
+        }),
+    )
+    .await;
+    let noreply_id = response["created"]["a"]["id"].as_str().unwrap().to_string();
+    let jdoe_id = response["created"]["d"]["id"].as_str().unwrap().to_string();
+    assert_eq!(
+        response["notCreated"]["b"]["properties"],
+        json!(["email"]),
+        "{response}"
+    );
+    assert_eq!(
+        response["notCreated"]["c"]["properties"],
+        json!(["textSignature"]),
+        "{response}"
+    );
+
+    // The number of identities per account is limited
+    let core = params.server.inner.shared_core.load_full();
+    let mut limit_core = core.as_ref().clone();
+    limit_core.jmap.identity_max_identities = 2;
+    params.server.inner.shared_core.store(limit_core.into());
+    let response = identity_set(
+        &account_id,
+        json!({"e": {"name": "John Doe", "email": "jdoe@example.com"}}),
+    )
+    .await;
+    assert_eq!(
+        response["notCreated"]["e"]["type"], "overQuota",
+        "{response}"
+    );
+    params.server.inner.shared_core.store(core);
+
+    // Submissions are rejected once the address is no longer authorized
+    api.patch::<()>(
+        "/api/principal/jdoe@example.com",
+        &vec![PrincipalUpdate::remove_item(
+            PrincipalField::AllowedSenders,
+            PrincipalValue::String("noreply@example.com".into()),
+        )],
+    )
+    .await
+    .unwrap()
+    .unwrap_data();
+    let email_id = params
+        .client
+        .email_import(
+            concat!(
+                "From: noreply@example.com\r\n",
+                "To: jane@example.net\r\n",
+                "Subject: Unauthorized\r\n",
+                "\r\n",
+                "Test message.\r\n"
+            )
+            .as_bytes()
+            .to_vec(),
+            [Id::from(INBOX_ID).to_string()],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    let response = jmap_json_request(
+        json!([[
+            "EmailSubmission/set",
+            {
+                "accountId": account_id,
+                "create": {"s": {"emailId": email_id, "identityId": noreply_id}}
+            },
+            "0"
+        ]])
+        .to_string(),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response["methodResponses"][0][1]["notCreated"]["s"]["type"], "forbiddenFrom",
+        "{response}"
+    );
+
+    // Remove test data
+    for identity_id in [noreply_id, jdoe_id] {
+        params.client.identity_destroy(&identity_id).await.unwrap();
+    }
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn identity_set(account_id: &str, create: Value) -> Value {
+    let mut response = jmap_json_request(
+        json!([[
+            "Identity/set",
+            {"accountId": account_id, "create": create},
+            "0"
+        ]])
+        .to_string(),
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    response["methodResponses"][0][1].take()
+}
@@ -50,25 +50,35 @@ use webhooks::{MockWebhookEndpoint, spawn_mock_webhook_endpoint};
 pub mod auth_acl;
 pub mod auth_limits;
 pub mod auth_oauth;
+pub mod autocrypt;
 pub mod blob;
 pub mod crypto;
 pub mod delivery;
+pub mod disposable_alias;
 pub mod email_changes;
 pub mod email_copy;
 pub mod email_get;
+pub mod email_mdn;
 pub mod email_parse;
 pub mod email_query;
 pub mod email_query_changes;
+pub mod email_recall;
 pub mod email_search_snippet;
 pub mod email_set;
+pub mod email_snooze;
+pub mod email_subaddress;
 pub mod email_submission;
 pub mod enterprise;
 pub mod event_source;
+pub mod identity_delegation;
+pub mod identity_set;
 pub mod mailbox;
 pub mod permissions;
 pub mod purge;
 pub mod push_subscription;
 pub mod quota;
+pub mod sessions;
+pub mod settings;
 pub mod sieve_script;
 pub mod thread_get;
 pub mod thread_merge;
@@ -111,7 +121,17 @@ async fn jmap_tests() {
     quota::test(&mut params).await;
     crypto::test(&mut params).await;
     blob::test(&mut params).await;
+    email_snooze::test(&mut params).await;
+    email_recall::test(&mut params).await;
+    email_mdn::test(&mut params).await;
+    email_subaddress::test(&mut params).await;
+    disposable_alias::test(&mut params).await;
+    identity_delegation::test(&mut params).await;
+    identity_set::test(&mut params).await;
+    autocrypt::test(&mut params).await;
     permissions::test(&params).await;
+    sessions::test(&params).await;
+    settings::test(&params).await;
     purge::test(&mut params).await;
     enterprise::test(&mut params).await;
 
@@ -574,6 +594,23 @@ impl ManagementApi {
         })
     }
 
+    pub async fn put<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        body: &impl Serialize,
+    ) -> Result<Response<T>, String> {
+        self.request_raw(
+            Method::PUT,
+            query,
+            Some(serde_json::to_string(body).unwrap()),
+        )
+        .await
+        .map(|result| {
+            serde_json::from_str::<Response<T>>(&result)
+                .unwrap_or_else(|err| panic!("{err}: {result}"))
+        })
+    }
+
     pub async fn delete<T: DeserializeOwned>(&self, query: &str) -> Result<Response<T>, String> {
         self.request_raw(Method::DELETE, query, None)
             .await
@@ -898,6 +935,22 @@ attempts.interval = "500ms"
 [email]
 auto-expunge = "1s"
 
+[email.folders.junk]
+name = "Junk Mail"
+protect = false
+
+[email.folders.drafts]
+name = "Drafts"
+protect = false
+
+[email.folders.sent]
+name = "Sent Items"
+protect = false
+
+[email.folders.archive]
+name = "Archive"
+protect = false
+
 [changes]
 max-history = "1"
 
@@ -8,19 +8,30 @@ use super::JMAPTest;
 use crate::{
     directory::internal::TestInternalDirectory,
     imap::{AssertResult, ImapConnection, Type},
-    jmap::assert_is_empty,
+    jmap::{ManagementApi, assert_is_empty},
 };
 use ahash::AHashSet;
 use common::Server;
-use directory::{QueryBy, backend::internal::manage::ManageDirectory};
+use directory::{
+    QueryBy,
+    backend::internal::{
+        PrincipalField, PrincipalUpdate, PrincipalValue,
+        manage::{ManageDirectory, UpdatePrincipal},
+    },
+};
 use email::{
     cache::{MessageCacheFetch, email::MessageCacheAccess},
     mailbox::{INBOX_ID, JUNK_ID, TRASH_ID},
     message::delete::EmailDeletion,
 };
+use http::management::redact::{RedactAction, RedactRequest, RedactResult};
 use imap_proto::ResponseType;
 use jmap_proto::types::{collection::Collection, id::Id};
-use store::{IterateParams, LogKey, U32_LEN, U64_LEN, write::key::DeserializeBigEndian};
+use store::{
+    IterateParams, LogKey, U32_LEN, U64_LEN,
+    roaring::RoaringBitmap,
+    write::{BatchBuilder, key::DeserializeBigEndian},
+};
 
 pub async fn test(params: &mut JMAPTest) {
     println!("Running purge tests...");
@@ -116,6 +127,40 @@ pub async fn test(params: &mut JMAPTest) {
         6
     );
 
+    // Tombstones are not purged while the account is under legal hold
+    set_legal_hold(&server, account_id, true).await;
+    assert!(
+        server
+            .get_access_token(account_id)
+            .await
+            .unwrap()
+            .legal_hold
+            .is_some()
+    );
+    server.purge_account(account_id).await;
+    let cache = server.get_cached_messages(account_id).await.unwrap();
+    assert_eq!(
+        server
+            .get_document_ids(account_id, Collection::Email)
+            .await
+            .unwrap()
+            .unwrap()
+            .len(),
+        6
+    );
+    assert_eq!(cache.in_mailbox(TRASH_ID).count(), 1);
+    assert_eq!(cache.in_mailbox(JUNK_ID).count(), 1);
+
+    // Accounts under legal hold cannot be deleted
+    let api = ManagementApi::new(8899, "admin", "secret");
+    let (_, details, _) = api
+        .delete::<()>("/api/principal/jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap_error();
+    assert_eq!(details.as_deref(), Some("Account is under legal hold"));
+    set_legal_hold(&server, account_id, false).await;
+
     // Purge junk/trash messages and old changes
     server.purge_account(account_id).await;
     let cache = server.get_cached_messages(account_id).await.unwrap();
@@ -157,6 +202,97 @@ pub async fn test(params: &mut JMAPTest) {
         );
     }
 
+    // Tombstones not created by a redaction are left for the regular purge
+    let junk_document_id = cache.in_mailbox(JUNK_ID).next().unwrap().document_id;
+    let mut batch = BatchBuilder::new();
+    server
+        .emails_tombstone(
+            account_id,
+            &mut batch,
+            RoaringBitmap::from_iter([junk_document_id]),
+        )
+        .await
+        .unwrap();
+    server.commit_batch(batch).await.unwrap();
+
+    // Redact a message by replacing it with a stub
+    client
+        .email_import(
+            concat!(
+                "From: bill@example.com\r\n",
+                "To: jdoe@example.com\r\n",
+                "Message-ID: <redact-test@example.com>\r\n",
+                "Subject: Customer records\r\n",
+                "\r\n",
+                "Here is the full customer database."
+            )
+            .as_bytes()
+            .to_vec(),
+            [&inbox_id],
+            None::<Vec<&str>>,
+            None,
+        )
+        .await
+        .unwrap();
+    let result = api
+        .post::<Vec<RedactResult>>(
+            "/api/store/redact",
+            &RedactRequest {
+                message_id: "<redact-test@example.com>".into(),
+                action: RedactAction::Replace,
+                reason: Some("Sent in error".into()),
+            },
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(
+        result,
+        vec![RedactResult {
+            account: "jdoe@example.com".into(),
+            total: 1
+        }]
+    );
+    imap.send("SELECT INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("3 EXISTS");
+    imap.send("FETCH 3 BODY[]").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("Subject: [Redacted]")
+        .assert_contains("Reason: Sent in error")
+        .assert_count("customer database", 0)
+        .assert_count("redact-test@example.com", 0);
+    assert_eq!(
+        server
+            .get_document_ids(account_id, Collection::Email)
+            .await
+            .unwrap()
+            .unwrap()
+            .len(),
+        5
+    );
+
+    // Stubs are not matched by later redactions
+    let result = api
+        .post::<Vec<RedactResult>>(
+            "/api/store/redact",
+            &RedactRequest {
+                message_id: "redact-test@example.com".into(),
+                action: RedactAction::Delete,
+                reason: None,
+            },
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(result, vec![]);
+    imap.send("STATUS INBOX (MESSAGES)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("MESSAGES 3");
+
     // Delete account
     server
         .core
@@ -168,6 +304,22 @@ pub async fn test(params: &mut JMAPTest) {
     assert_is_empty(server).await;
 }
 
+async fn set_legal_hold(server: &Server, account_id: u32, hold: bool) {
+    let changes = server
+        .core
+        .storage
+        .data
+        .update_principal(UpdatePrincipal::by_id(account_id).with_updates(vec![
+            PrincipalUpdate::set(
+                PrincipalField::LegalHold,
+                PrincipalValue::Integer(hold as u64),
+            ),
+        ]))
+        .await
+        .unwrap();
+    server.invalidate_principal_caches(changes).await;
+}
+
 async fn get_changes(server: &Server) -> (AHashSet<(u64, u8)>, bool) {
     let mut changes = AHashSet::new();
     let mut is_truncated = false;
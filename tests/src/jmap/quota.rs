@@ -13,6 +13,10 @@ use crate::{
     smtp::queue::QueuedEvents,
 };
 use common::config::smtp::queue::QueueName;
+use directory::backend::internal::{
+    PrincipalField, PrincipalUpdate, PrincipalValue,
+    manage::{ManageDirectory, UpdatePrincipal},
+};
 use email::mailbox::INBOX_ID;
 use jmap::blob::upload::DISABLE_UPLOAD_QUOTA;
 use jmap_client::{
@@ -127,6 +131,7 @@ pub async fn test(params: &mut JMAPTest) {
     .await;
     assert!(response.contains("\"used\":0"), "{}", response);
     assert!(response.contains("\"hardLimit\":1024"), "{}", response);
+    assert!(response.contains("\"softLimit\":921"), "{}", response);
     assert!(response.contains("\"scope\":\"account\""), "{}", response);
     assert!(
         response.contains("\"name\":\"robert@example.com\""),
@@ -134,6 +139,35 @@ pub async fn test(params: &mut JMAPTest) {
         response
     );
 
+    // Accounts can have their own soft limit
+    let changed_principals = server
+        .store()
+        .update_principal(
+            UpdatePrincipal::by_name("robert@example.com").with_updates(vec![
+                PrincipalUpdate::set(PrincipalField::SoftQuota, PrincipalValue::Integer(512)),
+            ]),
+        )
+        .await
+        .unwrap();
+    server.invalidate_principal_caches(changed_principals).await;
+    let response = jmap_raw_request(
+        r#"[[ "Quota/get", {
+            "accountId": "$$",
+            "ids": null
+          }, "0" ]]"#
+            .replace("$$", &account_id.to_string()),
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    assert!(response.contains("\"softLimit\":512"), "{}", response);
+    assert!(
+        !server
+            .is_over_soft_quota(account_id.document_id(), 1024, Some(512))
+            .await
+            .unwrap()
+    );
+
     // Test Email/import quota
     let inbox_id = Id::new(INBOX_ID as u64).to_string();
     let mut message_ids = Vec::new();
@@ -322,6 +356,77 @@ pub async fn test(params: &mut JMAPTest) {
         0
     );
 
+    // Test per-account message count quota
+    let changed_principals = server
+        .store()
+        .update_principal(
+            UpdatePrincipal::by_name("robert@example.com").with_updates(vec![
+                PrincipalUpdate::set(PrincipalField::MessageQuota, PrincipalValue::Integer(1)),
+            ]),
+        )
+        .await
+        .unwrap();
+    server.invalidate_principal_caches(changed_principals).await;
+    let message_id = client
+        .email_import(
+            create_message_with_size("jane@example.com", "robert@example.com", "Count 1", 10),
+            vec![&inbox_id],
+            None::<Vec<String>>,
+            None,
+        )
+        .await
+        .unwrap()
+        .take_id();
+    assert_over_quota(
+        client
+            .email_import(
+                create_message_with_size("jane@example.com", "robert@example.com", "Count 2", 10),
+                vec![&inbox_id],
+                None::<Vec<String>>,
+                None,
+            )
+            .await,
+    );
+    let request = r#"[[ "Quota/get", {
+            "accountId": "$$",
+            "ids": ["b"]
+          }, "0" ]]"#
+        .replace("$$", &account_id.to_string());
+    let response = jmap_raw_request(request.clone(), "robert@example.com", "aabbcc").await;
+    assert!(
+        response.contains("\"resourceType\":\"count\""),
+        "{}",
+        response
+    );
+    assert!(response.contains("\"used\":1"), "{}", response);
+    assert!(response.contains("\"hardLimit\":1"), "{}", response);
+
+    // Quota states are stable while usage does not change
+    let state = |response: &str| {
+        serde_json::from_str::<serde_json::Value>(response).unwrap()["methodResponses"][0][1]
+            ["state"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    assert_eq!(
+        state(&response),
+        state(&jmap_raw_request(request, "robert@example.com", "aabbcc").await)
+    );
+
+    client.email_destroy(&message_id).await.unwrap();
+    emails_purge_tombstoned(&server).await;
+    let changed_principals = server
+        .store()
+        .update_principal(
+            UpdatePrincipal::by_name("robert@example.com").with_updates(vec![
+                PrincipalUpdate::set(PrincipalField::MessageQuota, PrincipalValue::Integer(0)),
+            ]),
+        )
+        .await
+        .unwrap();
+    server.invalidate_principal_caches(changed_principals).await;
+
     // Test delivery quota
     let mut lmtp = SmtpConnection::connect().await;
     for i in 0..2 {
@@ -0,0 +1,120 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::{QueryBy, backend::internal::manage::ManageDirectory};
+use imap_proto::ResponseType;
+use serde_json::Value;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    imap::{ImapConnection, Type},
+    jmap::{ManagementApi, assert_is_empty},
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &JMAPTest) {
+    println!("Running session management tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+    let account_id = server
+        .core
+        .storage
+        .data
+        .create_test_user(
+            "sessions@example.com",
+            "secret",
+            "Session Test",
+            &["sessions@example.com"],
+        )
+        .await;
+
+    // Open two IMAP sessions for the account
+    let mut imap_a = ImapConnection::connect(b"_a ").await;
+    let mut imap_b = ImapConnection::connect(b"_b ").await;
+    for imap in [&mut imap_a, &mut imap_b] {
+        imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+        imap.authenticate("sessions@example.com", "secret").await;
+    }
+
+    // List sessions by account
+    let sessions = api
+        .get::<Value>("/api/sessions?account=sessions@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(sessions["total"], 2, "{sessions:?}");
+    let items = sessions["items"].as_array().unwrap();
+    for item in items {
+        assert_eq!(item["protocol"], "imap");
+        assert_eq!(item["account"], "sessions@example.com");
+        assert_eq!(item["remoteIp"], "127.0.0.1");
+    }
+
+    // Terminating every session at once requires a filter
+    api.delete::<Value>("/api/sessions")
+        .await
+        .unwrap()
+        .expect_error("At least one session filter is required");
+
+    // Terminate a single session
+    let session_id = items[0]["id"].as_str().unwrap().to_string();
+    api.delete::<Value>(&format!("/api/sessions/{session_id}"))
+        .await
+        .unwrap()
+        .unwrap_data();
+    let sessions = api
+        .get::<Value>("/api/sessions?account=sessions@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(sessions["total"], 1, "{sessions:?}");
+    assert_ne!(sessions["items"][0]["id"], session_id.as_str());
+    assert_eq!(
+        api.delete::<Value>(&format!("/api/sessions/{session_id}"))
+            .await
+            .unwrap()
+            .unwrap_request_error()
+            .status,
+        404
+    );
+
+    // The terminated session is closed, the other one keeps working
+    let (terminated, active) = if sessions["items"][0]["id"] == items[1]["id"] {
+        (&mut imap_a, &mut imap_b)
+    } else {
+        (&mut imap_b, &mut imap_a)
+    };
+    terminated.assert_disconnected().await;
+    active.send("NOOP").await;
+    active.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Terminate the remaining sessions by account
+    let terminated = api
+        .delete::<u64>("/api/sessions?account=sessions@example.com")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(terminated, 1);
+    active.assert_disconnected().await;
+    assert_eq!(
+        api.get::<Value>("/api/sessions?account=sessions@example.com")
+            .await
+            .unwrap()
+            .unwrap_data()["total"],
+        0
+    );
+
+    // Remove test data
+    server
+        .core
+        .storage
+        .data
+        .delete_principal(QueryBy::Id(account_id))
+        .await
+        .unwrap();
+    assert_is_empty(server).await;
+}
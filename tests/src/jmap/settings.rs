@@ -0,0 +1,113 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::collections::BTreeMap;
+
+use futures::future::join_all;
+use serde_json::{Value, json};
+
+use crate::jmap::ManagementApi;
+
+use super::JMAPTest;
+
+pub async fn test(params: &JMAPTest) {
+    println!("Running settings history tests...");
+    let server = params.server.clone();
+    let config = &server.core.storage.config;
+    let api = ManagementApi::new(8899, "admin", "secret");
+    let latest_id = || async {
+        config
+            .versions()
+            .await
+            .unwrap()
+            .first()
+            .map_or(0, |version| version.id)
+    };
+
+    // Concurrent changes are recorded under distinct versions
+    let base_id = latest_id().await;
+    let mut ids = join_all((0..10).map(|i| {
+        config.record_version(
+            "admin",
+            Some(format!("Change {i}")),
+            BTreeMap::from([(format!("test.history.concurrent.{i}"), None)]),
+        )
+    }))
+    .await
+    .into_iter()
+    .map(|id| id.unwrap())
+    .collect::<Vec<_>>();
+    ids.sort_unstable();
+    assert_eq!(ids, (base_id + 1..=base_id + 10).collect::<Vec<_>>());
+
+    // Updates record the values the keys had before the change
+    for value in ["1", "2"] {
+        api.post::<Value>(
+            "/api/settings",
+            &json!([{
+                "type": "insert",
+                "prefix": null,
+                "values": [["test.history.key", value], ["test.history.password", value]],
+                "assert_empty": false
+            }]),
+        )
+        .await
+        .unwrap()
+        .unwrap_data();
+    }
+    let first_id = base_id + 11;
+    let history = api
+        .get::<Value>("/api/settings/history?limit=2")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(history["items"][0]["id"], first_id + 1, "{history}");
+    assert_eq!(history["items"][0]["author"], "admin", "{history}");
+    assert_eq!(
+        history["items"][0]["previous"],
+        json!({"test.history.key": "1", "test.history.password": "********"}),
+        "{history}"
+    );
+    assert_eq!(
+        history["items"][1]["previous"],
+        json!({"test.history.key": null, "test.history.password": null}),
+        "{history}"
+    );
+
+    // Rolling back a version also reverts the versions that followed it
+    assert_eq!(config.rollback(u64::MAX, "admin").await.unwrap(), None);
+    let rollback_id = config.rollback(first_id, "admin").await.unwrap().unwrap();
+    assert_eq!(rollback_id, first_id + 2);
+    assert_eq!(config.get("test.history.key").await.unwrap(), None);
+    assert_eq!(config.get("test.history.password").await.unwrap(), None);
+    let version = config.versions().await.unwrap().remove(0);
+    assert_eq!(version.id, rollback_id);
+    assert_eq!(
+        version.description.as_deref(),
+        Some(format!("Rollback of version {first_id}").as_str())
+    );
+    assert_eq!(
+        version.previous.get("test.history.key"),
+        Some(&Some("2".to_string()))
+    );
+
+    // Rolling back the rollback restores the latest values
+    config
+        .rollback(rollback_id, "admin")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        config.get("test.history.key").await.unwrap().as_deref(),
+        Some("2")
+    );
+    assert_eq!(latest_id().await, rollback_id + 1);
+
+    // Remove test data
+    config.clear_prefix("test.history.").await.unwrap();
+    config.clear_prefix("@history.").await.unwrap();
+    assert_eq!(latest_id().await, 0);
+}
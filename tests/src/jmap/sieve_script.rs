@@ -4,6 +4,10 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use ::email::message::forward::{EmailForwarding, ForwardingSettings};
+use common::config::scripts::{SieveLayers, SieveNotify};
+use http_proto::HttpResponse;
+use hyper::StatusCode;
 use jmap_client::{
     Error,
     core::set::{SetError, SetErrorType},
@@ -14,15 +18,24 @@ use jmap_proto::types::id::Id;
 use std::{
     fs,
     path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
     time::{Duration, Instant},
 };
+use utils::config::Config;
 
 use crate::{
     directory::internal::TestInternalDirectory,
+    http_server::{HttpMessage, spawn_mock_http_server},
     jmap::{
         assert_is_empty,
         delivery::SmtpConnection,
-        email_submission::{MockMessage, assert_message_delivery, spawn_mock_smtp_server},
+        email_submission::{
+            MockMessage, assert_message_delivery, expect_message_delivery, expect_nothing,
+            spawn_mock_smtp_server,
+        },
         mailbox::destroy_all_mailboxes,
     },
     smtp::DnsCache,
@@ -426,6 +439,61 @@ pub async fn test(params: &mut JMAPTest) {
         "Redirected message was stored."
     );
 
+    // Spam is not forwarded when a Sieve script is active
+    let account_document_id = Id::from_bytes(account_id.as_bytes()).unwrap().document_id();
+    client
+        .sieve_script_create("test_forward", "discard;\r\n", true)
+        .await
+        .unwrap();
+    server
+        .set_forwarding(
+            account_document_id,
+            ForwardingSettings {
+                addresses: vec!["jane@remote.org".into()],
+                keep_copy: true,
+                expires: None,
+            },
+        )
+        .await
+        .unwrap();
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Cheap TPS reports\r\n",
+            "X-Spam-Status: Yes, score=13.9\r\n",
+            "\r\n",
+            "Buy TPS reports now."
+        ),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: TPS reports cover sheet\r\n",
+            "\r\n",
+            "Did you get the memo about the new cover sheets?"
+        ),
+    )
+    .await;
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.rcpt_to, vec!["<jane@remote.org>".to_string()]);
+    assert!(
+        message.message.contains("TPS reports cover sheet"),
+        "{}",
+        message.message
+    );
+    server
+        .set_forwarding(account_document_id, ForwardingSettings::default())
+        .await
+        .unwrap();
+
     // Run notify + editheader + notify + fcc tests
     client
         .sieve_script_create("test_notify_fcc", get_script("test_notify_fcc"), true)
@@ -509,6 +577,197 @@ pub async fn test(params: &mut JMAPTest) {
         panic!("Email {:?} not found in: {:#?}", subject, emails);
     }
 
+    // Run mime + foreverypart + extracttext tests
+    client
+        .sieve_script_create("test_mime", get_script("test_mime"), true)
+        .await
+        .unwrap();
+    lmtp.ingest(
+        "billing@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: billing@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Your monthly statement\r\n",
+            "MIME-Version: 1.0\r\n",
+            "Content-Type: multipart/mixed; boundary=\"boundary\"\r\n",
+            "\r\n",
+            "--boundary\r\n",
+            "Content-Type: text/plain; charset=utf-8\r\n",
+            "\r\n",
+            "Please find attached your statement. Amount due: $42.\r\n",
+            "--boundary\r\n",
+            "Content-Type: application/pdf\r\n",
+            "Content-Disposition: attachment; filename=\"invoice-1234.pdf\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "JVBERi0xLjQK\r\n",
+            "--boundary--\r\n"
+        ),
+    )
+    .await;
+
+    let mailbox_id = client
+        .mailbox_query(
+            mailbox::query::Filter::name("Accounting").into(),
+            None::<Vec<_>>,
+        )
+        .await
+        .unwrap()
+        .take_ids()
+        .pop()
+        .expect("Accounting mailbox was not created");
+    let email_id = client
+        .email_query(
+            email::query::Filter::in_mailbox(&mailbox_id).into(),
+            None::<Vec<_>>,
+        )
+        .await
+        .unwrap()
+        .take_ids()
+        .pop()
+        .expect("Invoice was not filed into Accounting");
+    let email = client
+        .email_get(&email_id, [email::Property::Keywords].into())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(
+        email.keywords().contains(&"$invoice") && email.keywords().contains(&"$due"),
+        "Unexpected keywords: {:#?}",
+        email
+    );
+
+    // Run the administrator defined layers around the user's script
+    let core = server.inner.shared_core.load_full();
+    let mut layered_core = core.as_ref().clone();
+    layered_core.sieve.delivery_layers = SieveLayers::parse(
+        &mut Config::new(concat!(
+            "[sieve.trusted.scripts.layer_before]\n",
+            "contents = \"require \\\"imap4flags\\\";\\r\\naddflag \\\"$layered\\\";\\r\\n\"\n",
+            "[sieve.trusted.scripts.layer_after]\n",
+            "contents = \"require [\\\"fileinto\\\", \\\"mailbox\\\"];\\r\\n",
+            "fileinto :create \\\"Layered\\\";\\r\\n\"\n",
+            "[sieve.trusted.scripts.layer_other]\n",
+            "contents = \"discard;\\r\\n\"\n",
+            "[sieve.trusted.layers]\n",
+            "before = \"layer_before\"\n",
+            "[sieve.trusted.layers.domain.\"example.com\"]\n",
+            "after = \"layer_after\"\n",
+            "[sieve.trusted.layers.domain.\"other.org\"]\n",
+            "after = \"layer_other\"\n",
+        ))
+        .unwrap(),
+        &layered_core.sieve.untrusted_compiler,
+    );
+    assert!(layered_core.sieve.delivery_layers.applies_to("example.com"));
+    server.inner.shared_core.store(layered_core.into());
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Layered delivery\r\n",
+            "\r\n",
+            "Global before, user and domain after scripts."
+        ),
+    )
+    .await;
+    server.inner.shared_core.store(core);
+
+    let mailbox_id = client
+        .mailbox_query(
+            mailbox::query::Filter::name("Layered").into(),
+            None::<Vec<_>>,
+        )
+        .await
+        .unwrap()
+        .take_ids()
+        .pop()
+        .expect("Layered mailbox was not created by the domain layer");
+    let email_id = client
+        .email_query(
+            email::query::Filter::in_mailbox(&mailbox_id).into(),
+            None::<Vec<_>>,
+        )
+        .await
+        .unwrap()
+        .take_ids()
+        .pop()
+        .expect("Message was not filed by the domain layer");
+    let email = client
+        .email_get(
+            &email_id,
+            [email::Property::Keywords, email::Property::MailboxIds].into(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        email.keywords(),
+        vec!["$layered"],
+        "Global layer flags were not kept: {:#?}",
+        email
+    );
+    assert_eq!(
+        email.mailbox_ids(),
+        vec![mailbox_id.as_str()],
+        "Implicit keep was not cancelled by the domain layer: {:#?}",
+        email
+    );
+
+    // Webhook notifications can't reach internal addresses, even when allowed
+    let webhook_requests = Arc::new(AtomicUsize::new(0));
+    let webhook_requests_ = webhook_requests.clone();
+    let _tx = spawn_mock_http_server(Arc::new(move |_: HttpMessage| {
+        webhook_requests_.fetch_add(1, Ordering::Relaxed);
+        HttpResponse::new(StatusCode::OK)
+    }))
+    .await;
+    let core = server.inner.shared_core.load_full();
+    let mut notify_core = core.as_ref().clone();
+    notify_core.sieve.notify = SieveNotify::parse(
+        &mut Config::new(concat!(
+            "[sieve.untrusted.notify]\n",
+            "allowed-hosts = [\"localhost\", \"127.0.0.1\"]\n",
+            "allow-invalid-certs = true\n",
+        ))
+        .unwrap(),
+    );
+    assert!(notify_core.sieve.notify.client.is_some());
+    server.inner.shared_core.store(notify_core.into());
+    client
+        .sieve_script_create(
+            "test_notify_webhook",
+            concat!(
+                "require \"enotify\";\r\n",
+                "notify \"https://localhost:9090/alert\";\r\n",
+                "notify \"https://127.0.0.1:9090/alert\";\r\n",
+                "notify \"https://hooks.example.com/alert\";\r\n",
+            )
+            .as_bytes()
+            .to_vec(),
+            true,
+        )
+        .await
+        .unwrap();
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: jdoe@example.com\r\n",
+            "Subject: Webhook notification\r\n",
+            "\r\n",
+            "This should not reach any internal service."
+        ),
+    )
+    .await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    server.inner.shared_core.store(core);
+    assert_eq!(webhook_requests.load(Ordering::Relaxed), 0);
+
     // Remove test data
     client.sieve_script_deactivate().await.unwrap();
     let mut request = client.build();
@@ -77,6 +77,29 @@ pub async fn test(params: &mut JMAPTest) {
         .unwrap()
         .take_id();
 
+    // Multiple requests can be in flight on the same socket
+    let mut request_ids = AHashSet::new();
+    for _ in 0..3 {
+        let mut request = client.build();
+        request.get_mailbox().ids([&mailbox_id]);
+        request_ids.insert(request.send_ws().await.unwrap());
+    }
+    for _ in 0..3 {
+        let mut response = expect_response(&mut stream_rx).await;
+        assert!(request_ids.remove(response.request_id().unwrap()));
+        assert_eq!(
+            response
+                .pop_method_response()
+                .unwrap()
+                .unwrap_get_mailbox()
+                .unwrap()
+                .list()
+                .len(),
+            1
+        );
+    }
+    assert!(request_ids.is_empty());
+
     // Enable push notifications
     client
         .enable_push_ws(None::<Vec<_>>, None::<&str>)
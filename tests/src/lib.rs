@@ -30,6 +30,8 @@ pub mod smtp;
 #[cfg(test)]
 pub mod store;
 #[cfg(test)]
+pub mod telemetry;
+#[cfg(test)]
 pub mod webdav;
 
 pub fn add_test_certs(config: &str) -> String {
@@ -537,6 +537,7 @@ async fn antispam() {
                         dmarc_policy.as_ref(),
                     )
                     .await
+                    .action
                 {
                     SpamFilterAction::Allow(header) => {
                         let mut last_ch = 'x';
@@ -4,7 +4,9 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use common::Core;
+use std::sync::Arc;
+
+use common::{Core, auth::AccessToken};
 
 use store::Stores;
 use utils::config::Config;
@@ -68,6 +70,25 @@ future-release = [{if = '!is_empty(authenticated_as)', then = '1d'},
                   {else = false}]
 "#;
 
+const SENDERS_CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/queue.db"
+
+[spam-filter]
+enable = false
+
+[session.auth]
+must-match-sender = true
+must-match-from = true
+"#;
+
 #[tokio::test]
 async fn auth() {
     // Enable logging
@@ -161,3 +182,51 @@ async fn auth() {
         .cmd("AUTH PLAIN AGpvaG4Ac2VjcmV0", "503 5.5.1")
         .await;
 }
+
+#[tokio::test]
+async fn allowed_senders() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_allowed_senders_test", true);
+    let mut config = Config::new(tmp_dir.update_config(SENDERS_CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+
+    let mut token = AccessToken::from_id(1);
+    token.name = "john".into();
+    token.emails = vec!["john@example.org".into(), "@example.net".into()];
+    token.allowed_senders = vec!["noreply@example.org".into(), "@example.com".into()];
+    let mut session = Session::test(TestSMTP::from_core(core).server);
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.example.org").await;
+    session.data.authenticated_as = Some(Arc::new(token));
+
+    // Granted senders and catch-all addresses can be used as the envelope sender
+    for sender in [
+        "john@example.org",
+        "noreply@example.org",
+        "anyone@example.net",
+    ] {
+        session.mail_from(sender, "250").await;
+        session.rset().await;
+    }
+
+    // Granted senders are matched exactly
+    session.mail_from("anyone@example.com", "501 5.5.4").await;
+    session.mail_from("bill@example.org", "501 5.5.4").await;
+
+    // The From header must match the envelope sender
+    session.mail_from("noreply@example.org", "250").await;
+    session.rcpt_to("jane@example.net", "250").await;
+    session.ingest(b"DATA\r\n").await.unwrap();
+    session.response().assert_code("354");
+    session
+        .ingest(b"From: john@example.org\r\nSubject: Hi\r\n\r\nHi!\r\n.\r\n")
+        .await
+        .unwrap();
+    assert!(session.stream.tx_buf.ends_with(b"sender.\r\n"));
+    session.response().assert_code("550 5.7.1");
+}
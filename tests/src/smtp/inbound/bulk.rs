@@ -0,0 +1,163 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Core;
+use smtp::{core::Session, reporting::feedback::FeedbackLoop};
+use store::Stores;
+use utils::config::Config;
+
+use crate::{
+    AssertConfig,
+    smtp::{
+        TempDir, TestSMTP,
+        inbound::TestMessage,
+        session::{TestSession, VerifyResponse},
+    },
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/queue.db"
+
+[spam-filter]
+enable = false
+
+[session.rcpt]
+relay = true
+
+[session.bulk."newsletter"]
+domains = ["news.foobar.org"]
+enable = true
+feedback-id = "weekly:newsletter:foobar"
+unsubscribe.url = "https://mx.foobar.org/unsubscribe"
+"#;
+
+#[tokio::test]
+async fn bulk_headers() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_bulk_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Single recipient messages include a one-click unsubscribe link
+    session
+        .send_message(
+            "john@news.foobar.org",
+            &["bill@example.org"],
+            "From: john@news.foobar.org\r\nSubject: Weekly news\r\n\r\nHi.\r\n",
+            "250",
+        )
+        .await;
+    let lines = qr.expect_message().await.read_lines(&qr).await;
+    let lines = lines
+        .assert_contains("Feedback-ID: weekly:newsletter:foobar")
+        .assert_contains("List-Unsubscribe: <https://mx.foobar.org/unsubscribe?t=")
+        .assert_contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click");
+    qr.clear_queue(&test.server).await;
+
+    // Unsubscribing suppresses further messages from the sender domain
+    let url = lines
+        .iter()
+        .find_map(|line| line.strip_prefix("List-Unsubscribe: <"))
+        .and_then(|line| line.trim_end().strip_suffix('>'))
+        .unwrap();
+    let token = form_urlencoded::parse(url.split_once('?').unwrap().1.as_bytes())
+        .find(|(key, _)| key == "t")
+        .map(|(_, value)| value.into_owned())
+        .unwrap();
+    assert!(
+        !test
+            .server
+            .is_suppressed("news.foobar.org", "bill@example.org", 0)
+            .await
+    );
+    test.server.unsubscribe(&token, 0).await.unwrap();
+    assert!(
+        test.server
+            .is_suppressed("news.foobar.org", "bill@example.org", 0)
+            .await
+    );
+    assert!(
+        !test
+            .server
+            .is_suppressed("foobar.org", "bill@example.org", 0)
+            .await
+    );
+    assert!(test.server.unsubscribe("invalid", 0).await.is_err());
+
+    // Multiple recipients cannot share an unsubscribe link
+    session
+        .send_message(
+            "john@news.foobar.org",
+            &["bill@example.org", "jane@example.org"],
+            "From: john@news.foobar.org\r\nSubject: Weekly news\r\n\r\nHi.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Feedback-ID: weekly:newsletter:foobar")
+        .assert_not_contains("List-Unsubscribe");
+    qr.clear_queue(&test.server).await;
+
+    // Headers set by the sender are preserved
+    session
+        .send_message(
+            "john@news.foobar.org",
+            &["jane@example.org"],
+            concat!(
+                "From: john@news.foobar.org\r\nFeedback-ID: custom:id\r\n",
+                "List-Unsubscribe: <mailto:unsubscribe@news.foobar.org>\r\n",
+                "Subject: Weekly news\r\n\r\nHi.\r\n"
+            ),
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Feedback-ID: custom:id")
+        .assert_count("Feedback-ID:", 1)
+        .assert_count("List-Unsubscribe:", 1);
+    qr.clear_queue(&test.server).await;
+
+    // Other sender domains are not modified
+    session
+        .send_message(
+            "john@foobar.org",
+            &["jane@example.org"],
+            "From: john@foobar.org\r\nSubject: Hello\r\n\r\nHi.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_not_contains("Feedback-ID")
+        .assert_not_contains("List-Unsubscribe");
+}
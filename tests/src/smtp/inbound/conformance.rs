@@ -0,0 +1,80 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Core;
+use smtp::core::Session;
+use utils::config::Config;
+
+use crate::smtp::{
+    TestSMTP,
+    session::{TestSession, VerifyResponse},
+};
+
+const CONFIG: &str = r#"
+[session.conformance]
+bare-lf = [{if = "remote_ip = '10.0.0.1'", then = 'reject'},
+           {else = 'tag'}]
+pipelining = [{if = "remote_ip = '10.0.0.1'", then = 'reject'},
+              {else = 'tag'}]
+"#;
+
+#[tokio::test]
+async fn conformance() {
+    // Enable logging
+    crate::enable_logging();
+
+    let mut config = Config::new(CONFIG).unwrap();
+    let core = Core::parse(&mut config, Default::default(), Default::default()).await;
+    let server = TestSMTP::from_core(core).server;
+
+    // Bare LFs close the connection in reject mode
+    let mut session = Session::test(server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.ingest(b"EHLO mx.foobar.org\n").await.unwrap_err();
+    session.response().assert_code("554 5.5.0");
+
+    // CRLFs split across reads are not bare LFs
+    let mut session = Session::test(server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.foobar.org").await;
+    assert!(session.ingest(b"NOOP\r").await.unwrap());
+    assert!(session.ingest(b"\n").await.unwrap());
+    session.response().assert_code("250");
+
+    // Commands pipelined after HELO close the connection in reject mode
+    session.cmd("HELO mx.foobar.org", "250").await;
+    session
+        .ingest(b"MAIL FROM:<bill@foobar.org>\r\nRCPT TO:<jdoe@example.com>\r\n")
+        .await
+        .unwrap_err();
+    session.response().assert_code("554 5.5.0");
+
+    // Pipelining is allowed once negotiated with EHLO
+    let mut session = Session::test(server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("mx.foobar.org").await;
+    assert!(session.ingest(b"NOOP\r\nNOOP\r\n").await.unwrap());
+    session.response().assert_not_contains("554");
+
+    // Violations are tagged once for the spam filter in tag mode
+    let mut session = Session::test(server);
+    session.data.remote_ip_str = "10.0.0.2".into();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    assert!(session.ingest(b"HELO mx.foobar.org\n").await.unwrap());
+    assert!(session.ingest(b"NOOP\nNOOP\r\n").await.unwrap());
+    session.response().assert_not_contains("554");
+    assert_eq!(
+        session.data.conformance_tags,
+        vec!["SMTP_BARE_LF", "SMTP_PIPELINING"]
+    );
+}
@@ -0,0 +1,177 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Core;
+use smtp::core::Session;
+use store::Stores;
+use utils::config::Config;
+
+use crate::{
+    AssertConfig,
+    smtp::{
+        TempDir, TestSMTP,
+        inbound::TestMessage,
+        session::{TestSession, VerifyResponse},
+    },
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/queue.db"
+
+[spam-filter]
+enable = false
+
+[session.rcpt]
+relay = true
+
+[session.disclaimer."corp"]
+enable = [{if = "sender_domain = 'test.org'", then = true},
+          {else = false}]
+text = "This message is confidential."
+signed = "wrap"
+"#;
+
+#[tokio::test]
+async fn disclaimer() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_disclaimer_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Plain text messages
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Hi\r\n\r\nHello world.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Hello world.")
+        .assert_contains("This message is confidential.");
+    qr.clear_queue(&test.server).await;
+
+    // Both alternatives are updated
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            concat!(
+                "From: john@test.org\r\n",
+                "Subject: Hi\r\n",
+                "MIME-Version: 1.0\r\n",
+                "Content-Type: multipart/alternative; boundary=\"b1\"\r\n\r\n",
+                "--b1\r\n",
+                "Content-Type: text/plain; charset=utf-8\r\n\r\n",
+                "Hello world.\r\n",
+                "--b1\r\n",
+                "Content-Type: text/html; charset=utf-8\r\n\r\n",
+                "<html><body><p>Hello world.</p></body></html>\r\n",
+                "--b1--\r\n"
+            ),
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("This message is confidential.")
+        .assert_contains("<p>This message is confidential.</p></body></html>");
+    qr.clear_queue(&test.server).await;
+
+    // Disclaimer not enabled for this sender
+    session
+        .send_message(
+            "john@foobar.org",
+            &["bill@foobar.org"],
+            "From: john@foobar.org\r\nSubject: Hi\r\n\r\nHello world.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_not_contains("This message is confidential.");
+    qr.clear_queue(&test.server).await;
+
+    // Signed messages are wrapped
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            concat!(
+                "From: john@test.org\r\n",
+                "Subject: Hi\r\n",
+                "MIME-Version: 1.0\r\n",
+                "Content-Type: multipart/signed; protocol=\"application/pgp-signature\";\r\n",
+                "\tboundary=\"b2\"\r\n\r\n",
+                "--b2\r\n",
+                "Content-Type: text/plain\r\n\r\n",
+                "Hello world.\r\n",
+                "--b2\r\n",
+                "Content-Type: application/pgp-signature\r\n\r\n",
+                "SIGNATURE\r\n",
+                "--b2--\r\n"
+            ),
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Content-Type: multipart/mixed;")
+        .assert_contains("Content-Type: multipart/signed; protocol=\"application/pgp-signature\";")
+        .assert_contains("Content-Disposition: inline")
+        .assert_count("Content-Type: text/plain", 2);
+    qr.clear_queue(&test.server).await;
+
+    // DKIM signed messages are left untouched
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            concat!(
+                "DKIM-Signature: v=1; a=rsa-sha256; d=test.org; s=default; b=abc\r\n",
+                "From: john@test.org\r\n",
+                "Subject: Hi\r\n\r\n",
+                "Hello world.\r\n"
+            ),
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_not_contains("This message is confidential.");
+    qr.clear_queue(&test.server).await;
+}
@@ -0,0 +1,372 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{Core, auth::oauth::GrantType, config::smtp::queue::QueueName};
+use smtp::{
+    core::Session,
+    queue::{
+        HELD_FOR_APPROVAL,
+        moderation::{ModerationAction, QueueModeration},
+        spool::SmtpSpool,
+    },
+};
+use smtp_proto::MAIL_REQUIRETLS;
+use store::{Stores, dispatch::lookup::KeyValue};
+use utils::config::Config;
+
+use crate::{
+    AssertConfig,
+    smtp::{
+        TempDir, TestSMTP,
+        inbound::{TestMessage, TestQueueEvent},
+        session::{TestSession, VerifyResponse},
+    },
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/queue.db"
+
+[spam-filter]
+enable = false
+
+[session.rcpt]
+relay = true
+
+[queue.moderation]
+address = "'supervisor@test.org'"
+
+[session.dlp."cards"]
+type = "credit-card"
+enable = true
+action = "reject"
+
+[session.dlp."iban"]
+type = "iban"
+enable = [{if = "sender_domain = 'test.org'", then = true},
+          {else = false}]
+action = "quarantine"
+
+[session.dlp."projects"]
+type = "regex"
+lookup.store = "rocksdb"
+lookup.key = "dlp-projects"
+enable = true
+threshold = 2
+action = "require-tls"
+
+[session.dlp."classified"]
+type = "regex"
+patterns = ["(?i)top\\s+secret"]
+enable = true
+action = "encrypt"
+"#;
+
+#[tokio::test]
+async fn dlp() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_dlp_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Valid card numbers are rejected
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Order\r\n\r\nMy card is 4111 1111 1111 1111.\r\n",
+            "550 5.7.1",
+        )
+        .await;
+
+    // Numbers failing the Luhn check are allowed
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Order\r\n\r\nTracking number 4111 1111 1111 1112.\r\n",
+            "250",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    assert_eq!(
+        message.message.flags & (HELD_FOR_APPROVAL | MAIL_REQUIRETLS),
+        0
+    );
+    qr.clear_queue(&test.server).await;
+
+    // Valid IBANs are held for approval
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Wire\r\n\r\nPlease send it to GB82 WEST 1234 5698 7654 32.\r\n",
+            "250 2.0.0 Message held",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    assert_ne!(message.message.flags & HELD_FOR_APPROVAL, 0);
+
+    // Held messages are only scheduled to expire once the approval window elapses
+    assert_eq!(
+        qr.message_due(message.queue_id).await,
+        message.message.created + 7 * 24 * 60 * 60
+    );
+
+    // The moderator is notified
+    let notification = qr.expect_message().await;
+    assert_eq!(
+        notification.message.recipients.last().unwrap().address(),
+        "supervisor@test.org"
+    );
+    notification
+        .read_lines(&qr)
+        .await
+        .assert_contains("Subject: Message from john@test.org held for approval")
+        .assert_contains("Content-Type: message/rfc822");
+    let due = qr.message_due(notification.queue_id).await;
+    notification.remove(&test.server, due.into()).await;
+
+    // Approve the message
+    let token = test
+        .server
+        .encode_access_token(
+            GrantType::Moderation,
+            0,
+            &format!("{:x}", message.queue_id),
+            3600,
+        )
+        .await
+        .unwrap();
+    assert!(
+        test.server
+            .moderate_message(&token, ModerationAction::Approve)
+            .await
+            .unwrap()
+    );
+    let messages = qr.read_queued_messages().await;
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].message.flags & HELD_FOR_APPROVAL, 0);
+    assert_eq!(qr.read_queued_events().await.len(), 1);
+
+    // Links can only be used once
+    assert!(
+        !test
+            .server
+            .moderate_message(&token, ModerationAction::Reject)
+            .await
+            .unwrap()
+    );
+    qr.clear_queue(&test.server).await;
+
+    // Reject a held message
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Wire\r\n\r\nPlease send it to GB82 WEST 1234 5698 7654 32.\r\n",
+            "250 2.0.0 Message held",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    qr.consume_message(&test.server).await;
+    let token = test
+        .server
+        .encode_access_token(
+            GrantType::Moderation,
+            0,
+            &format!("{:x}", message.queue_id),
+            3600,
+        )
+        .await
+        .unwrap();
+
+    // Messages locked by another moderator or by the expiry task are skipped
+    assert!(
+        test.server
+            .try_lock_event(message.queue_id, QueueName::default())
+            .await
+    );
+    assert!(
+        !test
+            .server
+            .moderate_message(&token, ModerationAction::Reject)
+            .await
+            .unwrap()
+    );
+    test.server
+        .unlock_event(message.queue_id, QueueName::default())
+        .await;
+
+    assert!(
+        test.server
+            .moderate_message(&token, ModerationAction::Reject)
+            .await
+            .unwrap()
+    );
+    let dsn = qr.expect_message().await;
+    assert_eq!(dsn.message.return_path, "");
+    assert_eq!(
+        dsn.message.recipients.last().unwrap().address(),
+        "john@test.org"
+    );
+    assert!(
+        qr.read_queued_messages()
+            .await
+            .iter()
+            .all(|m| m.queue_id != message.queue_id)
+    );
+    qr.clear_queue(&test.server).await;
+
+    // Held messages are bounced once the approval window elapses
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Wire\r\n\r\nPlease send it to GB82 WEST 1234 5698 7654 32.\r\n",
+            "250 2.0.0 Message held",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    qr.consume_message(&test.server).await;
+    qr.delivery_attempt(message.queue_id)
+        .await
+        .try_deliver(test.server.clone());
+    qr.read_event().await.assert_done();
+    let dsn = qr.expect_message().await;
+    assert_eq!(
+        dsn.message.recipients.last().unwrap().address(),
+        "john@test.org"
+    );
+    dsn.read_lines(&qr)
+        .await
+        .assert_contains("Message not approved by moderator in time.");
+    assert!(
+        qr.read_queued_messages()
+            .await
+            .iter()
+            .all(|m| m.queue_id != message.queue_id)
+    );
+    qr.clear_queue(&test.server).await;
+
+    // Rule not enabled for this sender
+    session
+        .send_message(
+            "john@foobar.org",
+            &["bill@foobar.org"],
+            "From: john@foobar.org\r\nSubject: Wire\r\n\r\nPlease send it to GB82 WEST 1234 5698 7654 32.\r\n",
+            "250 2.0.0 Message queued",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    assert_eq!(message.message.flags & HELD_FOR_APPROVAL, 0);
+    qr.clear_queue(&test.server).await;
+
+    // Regex dictionaries with a threshold
+    let store = test.server.core.storage.lookups.get("rocksdb").unwrap();
+    store
+        .key_set(KeyValue::new(
+            "dlp-projects",
+            "(?i)project\\s+falcon\n(?i)codename\\s+[a-z]+\n"
+                .as_bytes()
+                .to_vec(),
+        ))
+        .await
+        .unwrap();
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Project Falcon\r\n\r\nStatus update.\r\n",
+            "250",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    assert_eq!(message.message.flags & MAIL_REQUIRETLS, 0);
+    qr.clear_queue(&test.server).await;
+
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Project Falcon\r\n\r\nCodename Kestrel is ready.\r\n",
+            "250",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    assert_ne!(message.message.flags & MAIL_REQUIRETLS, 0);
+    qr.clear_queue(&test.server).await;
+
+    // Dictionaries are reloaded when the stored patterns change
+    store
+        .key_set(KeyValue::new(
+            "dlp-projects",
+            "(?i)project\\s+heron\n".as_bytes().to_vec(),
+        ))
+        .await
+        .unwrap();
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Project Falcon\r\n\r\nCodename Kestrel is ready.\r\n",
+            "250",
+        )
+        .await;
+    let message = qr.expect_message().await;
+    assert_eq!(message.message.flags & MAIL_REQUIRETLS, 0);
+    qr.clear_queue(&test.server).await;
+
+    // Messages that must be encrypted are rejected when a recipient has no key
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Plans\r\n\r\nThis is top secret.\r\n",
+            "550 5.7.1 Message could not be encrypted",
+        )
+        .await;
+    qr.assert_no_events();
+
+    // Messages that are already encrypted are accepted as they are
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            concat!(
+                "From: john@test.org\r\n",
+                "Subject: Top secret\r\n",
+                "Content-Type: multipart/encrypted; protocol=\"application/pgp-encrypted\";\r\n",
+                "\tboundary=\"b1\"\r\n\r\n",
+                "--b1\r\nContent-Type: application/pgp-encrypted\r\n\r\nVersion: 1\r\n",
+                "--b1\r\nContent-Type: application/octet-stream\r\n\r\n",
+                "-----BEGIN PGP MESSAGE-----\r\n-----END PGP MESSAGE-----\r\n",
+                "--b1--\r\n"
+            ),
+            "250",
+        )
+        .await;
+    qr.expect_message().await;
+    qr.clear_queue(&test.server).await;
+}
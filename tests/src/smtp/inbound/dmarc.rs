@@ -267,7 +267,10 @@ async fn dmarc() {
         .assert_contains("To: dmarc-failures@example.com")
         .assert_contains("Feedback-Type: auth-failure")
         .assert_contains("Auth-Failure: dmarc")
-        .assert_contains("dmarc=3Dnone");
+        .assert_contains("dmarc=3Dnone")
+        .assert_contains("To: redacted@example.com")
+        .assert_not_contains("jdoe@example.com")
+        .assert_not_contains("Body hash will not match.");
 
     // Expect DMARC aggregate report
     let report = rr.read_report().await.unwrap_dmarc();
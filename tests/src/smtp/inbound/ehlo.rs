@@ -27,6 +27,8 @@ future-release = [{if = "remote_ip = '10.0.0.1'", then = '1h'},
                   {else = false}]
 mt-priority = [{if = "remote_ip = '10.0.0.1'", then = 'nsep'},
                {else = false}]
+limits = [{if = "remote_ip = '10.0.0.1'", then = true},
+          {else = false}]
 
 [session.ehlo]
 reject-non-fqdn = "starts_with(remote_ip, '10.0.0.')"
@@ -70,6 +72,8 @@ async fn ehlo() {
         .assert_contains("SIZE 1024")
         .assert_contains("MT-PRIORITY NSEP")
         .assert_contains("FUTURERELEASE 3600")
+        .assert_contains("LIMITS RCPTMAX=100 MAILMAX=10")
+        .assert_not_contains("RCPTDOMAINMAX")
         .assert_contains("STARTTLS");
 
     // SPF should be a Pass for 10.0.0.1
@@ -99,5 +103,6 @@ async fn ehlo() {
         .assert_contains("SIZE 2048")
         .assert_not_contains("MT-PRIORITY")
         .assert_not_contains("FUTURERELEASE")
+        .assert_not_contains("LIMITS")
         .assert_not_contains("STARTTLS");
 }
@@ -7,6 +7,7 @@
 use std::{fs, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use ahash::AHashSet;
+use base64::Engine;
 use common::{
     Core,
     config::smtp::session::{Milter, MilterVersion, Stage},
@@ -94,6 +95,7 @@ relay = true
 url = "http://127.0.0.1:9333"
 enable = true
 stages = ["data"]
+options.message-format = "mime"
 "#;
 
 #[tokio::test]
@@ -221,6 +223,23 @@ async fn milter_session() {
         .await
         .assert_contains("X-Spam: Yes")
         .assert_contains("123456");
+
+    // Test accept with message replacement
+    session
+        .send_message(
+            "transform@doe.org",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250 2.0.0",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Subject: Transformed")
+        .assert_contains("New contents.")
+        .assert_not_contains("Are you hungry yet?");
 }
 
 #[tokio::test]
@@ -499,6 +518,31 @@ fn milter_message_modifications() {
             .unwrap()
         )
     }
+
+    // Header changes are applied on top of a replaced message
+    assert_eq!(
+        String::from_utf8(
+            session_data
+                .apply_milter_modifications(
+                    vec![
+                        Modification::ReplaceMessage {
+                            value: b"Subject: replaced\r\n\r\nNew body\r\n".to_vec(),
+                        },
+                        Modification::AddHeader {
+                            name: "X-Spam".into(),
+                            value: "Yes".into(),
+                        },
+                        Modification::ReplaceBody {
+                            value: b"Ignored\r\n".to_vec(),
+                        },
+                    ],
+                    &parsed_test_message
+                )
+                .unwrap()
+        )
+        .unwrap(),
+        "X-Spam: Yes\r\nSubject: replaced\r\n\r\nNew body\r\n"
+    );
 }
 
 #[test]
@@ -748,7 +792,9 @@ async fn accept_milter(
                                     // Write modifications
                                     stream
                                         .write_all(
-                                            &Response::Modification(modification).serialize(),
+                                            &Response::Modification(modification)
+                                                .serialize()
+                                                .unwrap(),
                                         )
                                         .await
                                         .unwrap();
@@ -760,7 +806,10 @@ async fn accept_milter(
                     };
 
                     // Write response
-                    stream.write_all(&response.serialize()).await.unwrap();
+                    stream
+                        .write_all(&response.serialize().unwrap())
+                        .await
+                        .unwrap();
                 }
                 FrameResult::Incomplete => continue 'outer,
                 FrameResult::TooLarge(size) => {
@@ -859,6 +908,22 @@ fn handle_mta_hook(request: Request, tests: Arc<Vec<HeaderTest>>) -> hooks::Resp
             response: None,
             modifications: vec![],
         },
+        "transform" => {
+            let message = request.message.unwrap();
+            assert!(!message.parts.is_empty());
+            assert_eq!(message.parts[0].offset_header, 0);
+
+            hooks::Response {
+                action: hooks::Action::Accept,
+                response: None,
+                modifications: vec![hooks::Modification::ReplaceMessage {
+                    value: base64::engine::general_purpose::STANDARD.encode(
+                        "From: john@doe.org\r\nSubject: Transformed\r\n\r\nNew contents.\r\n",
+                    ),
+                    encoding: Some("base64".into()),
+                }],
+            }
+        }
         "discard" => hooks::Response {
             action: hooks::Action::Discard,
             response: None,
@@ -943,6 +1008,10 @@ fn handle_mta_hook(request: Request, tests: Arc<Vec<HeaderTest>>) -> hooks::Resp
                     Modification::ReplaceBody { value } => hooks::Modification::ReplaceContents {
                         value: String::from_utf8(value.clone()).unwrap(),
                     },
+                    Modification::ReplaceMessage { value } => hooks::Modification::ReplaceMessage {
+                        value: String::from_utf8(value.clone()).unwrap(),
+                        encoding: None,
+                    },
                     Modification::AddHeader { name, value } => hooks::Modification::AddHeader {
                         name: name.clone(),
                         value: value.clone(),
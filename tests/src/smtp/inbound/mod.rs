@@ -26,16 +26,25 @@ pub mod antispam;
 pub mod asn;
 pub mod auth;
 pub mod basic;
+pub mod bulk;
+pub mod conformance;
 pub mod data;
+pub mod disclaimer;
+pub mod dlp;
 pub mod dmarc;
 pub mod ehlo;
 pub mod limits;
 pub mod mail;
 pub mod milter;
+pub mod prdr;
 pub mod rcpt;
+pub mod relay;
 pub mod rewrite;
+pub mod rspamd;
 pub mod scripts;
 pub mod sign;
+pub mod spamd;
+pub mod tag;
 pub mod throttle;
 pub mod vrfy;
 
@@ -144,6 +153,7 @@ impl QueueReceiver {
             due: self.message_due(queue_id).await,
             queue_id,
             queue_name: Default::default(),
+            priority: 0,
         }
     }
 
@@ -0,0 +1,197 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Core;
+use smtp::core::Session;
+use store::Stores;
+use utils::config::Config;
+
+use crate::{
+    AssertConfig,
+    smtp::{
+        TempDir, TestSMTP,
+        inbound::{TestMessage, TestQueueEvent},
+        session::{TestSession, VerifyResponse},
+    },
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/queue.db"
+
+[spam-filter]
+enable = false
+
+[session.extensions]
+prdr = true
+
+[session.rcpt]
+relay = true
+
+[session.data]
+script = [{if = "rcpt = 'sales@foobar.org'", then = "'tag_sales'"},
+          {if = "rcpt = 'support@foobar.org'", then = "'tag_support'"},
+          {if = "rcpt = 'blocked@foobar.org'", then = "'reject_all'"},
+          {else = false}]
+
+[session.data.add-headers]
+received = false
+received-spf = false
+auth-results = false
+message-id = false
+date = false
+return-path = false
+
+[[queue.quota]]
+match = "rcpt = 'full@foobar.org'"
+key = ['rcpt']
+size = 10
+enable = true
+
+[sieve.trusted.scripts.tag_sales]
+contents = '''
+require "editheader";
+addheader "X-Team" "sales";
+'''
+
+[sieve.trusted.scripts.tag_support]
+contents = '''
+require "editheader";
+addheader "X-Team" "support";
+'''
+
+[sieve.trusted.scripts.reject_all]
+contents = '''
+require "reject";
+reject "550 5.7.1 Recipient does not accept mail";
+'''
+"#;
+
+const MESSAGE: &str = "From: bill@example.org\r\nSubject: Hello\r\n\r\nHello world";
+
+#[tokio::test]
+async fn prdr() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_prdr_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session
+        .ehlo("mx.example.org")
+        .await
+        .assert_contains("250-PRDR");
+
+    // Diverging scripts queue one copy per set of added headers
+    session
+        .send_message(
+            "bill@example.org",
+            &["sales@foobar.org", "support@foobar.org", "info@foobar.org"],
+            MESSAGE,
+            "250",
+        )
+        .await;
+    let mut messages = Vec::new();
+    for _ in 0..3 {
+        qr.read_event().await.assert_refresh();
+    }
+    for message in qr.read_queued_messages().await {
+        let mut recipients = message
+            .message
+            .recipients
+            .iter()
+            .map(|rcpt| rcpt.address().to_string())
+            .collect::<Vec<_>>();
+        recipients.sort_unstable();
+        let team = message
+            .read_lines(&qr)
+            .await
+            .into_iter()
+            .find_map(|line| line.strip_prefix("X-Team: ").map(|v| v.trim().to_string()));
+        messages.push((recipients, team));
+    }
+    messages.sort_unstable();
+    assert_eq!(
+        messages,
+        vec![
+            (vec!["info@foobar.org".to_string()], None),
+            (
+                vec!["sales@foobar.org".to_string()],
+                Some("sales".to_string())
+            ),
+            (
+                vec!["support@foobar.org".to_string()],
+                Some("support".to_string())
+            ),
+        ]
+    );
+    qr.assert_no_events();
+    qr.clear_queue(&test.server).await;
+
+    // Without PRDR a rejection applies to the whole transaction
+    session
+        .send_message(
+            "bill@example.org",
+            &["sales@foobar.org", "blocked@foobar.org"],
+            MESSAGE,
+            "550 5.7.1",
+        )
+        .await;
+    qr.assert_no_events();
+
+    // With PRDR only the rejected recipient is refused
+    session
+        .cmd("MAIL FROM:<bill@example.org> PRDR", "250")
+        .await;
+    session.rcpt_to("sales@foobar.org", "250").await;
+    session.rcpt_to("blocked@foobar.org", "250").await;
+    session.ingest(b"DATA\r\n").await.unwrap();
+    session.response().assert_code("354");
+    session.ingest(MESSAGE.as_bytes()).await.unwrap();
+    session.ingest(b"\r\n.\r\n").await.unwrap();
+    session
+        .response()
+        .assert_contains("353 Content analysis has begun.")
+        .assert_contains("250 2.1.5 <sales@foobar.org> Message accepted.")
+        .assert_contains("550 5.7.1 <blocked@foobar.org> Recipient does not accept mail")
+        .assert_contains("250 2.0.0 Message queued");
+    let message = qr.expect_message().await;
+    assert_eq!(message.message.recipients.len(), 1);
+    assert_eq!(
+        message.message.recipients.first().unwrap().address(),
+        "sales@foobar.org"
+    );
+    qr.assert_no_events();
+    qr.clear_queue(&test.server).await;
+
+    // No copy is queued when any of them exceeds its quota, so retries are not duplicated
+    session
+        .send_message(
+            "bill@example.org",
+            &["sales@foobar.org", "full@foobar.org"],
+            MESSAGE,
+            "452 4.3.1",
+        )
+        .await;
+    qr.assert_no_events();
+    qr.assert_queue_is_empty().await;
+}
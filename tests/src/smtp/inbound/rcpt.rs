@@ -61,6 +61,8 @@ email = "mike@foobar.org"
 directory = "'local'"
 max-recipients = [{if = "remote_ip = '10.0.0.1'", then = 3},
                 {else = 5}]
+max-domains = [{if = "remote_ip = '10.0.0.1'", then = 0},
+               {else = 2}]
 relay = [{if = "remote_ip = '10.0.0.1'", then = false},
          {else = true}]
 
@@ -162,4 +164,8 @@ async fn rcpt() {
     let rcpt = session.data.rcpt_to.last().unwrap();
     assert!((rcpt.flags & (RCPT_NOTIFY_DELAY | RCPT_NOTIFY_SUCCESS | RCPT_NOTIFY_FAILURE)) != 0);
     assert_eq!(rcpt.dsn_info.as_ref().unwrap(), "Jane.Doe@Foobar.org");
+
+    // Exceeding max number of recipient domains
+    session.rcpt_to("user@example.org", "452 4.5.3").await;
+    session.rcpt_to("bill@foobar.org", "250").await;
 }
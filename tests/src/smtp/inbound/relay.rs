@@ -0,0 +1,111 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Core;
+use smtp::core::Session;
+use store::Stores;
+use utils::config::Config;
+
+use crate::{
+    AssertConfig,
+    smtp::{
+        TempDir, TestSMTP,
+        inbound::TestMessage,
+        session::{TestSession, VerifyResponse},
+    },
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/queue.db"
+
+[spam-filter]
+enable = false
+
+[session.rcpt]
+relay = false
+
+[session.relay]
+trusted-networks = ["10.0.0.0/8"]
+
+[session.data.add-headers]
+received = false
+received-spf = false
+auth-results = false
+message-id = false
+date = false
+return-path = false
+"#;
+
+const MESSAGE: &str = concat!(
+    "From: app@example.org\r\n",
+    "To: jane@example.net\r\n",
+    "User-Agent: AppMailer/1.0\r\n",
+    "Subject: Report\r\n",
+    "X-Mailer: AppMailer/1.0\r\n",
+    "\r\n",
+    "Your report is ready."
+);
+
+#[tokio::test]
+async fn trusted_relay() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_trusted_relay_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+
+    // Hosts outside the trusted networks cannot relay
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "192.168.1.1".into();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("app.example.org").await;
+    session.mail_from("app@example.org", "250").await;
+    session.rcpt_to("jane@example.net", "550 5.1.2").await;
+    qr.assert_no_events();
+
+    // Trusted networks relay without authenticating
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
+    session.eval_session_params().await;
+    session.ehlo("app.example.org").await;
+    session
+        .send_message("app@example.org", &["jane@example.net"], MESSAGE, "250")
+        .await;
+
+    // User agent headers are removed and the missing Date and Message-ID are added
+    let message = qr.expect_message().await;
+    assert_eq!(
+        message.message.recipients.first().unwrap().address(),
+        "jane@example.net"
+    );
+    message
+        .read_lines(&qr)
+        .await
+        .assert_contains("Date: ")
+        .assert_contains("Message-ID: ")
+        .assert_contains("Subject: Report")
+        .assert_contains("Your report is ready.")
+        .assert_not_contains("User-Agent")
+        .assert_not_contains("X-Mailer");
+    qr.assert_no_events();
+    qr.clear_queue(&test.server).await;
+}
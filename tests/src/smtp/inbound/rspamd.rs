@@ -0,0 +1,229 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::{Core, manager::webadmin::Resource};
+use http_proto::{ToHttpResponse, request::fetch_body};
+use hyper::{body, server::conn::http1, service::service_fn};
+use hyper_util::rt::TokioIo;
+use smtp::{core::Session, inbound::rspamd::RspamdLearn};
+use store::Stores;
+use tokio::{net::TcpListener, sync::mpsc};
+use utils::config::Config;
+
+use crate::{
+    AssertConfig,
+    smtp::{
+        TempDir, TestSMTP,
+        inbound::TestMessage,
+        session::{TestSession, VerifyResponse},
+    },
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/queue.db"
+
+[spam-filter]
+enable = false
+
+[session.rcpt]
+relay = true
+
+[session.rspamd."rspamd"]
+url = "http://127.0.0.1:9335/"
+learn.url = "http://127.0.0.1:9335/controller"
+password = "secret"
+enable = true
+"#;
+
+#[tokio::test]
+async fn rspamd() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_rspamd_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+    let mut learn_rx = spawn_mock_rspamd_server().await;
+
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Milter headers are applied
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org", "jane@foobar.org"],
+            "From: john@test.org\r\nX-Spam: Maybe\r\nSubject: Hi\r\n\r\nHello.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("X-Spamd-Result: default: False [1.20 / 15.00]")
+        .assert_not_contains("X-Spam: Maybe")
+        .assert_not_contains("X-Spam: Yes");
+    qr.clear_queue(&test.server).await;
+
+    // Subject is rewritten
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Hi\r\n\r\nCheap pills.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Subject: *** SPAM *** Hi")
+        .assert_contains("X-Spam: Yes")
+        .assert_count("Subject:", 1);
+    qr.clear_queue(&test.server).await;
+
+    // Soft reject
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Hi\r\n\r\nRatelimited.\r\n",
+            "451 4.7.1 Try again later",
+        )
+        .await;
+    qr.assert_no_events();
+
+    // Reject with the message provided by Rspamd
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Hi\r\n\r\nXJS*C4JDBQADN1.NSBN3*2IDNEN*GTUBE-STANDARD-ANTI-UBE-TEST-EMAIL*C.34X\r\n",
+            "550 5.7.1 Gtube pattern",
+        )
+        .await;
+    qr.assert_no_events();
+
+    // Training actions are reported to the controller
+    test.server
+        .rspamd_learn(b"Subject: Hi\r\n\r\nCheap pills.\r\n", true, 1)
+        .await;
+    test.server
+        .rspamd_learn(b"Subject: Hi\r\n\r\nHello.\r\n", false, 1)
+        .await;
+    assert_eq!(learn_rx.recv().await.unwrap(), "/controller/learnspam");
+    assert_eq!(learn_rx.recv().await.unwrap(), "/controller/learnham");
+}
+
+async fn spawn_mock_rspamd_server() -> mpsc::Receiver<String> {
+    let listener = TcpListener::bind("127.0.0.1:9335")
+        .await
+        .unwrap_or_else(|e| {
+            panic!("Failed to bind mock rspamd server to 127.0.0.1:9335: {e}");
+        });
+    let (tx, rx) = mpsc::channel(10);
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let _ = http1::Builder::new()
+                    .keep_alive(false)
+                    .serve_connection(
+                        TokioIo::new(stream),
+                        service_fn(|mut req: hyper::Request<body::Incoming>| {
+                            let tx = tx.clone();
+
+                            async move {
+                                let path = req.uri().path().to_string();
+                                let headers = req.headers().clone();
+                                let body = String::from_utf8(
+                                    fetch_body(&mut req, 1024 * 1024, 0).await.unwrap(),
+                                )
+                                .unwrap();
+                                assert_eq!(
+                                    headers.get("password").and_then(|v| v.to_str().ok()),
+                                    Some("secret")
+                                );
+
+                                let response = if path == "/checkv2" {
+                                    assert_eq!(
+                                        headers.get("ip").and_then(|v| v.to_str().ok()),
+                                        Some("10.0.0.1")
+                                    );
+                                    assert_eq!(
+                                        headers.get("helo").and_then(|v| v.to_str().ok()),
+                                        Some("mx.test.org")
+                                    );
+                                    assert_eq!(
+                                        headers.get("from").and_then(|v| v.to_str().ok()),
+                                        Some("john@test.org")
+                                    );
+                                    assert!(headers.get_all("rcpt").iter().count() >= 1);
+
+                                    if body.contains("GTUBE") {
+                                        concat!(
+                                            r#"{"action":"reject","score":1000.0,"#,
+                                            r#""required_score":15.0,"symbols":{"GTUBE":{"score":0}},"#,
+                                            r#""messages":{"smtp_message":"Gtube pattern"}}"#
+                                        )
+                                    } else if body.contains("Ratelimited") {
+                                        r#"{"action":"soft reject","score":0.0,"required_score":15.0}"#
+                                    } else if body.contains("pills") {
+                                        concat!(
+                                            r#"{"action":"rewrite subject","score":9.5,"#,
+                                            r#""required_score":15.0,"subject":"*** SPAM *** Hi","#,
+                                            r#""symbols":{"PILLS":{"score":9.5}}}"#
+                                        )
+                                    } else {
+                                        assert_eq!(headers.get_all("rcpt").iter().count(), 2);
+                                        concat!(
+                                            r#"{"action":"no action","score":1.2,"required_score":15.0,"#,
+                                            r#""milter":{"add_headers":{"X-Spamd-Result":"#,
+                                            r#"{"value":"default: False [1.20 / 15.00]","order":0}},"#,
+                                            r#""remove_headers":{"X-Spam":0}}}"#
+                                        )
+                                    }
+                                    .to_string()
+                                } else {
+                                    tx.send(path).await.unwrap();
+                                    r#"{"success":true}"#.to_string()
+                                };
+
+                                Ok::<_, hyper::Error>(
+                                    Resource::new("application/json", response.into_bytes())
+                                        .into_http_response()
+                                        .build(),
+                                )
+                            }
+                        }),
+                    )
+                    .await;
+            });
+        }
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    rx
+}
@@ -0,0 +1,173 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::Core;
+use smtp::{core::Session, inbound::spamd::parse_spamd_response};
+use store::Stores;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+use utils::config::Config;
+
+use crate::{
+    AssertConfig,
+    smtp::{
+        TempDir, TestSMTP,
+        inbound::TestMessage,
+        session::{TestSession, VerifyResponse},
+    },
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/queue.db"
+
+[spam-filter]
+enable = false
+
+[session.rcpt]
+relay = true
+
+[session.spamd."sa"]
+hostname = "127.0.0.1"
+port = 9334
+enable = true
+command = "symbols"
+user = "stalwart"
+action.spam = "quarantine"
+action.reject-score = 15.0
+"#;
+
+#[tokio::test]
+async fn spamd() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Parse responses
+    let response = parse_spamd_response(
+        b"SPAMD/1.1 0 EX_OK\r\nContent-length: 19\r\nSpam: True ; 7.5 / 5.0\r\n\r\nGTUBE,URIBL_BLACK\r\n",
+    )
+    .unwrap();
+    assert!(response.is_spam);
+    assert_eq!(response.score, 7.5);
+    assert_eq!(response.threshold, 5.0);
+    assert_eq!(response.body.trim(), "GTUBE,URIBL_BLACK");
+    assert!(parse_spamd_response(b"SPAMD/1.1 76 Bad header line\r\n\r\n").is_err());
+    assert!(parse_spamd_response(b"SPAMD/1.1 0 EX_OK\r\n\r\n").is_err());
+
+    let tmp_dir = TempDir::new("smtp_spamd_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+    spawn_mock_spamd_server().await;
+
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Ham
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Hi\r\n\r\nHello.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("X-Spam-Flag: NO")
+        .assert_contains("X-Spam-Status: No, score=1.2 required=5.0")
+        .assert_not_contains("X-Quarantine");
+    qr.clear_queue(&test.server).await;
+
+    // Spam is quarantined
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Hi\r\n\r\nCheap pills.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("X-Spam-Flag: YES")
+        .assert_contains("X-Spam-Score: 7.5")
+        .assert_contains("tests=PILLS,URIBL_BLACK")
+        .assert_contains("X-Quarantine");
+    qr.clear_queue(&test.server).await;
+
+    // High scoring spam is rejected
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Hi\r\n\r\nXJS*C4JDBQADN1.NSBN3*2IDNEN*GTUBE-STANDARD-ANTI-UBE-TEST-EMAIL*C.34X\r\n",
+            "550 5.7.1",
+        )
+        .await;
+    qr.assert_no_events();
+}
+
+async fn spawn_mock_spamd_server() {
+    let listener = TcpListener::bind("127.0.0.1:9334")
+        .await
+        .unwrap_or_else(|e| {
+            panic!("Failed to bind mock spamd server to 127.0.0.1:9334: {e}");
+        });
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                stream.read_to_end(&mut request).await.unwrap();
+                let request = String::from_utf8(request).unwrap();
+                assert!(request.starts_with("SYMBOLS SPAMC/1.5\r\n"), "{request}");
+                assert!(request.contains("\r\nUser: stalwart\r\n"), "{request}");
+
+                let (verdict, symbols) = if request.contains("GTUBE") {
+                    ("True ; 1000.0 / 5.0", "GTUBE")
+                } else if request.contains("Cheap pills") {
+                    ("True ; 7.5 / 5.0", "PILLS,URIBL_BLACK")
+                } else {
+                    ("False ; 1.2 / 5.0", "")
+                };
+
+                stream
+                    .write_all(
+                        format!(
+                            "SPAMD/1.1 0 EX_OK\r\nContent-length: {}\r\nSpam: {verdict}\r\n\r\n{symbols}",
+                            symbols.len()
+                        )
+                        .as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+            });
+        }
+    });
+
+    tokio::time::sleep(Duration::from_millis(100)).await;
+}
@@ -0,0 +1,136 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::Core;
+use smtp::core::Session;
+use store::Stores;
+use utils::config::Config;
+
+use crate::{
+    AssertConfig,
+    smtp::{
+        TempDir, TestSMTP,
+        inbound::TestMessage,
+        session::{TestSession, VerifyResponse},
+    },
+};
+
+const CONFIG: &str = r#"
+[storage]
+data = "rocksdb"
+lookup = "rocksdb"
+blob = "rocksdb"
+fts = "rocksdb"
+
+[store."rocksdb"]
+type = "rocksdb"
+path = "{TMP}/queue.db"
+
+[spam-filter]
+enable = false
+
+[session.rcpt]
+relay = true
+
+[session.tag."external"]
+enable = "sender_domain != 'foobar.org'"
+subject = "[EXTERNAL]"
+headers = ["X-External: yes"]
+
+[session.tag."review"]
+enable = "sender = 'jane@test.org'"
+subject = "[REVIEW]"
+headers = ["X-External: review"]
+"#;
+
+#[tokio::test]
+async fn message_tags() {
+    // Enable logging
+    crate::enable_logging();
+
+    let tmp_dir = TempDir::new("smtp_tag_test", true);
+    let mut config = Config::new(tmp_dir.update_config(CONFIG)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+    let core = Core::parse(&mut config, stores, Default::default()).await;
+    config.assert_no_errors();
+
+    let test = TestSMTP::from_core(core);
+    let mut qr = test.queue_receiver;
+    let mut session = Session::test(test.server.clone());
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // Tag subject and replace spoofed headers
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Hello\r\nX-External: no\r\n\r\nHi.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Subject: [EXTERNAL] Hello")
+        .assert_contains("X-External: yes")
+        .assert_not_contains("X-External: no");
+    qr.clear_queue(&test.server).await;
+
+    // Tags are not stacked
+    session
+        .send_message(
+            "john@test.org",
+            &["bill@foobar.org"],
+            "From: john@test.org\r\nSubject: Re: [external] Hello\r\nX-External: yes\r\n\r\nHi.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Subject: Re: [external] Hello")
+        .assert_count("X-External:", 1);
+    qr.clear_queue(&test.server).await;
+
+    // Multiple tags, later headers take precedence
+    session
+        .send_message(
+            "jane@test.org",
+            &["bill@foobar.org"],
+            "From: jane@test.org\r\n\r\nHi.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Subject: [EXTERNAL] [REVIEW]")
+        .assert_contains("X-External: review")
+        .assert_count("X-External:", 1);
+    qr.clear_queue(&test.server).await;
+
+    // Tags not enabled for this sender
+    session
+        .send_message(
+            "john@foobar.org",
+            &["bill@foobar.org"],
+            "From: john@foobar.org\r\nSubject: Hello\r\n\r\nHi.\r\n",
+            "250",
+        )
+        .await;
+    qr.expect_message()
+        .await
+        .read_lines(&qr)
+        .await
+        .assert_contains("Subject: Hello")
+        .assert_not_contains("X-External");
+    qr.clear_queue(&test.server).await;
+}
@@ -254,6 +254,28 @@ async fn manage_queue() {
     }
     assert_eq!(id_map.len(), 6);
 
+    // Deferred recipients are grouped by retry time and normalized reason
+    let deferred = api
+        .request::<serde_json::Value>(Method::GET, "/api/queue/deferred")
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(deferred["total"], 1, "{deferred}");
+    assert_eq!(
+        deferred["retries"][3],
+        serde_json::json!({"label": "1h", "count": 1}),
+        "{deferred}"
+    );
+    assert_eq!(
+        deferred["reasons"],
+        serde_json::json!([{
+            "reason": "451 4.5.3: try again later.",
+            "count": 1,
+            "domains": ["foobar.org"]
+        }]),
+        "{deferred}"
+    );
+
     // Test list search
     for (query, expected_ids) in [
         (
@@ -8,7 +8,10 @@ use std::time::{Duration, Instant};
 
 use common::config::server::ServerProtocol;
 use mail_auth::MX;
-use smtp_proto::{MAIL_REQUIRETLS, MAIL_RET_HDRS, MAIL_SMTPUTF8, RCPT_NOTIFY_NEVER};
+use smtp::outbound::client::encode_binary_parts;
+use smtp_proto::{
+    MAIL_BODY_BINARYMIME, MAIL_REQUIRETLS, MAIL_RET_HDRS, MAIL_SMTPUTF8, RCPT_NOTIFY_NEVER,
+};
 
 use crate::smtp::{
     DnsCache, TestSMTP,
@@ -155,4 +158,52 @@ async fn extensions() {
     assert!((message.message.flags & MAIL_REQUIRETLS) != 0);
     assert!((message.message.flags & MAIL_SMTPUTF8) != 0);
     assert!((message.message.recipients.last().unwrap().flags & RCPT_NOTIFY_NEVER) != 0);
+
+    // Test BINARYMIME over BDAT
+    session
+        .send_message(
+            "<john@test.org> BODY=BINARYMIME",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_done();
+    let message = remote.queue_receiver.expect_message().await;
+    assert!((message.message.flags & MAIL_BODY_BINARYMIME) != 0);
+}
+
+#[test]
+fn binary_mime_downgrade() {
+    let message = concat!(
+        "From: john@test.org\r\n",
+        "To: bill@foobar.org\r\n",
+        "Subject: binary\r\n",
+        "MIME-Version: 1.0\r\n",
+        "Content-Type: multipart/mixed; boundary=\"b\"\r\n",
+        "\r\n",
+        "--b\r\n",
+        "Content-Type: text/plain\r\n",
+        "\r\n",
+        "Hello\r\n",
+        "--b\r\n",
+        "Content-Type: application/octet-stream\r\n",
+        "Content-Transfer-Encoding: binary\r\n",
+        "\r\n",
+        "\0\x01\x02\rbare\ncr\r\n",
+        "--b--\r\n"
+    );
+
+    let encoded = String::from_utf8(encode_binary_parts(message.as_bytes()).unwrap()).unwrap();
+    assert!(encoded.contains("Content-Transfer-Encoding: base64\r\n\r\nAAECDWJhcmUK"));
+    assert!(!encoded.contains("Content-Transfer-Encoding: binary"));
+    assert!(encoded.contains("Content-Type: text/plain\r\n\r\nHello\r\n"));
+
+    // Messages without binary parts are not modified
+    assert!(encode_binary_parts(b"Subject: test\r\n\r\nHello\r\n").is_none());
 }
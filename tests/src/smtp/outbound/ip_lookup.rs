@@ -8,6 +8,10 @@ use std::time::{Duration, Instant};
 
 use common::config::server::ServerProtocol;
 use mail_auth::{IpLookupStrategy, MX};
+use smtp::outbound::{
+    client::{RaceResult, SmtpClient},
+    lookup::IpLookupResult,
+};
 
 use crate::smtp::{DnsCache, TestSMTP, session::TestSession};
 
@@ -79,6 +83,9 @@ async fn ip_lookup_strategy() {
         tokio::time::sleep(Duration::from_millis(100)).await;
         if matches!(strategy, IpLookupStrategy::Ipv6thenIpv4) {
             remote.queue_receiver.expect_message().await;
+
+            // The address family that connected is preferred next time
+            assert_eq!(core.inner.cache.ip_family.get("mx.foobar.org"), Some(true));
         } else {
             let message = local.queue_receiver.last_queued_message().await;
             let status = message.message.recipients[0].status.to_string();
@@ -90,3 +97,68 @@ async fn ip_lookup_strategy() {
         }
     }
 }
+
+#[test]
+fn ip_attempt_order() {
+    let result = || IpLookupResult {
+        remote_ips: ["::1", "::2", "::3", "10.0.0.1", "10.0.0.2"]
+            .into_iter()
+            .map(|ip| ip.parse().unwrap())
+            .collect(),
+    };
+
+    for (prefer_ipv4, expected) in [
+        (None, ["::1", "10.0.0.1", "::2", "10.0.0.2", "::3"]),
+        (Some(false), ["::1", "10.0.0.1", "::2", "10.0.0.2", "::3"]),
+        (Some(true), ["10.0.0.1", "::1", "10.0.0.2", "::2", "::3"]),
+    ] {
+        assert_eq!(
+            result()
+                .into_attempt_order(prefer_ipv4)
+                .into_iter()
+                .map(|ip| ip.to_string())
+                .collect::<Vec<_>>(),
+            expected,
+            "prefer_ipv4: {prefer_ipv4:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn happy_eyeballs_race() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let attempt = |delay: u64, succeeds: bool| async move {
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        if succeeds {
+            SmtpClient::connect(addr, Duration::from_secs(1), 0).await
+        } else {
+            Err(mail_send::Error::Timeout)
+        }
+    };
+
+    for (primary, fallback, expected, is_ok) in [
+        // Primary completes before the fallback is started
+        ((0, true), (0, true), RaceResult::Primary, true),
+        ((0, false), (0, true), RaceResult::Primary, false),
+        // Fallback completes first
+        ((300, true), (0, true), RaceResult::Fallback, true),
+        ((100, false), (0, true), RaceResult::Fallback, true),
+        // Fallback fails, primary still completes
+        ((200, true), (0, false), RaceResult::Primary, true),
+        // Both attempts fail
+        ((100, false), (10, false), RaceResult::BothFailed, false),
+    ] {
+        let (result, client) = SmtpClient::connect_racing(
+            attempt(primary.0, primary.1),
+            attempt(fallback.0, fallback.1),
+            Duration::from_millis(50),
+        )
+        .await;
+        assert_eq!(
+            (result, client.is_ok()),
+            (expected, is_ok),
+            "primary: {primary:?}, fallback: {fallback:?}"
+        );
+    }
+}
@@ -0,0 +1,163 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+use smtp::outbound::client::SmtpLimits;
+
+use crate::smtp::{
+    DnsCache, TestSMTP,
+    inbound::TestQueueEvent,
+    session::TestSession,
+};
+
+const LOCAL: &str = r#"
+[session.rcpt]
+relay = true
+"#;
+
+const REMOTE: &str = r#"
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.rcpt]
+relay = true
+max-recipients = 2
+
+[session.data.limits]
+messages = 2
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn limits() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_limits_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+
+    let mut local = TestSMTP::new("smtp_limits_local", LOCAL).await;
+
+    // Add mock DNS entries
+    let core = local.build_smtp();
+    core.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+    session
+        .send_message(
+            "john@test.org",
+            &[
+                "a@foobar.org",
+                "b@foobar.org",
+                "c@foobar.org",
+                "d@foobar.org",
+                "e@foobar.org",
+            ],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+
+    // Recipients are split into RCPTMAX sized transactions, up to MAILMAX per connection
+    let message = local.queue_receiver.expect_message().await;
+    local
+        .queue_receiver
+        .delivery_attempt(message.queue_id)
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_refresh();
+    for expected_rcpts in [
+        ["a@foobar.org", "b@foobar.org"],
+        ["c@foobar.org", "d@foobar.org"],
+    ] {
+        assert_eq!(
+            remote
+                .queue_receiver
+                .expect_message()
+                .await
+                .message
+                .recipients
+                .iter()
+                .map(|rcpt| rcpt.address.as_str())
+                .collect::<Vec<_>>(),
+            expected_rcpts
+        );
+    }
+    remote.queue_receiver.assert_no_events();
+
+    // The remaining recipients are delivered over a new connection
+    local
+        .queue_receiver
+        .delivery_attempt(message.queue_id)
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_done();
+    assert_eq!(
+        remote
+            .queue_receiver
+            .expect_message()
+            .await
+            .message
+            .recipients
+            .iter()
+            .map(|rcpt| rcpt.address.as_str())
+            .collect::<Vec<_>>(),
+        ["e@foobar.org"]
+    );
+}
+
+#[test]
+fn limits_batches() {
+    let limits = SmtpLimits::parse(
+        b"250-mx.foobar.org\r\n250-LIMITS RCPTMAX=3 MAILMAX=2 RCPTDOMAINMAX=2 FOO=bar\r\n250 SIZE 0\r\n",
+    );
+    assert_eq!(
+        limits,
+        SmtpLimits {
+            rcpt_max: Some(3),
+            mail_max: Some(2),
+            rcpt_domain_max: Some(2),
+        }
+    );
+
+    // Invalid or zero values are ignored
+    assert_eq!(
+        SmtpLimits::parse(b"250-mx.foobar.org\r\n250 LIMITS RCPTMAX=0 MAILMAX=abc\r\n"),
+        SmtpLimits::default()
+    );
+
+    let domains = [
+        "a.org", "a.org", "b.org", "c.org", "a.org", "a.org", "a.org",
+    ];
+    let rcpt_idxs = (0..domains.len()).collect::<Vec<_>>();
+    assert_eq!(
+        limits.batches(&rcpt_idxs, |rcpt_idx| domains[rcpt_idx]),
+        vec![vec![0, 1], vec![2, 3, 4], vec![5]]
+    );
+    assert_eq!(
+        SmtpLimits::default().batches(&rcpt_idxs, |rcpt_idx| domains[rcpt_idx]),
+        vec![rcpt_idxs.clone()]
+    );
+}
@@ -8,8 +8,11 @@ pub mod dane;
 pub mod extensions;
 pub mod fallback_relay;
 pub mod ip_lookup;
+pub mod limits;
 pub mod lmtp;
 pub mod mta_sts;
+pub mod priority;
+pub mod proxy;
 pub mod smtp;
 pub mod throttle;
 pub mod tls;
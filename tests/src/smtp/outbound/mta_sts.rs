@@ -10,6 +10,7 @@ use std::{
 };
 
 use common::{
+    KV_MTA_STS_POLICY,
     config::{server::ServerProtocol, smtp::resolver::Policy},
     ipc::PolicyType,
 };
@@ -25,7 +26,10 @@ use crate::smtp::{
     inbound::{TestMessage, TestQueueEvent, TestReportingEvent},
     session::{TestSession, VerifyResponse},
 };
-use smtp::outbound::mta_sts::{lookup::STS_TEST_POLICY, parse::ParsePolicy};
+use smtp::outbound::mta_sts::{
+    lookup::{MtaStsLookup, STS_TEST_POLICY},
+    parse::ParsePolicy,
+};
 
 const LOCAL: &str = r#"
 [session.rcpt]
@@ -40,6 +44,17 @@ send = "weekly"
 
 "#;
 
+const LOCAL_FALLBACK: &str = r#"
+[session.rcpt]
+relay = true
+
+[queue.tls.default]
+mta-sts = "require"
+mta-sts-fallback = "deliver"
+allow-invalid-certs = true
+
+"#;
+
 const REMOTE: &str = r#"
 [session.ehlo]
 reject-non-fqdn = false
@@ -244,3 +259,100 @@ async fn mta_sts_verify() {
     );
     assert!(report.failure.is_none());
 }
+
+#[tokio::test]
+#[serial_test::serial]
+async fn mta_sts_persist_and_fallback() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_mta_sts_fallback_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+    let mut local = TestSMTP::new("smtp_mta_sts_fallback_local", LOCAL_FALLBACK).await;
+
+    // Add mock DNS entries
+    let core = local.build_smtp();
+    core.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.txt_add(
+        "_mta-sts.foobar.org",
+        MtaSts::parse(b"v=STSv1; id=policy_v1;").unwrap(),
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    // Fetched policies are persisted in the shared store
+    let policy = concat!(
+        "version: STSv1\n",
+        "mode: enforce\n",
+        "mx: *.foobar.org\n",
+        "max_age: 604800\n"
+    );
+    STS_TEST_POLICY.lock().clear();
+    STS_TEST_POLICY.lock().extend_from_slice(policy.as_bytes());
+    let expected_policy = Arc::new(Policy::parse(policy, "policy_v1".to_string()).unwrap());
+    assert_eq!(
+        core.lookup_mta_sts_policy("foobar.org", Duration::from_secs(1))
+            .await
+            .unwrap(),
+        expected_policy
+    );
+
+    // Persisted policies are used after a restart when the new policy can't be fetched
+    core.inner.cache.dbs_mta_sts.clear();
+    core.txt_add(
+        "_mta-sts.foobar.org",
+        MtaSts::parse(b"v=STSv1; id=policy_v2;").unwrap(),
+        Instant::now() + Duration::from_secs(10),
+    );
+    STS_TEST_POLICY.lock().clear();
+    assert_eq!(
+        core.lookup_mta_sts_policy("foobar.org", Duration::from_secs(1))
+            .await
+            .unwrap(),
+        expected_policy
+    );
+
+    // Without a persisted policy, the configured fallback delivers the message
+    core.inner.cache.dbs_mta_sts.clear();
+    core.in_memory_store()
+        .key_delete_prefix(&[KV_MTA_STS_POLICY])
+        .await
+        .unwrap();
+    assert!(
+        core.lookup_mta_sts_policy("foobar.org", Duration::from_secs(1))
+            .await
+            .is_err()
+    );
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_done();
+    remote
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&remote.queue_receiver)
+        .await
+        .assert_contains("bill@foobar.org");
+}
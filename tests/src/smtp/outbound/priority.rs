@@ -0,0 +1,119 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+
+use crate::smtp::{
+    DnsCache, TestSMTP,
+    inbound::TestQueueEvent,
+    session::{TestSession, VerifyResponse},
+};
+
+const LOCAL: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.extensions]
+mt-priority = 'mixer'
+"#;
+
+const REMOTE: &str = r#"
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.rcpt]
+relay = true
+
+[session.extensions]
+mt-priority = 'mixer'
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn mt_priority_relay() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_priority_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+
+    let mut local = TestSMTP::new("smtp_priority_local", LOCAL).await;
+
+    // Add mock DNS entries
+    let core = local.build_smtp();
+    core.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".into();
+    session.eval_session_params().await;
+    session
+        .ehlo("mx.test.org")
+        .await
+        .assert_contains("MT-PRIORITY MIXER");
+
+    // The priority is relayed to next hops that advertise MT-PRIORITY
+    session
+        .send_message(
+            "<john@test.org> MT-PRIORITY=3",
+            &["bill@foobar.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    let message = local.queue_receiver.expect_message().await;
+    assert_eq!(message.message.priority, 3);
+    local
+        .queue_receiver
+        .delivery_attempt(message.queue_id)
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_done();
+    assert_eq!(
+        remote
+            .queue_receiver
+            .expect_message()
+            .await
+            .message
+            .priority,
+        3
+    );
+
+    // Messages without a priority are relayed without the parameter
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_done();
+    assert_eq!(
+        remote
+            .queue_receiver
+            .expect_message()
+            .await
+            .message
+            .priority,
+        0
+    );
+}
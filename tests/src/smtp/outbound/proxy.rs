@@ -0,0 +1,113 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use mail_send::Credentials;
+use smtp::outbound::proxy::{ProxyTarget, http_connect, socks5_connect};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn proxy_handshake() {
+    // SOCKS5 with username/password authentication and remote DNS
+    let (mut client, mut server) = tokio::io::duplex(1024);
+    let proxy = tokio::spawn(async move {
+        let mut buf = [0u8; 4];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, [0x05, 0x02, 0x00, 0x02]);
+        server.write_all(&[0x05, 0x02]).await.unwrap();
+
+        let mut buf = [0u8; 13];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"\x01\x04john\x06secret");
+        server.write_all(&[0x01, 0x00]).await.unwrap();
+
+        let mut buf = [0u8; 20];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"\x05\x01\x00\x03\x0dmx.foobar.org\x00\x19");
+        server
+            .write_all(&[0x05, 0x00, 0x00, 0x01, 127, 0, 0, 1, 0x00, 0x19])
+            .await
+            .unwrap();
+        server
+            .write_all(b"220 mx.foobar.org ESMTP\r\n")
+            .await
+            .unwrap();
+        server
+    });
+    socks5_connect(
+        &mut client,
+        Some(&Credentials::new("john".into(), "secret".into())),
+        &ProxyTarget::Host("mx.foobar.org.", 25),
+    )
+    .await
+    .unwrap();
+    let _server = proxy.await.unwrap();
+    let mut greeting = [0u8; 25];
+    client.read_exact(&mut greeting).await.unwrap();
+    assert_eq!(&greeting, b"220 mx.foobar.org ESMTP\r\n");
+
+    // SOCKS5 connection refused
+    let (mut client, mut server) = tokio::io::duplex(1024);
+    tokio::spawn(async move {
+        let mut buf = [0u8; 3];
+        server.read_exact(&mut buf).await.unwrap();
+        server.write_all(&[0x05, 0x00]).await.unwrap();
+        let mut buf = [0u8; 10];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"\x05\x01\x00\x01\x0a\x00\x00\x01\x00\x19");
+        server
+            .write_all(&[0x05, 0x05, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+            .await
+            .unwrap();
+        server
+    });
+    assert!(
+        socks5_connect(
+            &mut client,
+            None,
+            &ProxyTarget::Addr("10.0.0.1:25".parse().unwrap()),
+        )
+        .await
+        .is_err()
+    );
+
+    // HTTP CONNECT with basic authentication
+    for (status, is_ok) in [("200 Connection established", true), ("407 Denied", false)] {
+        let (mut client, mut server) = tokio::io::duplex(1024);
+        let proxy = tokio::spawn(async move {
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                request.push(server.read_u8().await.unwrap());
+            }
+            server
+                .write_all(
+                    format!("HTTP/1.1 {status}\r\n\r\n220 mx.foobar.org ESMTP\r\n").as_bytes(),
+                )
+                .await
+                .unwrap();
+            (server, String::from_utf8(request).unwrap())
+        });
+        let result = http_connect(
+            &mut client,
+            Some(&Credentials::new("john".into(), "secret".into())),
+            &ProxyTarget::Addr("[::1]:25".parse().unwrap()),
+        )
+        .await;
+        let (_server, request) = proxy.await.unwrap();
+        assert_eq!(
+            request,
+            concat!(
+                "CONNECT [::1]:25 HTTP/1.1\r\nHost: [::1]:25\r\n",
+                "Proxy-Authorization: Basic am9objpzZWNyZXQ=\r\n\r\n"
+            )
+        );
+        assert_eq!(result.is_ok(), is_ok, "{status}");
+        if is_ok {
+            let mut greeting = [0u8; 25];
+            client.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(&greeting, b"220 mx.foobar.org ESMTP\r\n");
+        }
+    }
+}
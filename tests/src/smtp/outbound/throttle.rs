@@ -11,7 +11,10 @@ use crate::smtp::{
     session::TestSession,
 };
 use mail_auth::MX;
-use smtp::queue::{Message, QueueEnvelope, Recipient, throttle::IsAllowed};
+use smtp::queue::{
+    Message, QueueEnvelope, Recipient,
+    throttle::{IsAllowed, IsProviderAllowed},
+};
 use std::{
     net::{IpAddr, Ipv4Addr},
     time::{Duration, Instant},
@@ -275,6 +278,90 @@ async fn throttle_outbound() {
     assert!(due > 0, "Due: {}", due);
 }
 
+const CONFIG_PROVIDER: &str = r#"
+[queue.provider.google]
+mx = ["*.google.com", "*.googlemail.com"]
+rate = "2/1h"
+warmup.start = "2020-01-01T00:00:00Z"
+warmup.schedule = ["1/1h", "5/1h"]
+
+[queue.provider.outlook]
+mx = "*.mail.protection.outlook.com"
+backoff.initial = "10m"
+backoff.max = "30m"
+"#;
+
+#[tokio::test]
+async fn throttle_provider() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new("smtp_throttle_provider", CONFIG_PROVIDER).await;
+    let core = local.build_smtp();
+    let queue_config = &core.core.smtp.queue;
+
+    // Match MX hosts to providers
+    let google = queue_config
+        .provider_profile("gmail-smtp-in.l.google.com.")
+        .unwrap();
+    assert_eq!(google.id, "google");
+    let outlook = queue_config
+        .provider_profile("test-org.mail.protection.outlook.com")
+        .unwrap();
+    assert_eq!(outlook.id, "outlook");
+    assert!(queue_config.provider_profile("mx.test.org").is_none());
+    assert!(
+        queue_config
+            .provider_profile("google.com.evil.org")
+            .is_none()
+    );
+
+    // Warm-up schedule
+    let start = google.warmup_start;
+    assert_eq!(google.current_rate(start).unwrap().requests, 1);
+    assert_eq!(google.current_rate(start + 86400).unwrap().requests, 5);
+    assert_eq!(google.current_rate(start + 2 * 86400).unwrap().requests, 2);
+    assert!(outlook.current_rate(now()).is_none());
+
+    // Steady-state rate
+    core.is_provider_allowed(google, 0).await.unwrap();
+    core.is_provider_allowed(google, 0).await.unwrap();
+    let retry_at = core.is_provider_allowed(google, 0).await.unwrap_err();
+    assert!(retry_at > now());
+
+    // Unrelated temporary failures do not trigger a backoff
+    core.provider_backoff(outlook, "452 4.2.2 Mailbox full", 0)
+        .await;
+    core.is_provider_allowed(outlook, 0).await.unwrap();
+
+    // Rate limiting responses pause delivery to the provider
+    core.provider_backoff(
+        outlook,
+        "451 4.7.650 The mail server has been temporarily rate limited due to IP reputation.",
+        0,
+    )
+    .await;
+    let retry_at = core.is_provider_allowed(outlook, 0).await.unwrap_err();
+    assert!(retry_at > now() + 500 && retry_at <= now() + 600);
+
+    // Consecutive deferrals double the pause, up to the maximum
+    core.provider_backoff(outlook, "421 Too many connections", 0)
+        .await;
+    let retry_at = core.is_provider_allowed(outlook, 0).await.unwrap_err();
+    assert!(retry_at > now() + 1100 && retry_at <= now() + 1200);
+    core.provider_backoff(outlook, "421 Too many connections", 0)
+        .await;
+    let retry_at = core.is_provider_allowed(outlook, 0).await.unwrap_err();
+    assert!(retry_at > now() + 1700 && retry_at <= now() + 1800);
+
+    // A successful delivery resets the deferral streak
+    core.provider_reset(outlook, 0).await;
+    core.provider_backoff(outlook, "421 Too many connections", 0)
+        .await;
+    let retry_at = core.is_provider_allowed(outlook, 0).await.unwrap_err();
+    assert!(retry_at > now() + 500 && retry_at <= now() + 600);
+}
+
 pub trait TestQueueEnvelope<'x> {
     fn test(message: &'x Message, rcpt: &'x Recipient, mx: &'x str) -> Self;
 }
@@ -7,7 +7,9 @@
 use std::time::{Duration, Instant};
 
 use common::config::server::ServerProtocol;
+use futures::future::join_all;
 use mail_auth::MX;
+use smtp::outbound::posture::{TlsPosture, TlsPostureHistory};
 use store::write::now;
 
 use crate::smtp::{
@@ -87,6 +89,14 @@ async fn starttls_optional() {
         .await
         .try_deliver(core.clone());
     let mut retry = local.queue_receiver.expect_message().await;
+
+    // Failed deliveries do not change the TLS posture of the domain
+    assert!(
+        core.tls_posture_history("foobar.org")
+            .await
+            .unwrap()
+            .is_empty()
+    );
     let prev_due = retry.message.recipients[0].retry.due;
     let next_due = now();
     let queue_id = retry.queue_id;
@@ -105,4 +115,18 @@ async fn starttls_optional() {
         .read_lines(&remote.queue_receiver)
         .await
         .assert_not_contains("using TLSv1.3 with cipher");
+
+    // The plain-text delivery is recorded once it has been accepted
+    let history = core.tls_posture_history("foobar.org").await.unwrap();
+    assert_eq!(history.len(), 1, "{history:?}");
+    assert_eq!(history[0].mx, "mx.foobar.org");
+    assert!(!history[0].tls);
+
+    // Concurrent deliveries are not lost and only the most recent ones are kept
+    join_all((0..60).map(|_| core.record_tls_posture("foobar.org", TlsPosture::plaintext("mx"))))
+        .await;
+    assert_eq!(
+        core.tls_posture_history("foobar.org").await.unwrap().len(),
+        50
+    );
 }
@@ -72,6 +72,53 @@ async fn queue_due() {
     qr.assert_queue_is_empty().await;
 }
 
+#[tokio::test]
+async fn queue_priority() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new("smtp_queue_priority_test", CONFIG).await;
+    let core = local.build_smtp();
+    let qr = &local.queue_receiver;
+
+    for (queue_id, priority) in [(0, 0), (1, -4), (2, 4), (3, 0), (4, 2)] {
+        let mut message = new_message(queue_id);
+        message.message.priority = priority;
+        message.message.recipients.push(build_rcpt("a", 0, 6, 7));
+        message.save_changes(&core, 0.into()).await;
+    }
+
+    // Due messages are delivered by descending MT-PRIORITY
+    let mut queued = core.all_queued_messages().await;
+    queued.prioritize();
+    assert_eq!(
+        queued
+            .messages
+            .iter()
+            .map(|event| event.priority)
+            .collect::<Vec<_>>(),
+        vec![4, 2, 0, 0, -4]
+    );
+    assert_eq!(
+        queued
+            .messages
+            .iter()
+            .map(|event| event.queue_id)
+            .filter(|queue_id| ![0, 3].contains(queue_id))
+            .collect::<Vec<_>>(),
+        vec![2, 4, 1]
+    );
+
+    for queue_event in queued.messages {
+        core.read_message(queue_event.queue_id, QueueName::default())
+            .await
+            .unwrap()
+            .remove(&core, queue_event.due.into())
+            .await;
+    }
+    qr.assert_queue_is_empty().await;
+}
+
 #[test]
 fn delivery_events() {
     let mut message = new_message(0).message;
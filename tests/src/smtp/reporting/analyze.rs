@@ -4,16 +4,35 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::smtp::{TestSMTP, inbound::TestQueueEvent, session::TestSession};
+use crate::smtp::{DnsCache, TestSMTP, inbound::TestQueueEvent, session::TestSession};
 
+use mail_auth::{common::parse::TxtRecordParser, report::Feedback, spf::Spf};
+use mail_parser::MessageParser;
+use smtp::reporting::feedback::{FeedbackComplaint, FeedbackLoop};
 use store::{
     IterateParams, ValueKey,
     write::{ReportClass, ValueClass},
 };
 
 const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "spammer"
+description = "Some Spammer"
+secret = "secret"
+email = "somespammer@example.net"
+
+[auth.spf.verify]
+mail-from = [{if = "sender_domain = 'example.com'", then = 'relaxed'},
+             {else = false}]
+
 [session.rcpt]
 relay = true
 
@@ -24,6 +43,7 @@ messages = 100
 addresses = ["reports@*", "*@dmarc.foobar.org", "feedback@foobar.org"]
 forward = false
 store = "1s"
+suppress-complainants = "180d"
 "#;
 
 #[tokio::test(flavor = "multi_thread")]
@@ -36,8 +56,10 @@ async fn report_analyze() {
 
     // Create test message
     let mut session = local.new_session();
+    let server = local.build_smtp();
     let qr = &mut local.queue_receiver;
     session.data.remote_ip_str = "10.0.0.1".into();
+    session.data.remote_ip = session.data.remote_ip_str.parse().unwrap();
     session.eval_session_params().await;
     session.ehlo("mx.test.org").await;
 
@@ -65,6 +87,41 @@ async fn report_analyze() {
     }
     tokio::time::sleep(Duration::from_millis(200)).await;
 
+    // Complaints from unauthenticated reporters are ignored
+    assert!(
+        !server
+            .is_suppressed("example.net", "user@example.com", 0)
+            .await
+    );
+
+    // Abuse complaints suppress the complainant for the reported sender
+    server.txt_add(
+        "example.com",
+        Spf::parse(b"v=spf1 ip4:10.0.0.1 -all").unwrap(),
+        Instant::now() + Duration::from_secs(5),
+    );
+    total_reports_received += 1;
+    session
+        .send_message(
+            "abusedesk@example.com",
+            &["feedback@foobar.org"],
+            "report:arf2",
+            "250",
+        )
+        .await;
+    qr.assert_no_events();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(
+        server
+            .is_suppressed("example.net", "user@example.com", 0)
+            .await
+    );
+    assert!(
+        !server
+            .is_suppressed("example.org", "user@example.com", 0)
+            .await
+    );
+
     //let c = tokio::time::sleep(Duration::from_secs(86400)).await;
 
     // Purging the database shouldn't remove the reports
@@ -119,3 +176,77 @@ async fn report_analyze() {
     qr.read_event().await.assert_refresh();
     qr.last_queued_message().await;
 }
+
+#[test]
+fn report_feedback_correlation() {
+    let report = Feedback::parse_arf(FEEDBACK_REPORT.as_bytes()).unwrap();
+    let original = MessageParser::new().parse(ORIGINAL_MESSAGE).unwrap();
+
+    assert_eq!(
+        FeedbackComplaint::parse(&report, Some(&original)),
+        FeedbackComplaint {
+            complainant: Some("bill@example.org".into()),
+            sender_domain: Some("foobar.org".into()),
+            feedback_id: Some("campaign42:newsletter:foobar".into()),
+            queue_id: Some(0x1a2b3c4d),
+        }
+    );
+
+    // Without an original message only the report fields are used
+    assert_eq!(
+        FeedbackComplaint::parse(&report, None),
+        FeedbackComplaint {
+            complainant: Some("bill@example.org".into()),
+            sender_domain: Some("foobar.org".into()),
+            feedback_id: None,
+            queue_id: None,
+        }
+    );
+
+    // Received headers added by other MTAs are ignored
+    let original = ORIGINAL_MESSAGE.replace("Stalwart SMTP", "Postfix");
+    let original = MessageParser::new().parse(original.as_bytes()).unwrap();
+    assert_eq!(
+        FeedbackComplaint::parse(&report, Some(&original)).queue_id,
+        None
+    );
+
+    // The earliest hop is used when the recipient's MTA is also Stalwart
+    let original = format!(
+        concat!(
+            "Received: from mail.example.org (mail.example.org [10.0.0.3])\r\n",
+            "\tby mx.example.org (Stalwart SMTP) with ESMTPS id 5E6F;\r\n",
+            "\tThu, 8 Mar 2025 14:00:01 +0000\r\n",
+            "{}"
+        ),
+        ORIGINAL_MESSAGE
+    );
+    let original = MessageParser::new().parse(original.as_bytes()).unwrap();
+    assert_eq!(
+        FeedbackComplaint::parse(&report, Some(&original)).queue_id,
+        Some(0x1a2b3c4d)
+    );
+}
+
+const FEEDBACK_REPORT: &str = "Feedback-Type: abuse
+User-Agent: SomeGenerator/1.0
+Version: 1
+Original-Mail-From: <John@Foobar.org>
+Original-Rcpt-To: <Bill@Example.org>
+Reporting-MTA: dns; mail.example.org
+";
+
+const ORIGINAL_MESSAGE: &str = concat!(
+    "Received: from mx.foobar.org (mx.foobar.org [10.0.0.1])\r\n",
+    "\tby mail.example.org (Postfix) with ESMTPS id 4XyZ;\r\n",
+    "\tThu, 8 Mar 2025 14:00:00 +0000\r\n",
+    "Received: from [10.0.0.2] (unknown [10.0.0.2])\r\n",
+    "\tby mx.foobar.org (Stalwart SMTP) with ESMTPSA id 1A2B3C4D;\r\n",
+    "\tThu, 8 Mar 2025 13:59:59 +0000\r\n",
+    "Feedback-ID: campaign42:newsletter:foobar\r\n",
+    "From: john@foobar.org\r\n",
+    "To: bill@example.org\r\n",
+    "Subject: Newsletter\r\n",
+    "\r\n",
+    "Hello.\r\n"
+);
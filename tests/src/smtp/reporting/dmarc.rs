@@ -16,7 +16,7 @@ use mail_auth::{
     dmarc::Dmarc,
     report::{ActionDisposition, Disposition, DmarcResult, Record, Report},
 };
-use smtp::reporting::dmarc::DmarcReporting;
+use smtp::reporting::dmarc::{DmarcReporting, redact_recipients};
 use store::write::QueueClass;
 
 use crate::smtp::{
@@ -152,4 +152,56 @@ async fn report_dmarc() {
         }
     }
     qr.assert_report_is_empty().await;
+
+    // Reports exceeding the size limit of a reporting address are not sent
+    let dmarc_record = Arc::new(
+        Dmarc::parse(
+            b"v=DMARC1; p=none; rua=mailto:reports@foobar.net!1k,mailto:reports@example.net",
+        )
+        .unwrap(),
+    );
+    assert_eq!(dmarc_record.rua()[0].max_size(), 1024);
+    core.schedule_dmarc(Box::new(DmarcEvent {
+        domain: "foobar.org".to_string(),
+        report_record: Record::new()
+            .with_source_ip("192.168.1.2".parse().unwrap())
+            .with_action_disposition(ActionDisposition::Pass)
+            .with_dmarc_dkim_result(DmarcResult::Pass)
+            .with_dmarc_spf_result(DmarcResult::Pass),
+        dmarc_record,
+        interval: AggregateFrequency::Weekly,
+    }))
+    .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let reports = qr.read_report_events().await;
+    assert_eq!(reports.len(), 1);
+    match reports.into_iter().next().unwrap() {
+        QueueClass::DmarcReportHeader(event) => {
+            core.send_dmarc_aggregate_report(event).await;
+        }
+        _ => unreachable!(),
+    }
+    qr.assert_no_events();
+    qr.assert_report_is_empty().await;
+}
+
+#[test]
+fn report_dmarc_redaction() {
+    assert_eq!(
+        redact_recipients(concat!(
+            "From: bill@example.org\r\n",
+            "To: \"John Doe\" <john@foobar.org>,\r\n",
+            "\tjane@example.net\r\n",
+            "Cc: undisclosed-recipients:;\r\n",
+            "Subject: Hello\r\n",
+            "\r\n"
+        )),
+        concat!(
+            "From: bill@example.org\r\n",
+            "To: redacted@foobar.org, redacted@example.net\r\n",
+            "Cc: undisclosed-recipients:;\r\n",
+            "Subject: Hello\r\n",
+            "\r\n"
+        )
+    );
 }
@@ -415,6 +415,78 @@ pub async fn blob_tests() {
     temp_dir.delete();
 }
 
+#[tokio::test]
+pub async fn fs_spool_tests() {
+    let temp_dir = TempDir::new("fs_spool_tests", true);
+    let tmp = temp_dir.path.as_path().to_str().unwrap();
+
+    // Leave temporary files behind as if a write was interrupted
+    for dir in ["blob", "spool", "spool/ab"] {
+        let dir = temp_dir.path.join(dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("AAAA.1f2e3d.tmp"), b"abc").unwrap();
+    }
+
+    let mut config = Config::new(SPOOL_CONFIG.replace("{TMP}", tmp)).unwrap();
+    let stores = Stores::parse_all(&mut config, false).await;
+
+    // Stale temporary files are only removed from stores with synced writes
+    assert!(temp_dir.path.join("blob/AAAA.1f2e3d.tmp").exists());
+    assert!(!temp_dir.path.join("spool/AAAA.1f2e3d.tmp").exists());
+    assert!(!temp_dir.path.join("spool/ab/AAAA.1f2e3d.tmp").exists());
+
+    // Writes to the spool are synced and renamed into place
+    for store_id in ["blob", "spool"] {
+        println!("Testing blob store {}...", store_id);
+        test_store(stores.blob_stores.get(store_id).unwrap().clone()).await;
+    }
+    let spool = stores.blob_stores.get("spool").unwrap();
+    let hash = BlobHash::generate(b"spooled message".as_slice());
+    spool
+        .put_blob(hash.as_ref(), b"spooled message")
+        .await
+        .unwrap();
+    assert_eq!(
+        spool
+            .get_blob(hash.as_ref(), 0..usize::MAX)
+            .await
+            .unwrap()
+            .unwrap(),
+        b"spooled message"
+    );
+    assert_eq!(num_temporary_files(&temp_dir.path.join("spool")), 0);
+
+    temp_dir.delete();
+}
+
+const SPOOL_CONFIG: &str = r#"
+[store."blob"]
+type = "fs"
+path = "{TMP}/blob"
+
+[store."spool"]
+type = "fs"
+path = "{TMP}/spool"
+
+[storage]
+blob = "blob"
+queue = "spool"
+"#;
+
+fn num_temporary_files(path: &std::path::Path) -> usize {
+    std::fs::read_dir(path)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                num_temporary_files(&path)
+            } else {
+                path.extension().is_some_and(|ext| ext == "tmp") as usize
+            }
+        })
+        .sum()
+}
+
 async fn test_store(store: BlobStore) {
     // Test small blob
     const DATA: &[u8] = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit. Fusce erat nisl, dignissim a porttitor id, varius nec arcu. Sed mauris.";
@@ -19,7 +19,7 @@ use store::{
     },
     *,
 };
-use utils::BlobHash;
+use utils::{BlobHash, config::Config};
 
 pub async fn test(db: Store) {
     let mut core = Core::default();
@@ -46,6 +46,32 @@ pub async fn test(db: Store) {
             .unwrap();
         batch.set(ValueClass::Blob(BlobOp::Commit { hash }), vec![]);
     }
+
+    // Queued messages spooled to a separate blob store are exported as well
+    let spool_dir = TempDir::new("art_vandelay_spool", true);
+    let mut config = Config::new(format!(
+        "[store.\"spool\"]\ntype = \"fs\"\npath = {:?}\n",
+        spool_dir.path.to_str().unwrap()
+    ))
+    .unwrap();
+    let spool = Stores::parse_all(&mut config, false)
+        .await
+        .blob_stores
+        .remove("spool")
+        .unwrap();
+    let spooled_data = random_bytes(1024);
+    let spooled_hash = BlobHash::generate(spooled_data.as_slice());
+    spool
+        .put_blob(spooled_hash.as_ref(), &spooled_data)
+        .await
+        .unwrap();
+    core.storage.queue = Some(spool);
+    batch.set(
+        ValueClass::Blob(BlobOp::Commit {
+            hash: spooled_hash.clone(),
+        }),
+        vec![],
+    );
     db.write(batch.build_all()).await.unwrap();
 
     // Create account data
@@ -248,6 +274,22 @@ pub async fn test(db: Store) {
     println!("Importing store...");
     core.restore(temp_dir.path.clone()).await;
 
+    // Spooled messages are restored to the blob store
+    assert_eq!(
+        core.storage
+            .blob
+            .get_blob(spooled_hash.as_ref(), 0..usize::MAX)
+            .await
+            .unwrap(),
+        Some(spooled_data)
+    );
+    core.storage
+        .blob
+        .delete_blob(spooled_hash.as_ref())
+        .await
+        .unwrap();
+    spool_dir.delete();
+
     // Verify hash
     print!("Verifying store hash...");
     snapshot.assert_is_eq(&Snapshot::new(&db).await);
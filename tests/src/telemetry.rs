@@ -0,0 +1,119 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use common::config::telemetry::Telemetry;
+use store::Stores;
+use trc::{
+    Event, EventDetails, EventType, Key, Level, SmtpEvent, Value, ipc::subscriber::EventFilter,
+};
+use utils::config::Config;
+
+use crate::AssertConfig;
+
+const CONFIG: &str = r#"
+[tracer.filtered]
+type = "console"
+level = "trace"
+
+[tracer.filtered.sample]
+"smtp.rcpt-to" = 0.0
+"smtp.mail-from" = 1.0
+
+[tracer.filtered.filter]
+exclude = ["remote-ip == 10.0.0.1",
+           "size > 1000",
+           "domain ends-with .internal"]
+
+[tracer.unfiltered]
+type = "console"
+level = "trace"
+"#;
+
+#[test]
+fn telemetry_filters() {
+    let mut config = Config::new(CONFIG).unwrap();
+    let telemetry = Telemetry::parse(&mut config, &Stores::default());
+    config.assert_no_errors();
+
+    let filter_for = |id: &str| {
+        telemetry
+            .tracers
+            .subscribers
+            .iter()
+            .find(|tracer| tracer.id == id)
+            .unwrap()
+            .filter
+            .clone()
+    };
+    assert!(filter_for("t_unfiltered").is_none());
+    let filter = filter_for("t_filtered").unwrap();
+
+    // Sampling rates apply per event type
+    let mail_from = EventType::Smtp(SmtpEvent::MailFrom);
+    assert!(accepts(&filter, mail_from, vec![]));
+    assert!(!accepts(
+        &filter,
+        EventType::Smtp(SmtpEvent::RcptTo),
+        vec![]
+    ));
+    assert!(accepts(&filter, EventType::Smtp(SmtpEvent::Ehlo), vec![]));
+
+    // Exclusion conditions only apply to events that include the key
+    for (keys, expected) in [
+        (
+            vec![(Key::RemoteIp, Value::Ipv4("10.0.0.1".parse().unwrap()))],
+            false,
+        ),
+        (
+            vec![(Key::RemoteIp, Value::Ipv4("10.0.0.2".parse().unwrap()))],
+            true,
+        ),
+        (vec![(Key::Size, Value::UInt(1001))], false),
+        (vec![(Key::Size, Value::UInt(1000))], true),
+        (
+            vec![(Key::Domain, Value::String("host.internal".into()))],
+            false,
+        ),
+        (
+            vec![(Key::Domain, Value::String("internal.example.org".into()))],
+            true,
+        ),
+        (
+            vec![(
+                Key::Domain,
+                Value::Array(vec![
+                    Value::String("example.org".into()),
+                    Value::String("mx.internal".into()),
+                ]),
+            )],
+            false,
+        ),
+        (
+            vec![(Key::Hostname, Value::String("10.0.0.1".into()))],
+            true,
+        ),
+    ] {
+        assert_eq!(
+            accepts(&filter, mail_from, keys.clone()),
+            expected,
+            "{keys:?}"
+        );
+    }
+}
+
+fn accepts(filter: &Arc<dyn EventFilter>, typ: EventType, keys: Vec<(Key, Value)>) -> bool {
+    filter.accept(&Event {
+        inner: EventDetails {
+            typ,
+            timestamp: 0,
+            level: Level::Info,
+            span: None,
+        },
+        keys,
+    })
+}
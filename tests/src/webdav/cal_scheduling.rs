@@ -16,10 +16,15 @@ use calcard::{
         ICalendarProperty, ICalendarRecurrenceRule, ICalendarWeekday,
     },
 };
-use common::{Server, auth::AccessToken};
+use common::{Server, auth::AccessToken, config::groupware::ResourceBookingPolicy};
 use dav_proto::schema::property::{CalDavProperty, DavProperty, WebDavProperty};
+use directory::{
+    Type,
+    backend::internal::{PrincipalField, PrincipalSet, PrincipalValue, manage::ManageDirectory},
+};
 use email::cache::MessageCacheFetch;
 use groupware::{
+    DavResourceName,
     cache::GroupwareCache,
     scheduling::{
         ArchivedItipSummary, ItipField, ItipParticipant, ItipSummary, ItipTime, ItipValue,
@@ -519,7 +524,110 @@ pub async fn test(test: &WebDavTest) {
         Vec::<String>::new()
     );
 
-    for client in [bill_client, jane_client, john_client] {
+    // Bookings are left for the delegates of a resource to approve
+    let core = test.server.inner.shared_core.load_full();
+    let mut booking_core = core.as_ref().clone();
+    booking_core.groupware.resource_booking.policy = ResourceBookingPolicy::Manual;
+    test.server.inner.shared_core.store(booking_core.into());
+    let room_id = test
+        .server
+        .store()
+        .create_principal(
+            PrincipalSet::new(0, Type::Location)
+                .with_field(PrincipalField::Name, "room")
+                .with_field(PrincipalField::Description, "Meeting Room")
+                .with_field(
+                    PrincipalField::Secrets,
+                    PrincipalValue::StringList(vec!["room_secret".into()]),
+                )
+                .with_field(
+                    PrincipalField::Emails,
+                    PrincipalValue::StringList(vec!["room@example.com".into()]),
+                )
+                .with_field(
+                    PrincipalField::Roles,
+                    PrincipalValue::StringList(vec!["user".into()]),
+                ),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .id;
+    let room_client = DummyWebDavClient::new(room_id, "room", "room_secret", "room@example.com");
+    let booking_itip = test_itip
+        .replace("UID:9263504FD3AD", "UID:9263504FD3AE")
+        .replace(
+            "ATTENDEE;CUTYPE=INDIVIDUAL:mailto:jane.smith@example.com\n",
+            "",
+        )
+        .replace(
+            "ATTENDEE;CUTYPE=INDIVIDUAL:mailto:bill@example.com",
+            "ATTENDEE;CUTYPE=ROOM:mailto:room@example.com",
+        );
+    john_client
+        .request_with_headers(
+            "PUT",
+            "/dav/cal/john/default/booking.ics",
+            [("content-type", "text/calendar; charset=utf-8")],
+            &booking_itip,
+        )
+        .await
+        .with_status(StatusCode::CREATED);
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    let cals = fetch_icals(&room_client).await;
+    assert_eq!(cals.len(), 1);
+    assert!(
+        cals[0]
+            .ical
+            .contains("PARTSTAT=NEEDS-ACTION:mailto:room@example.com"),
+        "failed for cal: {}",
+        cals[0].ical
+    );
+    assert_eq!(
+        fetch_and_remove_itips(john_client).await,
+        Vec::<String>::new()
+    );
+
+    // Approve the booking as a delegate with write access to the resource calendar
+    room_client
+        .acl(
+            "/dav/cal/room/default/",
+            &format!("{}/jane/", DavResourceName::Principal.base_path()),
+            ["read", "write"],
+        )
+        .await
+        .with_status(StatusCode::OK);
+    let cals = fetch_account_icals(jane_client, "room").await;
+    assert_eq!(cals.len(), 1);
+    let cal = cals.into_iter().next().unwrap();
+    jane_client
+        .request_with_headers(
+            "PUT",
+            &cal.href,
+            [
+                ("content-type", "text/calendar; charset=utf-8"),
+                ("if-schedule-tag-match", cal.schedule_tag.as_str()),
+            ],
+            &cal.ical.replace(
+                "PARTSTAT=NEEDS-ACTION:mailto:room@example.com",
+                "PARTSTAT=ACCEPTED:mailto:room@example.com",
+            ),
+        )
+        .await
+        .with_status(StatusCode::NO_CONTENT);
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    let itips = fetch_and_remove_itips(john_client).await;
+    assert_eq!(itips.len(), 1);
+    assert!(
+        itips[0].contains("METHOD:REPLY")
+            && itips[0].contains("PARTSTAT=ACCEPTED:mailto:room@example.com"),
+        "failed for itip: {}",
+        itips[0]
+    );
+    test.server.inner.shared_core.store(core);
+
+    for client in [bill_client, jane_client, john_client, &room_client] {
         client.delete_default_containers().await;
         destroy_all_mailboxes_for_account(client.account_id).await;
     }
@@ -559,7 +667,11 @@ struct CalEntry {
 }
 
 async fn fetch_icals(client: &DummyWebDavClient) -> Vec<CalEntry> {
-    let cal_inbox = format!("/dav/cal/{}/default/", client.name);
+    fetch_account_icals(client, client.name).await
+}
+
+async fn fetch_account_icals(client: &DummyWebDavClient, account: &str) -> Vec<CalEntry> {
+    let cal_inbox = format!("/dav/cal/{account}/default/");
     let response = client
         .propfind_with_headers(&cal_inbox, ALL_DAV_PROPERTIES, [("depth", "1")])
         .await;
@@ -0,0 +1,209 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{DummyWebDavClient, WebDavTest};
+use groupware::DavResourceName;
+use hyper::StatusCode;
+use jmap_proto::types::id::Id;
+use serde_json::{Value, json};
+
+pub async fn test(test: &WebDavTest) {
+    println!("Running JMAP ContactCard tests...");
+    let client = test.client("john");
+    let account_id = Id::from(client.account_id).to_string();
+
+    // Create test data
+    let default_path = format!("{}/john/default/", DavResourceName::Card.base_path());
+    for (name, vcard) in [("sarah.vcf", VCARD_SARAH), ("carlos.vcf", VCARD_CARLOS)] {
+        client
+            .request("PUT", &format!("{default_path}{name}"), vcard)
+            .await
+            .with_status(StatusCode::CREATED);
+    }
+
+    // The CardDAV address book is visible over JMAP
+    let response = client
+        .jmap(json!([[
+            "AddressBook/get",
+            {"accountId": account_id, "properties": ["id", "isDefault"]},
+            "0"
+        ]]))
+        .await;
+    let books = response["list"].as_array().unwrap();
+    assert_eq!(books.len(), 1, "{response}");
+    assert_eq!(books[0]["isDefault"], true, "{response}");
+    let book_id = books[0]["id"].as_str().unwrap().to_string();
+
+    // Cards are sorted by name by default
+    let response = client
+        .jmap(json!([
+            ["ContactCard/query", {"accountId": account_id}, "0"],
+            [
+                "ContactCard/get",
+                {
+                    "accountId": account_id,
+                    "#ids": {"resultOf": "0", "name": "ContactCard/query", "path": "/ids"},
+                    "properties": ["name", "emails", "phones", "addressBookIds"]
+                },
+                "1"
+            ]
+        ]))
+        .await;
+    let cards = response["list"].as_array().unwrap();
+    assert_eq!(
+        cards
+            .iter()
+            .map(|card| card["name"]["full"].as_str().unwrap())
+            .collect::<Vec<_>>(),
+        ["Carlos Rodriguez-Martinez", "Sarah Johnson"],
+        "{response}"
+    );
+    assert_eq!(
+        cards[0]["emails"],
+        json!({
+            "e1": {"address": "carlos.rodriguez@example-corp.com"},
+            "e2": {"address": "carlosrm@personalmail.example"}
+        })
+    );
+    assert_eq!(
+        cards[1]["phones"],
+        json!({"p1": {"number": "+1-555-123-4567"}})
+    );
+    assert_eq!(
+        cards[1]["addressBookIds"],
+        json!({ book_id.as_str(): true })
+    );
+    let state = response["state"].as_str().unwrap().to_string();
+
+    // Name, email and text filters
+    for (filter, expected) in [
+        (json!({"name": "sarah"}), vec!["Sarah Johnson"]),
+        (
+            json!({"email": "PERSONALMAIL"}),
+            vec!["Carlos Rodriguez-Martinez"],
+        ),
+        (json!({"text": "555-123"}), vec!["Sarah Johnson"]),
+        (
+            json!({"inAddressBook": book_id}),
+            vec!["Carlos Rodriguez-Martinez", "Sarah Johnson"],
+        ),
+        (json!({"name": "nobody"}), vec![]),
+    ] {
+        assert_eq!(client.query_names(&account_id, filter).await, expected);
+    }
+
+    // Unsupported filters are rejected
+    let response = client
+        .jmap(json!([[
+            "ContactCard/query",
+            {"accountId": account_id, "filter": {"subject": "test"}},
+            "0"
+        ]]))
+        .await;
+    assert_eq!(response["type"], "unsupportedFilter", "{response}");
+
+    // Changes made over CardDAV are reported by ContactCard/changes
+    client
+        .request("DELETE", &format!("{default_path}sarah.vcf"), "")
+        .await
+        .with_status(StatusCode::NO_CONTENT);
+    let response = client
+        .jmap(json!([[
+            "ContactCard/changes",
+            {"accountId": account_id, "sinceState": state},
+            "0"
+        ]]))
+        .await;
+    assert_eq!(response["created"], json!([]), "{response}");
+    assert_eq!(
+        response["destroyed"].as_array().unwrap().len(),
+        1,
+        "{response}"
+    );
+    assert_eq!(
+        client.query_names(&account_id, json!({})).await,
+        ["Carlos Rodriguez-Martinez"]
+    );
+
+    // Other accounts can't read the address book unless it is shared
+    let response = test
+        .client("jane")
+        .jmap(json!([[
+            "ContactCard/query",
+            {"accountId": account_id},
+            "0"
+        ]]))
+        .await;
+    assert_eq!(response["type"], "forbidden", "{response}");
+
+    client.delete_default_containers().await;
+    test.assert_is_empty().await;
+}
+
+impl DummyWebDavClient {
+    // Returns the arguments of the last method response
+    pub async fn jmap(&self, method_calls: Value) -> Value {
+        let response = self
+            .request(
+                "POST",
+                "/jmap",
+                json!({
+                    "using": ["urn:ietf:params:jmap:core", "urn:ietf:params:jmap:contacts"],
+                    "methodCalls": method_calls
+                })
+                .to_string(),
+            )
+            .await
+            .with_status(StatusCode::OK);
+        let mut response: Value = serde_json::from_str(response.body.as_ref().unwrap()).unwrap();
+        response["methodResponses"]
+            .as_array_mut()
+            .and_then(|responses| responses.pop())
+            .map(|mut response| response[1].take())
+            .unwrap()
+    }
+
+    async fn query_names(&self, account_id: &str, filter: Value) -> Vec<String> {
+        let response = self
+            .jmap(json!([
+                ["ContactCard/query", {"accountId": account_id, "filter": filter}, "0"],
+                [
+                    "ContactCard/get",
+                    {
+                        "accountId": account_id,
+                        "#ids": {"resultOf": "0", "name": "ContactCard/query", "path": "/ids"},
+                        "properties": ["name"]
+                    },
+                    "1"
+                ]
+            ]))
+            .await;
+        response["list"]
+            .as_array()
+            .unwrap_or_else(|| panic!("{response}"))
+            .iter()
+            .map(|card| card["name"]["full"].as_str().unwrap().to_string())
+            .collect()
+    }
+}
+
+const VCARD_SARAH: &str = r#"BEGIN:VCARD
+VERSION:4.0
+UID:urn:uuid:4fbe8971-0bc3-424c-9c26-36c3e1eff6b1
+FN:Sarah Johnson
+EMAIL;TYPE=WORK:sarah.johnson@example.com
+TEL;TYPE=CELL:+1-555-123-4567
+END:VCARD
+"#;
+
+const VCARD_CARLOS: &str = r#"BEGIN:VCARD
+VERSION:4.0
+UID:urn:uuid:e1ee798b-3d4c-41b0-b217-b9c918e4686a
+FN:Carlos Rodriguez-Martinez
+EMAIL;TYPE=WORK,pref:carlos.rodriguez@example-corp.com
+EMAIL;TYPE=HOME:carlosrm@personalmail.example
+END:VCARD
+"#;
@@ -0,0 +1,151 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::WebDavTest;
+use common::{config::groupware::DirectoryAddressBook, core::BuildServer};
+use directory::backend::internal::{
+    PrincipalField, PrincipalUpdate, PrincipalValue,
+    manage::{ManageDirectory, UpdatePrincipal},
+};
+use groupware::{DestroyArchive, cache::GroupwareCache, contact::AddressBook};
+use hyper::StatusCode;
+use jmap_proto::types::collection::{Collection, SyncCollection};
+use services::housekeeper::directory_book::DirectoryBookSync;
+use store::write::BatchBuilder;
+use utils::config::{cron::SimpleCron, utils::ParseValue};
+
+pub async fn test(test: &WebDavTest) {
+    println!("Running directory address book tests...");
+    let jane_client = test.client("jane");
+    let john_id = test.client("john").account_id;
+    let domain_id = test
+        .server
+        .store()
+        .get_principal_id("example.com")
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Enable the directory address book
+    let core = test.server.inner.shared_core.load_full();
+    let mut book_core = core.as_ref().clone();
+    book_core.groupware.directory_addressbook = Some(DirectoryAddressBook {
+        frequency: SimpleCron::parse_value("0 * *").unwrap(),
+        name: "directory".into(),
+        display_name: "Directory".into(),
+    });
+    test.server.inner.shared_core.store(book_core.into());
+    test.server
+        .inner
+        .build_server()
+        .sync_directory_books()
+        .await;
+
+    // Domain members can read the cards of every principal in the domain
+    let book_path = "/dav/card/example.com/directory/";
+    let john_card = format!("{book_path}{john_id}.vcf");
+    let response = jane_client
+        .propfind_with_headers(book_path, ["D:displayname"], [("depth", "1")])
+        .await;
+    response
+        .properties(book_path)
+        .get("D:displayname")
+        .with_values(["Directory"]);
+    response.properties(&john_card);
+    let card = jane_client
+        .request("GET", &john_card, "")
+        .await
+        .with_status(StatusCode::OK)
+        .body
+        .unwrap();
+    assert!(card.contains("FN:John Doe"), "{card}");
+    assert!(card.contains("EMAIL;PREF=1:jdoe@example.com"), "{card}");
+    assert!(!card.contains("TEL"), "{card}");
+
+    // The address book is read only
+    jane_client
+        .request(
+            "PUT",
+            &format!("{book_path}new.vcf"),
+            "BEGIN:VCARD\r\nVERSION:4.0\r\nUID:new\r\nFN:New\r\nEND:VCARD\r\n",
+        )
+        .await
+        .with_status(StatusCode::FORBIDDEN);
+    jane_client
+        .request("DELETE", &john_card, "")
+        .await
+        .with_status(StatusCode::FORBIDDEN);
+
+    // Directory changes are published on the next synchronization
+    test.server
+        .store()
+        .update_principal(
+            UpdatePrincipal::by_id(john_id).with_updates(vec![PrincipalUpdate::set(
+                PrincipalField::Phone,
+                PrincipalValue::String("+1 555 0100".into()),
+            )]),
+        )
+        .await
+        .unwrap();
+    test.server
+        .inner
+        .build_server()
+        .sync_directory_books()
+        .await;
+    let card = jane_client
+        .request("GET", &john_card, "")
+        .await
+        .with_status(StatusCode::OK)
+        .body
+        .unwrap();
+    assert!(card.contains("TEL;VALUE=TEXT:+1 555 0100"), "{card}");
+
+    // Clean up
+    test.server
+        .store()
+        .update_principal(
+            UpdatePrincipal::by_id(john_id).with_updates(vec![PrincipalUpdate::set(
+                PrincipalField::Phone,
+                PrincipalValue::String("".into()),
+            )]),
+        )
+        .await
+        .unwrap();
+    test.server.inner.shared_core.store(core);
+    let access_token = test.server.get_access_token(domain_id).await.unwrap();
+    let resources = test
+        .server
+        .fetch_dav_resources(&access_token, domain_id, SyncCollection::AddressBook)
+        .await
+        .unwrap();
+    let book = resources.by_path("directory").unwrap();
+    let book_id = book.document_id();
+    let children_ids = resources
+        .children(book_id)
+        .map(|child| child.document_id())
+        .collect::<Vec<_>>();
+    let book_ = test
+        .server
+        .get_archive(domain_id, Collection::AddressBook, book_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut batch = BatchBuilder::new();
+    DestroyArchive(book_.to_unarchived::<AddressBook>().unwrap())
+        .delete_with_cards(
+            &test.server,
+            &access_token,
+            domain_id,
+            book_id,
+            children_ids,
+            None,
+            &mut batch,
+        )
+        .await
+        .unwrap();
+    test.server.commit_batch(batch).await.unwrap();
+    test.assert_is_empty().await;
+}
@@ -52,14 +52,17 @@ pub mod cal_alarm;
 pub mod cal_itip;
 pub mod cal_query;
 pub mod cal_scheduling;
+pub mod card_jmap;
 pub mod card_query;
 pub mod copy_move;
+pub mod directory_book;
 pub mod lock;
 pub mod mkcol;
 pub mod multiget;
 pub mod principals;
 pub mod prop;
 pub mod put_get;
+pub mod share;
 pub mod sync;
 
 #[test]
@@ -86,6 +89,7 @@ fn webdav_tests() {
 
             basic::test(&handle).await;
             put_get::test(&handle).await;
+            share::test(&handle).await;
             mkcol::test(&handle).await;
             copy_move::test(&handle, assisted_discovery).await;
             prop::test(&handle, assisted_discovery).await;
@@ -95,6 +99,8 @@ fn webdav_tests() {
             principals::test(&handle, assisted_discovery).await;
             acl::test(&handle).await;
             card_query::test(&handle).await;
+            card_jmap::test(&handle).await;
+            directory_book::test(&handle).await;
             cal_query::test(&handle).await;
             cal_alarm::test(&handle).await;
             cal_itip::test();
@@ -6,6 +6,7 @@
 
 use super::WebDavTest;
 use crate::webdav::*;
+use common::config::groupware::DavItemLimits;
 
 pub async fn test(test: &WebDavTest) {
     println!("Running PUT/GET tests...");
@@ -195,6 +196,71 @@ pub async fn test(test: &WebDavTest) {
             .with_status(StatusCode::NO_CONTENT);
     }
 
+    // Collections cannot hold more items than the limit configured for the domain
+    let core = test.server.inner.shared_core.load_full();
+    let mut limits_core = core.as_ref().clone();
+    limits_core.groupware.item_limits = DavItemLimits {
+        max_calendar_items: Some(100),
+        max_addressbook_items: Some(100),
+    };
+    limits_core.groupware.item_limits_by_domain.insert(
+        "example.com".into(),
+        DavItemLimits {
+            max_calendar_items: Some(2),
+            max_addressbook_items: Some(2),
+        },
+    );
+    test.server.inner.shared_core.store(limits_core.into());
+    for resource_type in [DavResourceName::Card, DavResourceName::Cal] {
+        let path = format!("{}/john/limits-test/", resource_type.base_path());
+        let source_path = format!("{}/john/limits-source/", resource_type.base_path());
+        for path in [&path, &source_path] {
+            client
+                .mkcol("MKCOL", path, [], [])
+                .await
+                .with_status(StatusCode::CREATED);
+        }
+        for i in 0..3 {
+            let response = client
+                .request("PUT", &format!("{path}item{i}"), resource_type.generate())
+                .await;
+            if i < 2 {
+                response.with_status(StatusCode::CREATED);
+            } else {
+                response
+                    .with_status(StatusCode::INSUFFICIENT_STORAGE)
+                    .with_failed_precondition("D:quota-not-exceeded", "");
+            }
+        }
+
+        // Moving an item into a full collection should fail as well
+        client
+            .request(
+                "PUT",
+                &format!("{source_path}item"),
+                resource_type.generate(),
+            )
+            .await
+            .with_status(StatusCode::CREATED);
+        client
+            .request_with_headers(
+                "MOVE",
+                &format!("{source_path}item"),
+                [("destination", format!("{path}item").as_str())],
+                "",
+            )
+            .await
+            .with_status(StatusCode::INSUFFICIENT_STORAGE);
+
+        for path in [&path, &source_path] {
+            client
+                .request("DELETE", path, "")
+                .await
+                .with_status(StatusCode::NO_CONTENT);
+        }
+    }
+    test.server.inner.shared_core.store(core);
+
     // PUT precondition enforcement
     let modseq = [
         test.resources("john", Collection::FileNode)
@@ -399,6 +465,97 @@ END:VCALENDAR
         .with_status(StatusCode::PRECONDITION_FAILED)
         .with_failed_precondition("A:valid-calendar-object-resource", "");
 
+    // Missing UIDs and unknown time zone references are repaired on PUT
+    for (path, ct, content, expect) in [
+        (
+            "/dav/cal/john/default/normalize.ics",
+            "text/calendar; charset=utf-8",
+            r#"BEGIN:VCALENDAR
+VERSION:2.0
+BEGIN:VEVENT
+SUMMARY:Test Event
+DTSTART;TZID=Not/A_Zone:20231001T120000
+DTEND;TZID=Not/A_Zone:20231001T130000
+END:VEVENT
+END:VCALENDAR
+"#,
+            ["UID:", "DTSTART:20231001T120000"],
+        ),
+        (
+            "/dav/card/john/default/normalize.vcf",
+            "text/vcard; charset=utf-8",
+            r#"BEGIN:VCARD
+VERSION:4.0
+N:Coyote;Wile;E.;;
+END:VCARD
+"#,
+            ["UID:urn:uuid:", "FN:Wile E. Coyote"],
+        ),
+    ] {
+        client
+            .request_with_headers(
+                "PUT",
+                path,
+                [("content-type", ct), ("if-none-match", "*")],
+                &content.replace('\n', "\r\n"),
+            )
+            .await
+            .with_status(StatusCode::CREATED);
+        let response = client
+            .request("GET", path, "")
+            .await
+            .with_status(StatusCode::OK);
+        let body = response.body.as_ref().unwrap();
+        for expect in expect {
+            if !body.contains(expect) {
+                response.dump_response();
+                panic!("Expected {expect:?} in normalized object");
+            }
+        }
+        client
+            .request("DELETE", path, "")
+            .await
+            .with_status(StatusCode::NO_CONTENT);
+    }
+
+    // Irreparable objects are rejected
+    for (path, ct, content, precond_key) in [
+        (
+            "/dav/cal/john/default/invalid.ics",
+            "text/calendar; charset=utf-8",
+            r#"BEGIN:VCALENDAR
+VERSION:2.0
+BEGIN:VEVENT
+UID:1234567892
+SUMMARY:Test Event
+END:VEVENT
+END:VCALENDAR
+"#,
+            "A:valid-calendar-data",
+        ),
+        (
+            "/dav/card/john/default/invalid.vcf",
+            "text/vcard; charset=utf-8",
+            r#"BEGIN:VCARD
+VERSION:4.0
+TEL:+1-555-555-5555
+END:VCARD
+"#,
+            "B:valid-address-data",
+        ),
+    ] {
+        client
+            .request_with_headers(
+                "PUT",
+                path,
+                [("content-type", ct), ("if-none-match", "*")],
+                &content.replace('\n', "\r\n"),
+            )
+            .await
+            .with_status(StatusCode::PRECONDITION_FAILED)
+            .with_failed_precondition(precond_key, "");
+    }
+
     // Deleting unknown/invalid destinations should fail
     for (path, expect) in [
         ("/dav/file/john/unknown.txt", StatusCode::NOT_FOUND),
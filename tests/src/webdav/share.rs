@@ -0,0 +1,125 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs LLC <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::WebDavTest;
+use crate::webdav::{DummyWebDavClient, TEST_FILE_1};
+use hyper::StatusCode;
+
+pub async fn test(test: &WebDavTest) {
+    println!("Running file share link tests...");
+    let client = test.client("john");
+
+    client
+        .request_with_headers(
+            "PUT",
+            "/dav/file/john/shared.txt",
+            [("content-type", "text/plain")],
+            TEST_FILE_1,
+        )
+        .await
+        .with_status(StatusCode::CREATED);
+    client
+        .mkcol("MKCOL", "/dav/file/john/shared-folder/", [], [])
+        .await
+        .with_status(StatusCode::CREATED);
+
+    // Only existing files can be shared
+    for path in ["missing.txt", "shared-folder"] {
+        client
+            .request("POST", "/api/account/share", share_request(path))
+            .await
+            .with_status(StatusCode::NOT_FOUND);
+    }
+
+    // Anyone holding the link can download the file
+    let link = client.share_link("shared.txt").await;
+    client
+        .request("GET", &link, "")
+        .await
+        .with_status(StatusCode::OK)
+        .with_header("content-type", "text/plain")
+        .with_header("content-disposition", "attachment; filename=\"shared.txt\"")
+        .with_body(TEST_FILE_1);
+
+    // Tampered links are rejected
+    client
+        .request("GET", &format!("{link}x"), "")
+        .await
+        .with_status(StatusCode::NOT_FOUND);
+    client
+        .request("GET", "/share", "")
+        .await
+        .with_status(StatusCode::NOT_FOUND);
+
+    // HEAD requests do not count towards the download limit
+    let response = client
+        .request(
+            "POST",
+            "/api/account/share",
+            serde_json::json!({ "path": "shared.txt", "maxDownloads": 1 }).to_string(),
+        )
+        .await
+        .with_status(StatusCode::OK);
+    let limited_link = share_path(response.body.as_ref().unwrap());
+    for _ in 0..2 {
+        client
+            .request("HEAD", &limited_link, "")
+            .await
+            .with_status(StatusCode::OK);
+    }
+    client
+        .request("GET", &limited_link, "")
+        .await
+        .with_status(StatusCode::OK)
+        .with_body(TEST_FILE_1);
+    for method in ["GET", "HEAD"] {
+        client
+            .request(method, &limited_link, "")
+            .await
+            .with_status(StatusCode::GONE);
+    }
+
+    // Links stop working once the file is removed
+    client
+        .request("DELETE", "/dav/file/john/shared.txt", "")
+        .await
+        .with_status(StatusCode::NO_CONTENT);
+    client
+        .request("GET", &link, "")
+        .await
+        .with_status(StatusCode::NOT_FOUND);
+
+    client
+        .request("DELETE", "/dav/file/john/shared-folder/", "")
+        .await
+        .with_status(StatusCode::NO_CONTENT);
+    test.assert_is_empty().await;
+}
+
+impl DummyWebDavClient {
+    pub async fn share_link(&self, path: &str) -> String {
+        let response = self
+            .request("POST", "/api/account/share", share_request(path))
+            .await
+            .with_status(StatusCode::OK);
+        share_path(response.body.as_ref().unwrap())
+    }
+}
+
+fn share_path(response: &str) -> String {
+    let url = serde_json::from_str::<serde_json::Value>(response).unwrap()["data"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let (_, query) = url
+        .split_once("/share?")
+        .unwrap_or_else(|| panic!("Invalid share link {url:?}"));
+    format!("/share?{query}")
+}
+
+fn share_request(path: &str) -> String {
+    serde_json::json!({ "path": path }).to_string()
+}